@@ -0,0 +1,246 @@
+//! Execute many requests concurrently with a bounded number of requests in flight.
+//! See [`execute_all`] and [`execute_stream`].
+
+#[cfg(test)]
+mod tests;
+
+use futures_util::{
+    future,
+    stream::{Fuse, FuturesUnordered},
+    FutureExt, Stream, StreamExt,
+};
+use pin_project::pin_project;
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::Service;
+
+/// Send all the given requests to the service, with at most `max_concurrency` requests
+/// in flight at any time, and return the result of each request in the order of the requests.
+///
+/// This is similar to buffering a stream of requests with [`StreamExt::buffer_unordered`],
+/// except that the results are reordered to match the requests and that an error of one request
+/// does not prevent the other requests from being sent.
+///
+/// All requests are driven by the returned future, without spawning any task,
+/// so that it can be used from a canister where futures are executed by the
+/// [`ic_cdk`] executor. Note that each request making an HTTPs outcall is only sent
+/// once it is polled, i.e. once the canister awaits, and that the replica may limit the number
+/// of concurrent outcalls issued by a canister.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() {
+/// use canhttp::batch::execute_all;
+/// use tower::ServiceBuilder;
+///
+/// let service = ServiceBuilder::new().service_fn(|n: u32| async move {
+///     if n % 2 == 0 {
+///         Ok(n / 2)
+///     } else {
+///         Err(format!("{n} is odd"))
+///     }
+/// });
+///
+/// let (_service, results) = execute_all(service, vec![4, 3, 10], 2).await;
+///
+/// assert_eq!(results, vec![Ok(2), Err("3 is odd".to_string()), Ok(5)]);
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// If `max_concurrency` is zero.
+pub async fn execute_all<S, Request>(
+    mut service: S,
+    requests: Vec<Request>,
+    max_concurrency: usize,
+) -> (S, Vec<Result<S::Response, S::Error>>)
+where
+    S: Service<Request>,
+{
+    assert!(
+        max_concurrency > 0,
+        "max_concurrency must be greater than zero"
+    );
+    let mut results: Vec<Option<Result<S::Response, S::Error>>> = std::iter::repeat_with(|| None)
+        .take(requests.len())
+        .collect();
+    let mut requests = requests.into_iter().enumerate().peekable();
+    let mut in_flight = FuturesUnordered::new();
+
+    future::poll_fn(|cx| loop {
+        while let Poll::Ready(Some((index, result))) = in_flight.poll_next_unpin(cx) {
+            results[index] = Some(result);
+        }
+        // Readiness of the service is only polled when a request can be sent,
+        // so that the requests in flight keep being driven while the service is not ready.
+        if in_flight.len() >= max_concurrency || requests.peek().is_none() {
+            return if in_flight.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            };
+        }
+        match service.poll_ready(cx) {
+            Poll::Ready(ready) => {
+                let (index, request) = requests.next().expect("BUG: missing request");
+                match ready {
+                    Ok(()) => in_flight.push(service.call(request).map(move |r| (index, r))),
+                    Err(e) => results[index] = Some(Err(e)),
+                }
+            }
+            Poll::Pending => return Poll::Pending,
+        }
+    })
+    .await;
+
+    let results = results
+        .into_iter()
+        .map(|result| result.expect("BUG: missing result"))
+        .collect();
+    (service, results)
+}
+
+/// Send the requests yielded by the given stream to the service, with at most `max_concurrency`
+/// requests in flight at any time, and yield the result of each request as soon as it completes.
+///
+/// Contrary to [`execute_all`], the requests are only pulled from the stream when they can be
+/// sent, so that an unbounded number of requests can be processed, e.g. by an indexer,
+/// and results are yielded in the order in which the requests completed.
+/// Responses should therefore contain enough information to identify the corresponding request,
+/// e.g. by mapping each request to a pair `(id, request)` and each response to `(id, response)`.
+///
+/// Since every request goes through the given service, the layers of the service,
+/// e.g. [`CyclesAccounting`](crate::cycles::CyclesAccounting), apply to each request individually.
+/// If the service is not ready, no further request is pulled from the stream until it becomes
+/// ready, while the requests in flight keep being driven.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() {
+/// use canhttp::batch::execute_stream;
+/// use futures_util::{stream, StreamExt};
+/// use tower::ServiceBuilder;
+///
+/// let service = ServiceBuilder::new().service_fn(|(id, n): (usize, u32)| async move {
+///     Ok::<_, String>((id, n * 2))
+/// });
+///
+/// let requests = stream::iter((1..=100).enumerate());
+/// let mut results: Vec<_> = execute_stream(service, requests, 10)
+///     .map(|result| result.unwrap())
+///     .collect()
+///     .await;
+/// results.sort();
+///
+/// assert_eq!(results.len(), 100);
+/// assert_eq!(results[99], (99, 200));
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// If `max_concurrency` is zero.
+pub fn execute_stream<S, St>(
+    service: S,
+    requests: St,
+    max_concurrency: usize,
+) -> ExecuteStream<S, St>
+where
+    St: Stream,
+    S: Service<St::Item>,
+{
+    assert!(
+        max_concurrency > 0,
+        "max_concurrency must be greater than zero"
+    );
+    ExecuteStream {
+        service,
+        requests: requests.fuse(),
+        next_request: None,
+        in_flight: FuturesUnordered::new(),
+        max_concurrency,
+    }
+}
+
+/// Stream of results returned by [`execute_stream`].
+#[pin_project]
+pub struct ExecuteStream<S, St>
+where
+    St: Stream,
+    S: Service<St::Item>,
+{
+    service: S,
+    #[pin]
+    requests: Fuse<St>,
+    next_request: Option<St::Item>,
+    in_flight: FuturesUnordered<S::Future>,
+    max_concurrency: usize,
+}
+
+impl<S, St> ExecuteStream<S, St>
+where
+    St: Stream,
+    S: Service<St::Item>,
+{
+    /// Returns the number of requests currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Consume the stream and return the underlying service.
+    ///
+    /// Requests in flight are dropped.
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+}
+
+impl<S, St> Stream for ExecuteStream<S, St>
+where
+    St: Stream,
+    S: Service<St::Item>,
+{
+    type Item = Result<S::Response, S::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let mut this = self.as_mut().project();
+            if this.in_flight.len() < *this.max_concurrency {
+                if this.next_request.is_none() {
+                    if let Poll::Ready(Some(request)) = this.requests.as_mut().poll_next(cx) {
+                        *this.next_request = Some(request);
+                    }
+                }
+                if this.next_request.is_some() {
+                    match this.service.poll_ready(cx) {
+                        Poll::Ready(ready) => {
+                            let request = this.next_request.take().expect("BUG: missing request");
+                            match ready {
+                                Ok(()) => {
+                                    this.in_flight.push(this.service.call(request));
+                                    continue;
+                                }
+                                Err(e) => return Poll::Ready(Some(Err(e))),
+                            }
+                        }
+                        Poll::Pending => {}
+                    }
+                }
+            }
+            return match this.in_flight.poll_next_unpin(cx) {
+                Poll::Ready(Some(result)) => Poll::Ready(Some(result)),
+                Poll::Ready(None) if this.requests.is_done() && this.next_request.is_none() => {
+                    Poll::Ready(None)
+                }
+                Poll::Ready(None) | Poll::Pending => Poll::Pending,
+            };
+        }
+    }
+}
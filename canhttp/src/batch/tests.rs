@@ -0,0 +1,106 @@
+use crate::batch::{execute_all, execute_stream};
+use futures_util::{stream, StreamExt};
+use std::{cell::Cell, rc::Rc, time::Duration};
+use tower::ServiceBuilder;
+
+#[tokio::test]
+async fn should_return_results_in_order_of_requests() {
+    let service = ServiceBuilder::new().service_fn(|delay_ms: u64| async move {
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        if delay_ms == 0 {
+            Err("no delay")
+        } else {
+            Ok(delay_ms)
+        }
+    });
+
+    let (_service, results) = execute_all(service, vec![30, 10, 0, 20], 4).await;
+
+    assert_eq!(results, vec![Ok(30), Ok(10), Err("no delay"), Ok(20)]);
+}
+
+#[tokio::test]
+async fn should_bound_number_of_requests_in_flight() {
+    for max_concurrency in [1, 2, 5, 20] {
+        let in_flight = Rc::new(Cell::new(0_usize));
+        let max_in_flight = Rc::new(Cell::new(0_usize));
+        let service = ServiceBuilder::new().service_fn(|request: u64| {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            async move {
+                in_flight.set(in_flight.get() + 1);
+                max_in_flight.set(max_in_flight.get().max(in_flight.get()));
+                tokio::time::sleep(Duration::from_millis(request % 3)).await;
+                in_flight.set(in_flight.get() - 1);
+                Ok::<_, String>(request)
+            }
+        });
+
+        let requests: Vec<u64> = (0..10).collect();
+        let (_service, results) = execute_all(service, requests.clone(), max_concurrency).await;
+
+        assert_eq!(results, requests.into_iter().map(Ok).collect::<Vec<_>>());
+        assert_eq!(max_in_flight.get(), max_concurrency.min(10));
+    }
+}
+
+#[tokio::test]
+async fn should_return_no_results_for_no_requests() {
+    let service =
+        ServiceBuilder::new().service_fn(|request: u8| async move { Ok::<_, ()>(request) });
+
+    let (_service, results) = execute_all(service, vec![], 3).await;
+
+    assert_eq!(results, vec![]);
+}
+
+#[tokio::test]
+#[should_panic(expected = "max_concurrency must be greater than zero")]
+async fn should_panic_when_max_concurrency_is_zero() {
+    let service =
+        ServiceBuilder::new().service_fn(|request: u8| async move { Ok::<_, ()>(request) });
+
+    let _ = execute_all(service, vec![1], 0).await;
+}
+
+#[tokio::test]
+async fn should_yield_results_as_they_complete() {
+    let service = ServiceBuilder::new().service_fn(|delay_ms: u64| async move {
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        if delay_ms == 0 {
+            Err("no delay")
+        } else {
+            Ok(delay_ms)
+        }
+    });
+
+    let results: Vec<_> = execute_stream(service, stream::iter(vec![60, 20, 0, 40]), 4)
+        .collect()
+        .await;
+
+    assert_eq!(results, vec![Err("no delay"), Ok(20), Ok(40), Ok(60)]);
+}
+
+#[tokio::test]
+async fn should_pull_requests_only_when_they_can_be_sent() {
+    let pulled = Rc::new(Cell::new(0_usize));
+    let requests = stream::iter(0..10_u64).inspect({
+        let pulled = pulled.clone();
+        move |_| pulled.set(pulled.get() + 1)
+    });
+    let service = ServiceBuilder::new().service_fn(|request: u64| async move {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        Ok::<_, String>(request)
+    });
+
+    let mut results = execute_stream(service, requests, 3);
+    assert_eq!(pulled.get(), 0);
+
+    assert!(results.next().await.unwrap().is_ok());
+    assert_eq!(pulled.get(), 3);
+    assert!(results.in_flight() <= 3);
+
+    let remaining: Vec<_> = results.collect().await;
+    assert_eq!(remaining.len(), 9);
+    assert_eq!(pulled.get(), 10);
+}
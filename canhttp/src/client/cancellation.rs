@@ -0,0 +1,120 @@
+use crate::convert::Filter;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use thiserror::Error;
+
+/// Token to cooperatively cancel the remaining attempts of a higher-level operation, e.g.
+/// once a result was obtained by other means, instead of burning cycles on HTTPs outcalls
+/// whose results nobody will use.
+///
+/// HTTPs outcalls that were already sent cannot be cancelled. Instead, the token is checked
+/// before each new attempt:
+/// * by [`CheckCancellation`], which rejects requests whose token is cancelled before they
+///   are sent;
+/// * by [`StopOnCancellation`](crate::retry::StopOnCancellation), which stops retrying
+///   requests whose token is cancelled;
+/// * by `multi::parallel_call_with_cancellation`, which stops dispatching the remaining
+///   requests of a multi-call once the token is cancelled.
+///
+/// All clones share the same state, so that the token attached to a request
+/// (see [`CancellationRequestExtension`]) can be cancelled by keeping a clone of it.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{CancellationRequestExtension, CancellationToken, CheckCancellation, Cancelled};
+/// use canhttp::convert::ConvertServiceBuilder;
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .filter_request(CheckCancellation)
+///     .service_fn(|_request: http::Request<Vec<u8>>| async move { Ok::<_, BoxError>(()) });
+///
+/// let token = CancellationToken::new();
+/// let request = || {
+///     http::Request::get("https://example.com")
+///         .cancellation_token(token.clone())
+///         .body(vec![])
+///         .unwrap()
+/// };
+///
+/// assert!(service.ready().await?.call(request()).await.is_ok());
+///
+/// token.cancel();
+/// let error = service.ready().await?.call(request()).await.unwrap_err();
+/// assert!(error.is::<Cancelled>());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new [`CancellationToken`] that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the token and all its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Error returned when a request is not sent because its [`CancellationToken`] was cancelled.
+#[derive(Error, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[error("Request was cancelled")]
+pub struct Cancelled;
+
+/// Add support for cancelling requests, see [`CancellationToken`].
+pub trait CancellationRequestExtension: Sized {
+    /// Set the cancellation token of the request.
+    fn set_cancellation_token(&mut self, value: CancellationToken);
+
+    /// Retrieves the cancellation token of the request, if any.
+    fn get_cancellation_token(&self) -> Option<&CancellationToken>;
+
+    /// Convenience method to use the builder pattern.
+    fn cancellation_token(mut self, value: CancellationToken) -> Self {
+        self.set_cancellation_token(value);
+        self
+    }
+
+    /// Returns `true` if the request has a cancellation token that was cancelled.
+    fn is_cancelled(&self) -> bool {
+        self.get_cancellation_token()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+/// [`Filter`] rejecting requests whose [`CancellationToken`] was cancelled with a [`Cancelled`]
+/// error, before they are sent.
+///
+/// To check the token before each attempt, this filter should be placed *inside* any retry layer.
+/// Note that `multi::parallel_call` sends all requests at once, so that cancelling a token
+/// only affects the subsequent attempts of those requests, unless the requests are sent with
+/// `multi::parallel_call_with_cancellation`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CheckCancellation;
+
+impl<Request: CancellationRequestExtension> Filter<Request> for CheckCancellation {
+    type Error = Cancelled;
+
+    fn filter(&mut self, request: Request) -> Result<Request, Self::Error> {
+        if request.is_cancelled() {
+            return Err(Cancelled);
+        }
+        Ok(request)
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{
+    convert::{ContextualError, ConvertError},
+    cycles::CyclesCostEstimator,
+    ConvertServiceBuilder,
+};
+use ic_cdk_management_canister::{
+    HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+};
+use std::{
+    future::Ready,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+/// Drop-in replacement of the [`Client`](crate::Client) that does not issue HTTPs outcalls,
+/// but instead fails with a [`DryRun`] containing the request that would have been sent
+/// together with its estimated cost, see [`simulate`].
+///
+/// Since no outcall is made, this can be used off-chain, e.g. in tests, or to quote the price
+/// of a request to the caller of a canister. Note that the layers of the stack are executed
+/// as usual, so that layers charging cycles, such as
+/// [`CyclesAccounting`](crate::cycles::CyclesAccounting), should be left out of the simulated stack.
+#[derive(Clone, Debug)]
+pub struct DryRunClient {
+    estimator: CyclesCostEstimator,
+}
+
+impl DryRunClient {
+    /// Create a new [`DryRunClient`] estimating the cost of requests with the given estimator.
+    pub fn new(estimator: CyclesCostEstimator) -> Self {
+        Self { estimator }
+    }
+
+    /// Create a new [`DryRunClient`] where the error type is erased, like
+    /// [`Client::new_with_box_error`](crate::Client::new_with_box_error).
+    pub fn new_with_box_error(estimator: CyclesCostEstimator) -> ConvertError<Self, BoxError> {
+        ServiceBuilder::new()
+            .convert_error::<BoxError>()
+            .service(Self::new(estimator))
+    }
+}
+
+impl Service<IcHttpRequest> for DryRunClient {
+    type Response = IcHttpResponse;
+    type Error = DryRun;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: IcHttpRequest) -> Self::Future {
+        let cycles_cost = self.estimator.cost_of_http_request(&request);
+        std::future::ready(Err(DryRun {
+            request,
+            cycles_cost,
+        }))
+    }
+}
+
+/// Request that would have been sent by a [`DryRunClient`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+#[error("Dry run of HTTPs outcall to `{}` costing {cycles_cost} cycles", request.url)]
+pub struct DryRun {
+    /// The request after going through all layers of the stack.
+    pub request: IcHttpRequest,
+    /// The estimated cost of the request in cycles.
+    pub cycles_cost: u128,
+}
+
+/// Error returned by [`simulate`].
+#[derive(Error, Debug)]
+pub enum SimulationError {
+    /// The stack failed before reaching the [`DryRunClient`], e.g. because the request is invalid.
+    #[error("Simulation failed: {0}")]
+    Failed(BoxError),
+    /// The stack produced a response without going through the [`DryRunClient`].
+    #[error("Simulation unexpectedly produced a response")]
+    UnexpectedResponse,
+}
+
+/// Run the given request through the given service, whose innermost service is expected to be
+/// a [`DryRunClient`], and return the [`IcHttpRequest`] that would have been sent together with
+/// its estimated cost in cycles.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{cycles::CyclesCostEstimator, http::HttpConversionLayer, simulate, DryRunClient};
+/// use tower::{BoxError, ServiceBuilder};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let service = ServiceBuilder::new()
+///     .layer(HttpConversionLayer)
+///     .service(DryRunClient::new_with_box_error(CyclesCostEstimator::new(13)));
+///
+/// let request = http::Request::post("https://example.com")
+///     .body(b"Hello, world!".to_vec())?;
+/// let dry_run = simulate(service, request).await?;
+///
+/// assert_eq!(dry_run.request.url, "https://example.com/");
+/// assert_eq!(dry_run.request.body.as_deref(), Some(b"Hello, world!".as_slice()));
+/// assert_eq!(
+///     dry_run.cycles_cost,
+///     CyclesCostEstimator::new(13).cost_of_http_request(&dry_run.request)
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub async fn simulate<S, Request>(service: S, request: Request) -> Result<DryRun, SimulationError>
+where
+    S: Service<Request>,
+    S::Error: Into<BoxError>,
+{
+    match service.oneshot(request).await {
+        Ok(_) => Err(SimulationError::UnexpectedResponse),
+        Err(error) => into_dry_run(error.into()).map_err(SimulationError::Failed),
+    }
+}
+
+fn into_dry_run(error: BoxError) -> Result<DryRun, BoxError> {
+    let error = match error.downcast::<DryRun>() {
+        Ok(dry_run) => return Ok(*dry_run),
+        Err(error) => error,
+    };
+    match error.downcast::<ContextualError>() {
+        Ok(error) => into_dry_run(error.into_inner()),
+        Err(error) => Err(error),
+    }
+}
@@ -0,0 +1,188 @@
+use crate::convert::{ConvertRequest, ConvertRequestLayer, Filter};
+use ic_cdk_management_canister::HttpRequestArgs as IcHttpRequest;
+use thiserror::Error;
+use tower_layer::Layer;
+
+/// Limits of HTTPs outcalls requests enforced by the Internet Computer.
+///
+/// Requests exceeding those limits are rejected by the replica, which still costs an outcall
+/// attempt. Validating them beforehand, e.g. with a [`RequestLimitsLayer`], turns such
+/// rejections into descriptive local errors.
+///
+/// The [`Default`] values correspond to the limits of the
+/// [`http_request`](https://internetcomputer.org/docs/references/ic-interface-spec#ic-http_request)
+/// method of the management canister.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequestLimits {
+    /// Maximum total size in bytes of the request, i.e. URL, headers and body.
+    pub max_request_bytes: u64,
+    /// Maximum length in bytes of the URL.
+    pub max_url_bytes: u64,
+    /// Maximum number of headers.
+    pub max_headers: u64,
+    /// Maximum size in bytes of a single header name or value.
+    pub max_header_name_or_value_bytes: u64,
+    /// Maximum total size in bytes of all header names and values.
+    pub max_headers_bytes: u64,
+    /// Maximum value of `max_response_bytes`.
+    pub max_response_bytes: u64,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_request_bytes: 2_000_000,
+            max_url_bytes: 8 * 1024,
+            max_headers: 64,
+            max_header_name_or_value_bytes: 8 * 1024,
+            max_headers_bytes: 48 * 1024,
+            max_response_bytes: 2_000_000,
+        }
+    }
+}
+
+/// Error returned by [`RequestLimits`] when a request exceeds a limit.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum RequestLimitError {
+    /// The URL is too long.
+    #[error("URL length of {length} bytes exceeds limit of {limit} bytes")]
+    UrlTooLong {
+        /// Length of the URL in bytes.
+        length: u64,
+        /// Maximum length in bytes.
+        limit: u64,
+    },
+    /// There are too many headers.
+    #[error("Number of headers {count} exceeds limit of {limit}")]
+    TooManyHeaders {
+        /// Number of headers.
+        count: u64,
+        /// Maximum number of headers.
+        limit: u64,
+    },
+    /// A header name or value is too large.
+    #[error("HTTP header `{name}` of {size} bytes exceeds limit of {limit} bytes")]
+    HeaderTooLarge {
+        /// Header name.
+        name: String,
+        /// Size in bytes of the header name or value.
+        size: u64,
+        /// Maximum size in bytes.
+        limit: u64,
+    },
+    /// The headers are too large in total.
+    #[error("Total size of headers of {size} bytes exceeds limit of {limit} bytes")]
+    HeadersTooLarge {
+        /// Total size in bytes of the headers.
+        size: u64,
+        /// Maximum size in bytes.
+        limit: u64,
+    },
+    /// The request is too large.
+    #[error("Request size of {size} bytes exceeds limit of {limit} bytes")]
+    RequestTooLarge {
+        /// Total size in bytes of the request.
+        size: u64,
+        /// Maximum size in bytes.
+        limit: u64,
+    },
+    /// `max_response_bytes` is too large.
+    #[error("max_response_bytes of {value} exceeds limit of {limit}")]
+    MaxResponseBytesTooLarge {
+        /// Value of `max_response_bytes` in the request.
+        value: u64,
+        /// Maximum value.
+        limit: u64,
+    },
+}
+
+impl Filter<IcHttpRequest> for RequestLimits {
+    type Error = RequestLimitError;
+
+    fn filter(&mut self, request: IcHttpRequest) -> Result<IcHttpRequest, Self::Error> {
+        let url_bytes = request.url.len() as u64;
+        if url_bytes > self.max_url_bytes {
+            return Err(RequestLimitError::UrlTooLong {
+                length: url_bytes,
+                limit: self.max_url_bytes,
+            });
+        }
+        let num_headers = request.headers.len() as u64;
+        if num_headers > self.max_headers {
+            return Err(RequestLimitError::TooManyHeaders {
+                count: num_headers,
+                limit: self.max_headers,
+            });
+        }
+        let mut headers_bytes = 0_u64;
+        for header in &request.headers {
+            let size = (header.name.len() as u64).max(header.value.len() as u64);
+            if size > self.max_header_name_or_value_bytes {
+                return Err(RequestLimitError::HeaderTooLarge {
+                    name: header.name.clone(),
+                    size,
+                    limit: self.max_header_name_or_value_bytes,
+                });
+            }
+            headers_bytes += (header.name.len() + header.value.len()) as u64;
+        }
+        if headers_bytes > self.max_headers_bytes {
+            return Err(RequestLimitError::HeadersTooLarge {
+                size: headers_bytes,
+                limit: self.max_headers_bytes,
+            });
+        }
+        let body_bytes = request.body.as_ref().map_or(0, |body| body.len() as u64);
+        let request_bytes = url_bytes + headers_bytes + body_bytes;
+        if request_bytes > self.max_request_bytes {
+            return Err(RequestLimitError::RequestTooLarge {
+                size: request_bytes,
+                limit: self.max_request_bytes,
+            });
+        }
+        if let Some(max_response_bytes) = request.max_response_bytes {
+            if max_response_bytes > self.max_response_bytes {
+                return Err(RequestLimitError::MaxResponseBytesTooLarge {
+                    value: max_response_bytes,
+                    limit: self.max_response_bytes,
+                });
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Middleware that validates requests against [`RequestLimits`] before they are sent.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{Client, RequestLimitsLayer};
+/// use ic_cdk_management_canister::{HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse};
+/// use tower::{BoxError, Service, ServiceBuilder};
+///
+/// fn client() -> impl Service<IcHttpRequest, Response = IcHttpResponse, Error = BoxError> {
+///     ServiceBuilder::new()
+///         .layer(RequestLimitsLayer::default())
+///         .service(Client::new_with_box_error())
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RequestLimitsLayer {
+    limits: RequestLimits,
+}
+
+impl RequestLimitsLayer {
+    /// Returns a new [`RequestLimitsLayer`] enforcing the given limits.
+    pub fn new(limits: RequestLimits) -> Self {
+        Self { limits }
+    }
+}
+
+impl<S> Layer<S> for RequestLimitsLayer {
+    type Service = ConvertRequest<S, RequestLimits>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.limits.clone()).layer(inner)
+    }
+}
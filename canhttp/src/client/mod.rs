@@ -1,22 +1,42 @@
 #[cfg(test)]
 mod tests;
 
-use crate::{convert::ConvertError, ConvertServiceBuilder};
+mod cancellation;
+mod dry_run;
+mod limits;
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+mod native;
+#[cfg(feature = "candid")]
+mod proxy;
+mod transport;
+
+pub use cancellation::{
+    CancellationRequestExtension, CancellationToken, Cancelled, CheckCancellation,
+};
+pub use dry_run::{simulate, DryRun, DryRunClient, SimulationError};
+pub use limits::{RequestLimitError, RequestLimits, RequestLimitsLayer};
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+pub use native::ReqwestTransport;
+#[cfg(feature = "candid")]
+pub use proxy::CanisterProxyTransport;
+pub use transport::{transport_fn, HttpOutcallTransport, ManagementCanisterTransport, TransportFn};
+
+use crate::{
+    convert::{ContextualError, ConvertError},
+    ConvertServiceBuilder,
+};
 use ic_cdk::call::Error as IcCdkError;
 use ic_cdk_management_canister::{
     HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse, TransformContext,
 };
 use ic_error_types::RejectCode;
-use std::{
-    future::Future,
-    pin::Pin,
-    task::{Context, Poll},
-};
+use std::task::{Context, Poll};
 use thiserror::Error;
 use tower::{BoxError, Service, ServiceBuilder};
 use tower_layer::Layer;
 
-/// Thin wrapper around [`ic_cdk_management_canister::http_request`] that implements the
+/// Thin wrapper around an [`HttpOutcallTransport`], by default [`ManagementCanisterTransport`]
+/// calling [`ic_cdk_management_canister::http_request`], that implements the
 /// [`tower::Service`] trait. Its functionality can be extended by composing so-called
 /// [tower middlewares](https://docs.rs/tower/latest/tower/#usage).
 ///
@@ -25,15 +45,23 @@ use tower_layer::Layer;
 /// * [`crate::observability`]: add logging or metrics.
 /// * [`crate::http`]: use types from the [http](https://crates.io/crates/http) crate for requests and responses.
 /// * [`crate::retry::DoubleMaxResponseBytes`]: automatically retry failed requests due to the response being too big.
-#[derive(Clone, Debug)]
-pub struct Client;
+///
+/// Changing the transport, e.g. to forward requests to a proxy canister or to use a test double
+/// with [`transport_fn`], leaves the middleware stack above the client unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct Client<Transport = ManagementCanisterTransport> {
+    transport: Transport,
+}
 
 impl Client {
+    /// Create a new client making HTTPs outcalls with the [`ManagementCanisterTransport`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     /// Create a new client returning custom errors.
     pub fn new_with_error<CustomError: From<IcError>>() -> ConvertError<Client, CustomError> {
-        ServiceBuilder::new()
-            .convert_error::<CustomError>()
-            .service(Client)
+        Self::new().with_error()
     }
 
     /// Creates a new client where the error type is erased.
@@ -42,6 +70,25 @@ impl Client {
     }
 }
 
+impl<Transport> Client<Transport> {
+    /// Create a new client issuing HTTPs outcalls with the given transport.
+    pub fn with_transport(transport: Transport) -> Self {
+        Self { transport }
+    }
+
+    /// Returns the transport of the client.
+    pub fn transport(&self) -> &Transport {
+        &self.transport
+    }
+
+    /// Convert the errors returned by the client into custom errors.
+    pub fn with_error<CustomError: From<IcError>>(self) -> ConvertError<Self, CustomError> {
+        ServiceBuilder::new()
+            .convert_error::<CustomError>()
+            .service(self)
+    }
+}
+
 /// Error returned by the Internet Computer when making an HTTPs outcall.
 #[derive(Error, Clone, Debug, PartialEq, Eq)]
 pub enum IcError {
@@ -66,58 +113,53 @@ pub enum IcError {
     },
 }
 
-impl Service<IcHttpRequest> for Client {
+impl<Transport: HttpOutcallTransport> Service<IcHttpRequest> for Client<Transport> {
     type Response = IcHttpResponse;
     type Error = IcError;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    type Future = Transport::Future;
 
     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
     }
 
     fn call(&mut self, request: IcHttpRequest) -> Self::Future {
-        fn convert_error(error: IcCdkError) -> IcError {
-            match error {
-                IcCdkError::CallRejected(e) => {
-                    IcError::CallRejected {
-                        // `CallRejected::reject_code()` can only return an error result if there is a
-                        // new error code on ICP that the CDK is not aware of. We map it to `SysFatal`
-                        // since none of the other error codes apply.
-                        // In particular, note that `RejectCode::SysUnknown` is only applicable to
-                        // inter-canister calls that used `ic0.call_with_best_effort_response`.
-                        code: e.reject_code().unwrap_or(RejectCode::SysFatal),
-                        message: e.reject_message().to_string(),
-                    }
-                }
-                IcCdkError::CallPerformFailed(e) => {
-                    IcError::CallRejected {
-                        // This error indicates that the `ic0.call_perform` system API returned a non-zero code.
-                        // The only possible non-zero value (2) has the same semantics as `RejectCode::SysFatal`.
-                        // See the IC specifications here:
-                        // https://internetcomputer.org/docs/references/ic-interface-spec#system-api-call
-                        code: RejectCode::SysFatal,
-                        message: e.to_string(),
-                    }
-                }
-                IcCdkError::InsufficientLiquidCycleBalance(e) => {
-                    IcError::InsufficientLiquidCycleBalance {
-                        available: e.available,
-                        required: e.required,
-                    }
-                }
-                IcCdkError::CandidDecodeFailed(e) => {
-                    // This can only happen if there is a bug in the CDK in the implementation
-                    // of `ic_cdk_management_canister::http_request`.
-                    panic!("Candid decode failed while performing HTTP outcall: {e}");
-                }
+        self.transport.send(request)
+    }
+}
+
+/// Convert an error from calling the management canister into an [`IcError`].
+pub(crate) fn convert_call_error(error: IcCdkError) -> IcError {
+    match error {
+        IcCdkError::CallRejected(e) => {
+            IcError::CallRejected {
+                // `CallRejected::reject_code()` can only return an error result if there is a
+                // new error code on ICP that the CDK is not aware of. We map it to `SysFatal`
+                // since none of the other error codes apply.
+                // In particular, note that `RejectCode::SysUnknown` is only applicable to
+                // inter-canister calls that used `ic0.call_with_best_effort_response`.
+                code: e.reject_code().unwrap_or(RejectCode::SysFatal),
+                message: e.reject_message().to_string(),
             }
         }
-
-        Box::pin(async move {
-            ic_cdk_management_canister::http_request(&request)
-                .await
-                .map_err(convert_error)
-        })
+        IcCdkError::CallPerformFailed(e) => {
+            IcError::CallRejected {
+                // This error indicates that the `ic0.call_perform` system API returned a non-zero code.
+                // The only possible non-zero value (2) has the same semantics as `RejectCode::SysFatal`.
+                // See the IC specifications here:
+                // https://internetcomputer.org/docs/references/ic-interface-spec#system-api-call
+                code: RejectCode::SysFatal,
+                message: e.to_string(),
+            }
+        }
+        IcCdkError::InsufficientLiquidCycleBalance(e) => IcError::InsufficientLiquidCycleBalance {
+            available: e.available,
+            required: e.required,
+        },
+        IcCdkError::CandidDecodeFailed(e) => {
+            // This can only happen if there is a bug in the CDK in the implementation
+            // of the management canister API, e.g. `ic_cdk_management_canister::http_request`.
+            panic!("Candid decode failed while calling the management canister: {e}");
+        }
     }
 }
 
@@ -181,8 +223,18 @@ impl TransformContextRequestExtension for IcHttpRequest {
 
 /// Add support for selecting replicated or non-replicated HTTP outcalls.
 ///
+/// By default, HTTPs outcalls are replicated: each node of the subnet sends the request and
+/// the responses (after the transform function) must reach consensus, so that no single node
+/// can tamper with the response. A non-replicated outcall is sent by a single node, which also
+/// works for endpoints returning a different response to each request, but the canister must
+/// then trust that node:
+/// * a malicious node can return an arbitrary response, so that non-replicated outcalls should
+///   only be used when the response is verified by other means, e.g. a signature, or when
+///   its integrity does not matter;
+/// * since the request is only sent once, it is also a good fit for non-idempotent requests.
+///
 /// Warning: non-replicated outcalls are currently experimental.
-/// See the [docs](https://docs.internetcomputer.org/references/ic-interface-spec/#ic-http_request) for more ditails.
+/// See the [docs](https://docs.internetcomputer.org/references/ic-interface-spec/#ic-http_request) for more details.
 pub trait IsReplicatedRequestExtension: Sized {
     /// Set the request replication mode.
     fn set_is_replicated(&mut self, value: bool);
@@ -214,6 +266,29 @@ pub trait HttpsOutcallError {
     ///
     /// If true, retrying with a larger value for `max_response_bytes` may help.
     fn is_response_too_large(&self) -> bool;
+
+    /// Determines whether the error is transient, i.e. retrying the same request later may succeed.
+    ///
+    /// Rate-limiting errors and timeouts are transient.
+    fn is_transient(&self) -> bool {
+        self.is_rate_limited() || self.is_timeout()
+    }
+
+    /// Determines whether the error indicates that the request was rate-limited.
+    fn is_rate_limited(&self) -> bool {
+        false
+    }
+
+    /// Determines whether the error indicates that the request timed out.
+    fn is_timeout(&self) -> bool {
+        false
+    }
+
+    /// Returns the reject code and message if the outcall was rejected by the Internet Computer,
+    /// e.g. because the replicas could not reach consensus on the response.
+    fn reject(&self) -> Option<(RejectCode, &str)> {
+        None
+    }
 }
 
 impl HttpsOutcallError for IcError {
@@ -226,15 +301,70 @@ impl HttpsOutcallError for IcError {
             IcError::InsufficientLiquidCycleBalance { .. } => false,
         }
     }
+
+    fn is_transient(&self) -> bool {
+        match self {
+            IcError::CallRejected { code, .. } => code == &RejectCode::SysTransient,
+            IcError::InsufficientLiquidCycleBalance { .. } => false,
+        }
+    }
+
+    fn is_timeout(&self) -> bool {
+        match self {
+            IcError::CallRejected { code, message } => {
+                let message = message.to_lowercase();
+                code == &RejectCode::SysTransient
+                    && (message.contains("timeout") || message.contains("timed out"))
+            }
+            IcError::InsufficientLiquidCycleBalance { .. } => false,
+        }
+    }
+
+    fn reject(&self) -> Option<(RejectCode, &str)> {
+        match self {
+            IcError::CallRejected { code, message } => Some((*code, message.as_str())),
+            IcError::InsufficientLiquidCycleBalance { .. } => None,
+        }
+    }
 }
 
 impl HttpsOutcallError for BoxError {
     fn is_response_too_large(&self) -> bool {
-        if let Some(ic_error) = self.downcast_ref::<IcError>() {
-            return ic_error.is_response_too_large();
-        }
-        false
+        as_https_outcall_error(self).is_some_and(|error| error.is_response_too_large())
+    }
+
+    fn is_transient(&self) -> bool {
+        as_https_outcall_error(self).is_some_and(|error| error.is_transient())
+    }
+
+    fn is_rate_limited(&self) -> bool {
+        as_https_outcall_error(self).is_some_and(|error| error.is_rate_limited())
+    }
+
+    fn is_timeout(&self) -> bool {
+        as_https_outcall_error(self).is_some_and(|error| error.is_timeout())
+    }
+
+    fn reject(&self) -> Option<(RejectCode, &str)> {
+        as_https_outcall_error(self).and_then(|error| error.reject())
+    }
+}
+
+fn as_https_outcall_error(error: &BoxError) -> Option<&dyn HttpsOutcallError> {
+    if let Some(error) = error.downcast_ref::<ContextualError>() {
+        return as_https_outcall_error(error.inner());
+    }
+    if let Some(ic_error) = error.downcast_ref::<IcError>() {
+        return Some(ic_error);
+    }
+    if let Some(error) = error.downcast_ref::<crate::error::CanHttpError>() {
+        return Some(error);
+    }
+    #[cfg(feature = "http")]
+    if let Some(error) = error.downcast_ref::<crate::http::HttpStatusError<Vec<u8>>>() {
+        return Some(error);
     }
+    None
 }
 
 /// A [`tower::Layer`] that wraps services in a [`CanisterReadyService`] middleware.
@@ -0,0 +1,130 @@
+use crate::{HttpOutcallTransport, IcError};
+use ic_cdk_management_canister::{
+    HttpHeader, HttpMethod, HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+};
+use ic_error_types::RejectCode;
+use std::{future::Future, pin::Pin};
+
+// This constant comes from the IC specification:
+// > If provided, the value must not exceed 2MB
+const HTTP_MAX_SIZE: u64 = 2_000_000;
+
+/// Transport sending requests with [`reqwest`] instead of making HTTPs outcalls,
+/// so that the same service stack can be used off-chain, e.g. in host-side tests or in CLI tools.
+///
+/// The behavior of HTTPs outcalls is mimicked where possible:
+/// * The response size, i.e. headers and body, is limited by the `max_response_bytes`
+///   of the request, which defaults to 2MB, and exceeding it results in an
+///   [`IcError::CallRejected`] error with [`RejectCode::SysFatal`] code.
+/// * Network errors result in an [`IcError::CallRejected`] error with
+///   [`RejectCode::SysTransient`] code.
+///
+/// However, the transform function of the request is *not* applied, since it is a query method
+/// of the canister, and whether the request is replicated is ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{retry::DoubleMaxResponseBytes, Client, ReqwestTransport};
+/// use tower::{BoxError, ServiceBuilder};
+///
+/// let service = ServiceBuilder::new()
+///     .retry(DoubleMaxResponseBytes)
+///     .service(Client::with_transport(ReqwestTransport::new()).with_error::<BoxError>());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Create a new transport with a default [`reqwest::Client`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new transport sending requests with the given [`reqwest::Client`],
+    /// e.g. to configure timeouts or proxies.
+    pub fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl HttpOutcallTransport for ReqwestTransport {
+    type Future = Pin<Box<dyn Future<Output = Result<IcHttpResponse, IcError>> + Send>>;
+
+    fn send(&self, request: IcHttpRequest) -> Self::Future {
+        let client = self.client.clone();
+        Box::pin(async move { send(&client, request).await })
+    }
+}
+
+async fn send(client: &reqwest::Client, request: IcHttpRequest) -> Result<IcHttpResponse, IcError> {
+    let method = match request.method {
+        HttpMethod::GET => reqwest::Method::GET,
+        HttpMethod::POST => reqwest::Method::POST,
+        HttpMethod::HEAD => reqwest::Method::HEAD,
+    };
+    let mut builder = client.request(method, &request.url);
+    for HttpHeader { name, value } in request.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+    let max_response_bytes = request.max_response_bytes.unwrap_or(HTTP_MAX_SIZE);
+
+    let mut response = builder.send().await.map_err(convert_reqwest_error)?;
+    let status = response.status().as_u16();
+    let headers: Vec<_> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| HttpHeader {
+            name: name.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        })
+        .collect();
+    let mut response_bytes: u64 = headers
+        .iter()
+        .map(|header| (header.name.len() + header.value.len()) as u64)
+        .sum();
+    let mut body = Vec::new();
+    loop {
+        if response_bytes > max_response_bytes {
+            return Err(IcError::CallRejected {
+                code: RejectCode::SysFatal,
+                message: format!("Http body exceeds size limit of {max_response_bytes} bytes."),
+            });
+        }
+        match response.chunk().await.map_err(convert_reqwest_error)? {
+            Some(chunk) => {
+                response_bytes = response_bytes.saturating_add(chunk.len() as u64);
+                body.extend_from_slice(&chunk);
+            }
+            None => break,
+        }
+    }
+    Ok(IcHttpResponse {
+        status: status.into(),
+        headers,
+        body,
+    })
+}
+
+fn convert_reqwest_error(error: reqwest::Error) -> IcError {
+    if error.is_builder() {
+        return IcError::CallRejected {
+            code: RejectCode::CanisterReject,
+            message: format!("Invalid request: {error}"),
+        };
+    }
+    let message = if error.is_timeout() {
+        format!("Timeout expired: {error}")
+    } else {
+        format!("Connecting to host failed: {error}")
+    };
+    IcError::CallRejected {
+        code: RejectCode::SysTransient,
+        message,
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{client::convert_call_error, HttpOutcallTransport, IcError};
+use candid::Principal;
+use ic_cdk::call::Call;
+use ic_cdk_management_canister::{
+    HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+};
+use ic_error_types::RejectCode;
+use std::{future::Future, pin::Pin};
+
+/// Transport forwarding requests to another canister,
+/// e.g. a shared proxy canister holding the cycles and API keys needed to make HTTPs outcalls,
+/// instead of calling the management canister directly.
+///
+/// The proxy canister must expose an update method, by default `http_request`, with the same
+/// interface as the [`http_request`](https://internetcomputer.org/docs/references/ic-interface-spec#ic-http_request)
+/// method of the management canister, i.e. taking an [`IcHttpRequest`] and replying with an
+/// [`IcHttpResponse`], and is expected to reject the call with the reject code and message of
+/// the HTTPs outcall if it failed, so that errors can be handled as with the
+/// [`ManagementCanisterTransport`](crate::ManagementCanisterTransport).
+/// No cycles are attached to the call, since the proxy canister pays for the HTTPs outcall.
+///
+/// # Examples
+///
+/// ```rust
+/// use candid::Principal;
+/// use canhttp::{http::HttpConversionLayer, CanisterProxyTransport, Client};
+/// use tower::{BoxError, ServiceBuilder};
+///
+/// let proxy = Principal::from_text("7hfb6-caaaa-aaaar-qadga-cai").unwrap();
+/// let transport = CanisterProxyTransport::new(proxy).method("proxy_http_request");
+/// let service = ServiceBuilder::new()
+///     .layer(HttpConversionLayer)
+///     .service(Client::with_transport(transport).with_error::<BoxError>());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CanisterProxyTransport {
+    proxy_id: Principal,
+    method: String,
+}
+
+impl CanisterProxyTransport {
+    /// Create a new transport forwarding requests to the `http_request` method of the canister
+    /// with the given ID.
+    pub fn new(proxy_id: Principal) -> Self {
+        Self {
+            proxy_id,
+            method: "http_request".to_string(),
+        }
+    }
+
+    /// Change the name of the method of the proxy canister to call.
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.method = method.into();
+        self
+    }
+
+    /// Returns the ID of the proxy canister.
+    pub fn proxy_id(&self) -> Principal {
+        self.proxy_id
+    }
+}
+
+impl HttpOutcallTransport for CanisterProxyTransport {
+    type Future = Pin<Box<dyn Future<Output = Result<IcHttpResponse, IcError>>>>;
+
+    fn send(&self, request: IcHttpRequest) -> Self::Future {
+        let proxy_id = self.proxy_id;
+        let method = self.method.clone();
+        Box::pin(async move {
+            let response = Call::unbounded_wait(proxy_id, &method)
+                .with_arg(request)
+                .await
+                .map_err(|e| convert_call_error(e.into()))?;
+            // Contrary to the management canister, the proxy canister may have a bug
+            // or an incompatible interface, which is reported as an error of the canister.
+            response
+                .candid::<IcHttpResponse>()
+                .map_err(|e| IcError::CallRejected {
+                    code: RejectCode::CanisterError,
+                    message: format!("Invalid response from proxy canister {proxy_id}: {e}"),
+                })
+        })
+    }
+}
@@ -26,6 +26,51 @@ async fn should_be_able_to_use_retry_layer() {
     let _ = service.ready().await.unwrap();
 }
 
+// Note that calling `Client::call` only creates the response future,
+// which would require a canister environment to be polled.
+#[cfg(all(feature = "send", not(target_arch = "wasm32")))]
+#[test]
+fn should_have_send_futures() {
+    use ic_cdk_management_canister::HttpRequestArgs as IcHttpRequest;
+    use tower::Service;
+
+    fn assert_send<T: Send>(_value: &T) {}
+
+    let future = Client::new().call(IcHttpRequest::default());
+    assert_send(&future);
+
+    let mut service = ServiceBuilder::new()
+        .retry(DoubleMaxResponseBytes)
+        .service(Client::new_with_box_error());
+    let future = service.call(IcHttpRequest::default());
+    assert_send(&future);
+}
+
+// Note that sending requests with `CanisterProxyTransport` would require a canister environment.
+#[cfg(feature = "candid")]
+#[tokio::test]
+async fn should_use_canister_proxy_transport_with_same_stack() {
+    use crate::{http::HttpConversionLayer, CanisterProxyTransport};
+    use candid::Principal;
+    use tower::BoxError;
+
+    let proxy_id = Principal::from_slice(&[1, 2, 3]);
+    let transport = CanisterProxyTransport::new(proxy_id);
+    assert_eq!(transport.proxy_id(), proxy_id);
+    assert_eq!(
+        transport,
+        CanisterProxyTransport::new(proxy_id).method("http_request")
+    );
+
+    let mut service = ServiceBuilder::new()
+        .retry(DoubleMaxResponseBytes)
+        .layer(HttpConversionLayer)
+        .service(
+            Client::with_transport(transport.method("proxy_http_request")).with_error::<BoxError>(),
+        );
+    let _ = service.ready().await.unwrap();
+}
+
 #[derive(Debug)]
 struct CustomError(IcError);
 
@@ -40,3 +85,488 @@ impl From<IcError> for CustomError {
         CustomError(value)
     }
 }
+
+mod request_limits {
+    use crate::{convert::Filter, RequestLimitError, RequestLimits};
+    use ic_cdk_management_canister::{HttpHeader, HttpMethod, HttpRequestArgs as IcHttpRequest};
+
+    #[test]
+    fn should_accept_request_within_limits() {
+        let request = request();
+
+        assert_eq!(
+            RequestLimits::default().filter(request.clone()),
+            Ok(request)
+        );
+    }
+
+    #[test]
+    fn should_reject_request_exceeding_limits() {
+        let mut limits = RequestLimits::default();
+
+        let long_url = IcHttpRequest {
+            url: format!("https://example.com/{}", "a".repeat(8 * 1024)),
+            ..request()
+        };
+        assert_eq!(
+            limits.filter(long_url),
+            Err(RequestLimitError::UrlTooLong {
+                length: 8 * 1024 + 20,
+                limit: 8 * 1024
+            })
+        );
+
+        let too_many_headers = IcHttpRequest {
+            headers: (0..65)
+                .map(|i| header(&format!("x-{i}"), "value"))
+                .collect(),
+            ..request()
+        };
+        assert_eq!(
+            limits.filter(too_many_headers),
+            Err(RequestLimitError::TooManyHeaders {
+                count: 65,
+                limit: 64
+            })
+        );
+
+        let large_header = IcHttpRequest {
+            headers: vec![header("authorization", &"a".repeat(8 * 1024 + 1))],
+            ..request()
+        };
+        assert_eq!(
+            limits.filter(large_header),
+            Err(RequestLimitError::HeaderTooLarge {
+                name: "authorization".to_string(),
+                size: 8 * 1024 + 1,
+                limit: 8 * 1024
+            })
+        );
+
+        let large_headers = IcHttpRequest {
+            headers: (0..7)
+                .map(|i| header(&format!("x-{i}"), &"a".repeat(8 * 1024 - 3)))
+                .collect(),
+            ..request()
+        };
+        assert_eq!(
+            limits.filter(large_headers),
+            Err(RequestLimitError::HeadersTooLarge {
+                size: 7 * 8 * 1024,
+                limit: 48 * 1024
+            })
+        );
+
+        let large_body = IcHttpRequest {
+            body: Some(vec![0; 2_000_000]),
+            ..request()
+        };
+        assert_eq!(
+            limits.filter(large_body),
+            Err(RequestLimitError::RequestTooLarge {
+                size: 2_000_000 + 19 + 28,
+                limit: 2_000_000
+            })
+        );
+
+        let large_max_response_bytes = IcHttpRequest {
+            max_response_bytes: Some(2_000_001),
+            ..request()
+        };
+        assert_eq!(
+            limits.filter(large_max_response_bytes),
+            Err(RequestLimitError::MaxResponseBytesTooLarge {
+                value: 2_000_001,
+                limit: 2_000_000
+            })
+        );
+    }
+
+    fn request() -> IcHttpRequest {
+        IcHttpRequest {
+            url: "https://example.com".to_string(),
+            method: HttpMethod::POST,
+            headers: vec![header("content-type", "application/json")],
+            body: Some(b"{}".to_vec()),
+            max_response_bytes: Some(2_000_000),
+            ..Default::default()
+        }
+    }
+
+    fn header(name: &str, value: &str) -> HttpHeader {
+        HttpHeader {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+mod https_outcall_error {
+    use crate::{error::CanHttpError, http::HttpStatusError, HttpsOutcallError, IcError};
+    use ic_error_types::RejectCode;
+    use tower::BoxError;
+
+    #[test]
+    fn should_classify_ic_errors() {
+        let timeout = IcError::CallRejected {
+            code: RejectCode::SysTransient,
+            message: "Timeout expired".to_string(),
+        };
+        assert!(timeout.is_transient());
+        assert!(timeout.is_timeout());
+        assert!(!timeout.is_rate_limited());
+
+        let no_consensus = IcError::CallRejected {
+            code: RejectCode::SysTransient,
+            message: "No consensus could be reached".to_string(),
+        };
+        assert!(no_consensus.is_transient());
+        assert!(!no_consensus.is_timeout());
+        assert_eq!(
+            no_consensus.reject(),
+            Some((RejectCode::SysTransient, "No consensus could be reached"))
+        );
+
+        let fatal = IcError::CallRejected {
+            code: RejectCode::SysFatal,
+            message: "Http body exceeds size limit".to_string(),
+        };
+        assert!(!fatal.is_transient());
+        assert!(!fatal.is_timeout());
+
+        let insufficient_cycles = IcError::InsufficientLiquidCycleBalance {
+            available: 0,
+            required: 1,
+        };
+        assert!(!insufficient_cycles.is_transient());
+        assert_eq!(insufficient_cycles.reject(), None);
+    }
+
+    #[test]
+    fn should_classify_http_status_errors() {
+        for (status, transient, rate_limited, timeout) in [
+            (400, false, false, false),
+            (404, false, false, false),
+            (408, true, false, true),
+            (429, true, true, false),
+            (500, true, false, false),
+            (501, false, false, false),
+            (503, true, false, false),
+            (504, true, false, true),
+        ] {
+            let error = HttpStatusError {
+                status: http::StatusCode::from_u16(status).unwrap(),
+                headers: http::HeaderMap::new(),
+                body: Vec::<u8>::new(),
+            };
+            assert_eq!(error.is_transient(), transient, "{status}");
+            assert_eq!(error.is_rate_limited(), rate_limited, "{status}");
+            assert_eq!(error.is_timeout(), timeout, "{status}");
+            assert!(!error.is_response_too_large());
+            assert_eq!(error.reject(), None);
+        }
+    }
+
+    #[test]
+    fn should_classify_boxed_errors() {
+        let rate_limited = HttpStatusError {
+            status: http::StatusCode::TOO_MANY_REQUESTS,
+            headers: http::HeaderMap::new(),
+            body: Vec::<u8>::new(),
+        };
+        let timeout = IcError::CallRejected {
+            code: RejectCode::SysTransient,
+            message: "Request timed out".to_string(),
+        };
+
+        let error = BoxError::from(rate_limited.clone());
+        assert!(error.is_rate_limited());
+        assert!(error.is_transient());
+
+        let error = BoxError::from(CanHttpError::from(rate_limited));
+        assert!(error.is_rate_limited());
+
+        let error = BoxError::from(timeout.clone());
+        assert!(error.is_timeout());
+
+        let error = BoxError::from(CanHttpError::from(timeout));
+        assert!(error.is_timeout());
+        assert_eq!(
+            error.reject(),
+            Some((RejectCode::SysTransient, "Request timed out"))
+        );
+
+        let error = BoxError::from("unknown error");
+        assert!(!error.is_transient());
+        assert!(!error.is_rate_limited());
+        assert!(!error.is_timeout());
+        assert_eq!(error.reject(), None);
+    }
+}
+
+mod dry_run {
+    use crate::{
+        convert::ErrorContext, cycles::CyclesCostEstimator, http::HttpConversionLayer, simulate,
+        ConvertServiceBuilder, DryRunClient, SimulationError,
+    };
+    use assert_matches::assert_matches;
+    use ic_cdk_management_canister::HttpMethod;
+    use tower::ServiceBuilder;
+
+    #[tokio::test]
+    async fn should_return_request_through_error_context() {
+        let service = ServiceBuilder::new()
+            .error_context(|request: &http::Request<Vec<u8>>| {
+                ErrorContext::new().with_url(request.uri().to_string())
+            })
+            .layer(HttpConversionLayer)
+            .service(DryRunClient::new_with_box_error(CyclesCostEstimator::new(
+                34,
+            )));
+        let request = http::Request::get("https://example.com/prices")
+            .header("Accept", "application/json")
+            .body(vec![])
+            .unwrap();
+
+        let dry_run = simulate(service, request).await.unwrap();
+
+        assert_eq!(dry_run.request.url, "https://example.com/prices");
+        assert_eq!(dry_run.request.method, HttpMethod::GET);
+        assert_eq!(dry_run.request.headers.len(), 1);
+        assert_eq!(
+            dry_run.cycles_cost,
+            CyclesCostEstimator::new(34).cost_of_http_request(&dry_run.request)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_request_is_rejected() {
+        let service = ServiceBuilder::new().layer(HttpConversionLayer).service(
+            DryRunClient::new_with_box_error(CyclesCostEstimator::new(13)),
+        );
+        let request = http::Request::put("https://example.com")
+            .body(vec![])
+            .unwrap();
+
+        let result = simulate(service, request).await;
+
+        assert_matches!(result, Err(SimulationError::Failed(_)));
+    }
+}
+
+#[cfg(feature = "http")]
+mod cancellation {
+    use crate::{
+        convert::Filter, CancellationRequestExtension, CancellationToken, Cancelled,
+        CheckCancellation,
+    };
+
+    #[test]
+    fn should_share_cancellation_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+
+        clone.cancel();
+
+        assert!(token.is_cancelled());
+        assert!(clone.is_cancelled());
+    }
+
+    #[test]
+    fn should_reject_cancelled_requests() {
+        let token = CancellationToken::new();
+        let request = || {
+            http::Request::get("https://example.com")
+                .cancellation_token(token.clone())
+                .body(Vec::<u8>::new())
+                .unwrap()
+        };
+
+        assert!(CheckCancellation.filter(request()).is_ok());
+        assert!(CheckCancellation
+            .filter(http::Request::new(Vec::<u8>::new()))
+            .is_ok());
+
+        token.cancel();
+
+        assert!(request().is_cancelled());
+        assert_eq!(CheckCancellation.filter(request()).unwrap_err(), Cancelled);
+    }
+}
+
+#[cfg(feature = "http")]
+mod transport {
+    use crate::{
+        http::HttpConversionLayer, retry::DoubleMaxResponseBytes, transport_fn, Client, IcError,
+        MaxResponseBytesRequestExtension,
+    };
+    use ic_cdk_management_canister::{
+        HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+    };
+    use ic_error_types::RejectCode;
+    use std::{cell::RefCell, rc::Rc};
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    #[tokio::test]
+    async fn should_use_middleware_stack_with_test_double() {
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let transport = transport_fn({
+            let requests = requests.clone();
+            move |request: IcHttpRequest| {
+                requests.borrow_mut().push(request.max_response_bytes);
+                let result = match request.max_response_bytes {
+                    Some(max_response_bytes) if max_response_bytes >= 4096 => Ok(IcHttpResponse {
+                        status: 200_u16.into(),
+                        body: b"done".to_vec(),
+                        ..Default::default()
+                    }),
+                    _ => Err(IcError::CallRejected {
+                        code: RejectCode::SysFatal,
+                        message: "Http body exceeds size limit".to_string(),
+                    }),
+                };
+                async move { result }
+            }
+        });
+        let mut service = ServiceBuilder::new()
+            .retry(DoubleMaxResponseBytes)
+            .layer(HttpConversionLayer)
+            .service(Client::with_transport(transport).with_error::<BoxError>());
+
+        let request = http::Request::post("https://internetcomputer.org/")
+            .max_response_bytes(1024)
+            .body(vec![])
+            .unwrap();
+        let response = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(response.body(), b"done");
+        assert_eq!(*requests.borrow(), vec![Some(1024), Some(2048), Some(4096)]);
+    }
+}
+
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+mod reqwest_transport {
+    use crate::{Client, HttpsOutcallError, IcError, ReqwestTransport};
+    use assert_matches::assert_matches;
+    use ic_cdk_management_canister::{
+        HttpHeader, HttpMethod, HttpRequestArgs as IcHttpRequest,
+        HttpRequestResult as IcHttpResponse,
+    };
+    use ic_error_types::RejectCode;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use tower::{Service, ServiceExt};
+
+    #[tokio::test]
+    async fn should_send_request() {
+        let (url, server) =
+            serve_once("HTTP/1.1 201 Created\r\ncontent-length: 5\r\n\r\nhello").await;
+        let request = IcHttpRequest {
+            url,
+            method: HttpMethod::POST,
+            headers: vec![HttpHeader {
+                name: "x-test".to_string(),
+                value: "canhttp".to_string(),
+            }],
+            body: Some(b"ping".to_vec()),
+            ..Default::default()
+        };
+
+        let response = Client::with_transport(ReqwestTransport::new())
+            .ready()
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("POST / HTTP/1.1\r\n"));
+        assert!(received.contains("x-test: canhttp\r\n"));
+        assert!(received.ends_with("\r\n\r\nping"));
+        assert_eq!(
+            response,
+            IcHttpResponse {
+                status: 201_u16.into(),
+                headers: vec![HttpHeader {
+                    name: "content-length".to_string(),
+                    value: "5".to_string(),
+                }],
+                body: b"hello".to_vec(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_response_is_too_large() {
+        let (url, _server) =
+            serve_once("HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\n0123456789").await;
+        let request = IcHttpRequest {
+            url,
+            max_response_bytes: Some(20),
+            ..Default::default()
+        };
+
+        let error = Client::with_transport(ReqwestTransport::new())
+            .call(request)
+            .await
+            .unwrap_err();
+
+        assert!(error.is_response_too_large());
+    }
+
+    #[tokio::test]
+    async fn should_fail_with_transient_error_when_host_is_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let error = Client::with_transport(ReqwestTransport::new())
+            .call(IcHttpRequest {
+                url,
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+
+        assert_matches!(
+            error,
+            IcError::CallRejected {
+                code: RejectCode::SysTransient,
+                ..
+            }
+        );
+    }
+
+    async fn serve_once(response: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buffer = [0_u8; 1024];
+            loop {
+                let n = stream.read(&mut buffer).await.unwrap();
+                received.extend_from_slice(&buffer[..n]);
+                let request = String::from_utf8_lossy(&received);
+                if let Some((head, body)) = request.split_once("\r\n\r\n") {
+                    let content_length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .map_or(0, |length| length.parse().unwrap());
+                    if body.len() >= content_length {
+                        break;
+                    }
+                }
+            }
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(received).unwrap()
+        });
+        (url, server)
+    }
+}
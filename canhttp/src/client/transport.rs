@@ -0,0 +1,110 @@
+use crate::{client::convert_call_error, IcError};
+use ic_cdk_management_canister::{
+    HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+};
+use std::{fmt, future::Future, pin::Pin};
+
+/// Transport used by a [`Client`](crate::Client) to issue HTTPs outcalls.
+///
+/// Implementations from this crate:
+/// * [`ManagementCanisterTransport`]: calls the `http_request` method of the management canister.
+///   This is the default transport of a [`Client`](crate::Client).
+/// * `CanisterProxyTransport`: forwards requests to a proxy canister (requires the `candid` feature).
+/// * `ReqwestTransport`: sends requests with `reqwest` outside of a canister (requires the `reqwest`
+///   feature).
+/// * [`TransportFn`]: uses a closure, e.g. as a test double, see [`transport_fn`].
+///
+/// Since the [`Client`](crate::Client) only depends on the transport to issue HTTPs outcalls,
+/// the whole middleware stack above it is unchanged when changing transports.
+pub trait HttpOutcallTransport {
+    /// Future returned by [`HttpOutcallTransport::send`].
+    type Future: Future<Output = Result<IcHttpResponse, IcError>>;
+
+    /// Send the given request.
+    fn send(&self, request: IcHttpRequest) -> Self::Future;
+}
+
+/// Transport making HTTPs outcalls with [`ic_cdk_management_canister::http_request`].
+///
+/// The response future is not [`Send`], since canisters are single-threaded.
+/// When the `send` feature is enabled and the target is not `wasm32`, e.g. to use the same
+/// service stack in off-chain tools or in tests spawning tasks on a multithreaded runtime,
+/// the response future is [`Send`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ManagementCanisterTransport;
+
+#[cfg(not(all(feature = "send", not(target_arch = "wasm32"))))]
+type ManagementCanisterFuture = Pin<Box<dyn Future<Output = Result<IcHttpResponse, IcError>>>>;
+#[cfg(all(feature = "send", not(target_arch = "wasm32")))]
+type ManagementCanisterFuture =
+    Pin<Box<dyn Future<Output = Result<IcHttpResponse, IcError>> + Send>>;
+
+impl HttpOutcallTransport for ManagementCanisterTransport {
+    type Future = ManagementCanisterFuture;
+
+    fn send(&self, request: IcHttpRequest) -> Self::Future {
+        Box::pin(async move {
+            ic_cdk_management_canister::http_request(&request)
+                .await
+                .map_err(convert_call_error)
+        })
+    }
+}
+
+/// Create a transport from a closure, e.g. to unit test a middleware stack without
+/// a canister environment.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{transport_fn, Client, IcError};
+/// use ic_cdk_management_canister::{HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse};
+/// use tower::{Service, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut client = Client::with_transport(transport_fn(|request: IcHttpRequest| async move {
+///     Ok::<_, IcError>(IcHttpResponse {
+///         status: 200_u16.into(),
+///         body: request.url.into_bytes(),
+///         ..Default::default()
+///     })
+/// }));
+///
+/// let request = IcHttpRequest {
+///     url: "https://internetcomputer.org".to_string(),
+///     ..Default::default()
+/// };
+/// let response = client.ready().await?.call(request).await?;
+///
+/// assert_eq!(response.body, b"https://internetcomputer.org");
+/// # Ok(())
+/// # }
+/// ```
+pub fn transport_fn<F>(f: F) -> TransportFn<F> {
+    TransportFn { f }
+}
+
+/// Transport created from a closure, see [`transport_fn`].
+#[derive(Clone, Copy)]
+pub struct TransportFn<F> {
+    f: F,
+}
+
+impl<F> fmt::Debug for TransportFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransportFn").finish_non_exhaustive()
+    }
+}
+
+impl<F, Fut> HttpOutcallTransport for TransportFn<F>
+where
+    F: Fn(IcHttpRequest) -> Fut,
+    Fut: Future<Output = Result<IcHttpResponse, IcError>>,
+{
+    type Future = Fut;
+
+    fn send(&self, request: IcHttpRequest) -> Self::Future {
+        (self.f)(request)
+    }
+}
@@ -0,0 +1,34 @@
+use std::{fmt, rc::Rc};
+
+/// Source of the current time used by middlewares and helpers that depend on it.
+///
+/// By default, the time is given by [`ic_cdk::api::time`], i.e. in nanoseconds since the UNIX
+/// epoch. Types holding a [`Clock`] expose a `clock` builder method to change how the current
+/// time is obtained, e.g. to use a fixed time in tests, where [`ic_cdk::api::time`] is not
+/// available.
+#[derive(Clone)]
+pub(crate) struct Clock(Rc<dyn Fn() -> u64>);
+
+impl Clock {
+    /// Create a new [`Clock`] returning the time given by the given function.
+    pub(crate) fn new(clock: impl Fn() -> u64 + 'static) -> Self {
+        Self(Rc::new(clock))
+    }
+
+    /// Return the current time.
+    pub(crate) fn now(&self) -> u64 {
+        (self.0)()
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new(ic_cdk::api::time)
+    }
+}
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Clock").finish_non_exhaustive()
+    }
+}
@@ -1,9 +1,10 @@
 use pin_project::pin_project;
+use std::fmt;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use tower::Service;
+use tower::{BoxError, Service};
 use tower_layer::Layer;
 
 /// Convert error of a service into another type, where the conversion does *not* fail.
@@ -113,3 +114,185 @@ where
         }
     }
 }
+
+/// Contextual metadata attached to an error by an [`ErrorContextLayer`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ErrorContext {
+    /// URL targeted by the request.
+    pub url: Option<String>,
+    /// Method called, e.g. a JSON-RPC method.
+    pub method: Option<String>,
+    /// Attempt number of the request, when the request may be retried.
+    pub attempt: Option<u32>,
+}
+
+impl ErrorContext {
+    /// Create a new empty [`ErrorContext`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the URL targeted by the request.
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the method called.
+    pub fn with_method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+
+    /// Set the attempt number.
+    pub fn with_attempt(mut self, attempt: u32) -> Self {
+        self.attempt = Some(attempt);
+        self
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut fields = Vec::new();
+        if let Some(url) = &self.url {
+            fields.push(format!("url: {url}"));
+        }
+        if let Some(method) = &self.method {
+            fields.push(format!("method: {method}"));
+        }
+        if let Some(attempt) = &self.attempt {
+            fields.push(format!("attempt: {attempt}"));
+        }
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+/// Error with some [`ErrorContext`], returned by the [`ErrorContextService`].
+#[derive(Debug)]
+pub struct ContextualError {
+    context: ErrorContext,
+    source: BoxError,
+}
+
+impl ContextualError {
+    /// Create a new [`ContextualError`].
+    pub fn new(context: ErrorContext, source: impl Into<BoxError>) -> Self {
+        Self {
+            context,
+            source: source.into(),
+        }
+    }
+
+    /// Returns the context of the error.
+    pub fn context(&self) -> &ErrorContext {
+        &self.context
+    }
+
+    /// Returns the underlying error.
+    pub fn inner(&self) -> &BoxError {
+        &self.source
+    }
+
+    /// Consumes the error and returns the underlying error.
+    pub fn into_inner(self) -> BoxError {
+        self.source
+    }
+}
+
+impl fmt::Display for ContextualError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.source, self.context)
+    }
+}
+
+impl std::error::Error for ContextualError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// Attach contextual metadata, derived from the request, to errors of a service.
+///
+/// This [`Layer`] produces instances of the [`ErrorContextService`] service.
+///
+/// [`Layer`]: tower::Layer
+#[derive(Debug, Clone)]
+pub struct ErrorContextLayer<F> {
+    f: F,
+}
+
+impl<F> ErrorContextLayer<F> {
+    /// Returns a new [`ErrorContextLayer`] where the context is computed from the request
+    /// with the given closure.
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<S, F: Clone> Layer<S> for ErrorContextLayer<F> {
+    type Service = ErrorContextService<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Self::Service {
+            inner,
+            f: self.f.clone(),
+        }
+    }
+}
+
+/// Wrap errors of the inner service into a [`ContextualError`].
+#[derive(Debug, Clone)]
+pub struct ErrorContextService<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F, Request> Service<Request> for ErrorContextService<S, F>
+where
+    S: Service<Request>,
+    S::Error: Into<BoxError>,
+    F: Fn(&Request) -> ErrorContext,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = ContextualResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let context = (self.f)(&req);
+        ContextualResponseFuture {
+            response_future: self.inner.call(req),
+            context: Some(context),
+        }
+    }
+}
+
+#[pin_project]
+pub struct ContextualResponseFuture<F> {
+    #[pin]
+    response_future: F,
+    context: Option<ErrorContext>,
+}
+
+impl<F, Response, Error> Future for ContextualResponseFuture<F>
+where
+    F: Future<Output = Result<Response, Error>>,
+    Error: Into<BoxError>,
+{
+    type Output = Result<Response, BoxError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.response_future.poll(cx) {
+            Poll::Ready(Ok(response)) => Poll::Ready(Ok(response)),
+            Poll::Ready(Err(e)) => {
+                let context = this.context.take().unwrap_or_default();
+                Poll::Ready(Err(Box::new(ContextualError::new(context, e))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use futures_util::future;
+use pin_project::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::Service;
+use tower_layer::Layer;
+
+/// Map requests of a service with a closure, where the mapping may fail.
+///
+/// This [`Layer`] produces instances of the [`TryMapRequest`] service.
+///
+/// [`Layer`]: tower::Layer
+#[derive(Debug, Clone)]
+pub struct TryMapRequestLayer<F> {
+    f: F,
+}
+
+impl<F> TryMapRequestLayer<F> {
+    /// Returns a new [`TryMapRequestLayer`]
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+/// Map requests with a closure and forward the mapped request to the inner service
+/// *only if* the mapping was successful.
+#[derive(Debug, Clone)]
+pub struct TryMapRequest<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F: Clone> Layer<S> for TryMapRequestLayer<F> {
+    type Service = TryMapRequest<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Self::Service {
+            inner,
+            f: self.f.clone(),
+        }
+    }
+}
+
+impl<S, F, Request, NewRequest, Error> Service<NewRequest> for TryMapRequest<S, F>
+where
+    S: Service<Request>,
+    F: FnMut(NewRequest) -> Result<Request, Error>,
+    Error: Into<S::Error>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = future::Either<S::Future, future::Ready<Result<S::Response, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, new_req: NewRequest) -> Self::Future {
+        match (self.f)(new_req) {
+            Ok(request) => future::Either::Left(self.inner.call(request)),
+            Err(err) => future::Either::Right(future::ready(Err(err.into()))),
+        }
+    }
+}
+
+/// Map responses of a service with a closure, where the mapping may fail.
+///
+/// This [`Layer`] produces instances of the [`TryMapResponse`] service.
+///
+/// [`Layer`]: tower::Layer
+#[derive(Debug, Clone)]
+pub struct TryMapResponseLayer<F> {
+    f: F,
+}
+
+impl<F> TryMapResponseLayer<F> {
+    /// Returns a new [`TryMapResponseLayer`]
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+/// Map the inner service response with a closure, where the mapping may fail.
+#[derive(Debug, Clone)]
+pub struct TryMapResponse<S, F> {
+    inner: S,
+    f: F,
+}
+
+impl<S, F: Clone> Layer<S> for TryMapResponseLayer<F> {
+    type Service = TryMapResponse<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Self::Service {
+            inner,
+            f: self.f.clone(),
+        }
+    }
+}
+
+impl<S, F, Request, NewResponse, Error> Service<Request> for TryMapResponse<S, F>
+where
+    S: Service<Request>,
+    F: FnOnce(S::Response) -> Result<NewResponse, Error> + Clone,
+    Error: Into<S::Error>,
+{
+    type Response = NewResponse;
+    type Error = S::Error;
+    type Future = TryMapResponseFuture<S::Future, F>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        TryMapResponseFuture {
+            response_future: self.inner.call(req),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+/// Response future of the [`TryMapResponse`] service, mapping the response of the inner
+/// service once it is available.
+#[pin_project]
+pub struct TryMapResponseFuture<Fut, F> {
+    #[pin]
+    response_future: Fut,
+    f: Option<F>,
+}
+
+impl<Fut, F, Response, NewResponse, Error, NewError> Future for TryMapResponseFuture<Fut, F>
+where
+    Fut: Future<Output = Result<Response, Error>>,
+    F: FnOnce(Response) -> Result<NewResponse, NewError>,
+    NewError: Into<Error>,
+{
+    type Output = Result<NewResponse, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.response_future.poll(cx) {
+            Poll::Ready(Ok(response)) => {
+                let f = this
+                    .f
+                    .take()
+                    .expect("BUG: TryMapResponseFuture polled after completion");
+                Poll::Ready(f(response).map_err(Into::into))
+            }
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -148,17 +148,133 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! ## To attach context to errors
+//!
+//! Errors can be enriched with metadata derived from the request, like the target URL,
+//! so that the final error message is actionable.
+//! The resulting error is a [`ContextualError`] wrapping the original one.
+//!
+//! ```rust
+//! use canhttp::convert::{ContextualError, ConvertServiceBuilder, ErrorContext};
+//! use tower::{BoxError, ServiceBuilder, Service, ServiceExt};
+//!
+//! async fn bare_bone_service(_request: http::Request<Vec<u8>>) -> Result<(), BoxError> {
+//!     Err(BoxError::from("invalid JSON response"))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let mut service = ServiceBuilder::new()
+//!     .error_context(|request: &http::Request<Vec<u8>>| {
+//!         ErrorContext::new().with_url(request.uri().to_string())
+//!     })
+//!     .service_fn(bare_bone_service);
+//!
+//! let request = http::Request::post("https://mainnet.infura.io/v3").body(vec![]).unwrap();
+//! let error = service.ready().await.unwrap().call(request).await.unwrap_err();
+//!
+//! assert_eq!(
+//!     error.to_string(),
+//!     "invalid JSON response [url: https://mainnet.infura.io/v3]"
+//! );
+//! let error = error.downcast::<ContextualError>().unwrap();
+//! assert_eq!(
+//!     error.context().url.as_deref(),
+//!     Some("https://mainnet.infura.io/v3")
+//! );
+//! # }
+//! ```
+//!
+//! ## To filter requests
+//!
+//! A [`Filter`] can reject requests with a typed error before they reach the inner service,
+//! e.g. to enforce a policy. Use [`FilterFn`] to define such a filter with a closure.
+//!
+//! ```rust
+//! use canhttp::convert::{ConvertServiceBuilder, FilterFn};
+//! use tower::{ServiceBuilder, Service, ServiceExt};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct BodyTooLarge(usize);
+//!
+//! async fn bare_bone_service(request: Vec<u8>) -> Result<Vec<u8>, BodyTooLarge> {
+//!     Ok(request)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let mut service = ServiceBuilder::new()
+//!     .filter_request(FilterFn::new(|request: &Vec<u8>| {
+//!         if request.len() > 2 {
+//!             return Err(BodyTooLarge(request.len()));
+//!         }
+//!         Ok(())
+//!     }))
+//!     .service_fn(bare_bone_service);
+//!
+//! let response = service.ready().await.unwrap().call(vec![1, 2]).await;
+//! assert_eq!(response, Ok(vec![1, 2]));
+//!
+//! let response = service.ready().await.unwrap().call(vec![1, 2, 3]).await;
+//! assert_eq!(response, Err(BodyTooLarge(3)));
+//! # }
+//! ```
+//!
+//! ## To map requests or responses with a closure
+//!
+//! For light transformations, implementing [`Convert`] may be overkill.
+//! Requests and responses can instead be mapped with a closure that may fail.
+//!
+//! ```rust
+//! use canhttp::convert::ConvertServiceBuilder;
+//! use tower::{ServiceBuilder, Service, ServiceExt};
+//!
+//! async fn bare_bone_service(request: Vec<u8>) -> Result<Vec<u8>, String> {
+//!     Ok(request)
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let mut service = ServiceBuilder::new()
+//!     .try_map_request(|request: String| {
+//!         if request.is_empty() {
+//!             return Err("empty request".to_string());
+//!         }
+//!         Ok(request.into_bytes())
+//!     })
+//!     .try_map_response(|response: Vec<u8>| {
+//!         String::from_utf8(response).map_err(|e| e.to_string())
+//!     })
+//!     .service_fn(bare_bone_service);
+//!
+//! let response = service.ready().await.unwrap().call("hello".to_string()).await;
+//! assert_eq!(response, Ok("hello".to_string()));
+//!
+//! let response = service.ready().await.unwrap().call(String::new()).await;
+//! assert_eq!(response, Err("empty request".to_string()));
+//! # }
+//! ```
 
-pub use error::{ConvertError, ConvertErrorLayer};
-pub use request::{ConvertRequest, ConvertRequestLayer};
+pub use error::{
+    ContextualError, ConvertError, ConvertErrorLayer, ErrorContext, ErrorContextLayer,
+    ErrorContextService,
+};
+pub use map::{
+    TryMapRequest, TryMapRequestLayer, TryMapResponse, TryMapResponseFuture, TryMapResponseLayer,
+};
+pub use request::{ConvertRequest, ConvertRequestLayer, RequestFilterLayer};
 pub use response::{
     ConvertResponse, ConvertResponseLayer, CreateResponseFilter, CreateResponseFilterLayer,
     FilterResponse,
 };
 
 mod error;
+mod map;
 mod request;
 mod response;
+#[cfg(test)]
+mod tests;
 
 use tower::ServiceBuilder;
 use tower_layer::Stack;
@@ -188,6 +304,11 @@ pub trait ConvertServiceBuilder<L> {
     /// See the [module docs](crate::convert) for examples.
     fn convert_response<C>(self, f: C) -> ServiceBuilder<Stack<ConvertResponseLayer<C>, L>>;
 
+    /// Filter the request and reject it with a typed error before calling the inner service.
+    ///
+    /// See the [module docs](crate::convert) for examples.
+    fn filter_request<F>(self, f: F) -> ServiceBuilder<Stack<RequestFilterLayer<F>, L>>;
+
     /// Filter the response depending on the request.
     ///
     /// See the [module docs](crate::convert) for examples.
@@ -197,6 +318,27 @@ pub trait ConvertServiceBuilder<L> {
     ///
     /// See the [module docs](crate::convert) for examples.
     fn convert_error<NewError>(self) -> ServiceBuilder<Stack<ConvertErrorLayer<NewError>, L>>;
+
+    /// Attach contextual metadata derived from the request to errors.
+    ///
+    /// See the [module docs](crate::convert) for examples.
+    fn error_context<F>(self, f: F) -> ServiceBuilder<Stack<ErrorContextLayer<F>, L>>;
+
+    /// Map the request type with a closure, where the mapping may fail.
+    ///
+    /// This avoids implementing [`Convert`] for light transformations.
+    /// For mappings that cannot fail, use [`ServiceBuilder::map_request`] instead.
+    ///
+    /// See the [module docs](crate::convert) for examples.
+    fn try_map_request<F>(self, f: F) -> ServiceBuilder<Stack<TryMapRequestLayer<F>, L>>;
+
+    /// Map the response type with a closure, where the mapping may fail.
+    ///
+    /// This avoids implementing [`Convert`] for light transformations.
+    /// For mappings that cannot fail, use [`ServiceBuilder::map_response`] instead.
+    ///
+    /// See the [module docs](crate::convert) for examples.
+    fn try_map_response<F>(self, f: F) -> ServiceBuilder<Stack<TryMapResponseLayer<F>, L>>;
 }
 
 impl<L> ConvertServiceBuilder<L> for ServiceBuilder<L> {
@@ -211,6 +353,10 @@ impl<L> ConvertServiceBuilder<L> for ServiceBuilder<L> {
         self.layer(ConvertResponseLayer::new(converter))
     }
 
+    fn filter_request<F>(self, f: F) -> ServiceBuilder<Stack<RequestFilterLayer<F>, L>> {
+        self.layer(RequestFilterLayer::new(f))
+    }
+
     fn filter_response<F>(self, f: F) -> ServiceBuilder<Stack<CreateResponseFilterLayer<F>, L>> {
         self.layer(CreateResponseFilterLayer::new(f))
     }
@@ -218,6 +364,18 @@ impl<L> ConvertServiceBuilder<L> for ServiceBuilder<L> {
     fn convert_error<NewError>(self) -> ServiceBuilder<Stack<ConvertErrorLayer<NewError>, L>> {
         self.layer(ConvertErrorLayer::new())
     }
+
+    fn error_context<F>(self, f: F) -> ServiceBuilder<Stack<ErrorContextLayer<F>, L>> {
+        self.layer(ErrorContextLayer::new(f))
+    }
+
+    fn try_map_request<F>(self, f: F) -> ServiceBuilder<Stack<TryMapRequestLayer<F>, L>> {
+        self.layer(TryMapRequestLayer::new(f))
+    }
+
+    fn try_map_response<F>(self, f: F) -> ServiceBuilder<Stack<TryMapResponseLayer<F>, L>> {
+        self.layer(TryMapResponseLayer::new(f))
+    }
 }
 
 /// Filter the response.
@@ -239,3 +397,30 @@ impl<Input, F: Filter<Input>> Convert<Input> for F {
         self.filter(response)
     }
 }
+
+/// Filter implemented by a closure that inspects the input and returns an error to reject it.
+///
+/// See the [module docs](crate::convert) for examples.
+#[derive(Clone, Debug)]
+pub struct FilterFn<F> {
+    f: F,
+}
+
+impl<F> FilterFn<F> {
+    /// Create a new [`FilterFn`] from the given closure.
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<Input, Error, F> Filter<Input> for FilterFn<F>
+where
+    F: FnMut(&Input) -> Result<(), Error>,
+{
+    type Error = Error;
+
+    fn filter(&mut self, input: Input) -> Result<Input, Self::Error> {
+        (self.f)(&input)?;
+        Ok(input)
+    }
+}
@@ -61,3 +61,31 @@ where
         }
     }
 }
+
+/// Filter requests of a service, where the request is rejected with a typed error
+/// if the filter fails, *before* calling the inner service.
+///
+/// This [`Layer`] produces instances of the [`ConvertRequest`] service,
+/// where the converter is a [`Filter`](crate::convert::Filter).
+/// Use [`FilterFn`](crate::convert::FilterFn) to filter requests with a closure.
+///
+/// [`Layer`]: tower::Layer
+#[derive(Debug, Clone)]
+pub struct RequestFilterLayer<F> {
+    filter: F,
+}
+
+impl<F> RequestFilterLayer<F> {
+    /// Returns a new [`RequestFilterLayer`]
+    pub fn new(filter: F) -> Self {
+        Self { filter }
+    }
+}
+
+impl<S, F: Clone> Layer<S> for RequestFilterLayer<F> {
+    type Service = ConvertRequest<S, F>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.filter.clone()).layer(inner)
+    }
+}
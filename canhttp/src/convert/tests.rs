@@ -0,0 +1,198 @@
+use crate::convert::{ConvertServiceBuilder, FilterFn};
+use std::{cell::RefCell, rc::Rc};
+use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+type Requests = Rc<RefCell<Vec<Vec<u8>>>>;
+
+fn echo_service(
+    requests: Requests,
+) -> impl Service<Vec<u8>, Response = Vec<u8>, Error = String> + Clone {
+    tower::service_fn(move |request: Vec<u8>| {
+        requests.borrow_mut().push(request.clone());
+        async move {
+            if request.is_empty() {
+                return Err("empty body".to_string());
+            }
+            Ok(request)
+        }
+    })
+}
+
+mod try_map_request {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_map_request() {
+        let requests = Requests::default();
+        let mut service = ServiceBuilder::new()
+            .try_map_request(|request: String| Ok::<_, String>(request.into_bytes()))
+            .service(echo_service(requests.clone()));
+
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call("hello".to_string())
+            .await;
+
+        assert_eq!(response, Ok(b"hello".to_vec()));
+        assert_eq!(*requests.borrow(), vec![b"hello".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn should_reject_request_without_calling_inner_service() {
+        let requests = Requests::default();
+        let mut service = ServiceBuilder::new()
+            .try_map_request(|request: String| {
+                if request.len() > 3 {
+                    return Err(format!("request too long: {}", request.len()));
+                }
+                Ok(request.into_bytes())
+            })
+            .service(echo_service(requests.clone()));
+
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call("hello".to_string())
+            .await;
+
+        assert_eq!(response, Err("request too long: 5".to_string()));
+        assert!(requests.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_convert_mapping_error_into_service_error() {
+        let mut service = ServiceBuilder::new()
+            .try_map_request(|request: &str| request.parse::<u8>().map(|byte| vec![byte]))
+            .service(echo_service(Requests::default()).map_err(BoxError::from));
+
+        let response = service.ready().await.unwrap().call("1").await;
+        assert_eq!(response.unwrap(), vec![1]);
+
+        let error = service.ready().await.unwrap().call("x").await.unwrap_err();
+        assert_eq!(error.to_string(), "invalid digit found in string");
+    }
+}
+
+mod try_map_response {
+    use super::*;
+
+    #[tokio::test]
+    async fn should_map_response() {
+        let mut service = ServiceBuilder::new()
+            .try_map_response(|response: Vec<u8>| {
+                String::from_utf8(response).map_err(|e| e.to_string())
+            })
+            .service(echo_service(Requests::default()));
+
+        let response = service.ready().await.unwrap().call(b"hello".to_vec()).await;
+
+        assert_eq!(response, Ok("hello".to_string()));
+    }
+
+    #[tokio::test]
+    async fn should_return_mapping_error() {
+        let mut service = ServiceBuilder::new()
+            .try_map_response(|response: Vec<u8>| {
+                String::from_utf8(response).map_err(|_| "invalid UTF-8".to_string())
+            })
+            .service(echo_service(Requests::default()));
+
+        let response = service.ready().await.unwrap().call(vec![0xff]).await;
+
+        assert_eq!(response, Err("invalid UTF-8".to_string()));
+    }
+
+    #[tokio::test]
+    async fn should_not_map_error_of_inner_service() {
+        let mapped = Rc::new(RefCell::new(0_usize));
+        let mut service = ServiceBuilder::new()
+            .try_map_response({
+                let mapped = mapped.clone();
+                move |response: Vec<u8>| {
+                    *mapped.borrow_mut() += 1;
+                    Ok::<_, String>(response.len())
+                }
+            })
+            .service(echo_service(Requests::default()));
+
+        let response = service.ready().await.unwrap().call(vec![]).await;
+
+        assert_eq!(response, Err("empty body".to_string()));
+        assert_eq!(*mapped.borrow(), 0);
+    }
+}
+
+mod filter_request {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct BodyTooLarge(usize);
+
+    impl From<BodyTooLarge> for String {
+        fn from(error: BodyTooLarge) -> Self {
+            format!("body too large: {}", error.0)
+        }
+    }
+
+    fn max_body_size(
+        max: usize,
+    ) -> FilterFn<impl Fn(&Vec<u8>) -> Result<(), BodyTooLarge> + Clone> {
+        FilterFn::new(move |request: &Vec<u8>| {
+            if request.len() > max {
+                return Err(BodyTooLarge(request.len()));
+            }
+            Ok(())
+        })
+    }
+
+    #[tokio::test]
+    async fn should_forward_accepted_request_unchanged() {
+        let requests = Requests::default();
+        let mut service = ServiceBuilder::new()
+            .filter_request(max_body_size(2))
+            .service(echo_service(requests.clone()));
+
+        let response = service.ready().await.unwrap().call(vec![1, 2]).await;
+
+        assert_eq!(response, Ok(vec![1, 2]));
+        assert_eq!(*requests.borrow(), vec![vec![1, 2]]);
+    }
+
+    #[tokio::test]
+    async fn should_reject_request_without_calling_inner_service() {
+        let requests = Requests::default();
+        let mut service = ServiceBuilder::new()
+            .filter_request(max_body_size(2))
+            .service(echo_service(requests.clone()));
+
+        let response = service.ready().await.unwrap().call(vec![1, 2, 3]).await;
+
+        assert_eq!(response, Err("body too large: 3".to_string()));
+        assert!(requests.borrow().is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_apply_filters_in_order() {
+        let requests = Requests::default();
+        let mut service = ServiceBuilder::new()
+            .filter_request(FilterFn::new(|request: &Vec<u8>| {
+                if request.first() == Some(&0) {
+                    return Err("leading zero".to_string());
+                }
+                Ok(())
+            }))
+            .filter_request(max_body_size(2))
+            .service(echo_service(requests.clone()));
+
+        let response = service.ready().await.unwrap().call(vec![0, 1, 2]).await;
+        assert_eq!(response, Err("leading zero".to_string()));
+
+        let response = service.ready().await.unwrap().call(vec![1, 2, 3]).await;
+        assert_eq!(response, Err("body too large: 3".to_string()));
+
+        assert!(requests.borrow().is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::cycles::CyclesChargingPolicy;
+use candid::Principal;
+use ic_cdk_management_canister::HttpRequestArgs;
+use std::collections::BTreeSet;
+
+/// Waive charging for callers on an allowlist, e.g. the controllers of the canister or partner
+/// canisters, and charge all other callers with the given policy, typically a
+/// [`ChargeCaller`](crate::cycles::ChargeCaller).
+///
+/// The canister pays for the HTTPs outcalls of callers on the allowlist with its own cycles.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{cycles::{ChargeCaller, ChargeCallerExcept, CyclesAccountingServiceBuilder}, Client};
+/// use candid::Principal;
+/// use tower::ServiceBuilder;
+///
+/// let partner = Principal::from_text("mxzaz-hqaaa-aaaar-qaada-cai").unwrap();
+/// let service = ServiceBuilder::new()
+///   .cycles_accounting(ChargeCallerExcept::new([partner], ChargeCaller::with_markup_percent(10)))
+///   .service(Client::new_with_box_error());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChargeCallerExcept<P> {
+    allowlist: BTreeSet<Principal>,
+    policy: P,
+}
+
+impl<P> ChargeCallerExcept<P> {
+    /// Create a new instance of [`ChargeCallerExcept`] waiving charging for the given principals
+    /// and charging other callers with the given policy.
+    pub fn new(allowlist: impl IntoIterator<Item = Principal>, policy: P) -> Self {
+        Self {
+            allowlist: allowlist.into_iter().collect(),
+            policy,
+        }
+    }
+
+    /// Returns `true` if the given principal is not charged.
+    pub fn is_exempt(&self, principal: &Principal) -> bool {
+        self.allowlist.contains(principal)
+    }
+}
+
+impl<P: CyclesChargingPolicy> CyclesChargingPolicy for ChargeCallerExcept<P> {
+    type Error = P::Error;
+
+    fn cycles_to_charge(&self, request: &HttpRequestArgs, request_cycles_cost: u128) -> u128 {
+        if self.is_exempt(&ic_cdk::api::msg_caller()) {
+            return 0;
+        }
+        self.policy.cycles_to_charge(request, request_cycles_cost)
+    }
+
+    fn charge_cycles(
+        &self,
+        request: &HttpRequestArgs,
+        request_cycles_cost: u128,
+    ) -> Result<u128, Self::Error> {
+        if self.is_exempt(&ic_cdk::api::msg_caller()) {
+            return Ok(0);
+        }
+        self.policy.charge_cycles(request, request_cycles_cost)
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{
+    cycles::{CyclesChargingPolicy, CyclesCostEstimator, CyclesEvent, CyclesObserver},
+    http::take_ready_service,
+};
+use ic_cdk_management_canister::HttpRequestArgs;
+use std::{
+    future::{Future, Ready},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::Service;
+use tower_layer::Layer;
+
+/// Charge cycles to pay for a single HTTPs outcall, where charging may require awaiting,
+/// e.g. to check an allowance on another canister.
+///
+/// Any [`CyclesChargingPolicy`] is also an [`AsyncCyclesChargingPolicy`] whose future
+/// is immediately ready.
+pub trait AsyncCyclesChargingPolicy {
+    /// Type returned in case of a charging error.
+    type Error;
+    /// Future returned by [`AsyncCyclesChargingPolicy::charge_cycles_async`].
+    type Future: Future<Output = Result<u128, Self::Error>>;
+
+    /// Charge cycles and return the charged amount.
+    fn charge_cycles_async(
+        &self,
+        request: &HttpRequestArgs,
+        request_cycles_cost: u128,
+    ) -> Self::Future;
+}
+
+impl<P: CyclesChargingPolicy> AsyncCyclesChargingPolicy for P {
+    type Error = P::Error;
+    type Future = Ready<Result<u128, P::Error>>;
+
+    fn charge_cycles_async(
+        &self,
+        request: &HttpRequestArgs,
+        request_cycles_cost: u128,
+    ) -> Self::Future {
+        std::future::ready(self.charge_cycles(request, request_cycles_cost))
+    }
+}
+
+/// Middleware to handle cycles accounting with an [`AsyncCyclesChargingPolicy`], like
+/// [`CyclesAccounting`](crate::cycles::CyclesAccounting) does for a [`CyclesChargingPolicy`].
+///
+/// The request is forwarded to the inner service once charging completed successfully.
+/// The cost of each request is computed with [`ic_cdk_management_canister::cost_http_request`]
+/// unless a [`CyclesCostEstimator`] is given with
+/// [`AsyncCyclesAccountingLayer::with_cost_estimator`].
+///
+/// This [`Layer`] produces instances of the [`AsyncCyclesAccounting`] service.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{
+///     cycles::{AsyncCyclesChargingPolicy, CyclesAccountingServiceBuilder},
+///     Client,
+/// };
+/// use ic_cdk_management_canister::HttpRequestArgs;
+/// use std::{future::Future, pin::Pin};
+/// use tower::{BoxError, ServiceBuilder};
+///
+/// #[derive(Clone)]
+/// struct ChargeAfterCheck;
+///
+/// impl AsyncCyclesChargingPolicy for ChargeAfterCheck {
+///     type Error = BoxError;
+///     type Future = Pin<Box<dyn Future<Output = Result<u128, BoxError>>>>;
+///
+///     fn charge_cycles_async(&self, _request: &HttpRequestArgs, cost: u128) -> Self::Future {
+///         Box::pin(async move {
+///             // e.g. call another canister to check that the caller is allowed to spend `cost`.
+///             Ok(0)
+///         })
+///     }
+/// }
+///
+/// let service = ServiceBuilder::new()
+///   .async_cycles_accounting(ChargeAfterCheck)
+///   .service(Client::new_with_box_error());
+/// ```
+///
+/// [`Layer`]: tower::Layer
+#[derive(Clone, Debug)]
+pub struct AsyncCyclesAccountingLayer<ChargingPolicy, Observer = ()> {
+    charging_policy: ChargingPolicy,
+    observer: Observer,
+    cost_estimator: Option<CyclesCostEstimator>,
+}
+
+impl<ChargingPolicy> AsyncCyclesAccountingLayer<ChargingPolicy> {
+    /// Create a new middleware given the charging policy.
+    pub fn new(charging_policy: ChargingPolicy) -> Self {
+        Self {
+            charging_policy,
+            observer: (),
+            cost_estimator: None,
+        }
+    }
+}
+
+impl<ChargingPolicy, Observer> AsyncCyclesAccountingLayer<ChargingPolicy, Observer> {
+    /// Compute the cost of each request with the given [`CyclesCostEstimator`] instead of
+    /// [`ic_cdk_management_canister::cost_http_request`], see
+    /// [`CyclesAccounting::with_cost_estimator`](crate::cycles::CyclesAccounting::with_cost_estimator).
+    pub fn with_cost_estimator(mut self, cost_estimator: CyclesCostEstimator) -> Self {
+        self.cost_estimator = Some(cost_estimator);
+        self
+    }
+
+    /// Report a [`CyclesEvent`] to the given [`CyclesObserver`] for each request,
+    /// see [`CyclesAccounting::on_cycles`](crate::cycles::CyclesAccounting::on_cycles).
+    pub fn on_cycles<NewObserver>(
+        self,
+        observer: NewObserver,
+    ) -> AsyncCyclesAccountingLayer<ChargingPolicy, NewObserver> {
+        AsyncCyclesAccountingLayer {
+            charging_policy: self.charging_policy,
+            observer,
+            cost_estimator: self.cost_estimator,
+        }
+    }
+}
+
+impl<S, ChargingPolicy: Clone, Observer: Clone> Layer<S>
+    for AsyncCyclesAccountingLayer<ChargingPolicy, Observer>
+{
+    type Service = AsyncCyclesAccounting<S, ChargingPolicy, Observer>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AsyncCyclesAccounting {
+            inner,
+            charging_policy: self.charging_policy.clone(),
+            observer: self.observer.clone(),
+            cost_estimator: self.cost_estimator.clone(),
+        }
+    }
+}
+
+/// Charge cycles with an [`AsyncCyclesChargingPolicy`] before forwarding requests to the inner
+/// service, see [`AsyncCyclesAccountingLayer`].
+#[derive(Clone, Debug)]
+pub struct AsyncCyclesAccounting<S, ChargingPolicy, Observer> {
+    inner: S,
+    charging_policy: ChargingPolicy,
+    observer: Observer,
+    cost_estimator: Option<CyclesCostEstimator>,
+}
+
+impl<S, ChargingPolicy, Observer> Service<HttpRequestArgs>
+    for AsyncCyclesAccounting<S, ChargingPolicy, Observer>
+where
+    S: Service<HttpRequestArgs> + Clone + 'static,
+    ChargingPolicy: AsyncCyclesChargingPolicy,
+    ChargingPolicy::Future: 'static,
+    ChargingPolicy::Error: Into<S::Error>,
+    Observer: CyclesObserver + Clone + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequestArgs) -> Self::Future {
+        let mut inner = take_ready_service(&mut self.inner);
+        let observer = self.observer.clone();
+        let cycles_to_attach = match &self.cost_estimator {
+            Some(estimator) => estimator.cost_of_http_request(&request),
+            None => ic_cdk_management_canister::cost_http_request(&request),
+        };
+        let charging = self
+            .charging_policy
+            .charge_cycles_async(&request, cycles_to_attach);
+        Box::pin(async move {
+            let charged = charging.await;
+            observer.observe_cycles(
+                &request,
+                &CyclesEvent::new(cycles_to_attach, charged.as_ref().ok().copied()),
+            );
+            charged.map_err(Into::into)?;
+            inner.call(request).await
+        })
+    }
+}
@@ -0,0 +1,99 @@
+use std::{cell::Cell, rc::Rc};
+
+/// Access to the cycles attached by the caller of the canister to the current call, so that
+/// charging policies such as [`ChargeCaller`](crate::cycles::ChargeCaller) can be tested
+/// off-chain with a [`StubCyclesEnvironment`].
+pub trait CyclesEnvironment {
+    /// Returns the number of cycles attached to the current call that are still available,
+    /// see [`ic_cdk::api::msg_cycles_available`].
+    fn cycles_available(&self) -> u128;
+
+    /// Accept at most the given number of cycles attached to the current call and return
+    /// the number of cycles actually accepted, see [`ic_cdk::api::msg_cycles_accept`].
+    fn accept_cycles(&self, max_amount: u128) -> u128;
+
+    /// Returns the number of cycles refunded by the last completed inter-canister call,
+    /// see [`ic_cdk::api::msg_cycles_refunded`].
+    fn cycles_refunded(&self) -> u128;
+}
+
+/// [`CyclesEnvironment`] of a canister running on the Internet Computer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IcCyclesEnvironment;
+
+impl CyclesEnvironment for IcCyclesEnvironment {
+    fn cycles_available(&self) -> u128 {
+        ic_cdk::api::msg_cycles_available()
+    }
+
+    fn accept_cycles(&self, max_amount: u128) -> u128 {
+        ic_cdk::api::msg_cycles_accept(max_amount)
+    }
+
+    fn cycles_refunded(&self) -> u128 {
+        ic_cdk::api::msg_cycles_refunded()
+    }
+}
+
+/// [`CyclesEnvironment`] where the caller attached a fixed number of cycles, e.g. for unit tests.
+///
+/// All clones share the same cycles, so that a clone can be used to inspect how many cycles
+/// were accepted by a charging policy.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::cycles::{ChargeCaller, CyclesChargingPolicy, CyclesEnvironment, StubCyclesEnvironment};
+/// use ic_cdk_management_canister::HttpRequestArgs;
+///
+/// let environment = StubCyclesEnvironment::new(2_000_000);
+/// let policy = ChargeCaller::with_markup_percent(10).with_environment(environment.clone());
+///
+/// assert_eq!(policy.charge_cycles(&HttpRequestArgs::default(), 1_000_000), Ok(1_100_000));
+/// assert_eq!(environment.accepted(), 1_100_000);
+/// assert_eq!(environment.cycles_available(), 900_000);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StubCyclesEnvironment {
+    available: Rc<Cell<u128>>,
+    accepted: Rc<Cell<u128>>,
+    refunded: Rc<Cell<u128>>,
+}
+
+impl StubCyclesEnvironment {
+    /// Create a new [`StubCyclesEnvironment`] where the caller attached the given number of cycles.
+    pub fn new(attached: u128) -> Self {
+        Self {
+            available: Rc::new(Cell::new(attached)),
+            accepted: Rc::new(Cell::new(0)),
+            refunded: Rc::new(Cell::new(0)),
+        }
+    }
+
+    /// Returns the total number of cycles accepted so far.
+    pub fn accepted(&self) -> u128 {
+        self.accepted.get()
+    }
+
+    /// Set the number of cycles refunded by the last completed inter-canister call.
+    pub fn set_refunded(&self, refunded: u128) {
+        self.refunded.set(refunded);
+    }
+}
+
+impl CyclesEnvironment for StubCyclesEnvironment {
+    fn cycles_available(&self) -> u128 {
+        self.available.get()
+    }
+
+    fn accept_cycles(&self, max_amount: u128) -> u128 {
+        let accepted = max_amount.min(self.available.get());
+        self.available.set(self.available.get() - accepted);
+        self.accepted.set(self.accepted.get() + accepted);
+        accepted
+    }
+
+    fn cycles_refunded(&self) -> u128 {
+        self.refunded.get()
+    }
+}
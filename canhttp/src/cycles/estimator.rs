@@ -0,0 +1,195 @@
+use ic_cdk_management_canister::HttpRequestArgs;
+
+/// Estimate the cost in cycles of HTTPs outcalls without calling the Internet Computer, e.g. to
+/// quote a price to the caller of a canister before the request is sent, following the
+/// [cycles cost formulas](https://internetcomputer.org/docs/references/cycles-cost-formulas#https-outcalls).
+///
+/// The cost of an HTTPs outcall depends on the number of nodes in the subnet of the canister
+/// issuing it, and is made of:
+/// 1. a base fee of `(3_000_000 + 60_000 * n) * n` cycles,
+/// 2. a request fee of `400 * n` cycles per request byte,
+/// 3. a response fee of `800 * n` cycles per byte of `max_response_bytes`,
+///
+/// where `n` is the number of nodes in the subnet and the constants are given by the
+/// [`PricingTable`], by default the current mainnet pricing. The request bytes are the bytes of the URL,
+/// of the header names and values, of the body, and of the transform method name and context.
+///
+/// These fees do not reflect that each node executes the transform function, whose result can
+/// be as large as `max_response_bytes`. To avoid undercharging callers, the estimator can
+/// additionally model a transform fee, priced like the response bytes, per byte of transform result,
+/// see [`CyclesCostEstimator::transform_result_bytes`].
+///
+/// Non-replicated outcalls (see [`IsReplicatedRequestExtension`]) are estimated like replicated
+/// ones, since the cost computed by the Internet Computer with
+/// [`ic0.cost_http_request`](https://internetcomputer.org/docs/references/ic-interface-spec#system-api-costs)
+/// only depends on the request size and on `max_response_bytes`.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::cycles::CyclesCostEstimator;
+/// use ic_cdk_management_canister::HttpRequestArgs as IcHttpRequest;
+///
+/// let estimator = CyclesCostEstimator::new(13);
+/// let request = IcHttpRequest {
+///     url: "https://example.com".to_string(),
+///     max_response_bytes: Some(1_000),
+///     ..Default::default()
+/// };
+///
+/// let breakdown = estimator.cost_breakdown(&request);
+/// assert_eq!(breakdown.request_bytes, 19);
+/// assert_eq!(breakdown.base_fee, 49_140_000);
+/// assert_eq!(breakdown.request_fee, 98_800);
+/// assert_eq!(breakdown.response_fee, 10_400_000);
+/// assert_eq!(breakdown.total(), estimator.cost_of_http_request(&request));
+/// ```
+///
+/// [`IsReplicatedRequestExtension`]: crate::IsReplicatedRequestExtension
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CyclesCostEstimator {
+    num_nodes_in_subnet: u32,
+    transform_result_bytes: Option<u64>,
+    pricing: PricingTable,
+}
+
+impl CyclesCostEstimator {
+    /// Maximum response size used when a request does not specify `max_response_bytes`.
+    pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 2_000_000;
+
+    /// Create a new [`CyclesCostEstimator`] for a canister running on a subnet with the given
+    /// number of nodes.
+    pub const fn new(num_nodes_in_subnet: u32) -> Self {
+        Self {
+            num_nodes_in_subnet,
+            transform_result_bytes: None,
+            pricing: PricingTable::MAINNET,
+        }
+    }
+
+    /// Use the given pricing instead of the current mainnet pricing, e.g. to follow a pricing
+    /// change without waiting for a new release of this crate.
+    pub const fn pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Account for the result of the transform function of requests having one, which is
+    /// expected to be at most the given number of bytes per node.
+    ///
+    /// The transform result size is capped by the `max_response_bytes` of each request.
+    /// By default, the transform result is not accounted for.
+    pub const fn transform_result_bytes(mut self, transform_result_bytes: u64) -> Self {
+        self.transform_result_bytes = Some(transform_result_bytes);
+        self
+    }
+
+    /// Returns the number of nodes in the subnet used to estimate costs.
+    pub fn num_nodes_in_subnet(&self) -> u32 {
+        self.num_nodes_in_subnet
+    }
+
+    /// Estimate the total cost in cycles of the given request.
+    pub fn cost_of_http_request(&self, request: &HttpRequestArgs) -> u128 {
+        self.cost_breakdown(request).total()
+    }
+
+    /// Estimate the cost in cycles of the given request, broken down by fee.
+    pub fn cost_breakdown(&self, request: &HttpRequestArgs) -> CyclesCostBreakdown {
+        let n = self.num_nodes_in_subnet as u128;
+        let request_bytes = request_bytes(request);
+        let max_response_bytes = request
+            .max_response_bytes
+            .unwrap_or(Self::DEFAULT_MAX_RESPONSE_BYTES);
+        let transform_result_bytes = match (&request.transform, self.transform_result_bytes) {
+            (Some(_), Some(bytes)) => bytes.min(max_response_bytes),
+            _ => 0,
+        };
+        CyclesCostBreakdown {
+            num_nodes_in_subnet: self.num_nodes_in_subnet,
+            request_bytes,
+            max_response_bytes,
+            transform_result_bytes,
+            base_fee: (self.pricing.base_fee + self.pricing.base_fee_per_node * n) * n,
+            request_fee: self.pricing.request_byte_fee * n * request_bytes as u128,
+            response_fee: self.pricing.response_byte_fee * n * max_response_bytes as u128,
+            transform_fee: self.pricing.response_byte_fee * n * transform_result_bytes as u128,
+        }
+    }
+}
+
+/// Fees used by [`CyclesCostEstimator`] to compute the cost of HTTPs outcalls.
+///
+/// The fees are per node of the subnet, e.g. the request fee of an HTTPs outcall is
+/// `request_byte_fee * n * request_bytes` for a subnet with `n` nodes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PricingTable {
+    /// Base fee in cycles charged for any HTTPs outcall.
+    pub base_fee: u128,
+    /// Additional base fee in cycles per node of the subnet.
+    pub base_fee_per_node: u128,
+    /// Fee in cycles per request byte.
+    pub request_byte_fee: u128,
+    /// Fee in cycles per byte of `max_response_bytes`.
+    pub response_byte_fee: u128,
+}
+
+impl PricingTable {
+    /// Current pricing on the Internet Computer mainnet.
+    pub const MAINNET: PricingTable = PricingTable {
+        base_fee: 3_000_000,
+        base_fee_per_node: 60_000,
+        request_byte_fee: 400,
+        response_byte_fee: 800,
+    };
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        Self::MAINNET
+    }
+}
+
+fn request_bytes(request: &HttpRequestArgs) -> u64 {
+    let headers_bytes: usize = request
+        .headers
+        .iter()
+        .map(|header| header.name.len() + header.value.len())
+        .sum();
+    let body_bytes = request.body.as_ref().map_or(0, Vec::len);
+    let transform_bytes = request.transform.as_ref().map_or(0, |transform| {
+        transform.function.0.method.len() + transform.context.len()
+    });
+    (request.url.len() + headers_bytes + body_bytes + transform_bytes) as u64
+}
+
+/// Cost in cycles of an HTTPs outcall broken down by fee, together with the inputs used to
+/// compute it, see [`CyclesCostEstimator::cost_breakdown`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CyclesCostBreakdown {
+    /// Number of nodes in the subnet.
+    pub num_nodes_in_subnet: u32,
+    /// Number of bytes of the request that are charged.
+    pub request_bytes: u64,
+    /// Maximum number of bytes of the response that are charged.
+    pub max_response_bytes: u64,
+    /// Maximum number of bytes of the transform result per node that are accounted for,
+    /// see [`CyclesCostEstimator::transform_result_bytes`].
+    pub transform_result_bytes: u64,
+    /// Fee charged for any HTTPs outcall.
+    pub base_fee: u128,
+    /// Fee charged for the request bytes.
+    pub request_fee: u128,
+    /// Fee charged for the maximum response bytes.
+    pub response_fee: u128,
+    /// Fee modelling the execution of the transform function on each node, which is `0`
+    /// unless [`CyclesCostEstimator::transform_result_bytes`] is set.
+    pub transform_fee: u128,
+}
+
+impl CyclesCostBreakdown {
+    /// Returns the total cost in cycles.
+    pub fn total(&self) -> u128 {
+        self.base_fee + self.request_fee + self.response_fee + self.transform_fee
+    }
+}
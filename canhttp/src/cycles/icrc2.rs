@@ -0,0 +1,319 @@
+#[cfg(feature = "http")]
+use crate::cycles::AsyncCyclesChargingPolicy;
+use crate::{client::convert_call_error, IcError};
+use candid::{CandidType, Deserialize, Nat, Principal};
+use ic_cdk::call::Call;
+use ic_cdk_management_canister::HttpRequestArgs;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tower::Service;
+#[cfg(feature = "http")]
+use tower::ServiceExt;
+
+/// Subaccount of an ICRC-1 [`Account`].
+pub type Subaccount = [u8; 32];
+
+/// Account on an ICRC-1 ledger, see the
+/// [ICRC-1 standard](https://github.com/dfinity/ICRC-1/blob/main/standards/ICRC-1/README.md#account).
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Account {
+    /// Owner of the account.
+    pub owner: Principal,
+    /// Subaccount of the account, where `None` designates the default subaccount.
+    pub subaccount: Option<Subaccount>,
+}
+
+impl From<Principal> for Account {
+    fn from(owner: Principal) -> Self {
+        Self {
+            owner,
+            subaccount: None,
+        }
+    }
+}
+
+/// Arguments of the `icrc2_transfer_from` method of an ICRC-2 ledger, see the
+/// [ICRC-2 standard](https://github.com/dfinity/ICRC-1/blob/main/standards/ICRC-2/README.md#icrc2_transfer_from).
+#[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct TransferFromArgs {
+    /// Subaccount of the spender, i.e. of the canister charging the caller.
+    pub spender_subaccount: Option<Subaccount>,
+    /// Account from which the tokens are transferred.
+    pub from: Account,
+    /// Account to which the tokens are transferred.
+    pub to: Account,
+    /// Amount of tokens to transfer.
+    pub amount: Nat,
+    /// Transfer fee, if any, which must match the fee of the ledger.
+    pub fee: Option<Nat>,
+    /// Memo of the transfer.
+    pub memo: Option<Vec<u8>>,
+    /// Time of the creation of the transfer in nanoseconds since the Unix epoch,
+    /// used by the ledger to deduplicate transfers.
+    pub created_at_time: Option<u64>,
+}
+
+/// Error returned by the `icrc2_transfer_from` method of an ICRC-2 ledger.
+#[derive(CandidType, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum TransferFromError {
+    /// The fee of the transfer does not match the fee of the ledger.
+    BadFee {
+        /// Fee expected by the ledger.
+        expected_fee: Nat,
+    },
+    /// The amount of a burn transaction is too small.
+    BadBurn {
+        /// Minimum amount of a burn transaction.
+        min_burn_amount: Nat,
+    },
+    /// The balance of the caller is too low.
+    InsufficientFunds {
+        /// Balance of the caller.
+        balance: Nat,
+    },
+    /// The caller did not approve sufficiently many tokens to the canister.
+    InsufficientAllowance {
+        /// Current allowance of the canister.
+        allowance: Nat,
+    },
+    /// The `created_at_time` of the transfer is too far in the past.
+    TooOld,
+    /// The `created_at_time` of the transfer is in the future.
+    CreatedInFuture {
+        /// Current time of the ledger.
+        ledger_time: u64,
+    },
+    /// The transfer is a duplicate of a previous transfer.
+    Duplicate {
+        /// Index of the block containing the original transfer.
+        duplicate_of: Nat,
+    },
+    /// The ledger is temporarily unavailable.
+    TemporarilyUnavailable,
+    /// Any other error.
+    GenericError {
+        /// Error code.
+        error_code: Nat,
+        /// Error message.
+        message: String,
+    },
+}
+
+/// Error returned when charging the caller on an ICRC-2 ledger fails.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum Icrc2ChargingError {
+    /// The call to the ledger failed.
+    #[error(transparent)]
+    Ic(#[from] IcError),
+    /// The response of the ledger could not be decoded.
+    #[error("Invalid response from the ledger: {0}")]
+    InvalidResponse(String),
+    /// The ledger rejected the transfer, e.g. because the caller did not approve sufficiently
+    /// many tokens to the canister.
+    #[error("Ledger rejected the transfer: {0:?}")]
+    TransferFrom(TransferFromError),
+}
+
+/// Thin wrapper around the `icrc2_transfer_from` method of an ICRC-2 ledger that implements
+/// the [`tower::Service`] trait.
+///
+/// The service returns the index of the block containing the transfer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Icrc2LedgerClient {
+    ledger_id: Principal,
+}
+
+impl Icrc2LedgerClient {
+    /// Create a new [`Icrc2LedgerClient`] for the ledger with the given canister ID.
+    pub fn new(ledger_id: Principal) -> Self {
+        Self { ledger_id }
+    }
+}
+
+impl Service<TransferFromArgs> for Icrc2LedgerClient {
+    type Response = Nat;
+    type Error = Icrc2ChargingError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, args: TransferFromArgs) -> Self::Future {
+        let ledger_id = self.ledger_id;
+        Box::pin(async move {
+            let response = Call::unbounded_wait(ledger_id, "icrc2_transfer_from")
+                .with_arg(args)
+                .await
+                .map_err(|e| convert_call_error(e.into()))?;
+            response
+                .candid::<Result<Nat, TransferFromError>>()
+                .map_err(|e| Icrc2ChargingError::InvalidResponse(e.to_string()))?
+                .map_err(Icrc2ChargingError::TransferFrom)
+        })
+    }
+}
+
+/// Tokens will be transferred from the caller of the canister to pay for HTTPs outcalls with
+/// an ICRC-2 `transfer_from`, instead of attaching cycles to the call, so that users can pay
+/// in a token.
+///
+/// The caller must first approve the canister to spend the tokens on their behalf with
+/// `icrc2_approve`. The canister still pays for the HTTPs outcall with its own cycles.
+///
+/// Since charging requires a call to the ledger, this policy is an [`AsyncCyclesChargingPolicy`]
+/// to be used with the [`AsyncCyclesAccountingLayer`]. The tokens are transferred by the
+/// given client (by default an [`Icrc2LedgerClient`]) before the request is forwarded to the
+/// inner service, which is not called if charging fails.
+///
+/// <div class="warning">
+/// The transferred tokens are <strong>not</strong> refunded if the HTTPs outcall fails after the
+/// <code>transfer_from</code> succeeded, e.g. because the request timed out or the response was
+/// too large. Refunding the caller, if desired, is up to the canister.
+/// </div>
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{
+///     cycles::{ChargeCallerIcrc2, CyclesAccountingServiceBuilder},
+///     Client,
+/// };
+/// use candid::Principal;
+/// use tower::ServiceBuilder;
+///
+/// let ckusdc_ledger = Principal::from_text("xevnm-gaaaa-aaaar-qafnq-cai").unwrap();
+/// // Charge 1 ckUSDC (6 decimals) per trillion cycles, rounded up.
+/// let policy = ChargeCallerIcrc2::new(ckusdc_ledger, |_request, cost| cost.div_ceil(1_000_000));
+///
+/// let service = ServiceBuilder::new()
+///   .async_cycles_accounting(policy)
+///   .service(Client::new_with_box_error());
+/// ```
+///
+/// [`AsyncCyclesAccountingLayer`]: crate::cycles::AsyncCyclesAccountingLayer
+/// [`AsyncCyclesChargingPolicy`]: crate::cycles::AsyncCyclesChargingPolicy
+#[derive(Clone, Debug)]
+pub struct ChargeCallerIcrc2<F, C = Icrc2LedgerClient> {
+    tokens_to_charge: F,
+    client: C,
+    to: Option<Account>,
+    spender_subaccount: Option<Subaccount>,
+    fee: Option<Nat>,
+    memo: Option<Vec<u8>>,
+}
+
+impl<F> ChargeCallerIcrc2<F>
+where
+    F: Fn(&HttpRequestArgs, u128) -> u128,
+{
+    /// Create a new instance of [`ChargeCallerIcrc2`] transferring tokens on the ledger with the
+    /// given canister ID, where the given function converts the cost in cycles of a request into
+    /// the amount of tokens to charge.
+    pub fn new(ledger_id: Principal, tokens_to_charge: F) -> Self {
+        Self {
+            tokens_to_charge,
+            client: Icrc2LedgerClient::new(ledger_id),
+            to: None,
+            spender_subaccount: None,
+            fee: None,
+            memo: None,
+        }
+    }
+}
+
+impl<F, C> ChargeCallerIcrc2<F, C>
+where
+    F: Fn(&HttpRequestArgs, u128) -> u128,
+{
+    /// Transfer the tokens with the given client instead of an [`Icrc2LedgerClient`].
+    pub fn with_client<NewClient>(self, client: NewClient) -> ChargeCallerIcrc2<F, NewClient> {
+        ChargeCallerIcrc2 {
+            tokens_to_charge: self.tokens_to_charge,
+            client,
+            to: self.to,
+            spender_subaccount: self.spender_subaccount,
+            fee: self.fee,
+            memo: self.memo,
+        }
+    }
+
+    /// Set the account receiving the tokens.
+    ///
+    /// Defaults to the default account of the canister.
+    pub fn to(mut self, account: impl Into<Account>) -> Self {
+        self.to = Some(account.into());
+        self
+    }
+
+    /// Set the subaccount of the canister that was approved by the caller.
+    pub fn spender_subaccount(mut self, subaccount: Subaccount) -> Self {
+        self.spender_subaccount = Some(subaccount);
+        self
+    }
+
+    /// Set the transfer fee, which must then match the fee of the ledger.
+    pub fn fee(mut self, fee: impl Into<Nat>) -> Self {
+        self.fee = Some(fee.into());
+        self
+    }
+
+    /// Set the memo of the transfers.
+    pub fn memo(mut self, memo: Vec<u8>) -> Self {
+        self.memo = Some(memo);
+        self
+    }
+
+    /// Return the number of tokens that would be charged for the given request.
+    pub fn tokens_to_charge(&self, request: &HttpRequestArgs, request_cycles_cost: u128) -> u128 {
+        (self.tokens_to_charge)(request, request_cycles_cost)
+    }
+
+    /// Returns the arguments of the `icrc2_transfer_from` call charging the given caller
+    /// for the given request.
+    pub fn transfer_from_args(
+        &self,
+        caller: impl Into<Account>,
+        request: &HttpRequestArgs,
+        request_cycles_cost: u128,
+    ) -> TransferFromArgs {
+        TransferFromArgs {
+            spender_subaccount: self.spender_subaccount,
+            from: caller.into(),
+            to: self
+                .to
+                .unwrap_or_else(|| Account::from(ic_cdk::api::canister_self())),
+            amount: Nat::from(self.tokens_to_charge(request, request_cycles_cost)),
+            fee: self.fee.clone(),
+            memo: self.memo.clone(),
+            created_at_time: None,
+        }
+    }
+}
+
+/// No cycles are charged, since the caller pays with tokens. The charged amount is therefore
+/// always `0`.
+#[cfg(feature = "http")]
+impl<F, C> AsyncCyclesChargingPolicy for ChargeCallerIcrc2<F, C>
+where
+    F: Fn(&HttpRequestArgs, u128) -> u128,
+    C: Service<TransferFromArgs> + Clone + 'static,
+    C::Future: 'static,
+{
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<u128, Self::Error>>>>;
+
+    fn charge_cycles_async(
+        &self,
+        request: &HttpRequestArgs,
+        request_cycles_cost: u128,
+    ) -> Self::Future {
+        let args = self.transfer_from_args(ic_cdk::api::msg_caller(), request, request_cycles_cost);
+        let transfer = self.client.clone().oneshot(args);
+        Box::pin(async move { transfer.await.map(|_block_index| 0) })
+    }
+}
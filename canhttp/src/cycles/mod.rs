@@ -24,7 +24,8 @@
 //! # }
 //! ```
 //!
-//! To charge the caller of the canister for the whole cost of the HTTPs outcall with an additional fixed fee of 1M cycles:
+//! To charge the caller of the canister for the whole cost of the HTTPs outcall with an additional fixed fee of 1M cycles
+//! (see also [`ChargeCaller::with_markup`]):
 //! ```rust
 //! use canhttp::{cycles::{ChargeCaller, CyclesAccountingServiceBuilder}, Client};
 //! use tower::{Service, ServiceBuilder, ServiceExt, BoxError};
@@ -40,7 +41,73 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! Cycles accounting can also be done on requests of type [`http::Request<Vec<u8>>`], i.e. before
+//! they are converted by the [`HttpConversionLayer`], in which case the cost of each request is
+//! added to its extensions, so that the layers in between can read it. These layers must not
+//! modify the request, since the cost charged would otherwise differ from the cost of the
+//! HTTPs outcall, see [`CyclesAccounting`]:
+//! ```rust
+//! use canhttp::{
+//!     cycles::{ChargeMyself, CyclesAccountingServiceBuilder, CyclesCostRequestExtension},
+//!     http::HttpConversionLayer,
+//!     Client,
+//! };
+//! use tower::ServiceBuilder;
+//!
+//! let service = ServiceBuilder::new()
+//!   .cycles_accounting(ChargeMyself::default())
+//!   .map_request(|request: http::Request<Vec<u8>>| {
+//!       ic_cdk::println!("Attaching {:?} cycles", request.get_cycles_cost());
+//!       request
+//!   })
+//!   .layer(HttpConversionLayer)
+//!   .service(Client::new_with_box_error());
+//! ```
+//!
+//! [`HttpConversionLayer`]: crate::http::HttpConversionLayer
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "candid")]
+mod allowlist;
+#[cfg(feature = "http")]
+mod asynchronous;
+mod environment;
+mod estimator;
+#[cfg(feature = "candid")]
+mod icrc2;
+#[cfg(feature = "http")]
+mod refund;
+#[cfg(feature = "http")]
+mod report;
+mod subnet;
+
+#[cfg(feature = "candid")]
+pub use allowlist::ChargeCallerExcept;
+#[cfg(feature = "http")]
+pub use asynchronous::{
+    AsyncCyclesAccounting, AsyncCyclesAccountingLayer, AsyncCyclesChargingPolicy,
+};
+pub use environment::{CyclesEnvironment, IcCyclesEnvironment, StubCyclesEnvironment};
+pub use estimator::{CyclesCostBreakdown, CyclesCostEstimator, PricingTable};
+#[cfg(feature = "candid")]
+pub use icrc2::{
+    Account, ChargeCallerIcrc2, Icrc2ChargingError, Icrc2LedgerClient, Subaccount,
+    TransferFromArgs, TransferFromError,
+};
+#[cfg(feature = "http")]
+pub use refund::{RefundOvercharge, RefundOverchargeFuture, RefundOverchargeLayer};
+#[cfg(feature = "http")]
+pub use report::{ReportCycles, ReportCyclesFuture, ReportCyclesLayer};
+pub use subnet::SubnetSize;
 
+#[cfg(feature = "http")]
+use crate::{
+    convert::Convert,
+    http::{HttpRequest, HttpRequestConverter},
+};
 use crate::{
     convert::{ConvertRequestLayer, Filter},
     ConvertServiceBuilder,
@@ -89,9 +156,13 @@ impl CyclesChargingPolicy for ChargeMyself {
 }
 
 /// Cycles will be transferred from the caller of the canister using that library to pay for HTTPs outcalls.
+///
+/// The cycles attached by the caller are accessed through a [`CyclesEnvironment`], by default
+/// the [`IcCyclesEnvironment`], see [`ChargeCaller::with_environment`].
 #[derive(Clone)]
-pub struct ChargeCaller<F> {
+pub struct ChargeCaller<F, Environment = IcCyclesEnvironment> {
     cycles_to_charge: F,
+    environment: Environment,
 }
 
 impl<F> ChargeCaller<F>
@@ -100,13 +171,31 @@ where
 {
     /// Create a new instance of [`ChargeCaller`].
     pub fn new(cycles_to_charge: F) -> Self {
-        ChargeCaller { cycles_to_charge }
+        ChargeCaller {
+            cycles_to_charge,
+            environment: IcCyclesEnvironment,
+        }
     }
 }
 
-impl<F> CyclesChargingPolicy for ChargeCaller<F>
+impl<F, Environment> ChargeCaller<F, Environment> {
+    /// Access the cycles attached by the caller through the given [`CyclesEnvironment`],
+    /// e.g. a [`StubCyclesEnvironment`] in unit tests.
+    pub fn with_environment<NewEnvironment>(
+        self,
+        environment: NewEnvironment,
+    ) -> ChargeCaller<F, NewEnvironment> {
+        ChargeCaller {
+            cycles_to_charge: self.cycles_to_charge,
+            environment,
+        }
+    }
+}
+
+impl<F, Environment> CyclesChargingPolicy for ChargeCaller<F, Environment>
 where
     F: Fn(&HttpRequestArgs, u128) -> u128,
+    Environment: CyclesEnvironment,
 {
     type Error = ChargeCallerError;
 
@@ -119,23 +208,166 @@ where
         request: &HttpRequestArgs,
         request_cycles_cost: u128,
     ) -> Result<u128, Self::Error> {
-        let cycles_to_charge = self.cycles_to_charge(request, request_cycles_cost);
-        if cycles_to_charge > 0 {
-            let cycles_available = ic_cdk::api::msg_cycles_available();
-            if cycles_available < cycles_to_charge {
-                return Err(ChargeCallerError::InsufficientCyclesError {
-                    expected: cycles_to_charge,
-                    received: cycles_available,
-                });
-            }
-            let cycles_received = ic_cdk::api::msg_cycles_accept(cycles_to_charge);
-            assert_eq!(
-                cycles_received, cycles_to_charge,
-                "Expected to receive {cycles_to_charge}, but got {cycles_received}"
-            );
+        accept_cycles_from_caller(
+            &self.environment,
+            self.cycles_to_charge(request, request_cycles_cost),
+        )
+    }
+}
+
+impl ChargeCaller<Markup> {
+    /// Create a new instance of [`ChargeCaller`] charging the cost of the HTTPs outcall plus
+    /// the given percentage of it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::cycles::{ChargeCaller, CyclesChargingPolicy};
+    /// use ic_cdk_management_canister::HttpRequestArgs;
+    ///
+    /// let policy = ChargeCaller::with_markup_percent(10);
+    ///
+    /// assert_eq!(policy.cycles_to_charge(&HttpRequestArgs::default(), 1_000_000), 1_100_000);
+    /// ```
+    pub fn with_markup_percent(percent: u32) -> Self {
+        Self::with_markup(Markup {
+            percent,
+            ..Markup::default()
+        })
+    }
+
+    /// Create a new instance of [`ChargeCaller`] charging the cost of the HTTPs outcall plus
+    /// the given markup.
+    pub fn with_markup(markup: Markup) -> Self {
+        ChargeCaller {
+            cycles_to_charge: markup,
+            environment: IcCyclesEnvironment,
+        }
+    }
+}
+
+impl<Environment: CyclesEnvironment> CyclesChargingPolicy for ChargeCaller<Markup, Environment> {
+    type Error = ChargeCallerError;
+
+    fn cycles_to_charge(&self, _request: &HttpRequestArgs, request_cycles_cost: u128) -> u128 {
+        self.cycles_to_charge.apply(request_cycles_cost)
+    }
+
+    fn charge_cycles(
+        &self,
+        request: &HttpRequestArgs,
+        request_cycles_cost: u128,
+    ) -> Result<u128, Self::Error> {
+        accept_cycles_from_caller(
+            &self.environment,
+            self.cycles_to_charge(request, request_cycles_cost),
+        )
+    }
+}
+
+/// The cost of HTTPs outcalls is split between the caller, who pays the given percentage of
+/// it, and the canister, which pays the remainder with its own cycles, e.g. for subsidized APIs.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{cycles::{CyclesAccountingServiceBuilder, SplitCharge}, Client};
+/// use tower::ServiceBuilder;
+///
+/// // The caller pays for 25% of the cost, and the canister for the remaining 75%.
+/// let service = ServiceBuilder::new()
+///   .cycles_accounting(SplitCharge::new(25))
+///   .service(Client::new_with_box_error());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SplitCharge<Environment = IcCyclesEnvironment> {
+    caller_percent: u32,
+    environment: Environment,
+}
+
+impl SplitCharge {
+    /// Create a new instance of [`SplitCharge`] where the caller pays the given percentage
+    /// of the cost, which is capped at 100.
+    pub fn new(caller_percent: u32) -> Self {
+        Self {
+            caller_percent: caller_percent.min(100),
+            environment: IcCyclesEnvironment,
+        }
+    }
+}
+
+impl<Environment> SplitCharge<Environment> {
+    /// Access the cycles attached by the caller through the given [`CyclesEnvironment`],
+    /// see [`ChargeCaller::with_environment`].
+    pub fn with_environment<NewEnvironment>(
+        self,
+        environment: NewEnvironment,
+    ) -> SplitCharge<NewEnvironment> {
+        SplitCharge {
+            caller_percent: self.caller_percent,
+            environment,
+        }
+    }
+}
+
+impl<Environment: CyclesEnvironment> CyclesChargingPolicy for SplitCharge<Environment> {
+    type Error = ChargeCallerError;
+
+    fn cycles_to_charge(&self, _request: &HttpRequestArgs, request_cycles_cost: u128) -> u128 {
+        request_cycles_cost
+            .saturating_mul(self.caller_percent as u128)
+            .div_ceil(100)
+    }
+
+    fn charge_cycles(
+        &self,
+        request: &HttpRequestArgs,
+        request_cycles_cost: u128,
+    ) -> Result<u128, Self::Error> {
+        accept_cycles_from_caller(
+            &self.environment,
+            self.cycles_to_charge(request, request_cycles_cost),
+        )
+    }
+}
+
+/// Markup added to the cost of HTTPs outcalls charged to the caller,
+/// see [`ChargeCaller::with_markup`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Markup {
+    /// Percentage of the cost added to the cost, rounded up.
+    pub percent: u32,
+    /// Fixed number of cycles added to the cost.
+    pub flat: u128,
+}
+
+impl Markup {
+    /// Returns the given cost with the markup added.
+    pub fn apply(&self, cost: u128) -> u128 {
+        let percentage = cost.saturating_mul(self.percent as u128).div_ceil(100);
+        cost.saturating_add(percentage).saturating_add(self.flat)
+    }
+}
+
+fn accept_cycles_from_caller<Environment: CyclesEnvironment>(
+    environment: &Environment,
+    cycles_to_charge: u128,
+) -> Result<u128, ChargeCallerError> {
+    if cycles_to_charge > 0 {
+        let cycles_available = environment.cycles_available();
+        if cycles_available < cycles_to_charge {
+            return Err(ChargeCallerError::InsufficientCyclesError {
+                expected: cycles_to_charge,
+                received: cycles_available,
+            });
         }
-        Ok(cycles_to_charge)
+        let cycles_received = environment.accept_cycles(cycles_to_charge);
+        assert_eq!(
+            cycles_received, cycles_to_charge,
+            "Expected to receive {cycles_to_charge}, but got {cycles_received}"
+        );
     }
+    Ok(cycles_to_charge)
 }
 
 /// Error returned by the [`CyclesAccounting`] middleware.
@@ -151,34 +383,328 @@ pub enum ChargeCallerError {
     },
 }
 
+/// Cycles accounting of a single HTTPs outcall, reported by [`CyclesAccounting`] to a
+/// [`CyclesObserver`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CyclesEvent {
+    /// Estimated cost of the HTTPs outcall in cycles, as computed by
+    /// [`ic_cdk_management_canister::cost_http_request`].
+    pub estimated_cost: u128,
+    /// Cycles attached to the HTTPs outcall, which is `0` if charging failed, since the request
+    /// is then not sent.
+    pub attached: u128,
+    /// Outcome of the charging policy, i.e. the number of cycles charged, or `None` if
+    /// charging failed.
+    pub charged: Option<u128>,
+}
+
+impl CyclesEvent {
+    /// Event for a request with the given cost, where no cycles are attached if charging failed.
+    pub(super) fn new(estimated_cost: u128, charged: Option<u128>) -> Self {
+        Self {
+            estimated_cost,
+            attached: if charged.is_some() { estimated_cost } else { 0 },
+            charged,
+        }
+    }
+}
+
+/// Trait used to tell [`CyclesAccounting`] what to do with the [`CyclesEvent`] of each request,
+/// e.g. to track how many cycles each endpoint burns over time.
+///
+/// See [`MetricsLayer::record_cycles`](crate::observability::MetricsLayer::record_cycles)
+/// to aggregate the events into metrics.
+pub trait CyclesObserver {
+    /// Observe the cycles accounting of the given request.
+    fn observe_cycles(&self, request: &HttpRequestArgs, event: &CyclesEvent);
+}
+
+impl CyclesObserver for () {
+    fn observe_cycles(&self, _request: &HttpRequestArgs, _event: &CyclesEvent) {
+        //NOP
+    }
+}
+
+impl<F> CyclesObserver for F
+where
+    F: Fn(&HttpRequestArgs, &CyclesEvent),
+{
+    fn observe_cycles(&self, request: &HttpRequestArgs, event: &CyclesEvent) {
+        self(request, event)
+    }
+}
+
 /// A middleware to handle cycles accounting, i.e. verify if sufficiently many cycles are available in a request.
-/// The cost of sending the request is calculated by [`ic_cdk_management_canister::cost_http_request`].
+/// The cost of sending the request is calculated by [`ic_cdk_management_canister::cost_http_request`],
+/// unless a [`CyclesCostEstimator`] is given with [`CyclesAccounting::with_cost_estimator`].
 #[derive(Clone, Debug)]
-pub struct CyclesAccounting<ChargingPolicy> {
+pub struct CyclesAccounting<ChargingPolicy, Observer = ()> {
     charging_policy: ChargingPolicy,
+    observer: Observer,
+    cost_estimator: Option<CyclesCostEstimator>,
 }
 
 impl<ChargingPolicy> CyclesAccounting<ChargingPolicy> {
     /// Create a new middleware given the charging policy.
     pub fn new(charging_policy: ChargingPolicy) -> Self {
-        Self { charging_policy }
+        Self {
+            charging_policy,
+            observer: (),
+            cost_estimator: None,
+        }
+    }
+}
+
+impl<ChargingPolicy, Observer> CyclesAccounting<ChargingPolicy, Observer> {
+    /// Compute the cost of each request with the given [`CyclesCostEstimator`] instead of
+    /// [`ic_cdk_management_canister::cost_http_request`], e.g. to test the cycles accounting
+    /// off-chain.
+    ///
+    /// Note that the number of cycles attached to the HTTPs outcall by the [`Client`] is
+    /// always computed by the Internet Computer.
+    ///
+    /// [`Client`]: crate::Client
+    pub fn with_cost_estimator(mut self, cost_estimator: CyclesCostEstimator) -> Self {
+        self.cost_estimator = Some(cost_estimator);
+        self
+    }
+
+    /// Report a [`CyclesEvent`] to the given [`CyclesObserver`] for each request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::{
+    ///     convert::ConvertServiceBuilder,
+    ///     cycles::{ChargeMyself, CyclesAccounting},
+    ///     observability::{Metrics, MetricsLayer},
+    ///     Client,
+    /// };
+    /// use tower::ServiceBuilder;
+    ///
+    /// let metrics = MetricsLayer::new(Metrics::new());
+    /// let service = ServiceBuilder::new()
+    ///     .convert_request(CyclesAccounting::new(ChargeMyself::default()).on_cycles(metrics.record_cycles()))
+    ///     .layer(metrics)
+    ///     .service(Client::new_with_box_error());
+    /// ```
+    pub fn on_cycles<NewObserver>(
+        self,
+        observer: NewObserver,
+    ) -> CyclesAccounting<ChargingPolicy, NewObserver> {
+        CyclesAccounting {
+            charging_policy: self.charging_policy,
+            observer,
+            cost_estimator: self.cost_estimator,
+        }
+    }
+}
+
+impl<ChargingPolicy, Observer> CyclesAccounting<ChargingPolicy, Observer>
+where
+    ChargingPolicy: CyclesChargingPolicy,
+    Observer: CyclesObserver,
+{
+    /// Charge the cycles for the given request and return its cost.
+    fn account(&self, request: &HttpRequestArgs) -> Result<u128, ChargingPolicy::Error> {
+        let cycles_to_attach = match &self.cost_estimator {
+            Some(estimator) => estimator.cost_of_http_request(request),
+            None => ic_cdk_management_canister::cost_http_request(request),
+        };
+        let charged = self
+            .charging_policy
+            .charge_cycles(request, cycles_to_attach);
+        self.observer.observe_cycles(
+            request,
+            &CyclesEvent::new(cycles_to_attach, charged.as_ref().ok().copied()),
+        );
+        charged.map(|_| cycles_to_attach)
     }
 }
 
-impl<ChargingPolicy> Filter<HttpRequestArgs> for CyclesAccounting<ChargingPolicy>
+impl<ChargingPolicy, Observer> Filter<HttpRequestArgs>
+    for CyclesAccounting<ChargingPolicy, Observer>
 where
     ChargingPolicy: CyclesChargingPolicy,
+    Observer: CyclesObserver,
 {
     type Error = ChargingPolicy::Error;
 
     fn filter(&mut self, request: HttpRequestArgs) -> Result<HttpRequestArgs, Self::Error> {
-        let cycles_to_attach = ic_cdk_management_canister::cost_http_request(&request);
-        self.charging_policy
-            .charge_cycles(&request, cycles_to_attach)?;
+        self.account(&request)?;
         Ok(request)
     }
 }
 
+/// Cycles accounting of [`HttpRequest`]s, which adds the cost of each request to its
+/// extensions, so that the layers between [`CyclesAccounting`] and the [`HttpRequestConverter`]
+/// can read it, see [`CyclesCostRequestExtension`].
+///
+/// The cost is computed on the request as it reaches [`CyclesAccounting`]. Layers between
+/// [`CyclesAccounting`] and the [`HttpRequestConverter`] that change the request, e.g. by adding
+/// an `Authorization` header or signing the body, also change the cost of the HTTPs outcall,
+/// which is then no longer the one that was charged. [`CyclesAccounting`] must therefore be the
+/// innermost layer modifying the request, or be applied to [`HttpRequestArgs`] after the
+/// conversion to charge exactly the cost of the HTTPs outcall.
+///
+/// Requests that cannot be converted into [`HttpRequestArgs`] are left untouched and are not
+/// charged, since they will be rejected by the [`HttpRequestConverter`] anyway.
+#[cfg(feature = "http")]
+impl<ChargingPolicy, Observer> Filter<HttpRequest> for CyclesAccounting<ChargingPolicy, Observer>
+where
+    ChargingPolicy: CyclesChargingPolicy,
+    Observer: CyclesObserver,
+{
+    type Error = ChargingPolicy::Error;
+
+    fn filter(&mut self, request: HttpRequest) -> Result<HttpRequest, Self::Error> {
+        let (parts, body) = request.into_parts();
+        // Convert the request without its body to avoid copying it.
+        let mut ic_request = match HttpRequestConverter
+            .try_convert(http::Request::from_parts(parts.clone(), Vec::new()))
+        {
+            Ok(ic_request) => ic_request,
+            Err(_) => return Ok(http::Request::from_parts(parts, body)),
+        };
+        ic_request.body = Some(body);
+        let cost = self.account(&ic_request)?;
+        let body = ic_request.body.take().unwrap_or_default();
+        Ok(http::Request::from_parts(parts, body).cycles_cost(cost))
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CyclesCostExtension(u128);
+
+/// Add support for the cost in cycles of a request, as computed by [`CyclesAccounting`].
+pub trait CyclesCostRequestExtension: Sized {
+    /// Set the cost in cycles of the request.
+    fn set_cycles_cost(&mut self, value: u128);
+
+    /// Retrieves the cost in cycles of the request, if any.
+    fn get_cycles_cost(&self) -> Option<u128>;
+
+    /// Convenience method to use the builder pattern.
+    fn cycles_cost(mut self, value: u128) -> Self {
+        self.set_cycles_cost(value);
+        self
+    }
+}
+
+#[cfg(feature = "http")]
+impl<T> CyclesCostRequestExtension for http::Request<T> {
+    fn set_cycles_cost(&mut self, value: u128) {
+        let extensions = self.extensions_mut();
+        extensions.insert(CyclesCostExtension(value));
+    }
+
+    fn get_cycles_cost(&self) -> Option<u128> {
+        self.extensions().get::<CyclesCostExtension>().map(|e| e.0)
+    }
+}
+
+#[cfg(feature = "http")]
+impl CyclesCostRequestExtension for http::request::Builder {
+    fn set_cycles_cost(&mut self, value: u128) {
+        if let Some(extensions) = self.extensions_mut() {
+            extensions.insert(CyclesCostExtension(value));
+        }
+    }
+
+    fn get_cycles_cost(&self) -> Option<u128> {
+        self.extensions_ref()
+            .and_then(|extensions| extensions.get::<CyclesCostExtension>().map(|e| e.0))
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CyclesAttachedExtension(u128);
+
+#[cfg(feature = "http")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CyclesRefundedExtension(u128);
+
+/// Add support for the cycles spent on the HTTPs outcall that produced a response, as reported
+/// by [`ReportCycles`] or [`RefundOvercharge`].
+///
+/// The cycles actually spent on the HTTPs outcall are the attached cycles minus the refunded ones.
+#[cfg(feature = "http")]
+pub trait CyclesResponseExtension: Sized {
+    /// Set the cycles attached to the HTTPs outcall.
+    fn set_cycles_attached(&mut self, value: u128);
+
+    /// Retrieves the cycles attached to the HTTPs outcall, if any.
+    fn get_cycles_attached(&self) -> Option<u128>;
+
+    /// Convenience method to use the builder pattern.
+    fn cycles_attached(mut self, value: u128) -> Self {
+        self.set_cycles_attached(value);
+        self
+    }
+
+    /// Set the cycles refunded to the canister for the HTTPs outcall.
+    fn set_cycles_refunded(&mut self, value: u128);
+
+    /// Retrieves the cycles refunded to the canister for the HTTPs outcall, if any.
+    fn get_cycles_refunded(&self) -> Option<u128>;
+
+    /// Convenience method to use the builder pattern.
+    fn cycles_refunded(mut self, value: u128) -> Self {
+        self.set_cycles_refunded(value);
+        self
+    }
+}
+
+#[cfg(feature = "http")]
+impl<T> CyclesResponseExtension for http::Response<T> {
+    fn set_cycles_attached(&mut self, value: u128) {
+        self.extensions_mut().insert(CyclesAttachedExtension(value));
+    }
+
+    fn get_cycles_attached(&self) -> Option<u128> {
+        self.extensions()
+            .get::<CyclesAttachedExtension>()
+            .map(|e| e.0)
+    }
+
+    fn set_cycles_refunded(&mut self, value: u128) {
+        self.extensions_mut().insert(CyclesRefundedExtension(value));
+    }
+
+    fn get_cycles_refunded(&self) -> Option<u128> {
+        self.extensions()
+            .get::<CyclesRefundedExtension>()
+            .map(|e| e.0)
+    }
+}
+
+#[cfg(feature = "http")]
+impl CyclesResponseExtension for http::response::Builder {
+    fn set_cycles_attached(&mut self, value: u128) {
+        if let Some(extensions) = self.extensions_mut() {
+            extensions.insert(CyclesAttachedExtension(value));
+        }
+    }
+
+    fn get_cycles_attached(&self) -> Option<u128> {
+        self.extensions_ref()
+            .and_then(|extensions| extensions.get::<CyclesAttachedExtension>().map(|e| e.0))
+    }
+
+    fn set_cycles_refunded(&mut self, value: u128) {
+        if let Some(extensions) = self.extensions_mut() {
+            extensions.insert(CyclesRefundedExtension(value));
+        }
+    }
+
+    fn get_cycles_refunded(&self) -> Option<u128> {
+        self.extensions_ref()
+            .and_then(|extensions| extensions.get::<CyclesRefundedExtension>().map(|e| e.0))
+    }
+}
+
 /// Extension trait that adds methods to [`tower::ServiceBuilder`] for adding middleware
 /// related to cycles accounting
 pub trait CyclesAccountingServiceBuilder<L> {
@@ -189,6 +715,15 @@ pub trait CyclesAccountingServiceBuilder<L> {
         self,
         charging: C,
     ) -> ServiceBuilder<Stack<ConvertRequestLayer<CyclesAccounting<C>>, L>>;
+
+    /// Add cycles accounting with an [`AsyncCyclesChargingPolicy`].
+    ///
+    /// See [`AsyncCyclesAccountingLayer`] for an example.
+    #[cfg(feature = "http")]
+    fn async_cycles_accounting<C>(
+        self,
+        charging: C,
+    ) -> ServiceBuilder<Stack<AsyncCyclesAccountingLayer<C>, L>>;
 }
 
 impl<L> CyclesAccountingServiceBuilder<L> for ServiceBuilder<L> {
@@ -198,4 +733,12 @@ impl<L> CyclesAccountingServiceBuilder<L> for ServiceBuilder<L> {
     ) -> ServiceBuilder<Stack<ConvertRequestLayer<CyclesAccounting<C>>, L>> {
         self.convert_request(CyclesAccounting::new(charging))
     }
+
+    #[cfg(feature = "http")]
+    fn async_cycles_accounting<C>(
+        self,
+        charging: C,
+    ) -> ServiceBuilder<Stack<AsyncCyclesAccountingLayer<C>, L>> {
+        self.layer(AsyncCyclesAccountingLayer::new(charging))
+    }
 }
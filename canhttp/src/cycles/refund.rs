@@ -0,0 +1,191 @@
+use crate::{
+    convert::Convert,
+    cycles::{ChargeCallerError, CyclesChargingPolicy, CyclesResponseExtension},
+    http::{HttpRequest, HttpRequestConverter, HttpResponse},
+};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::Service;
+use tower_layer::Layer;
+
+/// Middleware that charges the caller of the canister like [`CyclesAccounting`], but only for
+/// the cycles actually consumed by HTTPs outcalls, by refunding to the caller the cycles
+/// that were refunded to the canister, e.g. because the response was far below
+/// `max_response_bytes`.
+///
+/// To that end, the cycles to charge are only *reserved* when the request is sent, i.e. the
+/// request fails with [`ChargeCallerError::InsufficientCyclesError`] if the caller did not
+/// attach sufficiently many cycles, but they are only accepted once the response is received.
+/// The caller is then charged the cycles given by the charging policy minus the cycles refunded
+/// to the canister, and the remaining attached cycles are returned to the caller when the canister
+/// replies. The cycles attached to the HTTPs outcall and refunded to the canister are added to
+/// the extensions of the response, see [`CyclesResponseExtension`].
+///
+/// The refunded cycles are read with [`ic_cdk::api::msg_cycles_refunded`] once the inner service
+/// completes, so no other inter-canister call must be made in between, e.g. this layer must be
+/// placed *inside* any retry layer.
+///
+/// This [`Layer`] produces instances of the [`RefundOvercharge`] service.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{cycles::{ChargeCaller, RefundOverchargeLayer}, http::HttpConversionLayer, Client};
+/// use tower::ServiceBuilder;
+///
+/// let service = ServiceBuilder::new()
+///   .layer(RefundOverchargeLayer::new(ChargeCaller::with_markup_percent(10)))
+///   .layer(HttpConversionLayer)
+///   .service(Client::new_with_box_error());
+/// ```
+///
+/// [`CyclesAccounting`]: crate::cycles::CyclesAccounting
+/// [`Layer`]: tower::Layer
+#[derive(Clone, Debug)]
+pub struct RefundOverchargeLayer<ChargingPolicy> {
+    charging_policy: ChargingPolicy,
+}
+
+impl<ChargingPolicy> RefundOverchargeLayer<ChargingPolicy> {
+    /// Returns a new [`RefundOverchargeLayer`] charging the caller with the given policy.
+    pub fn new(charging_policy: ChargingPolicy) -> Self {
+        Self { charging_policy }
+    }
+}
+
+impl<S, ChargingPolicy: Clone> Layer<S> for RefundOverchargeLayer<ChargingPolicy> {
+    type Service = RefundOvercharge<S, ChargingPolicy>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RefundOvercharge {
+            inner,
+            charging_policy: self.charging_policy.clone(),
+        }
+    }
+}
+
+/// Charge the caller of the canister for the cycles actually consumed by HTTPs outcalls,
+/// see [`RefundOverchargeLayer`].
+#[derive(Clone, Debug)]
+pub struct RefundOvercharge<S, ChargingPolicy> {
+    inner: S,
+    charging_policy: ChargingPolicy,
+}
+
+impl<S, ChargingPolicy, Error> Service<HttpRequest> for RefundOvercharge<S, ChargingPolicy>
+where
+    S: Service<HttpRequest, Response = HttpResponse, Error = Error>,
+    ChargingPolicy: CyclesChargingPolicy<Error = ChargeCallerError>,
+    ChargeCallerError: Into<Error>,
+{
+    type Response = HttpResponse;
+    type Error = Error;
+    type Future = RefundOverchargeFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        let (parts, body) = request.into_parts();
+        // Convert the request without its body to avoid copying it.
+        let (request, cycles_attached, cycles_to_charge) = match HttpRequestConverter
+            .try_convert(http::Request::from_parts(parts.clone(), Vec::new()))
+        {
+            Ok(mut ic_request) => {
+                ic_request.body = Some(body);
+                let cost = ic_cdk_management_canister::cost_http_request(&ic_request);
+                let cycles_to_charge = self.charging_policy.cycles_to_charge(&ic_request, cost);
+                if let Err(e) = reserve_cycles_from_caller(cycles_to_charge) {
+                    return RefundOverchargeFuture::Rejected(Some(Err(e.into())));
+                }
+                let body = ic_request.body.take().unwrap_or_default();
+                (
+                    http::Request::from_parts(parts, body),
+                    Some(cost),
+                    cycles_to_charge,
+                )
+            }
+            // Requests that cannot be converted are not charged, since they will be rejected
+            // by the `HttpRequestConverter` anyway.
+            Err(_) => (http::Request::from_parts(parts, body), None, 0),
+        };
+        RefundOverchargeFuture::Charging {
+            future: self.inner.call(request),
+            cycles_attached,
+            cycles_to_charge,
+        }
+    }
+}
+
+/// Response future of the [`RefundOvercharge`] service.
+#[pin_project(project = RefundOverchargeFutureProj)]
+pub enum RefundOverchargeFuture<F: Future> {
+    /// The caller did not attach sufficiently many cycles.
+    Rejected(Option<F::Output>),
+    /// The request was forwarded to the inner service.
+    Charging {
+        /// Response future of the inner service.
+        #[pin]
+        future: F,
+        /// Cycles attached to the HTTPs outcall, if the request is valid.
+        cycles_attached: Option<u128>,
+        /// Cycles to charge to the caller before refunds.
+        cycles_to_charge: u128,
+    },
+}
+
+impl<F, E> Future for RefundOverchargeFuture<F>
+where
+    F: Future<Output = Result<HttpResponse, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            RefundOverchargeFutureProj::Rejected(error) => {
+                Poll::Ready(error.take().expect("BUG: future polled after completion"))
+            }
+            RefundOverchargeFutureProj::Charging {
+                future,
+                cycles_attached,
+                cycles_to_charge,
+            } => {
+                let mut result = std::task::ready!(future.poll(cx));
+                if let Some(attached) = *cycles_attached {
+                    let refunded = ic_cdk::api::msg_cycles_refunded();
+                    let cycles_to_accept = *cycles_to_charge - refunded.min(*cycles_to_charge);
+                    if cycles_to_accept > 0 {
+                        let accepted = ic_cdk::api::msg_cycles_accept(cycles_to_accept);
+                        assert_eq!(
+                            accepted, cycles_to_accept,
+                            "Expected to accept {cycles_to_accept} cycles, but got {accepted}"
+                        );
+                    }
+                    if let Ok(response) = result.as_mut() {
+                        response.set_cycles_attached(attached);
+                        response.set_cycles_refunded(refunded);
+                    }
+                }
+                Poll::Ready(result)
+            }
+        }
+    }
+}
+
+fn reserve_cycles_from_caller(cycles_to_charge: u128) -> Result<(), ChargeCallerError> {
+    if cycles_to_charge > 0 {
+        let cycles_available = ic_cdk::api::msg_cycles_available();
+        if cycles_available < cycles_to_charge {
+            return Err(ChargeCallerError::InsufficientCyclesError {
+                expected: cycles_to_charge,
+                received: cycles_available,
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use crate::{
+    convert::Convert,
+    cycles::{
+        CyclesCostRequestExtension, CyclesEnvironment, CyclesResponseExtension, IcCyclesEnvironment,
+    },
+    http::{HttpRequest, HttpRequestConverter, HttpResponse},
+};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::Service;
+use tower_layer::Layer;
+
+/// Middleware that adds the cycles attached to each HTTPs outcall, and refunded to the canister
+/// for it, to the extensions of the response, see [`CyclesResponseExtension`].
+///
+/// The attached cycles are the cost of the request, which is read from the request extensions
+/// if set by [`CyclesAccounting`] (see [`CyclesCostRequestExtension`]), or computed otherwise.
+/// The refunded cycles are read from the [`CyclesEnvironment`] once the inner service completes,
+/// so no other inter-canister call must be made in between, e.g. this layer must be placed
+/// *inside* any retry layer.
+///
+/// This [`Layer`] produces instances of the [`ReportCycles`] service.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{cycles::{CyclesResponseExtension, ReportCyclesLayer}, http::HttpConversionLayer, Client};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// async fn spent_cycles() -> Result<u128, BoxError> {
+///     let mut service = ServiceBuilder::new()
+///         .layer(ReportCyclesLayer::new())
+///         .layer(HttpConversionLayer)
+///         .service(Client::new_with_box_error());
+///
+///     let request = http::Request::get("https://example.com").body(vec![])?;
+///     let response = service.ready().await?.call(request).await?;
+///     Ok(response.get_cycles_attached().unwrap_or_default()
+///         - response.get_cycles_refunded().unwrap_or_default())
+/// }
+/// ```
+///
+/// [`CyclesAccounting`]: crate::cycles::CyclesAccounting
+/// [`Layer`]: tower::Layer
+#[derive(Clone, Debug, Default)]
+pub struct ReportCyclesLayer<Environment = IcCyclesEnvironment> {
+    environment: Environment,
+}
+
+impl ReportCyclesLayer {
+    /// Returns a new [`ReportCyclesLayer`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<Environment> ReportCyclesLayer<Environment> {
+    /// Read the refunded cycles from the given [`CyclesEnvironment`],
+    /// e.g. a [`StubCyclesEnvironment`](crate::cycles::StubCyclesEnvironment) in unit tests.
+    pub fn with_environment<NewEnvironment>(
+        self,
+        environment: NewEnvironment,
+    ) -> ReportCyclesLayer<NewEnvironment> {
+        ReportCyclesLayer { environment }
+    }
+}
+
+impl<S, Environment: Clone> Layer<S> for ReportCyclesLayer<Environment> {
+    type Service = ReportCycles<S, Environment>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ReportCycles {
+            inner,
+            environment: self.environment.clone(),
+        }
+    }
+}
+
+/// Add the cycles attached to each HTTPs outcall, and refunded for it, to the extensions of
+/// the response, see [`ReportCyclesLayer`].
+#[derive(Clone, Debug)]
+pub struct ReportCycles<S, Environment> {
+    inner: S,
+    environment: Environment,
+}
+
+impl<S, Environment> Service<HttpRequest> for ReportCycles<S, Environment>
+where
+    S: Service<HttpRequest, Response = HttpResponse>,
+    Environment: CyclesEnvironment + Clone,
+{
+    type Response = HttpResponse;
+    type Error = S::Error;
+    type Future = ReportCyclesFuture<S::Future, Environment>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest) -> Self::Future {
+        let cycles_attached = request.get_cycles_cost().or_else(|| {
+            // Requests that cannot be converted will be rejected by the `HttpRequestConverter`,
+            // and hence do not have any cycles attached.
+            HttpRequestConverter
+                .try_convert(request.clone())
+                .ok()
+                .map(|ic_request| ic_cdk_management_canister::cost_http_request(&ic_request))
+        });
+        ReportCyclesFuture {
+            future: self.inner.call(request),
+            cycles_attached,
+            environment: self.environment.clone(),
+        }
+    }
+}
+
+/// Response future of the [`ReportCycles`] service.
+#[pin_project]
+pub struct ReportCyclesFuture<F, Environment> {
+    #[pin]
+    future: F,
+    cycles_attached: Option<u128>,
+    environment: Environment,
+}
+
+impl<F, E, Environment> Future for ReportCyclesFuture<F, Environment>
+where
+    F: Future<Output = Result<HttpResponse, E>>,
+    Environment: CyclesEnvironment,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut result = std::task::ready!(this.future.poll(cx));
+        if let (Ok(response), Some(attached)) = (result.as_mut(), *this.cycles_attached) {
+            response.set_cycles_attached(attached);
+            response.set_cycles_refunded(this.environment.cycles_refunded());
+        }
+        Poll::Ready(result)
+    }
+}
@@ -0,0 +1,100 @@
+use crate::cycles::{CyclesCostEstimator, PricingTable};
+use ic_cdk_management_canister::HttpRequestArgs;
+use std::{cell::Cell, fmt, rc::Rc};
+
+/// Number of nodes in the subnet of the canister, needed by [`CyclesCostEstimator`].
+///
+/// The number of nodes is obtained from a provider the first time it is needed and then cached,
+/// so that estimates stay correctly sized without hard-coding the subnet of the canister.
+/// All clones share the same cache.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::cycles::SubnetSize;
+/// use ic_cdk_management_canister::HttpRequestArgs as IcHttpRequest;
+///
+/// // Discover the number of nodes from the cost of HTTPs outcalls on the current subnet.
+/// let subnet_size = SubnetSize::discover();
+/// # let subnet_size = SubnetSize::with_provider(|| Some(SubnetSize::LARGE));
+///
+/// let estimator = subnet_size.estimator().unwrap();
+/// assert_eq!(estimator.num_nodes_in_subnet(), 34);
+/// ```
+#[derive(Clone)]
+pub struct SubnetSize {
+    provider: Rc<dyn Fn() -> Option<u32>>,
+    cache: Rc<Cell<Option<u32>>>,
+}
+
+impl SubnetSize {
+    /// Number of nodes in standard application subnets.
+    pub const SMALL: u32 = 13;
+    /// Number of nodes in subnets with 28 nodes.
+    pub const MEDIUM: u32 = 28;
+    /// Number of nodes in large subnets, such as the fiduciary subnet.
+    pub const LARGE: u32 = 34;
+
+    /// Discover the number of nodes in the subnet from the cost of an empty HTTPs outcall
+    /// computed by the Internet Computer, see [`ic_cdk_management_canister::cost_http_request`].
+    ///
+    /// This assumes that the current subnet uses the [`PricingTable::MAINNET`] pricing.
+    pub fn discover() -> Self {
+        Self::with_provider(|| {
+            let request = HttpRequestArgs {
+                max_response_bytes: Some(0),
+                ..Default::default()
+            };
+            num_nodes_from_base_fee(
+                ic_cdk_management_canister::cost_http_request(&request),
+                &PricingTable::MAINNET,
+            )
+        })
+    }
+
+    /// Use a fixed number of nodes, e.g. [`SubnetSize::SMALL`].
+    pub fn fixed(num_nodes_in_subnet: u32) -> Self {
+        Self::with_provider(move || Some(num_nodes_in_subnet))
+    }
+
+    /// Obtain the number of nodes from the given provider, e.g. by querying it from another
+    /// canister beforehand. Only successful results (i.e. `Some`) are cached.
+    pub fn with_provider(provider: impl Fn() -> Option<u32> + 'static) -> Self {
+        Self {
+            provider: Rc::new(provider),
+            cache: Rc::new(Cell::new(None)),
+        }
+    }
+
+    /// Returns the number of nodes in the subnet, if known.
+    pub fn get(&self) -> Option<u32> {
+        if let Some(num_nodes) = self.cache.get() {
+            return Some(num_nodes);
+        }
+        let num_nodes = (self.provider)()?;
+        self.cache.set(Some(num_nodes));
+        Some(num_nodes)
+    }
+
+    /// Returns a [`CyclesCostEstimator`] for the subnet, if its number of nodes is known.
+    pub fn estimator(&self) -> Option<CyclesCostEstimator> {
+        self.get().map(CyclesCostEstimator::new)
+    }
+}
+
+impl fmt::Debug for SubnetSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubnetSize")
+            .field("cache", &self.cache.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the number of nodes `n` such that the base fee for a subnet of `n` nodes is
+/// the given cost, if any.
+pub(super) fn num_nodes_from_base_fee(cost: u128, pricing: &PricingTable) -> Option<u32> {
+    (1..=u8::MAX as u32).find(|n| {
+        let n = *n as u128;
+        (pricing.base_fee + pricing.base_fee_per_node * n) * n == cost
+    })
+}
@@ -0,0 +1,581 @@
+mod cycles_cost_estimator {
+    use crate::cycles::{CyclesCostEstimator, PricingTable};
+    use candid::Principal;
+    use ic_cdk_management_canister::{
+        HttpHeader as IcHttpHeader, HttpRequestArgs as IcHttpRequest, TransformContext,
+        TransformFunc,
+    };
+
+    #[test]
+    fn should_count_request_bytes() {
+        let request = IcHttpRequest {
+            url: "https://example.com".to_string(),
+            headers: vec![IcHttpHeader {
+                name: "Content-Type".to_string(),
+                value: "application/json".to_string(),
+            }],
+            body: Some(b"{\"jsonrpc\":\"2.0\"}".to_vec()),
+            transform: Some(TransformContext {
+                function: TransformFunc::new(
+                    Principal::management_canister(),
+                    "transform".to_string(),
+                ),
+                context: vec![1, 2, 3],
+            }),
+            ..Default::default()
+        };
+
+        let breakdown = CyclesCostEstimator::new(13).cost_breakdown(&request);
+
+        assert_eq!(breakdown.request_bytes, 19 + 12 + 16 + 17 + 9 + 3);
+        assert_eq!(
+            breakdown.max_response_bytes,
+            CyclesCostEstimator::DEFAULT_MAX_RESPONSE_BYTES
+        );
+    }
+
+    #[test]
+    fn should_scale_fees_with_subnet_size() {
+        let request = IcHttpRequest {
+            url: "https://example.com".to_string(),
+            max_response_bytes: Some(1_000),
+            ..Default::default()
+        };
+
+        let small = CyclesCostEstimator::new(13).cost_breakdown(&request);
+        let large = CyclesCostEstimator::new(34).cost_breakdown(&request);
+
+        assert_eq!(small.num_nodes_in_subnet, 13);
+        assert_eq!(large.base_fee, (3_000_000 + 60_000 * 34) * 34);
+        assert_eq!(large.request_fee, 400 * 34 * 19);
+        assert_eq!(large.response_fee, 800 * 34 * 1_000);
+        assert_eq!(
+            large.total(),
+            large.base_fee + large.request_fee + large.response_fee
+        );
+        assert!(large.total() > small.total());
+    }
+
+    #[test]
+    fn should_account_for_transform_result() {
+        let transform = TransformContext {
+            function: TransformFunc::new(Principal::management_canister(), "transform".to_string()),
+            context: vec![],
+        };
+        let request = IcHttpRequest {
+            url: "https://example.com".to_string(),
+            max_response_bytes: Some(1_000),
+            transform: Some(transform),
+            ..Default::default()
+        };
+        let estimator = CyclesCostEstimator::new(13);
+
+        let without_transform_result = estimator.cost_breakdown(&request);
+        assert_eq!(without_transform_result.transform_fee, 0);
+
+        let with_transform_result = estimator
+            .clone()
+            .transform_result_bytes(500)
+            .cost_breakdown(&request);
+        assert_eq!(with_transform_result.transform_result_bytes, 500);
+        assert_eq!(with_transform_result.transform_fee, 800 * 13 * 500);
+        assert_eq!(
+            with_transform_result.total(),
+            without_transform_result.total() + 800 * 13 * 500
+        );
+
+        let capped = estimator
+            .clone()
+            .transform_result_bytes(10_000)
+            .cost_breakdown(&request);
+        assert_eq!(capped.transform_result_bytes, 1_000);
+
+        let no_transform = estimator
+            .transform_result_bytes(500)
+            .cost_breakdown(&IcHttpRequest {
+                transform: None,
+                ..request
+            });
+        assert_eq!(no_transform.transform_fee, 0);
+    }
+
+    #[test]
+    fn should_use_custom_pricing() {
+        let request = IcHttpRequest {
+            url: "https://example.com".to_string(),
+            max_response_bytes: Some(1_000),
+            ..Default::default()
+        };
+        let pricing = PricingTable {
+            base_fee: 1_000_000,
+            base_fee_per_node: 0,
+            request_byte_fee: 100,
+            response_byte_fee: 200,
+        };
+
+        let breakdown = CyclesCostEstimator::new(13)
+            .pricing(pricing)
+            .cost_breakdown(&request);
+
+        assert_eq!(breakdown.base_fee, 13_000_000);
+        assert_eq!(breakdown.request_fee, 100 * 13 * 19);
+        assert_eq!(breakdown.response_fee, 200 * 13 * 1_000);
+        assert_eq!(PricingTable::default(), PricingTable::MAINNET);
+    }
+
+    #[test]
+    fn should_estimate_non_replicated_outcalls_like_replicated_ones() {
+        let request = IcHttpRequest {
+            url: "https://example.com".to_string(),
+            max_response_bytes: Some(1_000),
+            ..Default::default()
+        };
+        let estimator = CyclesCostEstimator::new(13);
+
+        let breakdown = estimator.cost_breakdown(&request);
+        for is_replicated in [Some(true), Some(false)] {
+            assert_eq!(
+                estimator.cost_breakdown(&IcHttpRequest {
+                    is_replicated,
+                    ..request.clone()
+                }),
+                breakdown
+            );
+        }
+    }
+}
+
+mod subnet_size {
+    use crate::cycles::{subnet::num_nodes_from_base_fee, PricingTable, SubnetSize};
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn should_find_num_nodes_from_base_fee() {
+        for num_nodes in [SubnetSize::SMALL, SubnetSize::MEDIUM, SubnetSize::LARGE] {
+            let n = num_nodes as u128;
+            let base_fee = (3_000_000 + 60_000 * n) * n;
+            assert_eq!(
+                num_nodes_from_base_fee(base_fee, &PricingTable::MAINNET),
+                Some(num_nodes)
+            );
+        }
+        assert_eq!(num_nodes_from_base_fee(42, &PricingTable::MAINNET), None);
+    }
+
+    #[test]
+    fn should_cache_num_nodes() {
+        let calls = Rc::new(Cell::new(0));
+        let subnet_size = SubnetSize::with_provider({
+            let calls = calls.clone();
+            move || {
+                calls.set(calls.get() + 1);
+                (calls.get() > 1).then_some(SubnetSize::MEDIUM)
+            }
+        });
+
+        assert_eq!(subnet_size.get(), None);
+        assert_eq!(subnet_size.clone().get(), Some(28));
+        assert_eq!(subnet_size.get(), Some(28));
+        assert_eq!(calls.get(), 2);
+        assert_eq!(
+            subnet_size.estimator().map(|e| e.num_nodes_in_subnet()),
+            Some(28)
+        );
+    }
+}
+
+mod charge_caller {
+    use crate::cycles::{
+        ChargeCaller, ChargeCallerError, CyclesChargingPolicy, CyclesEnvironment, SplitCharge,
+        StubCyclesEnvironment,
+    };
+    use ic_cdk_management_canister::HttpRequestArgs;
+
+    #[test]
+    fn should_accept_cycles_from_caller() {
+        let environment = StubCyclesEnvironment::new(10_000);
+        let policy =
+            ChargeCaller::new(|_request, cost| cost + 1_000).with_environment(environment.clone());
+
+        assert_eq!(
+            policy.charge_cycles(&HttpRequestArgs::default(), 2_000),
+            Ok(3_000)
+        );
+        assert_eq!(environment.accepted(), 3_000);
+        assert_eq!(environment.cycles_available(), 7_000);
+    }
+
+    #[test]
+    fn should_fail_when_caller_attached_insufficient_cycles() {
+        let environment = StubCyclesEnvironment::new(1_000);
+        let policy = ChargeCaller::with_markup_percent(10).with_environment(environment.clone());
+
+        assert_eq!(
+            policy.charge_cycles(&HttpRequestArgs::default(), 1_000),
+            Err(ChargeCallerError::InsufficientCyclesError {
+                expected: 1_100,
+                received: 1_000
+            })
+        );
+        assert_eq!(environment.accepted(), 0);
+        assert_eq!(environment.cycles_available(), 1_000);
+    }
+
+    #[test]
+    fn should_not_accept_cycles_when_nothing_to_charge() {
+        let environment = StubCyclesEnvironment::new(0);
+        let policy = SplitCharge::new(0).with_environment(environment.clone());
+
+        assert_eq!(
+            policy.charge_cycles(&HttpRequestArgs::default(), 1_000),
+            Ok(0)
+        );
+        assert_eq!(environment.accepted(), 0);
+    }
+
+    #[test]
+    fn should_accept_caller_share_of_cost() {
+        let environment = StubCyclesEnvironment::new(1_000);
+        let policy = SplitCharge::new(25).with_environment(environment.clone());
+
+        assert_eq!(
+            policy.charge_cycles(&HttpRequestArgs::default(), 1_000),
+            Ok(250)
+        );
+        assert_eq!(environment.accepted(), 250);
+        assert_eq!(environment.cycles_available(), 750);
+    }
+}
+
+#[cfg(feature = "http")]
+mod async_charging_policy {
+    use crate::cycles::{AsyncCyclesChargingPolicy, ChargeCaller, StubCyclesEnvironment};
+    use ic_cdk_management_canister::HttpRequestArgs;
+
+    #[tokio::test]
+    async fn should_charge_with_sync_policy() {
+        let environment = StubCyclesEnvironment::new(10_000);
+        let policy = ChargeCaller::with_markup_percent(50).with_environment(environment.clone());
+
+        let charged = policy
+            .charge_cycles_async(&HttpRequestArgs::default(), 2_000)
+            .await;
+
+        assert_eq!(charged, Ok(3_000));
+        assert_eq!(environment.accepted(), 3_000);
+    }
+}
+
+#[cfg(feature = "http")]
+mod async_cycles_accounting {
+    use crate::cycles::{
+        AsyncCyclesAccountingLayer, ChargeMyself, CyclesCostEstimator, CyclesEvent,
+    };
+    use ic_cdk_management_canister::{HttpRequestArgs, HttpRequestResult};
+    use std::{cell::RefCell, rc::Rc};
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    #[tokio::test]
+    async fn should_compute_cost_with_estimator() {
+        let estimator = CyclesCostEstimator::new(13);
+        let request = HttpRequestArgs {
+            url: "https://example.com".to_string(),
+            max_response_bytes: Some(1_000),
+            ..Default::default()
+        };
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut service = ServiceBuilder::new()
+            .layer(
+                AsyncCyclesAccountingLayer::new(ChargeMyself::default())
+                    .with_cost_estimator(estimator.clone())
+                    .on_cycles({
+                        let events = events.clone();
+                        move |_request: &HttpRequestArgs, event: &CyclesEvent| {
+                            events.borrow_mut().push(*event)
+                        }
+                    }),
+            )
+            .service_fn(|_request: HttpRequestArgs| async move {
+                Ok::<_, BoxError>(HttpRequestResult::default())
+            });
+
+        service
+            .ready()
+            .await
+            .unwrap()
+            .call(request.clone())
+            .await
+            .unwrap();
+
+        let cost = estimator.cost_of_http_request(&request);
+        assert_eq!(
+            *events.borrow(),
+            vec![CyclesEvent {
+                estimated_cost: cost,
+                attached: cost,
+                charged: Some(0),
+            }]
+        );
+    }
+}
+
+mod markup {
+    use crate::cycles::{ChargeCaller, CyclesChargingPolicy, Markup};
+    use ic_cdk_management_canister::HttpRequestArgs;
+
+    #[test]
+    fn should_add_markup_to_cost() {
+        assert_eq!(Markup::default().apply(1_000), 1_000);
+        assert_eq!(
+            Markup {
+                percent: 10,
+                flat: 0
+            }
+            .apply(1_000),
+            1_100
+        );
+        assert_eq!(
+            Markup {
+                percent: 10,
+                flat: 1_000_000
+            }
+            .apply(1_000),
+            1_001_100
+        );
+        // The percentage is rounded up to avoid undercharging.
+        assert_eq!(
+            Markup {
+                percent: 10,
+                flat: 0
+            }
+            .apply(1_001),
+            1_102
+        );
+        assert_eq!(
+            Markup {
+                percent: 50,
+                flat: 1
+            }
+            .apply(u128::MAX),
+            u128::MAX
+        );
+    }
+
+    #[test]
+    fn should_charge_cost_with_markup() {
+        let request = HttpRequestArgs::default();
+
+        let policy = ChargeCaller::with_markup(Markup {
+            percent: 25,
+            flat: 500,
+        });
+        assert_eq!(policy.cycles_to_charge(&request, 2_000), 3_000);
+
+        let policy = ChargeCaller::with_markup_percent(0);
+        assert_eq!(policy.cycles_to_charge(&request, 2_000), 2_000);
+    }
+}
+
+mod split_charge {
+    use crate::cycles::{CyclesChargingPolicy, SplitCharge};
+    use ic_cdk_management_canister::HttpRequestArgs;
+
+    #[test]
+    fn should_charge_caller_percentage_of_cost() {
+        let request = HttpRequestArgs::default();
+
+        assert_eq!(SplitCharge::new(0).cycles_to_charge(&request, 1_000), 0);
+        assert_eq!(SplitCharge::new(25).cycles_to_charge(&request, 1_000), 250);
+        assert_eq!(SplitCharge::new(25).cycles_to_charge(&request, 1_001), 251);
+        assert_eq!(
+            SplitCharge::new(100).cycles_to_charge(&request, 1_000),
+            1_000
+        );
+        assert_eq!(
+            SplitCharge::new(150).cycles_to_charge(&request, 1_000),
+            1_000
+        );
+    }
+}
+
+#[cfg(feature = "candid")]
+mod charge_caller_except {
+    use crate::cycles::{ChargeCallerExcept, ChargeMyself};
+    use candid::Principal;
+
+    #[test]
+    fn should_exempt_principals_on_allowlist() {
+        let partner = Principal::from_slice(&[1, 2, 3]);
+        let policy = ChargeCallerExcept::new([partner], ChargeMyself::default());
+
+        assert!(policy.is_exempt(&partner));
+        assert!(!policy.is_exempt(&Principal::anonymous()));
+    }
+}
+
+#[cfg(feature = "candid")]
+mod icrc2 {
+    use crate::cycles::{Account, ChargeCallerIcrc2, TransferFromArgs};
+    use candid::{Nat, Principal};
+    use ic_cdk_management_canister::HttpRequestArgs;
+
+    const CALLER: Principal = Principal::from_slice(&[1, 2, 3]);
+    const CANISTER: Principal = Principal::from_slice(&[4, 5, 6]);
+    const LEDGER: Principal = Principal::from_slice(&[7, 8, 9]);
+
+    #[test]
+    fn should_transfer_tokens_from_caller() {
+        let policy = ChargeCallerIcrc2::new(LEDGER, |_request, cost| cost / 1_000).to(CANISTER);
+
+        let args = policy.transfer_from_args(CALLER, &HttpRequestArgs::default(), 2_000_000);
+
+        assert_eq!(
+            args,
+            TransferFromArgs {
+                spender_subaccount: None,
+                from: Account::from(CALLER),
+                to: Account::from(CANISTER),
+                amount: Nat::from(2_000_u32),
+                fee: None,
+                memo: None,
+                created_at_time: None,
+            }
+        );
+    }
+
+    #[test]
+    fn should_set_optional_transfer_fields() {
+        let to = Account {
+            owner: CANISTER,
+            subaccount: Some([1; 32]),
+        };
+        let policy = ChargeCallerIcrc2::new(LEDGER, |_request, cost| cost)
+            .to(to)
+            .spender_subaccount([2; 32])
+            .fee(10_u32)
+            .memo(b"canhttp".to_vec());
+
+        let args = policy.transfer_from_args(CALLER, &HttpRequestArgs::default(), 1_000);
+
+        assert_eq!(args.to, to);
+        assert_eq!(args.spender_subaccount, Some([2; 32]));
+        assert_eq!(args.amount, Nat::from(1_000_u32));
+        assert_eq!(args.fee, Some(Nat::from(10_u32)));
+        assert_eq!(args.memo, Some(b"canhttp".to_vec()));
+    }
+}
+
+#[cfg(feature = "http")]
+mod cycles_accounting {
+    use crate::{
+        convert::ConvertServiceBuilder,
+        cycles::{ChargeMyself, CyclesAccounting, CyclesCostEstimator, CyclesCostRequestExtension},
+        http::{HttpConversionLayer, HttpRequest},
+    };
+    use ic_cdk_management_canister::{
+        HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+    };
+    use std::{cell::Cell, rc::Rc};
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    const ESTIMATOR: CyclesCostEstimator = CyclesCostEstimator::new(13);
+
+    #[tokio::test]
+    async fn should_charge_cost_of_request_as_seen_by_cycles_accounting() {
+        let charged = Rc::new(Cell::new(None));
+        let attached = Rc::new(Cell::new(None));
+        let mut service = ServiceBuilder::new()
+            .convert_request(
+                CyclesAccounting::new(ChargeMyself::default()).with_cost_estimator(ESTIMATOR),
+            )
+            .map_request({
+                let charged = charged.clone();
+                move |mut request: HttpRequest| {
+                    charged.set(request.get_cycles_cost());
+                    // Layer modifying the request after it was charged.
+                    request
+                        .headers_mut()
+                        .insert("authorization", "Bearer secret".parse().unwrap());
+                    request
+                }
+            })
+            .layer(HttpConversionLayer)
+            .service_fn({
+                let attached = attached.clone();
+                move |request: IcHttpRequest| {
+                    attached.set(Some(ESTIMATOR.cost_of_http_request(&request)));
+                    async move {
+                        Ok::<_, BoxError>(IcHttpResponse {
+                            status: 200_u16.into(),
+                            ..Default::default()
+                        })
+                    }
+                }
+            });
+
+        let request = http::Request::post("https://example.com")
+            .body(b"{}".to_vec())
+            .unwrap();
+        service.ready().await.unwrap().call(request).await.unwrap();
+
+        let charged = charged.get().unwrap();
+        let attached = attached.get().unwrap();
+        let header_bytes = ("authorization".len() + "Bearer secret".len()) as u128;
+        assert_eq!(attached - charged, 400 * 13 * header_bytes);
+    }
+}
+
+#[cfg(feature = "http")]
+mod report_cycles {
+    use crate::{
+        cycles::{
+            CyclesCostRequestExtension, CyclesResponseExtension, ReportCyclesLayer,
+            StubCyclesEnvironment,
+        },
+        http::HttpRequest,
+    };
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    #[test]
+    fn should_set_cycles_extensions() {
+        let response = http::Response::new(Vec::<u8>::new());
+        assert_eq!(response.get_cycles_attached(), None);
+        assert_eq!(response.get_cycles_refunded(), None);
+
+        let response = response.cycles_attached(3_000).cycles_refunded(1_000);
+        assert_eq!(response.get_cycles_attached(), Some(3_000));
+        assert_eq!(response.get_cycles_refunded(), Some(1_000));
+
+        let builder = http::Response::builder()
+            .cycles_attached(4_000)
+            .cycles_refunded(2_000);
+        assert_eq!(builder.get_cycles_attached(), Some(4_000));
+        assert_eq!(builder.get_cycles_refunded(), Some(2_000));
+        let response = builder.body(Vec::<u8>::new()).unwrap();
+        assert_eq!(response.get_cycles_attached(), Some(4_000));
+        assert_eq!(response.get_cycles_refunded(), Some(2_000));
+    }
+
+    #[tokio::test]
+    async fn should_report_attached_and_refunded_cycles() {
+        let environment = StubCyclesEnvironment::default();
+        let mut service = ServiceBuilder::new()
+            .layer(ReportCyclesLayer::new().with_environment(environment.clone()))
+            .service_fn(|_request: HttpRequest| {
+                let environment = environment.clone();
+                async move {
+                    environment.set_refunded(1_500);
+                    Ok::<_, BoxError>(http::Response::new(Vec::new()))
+                }
+            });
+
+        let request = http::Request::post("https://example.com")
+            .cycles_cost(5_000)
+            .body(vec![])
+            .unwrap();
+        let response = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(response.get_cycles_attached(), Some(5_000));
+        assert_eq!(response.get_cycles_refunded(), Some(1_500));
+    }
+}
@@ -0,0 +1,503 @@
+//! Unified error type for the middlewares of this crate.
+//!
+//! Errors of every middleware can be converted into a [`CanHttpError`], which can be used as the
+//! error type of the whole service stack instead of an opaque [`tower::BoxError`].
+//! Each error belongs to an [`ErrorCategory`], which is typically enough to decide how to handle it.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::{
+//!     error::{CanHttpError, ErrorCategory},
+//!     http::HttpConversionLayer,
+//!     IcError,
+//! };
+//! use ic_cdk_management_canister::{HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse};
+//! use ic_error_types::RejectCode;
+//! use tower::{Service, ServiceBuilder, ServiceExt};
+//!
+//! async fn always_reject(_request: IcHttpRequest) -> Result<IcHttpResponse, CanHttpError> {
+//!     Err(IcError::CallRejected {
+//!         code: RejectCode::SysTransient,
+//!         message: "No consensus could be reached".to_string(),
+//!     }
+//!     .into())
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let mut service = ServiceBuilder::new()
+//!     .layer(HttpConversionLayer)
+//!     .service_fn(always_reject);
+//!
+//! let request = http::Request::put("https://internetcomputer.org").body(vec![]).unwrap();
+//! let error = service.ready().await.unwrap().call(request).await.unwrap_err();
+//! assert!(matches!(error, CanHttpError::HttpRequestConversion(_)));
+//! assert_eq!(error.category(), ErrorCategory::Request);
+//!
+//! let request = http::Request::get("https://internetcomputer.org").body(vec![]).unwrap();
+//! let error = service.ready().await.unwrap().call(request).await.unwrap_err();
+//! assert!(matches!(error, CanHttpError::Ic(IcError::CallRejected { .. })));
+//! assert_eq!(error.category(), ErrorCategory::Network);
+//! # }
+//! ```
+
+#[cfg(all(test, feature = "http"))]
+mod tests;
+
+#[cfg(all(feature = "http", feature = "json"))]
+use crate::http::json::{
+    ConsistentResponseIdFilterError, JsonRequestConversionError, JsonResponseConversionError,
+    NdJsonResponseConversionError,
+};
+#[cfg(feature = "jws")]
+use crate::http::jws::JwsError;
+#[cfg(all(feature = "http", feature = "json"))]
+use crate::http::oauth2::OAuth2Error;
+#[cfg(feature = "http")]
+use crate::http::{
+    ApiKeyError, AuthorizationError, BodyChecksumError, CharsetConversionError, CorrelationIdError,
+    EcdsaSignatureError, FilterNonSuccessfulHttpResponseError, HmacSignatureError,
+    HttpRequestConversionError, HttpResponseConversionError, HttpStatusError, IdempotencyKeyError,
+    RequestBuilderError, UnexpectedContentTypeError, UrlPolicyError,
+};
+#[cfg(feature = "multi")]
+use crate::multi::NoEligibleProviderError;
+use crate::{
+    convert::ContextualError, cycles::ChargeCallerError, CanisterReadyError, HttpsOutcallError,
+    IcError, RequestLimitError,
+};
+use ic_error_types::RejectCode;
+use std::convert::Infallible;
+use thiserror::Error;
+use tower::BoxError;
+
+/// Applies the given macro to the error and each group of error types (gated by features)
+/// that can be converted into a [`CanHttpError`].
+macro_rules! for_each_error_type {
+    ($apply:ident!($error:expr)) => {{
+        let error = $apply!(
+            $error,
+            CanHttpError,
+            IcError,
+            CanisterReadyError,
+            RequestLimitError,
+            ChargeCallerError,
+        );
+        #[cfg(feature = "http")]
+        let error = $apply!(
+            error,
+            HttpRequestConversionError,
+            RequestBuilderError,
+            ApiKeyError,
+            AuthorizationError,
+            CorrelationIdError,
+            EcdsaSignatureError,
+            HmacSignatureError,
+            IdempotencyKeyError,
+            UrlPolicyError,
+            HttpResponseConversionError,
+            HttpStatusError<Vec<u8>>,
+            FilterNonSuccessfulHttpResponseError<Vec<u8>>,
+            UnexpectedContentTypeError,
+            CharsetConversionError,
+            BodyChecksumError,
+        );
+        #[cfg(all(feature = "http", feature = "json"))]
+        let error = $apply!(
+            error,
+            JsonRequestConversionError,
+            JsonResponseConversionError,
+            NdJsonResponseConversionError,
+            ConsistentResponseIdFilterError,
+            OAuth2Error,
+        );
+        #[cfg(feature = "jws")]
+        let error = $apply!(error, JwsError);
+        #[cfg(feature = "multi")]
+        let error = $apply!(error, NoEligibleProviderError);
+        error
+    }};
+}
+
+/// Category of a [`CanHttpError`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ErrorCategory {
+    /// The HTTPs outcall was rejected by the Internet Computer, e.g. because no consensus
+    /// could be reached or the response was too large.
+    Network,
+    /// The request is invalid or cannot be issued, e.g. because the URL is not allowed or
+    /// not enough cycles were attached.
+    Request,
+    /// The upstream server returned an unexpected response, e.g. a non-successful HTTP status.
+    Upstream,
+    /// The response could not be decoded.
+    Decoding,
+}
+
+/// Error returned by any middleware of this crate.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum CanHttpError {
+    /// Error returned by the Internet Computer, see [`IcError`].
+    #[error(transparent)]
+    Ic(#[from] IcError),
+    /// The canister is not running, see [`CanisterReadyError`].
+    #[error(transparent)]
+    CanisterReady(#[from] CanisterReadyError),
+    /// The request exceeds a limit of HTTPs outcalls, see [`RequestLimitError`].
+    #[error(transparent)]
+    RequestLimit(#[from] RequestLimitError),
+    /// The caller could not be charged, see [`ChargeCallerError`].
+    #[error(transparent)]
+    ChargeCaller(#[from] ChargeCallerError),
+    /// The request could not be converted, see [`HttpRequestConversionError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    HttpRequestConversion(#[from] HttpRequestConversionError),
+    /// The request could not be built, see [`RequestBuilderError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    RequestBuilder(#[from] RequestBuilderError),
+    /// No API key could be added to the request, see [`ApiKeyError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    ApiKey(#[from] ApiKeyError),
+    /// The `Authorization` header could not be set, see [`AuthorizationError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Authorization(#[from] AuthorizationError),
+    /// The correlation ID could not be set, see [`CorrelationIdError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    CorrelationId(#[from] CorrelationIdError),
+    /// The request could not be signed with threshold ECDSA, see [`EcdsaSignatureError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    EcdsaSignature(#[from] EcdsaSignatureError),
+    /// The request could not be signed, see [`HmacSignatureError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    HmacSignature(#[from] HmacSignatureError),
+    /// The idempotency key could not be set, see [`IdempotencyKeyError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    IdempotencyKey(#[from] IdempotencyKeyError),
+    /// The request URL is not allowed, see [`UrlPolicyError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    UrlPolicy(#[from] UrlPolicyError),
+    /// The response body does not have the expected checksum, see [`BodyChecksumError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    BodyChecksum(#[from] BodyChecksumError),
+    /// The response could not be converted, see [`HttpResponseConversionError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    HttpResponseConversion(#[from] HttpResponseConversionError),
+    /// The response has a non-successful status code, see [`HttpStatusError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    HttpStatus(#[from] HttpStatusError<Vec<u8>>),
+    /// The response has an unexpected content type, see [`UnexpectedContentTypeError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    UnexpectedContentType(#[from] UnexpectedContentTypeError),
+    /// The response body could not be converted to UTF-8, see [`CharsetConversionError`].
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    CharsetConversion(#[from] CharsetConversionError),
+    /// The request body could not be serialized, see [`JsonRequestConversionError`].
+    #[cfg(all(feature = "http", feature = "json"))]
+    #[error(transparent)]
+    JsonRequestConversion(#[from] JsonRequestConversionError),
+    /// The response body could not be deserialized, see [`JsonResponseConversionError`].
+    #[cfg(all(feature = "http", feature = "json"))]
+    #[error(transparent)]
+    JsonResponseConversion(#[from] JsonResponseConversionError),
+    /// The response body could not be deserialized, see [`NdJsonResponseConversionError`].
+    #[cfg(all(feature = "http", feature = "json"))]
+    #[error(transparent)]
+    NdJsonResponseConversion(#[from] NdJsonResponseConversionError),
+    /// The JSON-RPC response IDs do not match the request IDs,
+    /// see [`ConsistentResponseIdFilterError`].
+    #[cfg(all(feature = "http", feature = "json"))]
+    #[error(transparent)]
+    ConsistentResponseId(#[from] ConsistentResponseIdFilterError),
+    /// An OAuth2 access token could not be obtained, see [`OAuth2Error`].
+    #[cfg(all(feature = "http", feature = "json"))]
+    #[error(transparent)]
+    OAuth2(#[from] OAuth2Error),
+    /// A signed response could not be verified, see [`JwsError`].
+    #[cfg(feature = "jws")]
+    #[error(transparent)]
+    Jws(#[from] JwsError),
+    /// No provider may serve the request, see [`NoEligibleProviderError`].
+    #[cfg(feature = "multi")]
+    #[error(transparent)]
+    NoEligibleProvider(#[from] NoEligibleProviderError),
+}
+
+impl CanHttpError {
+    /// Returns the category of the error.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            CanHttpError::Ic(IcError::CallRejected { .. }) => ErrorCategory::Network,
+            CanHttpError::Ic(IcError::InsufficientLiquidCycleBalance { .. })
+            | CanHttpError::CanisterReady(_)
+            | CanHttpError::RequestLimit(_)
+            | CanHttpError::ChargeCaller(_) => ErrorCategory::Request,
+            #[cfg(feature = "http")]
+            CanHttpError::EcdsaSignature(EcdsaSignatureError::Ic(IcError::CallRejected {
+                ..
+            })) => ErrorCategory::Network,
+            #[cfg(feature = "http")]
+            CanHttpError::HttpRequestConversion(_)
+            | CanHttpError::RequestBuilder(_)
+            | CanHttpError::ApiKey(_)
+            | CanHttpError::Authorization(_)
+            | CanHttpError::CorrelationId(_)
+            | CanHttpError::EcdsaSignature(_)
+            | CanHttpError::HmacSignature(_)
+            | CanHttpError::IdempotencyKey(_)
+            | CanHttpError::UrlPolicy(_) => ErrorCategory::Request,
+            #[cfg(feature = "http")]
+            CanHttpError::HttpStatus(_)
+            | CanHttpError::UnexpectedContentType(_)
+            | CanHttpError::BodyChecksum(_) => ErrorCategory::Upstream,
+            #[cfg(feature = "http")]
+            CanHttpError::HttpResponseConversion(_) | CanHttpError::CharsetConversion(_) => {
+                ErrorCategory::Decoding
+            }
+            #[cfg(all(feature = "http", feature = "json"))]
+            CanHttpError::JsonRequestConversion(_) => ErrorCategory::Request,
+            #[cfg(all(feature = "http", feature = "json"))]
+            CanHttpError::ConsistentResponseId(_) => ErrorCategory::Upstream,
+            #[cfg(all(feature = "http", feature = "json"))]
+            CanHttpError::JsonResponseConversion(_) | CanHttpError::NdJsonResponseConversion(_) => {
+                ErrorCategory::Decoding
+            }
+            #[cfg(all(feature = "http", feature = "json"))]
+            CanHttpError::OAuth2(OAuth2Error::InvalidTokenRequest(_)) => ErrorCategory::Request,
+            #[cfg(all(feature = "http", feature = "json"))]
+            CanHttpError::OAuth2(OAuth2Error::TokenEndpoint { .. }) => ErrorCategory::Upstream,
+            #[cfg(all(feature = "http", feature = "json"))]
+            CanHttpError::OAuth2(
+                OAuth2Error::InvalidTokenResponse(_) | OAuth2Error::UnsupportedTokenType(_),
+            ) => ErrorCategory::Decoding,
+            #[cfg(feature = "jws")]
+            CanHttpError::Jws(JwsError::InvalidKey(_)) => ErrorCategory::Request,
+            #[cfg(feature = "jws")]
+            CanHttpError::Jws(_) => ErrorCategory::Upstream,
+            #[cfg(feature = "multi")]
+            CanHttpError::NoEligibleProvider(_) => ErrorCategory::Request,
+        }
+    }
+}
+
+impl CanHttpError {
+    /// Recover a [`CanHttpError`] from a [`BoxError`] containing an error of any middleware
+    /// of this crate, or return the original error otherwise.
+    ///
+    /// This is useful for services returning a [`BoxError`], e.g. those built with
+    /// [`Client::new_with_box_error`](crate::Client::new_with_box_error).
+    /// Errors wrapped in a [`ContextualError`] are recovered as well, in which case the context
+    /// is discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::{error::CanHttpError, IcError};
+    /// use ic_error_types::RejectCode;
+    /// use tower::BoxError;
+    ///
+    /// let error = BoxError::from(IcError::CallRejected {
+    ///     code: RejectCode::SysTransient,
+    ///     message: "No consensus could be reached".to_string(),
+    /// });
+    ///
+    /// match CanHttpError::downcast(error) {
+    ///     Ok(CanHttpError::Ic(IcError::CallRejected { code, .. })) => {
+    ///         assert_eq!(code, RejectCode::SysTransient)
+    ///     }
+    ///     other => panic!("Unexpected error: {other:?}"),
+    /// }
+    /// ```
+    pub fn downcast(error: BoxError) -> Result<CanHttpError, BoxError> {
+        let error = match error.downcast::<ContextualError>() {
+            Ok(error) => {
+                let context = error.context().clone();
+                return Self::downcast(error.into_inner())
+                    .map_err(|inner| Box::new(ContextualError::new(context, inner)).into());
+            }
+            Err(error) => error,
+        };
+        macro_rules! try_downcast {
+            ($error:expr, $($ty:ty),+ $(,)?) => {{
+                let error = $error;
+                $(
+                    let error = match error.downcast::<$ty>() {
+                        Ok(e) => return Ok(CanHttpError::from(*e)),
+                        Err(e) => e,
+                    };
+                )+
+                error
+            }};
+        }
+
+        let error = for_each_error_type!(try_downcast!(error));
+        Err(error)
+    }
+}
+
+/// Returns the [`ErrorCategory`] of the given error, if it contains an error of any middleware
+/// of this crate.
+///
+/// Contrary to [`CanHttpError::downcast`], this does not take ownership of the error.
+pub fn error_category(error: &BoxError) -> Option<ErrorCategory> {
+    let error = without_context(error);
+    macro_rules! try_categorize {
+        ($error:expr, $($ty:ty),+ $(,)?) => {{
+            let error = $error;
+            $(
+                if let Some(e) = error.downcast_ref::<$ty>() {
+                    return Some(CanHttpError::from(e.clone()).category());
+                }
+            )+
+            error
+        }};
+    }
+
+    let _error = for_each_error_type!(try_categorize!(error));
+    None
+}
+
+/// Returns the [`IcError`] contained in the given error, if any.
+pub fn as_ic_error(error: &BoxError) -> Option<&IcError> {
+    let error = without_context(error);
+    error
+        .downcast_ref::<IcError>()
+        .or_else(|| match error.downcast_ref::<CanHttpError>() {
+            Some(CanHttpError::Ic(e)) => Some(e),
+            _ => None,
+        })
+}
+
+/// Returns the [`ChargeCallerError`] contained in the given error, if any.
+pub fn as_cycles_error(error: &BoxError) -> Option<&ChargeCallerError> {
+    let error = without_context(error);
+    error.downcast_ref::<ChargeCallerError>().or_else(|| {
+        match error.downcast_ref::<CanHttpError>() {
+            Some(CanHttpError::ChargeCaller(e)) => Some(e),
+            _ => None,
+        }
+    })
+}
+
+/// Returns the [`HttpStatusError`] contained in the given error, if any.
+#[cfg(feature = "http")]
+pub fn as_http_status_error(error: &BoxError) -> Option<&HttpStatusError<Vec<u8>>> {
+    let error = without_context(error);
+    error
+        .downcast_ref::<HttpStatusError<Vec<u8>>>()
+        .or_else(|| match error.downcast_ref::<CanHttpError>() {
+            Some(CanHttpError::HttpStatus(e)) => Some(e),
+            _ => None,
+        })
+}
+
+/// Returns the [`JsonResponseConversionError`] contained in the given error, if any.
+#[cfg(all(feature = "http", feature = "json"))]
+pub fn as_json_error(error: &BoxError) -> Option<&JsonResponseConversionError> {
+    let error = without_context(error);
+    error
+        .downcast_ref::<JsonResponseConversionError>()
+        .or_else(|| match error.downcast_ref::<CanHttpError>() {
+            Some(CanHttpError::JsonResponseConversion(e)) => Some(e),
+            _ => None,
+        })
+}
+
+/// Returns the [`ConsistentResponseIdFilterError`] contained in the given error, if any.
+#[cfg(all(feature = "http", feature = "json"))]
+pub fn as_json_rpc_error(error: &BoxError) -> Option<&ConsistentResponseIdFilterError> {
+    let error = without_context(error);
+    error
+        .downcast_ref::<ConsistentResponseIdFilterError>()
+        .or_else(|| match error.downcast_ref::<CanHttpError>() {
+            Some(CanHttpError::ConsistentResponseId(e)) => Some(e),
+            _ => None,
+        })
+}
+
+impl HttpsOutcallError for CanHttpError {
+    fn is_response_too_large(&self) -> bool {
+        match self {
+            CanHttpError::Ic(error) => error.is_response_too_large(),
+            _ => false,
+        }
+    }
+
+    fn is_transient(&self) -> bool {
+        match self {
+            CanHttpError::Ic(error) => error.is_transient(),
+            #[cfg(feature = "http")]
+            CanHttpError::HttpStatus(error) => error.is_transient(),
+            #[cfg(feature = "http")]
+            CanHttpError::EcdsaSignature(EcdsaSignatureError::Ic(error)) => error.is_transient(),
+            _ => false,
+        }
+    }
+
+    fn is_rate_limited(&self) -> bool {
+        match self {
+            CanHttpError::Ic(error) => error.is_rate_limited(),
+            #[cfg(feature = "http")]
+            CanHttpError::HttpStatus(error) => error.is_rate_limited(),
+            #[cfg(feature = "http")]
+            CanHttpError::ApiKey(ApiKeyError::AllKeysExhausted { .. }) => true,
+            _ => false,
+        }
+    }
+
+    fn is_timeout(&self) -> bool {
+        match self {
+            CanHttpError::Ic(error) => error.is_timeout(),
+            #[cfg(feature = "http")]
+            CanHttpError::HttpStatus(error) => error.is_timeout(),
+            _ => false,
+        }
+    }
+
+    fn reject(&self) -> Option<(RejectCode, &str)> {
+        match self {
+            CanHttpError::Ic(error) => error.reject(),
+            _ => None,
+        }
+    }
+}
+
+impl From<Infallible> for CanHttpError {
+    fn from(error: Infallible) -> Self {
+        match error {}
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<FilterNonSuccessfulHttpResponseError<Vec<u8>>> for CanHttpError {
+    fn from(error: FilterNonSuccessfulHttpResponseError<Vec<u8>>) -> Self {
+        let FilterNonSuccessfulHttpResponseError::UnsuccessfulResponse(response) = error;
+        let (parts, body) = response.into_parts();
+        CanHttpError::HttpStatus(HttpStatusError {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        })
+    }
+}
+
+fn without_context(mut error: &BoxError) -> &BoxError {
+    while let Some(contextual) = error.downcast_ref::<ContextualError>() {
+        error = contextual.inner();
+    }
+    error
+}
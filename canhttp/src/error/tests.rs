@@ -0,0 +1,284 @@
+use crate::{
+    cycles::ChargeCallerError,
+    error::{CanHttpError, ErrorCategory},
+    http::{
+        FilterNonSuccessfulHttpResponse, HttpConversionLayer, HttpStatusError, UrlPolicy,
+        UrlPolicyLayer,
+    },
+    ConvertServiceBuilder, IcError, RequestLimitError,
+};
+use ic_cdk_management_canister::{
+    HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+};
+use ic_error_types::RejectCode;
+use tower::{Service, ServiceBuilder, ServiceExt};
+
+#[test]
+fn should_categorize_errors() {
+    for (error, expected_category) in [
+        (
+            CanHttpError::from(IcError::CallRejected {
+                code: RejectCode::SysTransient,
+                message: "No consensus could be reached".to_string(),
+            }),
+            ErrorCategory::Network,
+        ),
+        (
+            CanHttpError::from(IcError::InsufficientLiquidCycleBalance {
+                available: 0,
+                required: 1,
+            }),
+            ErrorCategory::Request,
+        ),
+        (
+            CanHttpError::from(ChargeCallerError::InsufficientCyclesError {
+                expected: 2,
+                received: 1,
+            }),
+            ErrorCategory::Request,
+        ),
+        (
+            CanHttpError::from(RequestLimitError::UrlTooLong {
+                length: 10_000,
+                limit: 8_192,
+            }),
+            ErrorCategory::Request,
+        ),
+        (
+            CanHttpError::from(HttpStatusError {
+                status: http::StatusCode::TOO_MANY_REQUESTS,
+                headers: http::HeaderMap::new(),
+                body: vec![],
+            }),
+            ErrorCategory::Upstream,
+        ),
+        (
+            CanHttpError::from(crate::http::HttpResponseConversionError::InvalidStatusCode),
+            ErrorCategory::Decoding,
+        ),
+    ] {
+        assert_eq!(error.category(), expected_category, "{error:?}");
+    }
+}
+
+#[tokio::test]
+async fn should_use_as_error_type_of_whole_stack() {
+    let mut service = ServiceBuilder::new()
+        .layer(UrlPolicyLayer::new(
+            UrlPolicy::new().allow_hosts(["internetcomputer.org"]),
+        ))
+        .convert_response(FilterNonSuccessfulHttpResponse)
+        .layer(HttpConversionLayer)
+        .service_fn(|_request: IcHttpRequest| async {
+            Ok::<_, CanHttpError>(IcHttpResponse {
+                status: 503_u16.into(),
+                headers: vec![],
+                body: b"Service Unavailable".to_vec(),
+            })
+        });
+
+    let request = http::Request::get("https://example.com")
+        .body(vec![])
+        .unwrap();
+    let error = service
+        .ready()
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, CanHttpError::UrlPolicy(_)));
+    assert_eq!(error.category(), ErrorCategory::Request);
+
+    let request = http::Request::get("https://internetcomputer.org")
+        .body(vec![])
+        .unwrap();
+    let error = service
+        .ready()
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error,
+        CanHttpError::HttpStatus(HttpStatusError {
+            status: http::StatusCode::SERVICE_UNAVAILABLE,
+            headers: http::HeaderMap::new(),
+            body: b"Service Unavailable".to_vec(),
+        })
+    );
+    assert_eq!(error.category(), ErrorCategory::Upstream);
+}
+
+#[cfg(all(feature = "http", feature = "json"))]
+#[tokio::test]
+async fn should_categorize_json_decoding_error() {
+    use crate::http::{json::JsonConversionLayer, HttpRequest, HttpResponse};
+
+    let mut service = ServiceBuilder::new()
+        .layer(JsonConversionLayer::<serde_json::Value, serde_json::Value>::new())
+        .service_fn(|_request: HttpRequest| async {
+            Ok::<_, CanHttpError>(HttpResponse::new(b"not json".to_vec()))
+        });
+
+    let request = http::Request::post("https://internetcomputer.org")
+        .body(serde_json::json!({}))
+        .unwrap();
+    let error = service
+        .ready()
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, CanHttpError::JsonResponseConversion(_)));
+    assert_eq!(error.category(), ErrorCategory::Decoding);
+}
+
+mod downcast {
+    use crate::{
+        cycles::ChargeCallerError,
+        error::{as_cycles_error, as_http_status_error, as_ic_error, CanHttpError},
+        http::{FilterNonSuccessfulHttpResponseError, HttpStatusError},
+        IcError,
+    };
+    use ic_error_types::RejectCode;
+    use tower::BoxError;
+
+    fn ic_error() -> IcError {
+        IcError::CallRejected {
+            code: RejectCode::SysFatal,
+            message: "Http body exceeds size limit".to_string(),
+        }
+    }
+
+    #[test]
+    fn should_downcast_box_error() {
+        assert_eq!(
+            CanHttpError::downcast(BoxError::from(ic_error())).unwrap(),
+            CanHttpError::Ic(ic_error())
+        );
+        assert_eq!(
+            CanHttpError::downcast(BoxError::from(CanHttpError::from(ic_error()))).unwrap(),
+            CanHttpError::Ic(ic_error())
+        );
+
+        let response = http::Response::builder()
+            .status(404)
+            .body(b"Not Found".to_vec())
+            .unwrap();
+        assert_eq!(
+            CanHttpError::downcast(BoxError::from(
+                FilterNonSuccessfulHttpResponseError::UnsuccessfulResponse(response)
+            ))
+            .unwrap(),
+            CanHttpError::HttpStatus(HttpStatusError {
+                status: http::StatusCode::NOT_FOUND,
+                headers: http::HeaderMap::new(),
+                body: b"Not Found".to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_return_unknown_error() {
+        let error = CanHttpError::downcast(BoxError::from("unknown")).unwrap_err();
+
+        assert_eq!(error.to_string(), "unknown");
+    }
+
+    #[test]
+    fn should_get_reference_to_error() {
+        let error = BoxError::from(ic_error());
+        assert_eq!(as_ic_error(&error), Some(&ic_error()));
+        assert_eq!(as_cycles_error(&error), None);
+
+        let error = BoxError::from(CanHttpError::from(ic_error()));
+        assert_eq!(as_ic_error(&error), Some(&ic_error()));
+
+        let cycles_error = ChargeCallerError::InsufficientCyclesError {
+            expected: 2,
+            received: 1,
+        };
+        let error = BoxError::from(cycles_error.clone());
+        assert_eq!(as_cycles_error(&error), Some(&cycles_error));
+        assert_eq!(as_ic_error(&error), None);
+        assert_eq!(as_http_status_error(&error), None);
+    }
+}
+
+mod contextual_error {
+    use crate::{
+        convert::{ContextualError, ConvertServiceBuilder, ErrorContext},
+        error::{as_ic_error, CanHttpError},
+        HttpsOutcallError, IcError,
+    };
+    use ic_error_types::RejectCode;
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    fn response_too_large() -> IcError {
+        IcError::CallRejected {
+            code: RejectCode::SysFatal,
+            message: "Http body exceeds size limit".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_attach_context_to_errors() {
+        let mut service = ServiceBuilder::new()
+            .error_context(|request: &(&'static str, &'static str)| {
+                ErrorContext::new()
+                    .with_url(request.0)
+                    .with_method(request.1)
+                    .with_attempt(2)
+            })
+            .service_fn(|_request: (&'static str, &'static str)| async {
+                Err::<(), _>(response_too_large())
+            });
+
+        let error = service
+            .ready()
+            .await
+            .unwrap()
+            .call(("https://cloudflare-eth.com", "eth_getLogs"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{} [url: https://cloudflare-eth.com, method: eth_getLogs, attempt: 2]",
+                response_too_large()
+            )
+        );
+        assert!(error.is_response_too_large());
+        assert_eq!(as_ic_error(&error), Some(&response_too_large()));
+        let contextual = error.downcast_ref::<ContextualError>().unwrap();
+        assert_eq!(
+            contextual.context(),
+            &ErrorContext {
+                url: Some("https://cloudflare-eth.com".to_string()),
+                method: Some("eth_getLogs".to_string()),
+                attempt: Some(2),
+            }
+        );
+        assert_eq!(
+            CanHttpError::downcast(error).unwrap(),
+            CanHttpError::Ic(response_too_large())
+        );
+    }
+
+    #[test]
+    fn should_keep_context_of_unknown_error() {
+        let error: BoxError = Box::new(ContextualError::new(
+            ErrorContext::new().with_attempt(1),
+            "unknown",
+        ));
+
+        let error = CanHttpError::downcast(error).unwrap_err();
+
+        assert_eq!(error.to_string(), "unknown [attempt: 1]");
+    }
+}
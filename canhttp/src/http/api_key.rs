@@ -0,0 +1,375 @@
+use crate::{
+    clock::Clock,
+    http::{query::append_query, HttpResponse, Placement, QueryParams},
+};
+use futures_util::future;
+use http::{HeaderValue, StatusCode};
+use pin_project::pin_project;
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use thiserror::Error;
+use tower::Service;
+use tower_layer::Layer;
+
+/// Default duration during which an exhausted key is not used.
+const DEFAULT_COOL_DOWN: Duration = Duration::from_secs(60);
+
+/// Error returned when no API key can be added to a request.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum ApiKeyError {
+    /// The pool does not contain any API key.
+    #[error("No API keys configured")]
+    NoKeys,
+    /// All API keys are cooling down after their quota was exhausted.
+    #[error("All API keys are exhausted until {available_at}ns since the UNIX epoch")]
+    AllKeysExhausted {
+        /// Time, in nanoseconds since the UNIX epoch, at which the first key becomes
+        /// available again.
+        available_at: u64,
+    },
+    /// The API key is not a valid header value.
+    #[error("Invalid API key: {0}")]
+    InvalidKey(String),
+    /// The URL with the API key as query parameter is invalid.
+    #[error("Invalid URL after adding API key `{url}`: {reason}")]
+    InvalidUrl {
+        /// The invalid URL, without the API key.
+        url: String,
+        /// Reason for the URL being invalid.
+        reason: String,
+    },
+}
+
+struct KeyState {
+    key: String,
+    exhausted_until: Option<u64>,
+}
+
+/// Add API keys from a pool to requests, rotating to the next key when the quota
+/// of the current one is exhausted.
+///
+/// A response indicates that the quota of the key used for the request is exhausted if
+/// * its HTTP status code is one of [`ApiKeyRotation::exhausted_on_status`] (by default `429 Too Many Requests`); or
+/// * it contains a JSON-RPC error whose code is one of [`ApiKeyRotation::exhausted_on_json_rpc_error`].
+///
+/// The exhausted key is then not used for the [cool-down](ApiKeyRotation::cool_down) period,
+/// and subsequent requests use the next available key. The response itself is returned as is,
+/// so that a retry layer can retry the request with the next key.
+///
+/// All clones of an [`ApiKeyRotation`] share the same state.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{ApiKeyRotation, ApiKeyRotationLayer, HttpRequest, HttpResponse, Placement};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// async fn rate_limited_with_key_1(request: HttpRequest) -> Result<HttpResponse, BoxError> {
+///     let status = match request.headers()["x-api-key"].to_str()? {
+///         "key-1" => http::StatusCode::TOO_MANY_REQUESTS,
+///         _ => http::StatusCode::OK,
+///     };
+///     Ok(http::Response::builder().status(status).body(vec![])?)
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let keys = ApiKeyRotation::new(
+///     ["key-1", "key-2"],
+///     Placement::Header(http::HeaderName::from_static("x-api-key")),
+/// );
+/// # let keys = keys.clock(|| 0);
+///
+/// let mut service = ServiceBuilder::new()
+///     .layer(ApiKeyRotationLayer::new(keys.clone()))
+///     .service_fn(rate_limited_with_key_1);
+///
+/// let request = http::Request::get("https://example.com").body(vec![])?;
+/// let response = service.ready().await?.call(request).await?;
+/// assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+///
+/// let request = http::Request::get("https://example.com").body(vec![])?;
+/// let response = service.ready().await?.call(request).await?;
+/// assert_eq!(response.status(), http::StatusCode::OK);
+/// assert_eq!(keys.available_keys(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ApiKeyRotation {
+    keys: Rc<RefCell<Vec<KeyState>>>,
+    current: Rc<RefCell<usize>>,
+    placement: Placement,
+    cool_down: Duration,
+    exhausted_status_codes: Vec<StatusCode>,
+    #[cfg(feature = "json")]
+    exhausted_json_rpc_error_codes: Vec<i64>,
+    clock: Clock,
+}
+
+impl ApiKeyRotation {
+    /// Create a new [`ApiKeyRotation`] adding the given keys at the given place.
+    ///
+    /// To use the keys as bearer tokens, use [`Placement::Header`] with the
+    /// `Authorization` header and keys of the form `Bearer <key>`.
+    pub fn new<I, K>(keys: I, placement: Placement) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        let keys = keys
+            .into_iter()
+            .map(|key| KeyState {
+                key: key.into(),
+                exhausted_until: None,
+            })
+            .collect();
+        Self {
+            keys: Rc::new(RefCell::new(keys)),
+            current: Rc::new(RefCell::new(0)),
+            placement,
+            cool_down: DEFAULT_COOL_DOWN,
+            exhausted_status_codes: vec![StatusCode::TOO_MANY_REQUESTS],
+            #[cfg(feature = "json")]
+            exhausted_json_rpc_error_codes: Vec::new(),
+            clock: Clock::default(),
+        }
+    }
+
+    /// Set for how long an exhausted key is not used.
+    ///
+    /// Defaults to one minute.
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+
+    /// Replace the HTTP status codes indicating that the quota of a key is exhausted.
+    ///
+    /// Defaults to `429 Too Many Requests`.
+    pub fn exhausted_on_status(mut self, codes: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.exhausted_status_codes = codes.into_iter().collect();
+        self
+    }
+
+    /// Set the JSON-RPC error codes indicating that the quota of a key is exhausted.
+    ///
+    /// Both single and batch JSON-RPC responses are inspected.
+    /// By default, JSON-RPC errors are not inspected.
+    #[cfg(feature = "json")]
+    pub fn exhausted_on_json_rpc_error(mut self, codes: impl IntoIterator<Item = i64>) -> Self {
+        self.exhausted_json_rpc_error_codes = codes.into_iter().collect();
+        self
+    }
+
+    /// Change the clock used to determine when keys stop cooling down.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+
+    /// Returns the number of keys that are currently not cooling down.
+    pub fn available_keys(&self) -> usize {
+        let now = self.clock.now();
+        self.keys
+            .borrow()
+            .iter()
+            .filter(|key| is_available(key, now))
+            .count()
+    }
+
+    /// Returns the index and value of the first available key, starting from the current one.
+    fn select(&self) -> Result<(usize, String), ApiKeyError> {
+        let now = self.clock.now();
+        let keys = self.keys.borrow();
+        let mut current = self.current.borrow_mut();
+        let len = keys.len();
+        match (0..len)
+            .map(|offset| (*current + offset) % len)
+            .find(|index| is_available(&keys[*index], now))
+        {
+            Some(index) => {
+                *current = index;
+                Ok((index, keys[index].key.clone()))
+            }
+            None => Err(keys
+                .iter()
+                .filter_map(|key| key.exhausted_until)
+                .min()
+                .map_or(ApiKeyError::NoKeys, |available_at| {
+                    ApiKeyError::AllKeysExhausted { available_at }
+                })),
+        }
+    }
+
+    fn mark_exhausted(&self, index: usize) {
+        let until = self
+            .clock
+            .now()
+            .saturating_add(u64::try_from(self.cool_down.as_nanos()).unwrap_or(u64::MAX));
+        let mut keys = self.keys.borrow_mut();
+        keys[index].exhausted_until = Some(until);
+        let mut current = self.current.borrow_mut();
+        if *current == index {
+            *current = (index + 1) % keys.len();
+        }
+    }
+
+    fn is_exhausted(&self, response: &HttpResponse) -> bool {
+        if self.exhausted_status_codes.contains(&response.status()) {
+            return true;
+        }
+        #[cfg(feature = "json")]
+        if !self.exhausted_json_rpc_error_codes.is_empty() {
+            let error_code = |value: &serde_json::Value| value.pointer("/error/code")?.as_i64();
+            let codes: Vec<i64> = match serde_json::from_slice(response.body()) {
+                Ok(serde_json::Value::Array(responses)) => {
+                    responses.iter().filter_map(error_code).collect()
+                }
+                Ok(response) => error_code(&response).into_iter().collect(),
+                Err(_) => Vec::new(),
+            };
+            return codes
+                .iter()
+                .any(|code| self.exhausted_json_rpc_error_codes.contains(code));
+        }
+        false
+    }
+
+    fn add_key<T>(&self, request: &mut http::Request<T>, key: String) -> Result<(), ApiKeyError> {
+        match &self.placement {
+            Placement::Header(name) => {
+                let mut value = HeaderValue::try_from(key)
+                    .map_err(|e| ApiKeyError::InvalidKey(e.to_string()))?;
+                value.set_sensitive(true);
+                request.headers_mut().insert(name.clone(), value);
+            }
+            Placement::QueryParam(name) => {
+                let url = append_query(request.uri(), &QueryParams::new().with_param(name, key));
+                let uri =
+                    url.parse()
+                        .map_err(|e: http::uri::InvalidUri| ApiKeyError::InvalidUrl {
+                            url: request.uri().to_string(),
+                            reason: e.to_string(),
+                        })?;
+                *request.uri_mut() = uri;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_available(key: &KeyState, now: u64) -> bool {
+    key.exhausted_until.is_none_or(|until| until <= now)
+}
+
+impl fmt::Debug for ApiKeyRotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKeyRotation")
+            .field("keys", &self.keys.borrow().len())
+            .field("current", &self.current.borrow())
+            .field("placement", &self.placement)
+            .field("cool_down", &self.cool_down)
+            .field("exhausted_status_codes", &self.exhausted_status_codes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Middleware that adds API keys to requests and rotates them when their quota is exhausted,
+/// see [`ApiKeyRotation`].
+///
+/// This [`Layer`] produces instances of the [`ApiKeyRotationService`] service.
+///
+/// [`Layer`]: tower::Layer
+#[derive(Clone, Debug)]
+pub struct ApiKeyRotationLayer {
+    rotation: ApiKeyRotation,
+}
+
+impl ApiKeyRotationLayer {
+    /// Returns a new [`ApiKeyRotationLayer`] using keys from the given pool.
+    pub fn new(rotation: ApiKeyRotation) -> Self {
+        Self { rotation }
+    }
+}
+
+impl<S> Layer<S> for ApiKeyRotationLayer {
+    type Service = ApiKeyRotationService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyRotationService {
+            inner,
+            rotation: self.rotation.clone(),
+        }
+    }
+}
+
+/// Add an API key to requests and mark it as exhausted depending on the response.
+#[derive(Clone, Debug)]
+pub struct ApiKeyRotationService<S> {
+    inner: S,
+    rotation: ApiKeyRotation,
+}
+
+impl<S, T> Service<http::Request<T>> for ApiKeyRotationService<S>
+where
+    S: Service<http::Request<T>, Response = HttpResponse>,
+    ApiKeyError: Into<S::Error>,
+{
+    type Response = HttpResponse;
+    type Error = S::Error;
+    type Future =
+        future::Either<ResponseFuture<S::Future>, future::Ready<Result<HttpResponse, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<T>) -> Self::Future {
+        let index = match self.rotation.select().and_then(|(index, key)| {
+            self.rotation.add_key(&mut request, key)?;
+            Ok(index)
+        }) {
+            Ok(index) => index,
+            Err(err) => return future::Either::Right(future::ready(Err(err.into()))),
+        };
+        future::Either::Left(ResponseFuture {
+            response_future: self.inner.call(request),
+            rotation: self.rotation.clone(),
+            index,
+        })
+    }
+}
+
+#[pin_project]
+pub struct ResponseFuture<F> {
+    #[pin]
+    response_future: F,
+    rotation: ApiKeyRotation,
+    index: usize,
+}
+
+impl<F, Error> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<HttpResponse, Error>>,
+{
+    type Output = Result<HttpResponse, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = futures_util::ready!(this.response_future.poll(cx));
+        if let Ok(response) = &result {
+            if this.rotation.is_exhausted(response) {
+                this.rotation.mark_exhausted(*this.index);
+            }
+        }
+        Poll::Ready(result)
+    }
+}
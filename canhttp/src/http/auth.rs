@@ -0,0 +1,190 @@
+use crate::convert::{ConvertRequest, ConvertRequestLayer, Filter};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{header::AUTHORIZATION, HeaderValue};
+use std::convert::Infallible;
+use thiserror::Error;
+use tower_layer::Layer;
+
+/// Error returned when the `Authorization` header cannot be set.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum AuthorizationError {
+    /// The token is not a valid header value.
+    #[error("Invalid token for `Authorization` header: {0}")]
+    InvalidToken(String),
+}
+
+/// Set the `Authorization` header of requests to a bearer token.
+///
+/// The token is provided by a closure that is called for every request, so that it can be
+/// read from (stable) memory or rotated without rebuilding the service.
+/// Any existing `Authorization` header is replaced.
+#[derive(Clone, Debug)]
+pub struct BearerAuth<F> {
+    token_provider: F,
+}
+
+impl<F> BearerAuth<F>
+where
+    F: Fn() -> String,
+{
+    /// Create a new [`BearerAuth`] with the given token provider.
+    pub fn new(token_provider: F) -> Self {
+        Self { token_provider }
+    }
+}
+
+impl<F, T> Filter<http::Request<T>> for BearerAuth<F>
+where
+    F: Fn() -> String,
+{
+    type Error = AuthorizationError;
+
+    fn filter(&mut self, mut request: http::Request<T>) -> Result<http::Request<T>, Self::Error> {
+        let token = (self.token_provider)();
+        let value = HeaderValue::try_from(format!("Bearer {token}"))
+            .map_err(|e| AuthorizationError::InvalidToken(e.to_string()))?;
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, sensitive(value));
+        Ok(request)
+    }
+}
+
+/// Middleware that sets the `Authorization` header of requests to a bearer token,
+/// see [`BearerAuth`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{BearerAuthLayer, HttpRequest};
+/// use std::cell::RefCell;
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// thread_local! {
+///     static API_KEY: RefCell<String> = RefCell::new("secret".to_string());
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(BearerAuthLayer::new(|| API_KEY.with(|key| key.borrow().clone())))
+///     .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+///
+/// let request = http::Request::get("https://example.com").body(vec![])?;
+/// let request = service.ready().await?.call(request).await?;
+/// assert_eq!(request.headers()["authorization"], "Bearer secret");
+///
+/// API_KEY.with(|key| *key.borrow_mut() = "rotated".to_string());
+/// let request = http::Request::get("https://example.com").body(vec![])?;
+/// let request = service.ready().await?.call(request).await?;
+/// assert_eq!(request.headers()["authorization"], "Bearer rotated");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BearerAuthLayer<F> {
+    auth: BearerAuth<F>,
+}
+
+impl<F> BearerAuthLayer<F>
+where
+    F: Fn() -> String,
+{
+    /// Returns a new [`BearerAuthLayer`] with the given token provider.
+    pub fn new(token_provider: F) -> Self {
+        Self {
+            auth: BearerAuth::new(token_provider),
+        }
+    }
+}
+
+impl<S, F: Clone> Layer<S> for BearerAuthLayer<F> {
+    type Service = ConvertRequest<S, BearerAuth<F>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.auth.clone()).layer(inner)
+    }
+}
+
+/// Set the `Authorization` header of requests using the
+/// [Basic](https://www.rfc-editor.org/rfc/rfc7617) authentication scheme.
+///
+/// Any existing `Authorization` header is replaced.
+#[derive(Clone, Debug)]
+pub struct BasicAuth {
+    value: HeaderValue,
+}
+
+impl BasicAuth {
+    /// Create a new [`BasicAuth`] with the given credentials.
+    pub fn new(username: impl AsRef<str>, password: impl AsRef<str>) -> Self {
+        let credentials = STANDARD.encode(format!("{}:{}", username.as_ref(), password.as_ref()));
+        let value = HeaderValue::try_from(format!("Basic {credentials}"))
+            .expect("BUG: base64-encoded credentials must be a valid header value");
+        Self {
+            value: sensitive(value),
+        }
+    }
+}
+
+impl<T> Filter<http::Request<T>> for BasicAuth {
+    type Error = Infallible;
+
+    fn filter(&mut self, mut request: http::Request<T>) -> Result<http::Request<T>, Self::Error> {
+        request
+            .headers_mut()
+            .insert(AUTHORIZATION, self.value.clone());
+        Ok(request)
+    }
+}
+
+/// Middleware that sets the `Authorization` header of requests using the Basic authentication
+/// scheme, see [`BasicAuth`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{BasicAuthLayer, HttpRequest};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(BasicAuthLayer::new("Aladdin", "open sesame"))
+///     .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+///
+/// let request = http::Request::get("https://example.com").body(vec![])?;
+/// let request = service.ready().await?.call(request).await?;
+/// assert_eq!(
+///     request.headers()["authorization"],
+///     "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BasicAuthLayer {
+    auth: BasicAuth,
+}
+
+impl BasicAuthLayer {
+    /// Returns a new [`BasicAuthLayer`] with the given credentials.
+    pub fn new(username: impl AsRef<str>, password: impl AsRef<str>) -> Self {
+        Self {
+            auth: BasicAuth::new(username, password),
+        }
+    }
+}
+
+impl<S> Layer<S> for BasicAuthLayer {
+    type Service = ConvertRequest<S, BasicAuth>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.auth.clone()).layer(inner)
+    }
+}
+
+fn sensitive(mut value: HeaderValue) -> HeaderValue {
+    value.set_sensitive(true);
+    value
+}
@@ -0,0 +1,254 @@
+use crate::{
+    http::{query::append_query, HttpRequestConverter, QueryParams},
+    IsReplicatedRequestExtension, MaxResponseBytesRequestExtension, RequestLimits,
+    TransformContextRequestExtension,
+};
+use http::{header::CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use ic_cdk_management_canister::TransformContext;
+use std::fmt;
+use thiserror::Error;
+
+/// Builder for HTTP requests targeting HTTPs outcalls.
+///
+/// Compared to [`http::request::Builder`], this builder
+/// * only accepts HTTP methods supported by HTTPs outcalls;
+/// * supports IC-specific settings, like `max_response_bytes` or the transform context;
+/// * validates each setting upfront, so that the first invalid one is reported when building
+///   the request.
+///
+/// The resulting [`http::Request`] can be used with the existing layers, e.g.
+/// [`HttpConversionLayer`](crate::http::HttpConversionLayer).
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{http::RequestBuilder, MaxResponseBytesRequestExtension};
+///
+/// let request = RequestBuilder::post("https://api.example.com/v1/prices")
+///     .query("symbol", "ICPUSDT")
+///     .header("X-Api-Version", "2")
+///     .max_response_bytes(4_096)
+///     .json(vec![1, 2, 3])
+///     .unwrap();
+///
+/// assert_eq!(request.method(), http::Method::POST);
+/// assert_eq!(request.uri(), "https://api.example.com/v1/prices?symbol=ICPUSDT");
+/// assert_eq!(request.headers()["content-type"], "application/json");
+/// assert_eq!(request.get_max_response_bytes(), Some(4_096));
+/// assert_eq!(request.body(), &vec![1, 2, 3]);
+/// ```
+#[derive(Debug)]
+pub struct RequestBuilder {
+    parts: Result<Parts, RequestBuilderError>,
+}
+
+#[derive(Debug)]
+struct Parts {
+    method: http::Method,
+    uri: http::Uri,
+    query: QueryParams,
+    headers: HeaderMap,
+    max_response_bytes: Option<u64>,
+    transform_context: Option<TransformContext>,
+    is_replicated: Option<bool>,
+}
+
+impl RequestBuilder {
+    /// Create a new builder for a request with the given method and URL.
+    pub fn new(method: http::Method, url: impl AsRef<str>) -> Self {
+        let url = url.as_ref();
+        let parts = if HttpRequestConverter::SUPPORTED_HTTP_METHODS.contains(&method) {
+            url.parse::<http::Uri>()
+                .map_err(|e| RequestBuilderError::InvalidUrl {
+                    url: url.to_string(),
+                    reason: e.to_string(),
+                })
+                .map(|uri| Parts {
+                    method,
+                    uri,
+                    query: QueryParams::new(),
+                    headers: HeaderMap::new(),
+                    max_response_bytes: None,
+                    transform_context: None,
+                    is_replicated: None,
+                })
+        } else {
+            Err(RequestBuilderError::UnsupportedHttpMethod {
+                method,
+                supported: HttpRequestConverter::SUPPORTED_HTTP_METHODS.to_vec(),
+            })
+        };
+        Self { parts }
+    }
+
+    /// Create a new builder for a `GET` request.
+    pub fn get(url: impl AsRef<str>) -> Self {
+        Self::new(http::Method::GET, url)
+    }
+
+    /// Create a new builder for a `POST` request.
+    pub fn post(url: impl AsRef<str>) -> Self {
+        Self::new(http::Method::POST, url)
+    }
+
+    /// Create a new builder for a `HEAD` request.
+    pub fn head(url: impl AsRef<str>) -> Self {
+        Self::new(http::Method::HEAD, url)
+    }
+
+    /// Append a query parameter to the URL.
+    pub fn query(self, key: impl Into<String>, value: impl fmt::Display) -> Self {
+        self.and_then(|mut parts| {
+            parts.query.append(key, value);
+            Ok(parts)
+        })
+    }
+
+    /// Append the given query parameters to the URL.
+    pub fn query_params(self, params: impl Into<QueryParams>) -> Self {
+        let params = params.into();
+        self.and_then(|mut parts| {
+            parts.query.extend(params.iter());
+            Ok(parts)
+        })
+    }
+
+    /// Append a header.
+    pub fn header(self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        let (name, value) = (name.as_ref(), value.as_ref());
+        self.and_then(|mut parts| {
+            let invalid_header = |reason: String| RequestBuilderError::InvalidHeader {
+                name: name.to_string(),
+                reason,
+            };
+            let header_name =
+                HeaderName::try_from(name).map_err(|e| invalid_header(e.to_string()))?;
+            let header_value =
+                HeaderValue::try_from(value).map_err(|e| invalid_header(e.to_string()))?;
+            parts.headers.append(header_name, header_value);
+            Ok(parts)
+        })
+    }
+
+    /// Set the maximum number of bytes of the response.
+    ///
+    /// The value must not exceed 2MB (2_000_000B).
+    pub fn max_response_bytes(self, value: u64) -> Self {
+        self.and_then(|mut parts| {
+            let limit = RequestLimits::default().max_response_bytes;
+            if value > limit {
+                return Err(RequestBuilderError::MaxResponseBytesTooLarge { value, limit });
+            }
+            parts.max_response_bytes = Some(value);
+            Ok(parts)
+        })
+    }
+
+    /// Set the transform context, see [`TransformContextRequestExtension`].
+    pub fn transform_context(self, value: TransformContext) -> Self {
+        self.and_then(|mut parts| {
+            parts.transform_context = Some(value);
+            Ok(parts)
+        })
+    }
+
+    /// Set the replication mode, see [`IsReplicatedRequestExtension`].
+    pub fn replicated(self, value: bool) -> Self {
+        self.and_then(|mut parts| {
+            parts.is_replicated = Some(value);
+            Ok(parts)
+        })
+    }
+
+    /// Build the request with the given body.
+    pub fn body<T>(self, body: T) -> Result<http::Request<T>, RequestBuilderError> {
+        let parts = self.parts?;
+        let uri = if parts.query.is_empty() {
+            parts.uri
+        } else {
+            let url = append_query(&parts.uri, &parts.query);
+            url.parse()
+                .map_err(|e: http::uri::InvalidUri| RequestBuilderError::InvalidUrl {
+                    url,
+                    reason: e.to_string(),
+                })?
+        };
+        let mut request = http::Request::new(body);
+        *request.method_mut() = parts.method;
+        *request.uri_mut() = uri;
+        *request.headers_mut() = parts.headers;
+        if let Some(max_response_bytes) = parts.max_response_bytes {
+            request.set_max_response_bytes(max_response_bytes);
+        }
+        if let Some(transform_context) = parts.transform_context {
+            request.set_transform_context(transform_context);
+        }
+        if let Some(is_replicated) = parts.is_replicated {
+            request.set_is_replicated(is_replicated);
+        }
+        Ok(request)
+    }
+
+    /// Build the request with the given JSON body.
+    ///
+    /// The `Content-Type` header is set to `application/json`, unless already present.
+    /// The body is not serialized, which is typically done by a `JsonRequestConverter`
+    /// when the `json` feature is enabled.
+    pub fn json<T>(self, body: T) -> Result<http::Request<T>, RequestBuilderError> {
+        self.and_then(|mut parts| {
+            if !parts.headers.contains_key(CONTENT_TYPE) {
+                parts
+                    .headers
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            }
+            Ok(parts)
+        })
+        .body(body)
+    }
+
+    fn and_then(self, f: impl FnOnce(Parts) -> Result<Parts, RequestBuilderError>) -> Self {
+        Self {
+            parts: self.parts.and_then(f),
+        }
+    }
+}
+
+/// Error returned when building a request with [`RequestBuilder`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum RequestBuilderError {
+    /// HTTP method is not supported by HTTPs outcalls.
+    #[error(
+        "HTTP method `{method}` is not supported, expected one of: {}",
+        supported.iter().map(http::Method::as_str).collect::<Vec<_>>().join(", ")
+    )]
+    UnsupportedHttpMethod {
+        /// The unsupported HTTP method.
+        method: http::Method,
+        /// HTTP methods supported by HTTPs outcalls.
+        supported: Vec<http::Method>,
+    },
+    /// The URL is invalid.
+    #[error("Invalid URL `{url}`: {reason}")]
+    InvalidUrl {
+        /// The invalid URL.
+        url: String,
+        /// Reason for the URL being invalid.
+        reason: String,
+    },
+    /// A header name or value is invalid.
+    #[error("Invalid HTTP header `{name}`: {reason}")]
+    InvalidHeader {
+        /// Header name.
+        name: String,
+        /// Reason for the header being invalid.
+        reason: String,
+    },
+    /// The value of `max_response_bytes` is too large.
+    #[error("Max response bytes of {value} exceeds limit of {limit}")]
+    MaxResponseBytesTooLarge {
+        /// The requested value.
+        value: u64,
+        /// Maximum value.
+        limit: u64,
+    },
+}
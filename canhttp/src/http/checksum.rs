@@ -0,0 +1,245 @@
+use crate::{
+    convert::{CreateResponseFilter, CreateResponseFilterLayer, Filter, FilterResponse},
+    http::{encode_hex, HttpResponse},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::HeaderName;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tower_layer::Layer;
+
+/// Add support for the expected SHA-256 digest of the response body,
+/// see [`VerifyBodyChecksum`].
+pub trait BodyChecksumRequestExtension: Sized {
+    /// Set the expected SHA-256 digest of the response body.
+    fn set_expected_sha256(&mut self, value: [u8; 32]);
+
+    /// Retrieves the expected SHA-256 digest of the response body, if any.
+    fn get_expected_sha256(&self) -> Option<[u8; 32]>;
+
+    /// Convenience method to use the builder pattern.
+    fn expected_sha256(mut self, value: [u8; 32]) -> Self {
+        self.set_expected_sha256(value);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ExpectedSha256Extension(pub [u8; 32]);
+
+impl<T> BodyChecksumRequestExtension for http::Request<T> {
+    fn set_expected_sha256(&mut self, value: [u8; 32]) {
+        let extensions = self.extensions_mut();
+        extensions.insert(ExpectedSha256Extension(value));
+    }
+
+    fn get_expected_sha256(&self) -> Option<[u8; 32]> {
+        self.extensions()
+            .get::<ExpectedSha256Extension>()
+            .map(|e| e.0)
+    }
+}
+
+impl BodyChecksumRequestExtension for http::request::Builder {
+    fn set_expected_sha256(&mut self, value: [u8; 32]) {
+        if let Some(extensions) = self.extensions_mut() {
+            extensions.insert(ExpectedSha256Extension(value));
+        }
+    }
+
+    fn get_expected_sha256(&self) -> Option<[u8; 32]> {
+        self.extensions_ref()
+            .and_then(|extensions| extensions.get::<ExpectedSha256Extension>().map(|e| e.0))
+    }
+}
+
+/// Error returned when verifying the checksum of a response body with [`VerifyBodyChecksum`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum BodyChecksumError {
+    /// The SHA-256 digest of the body does not match the expected one.
+    #[error("SHA-256 digest of response body `{actual}` does not match expected `{expected}`")]
+    Mismatch {
+        /// Hex-encoded expected digest.
+        expected: String,
+        /// Hex-encoded digest of the body.
+        actual: String,
+    },
+    /// The checksum header is neither a base64- nor a hex-encoded SHA-256 digest.
+    #[error("Invalid SHA-256 checksum in header `{header}`: `{value}`")]
+    InvalidChecksumHeader {
+        /// Name of the checksum header.
+        header: String,
+        /// Value of the checksum header.
+        value: String,
+    },
+    /// No expected digest was given, neither in the request nor in the response.
+    #[error("Missing expected SHA-256 digest of response body")]
+    MissingChecksum,
+}
+
+/// Verify the SHA-256 digest of response bodies.
+///
+/// The expected digest is taken from
+/// 1. the request, see [`BodyChecksumRequestExtension`]; or else
+/// 2. the configured response header (e.g. `x-amz-checksum-sha256`), which may be
+///    base64- or hex-encoded.
+///
+/// Responses without an expected digest are accepted, unless a checksum is
+/// [required](VerifyBodyChecksum::required).
+#[derive(Clone, Debug, Default)]
+pub struct VerifyBodyChecksum {
+    header: Option<HeaderName>,
+    required: bool,
+}
+
+impl VerifyBodyChecksum {
+    /// Create a new [`VerifyBodyChecksum`] only considering digests given in requests.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the expected digest from the given response header if the request does not specify one.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// Reject responses without an expected digest.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+}
+
+impl<T> CreateResponseFilter<http::Request<T>, HttpResponse> for VerifyBodyChecksum {
+    type Filter = BodyChecksumFilter;
+    type Error = BodyChecksumError;
+
+    fn create_filter(&self, request: &http::Request<T>) -> Self::Filter {
+        BodyChecksumFilter {
+            expected: request.get_expected_sha256(),
+            header: self.header.clone(),
+            required: self.required,
+        }
+    }
+}
+
+/// Verify the SHA-256 digest of the response body to a given request,
+/// see [`VerifyBodyChecksum`].
+#[derive(Clone, Debug)]
+pub struct BodyChecksumFilter {
+    expected: Option<[u8; 32]>,
+    header: Option<HeaderName>,
+    required: bool,
+}
+
+impl BodyChecksumFilter {
+    fn expected(&self, response: &HttpResponse) -> Result<Option<[u8; 32]>, BodyChecksumError> {
+        if let Some(expected) = self.expected {
+            return Ok(Some(expected));
+        }
+        let Some((header, value)) = self
+            .header
+            .as_ref()
+            .and_then(|header| Some((header, response.headers().get(header)?)))
+        else {
+            return Ok(None);
+        };
+        let invalid = || BodyChecksumError::InvalidChecksumHeader {
+            header: header.to_string(),
+            value: String::from_utf8_lossy(value.as_bytes()).to_string(),
+        };
+        let value = value.to_str().map_err(|_| invalid())?.trim();
+        let digest = match value.len() {
+            64 => decode_hex(value),
+            _ => STANDARD.decode(value).ok(),
+        };
+        digest
+            .and_then(|digest| <[u8; 32]>::try_from(digest).ok())
+            .map(Some)
+            .ok_or_else(invalid)
+    }
+}
+
+impl Filter<HttpResponse> for BodyChecksumFilter {
+    type Error = BodyChecksumError;
+
+    fn filter(&mut self, response: HttpResponse) -> Result<HttpResponse, Self::Error> {
+        let expected = match self.expected(&response)? {
+            Some(expected) => expected,
+            None if self.required => return Err(BodyChecksumError::MissingChecksum),
+            None => return Ok(response),
+        };
+        let actual: [u8; 32] = Sha256::digest(response.body()).into();
+        if actual != expected {
+            return Err(BodyChecksumError::Mismatch {
+                expected: encode_hex(&expected),
+                actual: encode_hex(&actual),
+            });
+        }
+        Ok(response)
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Middleware that verifies the SHA-256 digest of response bodies, see [`VerifyBodyChecksum`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{BodyChecksumError, BodyChecksumLayer, BodyChecksumRequestExtension, HttpRequest, HttpResponse, VerifyBodyChecksum};
+/// use sha2::{Digest, Sha256};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// async fn download(_request: HttpRequest) -> Result<HttpResponse, BoxError> {
+///     Ok(http::Response::new(b"\0asm".to_vec()))
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(BodyChecksumLayer::new(VerifyBodyChecksum::new().required()))
+///     .service_fn(download);
+///
+/// let request = http::Request::get("https://example.com/canister.wasm")
+///     .expected_sha256(Sha256::digest(b"\0asm").into())
+///     .body(vec![])?;
+/// let response = service.ready().await?.call(request).await?;
+/// assert_eq!(response.body(), b"\0asm");
+///
+/// let request = http::Request::get("https://example.com/canister.wasm")
+///     .expected_sha256([0; 32])
+///     .body(vec![])?;
+/// let error = service.ready().await?.call(request).await.unwrap_err();
+/// assert!(matches!(
+///     error.downcast_ref::<BodyChecksumError>(),
+///     Some(BodyChecksumError::Mismatch { .. })
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct BodyChecksumLayer {
+    verify: VerifyBodyChecksum,
+}
+
+impl BodyChecksumLayer {
+    /// Returns a new [`BodyChecksumLayer`] using the given configuration.
+    pub fn new(verify: VerifyBodyChecksum) -> Self {
+        Self { verify }
+    }
+}
+
+impl<S> Layer<S> for BodyChecksumLayer {
+    type Service = FilterResponse<S, VerifyBodyChecksum>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CreateResponseFilterLayer::new(self.verify.clone()).layer(inner)
+    }
+}
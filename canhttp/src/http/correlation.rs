@@ -0,0 +1,227 @@
+use crate::{
+    convert::{ConvertRequest, ConvertRequestLayer, Filter},
+    http::{encode_hex, HttpRequest},
+};
+use http::{HeaderName, HeaderValue};
+use ic_cdk_management_canister::HttpRequestArgs as IcHttpRequest;
+use sha2::{Digest, Sha256};
+use std::{cell::Cell, fmt, rc::Rc};
+use thiserror::Error;
+use tower_layer::Layer;
+
+/// Default header used to send the correlation ID.
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+thread_local! {
+    static COUNTER: Cell<u64> = const { Cell::new(0) };
+}
+
+fn next_counter() -> u64 {
+    COUNTER.with(|counter| {
+        let value = counter.get();
+        counter.set(value.wrapping_add(1));
+        value
+    })
+}
+
+/// Retrieve the correlation ID set by [`AddCorrelationId`].
+///
+/// This is typically used in [`ObservabilityLayer`](crate::observability::ObservabilityLayer)
+/// hooks to correlate logs with provider-side logs.
+pub trait CorrelationIdRequestExtension {
+    /// Returns the correlation ID of the request, if any.
+    fn get_correlation_id(&self) -> Option<&str>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CorrelationIdExtension(pub String);
+
+impl<T> CorrelationIdRequestExtension for http::Request<T> {
+    fn get_correlation_id(&self) -> Option<&str> {
+        self.extensions()
+            .get::<CorrelationIdExtension>()
+            .map(|e| e.0.as_str())
+    }
+}
+
+/// Since [`IcHttpRequest`] has no extensions, the correlation ID is read from the
+/// [`X_REQUEST_ID`] header.
+impl CorrelationIdRequestExtension for IcHttpRequest {
+    fn get_correlation_id(&self) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(X_REQUEST_ID.as_str()))
+            .map(|header| header.value.as_str())
+    }
+}
+
+/// Error returned when the correlation ID header cannot be set.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum CorrelationIdError {
+    /// The generated ID is not a valid header value, see [`AddCorrelationId::with_generator`].
+    #[error("Invalid correlation ID `{id}`: {reason}")]
+    InvalidId {
+        /// The invalid ID.
+        id: String,
+        /// Reason for the ID being invalid.
+        reason: String,
+    },
+}
+
+/// Set a correlation ID on requests, so that logs of the canister can be correlated
+/// with logs of the provider.
+///
+/// The ID is sent in the [`X_REQUEST_ID`] header (configurable) and is made available to
+/// further middlewares with [`CorrelationIdRequestExtension`].
+/// If the request already has that header, its value is used as correlation ID.
+///
+/// Note that the ID is generated deterministically, so that all replicas
+/// send the same header in replicated HTTPs outcalls.
+#[derive(Clone)]
+pub struct AddCorrelationId {
+    header: HeaderName,
+    generator: Rc<dyn Fn() -> String>,
+}
+
+impl AddCorrelationId {
+    /// Generate IDs from the current time (see [`ic_cdk::api::time`]) and a per-canister
+    /// counter, e.g. `18a2b3c4d5e6f708-0000002a`.
+    pub fn new() -> Self {
+        Self::with_generator(|| format!("{:016x}-{:08x}", ic_cdk::api::time(), next_counter()))
+    }
+
+    /// Generate unpredictable IDs by hashing the given seed with a per-canister counter.
+    ///
+    /// The seed would typically be obtained from
+    /// [`raw_rand`](https://docs.rs/ic-cdk-management-canister/latest/ic_cdk_management_canister/fn.raw_rand.html).
+    ///
+    /// The counter is kept on the heap and therefore restarts from zero when the canister is
+    /// upgraded, so that the same seed generates the same sequence of IDs again. Use a new seed
+    /// after each upgrade, e.g. obtained in a timer set in the `post_upgrade` hook, if IDs must
+    /// be unique over the lifetime of the canister.
+    pub fn with_seed(seed: [u8; 32]) -> Self {
+        Self::with_generator(move || {
+            let mut hasher = Sha256::new();
+            hasher.update(seed);
+            hasher.update(next_counter().to_be_bytes());
+            encode_hex(&hasher.finalize()[..16])
+        })
+    }
+
+    /// Generate IDs with the given function.
+    ///
+    /// The generated IDs must be valid header values, otherwise requests are rejected with
+    /// [`CorrelationIdError::InvalidId`].
+    pub fn with_generator(generator: impl Fn() -> String + 'static) -> Self {
+        Self {
+            header: X_REQUEST_ID,
+            generator: Rc::new(generator),
+        }
+    }
+
+    /// Use another header than [`X_REQUEST_ID`], e.g. `X-Correlation-Id`.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+impl Default for AddCorrelationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AddCorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AddCorrelationId")
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Filter<HttpRequest> for AddCorrelationId {
+    type Error = CorrelationIdError;
+
+    fn filter(&mut self, mut request: HttpRequest) -> Result<HttpRequest, Self::Error> {
+        let existing = request
+            .headers()
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let id = (self.generator)();
+                let value = HeaderValue::try_from(id.as_str()).map_err(|e| {
+                    CorrelationIdError::InvalidId {
+                        id: id.clone(),
+                        reason: e.to_string(),
+                    }
+                })?;
+                request.headers_mut().insert(self.header.clone(), value);
+                id
+            }
+        };
+        request.extensions_mut().insert(CorrelationIdExtension(id));
+        Ok(request)
+    }
+}
+
+/// Middleware that sets a correlation ID on requests, see [`AddCorrelationId`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{
+///     http::{AddCorrelationId, CorrelationIdLayer, CorrelationIdRequestExtension, HttpRequest},
+///     observability::ObservabilityLayer,
+/// };
+/// use std::cell::RefCell;
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// thread_local! {
+///     static LOGS: RefCell<Vec<String>> = RefCell::default();
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(CorrelationIdLayer::new(AddCorrelationId::with_seed([42; 32])))
+///     .layer(
+///         ObservabilityLayer::new()
+///             .on_request(|request: &HttpRequest| request.get_correlation_id().unwrap().to_string())
+///             .on_response(|id: String, _response: &HttpRequest| {
+///                 LOGS.with_borrow_mut(|logs| logs.push(format!("[{id}] OK")));
+///             }),
+///     )
+///     .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+///
+/// let request = http::Request::post("https://example.com").body(vec![])?;
+/// let request = service.ready().await?.call(request).await?;
+///
+/// let id = request.headers()["x-request-id"].to_str()?;
+/// assert_eq!(id.len(), 32);
+/// assert_eq!(LOGS.with_borrow(|logs| logs.clone()), vec![format!("[{id}] OK")]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CorrelationIdLayer {
+    add_id: AddCorrelationId,
+}
+
+impl CorrelationIdLayer {
+    /// Returns a new [`CorrelationIdLayer`] using the given configuration.
+    pub fn new(add_id: AddCorrelationId) -> Self {
+        Self { add_id }
+    }
+}
+
+impl<S> Layer<S> for CorrelationIdLayer {
+    type Service = ConvertRequest<S, AddCorrelationId>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.add_id.clone()).layer(inner)
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    client::convert_call_error,
+    http::{
+        encode_hex, take_ready_service, Canonicalization, HttpRequest, Placement, SignatureEncoding,
+    },
+    IcError,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ic_cdk_management_canister::{
+    EcdsaKeyId, SignCallError, SignWithEcdsaArgs, SignWithEcdsaResult,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tower::{BoxError, Service, ServiceExt};
+use tower_layer::Layer;
+
+/// Error returned when a request cannot be signed with threshold ECDSA.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum EcdsaSignatureError {
+    /// The cycles cost of the signature could not be determined, e.g. because the key is unknown.
+    #[error("Cannot determine the cost of signing with threshold ECDSA: {0}")]
+    SignCost(String),
+    /// The call to the management canister failed.
+    #[error(transparent)]
+    Ic(#[from] IcError),
+    /// The URL with the added signature is invalid.
+    #[error("Invalid URL after adding query parameters `{url}`: {reason}")]
+    InvalidUrl {
+        /// The invalid URL.
+        url: String,
+        /// Reason for the URL being invalid.
+        reason: String,
+    },
+}
+
+/// Thin wrapper around [`ic_cdk_management_canister::sign_with_ecdsa`] that implements the
+/// [`tower::Service`] trait.
+///
+/// The cycles required for the signature are computed and attached to the call
+/// to the management canister.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThresholdEcdsaClient;
+
+impl Service<SignWithEcdsaArgs> for ThresholdEcdsaClient {
+    type Response = SignWithEcdsaResult;
+    type Error = EcdsaSignatureError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, args: SignWithEcdsaArgs) -> Self::Future {
+        Box::pin(async move {
+            ic_cdk_management_canister::sign_with_ecdsa(&args)
+                .await
+                .map_err(|e| match e {
+                    SignCallError::SignCostError(e) => EcdsaSignatureError::SignCost(e.to_string()),
+                    SignCallError::CallFailed(e) => {
+                        EcdsaSignatureError::Ic(convert_call_error(e.into()))
+                    }
+                    SignCallError::CandidDecodeFailed(e) => {
+                        EcdsaSignatureError::Ic(convert_call_error(e.into()))
+                    }
+                })
+        })
+    }
+}
+
+/// Sign requests with [threshold ECDSA](https://internetcomputer.org/docs/building-apps/network-features/signatures/t-ecdsa).
+///
+/// Signing a request consists of the following steps:
+/// 1. Compute the SHA-256 hash of the [`Canonicalization`] of the request.
+/// 2. Sign the hash with the threshold ECDSA key of the canister, using the configured
+///    key ID and derivation path.
+/// 3. Add the encoded signature (64 bytes, concatenation of `r` and `s`) to the request
+///    at the configured [`Placement`].
+#[derive(Clone, Debug)]
+pub struct EcdsaSigner {
+    key_id: EcdsaKeyId,
+    derivation_path: Vec<Vec<u8>>,
+    canonicalization: Canonicalization,
+    signature: Placement,
+    encoding: SignatureEncoding,
+}
+
+impl EcdsaSigner {
+    /// Create a new [`EcdsaSigner`] with the given key that signs the given part of
+    /// the request and adds the hex-encoded signature at the given place.
+    pub fn new(
+        key_id: EcdsaKeyId,
+        canonicalization: Canonicalization,
+        signature: Placement,
+    ) -> Self {
+        Self {
+            key_id,
+            derivation_path: Vec::new(),
+            canonicalization,
+            signature,
+            encoding: SignatureEncoding::default(),
+        }
+    }
+
+    /// Set the derivation path of the key used to sign requests.
+    ///
+    /// Defaults to the empty derivation path.
+    pub fn derivation_path(mut self, derivation_path: Vec<Vec<u8>>) -> Self {
+        self.derivation_path = derivation_path;
+        self
+    }
+
+    /// Set the encoding of the signature.
+    pub fn encoding(mut self, encoding: SignatureEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Returns the arguments to the management canister to sign the given request.
+    pub fn sign_args(&self, request: &HttpRequest) -> SignWithEcdsaArgs {
+        SignWithEcdsaArgs {
+            message_hash: Sha256::digest(self.canonicalization.message(request)).to_vec(),
+            derivation_path: self.derivation_path.clone(),
+            key_id: self.key_id.clone(),
+        }
+    }
+
+    fn add_signature(
+        &self,
+        request: &mut HttpRequest,
+        signature: &[u8],
+    ) -> Result<(), EcdsaSignatureError> {
+        let signature = match self.encoding {
+            SignatureEncoding::Hex => encode_hex(signature),
+            SignatureEncoding::Base64 => STANDARD.encode(signature),
+        };
+        self.signature
+            .add_to(request, signature)
+            .map_err(|(url, reason)| EcdsaSignatureError::InvalidUrl { url, reason })
+    }
+}
+
+/// Middleware that signs requests with threshold ECDSA, see [`EcdsaSigner`].
+///
+/// Signing requires an additional call to the management canister, which is made by the
+/// given client (by default [`ThresholdEcdsaClient`]) before the request is forwarded
+/// to the inner service.
+///
+/// This [`Layer`] produces instances of the [`EcdsaSignatureService`] service.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{Canonicalization, EcdsaSignatureLayer, EcdsaSigner, HttpRequest, Placement};
+/// use ic_cdk_management_canister::{EcdsaCurve, EcdsaKeyId, SignWithEcdsaArgs, SignWithEcdsaResult};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let signer = EcdsaSigner::new(
+///     EcdsaKeyId {
+///         curve: EcdsaCurve::Secp256k1,
+///         name: "key_1".to_string(),
+///     },
+///     Canonicalization::Body,
+///     Placement::Header(http::HeaderName::from_static("x-signature")),
+/// );
+///
+/// // Use `EcdsaSignatureLayer::new(signer)` to sign with the management canister.
+/// let client = tower::service_fn(|_args: SignWithEcdsaArgs| async move {
+///     Ok::<_, BoxError>(SignWithEcdsaResult { signature: vec![0xab; 64] })
+/// });
+///
+/// let mut service = ServiceBuilder::new()
+///     .layer(EcdsaSignatureLayer::with_client(signer, client))
+///     .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+///
+/// let request = http::Request::post("https://example.com").body(b"{}".to_vec())?;
+/// let request = service.ready().await?.call(request).await?;
+///
+/// assert_eq!(request.headers()["x-signature"], "ab".repeat(64));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Layer`]: tower::Layer
+#[derive(Clone, Debug)]
+pub struct EcdsaSignatureLayer<C = ThresholdEcdsaClient> {
+    signer: EcdsaSigner,
+    client: C,
+}
+
+impl EcdsaSignatureLayer {
+    /// Returns a new [`EcdsaSignatureLayer`] signing requests with the management canister.
+    pub fn new(signer: EcdsaSigner) -> Self {
+        Self::with_client(signer, ThresholdEcdsaClient)
+    }
+}
+
+impl<C> EcdsaSignatureLayer<C> {
+    /// Returns a new [`EcdsaSignatureLayer`] signing requests with the given client.
+    pub fn with_client(signer: EcdsaSigner, client: C) -> Self {
+        Self { signer, client }
+    }
+}
+
+impl<S, C: Clone> Layer<S> for EcdsaSignatureLayer<C> {
+    type Service = EcdsaSignatureService<S, C>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        EcdsaSignatureService {
+            inner,
+            signer: self.signer.clone(),
+            client: self.client.clone(),
+        }
+    }
+}
+
+/// Sign requests with threshold ECDSA before forwarding them to the inner service.
+#[derive(Clone, Debug)]
+pub struct EcdsaSignatureService<S, C> {
+    inner: S,
+    signer: EcdsaSigner,
+    client: C,
+}
+
+impl<S, C> Service<HttpRequest> for EcdsaSignatureService<S, C>
+where
+    S: Service<HttpRequest> + Clone + 'static,
+    S::Error: Into<BoxError>,
+    C: Service<SignWithEcdsaArgs, Response = SignWithEcdsaResult> + Clone + 'static,
+    C::Error: Into<BoxError>,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut request: HttpRequest) -> Self::Future {
+        let mut inner = take_ready_service(&mut self.inner);
+        let signer = self.signer.clone();
+        let client = self.client.clone();
+        Box::pin(async move {
+            let args = signer.sign_args(&request);
+            let result = client.oneshot(args).await.map_err(Into::into)?;
+            signer.add_signature(&mut request, &result.signature)?;
+            inner.call(request).await.map_err(Into::into)
+        })
+    }
+}
@@ -0,0 +1,445 @@
+//! Middleware to make (read-only) calls to canisters through an [HTTP gateway] of the
+//! Internet Computer.
+//!
+//! This is useful when a canister cannot directly call another canister, e.g. because the
+//! target canister is deployed on a different network.
+//! The call is sent as an HTTPs outcall to the [query endpoint] of the HTTP gateway:
+//! * [`CandidQueryRequestConverter`] encodes a [`CandidQueryRequest`] into a CBOR envelope.
+//! * [`CandidReplyConverter`] decodes the CBOR reply and the Candid value it contains.
+//!
+//! ```text
+//!                 │                     ▲
+//! CandidQueryRequest                    │T
+//!               ┌─┴─────────────────────┴───┐
+//!               │   CandidReplyConverter    │
+//!               └─┬─────────────────────▲───┘
+//!                 │                     │
+//!               ┌─▼─────────────────────┴───┐
+//!               │CandidQueryRequestConverter│
+//!               └─┬─────────────────────┬───┘
+//!      HttpRequest│                     │HttpResponse
+//!                 ▼                     │
+//!               ┌─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┐
+//!               │          SERVICE          │
+//!               └─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┘
+//! ```
+//!
+//! <div class="warning">
+//! Queries are sent with the anonymous identity and the certificate of the reply is
+//! <strong>not</strong> verified, so the reply must be trusted to the same extent as the
+//! HTTP gateway itself.
+//! </div>
+//!
+//! The reply to a query is signed by the replica that executed it, and these node signatures
+//! contain a timestamp. Since each node of the subnet receives a different reply when the HTTPs
+//! outcall is replicated, the replies cannot reach consensus unless the signatures are removed.
+//! The canister must therefore expose a transform function calling [`transform_query_response`],
+//! and register it with [`CandidGatewayLayer::with_transform`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::{
+//!     http::gateway::{transform_query_response, CandidGatewayLayer, CandidQueryRequest},
+//!     Client,
+//! };
+//! use candid::Principal;
+//! use ic_cdk_management_canister::{
+//!     transform_context_from_query, HttpRequestResult, TransformArgs,
+//! };
+//! use tower::{BoxError, Service, ServiceBuilder};
+//!
+//! #[ic_cdk::query(hidden = true)]
+//! fn transform_gateway_response(args: TransformArgs) -> HttpRequestResult {
+//!     transform_query_response(args.response)
+//! }
+//!
+//! fn client() -> impl Service<CandidQueryRequest, Response = String, Error = BoxError> {
+//!     ServiceBuilder::new()
+//!         .layer(
+//!             CandidGatewayLayer::<String>::new("https://icp-api.io").with_transform(
+//!                 transform_context_from_query("transform_gateway_response".to_string(), vec![]),
+//!             ),
+//!         )
+//!         .layer(canhttp::http::HttpConversionLayer)
+//!         .service(Client::new_with_box_error())
+//! }
+//!
+//! let ledger = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+//! let request = CandidQueryRequest::new(ledger, "name", ()).unwrap();
+//! ```
+//!
+//! [HTTP gateway]: https://internetcomputer.org/docs/references/http-gateway-protocol-spec
+//! [query endpoint]: https://internetcomputer.org/docs/references/ic-interface-spec#http-query
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    convert::{
+        Convert, ConvertRequest, ConvertRequestLayer, ConvertResponse, ConvertResponseLayer,
+    },
+    http::{HttpRequest, HttpResponse},
+    TransformContextRequestExtension,
+};
+use candid::{utils::ArgumentEncoder, CandidType, Principal};
+use ciborium::Value;
+use http::{header::CONTENT_TYPE, HeaderValue};
+use ic_cdk_management_canister::{HttpRequestResult as IcHttpResponse, TransformContext};
+use serde::de::DeserializeOwned;
+use std::{marker::PhantomData, time::Duration};
+use thiserror::Error;
+use tower_layer::Layer;
+
+// Self-described CBOR, see https://www.rfc-editor.org/rfc/rfc8949.html#name-self-described-cbor
+const SELF_DESCRIBED_CBOR_TAG: u64 = 55799;
+// The anonymous principal, see https://internetcomputer.org/docs/references/ic-interface-spec#principal
+const ANONYMOUS_PRINCIPAL: [u8; 1] = [0x04];
+// Requests expire after at most 5 minutes, see
+// https://internetcomputer.org/docs/references/ic-interface-spec#authentication
+const DEFAULT_INGRESS_EXPIRY: Duration = Duration::from_secs(4 * 60);
+
+/// A query call to a canister method with Candid-encoded arguments.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CandidQueryRequest {
+    canister_id: Principal,
+    method: String,
+    arg: Vec<u8>,
+    ingress_expiry: Option<u64>,
+}
+
+impl CandidQueryRequest {
+    /// Create a new query to the given canister method, by encoding the arguments with Candid.
+    pub fn new<In: ArgumentEncoder>(
+        canister_id: Principal,
+        method: impl Into<String>,
+        args: In,
+    ) -> Result<Self, CandidGatewayError> {
+        let arg = candid::utils::encode_args(args)
+            .map_err(|e| CandidGatewayError::InvalidCandid(e.to_string()))?;
+        Ok(Self::from_raw_arg(canister_id, method, arg))
+    }
+
+    /// Create a new query to the given canister method with already Candid-encoded arguments.
+    pub fn from_raw_arg(canister_id: Principal, method: impl Into<String>, arg: Vec<u8>) -> Self {
+        Self {
+            canister_id,
+            method: method.into(),
+            arg,
+            ingress_expiry: None,
+        }
+    }
+
+    /// Set the expiry time of the request, in nanoseconds since the Unix epoch.
+    ///
+    /// If not set, the request expires 4 minutes after the current
+    /// [canister time](ic_cdk::api::time).
+    pub fn with_ingress_expiry(mut self, ingress_expiry: u64) -> Self {
+        self.ingress_expiry = Some(ingress_expiry);
+        self
+    }
+
+    /// Returns the ID of the called canister.
+    pub fn canister_id(&self) -> &Principal {
+        &self.canister_id
+    }
+
+    /// Returns the name of the called method.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+}
+
+/// Error returned when calling a canister through an HTTP gateway.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum CandidGatewayError {
+    /// Candid arguments or reply could not be encoded or decoded.
+    #[error("Invalid Candid: {0}")]
+    InvalidCandid(String),
+    /// The gateway responded with a non-successful HTTP status.
+    #[error("HTTP gateway responded with status {status}: {body}")]
+    UnsuccessfulResponse {
+        /// Response status code.
+        status: u16,
+        /// Response body.
+        body: String,
+    },
+    /// The response body is not a valid query response.
+    #[error("Invalid query response: {0}")]
+    InvalidResponse(String),
+    /// The call was rejected.
+    #[error("Query rejected (code {reject_code}): {reject_message}")]
+    Rejected {
+        /// Rejection code as specified [here](https://internetcomputer.org/docs/current/references/ic-interface-spec#reject-codes)
+        reject_code: u64,
+        /// Associated helper message.
+        reject_message: String,
+    },
+}
+
+/// Convert requests of type [`CandidQueryRequest`] into [`HttpRequest`] to the
+/// [query endpoint](https://internetcomputer.org/docs/references/ic-interface-spec#http-query)
+/// of an HTTP gateway.
+#[derive(Clone, Debug)]
+pub struct CandidQueryRequestConverter {
+    gateway_url: String,
+    transform: Option<TransformContext>,
+}
+
+impl CandidQueryRequestConverter {
+    /// Create a new [`CandidQueryRequestConverter`] for the given gateway, e.g. `https://icp-api.io`.
+    pub fn new(gateway_url: impl Into<String>) -> Self {
+        Self {
+            gateway_url: gateway_url.into().trim_end_matches('/').to_string(),
+            transform: None,
+        }
+    }
+
+    /// Set the transform function of each request, which should call
+    /// [`transform_query_response`], see the [module docs](crate::http::gateway).
+    pub fn with_transform(mut self, transform: TransformContext) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+}
+
+impl Convert<CandidQueryRequest> for CandidQueryRequestConverter {
+    type Output = HttpRequest;
+    type Error = CandidGatewayError;
+
+    fn try_convert(&mut self, request: CandidQueryRequest) -> Result<Self::Output, Self::Error> {
+        let ingress_expiry = request.ingress_expiry.unwrap_or_else(|| {
+            ic_cdk::api::time().saturating_add(DEFAULT_INGRESS_EXPIRY.as_nanos() as u64)
+        });
+        let url = format!(
+            "{}/api/v2/canister/{}/query",
+            self.gateway_url,
+            request.canister_id.to_text()
+        );
+        let content = Value::Map(vec![
+            (text("request_type"), text("query")),
+            (text("sender"), Value::Bytes(ANONYMOUS_PRINCIPAL.to_vec())),
+            (
+                text("canister_id"),
+                Value::Bytes(request.canister_id.as_slice().to_vec()),
+            ),
+            (text("method_name"), text(&request.method)),
+            (text("arg"), Value::Bytes(request.arg)),
+            (
+                text("ingress_expiry"),
+                Value::Integer(ingress_expiry.into()),
+            ),
+        ]);
+        let envelope = Value::Tag(
+            SELF_DESCRIBED_CBOR_TAG,
+            Box::new(Value::Map(vec![(text("content"), content)])),
+        );
+        let mut body = Vec::new();
+        ciborium::into_writer(&envelope, &mut body).expect("BUG: failed to encode CBOR envelope");
+        let mut request = http::Request::post(url)
+            .header(CONTENT_TYPE, HeaderValue::from_static("application/cbor"))
+            .body(body)
+            .expect("BUG: invalid HTTP gateway request");
+        if let Some(transform) = &self.transform {
+            request.set_transform_context(transform.clone());
+        }
+        Ok(request)
+    }
+}
+
+/// Transform the response of the query endpoint of an HTTP gateway so that it is the same on
+/// all nodes of the subnet, by removing its headers and the node signatures of the reply.
+///
+/// Only the status of the query and the reply or the rejection are kept. Bodies that are not
+/// a valid query response, e.g. for a non-successful HTTP status, are kept as is.
+///
+/// This function is meant to be called by the transform function of the canister, see the
+/// [module docs](crate::http::gateway).
+pub fn transform_query_response(response: IcHttpResponse) -> IcHttpResponse {
+    const DETERMINISTIC_FIELDS: [&str; 5] = [
+        "status",
+        "reply",
+        "reject_code",
+        "reject_message",
+        "error_code",
+    ];
+
+    let body = match ciborium::from_reader::<Value, _>(response.body.as_slice()) {
+        Ok(Value::Tag(SELF_DESCRIBED_CBOR_TAG, value)) => match *value {
+            Value::Map(fields) => {
+                let fields = fields
+                    .into_iter()
+                    .filter(|(key, _)| {
+                        key.as_text()
+                            .is_some_and(|key| DETERMINISTIC_FIELDS.contains(&key))
+                    })
+                    .collect();
+                let value = Value::Tag(SELF_DESCRIBED_CBOR_TAG, Box::new(Value::Map(fields)));
+                let mut body = Vec::new();
+                ciborium::into_writer(&value, &mut body)
+                    .expect("BUG: failed to encode CBOR query response");
+                body
+            }
+            _ => response.body,
+        },
+        _ => response.body,
+    };
+    IcHttpResponse {
+        status: response.status,
+        headers: vec![],
+        body,
+    }
+}
+
+/// Convert responses of type [`HttpResponse`] from the
+/// [query endpoint](https://internetcomputer.org/docs/references/ic-interface-spec#http-query)
+/// of an HTTP gateway into the Candid-decoded reply of type `T`.
+#[derive(Debug)]
+pub struct CandidReplyConverter<T> {
+    _marker: PhantomData<T>,
+}
+
+impl<T> CandidReplyConverter<T> {
+    /// Create a new instance of [`CandidReplyConverter`].
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+// #[derive(Clone)] would otherwise introduce a bound T: Clone, which is not needed.
+impl<T> Clone for CandidReplyConverter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            _marker: self._marker,
+        }
+    }
+}
+
+impl<T> Default for CandidReplyConverter<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Convert<HttpResponse> for CandidReplyConverter<T>
+where
+    T: CandidType + DeserializeOwned,
+{
+    type Output = T;
+    type Error = CandidGatewayError;
+
+    fn try_convert(&mut self, response: HttpResponse) -> Result<Self::Output, Self::Error> {
+        if !response.status().is_success() {
+            return Err(CandidGatewayError::UnsuccessfulResponse {
+                status: response.status().as_u16(),
+                body: String::from_utf8_lossy(response.body()).to_string(),
+            });
+        }
+        let reply = parse_query_response(response.body())?;
+        candid::decode_one(&reply).map_err(|e| CandidGatewayError::InvalidCandid(e.to_string()))
+    }
+}
+
+fn parse_query_response(body: &[u8]) -> Result<Vec<u8>, CandidGatewayError> {
+    fn invalid(reason: &str) -> CandidGatewayError {
+        CandidGatewayError::InvalidResponse(reason.to_string())
+    }
+
+    let mut value: Value = ciborium::from_reader(body)
+        .map_err(|e| CandidGatewayError::InvalidResponse(e.to_string()))?;
+    while let Value::Tag(_, inner) = value {
+        value = *inner;
+    }
+    let fields = value.into_map().map_err(|_| invalid("expected a map"))?;
+    let mut status = None;
+    let mut reply = None;
+    let mut reject_code = None;
+    let mut reject_message = None;
+    for (key, value) in fields {
+        match key.as_text() {
+            Some("status") => status = value.into_text().ok(),
+            Some("reply") => reply = Some(value),
+            Some("reject_code") => {
+                reject_code = value.as_integer().and_then(|code| u64::try_from(code).ok())
+            }
+            Some("reject_message") => reject_message = value.into_text().ok(),
+            _ => {}
+        }
+    }
+    match status.as_deref() {
+        Some("replied") => reply
+            .and_then(|reply| reply.into_map().ok())
+            .and_then(|reply| {
+                reply
+                    .into_iter()
+                    .find(|(key, _)| key.as_text() == Some("arg"))
+                    .and_then(|(_, arg)| arg.into_bytes().ok())
+            })
+            .ok_or_else(|| invalid("missing reply argument")),
+        Some("rejected") => Err(CandidGatewayError::Rejected {
+            reject_code: reject_code.ok_or_else(|| invalid("missing reject code"))?,
+            reject_message: reject_message.unwrap_or_default(),
+        }),
+        _ => Err(invalid("unknown status")),
+    }
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+/// Middleware that combines [`CandidQueryRequestConverter`] to convert requests
+/// and [`CandidReplyConverter`] to convert responses to a [`Service`].
+///
+/// See the [module docs](crate::http::gateway) for an example.
+///
+/// [`Service`]: tower::Service
+#[derive(Debug)]
+pub struct CandidGatewayLayer<T> {
+    converter: CandidQueryRequestConverter,
+    _marker: PhantomData<T>,
+}
+
+impl<T> CandidGatewayLayer<T> {
+    /// Returns a new [`CandidGatewayLayer`] for the given gateway, e.g. `https://icp-api.io`.
+    pub fn new(gateway_url: impl Into<String>) -> Self {
+        Self {
+            converter: CandidQueryRequestConverter::new(gateway_url),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set the transform function of each request, see
+    /// [`CandidQueryRequestConverter::with_transform`].
+    pub fn with_transform(mut self, transform: TransformContext) -> Self {
+        self.converter = self.converter.with_transform(transform);
+        self
+    }
+}
+
+impl<T> Clone for CandidGatewayLayer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            converter: self.converter.clone(),
+            _marker: self._marker,
+        }
+    }
+}
+
+impl<S, T> Layer<S> for CandidGatewayLayer<T>
+where
+    T: CandidType + DeserializeOwned,
+{
+    type Service =
+        ConvertResponse<ConvertRequest<S, CandidQueryRequestConverter>, CandidReplyConverter<T>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let stack = tower_layer::Stack::new(
+            ConvertRequestLayer::new(self.converter.clone()),
+            ConvertResponseLayer::new(CandidReplyConverter::<T>::new()),
+        );
+        stack.layer(inner)
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{
+    convert::Convert,
+    http::gateway::{
+        transform_query_response, CandidGatewayError, CandidQueryRequest,
+        CandidQueryRequestConverter, CandidReplyConverter,
+    },
+    TransformContextRequestExtension,
+};
+use assert_matches::assert_matches;
+use candid::Principal;
+use ciborium::Value;
+use http::StatusCode;
+use ic_cdk_management_canister::{
+    HttpHeader as IcHttpHeader, HttpRequestResult as IcHttpResponse, TransformContext,
+    TransformFunc,
+};
+
+const CANISTER_ID: Principal = Principal::from_slice(&[0, 0, 0, 0, 0, 0, 0, 2, 1, 1]);
+
+#[test]
+fn should_convert_query_request() {
+    let request = CandidQueryRequest::new(CANISTER_ID, "greet", ("world".to_string(),))
+        .unwrap()
+        .with_ingress_expiry(1_000);
+    let expected_arg = candid::utils::encode_args(("world".to_string(),)).unwrap();
+
+    let http_request = CandidQueryRequestConverter::new("https://icp-api.io/")
+        .try_convert(request)
+        .unwrap();
+
+    assert_eq!(http_request.method(), http::Method::POST);
+    assert_eq!(
+        http_request.uri().to_string(),
+        format!(
+            "https://icp-api.io/api/v2/canister/{}/query",
+            CANISTER_ID.to_text()
+        )
+    );
+    assert_eq!(
+        http_request
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .unwrap(),
+        "application/cbor"
+    );
+
+    let envelope: Value = ciborium::from_reader(http_request.body().as_slice()).unwrap();
+    let Value::Tag(55799, envelope) = envelope else {
+        panic!("expected self-described CBOR, got {envelope:?}");
+    };
+    let content = field(*envelope, "content");
+    assert_eq!(
+        field(content.clone(), "request_type"),
+        Value::Text("query".to_string())
+    );
+    assert_eq!(field(content.clone(), "sender"), Value::Bytes(vec![4]));
+    assert_eq!(
+        field(content.clone(), "canister_id"),
+        Value::Bytes(CANISTER_ID.as_slice().to_vec())
+    );
+    assert_eq!(
+        field(content.clone(), "method_name"),
+        Value::Text("greet".to_string())
+    );
+    assert_eq!(field(content.clone(), "arg"), Value::Bytes(expected_arg));
+    assert_eq!(
+        field(content, "ingress_expiry"),
+        Value::Integer(1_000_u64.into())
+    );
+}
+
+#[test]
+fn should_decode_replied_response() {
+    let arg = candid::encode_one("Hello, world!".to_string()).unwrap();
+    let response = cbor_response(
+        StatusCode::OK,
+        Value::Map(vec![
+            (text("status"), text("replied")),
+            (
+                text("reply"),
+                Value::Map(vec![(text("arg"), Value::Bytes(arg))]),
+            ),
+        ]),
+    );
+
+    let reply = CandidReplyConverter::<String>::new()
+        .try_convert(response)
+        .unwrap();
+
+    assert_eq!(reply, "Hello, world!");
+}
+
+#[test]
+fn should_return_error_when_query_rejected() {
+    let response = cbor_response(
+        StatusCode::OK,
+        Value::Map(vec![
+            (text("status"), text("rejected")),
+            (text("reject_code"), Value::Integer(3.into())),
+            (text("reject_message"), text("Canister has no query method")),
+        ]),
+    );
+
+    let result = CandidReplyConverter::<String>::new().try_convert(response);
+
+    assert_eq!(
+        result,
+        Err(CandidGatewayError::Rejected {
+            reject_code: 3,
+            reject_message: "Canister has no query method".to_string(),
+        })
+    );
+}
+
+#[test]
+fn should_set_transform_context() {
+    let request = CandidQueryRequest::new(CANISTER_ID, "greet", ())
+        .unwrap()
+        .with_ingress_expiry(1_000);
+    let transform = TransformContext {
+        function: TransformFunc::new(CANISTER_ID, "transform".to_string()),
+        context: vec![],
+    };
+
+    let http_request = CandidQueryRequestConverter::new("https://icp-api.io")
+        .with_transform(transform.clone())
+        .try_convert(request)
+        .unwrap();
+
+    assert_eq!(http_request.get_transform_context(), Some(&transform));
+}
+
+#[test]
+fn should_remove_node_signatures_from_query_response() {
+    let arg = candid::encode_one("Hello, world!".to_string()).unwrap();
+    let response_from_node = |node: u8, timestamp: u64| {
+        let value = Value::Map(vec![
+            (text("status"), text("replied")),
+            (
+                text("reply"),
+                Value::Map(vec![(text("arg"), Value::Bytes(arg.clone()))]),
+            ),
+            (
+                text("signatures"),
+                Value::Array(vec![Value::Map(vec![
+                    (text("timestamp"), Value::Integer(timestamp.into())),
+                    (text("signature"), Value::Bytes(vec![node; 64])),
+                    (text("identity"), Value::Bytes(vec![node; 29])),
+                ])]),
+            ),
+        ]);
+        IcHttpResponse {
+            status: 200_u16.into(),
+            headers: vec![IcHttpHeader {
+                name: "date".to_string(),
+                value: format!("{timestamp}"),
+            }],
+            body: cbor_response(StatusCode::OK, value).into_body(),
+        }
+    };
+
+    let transformed = transform_query_response(response_from_node(1, 1_000));
+
+    assert_eq!(
+        transform_query_response(response_from_node(2, 2_000)),
+        transformed
+    );
+    assert!(transformed.headers.is_empty());
+    let reply = CandidReplyConverter::<String>::new()
+        .try_convert(http::Response::new(transformed.body))
+        .unwrap();
+    assert_eq!(reply, "Hello, world!");
+
+    let not_cbor = IcHttpResponse {
+        status: 503_u16.into(),
+        headers: vec![],
+        body: b"service unavailable".to_vec(),
+    };
+    assert_eq!(transform_query_response(not_cbor.clone()), not_cbor);
+}
+
+#[test]
+fn should_return_error_on_unsuccessful_response() {
+    let response = http::Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .body(b"invalid request".to_vec())
+        .unwrap();
+
+    let result = CandidReplyConverter::<String>::new().try_convert(response);
+
+    assert_eq!(
+        result,
+        Err(CandidGatewayError::UnsuccessfulResponse {
+            status: 400,
+            body: "invalid request".to_string(),
+        })
+    );
+}
+
+#[test]
+fn should_return_error_on_invalid_response() {
+    let not_cbor = http::Response::builder()
+        .status(StatusCode::OK)
+        .body(vec![0xff, 0xff])
+        .unwrap();
+    assert_matches!(
+        CandidReplyConverter::<String>::new().try_convert(not_cbor),
+        Err(CandidGatewayError::InvalidResponse(_))
+    );
+
+    let unknown_status = cbor_response(
+        StatusCode::OK,
+        Value::Map(vec![(text("status"), text("processing"))]),
+    );
+    assert_matches!(
+        CandidReplyConverter::<String>::new().try_convert(unknown_status),
+        Err(CandidGatewayError::InvalidResponse(_))
+    );
+}
+
+fn cbor_response(status: StatusCode, value: Value) -> crate::http::HttpResponse {
+    let mut body = Vec::new();
+    ciborium::into_writer(&Value::Tag(55799, Box::new(value)), &mut body).unwrap();
+    http::Response::builder().status(status).body(body).unwrap()
+}
+
+fn field(map: Value, name: &str) -> Value {
+    map.into_map()
+        .unwrap()
+        .into_iter()
+        .find(|(key, _)| key.as_text() == Some(name))
+        .map(|(_, value)| value)
+        .unwrap_or_else(|| panic!("missing field {name}"))
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
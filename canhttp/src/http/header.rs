@@ -0,0 +1,374 @@
+use crate::convert::{Convert, ConvertRequest, ConvertRequestLayer};
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+use std::{convert::Infallible, fmt, str::FromStr, time::Duration};
+use thiserror::Error;
+use tower_layer::Layer;
+
+/// Error returned when a header value cannot be parsed into the expected type.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+#[error("HTTP header `{name}` is invalid: {reason}")]
+pub struct InvalidHeaderError {
+    /// Header name.
+    pub name: HeaderName,
+    /// Reason for the header value being invalid.
+    pub reason: String,
+}
+
+/// Typed access to common response headers.
+///
+/// Each method returns `Ok(None)` if the header is absent and an [`InvalidHeaderError`] if it
+/// is present but cannot be parsed. If a header is repeated, only the first value is considered.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{HeaderResponseExtension, RetryAfter};
+/// use std::time::Duration;
+///
+/// let response = http::Response::builder()
+///     .status(503)
+///     .header("Retry-After", "120")
+///     .header("Content-Type", "application/json; charset=UTF-8")
+///     .header("Content-Length", "2")
+///     .body(b"{}".to_vec())
+///     .unwrap();
+///
+/// assert_eq!(
+///     response.retry_after(),
+///     Ok(Some(RetryAfter::Delay(Duration::from_secs(120))))
+/// );
+/// assert_eq!(response.content_length(), Ok(Some(2)));
+/// let content_type = response.content_type().unwrap().unwrap();
+/// assert_eq!(content_type.essence(), "application/json");
+/// assert_eq!(content_type.charset(), Some("UTF-8"));
+/// ```
+pub trait HeaderResponseExtension {
+    /// Returns the headers of the response.
+    fn header_map(&self) -> &HeaderMap;
+
+    /// Parse the `Retry-After` header, given either as a number of seconds or as an HTTP date.
+    fn retry_after(&self) -> Result<Option<RetryAfter>, InvalidHeaderError> {
+        parse_header(self.header_map(), header::RETRY_AFTER, |value| {
+            if let Ok(seconds) = value.parse::<u64>() {
+                return Ok(RetryAfter::Delay(Duration::from_secs(seconds)));
+            }
+            parse_http_date(value)
+                .map(RetryAfter::Date)
+                .ok_or_else(|| "expected a number of seconds or an HTTP date".to_string())
+        })
+    }
+
+    /// Parse the `Content-Length` header.
+    fn content_length(&self) -> Result<Option<u64>, InvalidHeaderError> {
+        parse_header(self.header_map(), header::CONTENT_LENGTH, |value| {
+            value.parse::<u64>().map_err(|e| e.to_string())
+        })
+    }
+
+    /// Parse the `Content-Type` header.
+    fn content_type(&self) -> Result<Option<MediaType>, InvalidHeaderError> {
+        parse_header(self.header_map(), header::CONTENT_TYPE, MediaType::from_str)
+    }
+}
+
+impl<T> HeaderResponseExtension for http::Response<T> {
+    fn header_map(&self) -> &HeaderMap {
+        self.headers()
+    }
+}
+
+impl HeaderResponseExtension for HeaderMap {
+    fn header_map(&self) -> &HeaderMap {
+        self
+    }
+}
+
+fn parse_header<T>(
+    headers: &HeaderMap,
+    name: HeaderName,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<Option<T>, InvalidHeaderError> {
+    let Some(value) = headers.get(&name) else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .map_err(|e| e.to_string())
+        .and_then(|value| parse(value.trim()))
+        .map(Some)
+        .map_err(|reason| InvalidHeaderError { name, reason })
+}
+
+/// Value of the [`Retry-After`] header.
+///
+/// [`Retry-After`]: https://www.rfc-editor.org/rfc/rfc9110#name-retry-after
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RetryAfter {
+    /// Delay after which the request can be retried.
+    Delay(Duration),
+    /// Date after which the request can be retried, as a duration since the Unix epoch.
+    Date(Duration),
+}
+
+impl RetryAfter {
+    /// Returns the delay to wait before retrying, given the current time as a duration since
+    /// the Unix epoch, e.g. from [`ic_cdk::api::time`].
+    ///
+    /// A date in the past results in a zero delay.
+    pub fn delay(&self, now: Duration) -> Duration {
+        match self {
+            RetryAfter::Delay(delay) => *delay,
+            RetryAfter::Date(date) => date.saturating_sub(now),
+        }
+    }
+}
+
+/// A media type, as found in the [`Content-Type`] header, e.g. `text/html; charset=utf-8`.
+///
+/// Type and subtype are lower-cased, while parameter values are kept as is.
+///
+/// [`Content-Type`]: https://www.rfc-editor.org/rfc/rfc9110#name-content-type
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MediaType {
+    type_: String,
+    subtype: String,
+    parameters: Vec<(String, String)>,
+}
+
+impl MediaType {
+    /// Returns the top-level type, e.g. `text`.
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    /// Returns the subtype, e.g. `html`.
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Returns the media type without parameters, e.g. `text/html`.
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    /// Returns the value of the parameter with the given (case-insensitive) name, if any.
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the value of the `charset` parameter, if any.
+    pub fn charset(&self) -> Option<&str> {
+        self.parameter("charset")
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.split(';');
+        let essence = parts.next().unwrap_or_default().trim();
+        let (type_, subtype) = essence
+            .split_once('/')
+            .filter(|(type_, subtype)| is_token(type_) && is_token(subtype))
+            .ok_or_else(|| format!("invalid media type `{essence}`"))?;
+        let parameters = parts
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                param
+                    .split_once('=')
+                    .filter(|(name, _)| is_token(name))
+                    .map(|(name, value)| (name.to_string(), value.trim_matches('"').to_string()))
+                    .ok_or_else(|| format!("invalid media type parameter `{param}`"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            parameters,
+        })
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in &self.parameters {
+            write!(f, "; {name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Parse an HTTP date in the preferred [IMF-fixdate] format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`,
+/// into a duration since the Unix epoch.
+///
+/// [IMF-fixdate]: https://www.rfc-editor.org/rfc/rfc9110#name-date-time-formats
+fn parse_http_date(value: &str) -> Option<Duration> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (_day_name, date) = value.split_once(", ")?;
+    let [day, month, year, time, "GMT"] = date.split(' ').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let day: u64 = day.parse().ok().filter(|day| (1..=31).contains(day))?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+    let year: u64 = year.parse().ok().filter(|year| *year >= 1970)?;
+    let [hours, minutes, seconds] = time
+        .split(':')
+        .map(|unit| unit.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?[..]
+    else {
+        return None;
+    };
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    let days = days_since_unix_epoch(year, month, day);
+    Some(Duration::from_secs(
+        days * 86_400 + hours * 3_600 + minutes * 60 + seconds,
+    ))
+}
+
+// Number of days since 1970-01-01 of the given date in the proleptic Gregorian calendar, see
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_since_unix_epoch(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Canonicalize the headers of a request.
+///
+/// Header names are already lower-cased by [`http::HeaderName`]. In addition, this converter
+/// merges repeated headers into a single comma-separated value (as allowed by
+/// [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#section-5.3)), or semicolon-separated in
+/// the case of `Cookie`, and optionally sorts headers by name.
+///
+/// Sorting makes the resulting [`IcHttpRequest`](ic_cdk_management_canister::HttpRequestArgs)
+/// deterministic, even when headers are inserted in an order that depends on runtime state.
+#[derive(Clone, Debug, Default)]
+pub struct CanonicalizeHeaders {
+    sort: bool,
+}
+
+impl CanonicalizeHeaders {
+    /// Create a new [`CanonicalizeHeaders`] that merges repeated headers and keeps the
+    /// insertion order.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Additionally sort headers by name.
+    pub fn sorted(self) -> Self {
+        Self { sort: true }
+    }
+
+    /// Canonicalize the given headers.
+    pub fn canonicalize(&self, headers: HeaderMap) -> HeaderMap {
+        let mut names: Vec<HeaderName> = headers.keys().cloned().collect();
+        if self.sort {
+            names.sort_by(|left, right| left.as_str().cmp(right.as_str()));
+        }
+        let mut canonicalized = HeaderMap::with_capacity(names.len());
+        for name in names {
+            let separator: &[u8] = if name == header::COOKIE { b"; " } else { b", " };
+            let mut values = headers.get_all(&name).iter();
+            let Some(first) = values.next() else {
+                continue;
+            };
+            let mut merged = first.clone();
+            for value in values {
+                let bytes = [merged.as_bytes(), separator, value.as_bytes()].concat();
+                let is_sensitive = merged.is_sensitive() || value.is_sensitive();
+                merged = HeaderValue::from_bytes(&bytes)
+                    .expect("BUG: concatenation of valid header values must be valid");
+                merged.set_sensitive(is_sensitive);
+            }
+            canonicalized.insert(name, merged);
+        }
+        canonicalized
+    }
+}
+
+impl<T> Convert<http::Request<T>> for CanonicalizeHeaders {
+    type Output = http::Request<T>;
+    type Error = Infallible;
+
+    fn try_convert(&mut self, request: http::Request<T>) -> Result<Self::Output, Self::Error> {
+        let (mut parts, body) = request.into_parts();
+        parts.headers = self.canonicalize(std::mem::take(&mut parts.headers));
+        Ok(http::Request::from_parts(parts, body))
+    }
+}
+
+/// Middleware that canonicalizes request headers, see [`CanonicalizeHeaders`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{CanonicalizeHeaders, CanonicalizeHeadersLayer, HttpRequest};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(CanonicalizeHeadersLayer::new(CanonicalizeHeaders::new().sorted()))
+///     .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+///
+/// let request = http::Request::post("https://example.com")
+///     .header("X-Request-Id", "42")
+///     .header("Accept", "application/json")
+///     .header("Accept", "text/plain")
+///     .body(vec![])?;
+/// let request = service.ready().await?.call(request).await?;
+///
+/// let headers: Vec<_> = request
+///     .headers()
+///     .iter()
+///     .map(|(name, value)| (name.as_str(), value.to_str().unwrap()))
+///     .collect();
+/// assert_eq!(
+///     headers,
+///     vec![
+///         ("accept", "application/json, text/plain"),
+///         ("x-request-id", "42")
+///     ]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CanonicalizeHeadersLayer {
+    canonicalize: CanonicalizeHeaders,
+}
+
+impl CanonicalizeHeadersLayer {
+    /// Returns a new [`CanonicalizeHeadersLayer`] using the given canonicalization.
+    pub fn new(canonicalize: CanonicalizeHeaders) -> Self {
+        Self { canonicalize }
+    }
+}
+
+impl<S> Layer<S> for CanonicalizeHeadersLayer {
+    type Service = ConvertRequest<S, CanonicalizeHeaders>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.canonicalize.clone()).layer(inner)
+    }
+}
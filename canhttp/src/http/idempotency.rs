@@ -0,0 +1,199 @@
+use crate::{
+    convert::{ConvertRequest, ConvertRequestLayer, Filter},
+    http::{encode_hex, HttpRequest},
+};
+use http::{HeaderName, HeaderValue, Method};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tower_layer::Layer;
+
+/// Add support for caller-supplied idempotency keys, see [`AddIdempotencyKey`].
+pub trait IdempotencyKeyRequestExtension: Sized {
+    /// Set the idempotency key.
+    fn set_idempotency_key(&mut self, value: String);
+
+    /// Retrieves the current idempotency key, if any.
+    fn get_idempotency_key(&self) -> Option<&str>;
+
+    /// Convenience method to use the builder pattern.
+    fn idempotency_key(mut self, value: impl Into<String>) -> Self {
+        self.set_idempotency_key(value.into());
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct IdempotencyKeyExtension(pub String);
+
+impl<T> IdempotencyKeyRequestExtension for http::Request<T> {
+    fn set_idempotency_key(&mut self, value: String) {
+        let extensions = self.extensions_mut();
+        extensions.insert(IdempotencyKeyExtension(value));
+    }
+
+    fn get_idempotency_key(&self) -> Option<&str> {
+        self.extensions()
+            .get::<IdempotencyKeyExtension>()
+            .map(|e| e.0.as_str())
+    }
+}
+
+impl IdempotencyKeyRequestExtension for http::request::Builder {
+    fn set_idempotency_key(&mut self, value: String) {
+        if let Some(extensions) = self.extensions_mut() {
+            extensions.insert(IdempotencyKeyExtension(value));
+        }
+    }
+
+    fn get_idempotency_key(&self) -> Option<&str> {
+        self.extensions_ref().and_then(|extensions| {
+            extensions
+                .get::<IdempotencyKeyExtension>()
+                .map(|e| e.0.as_str())
+        })
+    }
+}
+
+/// Error returned when the idempotency key header cannot be set.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum IdempotencyKeyError {
+    /// The caller-supplied key is not a valid header value.
+    #[error("Invalid idempotency key `{key}`: {reason}")]
+    InvalidKey {
+        /// The invalid key.
+        key: String,
+        /// Reason for the key being invalid.
+        reason: String,
+    },
+}
+
+/// Set an `Idempotency-Key` header on requests, so that they can be safely retried
+/// against APIs (e.g. payment APIs) that deduplicate requests by that key.
+///
+/// The key is either
+/// * supplied by the caller with [`IdempotencyKeyRequestExtension`]; or
+/// * derived deterministically from the request, as the hex-encoded SHA-256 hash of its
+///   method, URL and body.
+///
+/// Since a derived key is the same for all retries of a request, it is also the same
+/// for two distinct but identical requests. Supply a key explicitly if identical requests
+/// must be processed more than once.
+///
+/// By default, only `POST` requests receive a key and an existing header is never overwritten.
+#[derive(Clone, Debug)]
+pub struct AddIdempotencyKey {
+    header: HeaderName,
+    methods: Vec<Method>,
+}
+
+impl AddIdempotencyKey {
+    /// Create a new [`AddIdempotencyKey`] setting the `Idempotency-Key` header of `POST` requests.
+    pub fn new() -> Self {
+        Self {
+            header: HeaderName::from_static("idempotency-key"),
+            methods: vec![Method::POST],
+        }
+    }
+
+    /// Use another header name, e.g. `X-Idempotency-Key`.
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Replace the HTTP methods of the requests that receive a key.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Returns the key derived from the given request.
+    pub fn derive_key(request: &HttpRequest) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(request.method().as_str());
+        hasher.update(b"\n");
+        hasher.update(request.uri().to_string());
+        hasher.update(b"\n");
+        hasher.update(request.body());
+        encode_hex(&hasher.finalize())
+    }
+}
+
+impl Default for AddIdempotencyKey {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Filter<HttpRequest> for AddIdempotencyKey {
+    type Error = IdempotencyKeyError;
+
+    fn filter(&mut self, mut request: HttpRequest) -> Result<HttpRequest, Self::Error> {
+        if !self.methods.contains(request.method()) || request.headers().contains_key(&self.header)
+        {
+            return Ok(request);
+        }
+        let value = match request.get_idempotency_key() {
+            Some(key) => {
+                HeaderValue::try_from(key).map_err(|e| IdempotencyKeyError::InvalidKey {
+                    key: key.to_string(),
+                    reason: e.to_string(),
+                })?
+            }
+            None => HeaderValue::try_from(Self::derive_key(&request))
+                .expect("BUG: hex-encoded hash must be a valid header value"),
+        };
+        request.headers_mut().insert(self.header.clone(), value);
+        Ok(request)
+    }
+}
+
+/// Middleware that sets an idempotency key on requests, see [`AddIdempotencyKey`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{HttpRequest, IdempotencyKeyLayer, IdempotencyKeyRequestExtension};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(IdempotencyKeyLayer::default())
+///     .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+///
+/// let payment = || {
+///     http::Request::post("https://api.example.com/payments").body(br#"{"amount": 42}"#.to_vec())
+/// };
+///
+/// let first = service.ready().await?.call(payment()?).await?;
+/// let retry = service.ready().await?.call(payment()?).await?;
+/// assert_eq!(first.headers()["idempotency-key"], retry.headers()["idempotency-key"]);
+///
+/// let request = http::Request::post("https://api.example.com/payments")
+///     .idempotency_key("order-1234")
+///     .body(vec![])?;
+/// let request = service.ready().await?.call(request).await?;
+/// assert_eq!(request.headers()["idempotency-key"], "order-1234");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct IdempotencyKeyLayer {
+    add_key: AddIdempotencyKey,
+}
+
+impl IdempotencyKeyLayer {
+    /// Returns a new [`IdempotencyKeyLayer`] using the given configuration.
+    pub fn new(add_key: AddIdempotencyKey) -> Self {
+        Self { add_key }
+    }
+}
+
+impl<S> Layer<S> for IdempotencyKeyLayer {
+    type Service = ConvertRequest<S, AddIdempotencyKey>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.add_key.clone()).layer(inner)
+    }
+}
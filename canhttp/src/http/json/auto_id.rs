@@ -0,0 +1,230 @@
+use crate::{
+    convert::{ConvertRequest, ConvertRequestLayer, Filter},
+    http::{
+        json::{BatchJsonRpcRequest, ConstantSizeId, Id, JsonRpcRequest, StableIdCounter},
+        stable::Memory,
+    },
+};
+use std::{cell::Cell, convert::Infallible, fmt, rc::Rc};
+use tower_layer::Layer;
+
+/// Assign IDs to JSON-RPC requests whose ID was left unset, i.e. requests whose ID is still the
+/// default ID given by [`JsonRpcRequest::new`].
+///
+/// Each request of a batch is assigned its own ID. IDs are generated by a counter shared by all
+/// clones, so that concurrent requests sent by clones of the same service get distinct IDs.
+/// Note that the counter is kept on the heap and therefore starts again from its initial value
+/// after a canister upgrade, unless it is persisted with [`AssignJsonRpcId::persistent`].
+#[derive(Clone)]
+pub struct AssignJsonRpcId {
+    generator: Rc<dyn Fn() -> Id>,
+}
+
+impl AssignJsonRpcId {
+    /// Assign numeric IDs from a counter starting at 0.
+    pub fn new() -> Self {
+        let counter = Rc::new(Cell::new(0_u64));
+        Self::with_generator(move || {
+            let id = counter.get();
+            counter.set(id.wrapping_add(1));
+            Id::Number(id)
+        })
+    }
+
+    /// Assign [`ConstantSizeId`]s from a counter starting at [`ConstantSizeId::ZERO`],
+    /// so that the cycles cost of a request does not depend on its ID.
+    pub fn constant_size() -> Self {
+        Self::constant_size_from(ConstantSizeId::ZERO)
+    }
+
+    /// Assign [`ConstantSizeId`]s from a counter starting at the given ID,
+    /// e.g. to use a smaller width with [`ConstantSizeId::with_width`].
+    pub fn constant_size_from(first: ConstantSizeId) -> Self {
+        let counter = Rc::new(Cell::new(first));
+        Self::with_generator(move || {
+            let mut next = counter.take();
+            let id = next.get_and_increment();
+            counter.set(next);
+            Id::from(id)
+        })
+    }
+
+    /// Assign [`ConstantSizeId`]s from a counter persisted in stable memory,
+    /// so that IDs are not reused after a canister upgrade.
+    ///
+    /// # Panics
+    ///
+    /// If the stable memory cannot be grown to hold the counter.
+    pub fn persistent<M: Memory + 'static>(counter: StableIdCounter<M>) -> Self {
+        Self::with_generator(move || {
+            Id::from(
+                counter
+                    .get_and_increment()
+                    .unwrap_or_else(|e| panic!("Failed to generate JSON-RPC ID: {e}")),
+            )
+        })
+    }
+
+    /// Assign string IDs of the form `"{namespace}-{n}"`, where `n` is a counter starting at 0.
+    ///
+    /// IDs generated by different instances, e.g. by service stacks built for different update
+    /// calls or for different providers, each with their own counter, never collide as long as
+    /// their namespaces differ. A namespace can for instance be a nonce generated at the start
+    /// of each update call, so that the IDs of hedged or retried requests issued on behalf
+    /// of that call can always be told apart from those of concurrent calls.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::http::json::{AssignJsonRpcId, HttpJsonRpcRequest, Id, JsonRpcIdLayer, JsonRpcRequest};
+    /// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), BoxError> {
+    /// let service = |nonce: u64| {
+    ///     ServiceBuilder::new()
+    ///         .layer(JsonRpcIdLayer::new(AssignJsonRpcId::namespaced(format!("{nonce:x}"))))
+    ///         .service_fn(|request: HttpJsonRpcRequest<()>| async move {
+    ///             Ok::<_, BoxError>(request.into_body().id().clone())
+    ///         })
+    /// };
+    /// let request = || http::Request::new(JsonRpcRequest::new("eth_blockNumber", ()));
+    ///
+    /// let (mut first_call, mut second_call) = (service(0xa1), service(0xb2));
+    /// assert_eq!(
+    ///     first_call.ready().await?.call(request()).await?,
+    ///     Id::String("a1-0".to_string())
+    /// );
+    /// assert_eq!(
+    ///     second_call.ready().await?.call(request()).await?,
+    ///     Id::String("b2-0".to_string())
+    /// );
+    /// assert_eq!(
+    ///     first_call.ready().await?.call(request()).await?,
+    ///     Id::String("a1-1".to_string())
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn namespaced(namespace: impl Into<String>) -> Self {
+        let namespace = namespace.into();
+        let counter = Rc::new(Cell::new(0_u64));
+        Self::with_generator(move || {
+            let id = counter.get();
+            counter.set(id.wrapping_add(1));
+            Id::String(format!("{namespace}-{id}"))
+        })
+    }
+
+    /// Assign IDs generated by the given function.
+    pub fn with_generator(generator: impl Fn() -> Id + 'static) -> Self {
+        Self {
+            generator: Rc::new(generator),
+        }
+    }
+
+    fn assign<T>(&self, request: &mut JsonRpcRequest<T>) {
+        if request.id() == &Id::from(ConstantSizeId::ZERO) {
+            request.set_id((self.generator)());
+        }
+    }
+}
+
+impl Default for AssignJsonRpcId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AssignJsonRpcId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AssignJsonRpcId").finish_non_exhaustive()
+    }
+}
+
+impl<T> Filter<http::Request<JsonRpcRequest<T>>> for AssignJsonRpcId {
+    type Error = Infallible;
+
+    fn filter(
+        &mut self,
+        mut request: http::Request<JsonRpcRequest<T>>,
+    ) -> Result<http::Request<JsonRpcRequest<T>>, Self::Error> {
+        self.assign(request.body_mut());
+        Ok(request)
+    }
+}
+
+impl<T> Filter<http::Request<BatchJsonRpcRequest<T>>> for AssignJsonRpcId {
+    type Error = Infallible;
+
+    fn filter(
+        &mut self,
+        mut request: http::Request<BatchJsonRpcRequest<T>>,
+    ) -> Result<http::Request<BatchJsonRpcRequest<T>>, Self::Error> {
+        request
+            .body_mut()
+            .iter_mut()
+            .for_each(|request| self.assign(request));
+        Ok(request)
+    }
+}
+
+/// Middleware that assigns IDs to JSON-RPC requests whose ID was left unset,
+/// see [`AssignJsonRpcId`].
+///
+/// This layer must be placed before the [`JsonRpcHttpLayer`], so that the IDs of the responses
+/// are checked against the assigned IDs.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{
+///     AssignJsonRpcId, BatchJsonRpcRequest, HttpBatchJsonRpcRequest, Id, JsonRpcIdLayer,
+///     JsonRpcRequest,
+/// };
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(JsonRpcIdLayer::new(AssignJsonRpcId::new()))
+///     .service_fn(|request: HttpBatchJsonRpcRequest<()>| async move {
+///         Ok::<_, BoxError>(request.into_body())
+///     });
+///
+/// let batch: BatchJsonRpcRequest<()> = vec![
+///     JsonRpcRequest::new("eth_blockNumber", ()),
+///     JsonRpcRequest::new("eth_chainId", ()).with_id(42_u64),
+///     JsonRpcRequest::new("eth_gasPrice", ()),
+/// ]
+/// .into();
+/// let batch = service.ready().await?.call(http::Request::new(batch)).await?;
+///
+/// assert_eq!(
+///     batch.ids().cloned().collect::<Vec<_>>(),
+///     vec![Id::Number(0), Id::Number(42), Id::Number(1)]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`JsonRpcHttpLayer`]: crate::http::json::JsonRpcHttpLayer
+#[derive(Clone, Debug, Default)]
+pub struct JsonRpcIdLayer {
+    assign_id: AssignJsonRpcId,
+}
+
+impl JsonRpcIdLayer {
+    /// Returns a new [`JsonRpcIdLayer`] using the given configuration.
+    pub fn new(assign_id: AssignJsonRpcId) -> Self {
+        Self { assign_id }
+    }
+}
+
+impl<S> Layer<S> for JsonRpcIdLayer {
+    type Service = ConvertRequest<S, AssignJsonRpcId>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.assign_id.clone()).layer(inner)
+    }
+}
@@ -0,0 +1,108 @@
+//! Typed [Bitcoin Core JSON-RPC](https://developer.bitcoin.org/reference/rpc/) methods.
+//!
+//! Each method implements [`JsonRpcMethod`], so that requests can be sent with
+//! [`JsonRpcServiceExt::call_method`] without writing the parameters as raw JSON:
+//! * [`GetBlockCount`] for `getblockcount`;
+//! * [`GetRawTransaction`] for `getrawtransaction`;
+//! * [`SendRawTransaction`] for `sendrawtransaction`.
+//!
+//! Bitcoin Core predates JSON-RPC 2.0: unless the request is sent with `"jsonrpc": "2.0"` to a
+//! recent node (v28 or later), responses have no `jsonrpc` member and always contain both a
+//! `result` and an `error` member, one of which is `null`. Use [`json_rpc_layer`] to parse such
+//! responses, and [`BasicAuthLayer`] to authenticate with the node's `rpcuser` and `rpcpassword`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::http::{
+//!     json::{bitcoin::{self, GetBlockCount}, JsonRpcServiceExt},
+//!     BasicAuthLayer,
+//! };
+//! use ic_cdk_management_canister::{
+//!     HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+//! };
+//! use serde_json::{json, Value};
+//! use tower::{BoxError, ServiceBuilder};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let mut service = ServiceBuilder::new()
+//!     .layer(BasicAuthLayer::new("bitcoin", "secret"))
+//!     .layer(bitcoin::json_rpc_layer())
+//!     .service_fn(|request: IcHttpRequest| async move {
+//!         assert!(request
+//!             .headers
+//!             .iter()
+//!             .any(|header| header.name.eq_ignore_ascii_case("authorization")));
+//!         let id = serde_json::from_slice::<Value>(request.body.as_deref().unwrap())?["id"].clone();
+//!         // Response of a Bitcoin Core node prior to v28.
+//!         let body = json!({"result": 881_423, "error": null, "id": id});
+//!         Ok::<_, BoxError>(IcHttpResponse {
+//!             status: 200_u16.into(),
+//!             body: serde_json::to_vec(&body)?,
+//!             ..Default::default()
+//!         })
+//!     });
+//!
+//! let request = http::Request::post("http://127.0.0.1:8332");
+//! let block_count = service.call_method::<GetBlockCount>(request, []).await?;
+//!
+//! assert_eq!(block_count, Ok(881_423));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`BasicAuthLayer`]: crate::http::BasicAuthLayer
+//! [`JsonRpcServiceExt::call_method`]: crate::http::json::JsonRpcServiceExt::call_method
+
+#[cfg(test)]
+mod tests;
+
+use crate::http::json::{JsonRpcHttpLayer, JsonRpcLeniency, JsonRpcMethod};
+
+/// [`getblockcount`](https://developer.bitcoin.org/reference/rpc/getblockcount.html):
+/// returns the height of the most-work fully-validated chain.
+#[derive(Debug)]
+pub enum GetBlockCount {}
+
+impl JsonRpcMethod for GetBlockCount {
+    const METHOD_NAME: &'static str = "getblockcount";
+    type Params = [(); 0];
+    type Result = u64;
+}
+
+/// [`getrawtransaction`](https://developer.bitcoin.org/reference/rpc/getrawtransaction.html):
+/// returns the hex-encoded serialized transaction with the given transaction ID.
+///
+/// Unless the node is started with `-txindex`, only transactions in the mempool can be found.
+#[derive(Debug)]
+pub enum GetRawTransaction {}
+
+impl JsonRpcMethod for GetRawTransaction {
+    const METHOD_NAME: &'static str = "getrawtransaction";
+    type Params = (String,);
+    type Result = String;
+}
+
+/// [`sendrawtransaction`](https://developer.bitcoin.org/reference/rpc/sendrawtransaction.html):
+/// submits the given hex-encoded serialized transaction to the node and the network,
+/// and returns its transaction ID.
+#[derive(Debug)]
+pub enum SendRawTransaction {}
+
+impl JsonRpcMethod for SendRawTransaction {
+    const METHOD_NAME: &'static str = "sendrawtransaction";
+    type Params = (String,);
+    type Result = String;
+}
+
+/// Returns a [`JsonRpcHttpLayer`] that tolerates the legacy response format of
+/// Bitcoin Core, i.e. responses without a `jsonrpc` member and with both a `result`
+/// and an `error` member.
+pub fn json_rpc_layer<Request, Response>() -> JsonRpcHttpLayer<Request, Response> {
+    JsonRpcHttpLayer::new().with_leniency(
+        JsonRpcLeniency::default()
+            .allow_missing_version()
+            .allow_result_and_error(),
+    )
+}
@@ -0,0 +1,117 @@
+use crate::http::{
+    json::{
+        bitcoin::{json_rpc_layer, GetBlockCount, GetRawTransaction, SendRawTransaction},
+        JsonRpcError, JsonRpcRequest, JsonRpcResponse, JsonRpcServiceExt,
+    },
+    BasicAuthLayer,
+};
+use ic_cdk_management_canister::{
+    HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use tower::{BoxError, Service, ServiceBuilder};
+
+const URL: &str = "http://127.0.0.1:8332";
+const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+#[test]
+fn should_serialize_requests() {
+    assert_eq!(
+        serde_json::to_value(JsonRpcRequest::from_method::<GetBlockCount>([]).with_id(1_u64))
+            .unwrap(),
+        json!({"jsonrpc": "2.0", "method": "getblockcount", "params": [], "id": 1})
+    );
+    assert_eq!(
+        serde_json::to_value(
+            JsonRpcRequest::from_method::<GetRawTransaction>((TXID.to_string(),)).with_id(1_u64)
+        )
+        .unwrap(),
+        json!({"jsonrpc": "2.0", "method": "getrawtransaction", "params": [TXID], "id": 1})
+    );
+    assert_eq!(
+        serde_json::to_value(
+            JsonRpcRequest::from_method::<SendRawTransaction>(("0200".to_string(),)).with_id(1_u64)
+        )
+        .unwrap(),
+        json!({"jsonrpc": "2.0", "method": "sendrawtransaction", "params": ["0200"], "id": 1})
+    );
+}
+
+#[tokio::test]
+async fn should_parse_legacy_responses() {
+    let mut service = bitcoin_core(json!({"result": 881_423, "error": null}));
+
+    let block_count = service
+        .call_method::<GetBlockCount>(http::Request::post(URL), [])
+        .await
+        .unwrap();
+
+    assert_eq!(block_count, Ok(881_423));
+}
+
+#[tokio::test]
+async fn should_parse_legacy_error_responses() {
+    let mut service = bitcoin_core(json!({
+        "result": null,
+        "error": {"code": -5, "message": "No such mempool or blockchain transaction."},
+    }));
+
+    let transaction = service
+        .call_method::<GetRawTransaction>(http::Request::post(URL), (TXID.to_string(),))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        transaction,
+        Err(JsonRpcError::new(
+            -5,
+            "No such mempool or blockchain transaction."
+        ))
+    );
+}
+
+#[tokio::test]
+async fn should_parse_json_rpc_2_responses() {
+    let mut service = bitcoin_core(json!({"jsonrpc": "2.0", "result": TXID}));
+
+    let txid = service
+        .call_method::<SendRawTransaction>(http::Request::post(URL), ("0200".to_string(),))
+        .await
+        .unwrap();
+
+    assert_eq!(txid, Ok(TXID.to_string()));
+}
+
+fn bitcoin_core<Params, Result>(
+    response: Value,
+) -> impl Service<
+    http::Request<JsonRpcRequest<Params>>,
+    Response = http::Response<JsonRpcResponse<Result>>,
+    Error = BoxError,
+>
+where
+    Params: Serialize,
+    Result: DeserializeOwned,
+{
+    ServiceBuilder::new()
+        .layer(BasicAuthLayer::new("bitcoin", "secret"))
+        .layer(json_rpc_layer())
+        .service_fn(move |request: IcHttpRequest| {
+            assert!(request.headers.iter().any(|header| {
+                header.name.eq_ignore_ascii_case("authorization")
+                    && header.value == "Basic Yml0Y29pbjpzZWNyZXQ="
+            }));
+            let request_body: Value = serde_json::from_slice(&request.body.unwrap()).unwrap();
+            let mut response = response.clone();
+            response["id"] = request_body["id"].clone();
+            let body = serde_json::to_vec(&response).unwrap();
+            async move {
+                Ok::<_, BoxError>(IcHttpResponse {
+                    status: 200_u16.into(),
+                    body,
+                    ..Default::default()
+                })
+            }
+        })
+}
@@ -0,0 +1,258 @@
+use crate::{
+    batch::execute_all,
+    http::{
+        json::{
+            BatchJsonRpcRequest, HttpBatchJsonRpcRequest, HttpBatchJsonRpcResponse,
+            JsonRequestConversionError,
+        },
+        take_ready_service,
+    },
+};
+use serde::Serialize;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::Service;
+use tower_layer::Layer;
+
+/// Middleware that splits large batch JSON-RPC requests into several smaller batches,
+/// sends each of them in its own HTTPs outcall and merges the responses back into a single
+/// batch response.
+///
+/// A batch is split so that each chunk has at most the configured number of requests and,
+/// once serialized, at most the configured number of bytes, except for a single request
+/// exceeding that size on its own, which is sent alone. The responses of the chunks are
+/// concatenated in the order of the chunks and the HTTP head of the response to the first
+/// chunk is used. If any chunk fails, the first error is returned.
+///
+/// This layer must be placed before the [`JsonRpcHttpLayer`], so that the IDs of the
+/// responses are checked for each chunk. Since the [`JsonRpcHttpLayer`] orders the responses of a
+/// batch like the requests, the merged responses are then also in the order of the requests.
+/// Requests having the same ID but ending up in different chunks would not be detected by the
+/// [`JsonRpcHttpLayer`], so that a batch containing duplicate IDs is rejected with
+/// [`JsonRequestConversionError::DuplicateId`] before being split.
+///
+/// To measure the size of the chunks, each request is serialized once more, in addition to
+/// its serialization by the inner service, without allocating the serialized bytes.
+///
+/// This [`Layer`] produces instances of the [`BatchChunking`] service.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{
+///     BatchChunkingLayer, BatchJsonRpcRequest, BatchJsonRpcResponse, HttpBatchJsonRpcRequest,
+///     JsonRpcRequest, JsonRpcResponse,
+/// };
+/// use std::{cell::RefCell, rc::Rc};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let chunk_sizes = Rc::new(RefCell::new(Vec::new()));
+/// let mut service = ServiceBuilder::new()
+///     .layer(BatchChunkingLayer::new().max_entries(2))
+///     .service_fn({
+///         let chunk_sizes = chunk_sizes.clone();
+///         move |request: HttpBatchJsonRpcRequest<u64>| {
+///             chunk_sizes.borrow_mut().push(request.body().len());
+///             let responses: BatchJsonRpcResponse<u64> = request
+///                 .into_body()
+///                 .into_iter()
+///                 .map(|request| {
+///                     JsonRpcResponse::from_ok(request.id().clone(), request.params().unwrap() * 2)
+///                 })
+///                 .collect();
+///             async move { Ok::<_, BoxError>(http::Response::new(responses)) }
+///         }
+///     });
+///
+/// let batch: BatchJsonRpcRequest<u64> = (0..5_u64)
+///     .map(|n| JsonRpcRequest::new("double", n).with_id(n))
+///     .collect();
+/// let response = service.ready().await?.call(http::Request::new(batch)).await?;
+///
+/// assert_eq!(*chunk_sizes.borrow(), vec![2, 2, 1]);
+/// let results: Vec<u64> = response
+///     .into_body()
+///     .into_iter()
+///     .map(|response| response.into_result().unwrap())
+///     .collect();
+/// assert_eq!(results, vec![0, 2, 4, 6, 8]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`JsonRpcHttpLayer`]: crate::http::json::JsonRpcHttpLayer
+/// [`Layer`]: tower::Layer
+#[derive(Clone, Debug)]
+pub struct BatchChunkingLayer {
+    max_entries: usize,
+    max_bytes: usize,
+    max_concurrency: usize,
+}
+
+impl BatchChunkingLayer {
+    /// Returns a new [`BatchChunkingLayer`] that does not split batches until a threshold
+    /// is configured, and sends the chunks one after the other.
+    pub fn new() -> Self {
+        Self {
+            max_entries: usize::MAX,
+            max_bytes: usize::MAX,
+            max_concurrency: 1,
+        }
+    }
+
+    /// Set the maximum number of requests in a chunk.
+    ///
+    /// # Panics
+    ///
+    /// If `max_entries` is zero.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        assert!(max_entries > 0, "max_entries must be greater than zero");
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Set the maximum number of bytes of a serialized chunk.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Set the maximum number of chunks sent concurrently.
+    ///
+    /// # Panics
+    ///
+    /// If `max_concurrency` is zero.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        assert!(
+            max_concurrency > 0,
+            "max_concurrency must be greater than zero"
+        );
+        self.max_concurrency = max_concurrency;
+        self
+    }
+}
+
+impl Default for BatchChunkingLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for BatchChunkingLayer {
+    type Service = BatchChunking<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BatchChunking {
+            inner,
+            config: self.clone(),
+        }
+    }
+}
+
+/// Split large batch JSON-RPC requests into several smaller batches, see [`BatchChunkingLayer`].
+#[derive(Clone, Debug)]
+pub struct BatchChunking<S> {
+    inner: S,
+    config: BatchChunkingLayer,
+}
+
+impl<S, Params, Result> Service<HttpBatchJsonRpcRequest<Params>> for BatchChunking<S>
+where
+    S: Service<HttpBatchJsonRpcRequest<Params>, Response = HttpBatchJsonRpcResponse<Result>>
+        + Clone
+        + 'static,
+    S::Error: From<JsonRequestConversionError>,
+    Params: Serialize + 'static,
+    Result: 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpBatchJsonRpcRequest<Params>) -> Self::Future {
+        if let Some(id) = request.body().find_duplicate_id() {
+            let error = JsonRequestConversionError::DuplicateId { id: id.clone() };
+            return Box::pin(std::future::ready(Err(error.into())));
+        }
+        let mut inner = take_ready_service(&mut self.inner);
+        let (parts, batch) = request.into_parts();
+        let mut chunks = self.config.split(batch);
+        if chunks.len() <= 1 {
+            let batch = chunks.pop().unwrap_or_default();
+            return Box::pin(inner.call(http::Request::from_parts(parts, batch)));
+        }
+        let requests = chunks
+            .into_iter()
+            .map(|chunk| http::Request::from_parts(parts.clone(), chunk))
+            .collect();
+        let max_concurrency = self.config.max_concurrency;
+        Box::pin(async move {
+            let (_inner, results) = execute_all(inner, requests, max_concurrency).await;
+            let mut head = None;
+            let mut responses = Vec::new();
+            for result in results {
+                let (parts, batch) = result?.into_parts();
+                head.get_or_insert(parts);
+                responses.extend(batch.into_vec());
+            }
+            let head = head.expect("BUG: expected at least two chunks");
+            Ok(http::Response::from_parts(head, responses.into()))
+        })
+    }
+}
+
+impl BatchChunkingLayer {
+    fn split<T: Serialize>(&self, batch: BatchJsonRpcRequest<T>) -> Vec<BatchJsonRpcRequest<T>> {
+        // Serialized as `[request_1,request_2,...]`.
+        const BRACKETS_LEN: usize = 2;
+        const SEPARATOR_LEN: usize = 1;
+
+        let mut chunks = Vec::new();
+        let mut current = BatchJsonRpcRequest::new();
+        let mut current_bytes = BRACKETS_LEN;
+        for request in batch {
+            // Requests that cannot be serialized are rejected by the inner service anyway.
+            let mut counter = ByteCounter::default();
+            let request_bytes =
+                serde_json::to_writer(&mut counter, &request).map_or(0, |_| counter.0);
+            let separator = if current.is_empty() { 0 } else { SEPARATOR_LEN };
+            let fits = current.len() < self.max_entries
+                && current_bytes + separator + request_bytes <= self.max_bytes;
+            if !fits && !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+                current_bytes = BRACKETS_LEN;
+            }
+            current_bytes += if current.is_empty() { 0 } else { SEPARATOR_LEN } + request_bytes;
+            current.push(request);
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        chunks
+    }
+}
+
+/// Writer counting the written bytes without storing them.
+#[derive(Default)]
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,506 @@
+//! Typed [Ethereum JSON-RPC](https://ethereum.org/en/developers/docs/apis/json-rpc/) methods.
+//!
+//! Each method implements [`JsonRpcMethod`], so that requests can be sent with
+//! [`JsonRpcServiceExt::call_method`] without writing the parameters as raw JSON:
+//! * [`EthBlockNumber`] for `eth_blockNumber`;
+//! * [`EthCall`] for `eth_call`;
+//! * [`EthGetLogs`] for `eth_getLogs`;
+//! * [`EthGetTransactionReceipt`] for `eth_getTransactionReceipt`.
+//!
+//! Quantities and binary data are hex-encoded as required by the
+//! [specification](https://ethereum.org/en/developers/docs/apis/json-rpc/#hex-encoding),
+//! see [`Quantity`], [`Data`] and [`FixedBytes`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::http::json::{
+//!     eth::{BlockTag, CallRequest, Data, EthCall},
+//!     JsonRpcMethod, JsonRpcRequest, JsonRpcResponse,
+//! };
+//! use serde_json::json;
+//!
+//! // `decimals()` of the USDC contract.
+//! let call = CallRequest::new("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".parse()?)
+//!     .input("0x313ce567".parse()?);
+//! let request = JsonRpcRequest::from_method::<EthCall>((call, BlockTag::Finalized)).with_id(1_u64);
+//!
+//! assert_eq!(
+//!     serde_json::to_value(&request)?,
+//!     json!({
+//!         "jsonrpc": "2.0",
+//!         "method": "eth_call",
+//!         "params": [
+//!             {"to": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "input": "0x313ce567"},
+//!             "finalized"
+//!         ],
+//!         "id": 1
+//!     })
+//! );
+//!
+//! let response: JsonRpcResponse<<EthCall as JsonRpcMethod>::Result> = serde_json::from_value(json!({
+//!     "jsonrpc": "2.0",
+//!     "result": "0x0000000000000000000000000000000000000000000000000000000000000006",
+//!     "id": 1
+//! }))?;
+//! let decimals: Data = response.into_result()?;
+//! assert_eq!(decimals.0[31], 6);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`JsonRpcServiceExt::call_method`]: crate::http::json::JsonRpcServiceExt::call_method
+
+#[cfg(test)]
+mod tests;
+
+use crate::http::json::JsonRpcMethod;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+/// [`eth_blockNumber`](https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_blocknumber):
+/// returns the number of the most recent block.
+#[derive(Debug)]
+pub enum EthBlockNumber {}
+
+impl JsonRpcMethod for EthBlockNumber {
+    const METHOD_NAME: &'static str = "eth_blockNumber";
+    type Params = [(); 0];
+    type Result = Quantity;
+}
+
+/// [`eth_call`](https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_call):
+/// executes a message call without creating a transaction and returns its output.
+#[derive(Debug)]
+pub enum EthCall {}
+
+impl JsonRpcMethod for EthCall {
+    const METHOD_NAME: &'static str = "eth_call";
+    type Params = (CallRequest, BlockTag);
+    type Result = Data;
+}
+
+/// [`eth_getLogs`](https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_getlogs):
+/// returns the logs matching the given filter.
+#[derive(Debug)]
+pub enum EthGetLogs {}
+
+impl JsonRpcMethod for EthGetLogs {
+    const METHOD_NAME: &'static str = "eth_getLogs";
+    type Params = (LogFilter,);
+    type Result = Vec<Log>;
+}
+
+/// [`eth_getTransactionReceipt`](https://ethereum.org/en/developers/docs/apis/json-rpc/#eth_gettransactionreceipt):
+/// returns the receipt of the transaction with the given hash, or `None` if the transaction
+/// is unknown or still pending.
+#[derive(Debug)]
+pub enum EthGetTransactionReceipt {}
+
+impl JsonRpcMethod for EthGetTransactionReceipt {
+    const METHOD_NAME: &'static str = "eth_getTransactionReceipt";
+    type Params = (Hash,);
+    type Result = Option<TransactionReceipt>;
+}
+
+/// Block parameter of methods such as `eth_call` or `eth_getLogs`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BlockTag {
+    /// The most recent block.
+    #[default]
+    Latest,
+    /// The most recent block that is safe from re-orgs under honest majority.
+    Safe,
+    /// The most recent block accepted by more than 2/3 of the validators.
+    Finalized,
+    /// The genesis block.
+    Earliest,
+    /// The pending state and transactions.
+    Pending,
+    /// The block with the given number.
+    Number(Quantity),
+}
+
+impl Serialize for BlockTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BlockTag::Latest => serializer.serialize_str("latest"),
+            BlockTag::Safe => serializer.serialize_str("safe"),
+            BlockTag::Finalized => serializer.serialize_str("finalized"),
+            BlockTag::Earliest => serializer.serialize_str("earliest"),
+            BlockTag::Pending => serializer.serialize_str("pending"),
+            BlockTag::Number(number) => number.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockTag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        match value.as_str() {
+            "latest" => Ok(BlockTag::Latest),
+            "safe" => Ok(BlockTag::Safe),
+            "finalized" => Ok(BlockTag::Finalized),
+            "earliest" => Ok(BlockTag::Earliest),
+            "pending" => Ok(BlockTag::Pending),
+            number => number
+                .parse()
+                .map(BlockTag::Number)
+                .map_err(de::Error::custom),
+        }
+    }
+}
+
+impl From<u64> for BlockTag {
+    fn from(number: u64) -> Self {
+        BlockTag::Number(Quantity(number))
+    }
+}
+
+/// Parameters of a message call, see [`EthCall`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallRequest {
+    /// Address the call is sent from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<Address>,
+    /// Address the call is sent to.
+    pub to: Address,
+    /// Gas provided for the call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas: Option<Quantity>,
+    /// Value sent with the call, in wei.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Quantity>,
+    /// Input data of the call, e.g. an ABI-encoded function call.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<Data>,
+}
+
+impl CallRequest {
+    /// Create a new call to the given address without input data.
+    pub fn new(to: Address) -> Self {
+        Self {
+            from: None,
+            to,
+            gas: None,
+            value: None,
+            input: None,
+        }
+    }
+
+    /// Set the address the call is sent from.
+    pub fn from(mut self, from: Address) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Set the input data of the call.
+    pub fn input(mut self, input: Data) -> Self {
+        self.input = Some(input);
+        self
+    }
+}
+
+/// Filter of [`EthGetLogs`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    /// First block to search, inclusive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_block: Option<BlockTag>,
+    /// Last block to search, inclusive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_block: Option<BlockTag>,
+    /// Contract addresses emitting the logs. Logs of any contract match if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub address: Vec<Address>,
+    /// Topics of the logs by position, where `None` matches any topic and
+    /// `Some(topics)` matches any of the given topics.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<Option<Vec<Hash>>>,
+}
+
+impl LogFilter {
+    /// Create a new filter matching all logs of the latest block.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search the blocks between `from` and `to`, inclusive.
+    pub fn blocks(mut self, from: impl Into<BlockTag>, to: impl Into<BlockTag>) -> Self {
+        self.from_block = Some(from.into());
+        self.to_block = Some(to.into());
+        self
+    }
+
+    /// Only match logs emitted by the given contract.
+    pub fn address(mut self, address: Address) -> Self {
+        self.address.push(address);
+        self
+    }
+
+    /// Only match logs whose topic at the next position is one of the given topics.
+    pub fn topic(mut self, topics: impl IntoIterator<Item = Hash>) -> Self {
+        self.topics.push(Some(topics.into_iter().collect()));
+        self
+    }
+
+    /// Match logs with any topic at the next position.
+    pub fn any_topic(mut self) -> Self {
+        self.topics.push(None);
+        self
+    }
+}
+
+/// Log emitted by a contract.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    /// Address of the contract that emitted the log.
+    pub address: Address,
+    /// Indexed topics of the log.
+    pub topics: Vec<Hash>,
+    /// Non-indexed data of the log.
+    pub data: Data,
+    /// Number of the block containing the log, if the block is not pending.
+    #[serde(default)]
+    pub block_number: Option<Quantity>,
+    /// Hash of the block containing the log, if the block is not pending.
+    #[serde(default)]
+    pub block_hash: Option<Hash>,
+    /// Hash of the transaction that emitted the log, if the transaction is not pending.
+    #[serde(default)]
+    pub transaction_hash: Option<Hash>,
+    /// Position of the transaction in its block, if the transaction is not pending.
+    #[serde(default)]
+    pub transaction_index: Option<Quantity>,
+    /// Position of the log in its block, if the block is not pending.
+    #[serde(default)]
+    pub log_index: Option<Quantity>,
+    /// Whether the log was removed due to a chain re-organization.
+    #[serde(default)]
+    pub removed: bool,
+}
+
+/// Receipt of a transaction included in a block.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    /// Hash of the transaction.
+    pub transaction_hash: Hash,
+    /// Position of the transaction in its block.
+    pub transaction_index: Quantity,
+    /// Hash of the block containing the transaction.
+    pub block_hash: Hash,
+    /// Number of the block containing the transaction.
+    pub block_number: Quantity,
+    /// Address of the sender.
+    pub from: Address,
+    /// Address of the receiver, or `None` for a contract creation.
+    #[serde(default)]
+    pub to: Option<Address>,
+    /// Total amount of gas used in the block up to and including this transaction.
+    pub cumulative_gas_used: Quantity,
+    /// Amount of gas used by this transaction.
+    pub gas_used: Quantity,
+    /// Price per unit of gas actually paid, in wei.
+    #[serde(default)]
+    pub effective_gas_price: Option<Quantity>,
+    /// Address of the created contract, if the transaction was a contract creation.
+    #[serde(default)]
+    pub contract_address: Option<Address>,
+    /// Logs emitted by the transaction.
+    pub logs: Vec<Log>,
+    /// `1` if the transaction succeeded, `0` if it failed.
+    ///
+    /// Only present for transactions included after the Byzantium hard fork.
+    #[serde(default)]
+    pub status: Option<Quantity>,
+}
+
+impl TransactionReceipt {
+    /// Returns `true` if and only if the transaction succeeded.
+    pub fn is_success(&self) -> bool {
+        self.status == Some(Quantity(1))
+    }
+}
+
+/// Unsigned integer, hex-encoded without leading zeros, e.g. `"0x41"` for 65.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Quantity(pub u64);
+
+impl From<u64> for Quantity {
+    fn from(value: u64) -> Self {
+        Quantity(value)
+    }
+}
+
+impl From<Quantity> for u64 {
+    fn from(value: Quantity) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.0)
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = ParseHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = strip_hex_prefix(s)?;
+        if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+            return Err(ParseHexError::InvalidHex(s.to_string()));
+        }
+        u64::from_str_radix(digits, 16)
+            .map(Quantity)
+            .map_err(|_| ParseHexError::InvalidHex(s.to_string()))
+    }
+}
+
+/// Binary data of arbitrary length, hex-encoded with two digits per byte, e.g. `"0x0041"`.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Data(pub Vec<u8>);
+
+impl From<Vec<u8>> for Data {
+    fn from(bytes: Vec<u8>) -> Self {
+        Data(bytes)
+    }
+}
+
+impl fmt::Display for Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+impl FromStr for Data {
+    type Err = ParseHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        decode_hex(s).map(Data)
+    }
+}
+
+/// Binary data of fixed length `N`, hex-encoded with two digits per byte.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FixedBytes<const N: usize>(pub [u8; N]);
+
+/// 20-byte address of an account.
+pub type Address = FixedBytes<20>;
+
+/// 32-byte hash, e.g. of a block or a transaction, or log topic.
+pub type Hash = FixedBytes<32>;
+
+impl<const N: usize> Default for FixedBytes<N> {
+    fn default() -> Self {
+        FixedBytes([0; N])
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for FixedBytes<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        FixedBytes(bytes)
+    }
+}
+
+impl<const N: usize> fmt::Display for FixedBytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+impl<const N: usize> FromStr for FixedBytes<N> {
+    type Err = ParseHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = decode_hex(s)?;
+        <[u8; N]>::try_from(bytes.as_slice())
+            .map(FixedBytes)
+            .map_err(|_| ParseHexError::InvalidLength {
+                expected: N,
+                actual: bytes.len(),
+            })
+    }
+}
+
+macro_rules! serde_via_string {
+    ($($ty:ty),+ $(,)?) => {$(
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(de::Error::custom)
+            }
+        }
+    )+};
+}
+
+serde_via_string!(Quantity, Data);
+
+impl<const N: usize> Serialize for FixedBytes<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for FixedBytes<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Error returned when parsing hex-encoded values such as [`Quantity`], [`Data`] or [`FixedBytes`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum ParseHexError {
+    /// The value does not start with `0x`.
+    #[error("Expected hex value to start with 0x, but got {0}")]
+    MissingPrefix(String),
+    /// The value is not correctly hex-encoded.
+    #[error("Invalid hex value: {0}")]
+    InvalidHex(String),
+    /// The value does not have the expected number of bytes.
+    #[error("Expected {expected} bytes, but got {actual}")]
+    InvalidLength {
+        /// Expected number of bytes.
+        expected: usize,
+        /// Actual number of bytes.
+        actual: usize,
+    },
+}
+
+fn strip_hex_prefix(s: &str) -> Result<&str, ParseHexError> {
+    s.strip_prefix("0x")
+        .ok_or_else(|| ParseHexError::MissingPrefix(s.to_string()))
+}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>, ParseHexError> {
+    let digits = strip_hex_prefix(s)?;
+    if digits.len() % 2 != 0 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseHexError::InvalidHex(s.to_string()));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| ParseHexError::InvalidHex(s.to_string()))
+        })
+        .collect()
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    f.write_str("0x")?;
+    bytes.iter().try_for_each(|b| write!(f, "{b:02x}"))
+}
@@ -0,0 +1,265 @@
+use crate::http::json::{
+    eth::{
+        Address, BlockTag, CallRequest, Data, EthBlockNumber, EthCall, EthGetLogs,
+        EthGetTransactionReceipt, Hash, LogFilter, ParseHexError, Quantity,
+    },
+    JsonRpcMethod, JsonRpcRequest, JsonRpcResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+const TX_HASH: &str = "0x5e77a04531c7c107af1882d76cbff9486d0a9aa53701c30888509d4f5f2b003a";
+const BLOCK_HASH: &str = "0x1a2e3b2a5d5b6e3e3b0b5b0c1c3bf4f8fd8a3a4c0bb8f1e8e0a7a6b2f1e5c3d9";
+
+mod hex {
+    use super::*;
+
+    #[test]
+    fn should_serialize_quantity() {
+        for (value, expected) in [(0_u64, "0x0"), (1, "0x1"), (65, "0x41"), (1024, "0x400")] {
+            assert_eq!(
+                serde_json::to_value(Quantity(value)).unwrap(),
+                json!(expected)
+            );
+            assert_eq!(
+                serde_json::from_value::<Quantity>(json!(expected)).unwrap(),
+                Quantity(value)
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_quantity() {
+        assert_eq!(
+            "41".parse::<Quantity>(),
+            Err(ParseHexError::MissingPrefix("41".to_string()))
+        );
+        for invalid in ["0x", "0x0400", "0xg", "0x10000000000000000"] {
+            assert_eq!(
+                invalid.parse::<Quantity>(),
+                Err(ParseHexError::InvalidHex(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn should_serialize_data() {
+        for (value, expected) in [(vec![], "0x"), (vec![0x00, 0x41], "0x0041")] {
+            assert_eq!(
+                serde_json::to_value(Data(value.clone())).unwrap(),
+                json!(expected)
+            );
+            assert_eq!(
+                serde_json::from_value::<Data>(json!(expected)).unwrap(),
+                Data(value)
+            );
+        }
+        assert_eq!(
+            "0x041".parse::<Data>(),
+            Err(ParseHexError::InvalidHex("0x041".to_string()))
+        );
+    }
+
+    #[test]
+    fn should_parse_fixed_bytes() {
+        let address: Address = USDC.parse().unwrap();
+        assert_eq!(address.0[0], 0xa0);
+        assert_eq!(address.to_string(), USDC);
+
+        let mixed_case: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+        assert_eq!(mixed_case, address);
+
+        assert_eq!(
+            TX_HASH.parse::<Address>(),
+            Err(ParseHexError::InvalidLength {
+                expected: 20,
+                actual: 32
+            })
+        );
+    }
+
+    #[test]
+    fn should_serialize_block_tag() {
+        for (tag, expected) in [
+            (BlockTag::Latest, json!("latest")),
+            (BlockTag::Safe, json!("safe")),
+            (BlockTag::Finalized, json!("finalized")),
+            (BlockTag::Earliest, json!("earliest")),
+            (BlockTag::Pending, json!("pending")),
+            (BlockTag::from(0x1406f40), json!("0x1406f40")),
+        ] {
+            assert_eq!(serde_json::to_value(tag).unwrap(), expected);
+            assert_eq!(serde_json::from_value::<BlockTag>(expected).unwrap(), tag);
+        }
+    }
+}
+
+mod methods {
+    use super::*;
+
+    #[test]
+    fn should_serialize_eth_block_number() {
+        assert_eq!(
+            request::<EthBlockNumber>([]),
+            json!({"jsonrpc": "2.0", "method": "eth_blockNumber", "params": [], "id": 1})
+        );
+        assert_eq!(
+            response::<EthBlockNumber>(json!("0x1406f40")),
+            Quantity(21_000_000)
+        );
+    }
+
+    #[test]
+    fn should_serialize_eth_call() {
+        let call = CallRequest::new(USDC.parse().unwrap())
+            .from(Address::default())
+            .input("0x313ce567".parse().unwrap());
+
+        assert_eq!(
+            request::<EthCall>((call, BlockTag::from(21_000_000))),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "eth_call",
+                "params": [
+                    {
+                        "from": "0x0000000000000000000000000000000000000000",
+                        "to": USDC,
+                        "input": "0x313ce567"
+                    },
+                    "0x1406f40"
+                ],
+                "id": 1
+            })
+        );
+        assert_eq!(
+            response::<EthCall>(json!(
+                "0x0000000000000000000000000000000000000000000000000000000000000006"
+            ))
+            .0,
+            [vec![0; 31], vec![6]].concat()
+        );
+    }
+
+    #[test]
+    fn should_serialize_eth_get_logs() {
+        let filter = LogFilter::new()
+            .blocks(BlockTag::from(21_000_000), BlockTag::Finalized)
+            .address(USDC.parse().unwrap())
+            .topic([TRANSFER_TOPIC.parse().unwrap()])
+            .any_topic();
+
+        assert_eq!(
+            request::<EthGetLogs>((filter,)),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "eth_getLogs",
+                "params": [{
+                    "fromBlock": "0x1406f40",
+                    "toBlock": "finalized",
+                    "address": [USDC],
+                    "topics": [[TRANSFER_TOPIC], null]
+                }],
+                "id": 1
+            })
+        );
+        assert_eq!(
+            request::<EthGetLogs>((LogFilter::new(),)),
+            json!({"jsonrpc": "2.0", "method": "eth_getLogs", "params": [{}], "id": 1})
+        );
+
+        let logs = response::<EthGetLogs>(json!([log()]));
+
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].address, USDC.parse().unwrap());
+        assert_eq!(
+            logs[0].topics,
+            vec![TRANSFER_TOPIC.parse::<Hash>().unwrap()]
+        );
+        assert_eq!(logs[0].block_number, Some(Quantity(21_000_000)));
+        assert_eq!(logs[0].log_index, Some(Quantity(2)));
+        assert!(!logs[0].removed);
+    }
+
+    #[test]
+    fn should_serialize_eth_get_transaction_receipt() {
+        assert_eq!(
+            request::<EthGetTransactionReceipt>((TX_HASH.parse().unwrap(),)),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "eth_getTransactionReceipt",
+                "params": [TX_HASH],
+                "id": 1
+            })
+        );
+
+        let receipt = response::<EthGetTransactionReceipt>(json!({
+            "transactionHash": TX_HASH,
+            "transactionIndex": "0x0",
+            "blockHash": BLOCK_HASH,
+            "blockNumber": "0x1406f40",
+            "from": "0x0000000000000000000000000000000000000000",
+            "to": USDC,
+            "cumulativeGasUsed": "0xb4c8",
+            "gasUsed": "0xb4c8",
+            "effectiveGasPrice": "0x3b9aca00",
+            "contractAddress": null,
+            "logs": [log()],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "status": "0x1",
+            "type": "0x2"
+        }))
+        .unwrap();
+
+        assert!(receipt.is_success());
+        assert_eq!(receipt.gas_used, Quantity(0xb4c8));
+        assert_eq!(receipt.contract_address, None);
+        assert_eq!(receipt.logs.len(), 1);
+
+        assert_eq!(
+            response::<EthGetTransactionReceipt>(Value::Null),
+            None,
+            "unknown transaction"
+        );
+    }
+
+    fn request<M>(params: M::Params) -> Value
+    where
+        M: JsonRpcMethod,
+        M::Params: Serialize,
+    {
+        serde_json::to_value(JsonRpcRequest::from_method::<M>(params).with_id(1_u64)).unwrap()
+    }
+
+    fn response<M>(result: Value) -> M::Result
+    where
+        M: JsonRpcMethod,
+        M::Result: DeserializeOwned,
+    {
+        serde_json::from_value::<JsonRpcResponse<M::Result>>(json!({
+            "jsonrpc": "2.0",
+            "result": result,
+            "id": 1
+        }))
+        .unwrap()
+        .into_result()
+        .unwrap()
+    }
+
+    fn log() -> Value {
+        json!({
+            "address": USDC,
+            "topics": [TRANSFER_TOPIC],
+            "data": "0x00000000000000000000000000000000000000000000000000000000000f4240",
+            "blockNumber": "0x1406f40",
+            "blockHash": BLOCK_HASH,
+            "transactionHash": TX_HASH,
+            "transactionIndex": "0x0",
+            "logIndex": "0x2",
+            "removed": false
+        })
+    }
+}
@@ -0,0 +1,183 @@
+use crate::http::json::{
+    BatchJsonRpcRequest, BatchJsonRpcResponse, Id, JsonRpcMethod, JsonRpcRequest, JsonRpcResult,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{fmt, marker::PhantomData};
+use thiserror::Error;
+
+/// Builder for a batch of JSON-RPC requests with different methods, parameters and results.
+///
+/// A [`BatchJsonRpcRequest<Params>`] requires all requests in the batch to share the same type
+/// of parameters. Instead, each request added to a [`HeterogeneousBatch`] is serialized to a
+/// [`Value`], and a typed [`BatchEntry`] is returned to decode its own result from the
+/// [`BatchJsonRpcResponse<Value>`], so that a single HTTPs outcall can mix different methods.
+///
+/// Each request is given a numeric ID corresponding to its position in the batch.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{
+///     BatchJsonRpcRequest, BatchJsonRpcResponse, HeterogeneousBatch, JsonRpcResponse,
+/// };
+/// use serde_json::{json, Value};
+///
+/// let mut batch = HeterogeneousBatch::new();
+/// let block_number = batch.add::<String>("eth_blockNumber", ())?;
+/// let balance = batch.add::<Value>(
+///     "eth_getBalance",
+///     ("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045", "latest"),
+/// )?;
+/// let request: BatchJsonRpcRequest<Value> = batch.build();
+///
+/// // The response would usually come from a service with a `JsonRpcHttpLayer`.
+/// let response: BatchJsonRpcResponse<Value> = request
+///     .iter()
+///     .zip([json!("0x1"), json!({"balance": "0x2"})])
+///     .map(|(request, result)| JsonRpcResponse::from_ok(request.id().clone(), result))
+///     .collect();
+///
+/// assert_eq!(block_number.decode(&response)?, Ok("0x1".to_string()));
+/// assert_eq!(balance.decode(&response)?, Ok(json!({"balance": "0x2"})));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HeterogeneousBatch {
+    requests: Vec<JsonRpcRequest<Value>>,
+}
+
+impl HeterogeneousBatch {
+    /// Create a new empty batch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a request to the batch for the given method and parameters, whose result is
+    /// decoded as `R` (e.g. [`Value`] to skip decoding).
+    ///
+    /// Fails if the parameters cannot be serialized to JSON.
+    pub fn add<R>(
+        &mut self,
+        method: impl Into<String>,
+        params: impl Serialize,
+    ) -> Result<BatchEntry<R>, serde_json::Error> {
+        let params = serde_json::to_value(params)?;
+        let id = Id::from(self.requests.len() as u64);
+        self.requests
+            .push(JsonRpcRequest::new(method, params).with_id(id.clone()));
+        Ok(BatchEntry {
+            id,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Add a request to the batch for the method `M`, see [`JsonRpcMethod`].
+    ///
+    /// Fails if the parameters cannot be serialized to JSON.
+    pub fn add_method<M>(
+        &mut self,
+        params: M::Params,
+    ) -> Result<BatchEntry<M::Result>, serde_json::Error>
+    where
+        M: JsonRpcMethod,
+        M::Params: Serialize,
+    {
+        self.add(M::METHOD_NAME, params)
+    }
+
+    /// Returns the number of requests in the batch.
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Returns `true` if the batch contains no requests.
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Returns the body of the batch JSON-RPC request.
+    pub fn build(self) -> BatchJsonRpcRequest<Value> {
+        BatchJsonRpcRequest::from(self.requests)
+    }
+}
+
+impl From<HeterogeneousBatch> for BatchJsonRpcRequest<Value> {
+    fn from(batch: HeterogeneousBatch) -> Self {
+        batch.build()
+    }
+}
+
+/// Handle to a request added to a [`HeterogeneousBatch`], used to decode its result.
+pub struct BatchEntry<T = Value> {
+    id: Id,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> BatchEntry<T> {
+    /// Returns the ID of the request.
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl<T: DeserializeOwned> BatchEntry<T> {
+    /// Find the response to this request in the given batch and decode its result.
+    pub fn decode(
+        &self,
+        responses: &BatchJsonRpcResponse<Value>,
+    ) -> Result<JsonRpcResult<T>, BatchEntryError> {
+        let response =
+            responses
+                .get_by_id(&self.id)
+                .ok_or_else(|| BatchEntryError::MissingResponse {
+                    id: self.id.clone(),
+                })?;
+        match response.as_result() {
+            Ok(result) => {
+                T::deserialize(result)
+                    .map(Ok)
+                    .map_err(|e| BatchEntryError::InvalidResult {
+                        id: self.id.clone(),
+                        parsing_error: e.to_string(),
+                    })
+            }
+            Err(error) => Ok(Err(error.clone())),
+        }
+    }
+}
+
+// #[derive(Clone)] would otherwise introduce a bound T: Clone, which is not needed.
+impl<T> Clone for BatchEntry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for BatchEntry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchEntry").field("id", &self.id).finish()
+    }
+}
+
+/// Error returned by [`BatchEntry::decode`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum BatchEntryError {
+    /// The batch response does not contain a response for the request.
+    #[error("Missing response for batch request with ID {id}")]
+    MissingResponse {
+        /// ID of the request.
+        id: Id,
+    },
+    /// The result of the response could not be decoded.
+    #[error("Invalid result for batch request with ID {id}: {parsing_error}")]
+    InvalidResult {
+        /// ID of the request.
+        id: Id,
+        /// Deserialization error.
+        parsing_error: String,
+    },
+}
@@ -1,9 +1,11 @@
+use crate::http::stable::{region, CanisterStableMemory, Memory};
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::{Display, Formatter},
     num::ParseIntError,
     str::FromStr,
 };
+use thiserror::Error;
 
 /// An identifier established by the Client that MUST contain a String, Number, or NULL value if included.
 ///
@@ -60,6 +62,10 @@ impl Display for Id {
 /// cycles cost of an HTTP outcall, two requests only differing by their IDs will therefore require the same amount of cycles,
 /// which helps applications in estimating the cycle cost of their requests.
 ///
+/// The ID is serialized as a string of decimal digits, zero-padded to the left to a width of
+/// 20 digits by default, which is enough to represent any [`u64`]. A smaller width can be
+/// configured with [`ConstantSizeId::with_width`] to make requests smaller.
+///
 /// # Examples
 ///
 /// ```rust
@@ -73,12 +79,24 @@ impl Display for Id {
 ///     serde_json::to_vec(&request_2).unwrap().len()
 /// );
 /// ```
-#[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
-pub struct ConstantSizeId(u64);
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ConstantSizeId {
+    value: u64,
+    width: u8,
+}
 
 impl<T: Into<u64>> From<T> for ConstantSizeId {
     fn from(value: T) -> Self {
-        Self(value.into())
+        Self {
+            value: value.into(),
+            width: Self::DEFAULT_WIDTH,
+        }
+    }
+}
+
+impl Default for ConstantSizeId {
+    fn default() -> Self {
+        Self::ZERO
     }
 }
 
@@ -89,15 +107,68 @@ impl Display for ConstantSizeId {
 }
 
 impl ConstantSizeId {
+    /// Default number of digits, which is enough to represent any [`u64`]:
+    /// 19 < log_10(u64::MAX) < 20.
+    pub const DEFAULT_WIDTH: u8 = 20;
     /// Zero numeric ID.
-    pub const ZERO: ConstantSizeId = ConstantSizeId(0);
+    pub const ZERO: ConstantSizeId = ConstantSizeId {
+        value: 0,
+        width: Self::DEFAULT_WIDTH,
+    };
     /// Largest ID.
-    pub const MAX: ConstantSizeId = ConstantSizeId(u64::MAX);
+    pub const MAX: ConstantSizeId = ConstantSizeId {
+        value: u64::MAX,
+        width: Self::DEFAULT_WIDTH,
+    };
+
+    /// Change the number of digits of the ID.
+    ///
+    /// The ID only has a constant size as long as its value is at most
+    /// [`ConstantSizeId::max_value`], which [`ConstantSizeId::get_and_increment`] ensures.
+    ///
+    /// # Panics
+    ///
+    /// If the width is 0 or larger than [`ConstantSizeId::DEFAULT_WIDTH`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::http::json::ConstantSizeId;
+    ///
+    /// let id = ConstantSizeId::from(42_u8).with_width(4);
+    /// assert_eq!(id.to_string(), "0042");
+    /// assert_eq!(id.max_value(), 9_999);
+    /// ```
+    pub fn with_width(self, width: u8) -> Self {
+        assert!(
+            (1..=Self::DEFAULT_WIDTH).contains(&width),
+            "Width must be between 1 and {}, but got {width}",
+            Self::DEFAULT_WIDTH
+        );
+        Self { width, ..self }
+    }
+
+    /// Returns the numeric value of the ID.
+    pub fn get(&self) -> u64 {
+        self.value
+    }
+
+    /// Returns the number of digits of the ID.
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Returns the largest value that can be represented with the width of the ID.
+    pub fn max_value(&self) -> u64 {
+        10_u64
+            .checked_pow(u32::from(self.width))
+            .map_or(u64::MAX, |bound| bound - 1)
+    }
 
     /// Increment the current value and return the previous value.
     ///
-    /// If the maximum ID is reached, the next value will be wrapped to [`Self::ZERO`].
-    /// This method never panics.
+    /// If the maximum value for the width of the ID is reached, the next value will be wrapped
+    /// to zero. This method never panics.
     ///
     /// # Examples
     ///
@@ -112,17 +183,23 @@ impl ConstantSizeId {
     /// let mut id = ConstantSizeId::MAX;
     /// assert_eq!(id.get_and_increment(), u64::MAX.into());
     /// assert_eq!(id.get_and_increment(), 0_u64.into());
+    ///
+    /// let mut id = ConstantSizeId::from(99_u8).with_width(2);
+    /// assert_eq!(id.get_and_increment().to_string(), "99");
+    /// assert_eq!(id.get_and_increment().to_string(), "00");
     /// ```
     pub fn get_and_increment(&mut self) -> ConstantSizeId {
-        let previous = self.0;
-        self.0 = self.0.wrapping_add(1);
-        ConstantSizeId::from(previous)
+        let previous = self.clone();
+        self.value = if self.value >= self.max_value() {
+            0
+        } else {
+            self.value + 1
+        };
+        previous
     }
 
     fn to_constant_size_string(&self) -> String {
-        // Need at most 20 decimal characters to represent a u64:
-        // 19 < log_10(u64::MAX) < 20
-        format!("{:0>20}", self.0)
+        format!("{:0>width$}", self.value, width = usize::from(self.width))
     }
 }
 
@@ -132,6 +209,10 @@ impl From<ConstantSizeId> for Id {
     }
 }
 
+/// Parse an ID from its decimal representation.
+///
+/// The width of the parsed ID is the number of digits of the string, or
+/// [`ConstantSizeId::DEFAULT_WIDTH`] if the string has more digits (extra padding is ignored).
 impl FromStr for ConstantSizeId {
     type Err = ParseIntError;
 
@@ -139,7 +220,111 @@ impl FromStr for ConstantSizeId {
         let num = match s.find(|c| c != '0') {
             Some(non_zero_index) => s[non_zero_index..].parse::<u64>(),
             None => s.parse::<u64>(),
-        };
-        num.map(ConstantSizeId::from)
+        }?;
+        let width = u8::try_from(s.len())
+            .unwrap_or(Self::DEFAULT_WIDTH)
+            .clamp(1, Self::DEFAULT_WIDTH);
+        Ok(ConstantSizeId::from(num).with_width(width))
+    }
+}
+
+/// Counter of [`ConstantSizeId`]s persisted in stable memory, so that IDs are not reused
+/// after a canister upgrade.
+///
+/// The counter occupies [`StableIdCounter::SIZE_IN_BYTES`] bytes of stable memory starting
+/// at a given offset. The value is written to stable memory every time an ID is generated,
+/// so that no special handling is needed in the upgrade hooks of the canister.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{ConstantSizeId, StableIdCounter};
+///
+/// // Reserve 16 bytes of stable memory starting at offset 0 for the counter.
+/// let counter = StableIdCounter::new(0, ConstantSizeId::from(1_u8).with_width(8));
+/// # let memory = ic_stable_structures::VectorMemory::default();
+/// # let counter = StableIdCounter::with_memory(memory.clone(), 0, ConstantSizeId::from(1_u8).with_width(8));
+/// assert_eq!(counter.get_and_increment().unwrap().to_string(), "00000001");
+/// assert_eq!(counter.get_and_increment().unwrap().to_string(), "00000002");
+///
+/// // After an upgrade
+/// let counter = StableIdCounter::new(0, ConstantSizeId::from(1_u8).with_width(8));
+/// # let counter = StableIdCounter::with_memory(memory, 0, ConstantSizeId::from(1_u8).with_width(8));
+/// assert_eq!(counter.get_and_increment().unwrap().to_string(), "00000003");
+/// ```
+#[derive(Clone, Debug)]
+pub struct StableIdCounter<M = CanisterStableMemory> {
+    memory: M,
+    offset: u64,
+    first: ConstantSizeId,
+}
+
+/// Error returned by [`StableIdCounter`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum StableIdCounterError {
+    /// The stable memory could not be grown to hold the counter.
+    #[error("Failed to grow stable memory to {required_pages} pages")]
+    OutOfMemory {
+        /// Number of pages required to hold the counter.
+        required_pages: u64,
+    },
+}
+
+/// Marker written before the value, to distinguish a counter from uninitialized memory.
+const STABLE_ID_COUNTER_MAGIC: [u8; 8] = *b"CSIDCTR1";
+
+impl StableIdCounter {
+    /// Create a new [`StableIdCounter`] in the canister stable memory starting at `offset`.
+    ///
+    /// The counter starts at `first` if it was never persisted, and its IDs have the width
+    /// of `first`. The region must not be used for anything else by the canister.
+    pub fn new(offset: u64, first: ConstantSizeId) -> Self {
+        Self::with_memory(CanisterStableMemory, offset, first)
+    }
+}
+
+impl<M: Memory> StableIdCounter<M> {
+    /// Number of bytes of stable memory occupied by the counter.
+    pub const SIZE_IN_BYTES: u64 = 16;
+
+    /// Create a new [`StableIdCounter`] in the given memory starting at `offset`.
+    pub fn with_memory(memory: M, offset: u64, first: ConstantSizeId) -> Self {
+        Self {
+            memory,
+            offset,
+            first,
+        }
+    }
+
+    /// Returns the current ID and persists the incremented ID.
+    pub fn get_and_increment(&self) -> Result<ConstantSizeId, StableIdCounterError> {
+        let mut next = self.read();
+        let current = next.get_and_increment();
+        self.write(&next)?;
+        Ok(current)
+    }
+
+    fn read(&self) -> ConstantSizeId {
+        let end = self.offset + Self::SIZE_IN_BYTES;
+        if !region::has_size(&self.memory, end) {
+            return self.first.clone();
+        }
+        let mut bytes = [0_u8; 16];
+        self.memory.read(self.offset, &mut bytes);
+        if bytes[..8] != STABLE_ID_COUNTER_MAGIC {
+            return self.first.clone();
+        }
+        let value = u64::from_le_bytes(bytes[8..].try_into().expect("BUG: expected 8 bytes"));
+        ConstantSizeId::from(value).with_width(self.first.width())
+    }
+
+    fn write(&self, id: &ConstantSizeId) -> Result<(), StableIdCounterError> {
+        region::grow_to(&self.memory, self.offset + Self::SIZE_IN_BYTES)
+            .map_err(|required_pages| StableIdCounterError::OutOfMemory { required_pages })?;
+        let mut bytes = [0_u8; 16];
+        bytes[..8].copy_from_slice(&STABLE_ID_COUNTER_MAGIC);
+        bytes[8..].copy_from_slice(&id.get().to_le_bytes());
+        self.memory.write(self.offset, &bytes);
+        Ok(())
     }
 }
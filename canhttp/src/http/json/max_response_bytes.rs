@@ -0,0 +1,145 @@
+use crate::{
+    convert::{ConvertRequest, ConvertRequestLayer, Filter},
+    http::json::{HttpBatchJsonRpcRequest, HttpJsonRpcRequest},
+    MaxResponseBytesRequestExtension,
+};
+use std::{collections::BTreeMap, convert::Infallible};
+use tower_layer::Layer;
+
+// This constant comes from the IC specification:
+// > If provided, the value must not exceed 2MB
+const HTTP_MAX_SIZE: u64 = 2_000_000;
+
+/// Set `max_response_bytes` on JSON-RPC requests depending on their method,
+/// when the caller did not set a value.
+///
+/// Without a value for `max_response_bytes`, an HTTPs outcall is charged for the maximum
+/// response size of 2MB, which is wasteful for methods with small responses such as
+/// `eth_blockNumber`, while other methods such as `eth_getLogs` need more room.
+///
+/// A request whose method has no hint gets the [default](Self::default_max_response_bytes),
+/// if any, and is otherwise left unchanged. For a batch request, the hints of all its requests
+/// are added up (up to 2MB), unless one of them is missing.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MaxResponseBytesHints {
+    hints: BTreeMap<String, u64>,
+    default: Option<u64>,
+}
+
+impl MaxResponseBytesHints {
+    /// Create a new instance without any hint.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given `max_response_bytes` for requests with the given method.
+    pub fn method(mut self, method: impl Into<String>, max_response_bytes: u64) -> Self {
+        self.hints.insert(method.into(), max_response_bytes);
+        self
+    }
+
+    /// Use the given `max_response_bytes` for requests whose method has no hint.
+    pub fn default_max_response_bytes(mut self, max_response_bytes: u64) -> Self {
+        self.default = Some(max_response_bytes);
+        self
+    }
+
+    /// Returns the `max_response_bytes` to use for a request with the given method, if any.
+    pub fn get(&self, method: &str) -> Option<u64> {
+        self.hints.get(method).copied().or(self.default)
+    }
+}
+
+impl<T> Filter<HttpJsonRpcRequest<T>> for MaxResponseBytesHints {
+    type Error = Infallible;
+
+    fn filter(
+        &mut self,
+        mut request: HttpJsonRpcRequest<T>,
+    ) -> Result<HttpJsonRpcRequest<T>, Self::Error> {
+        if request.get_max_response_bytes().is_none() {
+            if let Some(max_response_bytes) = self.get(request.body().method()) {
+                request.set_max_response_bytes(max_response_bytes);
+            }
+        }
+        Ok(request)
+    }
+}
+
+impl<T> Filter<HttpBatchJsonRpcRequest<T>> for MaxResponseBytesHints {
+    type Error = Infallible;
+
+    fn filter(
+        &mut self,
+        mut request: HttpBatchJsonRpcRequest<T>,
+    ) -> Result<HttpBatchJsonRpcRequest<T>, Self::Error> {
+        if request.get_max_response_bytes().is_none() && !request.body().is_empty() {
+            let total = request
+                .body()
+                .iter()
+                .map(|request| self.get(request.method()))
+                .sum::<Option<u64>>();
+            if let Some(total) = total {
+                request.set_max_response_bytes(total.min(HTTP_MAX_SIZE));
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Middleware that sets `max_response_bytes` on JSON-RPC requests depending on their method,
+/// see [`MaxResponseBytesHints`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{
+///     http::json::{HttpJsonRpcRequest, JsonRpcRequest, MaxResponseBytesHints, MaxResponseBytesHintsLayer},
+///     MaxResponseBytesRequestExtension,
+/// };
+/// use serde_json::{json, Value};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(MaxResponseBytesHintsLayer::new(
+///         MaxResponseBytesHints::new()
+///             .method("eth_blockNumber", 256)
+///             .method("eth_getLogs", 1_000_000),
+///     ))
+///     .service_fn(|request: HttpJsonRpcRequest<Value>| async move {
+///         Ok::<_, BoxError>(request.get_max_response_bytes())
+///     });
+///
+/// let request = http::Request::post("https://ethereum.publicnode.com")
+///     .body(JsonRpcRequest::new("eth_blockNumber", json!([])))?;
+/// assert_eq!(service.ready().await?.call(request).await?, Some(256));
+///
+/// // A value set by the caller takes precedence.
+/// let request = http::Request::post("https://ethereum.publicnode.com")
+///     .max_response_bytes(500_000)
+///     .body(JsonRpcRequest::new("eth_getLogs", json!([{"fromBlock": "latest"}])))?;
+/// assert_eq!(service.ready().await?.call(request).await?, Some(500_000));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MaxResponseBytesHintsLayer {
+    hints: MaxResponseBytesHints,
+}
+
+impl MaxResponseBytesHintsLayer {
+    /// Returns a new [`MaxResponseBytesHintsLayer`] using the given hints.
+    pub fn new(hints: MaxResponseBytesHints) -> Self {
+        Self { hints }
+    }
+}
+
+impl<S> Layer<S> for MaxResponseBytesHintsLayer {
+    type Service = ConvertRequest<S, MaxResponseBytesHints>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.hints.clone()).layer(inner)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::http::json::{HttpJsonRpcRequest, HttpJsonRpcResponse, JsonRpcRequest, JsonRpcResult};
+use std::future::Future;
+use tower::{BoxError, Service, ServiceExt};
+
+/// A JSON-RPC method with typed parameters and result.
+///
+/// Implementing this trait once per method of a JSON-RPC API turns typos in method names and
+/// mismatches between the parameters or result and the method into compile-time errors, see
+/// [`JsonRpcRequest::from_method`] and [`JsonRpcServiceExt::call_method`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{JsonRpcMethod, JsonRpcRequest};
+/// use serde_json::json;
+///
+/// struct GetBalance;
+///
+/// impl JsonRpcMethod for GetBalance {
+///     const METHOD_NAME: &'static str = "getBalance";
+///     type Params = (String,);
+///     type Result = u64;
+/// }
+///
+/// let request = JsonRpcRequest::from_method::<GetBalance>(("83astBRguLMdt2h5U1Tpdq5tjFoJ6noeGwaY3mDLVcri".to_string(),));
+///
+/// assert_eq!(request.method(), "getBalance");
+/// ```
+pub trait JsonRpcMethod {
+    /// Name of the method, e.g. `eth_getBlockByNumber`.
+    const METHOD_NAME: &'static str;
+
+    /// Type of the parameters of the method.
+    type Params;
+
+    /// Type of the result of the method.
+    type Result;
+}
+
+impl<T> JsonRpcRequest<T> {
+    /// Create a new body of a JSON-RPC request for the given method.
+    ///
+    /// Like [`JsonRpcRequest::new`], the request has the default ID.
+    pub fn from_method<M: JsonRpcMethod<Params = T>>(params: T) -> Self {
+        Self::new(M::METHOD_NAME, params)
+    }
+}
+
+/// Extension trait to call typed JSON-RPC methods with a JSON-RPC over HTTP [`Service`],
+/// see [`JsonRpcMethod`].
+///
+/// This trait is implemented for all types.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{
+///     HttpJsonRpcRequest, JsonRpcMethod, JsonRpcResponse, JsonRpcServiceExt,
+/// };
+/// use tower::BoxError;
+///
+/// struct GetSlot;
+///
+/// impl JsonRpcMethod for GetSlot {
+///     const METHOD_NAME: &'static str = "getSlot";
+///     type Params = ();
+///     type Result = u64;
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = tower::service_fn(|request: HttpJsonRpcRequest<()>| async move {
+///     assert_eq!(request.body().method(), "getSlot");
+///     Ok::<_, BoxError>(http::Response::new(JsonRpcResponse::from_ok(
+///         request.body().id().clone(),
+///         42_u64,
+///     )))
+/// });
+///
+/// let request = http::Request::post("https://api.mainnet-beta.solana.com");
+/// let slot = service.call_method::<GetSlot>(request, ()).await?;
+///
+/// assert_eq!(slot, Ok(42));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Service`]: tower::Service
+pub trait JsonRpcServiceExt {
+    /// Send a JSON-RPC request for the method `M` with the given parameters, using the given
+    /// builder for the HTTP part of the request, once the service is ready.
+    fn call_method<M>(
+        &mut self,
+        request: http::request::Builder,
+        params: M::Params,
+    ) -> impl Future<Output = Result<JsonRpcResult<M::Result>, BoxError>>
+    where
+        M: JsonRpcMethod,
+        Self: Service<HttpJsonRpcRequest<M::Params>, Response = HttpJsonRpcResponse<M::Result>>
+            + Sized,
+        <Self as Service<HttpJsonRpcRequest<M::Params>>>::Error: Into<BoxError>,
+    {
+        async move {
+            let request = request.body(JsonRpcRequest::from_method::<M>(params))?;
+            let response = self
+                .ready()
+                .await
+                .map_err(Into::into)?
+                .call(request)
+                .await
+                .map_err(Into::into)?;
+            Ok(response.into_body().into_result())
+        }
+    }
+}
+
+impl<S> JsonRpcServiceExt for S {}
@@ -51,7 +51,7 @@
 //! ```
 //!
 //! [`Service`]: tower::Service
-use crate::convert::CreateResponseFilter;
+use crate::convert::{CreateResponseFilter, Filter};
 use crate::{
     convert::{
         ConvertRequest, ConvertRequestLayer, ConvertResponse, ConvertResponseLayer,
@@ -59,15 +59,25 @@ use crate::{
     },
     http::{HttpConversionLayer, HttpRequestConverter, HttpResponseConverter},
 };
-pub use id::{ConstantSizeId, Id};
+pub use auto_id::{AssignJsonRpcId, JsonRpcIdLayer};
+pub use chunk::{BatchChunking, BatchChunkingLayer};
+pub use heterogeneous::{BatchEntry, BatchEntryError, HeterogeneousBatch};
+pub use id::{ConstantSizeId, Id, StableIdCounter, StableIdCounterError};
+pub use max_response_bytes::{MaxResponseBytesHints, MaxResponseBytesHintsLayer};
+pub use method::{JsonRpcMethod, JsonRpcServiceExt};
+pub use params::{NamedParams, NamedParamsError};
 pub use request::{
-    BatchJsonRpcRequest, HttpBatchJsonRpcRequest, HttpJsonRpcRequest, JsonRequestConversionError,
-    JsonRequestConverter, JsonRpcRequest,
+    BatchJsonRpcRequest, HttpBatchJsonRpcRequest, HttpJsonRpcNotification, HttpJsonRpcRequest,
+    JsonRequestConversionError, JsonRequestConverter, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcRequestFilter,
 };
 pub use response::{
     BatchJsonRpcResponse, ConsistentJsonRpcIdFilter, ConsistentResponseIdFilterError,
-    CreateJsonRpcIdFilter, HttpBatchJsonRpcResponse, HttpJsonRpcResponse,
-    JsonResponseConversionError, JsonResponseConverter, JsonRpcError, JsonRpcResponse,
+    CreateJsonRpcIdFilter, HttpBatchJsonRpcResponse, HttpJsonRpcResponse, HttpJsonRpcResponseError,
+    HttpJsonRpcResponseExtension, IdMatching, JsonResponseConversionError, JsonResponseConverter,
+    JsonResponseExtension, JsonRpcError, JsonRpcErrorCode, JsonRpcLeniency, JsonRpcResponse,
+    JsonRpcResult, NdJsonResponseConversionError, NdJsonResponseConverter,
+    NotificationResponseConverter,
 };
 use serde::{de::DeserializeOwned, Serialize};
 use std::{fmt::Debug, marker::PhantomData};
@@ -77,9 +87,21 @@ pub use version::Version;
 #[cfg(test)]
 mod tests;
 
+mod auto_id;
+#[cfg(feature = "bitcoin")]
+pub mod bitcoin;
+mod chunk;
+#[cfg(feature = "eth")]
+pub mod eth;
+mod heterogeneous;
 mod id;
+mod max_response_bytes;
+mod method;
+mod params;
 mod request;
 mod response;
+#[cfg(feature = "solana")]
+pub mod solana;
 mod version;
 
 /// Middleware that combines [`JsonRequestConverter`] to convert requests
@@ -140,9 +162,14 @@ where
 /// [`JsonRpcRequest`] and [`JsonRpcResponse`]) or with batch JSON-RPC requests and responses
 /// (i.e. [`BatchJsonRpcRequest`] and [`BatchJsonRpcResponse`]).
 ///
-/// This middleware includes a [`ConsistentJsonRpcIdFilter`], which ensures that each response
+/// This middleware includes a [`JsonRpcRequestFilter`], which rejects empty batches and batches
+/// containing several requests with the same ID before they are sent.
+///
+/// This middleware also includes a [`ConsistentJsonRpcIdFilter`], which ensures that each response
 /// carries a valid JSON-RPC ID matching the corresponding request ID. This guarantees that the
 /// [`Service`] complies with the [JSON-RPC 2.0 specification].
+/// For batches, the filter also reorders the responses to match the order of the requests,
+/// so that responses can be correlated to requests by their position in the batch.
 ///
 /// # Examples
 ///
@@ -200,6 +227,9 @@ where
 /// [JSON-RPC 2.0 specification]: https://www.jsonrpc.org/specification
 #[derive(Debug)]
 pub struct JsonRpcHttpLayer<Request, Response> {
+    id_matching: IdMatching,
+    leniency: JsonRpcLeniency,
+    strict_version: bool,
     _marker: PhantomData<(Request, Response)>,
 }
 
@@ -207,14 +237,58 @@ impl<Request, Response> JsonRpcHttpLayer<Request, Response> {
     /// Returns a new [`JsonRpcHttpLayer`].
     pub fn new() -> Self {
         Self {
+            id_matching: IdMatching::default(),
+            leniency: JsonRpcLeniency::default(),
+            strict_version: false,
             _marker: PhantomData,
         }
     }
+
+    /// Set how response IDs are matched against request IDs.
+    ///
+    /// Defaults to [`IdMatching::Strict`].
+    pub fn with_id_matching(mut self, id_matching: IdMatching) -> Self {
+        self.id_matching = id_matching;
+        self
+    }
+
+    /// Tolerate the given deviations from the JSON-RPC specification when parsing responses.
+    ///
+    /// Defaults to [`JsonRpcLeniency::default`], which does not tolerate any deviation.
+    pub fn with_leniency(mut self, leniency: JsonRpcLeniency) -> Self {
+        self.leniency = leniency;
+        self
+    }
+
+    /// Reject responses whose `jsonrpc` member is not exactly `"2.0"` with
+    /// [`JsonResponseConversionError::UnsupportedVersion`], even if the
+    /// [leniency](Self::with_leniency) would tolerate a missing or legacy version.
+    ///
+    /// Without this, such responses are rejected as invalid JSON-RPC responses,
+    /// unless tolerated by the leniency.
+    pub fn with_strict_version(mut self) -> Self {
+        self.strict_version = true;
+        self
+    }
+}
+
+impl<Request, Response> JsonRpcHttpLayer<Request, Response> {
+    fn response_converter(&self) -> JsonResponseConverter<Response> {
+        let converter = JsonResponseConverter::new().with_leniency(self.leniency);
+        if self.strict_version {
+            converter.with_strict_version()
+        } else {
+            converter
+        }
+    }
 }
 
 impl<Request, Response> Clone for JsonRpcHttpLayer<Request, Response> {
     fn clone(&self) -> Self {
         Self {
+            id_matching: self.id_matching,
+            leniency: self.leniency,
+            strict_version: self.strict_version,
             _marker: self._marker,
         }
     }
@@ -230,25 +304,131 @@ impl<Request, Response, S> Layer<S> for JsonRpcHttpLayer<Request, Response>
 where
     Request: Serialize,
     Response: DeserializeOwned,
+    JsonRpcRequestFilter: Filter<http::Request<Request>>,
     CreateJsonRpcIdFilter<Request, Response>:
         CreateResponseFilter<http::Request<Request>, http::Response<Response>>,
 {
-    type Service = FilterResponse<
-        ConvertResponse<
-            ConvertRequest<
-                ConvertResponse<ConvertRequest<S, HttpRequestConverter>, HttpResponseConverter>,
-                JsonRequestConverter<Request>,
+    type Service = ConvertRequest<
+        FilterResponse<
+            ConvertResponse<
+                ConvertRequest<
+                    ConvertResponse<ConvertRequest<S, HttpRequestConverter>, HttpResponseConverter>,
+                    JsonRequestConverter<Request>,
+                >,
+                JsonResponseConverter<Response>,
             >,
-            JsonResponseConverter<Response>,
+            CreateJsonRpcIdFilter<Request, Response>,
+        >,
+        JsonRpcRequestFilter,
+    >;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let service = stack(
+            HttpConversionLayer,
+            Stack::new(
+                ConvertRequestLayer::new(JsonRequestConverter::<Request>::new()),
+                ConvertResponseLayer::new(self.response_converter()),
+            ),
+            CreateResponseFilterLayer::new(CreateJsonRpcIdFilter::with_id_matching(
+                self.id_matching,
+            )),
+        )
+        .layer(inner);
+        ConvertRequestLayer::new(JsonRpcRequestFilter::with_id_matching(self.id_matching))
+            .layer(service)
+    }
+}
+
+/// Middleware that combines an [`HttpConversionLayer`], a [`JsonRequestConverter`] and a
+/// [`NotificationResponseConverter`] to create a [`Service`] sending JSON-RPC notifications
+/// over HTTP.
+///
+/// Since the server does not reply to notifications, the response body is discarded without
+/// being parsed and no response ID is checked, so that empty and `204 No Content` responses
+/// are accepted. The status of the response is still available to the caller.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{
+///     HttpJsonRpcNotification, JsonRpcNotification, JsonRpcNotificationHttpLayer,
+/// };
+/// use http::StatusCode;
+/// use ic_cdk_management_canister::{
+///     HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+/// };
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(JsonRpcNotificationHttpLayer::<[u64; 1]>::new())
+///     .service_fn(|request: IcHttpRequest| async move {
+///         assert_eq!(
+///             request.body.unwrap(),
+///             br#"{"jsonrpc":"2.0","method":"cancelSubscription","params":[42]}"#
+///         );
+///         Ok::<_, BoxError>(IcHttpResponse {
+///             status: 204_u16.into(),
+///             ..Default::default()
+///         })
+///     });
+///
+/// let notification: HttpJsonRpcNotification<[u64; 1]> = http::Request::post("https://rpc.example.com")
+///     .body(JsonRpcNotification::new("cancelSubscription", [42]))?;
+/// let response = service.ready().await?.call(notification).await?;
+///
+/// assert_eq!(response.status(), StatusCode::NO_CONTENT);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Service`]: tower::Service
+#[derive(Debug)]
+pub struct JsonRpcNotificationHttpLayer<Params> {
+    _marker: PhantomData<Params>,
+}
+
+impl<Params> JsonRpcNotificationHttpLayer<Params> {
+    /// Returns a new [`JsonRpcNotificationHttpLayer`].
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Params> Clone for JsonRpcNotificationHttpLayer<Params> {
+    fn clone(&self) -> Self {
+        Self {
+            _marker: self._marker,
+        }
+    }
+}
+
+impl<Params> Default for JsonRpcNotificationHttpLayer<Params> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Params, S> Layer<S> for JsonRpcNotificationHttpLayer<Params>
+where
+    Params: Serialize,
+{
+    type Service = ConvertResponse<
+        ConvertRequest<
+            ConvertResponse<ConvertRequest<S, HttpRequestConverter>, HttpResponseConverter>,
+            JsonRequestConverter<JsonRpcNotification<Params>>,
         >,
-        CreateJsonRpcIdFilter<Request, Response>,
+        NotificationResponseConverter,
     >;
 
     fn layer(&self, inner: S) -> Self::Service {
         stack(
             HttpConversionLayer,
-            JsonConversionLayer::<Request, Response>::new(),
-            CreateResponseFilterLayer::new(CreateJsonRpcIdFilter::new()),
+            ConvertRequestLayer::new(JsonRequestConverter::<JsonRpcNotification<Params>>::new()),
+            ConvertResponseLayer::new(NotificationResponseConverter),
         )
         .layer(inner)
     }
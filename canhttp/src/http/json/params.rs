@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// Named JSON-RPC params, i.e. params given by-name as a JSON object instead of by-position
+/// as a JSON array, see the [specification].
+///
+/// Many APIs (e.g. Ethereum tracing or Tendermint) require named params. Instead of writing them
+/// by hand with `json!`, the params can be built from any struct serialized as a JSON object
+/// and completed with extra fields.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{JsonRpcRequest, NamedParams};
+/// use serde::Serialize;
+/// use serde_json::json;
+///
+/// #[derive(Serialize)]
+/// struct BlockParams {
+///     height: String,
+/// }
+///
+/// let params = NamedParams::from_struct(&BlockParams { height: "42".to_string() })?
+///     .with("prove", true);
+/// let request = JsonRpcRequest::new("block", params).with_id(1_u64);
+///
+/// assert_eq!(
+///     serde_json::to_value(&request)?,
+///     json!({
+///         "jsonrpc": "2.0",
+///         "method": "block",
+///         "params": {"height": "42", "prove": true},
+///         "id": 1
+///     })
+/// );
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [specification]: https://www.jsonrpc.org/specification#parameter_structures
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NamedParams(Map<String, Value>);
+
+impl NamedParams {
+    /// Create new empty named params.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create named params from the fields of the given value,
+    /// which must be serialized as a JSON object.
+    pub fn from_struct<T: Serialize>(params: &T) -> Result<Self, NamedParamsError> {
+        Self::new().merge(params)
+    }
+
+    /// Set the param with the given name, replacing any previous value.
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.0.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set all the fields of the given value as params, replacing any previous value
+    /// with the same name.
+    ///
+    /// The value must be serialized as a JSON object.
+    pub fn merge<T: Serialize>(mut self, params: &T) -> Result<Self, NamedParamsError> {
+        match serde_json::to_value(params) {
+            Ok(Value::Object(fields)) => {
+                self.0.extend(fields);
+                Ok(self)
+            }
+            Ok(other) => Err(NamedParamsError::NotAnObject {
+                kind: kind(&other).to_string(),
+            }),
+            Err(e) => Err(NamedParamsError::SerializationError {
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    /// Returns the value of the param with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// Returns the number of params.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no params.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the params as a JSON object.
+    pub fn into_inner(self) -> Map<String, Value> {
+        self.0
+    }
+}
+
+impl From<Map<String, Value>> for NamedParams {
+    fn from(fields: Map<String, Value>) -> Self {
+        Self(fields)
+    }
+}
+
+impl From<NamedParams> for Value {
+    fn from(params: NamedParams) -> Self {
+        Value::Object(params.0)
+    }
+}
+
+/// Error returned when building [`NamedParams`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum NamedParamsError {
+    /// The value could not be serialized to JSON.
+    #[error("Failed to serialize named params: {error}")]
+    SerializationError {
+        /// Serialization error.
+        error: String,
+    },
+    /// The value was not serialized as a JSON object.
+    #[error("Expected named params to be serialized as a JSON object, but got {kind}")]
+    NotAnObject {
+        /// Kind of JSON value that was obtained instead, e.g. `array`.
+        kind: String,
+    },
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
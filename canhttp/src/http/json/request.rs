@@ -1,13 +1,13 @@
 use crate::{
-    convert::Convert,
+    convert::{Convert, Filter},
     http::{
-        json::{ConstantSizeId, Id, Version},
+        json::{ConstantSizeId, Id, IdMatching, Version},
         HttpRequest,
     },
 };
 use http::{header::CONTENT_TYPE, HeaderValue};
 use serde::{Deserialize, Serialize};
-use std::marker::PhantomData;
+use std::{collections::BTreeSet, marker::PhantomData};
 use thiserror::Error;
 
 /// Convert requests of type [`http::Request<T>`], where `T` is `Serializable`,
@@ -41,12 +41,82 @@ impl<T> Default for JsonRequestConverter<T> {
     }
 }
 
-/// Error return when converting requests with [`JsonRequestConverter`].
+/// Error return when converting requests with [`JsonRequestConverter`]
+/// or when requests are rejected by a [`JsonRpcRequestFilter`].
 #[derive(Error, Clone, Debug, Eq, PartialEq)]
 pub enum JsonRequestConversionError {
     /// Request body failed to be serialized.
     #[error("Invalid JSON body: {0}")]
     InvalidJson(String),
+    /// Several requests of a batch have the same ID.
+    #[error("Invalid batch JSON-RPC request: duplicate ID {id}")]
+    DuplicateId {
+        /// ID used by more than one request of the batch.
+        id: Id,
+    },
+    /// The batch does not contain any request, which is invalid according to the
+    /// [JSON-RPC specification](https://www.jsonrpc.org/specification#batch).
+    #[error("Invalid batch JSON-RPC request: empty batch")]
+    EmptyBatch,
+}
+
+/// Reject invalid JSON-RPC requests, *before* they are serialized and sent.
+///
+/// A batch request is rejected with [`JsonRequestConversionError::EmptyBatch`] if it does not
+/// contain any request, and with [`JsonRequestConversionError::DuplicateId`] if several of its
+/// requests have the same ID according to the [`IdMatching`], since the responses could then not
+/// be correlated with the requests and would only be rejected once the HTTPs outcall was paid for.
+/// Single requests are always accepted.
+///
+/// This filter is part of the [`JsonRpcHttpLayer`](crate::http::json::JsonRpcHttpLayer).
+#[derive(Clone, Debug, Default)]
+pub struct JsonRpcRequestFilter {
+    id_matching: IdMatching,
+}
+
+impl JsonRpcRequestFilter {
+    /// Create a new [`JsonRpcRequestFilter`] with [`IdMatching::Strict`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new [`JsonRpcRequestFilter`] where IDs are compared with the given [`IdMatching`].
+    pub fn with_id_matching(id_matching: IdMatching) -> Self {
+        Self { id_matching }
+    }
+}
+
+impl<T> Filter<HttpJsonRpcRequest<T>> for JsonRpcRequestFilter {
+    type Error = JsonRequestConversionError;
+
+    fn filter(
+        &mut self,
+        request: HttpJsonRpcRequest<T>,
+    ) -> Result<HttpJsonRpcRequest<T>, Self::Error> {
+        Ok(request)
+    }
+}
+
+impl<T> Filter<HttpBatchJsonRpcRequest<T>> for JsonRpcRequestFilter {
+    type Error = JsonRequestConversionError;
+
+    fn filter(
+        &mut self,
+        request: HttpBatchJsonRpcRequest<T>,
+    ) -> Result<HttpBatchJsonRpcRequest<T>, Self::Error> {
+        if request.body().is_empty() {
+            return Err(JsonRequestConversionError::EmptyBatch);
+        }
+        let mut keys = BTreeSet::new();
+        if let Some(id) = request
+            .body()
+            .ids()
+            .find(|id| !keys.insert(self.id_matching.key(id)))
+        {
+            return Err(JsonRequestConversionError::DuplicateId { id: id.clone() });
+        }
+        Ok(request)
+    }
 }
 
 impl<T> Convert<http::Request<T>> for JsonRequestConverter<T>
@@ -88,10 +158,136 @@ pub type HttpBatchJsonRpcRequest<T> = http::Request<BatchJsonRpcRequest<T>>;
 /// JSON-RPC request over HTTP.
 pub type HttpJsonRpcRequest<T> = http::Request<JsonRpcRequest<T>>;
 
+/// JSON-RPC notification over HTTP.
+pub type HttpJsonRpcNotification<T> = http::Request<JsonRpcNotification<T>>;
+
 /// Batch JSON-RPC request body, see the [specification].
 ///
 /// [specification]: https://www.jsonrpc.org/specification
-pub type BatchJsonRpcRequest<T> = Vec<JsonRpcRequest<T>>;
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BatchJsonRpcRequest<T>(Vec<JsonRpcRequest<T>>);
+
+impl<T> BatchJsonRpcRequest<T> {
+    /// Create a new empty batch.
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Returns the number of requests in the batch.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the batch contains no requests.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the requests in the batch.
+    pub fn iter(&self) -> std::slice::Iter<'_, JsonRpcRequest<T>> {
+        self.0.iter()
+    }
+
+    /// Returns an iterator that allows modifying each request in the batch.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, JsonRpcRequest<T>> {
+        self.0.iter_mut()
+    }
+
+    /// Appends a request to the back of the batch.
+    pub fn push(&mut self, request: JsonRpcRequest<T>) {
+        self.0.push(request);
+    }
+
+    /// Retains only the requests specified by the predicate, preserving their order.
+    pub fn retain(&mut self, f: impl FnMut(&JsonRpcRequest<T>) -> bool) {
+        self.0.retain(f);
+    }
+
+    /// Returns an iterator over the IDs of the requests in the batch.
+    pub fn ids(&self) -> impl Iterator<Item = &Id> {
+        self.0.iter().map(|request| request.id())
+    }
+
+    /// Returns the first ID used by more than one request in the batch, if any.
+    pub fn find_duplicate_id(&self) -> Option<&Id> {
+        let mut ids = BTreeSet::new();
+        self.ids().find(|id| !ids.insert(*id))
+    }
+
+    /// Returns the first request in the batch with the given ID, if any.
+    pub fn get_by_id(&self, id: &Id) -> Option<&JsonRpcRequest<T>> {
+        self.0.iter().find(|request| request.id() == id)
+    }
+
+    /// Returns a mutable reference to the first request in the batch with the given ID, if any.
+    pub fn get_by_id_mut(&mut self, id: &Id) -> Option<&mut JsonRpcRequest<T>> {
+        self.0.iter_mut().find(|request| request.id() == id)
+    }
+
+    /// Removes and returns the first request in the batch with the given ID, if any.
+    pub fn remove_by_id(&mut self, id: &Id) -> Option<JsonRpcRequest<T>> {
+        let index = self.0.iter().position(|request| request.id() == id)?;
+        Some(self.0.remove(index))
+    }
+
+    /// Converts the batch into a vector of requests.
+    pub fn into_vec(self) -> Vec<JsonRpcRequest<T>> {
+        self.0
+    }
+}
+
+// #[derive(Default)] would otherwise introduce a bound T: Default, which is not needed.
+impl<T> Default for BatchJsonRpcRequest<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Vec<JsonRpcRequest<T>>> for BatchJsonRpcRequest<T> {
+    fn from(requests: Vec<JsonRpcRequest<T>>) -> Self {
+        Self(requests)
+    }
+}
+
+impl<T> From<BatchJsonRpcRequest<T>> for Vec<JsonRpcRequest<T>> {
+    fn from(batch: BatchJsonRpcRequest<T>) -> Self {
+        batch.0
+    }
+}
+
+impl<T> FromIterator<JsonRpcRequest<T>> for BatchJsonRpcRequest<T> {
+    fn from_iter<I: IntoIterator<Item = JsonRpcRequest<T>>>(iter: I) -> Self {
+        Self(Vec::from_iter(iter))
+    }
+}
+
+impl<T> IntoIterator for BatchJsonRpcRequest<T> {
+    type Item = JsonRpcRequest<T>;
+    type IntoIter = std::vec::IntoIter<JsonRpcRequest<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BatchJsonRpcRequest<T> {
+    type Item = &'a JsonRpcRequest<T>;
+    type IntoIter = std::slice::Iter<'a, JsonRpcRequest<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut BatchJsonRpcRequest<T> {
+    type Item = &'a mut JsonRpcRequest<T>;
+    type IntoIter = std::slice::IterMut<'a, JsonRpcRequest<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
 
 /// JSON-RPC request body, see the [specification].
 ///
@@ -108,6 +304,9 @@ impl<T> JsonRpcRequest<T> {
     /// Create a new body of a JSON-RPC request.
     ///
     /// By default, constant-size ID is used. See [`ConstantSizeId`].
+    /// That default ID is considered unset and replaced by the [`JsonRpcIdLayer`].
+    ///
+    /// [`JsonRpcIdLayer`]: crate::http::json::JsonRpcIdLayer
     pub fn new(method: impl Into<String>, params: T) -> Self {
         Self {
             jsonrpc: Version::V2,
@@ -145,3 +344,38 @@ impl<T> JsonRpcRequest<T> {
         self.params.as_ref()
     }
 }
+
+/// JSON-RPC notification body, i.e. a request without `id` member to which the server does not
+/// reply, see the [specification].
+///
+/// Contrary to a [`JsonRpcRequest`] with an [`Id::Null`] ID, the serialized notification
+/// does not contain any `id` member.
+///
+/// [specification]: https://www.jsonrpc.org/specification#notification
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct JsonRpcNotification<T> {
+    jsonrpc: Version,
+    method: String,
+    params: Option<T>,
+}
+
+impl<T> JsonRpcNotification<T> {
+    /// Create a new body of a JSON-RPC notification.
+    pub fn new(method: impl Into<String>, params: T) -> Self {
+        Self {
+            jsonrpc: Version::V2,
+            method: method.into(),
+            params: Some(params),
+        }
+    }
+
+    /// Returns the JSON-RPC method.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Return the JSON-RPC params, if any.
+    pub fn params(&self) -> Option<&T> {
+        self.params.as_ref()
+    }
+}
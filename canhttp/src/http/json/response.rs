@@ -12,7 +12,7 @@ use itertools::{Either, Itertools};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::BTreeSet;
-use std::{collections::BTreeMap, fmt::Debug, marker::PhantomData};
+use std::{collections::BTreeMap, convert::Infallible, fmt::Debug, marker::PhantomData};
 use thiserror::Error;
 
 #[cfg(test)]
@@ -22,6 +22,8 @@ mod tests;
 /// by parsing the response body as JSON text bytes.
 #[derive(Debug)]
 pub struct JsonResponseConverter<T> {
+    leniency: JsonRpcLeniency,
+    strict_version: bool,
     _marker: PhantomData<T>,
 }
 
@@ -29,15 +31,38 @@ impl<T> JsonResponseConverter<T> {
     /// Create a new instance of [`JsonResponseConverter`].
     pub fn new() -> Self {
         Self {
+            leniency: JsonRpcLeniency::default(),
+            strict_version: false,
             _marker: PhantomData,
         }
     }
+
+    /// Tolerate the given deviations from the JSON-RPC specification when parsing
+    /// JSON-RPC responses.
+    ///
+    /// By default, responses are parsed strictly.
+    pub fn with_leniency(mut self, leniency: JsonRpcLeniency) -> Self {
+        self.leniency = leniency;
+        self
+    }
+
+    /// Reject JSON-RPC responses whose `jsonrpc` member is not exactly `"2.0"` with
+    /// [`JsonResponseConversionError::UnsupportedVersion`].
+    ///
+    /// The version is checked before any [`JsonRpcLeniency`] is applied,
+    /// so that this takes precedence over [`JsonRpcLeniency::allow_missing_version`].
+    pub fn with_strict_version(mut self) -> Self {
+        self.strict_version = true;
+        self
+    }
 }
 
 // #[derive(Clone)] would otherwise introduce a bound T: Clone, which is not needed.
 impl<T> Clone for JsonResponseConverter<T> {
     fn clone(&self) -> Self {
         Self {
+            leniency: self.leniency,
+            strict_version: self.strict_version,
             _marker: self._marker,
         }
     }
@@ -49,6 +74,23 @@ impl<T> Default for JsonResponseConverter<T> {
     }
 }
 
+/// Convert responses to JSON-RPC notifications into [`http::Response<()>`] by discarding
+/// the response body.
+///
+/// Servers do not reply to notifications, so that the response body is usually empty
+/// (e.g. with status `204 No Content`) and is not parsed.
+#[derive(Clone, Debug, Default)]
+pub struct NotificationResponseConverter;
+
+impl Convert<HttpResponse> for NotificationResponseConverter {
+    type Output = http::Response<()>;
+    type Error = Infallible;
+
+    fn try_convert(&mut self, response: HttpResponse) -> Result<Self::Output, Self::Error> {
+        Ok(response.map(|_body| ()))
+    }
+}
+
 /// Error returned when converting responses with [`JsonResponseConverter`].
 #[derive(Error, Clone, Debug, Eq, PartialEq)]
 pub enum JsonResponseConversionError {
@@ -63,6 +105,14 @@ pub enum JsonResponseConversionError {
         /// Deserialization error
         parsing_error: String,
     },
+    /// The `jsonrpc` member of a JSON-RPC response is not exactly `"2.0"`.
+    #[error("Unsupported JSON-RPC version: status {status}, expected \"2.0\" but got {version:?}")]
+    UnsupportedVersion {
+        /// Response status code
+        status: u16,
+        /// Value of the `jsonrpc` member, if any
+        version: Option<String>,
+    },
 }
 
 impl<T> Convert<HttpResponse> for JsonResponseConverter<T>
@@ -74,27 +124,384 @@ where
 
     fn try_convert(&mut self, response: HttpResponse) -> Result<Self::Output, Self::Error> {
         let (parts, body) = response.into_parts();
-        let json_body: T = serde_json::from_slice(&body).map_err(|e| {
-            JsonResponseConversionError::InvalidJsonResponse {
+        let invalid_json_response =
+            |e: serde_json::Error| JsonResponseConversionError::InvalidJsonResponse {
                 status: parts.status.as_u16(),
                 body: String::from_utf8_lossy(&body).to_string(),
                 parsing_error: e.to_string(),
+            };
+        let json_body: T = if self.strict_version {
+            let value: Value = serde_json::from_slice(&body).map_err(invalid_json_response)?;
+            if let Some(version) = unsupported_version(&value) {
+                return Err(JsonResponseConversionError::UnsupportedVersion {
+                    status: parts.status.as_u16(),
+                    version,
+                });
             }
-        })?;
+            self.leniency.parse_value(value)
+        } else {
+            self.leniency.parse(&body)
+        }
+        .map_err(invalid_json_response)?;
         Ok(http::Response::from_parts(parts, json_body))
     }
 }
 
+/// Decode the body of an [`HttpResponse`] as JSON.
+///
+/// This is useful when only some responses are JSON and a [`JsonResponseConverter`] in the
+/// service stack would therefore be too restrictive.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::JsonResponseExtension;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Price {
+///     symbol: String,
+///     price: String,
+/// }
+///
+/// let response = http::Response::new(br#"{"symbol":"ICPUSDT","price":"5.42"}"#.to_vec());
+///
+/// assert_eq!(
+///     response.json::<Price>(),
+///     Ok(Price {
+///         symbol: "ICPUSDT".to_string(),
+///         price: "5.42".to_string()
+///     })
+/// );
+/// ```
+pub trait JsonResponseExtension {
+    /// Deserialize the response body as JSON.
+    fn json<T: DeserializeOwned>(&self) -> Result<T, JsonResponseConversionError>;
+}
+
+impl JsonResponseExtension for HttpResponse {
+    fn json<T: DeserializeOwned>(&self) -> Result<T, JsonResponseConversionError> {
+        serde_json::from_slice(self.body()).map_err(|e| {
+            JsonResponseConversionError::InvalidJsonResponse {
+                status: self.status().as_u16(),
+                body: String::from_utf8_lossy(self.body()).to_string(),
+                parsing_error: e.to_string(),
+            }
+        })
+    }
+}
+
+/// Convert responses of type [HttpResponse] into [`http::Response<Vec<T>>`], where `T` is `Deserialize`
+/// by parsing each line of the response body as JSON text bytes.
+///
+/// This is meant for responses in the [NDJSON](https://github.com/ndjson/ndjson-spec) format
+/// (also known as [JSON Lines](https://jsonlines.org/)), typically with a `Content-Type` header
+/// of `application/x-ndjson`. Lines are separated by `\n` (an optional trailing `\r` is ignored)
+/// and empty lines are skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{convert::ConvertServiceBuilder, http::{HttpRequest, HttpResponse, json::NdJsonResponseConverter}};
+/// use serde_json::json;
+/// use tower::{Service, ServiceBuilder, ServiceExt, BoxError};
+///
+/// async fn export(_request: HttpRequest) -> Result<HttpResponse, BoxError> {
+///     Ok(http::Response::new(b"{\"id\":1}\n{\"id\":2}\n".to_vec()))
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut service = ServiceBuilder::new()
+///   .convert_response(NdJsonResponseConverter::<serde_json::Value>::new())
+///   .service_fn(export);
+///
+/// let request = http::Request::get("https://internetcomputer.org").body(vec![]).unwrap();
+///
+/// let response = service.ready().await.unwrap().call(request).await.unwrap();
+///
+/// assert_eq!(response.into_body(), vec![json!({"id": 1}), json!({"id": 2})]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct NdJsonResponseConverter<T> {
+    _marker: PhantomData<T>,
+}
+
+impl<T> NdJsonResponseConverter<T> {
+    /// Create a new instance of [`NdJsonResponseConverter`].
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+// #[derive(Clone)] would otherwise introduce a bound T: Clone, which is not needed.
+impl<T> Clone for NdJsonResponseConverter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            _marker: self._marker,
+        }
+    }
+}
+
+impl<T> Default for NdJsonResponseConverter<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error returned when converting responses with [`NdJsonResponseConverter`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum NdJsonResponseConversionError {
+    /// A line of the response body could not be deserialized.
+    #[error("Invalid NDJSON response: status {status}, line {line_number}: {line}, parsing error: {parsing_error:?}")]
+    InvalidJsonLine {
+        /// Response status code
+        status: u16,
+        /// Line number (starting at 1) in the response body
+        line_number: usize,
+        /// Content of the offending line
+        line: String,
+        /// Deserialization error
+        parsing_error: String,
+    },
+}
+
+impl<T> Convert<HttpResponse> for NdJsonResponseConverter<T>
+where
+    T: DeserializeOwned,
+{
+    type Output = http::Response<Vec<T>>;
+    type Error = NdJsonResponseConversionError;
+
+    fn try_convert(&mut self, response: HttpResponse) -> Result<Self::Output, Self::Error> {
+        let (parts, body) = response.into_parts();
+        let values = body
+            .split(|byte| *byte == b'\n')
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.strip_suffix(b"\r").unwrap_or(line)))
+            .filter(|(_line_number, line)| !line.iter().all(u8::is_ascii_whitespace))
+            .map(|(line_number, line)| {
+                serde_json::from_slice(line).map_err(|e| {
+                    NdJsonResponseConversionError::InvalidJsonLine {
+                        status: parts.status.as_u16(),
+                        line_number,
+                        line: String::from_utf8_lossy(line).to_string(),
+                        parsing_error: e.to_string(),
+                    }
+                })
+            })
+            .collect::<Result<Vec<T>, _>>()?;
+        Ok(http::Response::from_parts(parts, values))
+    }
+}
+
 /// JSON-RPC response over HTTP.
 pub type HttpJsonRpcResponse<T> = http::Response<JsonRpcResponse<T>>;
 
+/// Extension trait for [`HttpJsonRpcResponse`] to check the HTTP status and the JSON-RPC result
+/// in one step.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{
+///     HttpJsonRpcResponseError, HttpJsonRpcResponseExtension, JsonRpcError, JsonRpcResponse,
+/// };
+///
+/// let response = http::Response::new(JsonRpcResponse::from_ok(1_u64.into(), "0x1"));
+/// assert_eq!(response.into_successful_result(), Ok("0x1"));
+///
+/// let error = JsonRpcError::new(-32005, "limit exceeded");
+/// let response = http::Response::builder()
+///     .status(http::StatusCode::TOO_MANY_REQUESTS)
+///     .body(JsonRpcResponse::<String>::from_error(1_u64.into(), error.clone()))?;
+/// assert_eq!(
+///     response.into_successful_result(),
+///     Err(HttpJsonRpcResponseError::HttpStatus {
+///         status: http::StatusCode::TOO_MANY_REQUESTS,
+///         error: Some(error),
+///     })
+/// );
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub trait HttpJsonRpcResponseExtension<T> {
+    /// Returns the result of the JSON-RPC response if and only if the HTTP status is successful
+    /// (`2xx`) and the response is not a JSON-RPC error.
+    fn into_successful_result(self) -> Result<T, HttpJsonRpcResponseError>;
+}
+
+impl<T> HttpJsonRpcResponseExtension<T> for HttpJsonRpcResponse<T> {
+    fn into_successful_result(self) -> Result<T, HttpJsonRpcResponseError> {
+        let status = self.status();
+        let result = self.into_body().into_result();
+        if !status.is_success() {
+            return Err(HttpJsonRpcResponseError::HttpStatus {
+                status,
+                error: result.err(),
+            });
+        }
+        Ok(result?)
+    }
+}
+
+/// Error returned by [`HttpJsonRpcResponseExtension::into_successful_result`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum HttpJsonRpcResponseError {
+    /// The HTTP response has a non-successful status.
+    #[error("HTTP response has non-successful status {status}")]
+    HttpStatus {
+        /// Response status code.
+        status: http::StatusCode,
+        /// JSON-RPC error contained in the response, if any.
+        error: Option<JsonRpcError>,
+    },
+    /// The JSON-RPC response is an error.
+    #[error(transparent)]
+    JsonRpc(#[from] JsonRpcError),
+}
+
 /// Batch JSON-RPC response body, see the [specification].
 ///
 /// [specification]: https://www.jsonrpc.org/specification
-pub type BatchJsonRpcResponse<T> = Vec<JsonRpcResponse<T>>;
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BatchJsonRpcResponse<T>(Vec<JsonRpcResponse<T>>);
+
+impl<T> BatchJsonRpcResponse<T> {
+    /// Create a new empty batch.
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Returns the number of responses in the batch.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the batch contains no responses.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the responses in the batch.
+    pub fn iter(&self) -> std::slice::Iter<'_, JsonRpcResponse<T>> {
+        self.0.iter()
+    }
+
+    /// Returns an iterator that allows modifying each response in the batch.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, JsonRpcResponse<T>> {
+        self.0.iter_mut()
+    }
+
+    /// Appends a response to the back of the batch.
+    pub fn push(&mut self, response: JsonRpcResponse<T>) {
+        self.0.push(response);
+    }
+
+    /// Retains only the responses specified by the predicate, preserving their order.
+    pub fn retain(&mut self, f: impl FnMut(&JsonRpcResponse<T>) -> bool) {
+        self.0.retain(f);
+    }
+
+    /// Returns an iterator over the IDs of the responses in the batch.
+    pub fn ids(&self) -> impl Iterator<Item = &Id> {
+        self.0.iter().map(|response| response.id())
+    }
+
+    /// Returns the first response in the batch with the given ID, if any.
+    pub fn get_by_id(&self, id: &Id) -> Option<&JsonRpcResponse<T>> {
+        self.0.iter().find(|response| response.id() == id)
+    }
+
+    /// Returns a mutable reference to the first response in the batch with the given ID, if any.
+    pub fn get_by_id_mut(&mut self, id: &Id) -> Option<&mut JsonRpcResponse<T>> {
+        self.0.iter_mut().find(|response| response.id() == id)
+    }
+
+    /// Removes and returns the first response in the batch with the given ID, if any.
+    pub fn remove_by_id(&mut self, id: &Id) -> Option<JsonRpcResponse<T>> {
+        let index = self.0.iter().position(|response| response.id() == id)?;
+        Some(self.0.remove(index))
+    }
+
+    /// Converts the batch into a vector of responses.
+    pub fn into_vec(self) -> Vec<JsonRpcResponse<T>> {
+        self.0
+    }
+
+    /// Converts the batch into a map from response ID to result.
+    ///
+    /// If several responses have the same ID, only the first one is kept, like with
+    /// [`Self::get_by_id`]. In particular, at most one of the responses with an [`Id::Null`] ID,
+    /// which a server sends for requests it could not parse, is kept.
+    pub fn into_map(self) -> BTreeMap<Id, JsonRpcResult<T>> {
+        let mut map = BTreeMap::new();
+        for response in self.0 {
+            let (id, result) = response.into_parts();
+            map.entry(id).or_insert(result);
+        }
+        map
+    }
+}
+
+// #[derive(Default)] would otherwise introduce a bound T: Default, which is not needed.
+impl<T> Default for BatchJsonRpcResponse<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Vec<JsonRpcResponse<T>>> for BatchJsonRpcResponse<T> {
+    fn from(responses: Vec<JsonRpcResponse<T>>) -> Self {
+        Self(responses)
+    }
+}
+
+impl<T> From<BatchJsonRpcResponse<T>> for Vec<JsonRpcResponse<T>> {
+    fn from(batch: BatchJsonRpcResponse<T>) -> Self {
+        batch.0
+    }
+}
+
+impl<T> FromIterator<JsonRpcResponse<T>> for BatchJsonRpcResponse<T> {
+    fn from_iter<I: IntoIterator<Item = JsonRpcResponse<T>>>(iter: I) -> Self {
+        Self(Vec::from_iter(iter))
+    }
+}
+
+impl<T> IntoIterator for BatchJsonRpcResponse<T> {
+    type Item = JsonRpcResponse<T>;
+    type IntoIter = std::vec::IntoIter<JsonRpcResponse<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BatchJsonRpcResponse<T> {
+    type Item = &'a JsonRpcResponse<T>;
+    type IntoIter = std::slice::Iter<'a, JsonRpcResponse<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut BatchJsonRpcResponse<T> {
+    type Item = &'a mut JsonRpcResponse<T>;
+    type IntoIter = std::slice::IterMut<'a, JsonRpcResponse<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
 
 /// Batch JSON-RPC response over HTTP.
-pub type HttpBatchJsonRpcResponse<T> = http::Response<Vec<JsonRpcResponse<T>>>;
+pub type HttpBatchJsonRpcResponse<T> = http::Response<BatchJsonRpcResponse<T>>;
 
 /// A specialized [`Result`] error type for JSON-RPC responses.
 ///
@@ -225,10 +632,40 @@ impl<T> JsonRpcResultEnvelope<T> {
 }
 
 /// A JSON-RPC error object.
+///
+/// The `data` member is a raw JSON [`Value`] by default. Use [`JsonRpcError::data_as`] to decode
+/// it, or [`JsonRpcError::into_typed`] to convert the error into a `JsonRpcError<E>` with
+/// structured data, e.g. the revert data of an Ethereum call.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::JsonRpcError;
+/// use serde::Deserialize;
+/// use serde_json::json;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct RevertData(String);
+///
+/// let error: JsonRpcError = serde_json::from_value(json!({
+///     "code": 3,
+///     "message": "execution reverted",
+///     "data": "0x08c379a0"
+/// }))
+/// .unwrap();
+///
+/// assert_eq!(
+///     error.data_as::<RevertData>().unwrap(),
+///     Some(RevertData("0x08c379a0".to_string()))
+/// );
+///
+/// let typed: JsonRpcError<RevertData> = error.into_typed().unwrap();
+/// assert_eq!(typed.data, Some(RevertData("0x08c379a0".to_string())));
+/// ```
 #[derive(Clone, Debug, Eq, PartialEq, Error, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 #[error("JSON-RPC error (code: {code}): {message}. Details: {data:?}")]
-pub struct JsonRpcError {
+pub struct JsonRpcError<E = Value> {
     /// Indicate error type that occurred.
     pub code: i64,
     /// Short description of the error.
@@ -238,7 +675,7 @@ pub struct JsonRpcError {
     /// The value of this member is defined by the Server
     /// (e.g. detailed error information, nested errors etc.).
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<Value>,
+    pub data: Option<E>,
 }
 
 impl JsonRpcError {
@@ -253,28 +690,143 @@ impl JsonRpcError {
         }
     }
 
-    /// Return `true` if and only if the error code indicates a parsing error
-    /// according to the [JSON-RPC specification](https://www.jsonrpc.org/specification).
-    pub fn is_parse_error(&self) -> bool {
-        self.code == -32700
+    /// Decode the `data` member of the error, if any.
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<Option<T>, serde_json::Error> {
+        self.data.clone().map(serde_json::from_value).transpose()
     }
 
-    /// Return `true` if and only if the error code indicates an invalid request
-    /// according to the [JSON-RPC specification](https://www.jsonrpc.org/specification).
-    pub fn is_invalid_request(&self) -> bool {
-        self.code == -32600
+    /// Convert the error into an error with structured data by decoding its `data` member.
+    pub fn into_typed<E: DeserializeOwned>(self) -> Result<JsonRpcError<E>, serde_json::Error> {
+        let data = self.data.map(serde_json::from_value).transpose()?;
+        Ok(JsonRpcError {
+            code: self.code,
+            message: self.message,
+            data,
+        })
     }
 
     /// An invalid request JSON-RPC error object,
     /// as defined in the [JSON-RPC specification](https://www.jsonrpc.org/specification).
     pub fn invalid_request() -> Self {
-        Self::new(-32600, "Invalid Request")
+        Self::new(JsonRpcErrorCode::InvalidRequest, "Invalid Request")
     }
 
     /// A parse error JSON-RPC error object,
     /// as defined in the [JSON-RPC specification](https://www.jsonrpc.org/specification).
     pub fn parse_error() -> Self {
-        Self::new(-32700, "Parse error")
+        Self::new(JsonRpcErrorCode::ParseError, "Parse error")
+    }
+}
+
+impl<E> JsonRpcError<E> {
+    /// Returns the kind of error indicated by the error code.
+    pub fn kind(&self) -> JsonRpcErrorCode {
+        JsonRpcErrorCode::from(self.code)
+    }
+
+    /// Return `true` if and only if the error code indicates a parsing error
+    /// according to the [JSON-RPC specification](https://www.jsonrpc.org/specification).
+    pub fn is_parse_error(&self) -> bool {
+        self.kind() == JsonRpcErrorCode::ParseError
+    }
+
+    /// Return `true` if and only if the error code indicates an invalid request
+    /// according to the [JSON-RPC specification](https://www.jsonrpc.org/specification).
+    pub fn is_invalid_request(&self) -> bool {
+        self.kind() == JsonRpcErrorCode::InvalidRequest
+    }
+
+    /// Return `true` if and only if the error code indicates that the method does not exist
+    /// according to the [JSON-RPC specification](https://www.jsonrpc.org/specification).
+    pub fn is_method_not_found(&self) -> bool {
+        self.kind() == JsonRpcErrorCode::MethodNotFound
+    }
+
+    /// Return `true` if and only if the error code indicates invalid method parameters
+    /// according to the [JSON-RPC specification](https://www.jsonrpc.org/specification).
+    pub fn is_invalid_params(&self) -> bool {
+        self.kind() == JsonRpcErrorCode::InvalidParams
+    }
+
+    /// Return `true` if and only if the error code indicates an internal error
+    /// according to the [JSON-RPC specification](https://www.jsonrpc.org/specification).
+    pub fn is_internal_error(&self) -> bool {
+        self.kind() == JsonRpcErrorCode::InternalError
+    }
+
+    /// Return `true` if and only if the error code is in the range reserved for
+    /// implementation-defined server errors, i.e. from -32099 to -32000, according to the
+    /// [JSON-RPC specification](https://www.jsonrpc.org/specification).
+    pub fn is_server_error(&self) -> bool {
+        matches!(self.kind(), JsonRpcErrorCode::ServerError(_))
+    }
+}
+
+/// Kind of a JSON-RPC error, as given by the error codes reserved by the
+/// [JSON-RPC specification](https://www.jsonrpc.org/specification#error_object).
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{JsonRpcError, JsonRpcErrorCode};
+///
+/// let error = JsonRpcError::new(JsonRpcErrorCode::MethodNotFound, "Method not found");
+/// assert_eq!(error.code, -32601);
+/// assert!(error.is_method_not_found());
+///
+/// let error = JsonRpcError::new(-32005, "Limit exceeded");
+/// assert_eq!(error.kind(), JsonRpcErrorCode::ServerError(-32005));
+/// assert!(error.is_server_error());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum JsonRpcErrorCode {
+    /// Invalid JSON was received by the server (-32700).
+    ParseError,
+    /// The JSON sent is not a valid request object (-32600).
+    InvalidRequest,
+    /// The method does not exist or is not available (-32601).
+    MethodNotFound,
+    /// Invalid method parameters (-32602).
+    InvalidParams,
+    /// Internal JSON-RPC error (-32603).
+    InternalError,
+    /// Implementation-defined server error, with a code from -32099 to -32000.
+    ServerError(i64),
+    /// Any other code, e.g. an application-defined error.
+    Other(i64),
+}
+
+impl JsonRpcErrorCode {
+    /// Returns the numeric error code.
+    pub fn code(&self) -> i64 {
+        match self {
+            JsonRpcErrorCode::ParseError => -32700,
+            JsonRpcErrorCode::InvalidRequest => -32600,
+            JsonRpcErrorCode::MethodNotFound => -32601,
+            JsonRpcErrorCode::InvalidParams => -32602,
+            JsonRpcErrorCode::InternalError => -32603,
+            JsonRpcErrorCode::ServerError(code) | JsonRpcErrorCode::Other(code) => *code,
+        }
+    }
+}
+
+impl From<i64> for JsonRpcErrorCode {
+    fn from(code: i64) -> Self {
+        match code {
+            -32700 => JsonRpcErrorCode::ParseError,
+            -32600 => JsonRpcErrorCode::InvalidRequest,
+            -32601 => JsonRpcErrorCode::MethodNotFound,
+            -32602 => JsonRpcErrorCode::InvalidParams,
+            -32603 => JsonRpcErrorCode::InternalError,
+            -32099..=-32000 => JsonRpcErrorCode::ServerError(code),
+            _ => JsonRpcErrorCode::Other(code),
+        }
+    }
+}
+
+impl From<JsonRpcErrorCode> for i64 {
+    fn from(code: JsonRpcErrorCode) -> Self {
+        code.code()
     }
 }
 
@@ -307,15 +859,167 @@ pub enum ConsistentResponseIdFilterError {
     },
 }
 
+/// Determines when a response ID is considered to match a request ID.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum IdMatching {
+    /// Response and request IDs must be equal, including their JSON type.
+    #[default]
+    Strict,
+    /// Response and request IDs must be equal, except that a string ID matches a numeric ID
+    /// if it represents the same number (e.g., `"1"` or `"001"` matches `1`).
+    ///
+    /// This is useful for providers that echo back numeric IDs as strings.
+    Lenient,
+}
+
+/// Deviations from the [JSON-RPC 2.0 specification] that are tolerated when parsing responses.
+///
+/// By default, no deviation is tolerated. Response IDs are validated in any case.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::json::{Id, JsonResponseConverter, JsonRpcLeniency, JsonRpcResponse};
+/// use canhttp::convert::Convert;
+/// use serde_json::Value;
+///
+/// let response = http::Response::new(br#"{"id":1,"result":"0x1"}"#.to_vec());
+///
+/// let mut strict = JsonResponseConverter::<JsonRpcResponse<Value>>::new();
+/// assert!(strict.try_convert(response.clone()).is_err());
+///
+/// let mut lenient = JsonResponseConverter::<JsonRpcResponse<Value>>::new()
+///     .with_leniency(JsonRpcLeniency::default().allow_missing_version());
+/// assert_eq!(
+///     lenient.try_convert(response).unwrap().into_body(),
+///     JsonRpcResponse::from_ok(Id::from(1_u64), Value::from("0x1"))
+/// );
+/// ```
+///
+/// [JSON-RPC 2.0 specification]: https://www.jsonrpc.org/specification
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct JsonRpcLeniency {
+    missing_version: bool,
+    result_and_error: bool,
+}
+
+impl JsonRpcLeniency {
+    /// Tolerate responses without a `jsonrpc` member or with `"jsonrpc": "1.0"`,
+    /// as returned by some older servers, and parse them as JSON-RPC 2.0 responses.
+    pub fn allow_missing_version(mut self) -> Self {
+        self.missing_version = true;
+        self
+    }
+
+    /// Tolerate responses containing both a `result` and an `error` member,
+    /// as returned by some providers (e.g. `"result": null` together with an error object).
+    ///
+    /// Such a response is parsed as an error response, unless the `error` member is `null`,
+    /// in which case it is parsed as a successful response.
+    pub fn allow_result_and_error(mut self) -> Self {
+        self.result_and_error = true;
+        self
+    }
+
+    fn parse<T: DeserializeOwned>(&self, body: &[u8]) -> Result<T, serde_json::Error> {
+        if *self == Self::default() {
+            return serde_json::from_slice(body);
+        }
+        self.parse_value(serde_json::from_slice(body)?)
+    }
+
+    fn parse_value<T: DeserializeOwned>(&self, mut value: Value) -> Result<T, serde_json::Error> {
+        if *self == Self::default() {
+            return serde_json::from_value(value);
+        }
+        match &mut value {
+            Value::Array(responses) => responses
+                .iter_mut()
+                .for_each(|response| self.normalize(response)),
+            response => self.normalize(response),
+        }
+        serde_json::from_value(value)
+    }
+
+    fn normalize(&self, response: &mut Value) {
+        let Value::Object(response) = response else {
+            return;
+        };
+        if self.missing_version {
+            let is_missing_or_legacy = match response.get("jsonrpc") {
+                None => true,
+                Some(Value::String(version)) => version == "1.0",
+                Some(_) => false,
+            };
+            if is_missing_or_legacy {
+                response.insert("jsonrpc".to_string(), Value::from(Version::V2.to_string()));
+            }
+        }
+        if self.result_and_error && response.contains_key("result") {
+            match response.get("error") {
+                Some(Value::Null) => {
+                    response.remove("error");
+                }
+                Some(_) => {
+                    response.remove("result");
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Returns the version of the first (batch) JSON-RPC response whose `jsonrpc` member
+/// is not exactly `"2.0"`, if any.
+fn unsupported_version(value: &Value) -> Option<Option<String>> {
+    let responses = match value {
+        Value::Array(responses) => responses.as_slice(),
+        response => std::slice::from_ref(response),
+    };
+    responses
+        .iter()
+        .filter_map(Value::as_object)
+        .find_map(|response| match response.get("jsonrpc") {
+            Some(Value::String(version)) if version == "2.0" => None,
+            Some(Value::String(version)) => Some(Some(version.clone())),
+            Some(version) => Some(Some(version.to_string())),
+            None => Some(None),
+        })
+}
+
+impl IdMatching {
+    pub(super) fn key(&self, id: &Id) -> Id {
+        match (self, id) {
+            (IdMatching::Lenient, Id::String(value))
+                if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                value.parse().map(Id::Number).unwrap_or_else(|_| id.clone())
+            }
+            _ => id.clone(),
+        }
+    }
+
+    fn matches(&self, request_id: &Id, response_id: &Id) -> bool {
+        self.key(request_id) == self.key(response_id)
+    }
+}
+
 /// Create [`ConsistentJsonRpcIdFilter`] for each request.
 pub struct CreateJsonRpcIdFilter<Request, Response> {
+    id_matching: IdMatching,
     _marker: PhantomData<(Request, Response)>,
 }
 
 impl<Request, Response> CreateJsonRpcIdFilter<Request, Response> {
-    /// Create a new instance of [`CreateJsonRpcIdFilter`]
+    /// Create a new instance of [`CreateJsonRpcIdFilter`] with [`IdMatching::Strict`].
     pub fn new() -> Self {
+        Self::with_id_matching(IdMatching::Strict)
+    }
+
+    /// Create a new instance of [`CreateJsonRpcIdFilter`] with the given [`IdMatching`].
+    pub fn with_id_matching(id_matching: IdMatching) -> Self {
         Self {
+            id_matching,
             _marker: PhantomData,
         }
     }
@@ -324,6 +1028,7 @@ impl<Request, Response> CreateJsonRpcIdFilter<Request, Response> {
 impl<Request, Response> Clone for CreateJsonRpcIdFilter<Request, Response> {
     fn clone(&self) -> Self {
         Self {
+            id_matching: self.id_matching,
             _marker: self._marker,
         }
     }
@@ -346,7 +1051,7 @@ where
 
     fn create_filter(&self, request: &HttpJsonRpcRequest<I>) -> Self::Filter {
         let request_id = expected_response_id(request.body());
-        ConsistentJsonRpcIdFilter::new(vec![request_id])
+        ConsistentJsonRpcIdFilter::new(vec![request_id], self.id_matching)
     }
 }
 
@@ -364,6 +1069,11 @@ where
     /// This implementation panics in the following cases:
     /// * The JSON-RPC batch is empty.
     /// * The IDs of the requests in the JSON-RPC batch are not unique.
+    ///
+    /// Both are checked beforehand by the [`JsonRpcRequestFilter`] in the [`JsonRpcHttpLayer`].
+    ///
+    /// [`JsonRpcRequestFilter`]: crate::http::json::JsonRpcRequestFilter
+    /// [`JsonRpcHttpLayer`]: crate::http::json::JsonRpcHttpLayer
     fn create_filter(&self, request: &HttpBatchJsonRpcRequest<I>) -> Self::Filter {
         let requests = request.body();
 
@@ -374,19 +1084,26 @@ where
             .map(expected_response_id)
             .collect::<Vec<_>>();
         assert_eq!(
-            BTreeSet::from_iter(request_ids.iter()).len(),
+            BTreeSet::from_iter(request_ids.iter().map(|id| self.id_matching.key(id))).len(),
             requests.len(),
             "Expected request IDs to be unique, but got: {request_ids:?}"
         );
 
-        ConsistentJsonRpcIdFilter::new(request_ids)
+        ConsistentJsonRpcIdFilter::new(request_ids, self.id_matching)
     }
 }
 
 /// Ensure that the ID of the response is consistent with the one from the request
 /// that is stored internally.
+///
+/// For a batch, the responses, which a server may send in any order, are reordered to match
+/// the order of the requests, so that the `i`-th response of the batch is the response to the
+/// `i`-th request. A request for which the server replied with an
+/// [invalid request](JsonRpcError::invalid_request) error with a `null` ID is matched with that
+/// error.
 pub struct ConsistentJsonRpcIdFilter<Request, Response> {
     request_ids: Vec<Id>,
+    id_matching: IdMatching,
     _marker: PhantomData<(Request, Response)>,
 }
 
@@ -400,9 +1117,10 @@ impl<Request, Response> ConsistentJsonRpcIdFilter<Request, Response> {
     /// This is because a request ID with value [`Id::Null`] indicates a Notification,
     /// which indicates that the client does not care about the response (see the
     /// JSON-RPC [specification](https://www.jsonrpc.org/specification)).
-    fn new(request_ids: Vec<Id>) -> Self {
+    fn new(request_ids: Vec<Id>, id_matching: IdMatching) -> Self {
         Self {
             request_ids,
+            id_matching,
             _marker: PhantomData,
         }
     }
@@ -435,7 +1153,8 @@ where
             .exactly_one()
             .expect("Expected request ID to contain only a single ID");
         let response_id = response.body().id();
-        if request_id == response_id || should_have_null_id(response.body()) {
+        if self.id_matching.matches(request_id, response_id) || should_have_null_id(response.body())
+        {
             Ok(response)
         } else {
             Err(ConsistentResponseIdFilterError::InconsistentId {
@@ -465,19 +1184,23 @@ where
             .map(|response| response.id())
             .cloned()
             .collect();
-        let correlated_responses = try_order_responses_by_id(&self.request_ids, responses)
-            .ok_or_else(|| ConsistentResponseIdFilterError::InconsistentBatchIds {
-                status: head.status.into(),
-                request_ids: self.request_ids.to_vec(),
-                response_ids,
-            })?;
-        Ok(http::Response::from_parts(head, correlated_responses))
+        let correlated_responses =
+            try_order_responses_by_id(&self.request_ids, responses.into_vec(), self.id_matching)
+                .ok_or_else(|| ConsistentResponseIdFilterError::InconsistentBatchIds {
+                    status: head.status.into(),
+                    request_ids: self.request_ids.to_vec(),
+                    response_ids,
+                })?;
+        Ok(http::Response::from_parts(
+            head,
+            BatchJsonRpcResponse::from(correlated_responses),
+        ))
     }
 }
 
 fn expected_response_id<T>(request: &JsonRpcRequest<T>) -> Id {
     match request.id() {
-        Id::Null => panic!("ERROR: a null request ID is a notification that indicates that the client is not interested in the response."),
+        Id::Null => panic!("ERROR: a null request ID is a notification that indicates that the client is not interested in the response. Use `JsonRpcNotification` instead."),
         id @ (Id::Number(_) | Id::String(_)) => id.clone()
     }
 }
@@ -485,6 +1208,7 @@ fn expected_response_id<T>(request: &JsonRpcRequest<T>) -> Id {
 fn try_order_responses_by_id<T>(
     request_ids: &[Id],
     responses: Vec<JsonRpcResponse<T>>,
+    id_matching: IdMatching,
 ) -> Option<Vec<JsonRpcResponse<T>>> {
     if request_ids.len() != responses.len() {
         return None;
@@ -495,7 +1219,7 @@ fn try_order_responses_by_id<T>(
             .into_iter()
             .partition_map(|response| match response.id() {
                 Id::Null => Either::Left(response),
-                _ => Either::Right((response.id().clone(), response)),
+                _ => Either::Right((id_matching.key(response.id()), response)),
             });
 
     // From the [JSON-RPC specification](https://www.jsonrpc.org/specification):
@@ -518,7 +1242,7 @@ fn try_order_responses_by_id<T>(
     let correlated_responses = request_ids
         .iter()
         .map(
-            |request_id| match responses_with_non_null_id.remove(request_id) {
+            |request_id| match responses_with_non_null_id.remove(&id_matching.key(request_id)) {
                 Some(response) => response,
                 None => {
                     num_missing_request_ids += 1;
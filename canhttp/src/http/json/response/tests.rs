@@ -1,4 +1,4 @@
-use super::{try_order_responses_by_id, Id, JsonRpcError, JsonRpcResponse};
+use super::{try_order_responses_by_id, Id, IdMatching, JsonRpcError, JsonRpcResponse};
 use crate::http::json::response::JsonRpcResult;
 use proptest::{
     arbitrary::any,
@@ -14,12 +14,34 @@ mod json_rpc_batch_response_id_validation_tests {
 
     #[test]
     fn should_succeed_for_empty_response() {
-        let result = try_order_responses_by_id::<serde_json::Value>(&[], Vec::new());
+        let result =
+            try_order_responses_by_id::<serde_json::Value>(&[], Vec::new(), IdMatching::Strict);
 
         assert!(result.is_some());
         assert_eq!(result.unwrap(), Vec::new());
     }
 
+    #[test]
+    fn should_match_numeric_string_ids_only_when_lenient() {
+        let request_ids = vec![Id::Number(1), Id::String("2".to_string())];
+        let responses = vec![
+            JsonRpcResponse::from_ok(Id::Number(2), json!("second")),
+            JsonRpcResponse::from_ok(Id::String("001".to_string()), json!("first")),
+        ];
+
+        assert_eq!(
+            try_order_responses_by_id(&request_ids, responses.clone(), IdMatching::Strict),
+            None
+        );
+
+        let ordered =
+            try_order_responses_by_id(&request_ids, responses, IdMatching::Lenient).unwrap();
+        assert_eq!(
+            response_ids(&ordered),
+            vec![Id::String("001".to_string()), Id::Number(2)]
+        );
+    }
+
     proptest! {
         #[test]
         fn should_succeed_with_responses_in_any_order(
@@ -29,7 +51,7 @@ mod json_rpc_batch_response_id_validation_tests {
                     (Just(responses).prop_shuffle(), Just(request_ids))
                 })
         ) {
-            let result = try_order_responses_by_id(&request_ids, shuffled_responses);
+            let result = try_order_responses_by_id(&request_ids, shuffled_responses, IdMatching::Strict);
 
             prop_assert!(result.is_some());
             prop_assert_eq!(request_ids, response_ids(&result.unwrap()));
@@ -45,7 +67,7 @@ mod json_rpc_batch_response_id_validation_tests {
                     (Just(responses).prop_shuffle(), Just(request_ids))
                 })
         ) {
-            let result = try_order_responses_by_id(&request_ids, shuffled_responses);
+            let result = try_order_responses_by_id(&request_ids, shuffled_responses, IdMatching::Strict);
 
             prop_assert!(result.is_some());
             prop_assert_eq!(request_ids, response_ids(&result.unwrap()));
@@ -67,7 +89,7 @@ mod json_rpc_batch_response_id_validation_tests {
             // Ensure one of the response IDs is not in the request IDs,
             set_id(&mut responses[i], unexpected_id);
 
-            let result = try_order_responses_by_id(&request_ids, responses);
+            let result = try_order_responses_by_id(&request_ids, responses, IdMatching::Strict);
 
             prop_assert!(result.is_none());
         }
@@ -85,7 +107,7 @@ mod json_rpc_batch_response_id_validation_tests {
             let id = responses[n - 2].id().clone();
             set_id(&mut responses[n - 1], id);
 
-            let result = try_order_responses_by_id(&request_ids, responses);
+            let result = try_order_responses_by_id(&request_ids, responses, IdMatching::Strict);
 
             prop_assert!(result.is_none());
         }
@@ -101,7 +123,7 @@ mod json_rpc_batch_response_id_validation_tests {
             // Ensure there is one more request ID than responses
             responses.remove(responses.len() - 1);
 
-            let result = try_order_responses_by_id(&request_ids, responses);
+            let result = try_order_responses_by_id(&request_ids, responses, IdMatching::Strict);
 
             prop_assert!(result.is_none());
         }
@@ -117,7 +139,7 @@ mod json_rpc_batch_response_id_validation_tests {
             // Ensure there is one more response than expected request IDs
             request_ids.remove(request_ids.len() - 1);
 
-            let result = try_order_responses_by_id(&request_ids, responses);
+            let result = try_order_responses_by_id(&request_ids, responses, IdMatching::Strict);
 
             prop_assert!(result.is_none());
         }
@@ -134,7 +156,7 @@ mod json_rpc_batch_response_id_validation_tests {
             // Ensure there is one more request ID than responses
             set_id(&mut responses[n - 1], Id::Null);
 
-            let result = try_order_responses_by_id(&request_ids, responses);
+            let result = try_order_responses_by_id(&request_ids, responses, IdMatching::Strict);
 
             prop_assert!(result.is_none());
         }
@@ -200,3 +222,134 @@ mod json_rpc_batch_response_id_validation_tests {
         *response = JsonRpcResponse::from_parts(id, response.clone().into_result());
     }
 }
+
+mod json_rpc_error_code {
+    use super::*;
+    use crate::http::json::JsonRpcErrorCode;
+
+    #[test]
+    fn should_classify_reserved_codes() {
+        let kinds: Vec<_> = [
+            -32700, -32600, -32601, -32602, -32603, -32000, -32099, -32100, 3,
+        ]
+        .into_iter()
+        .map(|code| JsonRpcError::new(code, "error").kind())
+        .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                JsonRpcErrorCode::ParseError,
+                JsonRpcErrorCode::InvalidRequest,
+                JsonRpcErrorCode::MethodNotFound,
+                JsonRpcErrorCode::InvalidParams,
+                JsonRpcErrorCode::InternalError,
+                JsonRpcErrorCode::ServerError(-32000),
+                JsonRpcErrorCode::ServerError(-32099),
+                JsonRpcErrorCode::Other(-32100),
+                JsonRpcErrorCode::Other(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_have_consistent_predicates() {
+        let error = JsonRpcError::new(JsonRpcErrorCode::InvalidParams, "Invalid params");
+        assert!(error.is_invalid_params());
+        assert!(!error.is_method_not_found());
+        assert!(!error.is_internal_error());
+        assert!(!error.is_server_error());
+        assert!(JsonRpcError::new(-32603, "Internal error").is_internal_error());
+        assert!(JsonRpcError::invalid_request().is_invalid_request());
+        assert!(JsonRpcError::parse_error().is_parse_error());
+    }
+
+    proptest! {
+        #[test]
+        fn should_roundtrip_code(code in any::<i64>()) {
+            prop_assert_eq!(JsonRpcErrorCode::from(code).code(), code);
+        }
+    }
+}
+
+mod json_rpc_error_data {
+    use super::*;
+
+    #[test]
+    fn should_decode_error_data() {
+        let mut error = JsonRpcError::new(-32000, "header not found");
+        assert_eq!(error.data_as::<u64>().unwrap(), None);
+        assert_eq!(error.clone().into_typed::<u64>().unwrap().data, None);
+
+        error.data = Some(json!({"block": 42}));
+        assert_eq!(
+            error
+                .data_as::<std::collections::BTreeMap<String, u64>>()
+                .unwrap(),
+            Some([("block".to_string(), 42)].into())
+        );
+        assert!(error.data_as::<String>().is_err());
+
+        let typed = error.into_typed::<serde_json::Map<_, _>>().unwrap();
+        assert!(typed.is_server_error());
+        assert_eq!(typed.data.unwrap()["block"], 42);
+    }
+}
+
+mod into_successful_result {
+    use crate::http::json::{
+        HttpJsonRpcResponseError, HttpJsonRpcResponseExtension, Id, JsonRpcError, JsonRpcResponse,
+    };
+
+    #[test]
+    fn should_return_result_of_successful_response() {
+        for status in [http::StatusCode::OK, http::StatusCode::ACCEPTED] {
+            let response = response(status, JsonRpcResponse::from_ok(Id::from(1_u64), 42_u64));
+
+            assert_eq!(response.into_successful_result(), Ok(42));
+        }
+    }
+
+    #[test]
+    fn should_return_json_rpc_error() {
+        let error = JsonRpcError::new(-32000, "header not found");
+        let response = response(
+            http::StatusCode::OK,
+            JsonRpcResponse::<u64>::from_error(Id::from(1_u64), error.clone()),
+        );
+
+        assert_eq!(
+            response.into_successful_result(),
+            Err(HttpJsonRpcResponseError::JsonRpc(error))
+        );
+    }
+
+    #[test]
+    fn should_reject_non_successful_status() {
+        let error = JsonRpcError::new(-32603, "internal error");
+        for (body, expected_error) in [
+            (JsonRpcResponse::from_ok(Id::from(1_u64), 42_u64), None),
+            (
+                JsonRpcResponse::from_error(Id::from(1_u64), error.clone()),
+                Some(error),
+            ),
+        ] {
+            let response = response(http::StatusCode::INTERNAL_SERVER_ERROR, body);
+
+            assert_eq!(
+                response.into_successful_result(),
+                Err(HttpJsonRpcResponseError::HttpStatus {
+                    status: http::StatusCode::INTERNAL_SERVER_ERROR,
+                    error: expected_error,
+                })
+            );
+        }
+    }
+
+    fn response<T>(
+        status: http::StatusCode,
+        body: JsonRpcResponse<T>,
+    ) -> http::Response<JsonRpcResponse<T>> {
+        http::Response::builder().status(status).body(body).unwrap()
+    }
+}
@@ -0,0 +1,315 @@
+//! Typed [Solana JSON-RPC](https://solana.com/docs/rpc/http) methods.
+//!
+//! Each method implements [`JsonRpcMethod`], so that requests can be sent with
+//! [`JsonRpcServiceExt::call_method`] without writing the parameters as raw JSON:
+//! * [`GetSlot`] for `getSlot`;
+//! * [`GetAccountInfo`] for `getAccountInfo`;
+//! * [`GetSignatureStatuses`] for `getSignatureStatuses`;
+//! * [`SendTransaction`] for `sendTransaction`.
+//!
+//! Public keys, signatures and transactions are passed as their usual string encoding
+//! (base-58 or base-64), since the crate does not decode them.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::http::json::{
+//!     solana::{CommitmentConfig, CommitmentLevel, GetSlot},
+//!     HttpJsonRpcRequest, JsonRpcResponse, JsonRpcServiceExt,
+//! };
+//! use serde_json::json;
+//! use tower::BoxError;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let mut service = tower::service_fn(|request: HttpJsonRpcRequest<(CommitmentConfig,)>| async move {
+//!     assert_eq!(
+//!         serde_json::to_value(request.body().params())?,
+//!         json!([{"commitment": "finalized"}])
+//!     );
+//!     Ok::<_, BoxError>(http::Response::new(JsonRpcResponse::from_ok(
+//!         request.body().id().clone(),
+//!         331_540_000_u64,
+//!     )))
+//! });
+//!
+//! let request = http::Request::post("https://api.mainnet-beta.solana.com");
+//! let slot = service
+//!     .call_method::<GetSlot>(request, (CommitmentLevel::Finalized.into(),))
+//!     .await?;
+//!
+//! assert_eq!(slot, Ok(331_540_000));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`JsonRpcServiceExt::call_method`]: crate::http::json::JsonRpcServiceExt::call_method
+
+#[cfg(test)]
+mod tests;
+
+use crate::http::json::JsonRpcMethod;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Slot number of the Solana blockchain.
+pub type Slot = u64;
+
+/// [`getSlot`](https://solana.com/docs/rpc/http/getslot):
+/// returns the slot that has reached the given commitment level.
+#[derive(Debug)]
+pub enum GetSlot {}
+
+impl JsonRpcMethod for GetSlot {
+    const METHOD_NAME: &'static str = "getSlot";
+    type Params = (CommitmentConfig,);
+    type Result = Slot;
+}
+
+/// [`getAccountInfo`](https://solana.com/docs/rpc/http/getaccountinfo):
+/// returns the information associated with the account of the given base-58 encoded
+/// public key, or `None` if the account does not exist.
+#[derive(Debug)]
+pub enum GetAccountInfo {}
+
+impl JsonRpcMethod for GetAccountInfo {
+    const METHOD_NAME: &'static str = "getAccountInfo";
+    type Params = (String, AccountInfoConfig);
+    type Result = RpcResponse<Option<Account>>;
+}
+
+/// [`getSignatureStatuses`](https://solana.com/docs/rpc/http/getsignaturestatuses):
+/// returns the statuses of the transactions with the given base-58 encoded signatures,
+/// where the status of an unknown transaction is `None`.
+#[derive(Debug)]
+pub enum GetSignatureStatuses {}
+
+impl JsonRpcMethod for GetSignatureStatuses {
+    const METHOD_NAME: &'static str = "getSignatureStatuses";
+    type Params = (Vec<String>, SignatureStatusesConfig);
+    type Result = RpcResponse<Vec<Option<SignatureStatus>>>;
+}
+
+/// [`sendTransaction`](https://solana.com/docs/rpc/http/sendtransaction):
+/// submits the given fully-signed and encoded transaction to the cluster and returns
+/// its base-58 encoded signature.
+#[derive(Debug)]
+pub enum SendTransaction {}
+
+impl JsonRpcMethod for SendTransaction {
+    const METHOD_NAME: &'static str = "sendTransaction";
+    type Params = (String, SendTransactionConfig);
+    type Result = String;
+}
+
+/// How finalized a block must be to be considered by a request,
+/// see the [Solana documentation](https://solana.com/docs/rpc#configuring-state-commitment).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitmentLevel {
+    /// The most recent block processed by the node, which may still be skipped by the cluster.
+    Processed,
+    /// The most recent block voted on by a supermajority of the cluster.
+    Confirmed,
+    /// The most recent block confirmed by a supermajority of the cluster as having reached
+    /// maximum lockout.
+    Finalized,
+}
+
+/// Configuration of requests that only depend on the commitment level, such as [`GetSlot`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitmentConfig {
+    /// Commitment level, or the node's default (usually `finalized`) if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<CommitmentLevel>,
+    /// Minimum slot that the request can be evaluated at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<Slot>,
+}
+
+impl From<CommitmentLevel> for CommitmentConfig {
+    fn from(commitment: CommitmentLevel) -> Self {
+        Self {
+            commitment: Some(commitment),
+            min_context_slot: None,
+        }
+    }
+}
+
+/// Configuration of [`GetAccountInfo`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInfoConfig {
+    /// Commitment level, or the node's default if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<CommitmentLevel>,
+    /// Encoding of the account data, or [`AccountEncoding::Base58`] if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<AccountEncoding>,
+    /// Only return the given slice of the account data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_slice: Option<DataSlice>,
+    /// Minimum slot that the request can be evaluated at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<Slot>,
+}
+
+impl From<CommitmentLevel> for AccountInfoConfig {
+    fn from(commitment: CommitmentLevel) -> Self {
+        Self {
+            commitment: Some(commitment),
+            ..Self::default()
+        }
+    }
+}
+
+/// Encoding of the data of an [`Account`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum AccountEncoding {
+    /// Base-58 encoding, limited to account data of less than 129 bytes.
+    #[serde(rename = "base58")]
+    Base58,
+    /// Base-64 encoding.
+    #[serde(rename = "base64")]
+    Base64,
+    /// Base-64 encoding of the Zstandard-compressed account data.
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+    /// JSON representation of the account data for known programs, such as the token program.
+    #[serde(rename = "jsonParsed")]
+    JsonParsed,
+}
+
+/// Slice of the account data to return.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DataSlice {
+    /// Offset of the first byte.
+    pub offset: usize,
+    /// Number of bytes.
+    pub length: usize,
+}
+
+/// Result of a method together with the context in which it was evaluated.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RpcResponse<T> {
+    /// Context of the evaluation.
+    pub context: RpcContext,
+    /// Actual result of the method.
+    pub value: T,
+}
+
+/// Context in which a method was evaluated.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcContext {
+    /// Slot at which the method was evaluated.
+    pub slot: Slot,
+    /// Version of the node, if provided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+}
+
+/// Information associated with an account.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Account {
+    /// Balance of the account, in lamports.
+    pub lamports: u64,
+    /// Base-58 encoded public key of the program owning the account.
+    pub owner: String,
+    /// Data of the account.
+    pub data: AccountData,
+    /// Whether the account contains a program.
+    pub executable: bool,
+    /// Epoch at which the account will next owe rent.
+    pub rent_epoch: u64,
+    /// Size of the account data, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub space: Option<u64>,
+}
+
+/// Data of an [`Account`], depending on the requested [`AccountEncoding`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AccountData {
+    /// Encoded binary data together with its encoding.
+    Binary(String, AccountEncoding),
+    /// JSON representation of the data, when requested with [`AccountEncoding::JsonParsed`].
+    Json(Value),
+}
+
+/// Configuration of [`GetSignatureStatuses`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureStatusesConfig {
+    /// Whether to search the whole ledger history instead of only the recent status cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_transaction_history: Option<bool>,
+}
+
+/// Status of a transaction.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureStatus {
+    /// Slot in which the transaction was processed.
+    pub slot: Slot,
+    /// Number of blocks since confirmation, or `None` if the transaction is rooted.
+    pub confirmations: Option<u64>,
+    /// Error of the transaction, or `None` if the transaction succeeded.
+    pub err: Option<Value>,
+    /// Commitment level reached by the transaction.
+    pub confirmation_status: Option<CommitmentLevel>,
+}
+
+impl SignatureStatus {
+    /// Returns `true` if and only if the transaction succeeded.
+    pub fn is_success(&self) -> bool {
+        self.err.is_none()
+    }
+
+    /// Returns `true` if and only if the transaction reached the given commitment level.
+    pub fn satisfies_commitment(&self, commitment: CommitmentLevel) -> bool {
+        match commitment {
+            CommitmentLevel::Processed => true,
+            CommitmentLevel::Confirmed => matches!(
+                self.confirmation_status,
+                Some(CommitmentLevel::Confirmed | CommitmentLevel::Finalized)
+            ),
+            CommitmentLevel::Finalized => {
+                self.confirmation_status == Some(CommitmentLevel::Finalized)
+            }
+        }
+    }
+}
+
+/// Configuration of [`SendTransaction`].
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendTransactionConfig {
+    /// Encoding of the transaction, or [`TransactionEncoding::Base58`] if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<TransactionEncoding>,
+    /// Whether to skip the preflight transaction checks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_preflight: Option<bool>,
+    /// Commitment level used for the preflight checks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preflight_commitment: Option<CommitmentLevel>,
+    /// Maximum number of times the node retries sending the transaction to the leader.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<usize>,
+    /// Minimum slot at which the preflight checks can be performed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_context_slot: Option<Slot>,
+}
+
+/// Encoding of a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionEncoding {
+    /// Base-58 encoding (deprecated by Solana).
+    Base58,
+    /// Base-64 encoding.
+    Base64,
+}
@@ -0,0 +1,194 @@
+use crate::http::json::{
+    solana::{
+        AccountData, AccountEncoding, AccountInfoConfig, CommitmentConfig, CommitmentLevel,
+        DataSlice, GetAccountInfo, GetSignatureStatuses, GetSlot, SendTransaction,
+        SendTransactionConfig, SignatureStatusesConfig, TransactionEncoding,
+    },
+    JsonRpcMethod, JsonRpcRequest, JsonRpcResponse,
+};
+use assert_matches::assert_matches;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+const PUBKEY: &str = "vines1vzrYbzLMRdu58ou5XTby4qAqVRLmqo36NKPTg";
+const SIGNATURE: &str =
+    "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+
+#[test]
+fn should_serialize_get_slot() {
+    assert_eq!(
+        request::<GetSlot>((CommitmentLevel::Finalized.into(),)),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "getSlot",
+            "params": [{"commitment": "finalized"}],
+            "id": 1
+        })
+    );
+    assert_eq!(
+        request::<GetSlot>((CommitmentConfig {
+            commitment: None,
+            min_context_slot: Some(42),
+        },)),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "getSlot",
+            "params": [{"minContextSlot": 42}],
+            "id": 1
+        })
+    );
+    assert_eq!(response::<GetSlot>(json!(331_540_000)), 331_540_000);
+}
+
+#[test]
+fn should_serialize_get_account_info() {
+    let config = AccountInfoConfig {
+        encoding: Some(AccountEncoding::Base64),
+        data_slice: Some(DataSlice {
+            offset: 0,
+            length: 8,
+        }),
+        ..CommitmentLevel::Confirmed.into()
+    };
+    assert_eq!(
+        request::<GetAccountInfo>((PUBKEY.to_string(), config)),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "getAccountInfo",
+            "params": [
+                PUBKEY,
+                {"commitment": "confirmed", "encoding": "base64", "dataSlice": {"offset": 0, "length": 8}}
+            ],
+            "id": 1
+        })
+    );
+
+    let result = response::<GetAccountInfo>(json!({
+        "context": {"apiVersion": "2.0.15", "slot": 341197053},
+        "value": {
+            "data": ["AQIDBAUGBwg=", "base64"],
+            "executable": false,
+            "lamports": 88849814690250_u64,
+            "owner": "11111111111111111111111111111111",
+            "rentEpoch": 18446744073709551615_u64,
+            "space": 0
+        }
+    }));
+    assert_eq!(result.context.slot, 341197053);
+    let account = result.value.unwrap();
+    assert_eq!(account.lamports, 88849814690250);
+    assert_eq!(account.rent_epoch, u64::MAX);
+    assert_eq!(
+        account.data,
+        AccountData::Binary("AQIDBAUGBwg=".to_string(), AccountEncoding::Base64)
+    );
+
+    let parsed = response::<GetAccountInfo>(json!({
+        "context": {"slot": 1},
+        "value": {
+            "data": {"program": "spl-token", "parsed": {"type": "mint"}, "space": 82},
+            "executable": false,
+            "lamports": 1,
+            "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            "rentEpoch": 0
+        }
+    }));
+    assert_matches!(parsed.value.unwrap().data, AccountData::Json(_));
+
+    let missing = response::<GetAccountInfo>(json!({"context": {"slot": 1}, "value": null}));
+    assert_eq!(missing.value, None);
+}
+
+#[test]
+fn should_serialize_get_signature_statuses() {
+    assert_eq!(
+        request::<GetSignatureStatuses>((
+            vec![SIGNATURE.to_string()],
+            SignatureStatusesConfig {
+                search_transaction_history: Some(true)
+            }
+        )),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "getSignatureStatuses",
+            "params": [[SIGNATURE], {"searchTransactionHistory": true}],
+            "id": 1
+        })
+    );
+
+    let result = response::<GetSignatureStatuses>(json!({
+        "context": {"slot": 82},
+        "value": [
+            {
+                "slot": 48,
+                "confirmations": null,
+                "err": null,
+                "status": {"Ok": null},
+                "confirmationStatus": "finalized"
+            },
+            {
+                "slot": 80,
+                "confirmations": 2,
+                "err": {"InstructionError": [0, "InvalidAccountData"]},
+                "status": {"Err": {"InstructionError": [0, "InvalidAccountData"]}},
+                "confirmationStatus": "processed"
+            },
+            null
+        ]
+    }));
+    let [finalized, failed, unknown]: [_; 3] = result.value.try_into().unwrap();
+
+    let finalized = finalized.unwrap();
+    assert!(finalized.is_success());
+    assert!(finalized.satisfies_commitment(CommitmentLevel::Finalized));
+
+    let failed = failed.unwrap();
+    assert!(!failed.is_success());
+    assert!(failed.satisfies_commitment(CommitmentLevel::Processed));
+    assert!(!failed.satisfies_commitment(CommitmentLevel::Confirmed));
+
+    assert_eq!(unknown, None);
+}
+
+#[test]
+fn should_serialize_send_transaction() {
+    let config = SendTransactionConfig {
+        encoding: Some(TransactionEncoding::Base64),
+        skip_preflight: Some(true),
+        max_retries: Some(0),
+        ..SendTransactionConfig::default()
+    };
+    assert_eq!(
+        request::<SendTransaction>(("AQID".to_string(), config)),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "sendTransaction",
+            "params": ["AQID", {"encoding": "base64", "skipPreflight": true, "maxRetries": 0}],
+            "id": 1
+        })
+    );
+    assert_eq!(response::<SendTransaction>(json!(SIGNATURE)), SIGNATURE);
+}
+
+fn request<M>(params: M::Params) -> Value
+where
+    M: JsonRpcMethod,
+    M::Params: Serialize,
+{
+    serde_json::to_value(JsonRpcRequest::from_method::<M>(params).with_id(1_u64)).unwrap()
+}
+
+fn response<M>(result: Value) -> M::Result
+where
+    M: JsonRpcMethod,
+    M::Result: DeserializeOwned,
+{
+    serde_json::from_value::<JsonRpcResponse<M::Result>>(json!({
+        "jsonrpc": "2.0",
+        "result": result,
+        "id": 1
+    }))
+    .unwrap()
+    .into_result()
+    .unwrap()
+}
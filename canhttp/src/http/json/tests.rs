@@ -1,10 +1,12 @@
 use crate::{
     http::{
         json::{
-            ConstantSizeId, CreateJsonRpcIdFilter, HttpBatchJsonRpcRequest,
-            HttpBatchJsonRpcResponse, HttpJsonRpcRequest, HttpJsonRpcResponse, Id,
-            JsonConversionLayer, JsonRequestConverter, JsonResponseConverter, JsonRpcError,
-            JsonRpcRequest, JsonRpcResponse, Version,
+            BatchJsonRpcRequest, BatchJsonRpcResponse, ConstantSizeId, CreateJsonRpcIdFilter,
+            HttpBatchJsonRpcRequest, HttpBatchJsonRpcResponse, HttpJsonRpcRequest,
+            HttpJsonRpcResponse, Id, IdMatching, JsonConversionLayer, JsonRequestConverter,
+            JsonResponseConversionError, JsonResponseConverter, JsonResponseExtension,
+            JsonRpcError, JsonRpcRequest, JsonRpcResponse, NdJsonResponseConversionError,
+            NdJsonResponseConverter, Version,
         },
         HttpRequest, HttpResponse,
     },
@@ -118,6 +120,89 @@ mod json_rpc {
             JsonRpcResponse::from_ok(Id::Number(0), 1)
         );
     }
+
+    #[test]
+    fn should_manipulate_batch_request() {
+        let mut batch: BatchJsonRpcRequest<serde_json::Value> = (0_u64..3)
+            .map(|i| JsonRpcRequest::new("foo", json!([i])).with_id(i))
+            .collect();
+        batch.push(JsonRpcRequest::new("bar", json!([])).with_id(3_u64));
+
+        assert_eq!(batch.len(), 4);
+        assert_eq!(
+            batch.ids().cloned().collect::<Vec<_>>(),
+            (0_u64..4).map(Id::from).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            batch.get_by_id(&Id::from(3_u64)).map(|r| r.method()),
+            Some("bar")
+        );
+        assert_eq!(batch.get_by_id(&Id::from(4_u64)), None);
+
+        for request in batch.iter_mut() {
+            let id = request.id().clone();
+            request.set_id(Id::String(id.to_string()));
+        }
+        batch.retain(|request| request.method() == "foo");
+        assert_eq!(
+            batch.ids().cloned().collect::<Vec<_>>(),
+            vec![
+                Id::String("0".to_string()),
+                Id::String("1".to_string()),
+                Id::String("2".to_string())
+            ]
+        );
+
+        assert!(batch.remove_by_id(&Id::String("1".to_string())).is_some());
+        assert_eq!(batch.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&batch).unwrap(),
+            serde_json::to_value(batch.into_vec()).unwrap()
+        );
+    }
+
+    #[test]
+    fn should_manipulate_batch_response() {
+        let mut batch: BatchJsonRpcResponse<u64> = serde_json::from_value(json!([
+            {"jsonrpc": "2.0", "id": 1, "result": 10},
+            {"jsonrpc": "2.0", "id": 2, "error": {"code": -32601, "message": "Method not found"}}
+        ]))
+        .unwrap();
+
+        assert_eq!(batch.len(), 2);
+        assert!(!batch.is_empty());
+        assert_eq!(
+            batch
+                .get_by_id(&Id::from(1_u64))
+                .map(|response| response.as_result()),
+            Some(Ok(&10))
+        );
+
+        if let Some(response) = batch.get_by_id_mut(&Id::from(1_u64)) {
+            *response.as_result_mut().unwrap() += 1;
+        }
+        batch.retain(|response| response.as_result().is_ok());
+
+        assert_eq!(
+            batch.into_iter().collect::<Vec<_>>(),
+            vec![JsonRpcResponse::from_ok(Id::from(1_u64), 11)]
+        );
+    }
+    #[test]
+    fn should_convert_batch_response_into_map() {
+        let batch: BatchJsonRpcResponse<u64> = serde_json::from_value(json!([
+            {"jsonrpc": "2.0", "id": 2, "error": {"code": -32601, "message": "Method not found"}},
+            {"jsonrpc": "2.0", "id": 1, "result": 10},
+            {"jsonrpc": "2.0", "id": 1, "result": 20}
+        ]))
+        .unwrap();
+
+        let map = batch.into_map();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&Id::from(1_u64)], Ok(10));
+        assert_matches!(&map[&Id::from(2_u64)], Err(e) if e.code == -32601);
+    }
 }
 
 mod constant_size_id {
@@ -139,6 +224,48 @@ mod constant_size_id {
         assert_eq!(u64_max, max);
     }
 
+    #[test]
+    fn should_wrap_at_max_value_for_width() {
+        let mut id = ConstantSizeId::from(998_u16).with_width(3);
+        let ids: Vec<_> = (0..3).map(|_| id.get_and_increment().to_string()).collect();
+        assert_eq!(ids, vec!["998", "999", "000"]);
+        assert_eq!(ConstantSizeId::ZERO.with_width(1).max_value(), 9);
+        assert_eq!(ConstantSizeId::ZERO.max_value(), u64::MAX);
+    }
+
+    #[test]
+    fn should_keep_width_when_parsing() {
+        let id: ConstantSizeId = "0042".parse().unwrap();
+        assert_eq!(id, ConstantSizeId::from(42_u8).with_width(4));
+        assert_eq!(id.to_string(), "0042");
+    }
+
+    #[test]
+    #[should_panic(expected = "Width must be between 1 and 20")]
+    fn should_panic_on_invalid_width() {
+        let _ = ConstantSizeId::ZERO.with_width(21);
+    }
+
+    #[test]
+    fn should_persist_counter_in_stable_memory() {
+        use crate::http::{json::StableIdCounter, stable::Memory};
+        use ic_stable_structures::VectorMemory;
+
+        let memory = VectorMemory::default();
+        let first = ConstantSizeId::from(9_u8).with_width(1);
+        let counter = StableIdCounter::with_memory(memory.clone(), 100, first.clone());
+        assert_eq!(memory.size(), 0);
+        assert_eq!(counter.get_and_increment().unwrap().to_string(), "9");
+        assert_eq!(memory.size(), 1);
+
+        let counter = StableIdCounter::with_memory(memory.clone(), 100, first.clone());
+        assert_eq!(counter.get_and_increment().unwrap().to_string(), "0");
+        assert_eq!(counter.get_and_increment().unwrap().to_string(), "1");
+
+        let other = StableIdCounter::with_memory(memory, 200, first);
+        assert_eq!(other.get_and_increment().unwrap().to_string(), "9");
+    }
+
     proptest! {
         #[test]
         fn should_have_constant_size_when_serialized(id in any::<u64>()) {
@@ -159,6 +286,98 @@ mod constant_size_id {
     }
 }
 
+mod auto_id {
+    use super::*;
+    use crate::http::json::{AssignJsonRpcId, JsonRpcIdLayer};
+
+    #[tokio::test]
+    async fn should_assign_unique_ids_across_clones() {
+        let mut service = ServiceBuilder::new()
+            .layer(JsonRpcIdLayer::new(AssignJsonRpcId::constant_size()))
+            .service_fn(|request: HttpJsonRpcRequest<()>| async move {
+                Ok::<_, BoxError>(request.into_body().id().clone())
+            });
+        let other = service.clone();
+
+        let mut ids = Vec::new();
+        for mut service in [service.clone(), other.clone(), service.clone()] {
+            let request = http::Request::new(JsonRpcRequest::new("method", ()));
+            ids.push(service.ready().await.unwrap().call(request).await.unwrap());
+        }
+        let request = http::Request::new(JsonRpcRequest::new("method", ()).with_id(Id::Null));
+        ids.push(service.ready().await.unwrap().call(request).await.unwrap());
+
+        assert_eq!(
+            ids,
+            vec![
+                Id::from(ConstantSizeId::ZERO),
+                Id::from(ConstantSizeId::from(1_u8)),
+                Id::from(ConstantSizeId::from(2_u8)),
+                Id::Null
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_assign_namespaced_ids() {
+        let service = |namespace: &str| {
+            ServiceBuilder::new()
+                .layer(JsonRpcIdLayer::new(AssignJsonRpcId::namespaced(namespace)))
+                .service_fn(|request: HttpBatchJsonRpcRequest<()>| async move {
+                    Ok::<_, BoxError>(request.into_body())
+                })
+        };
+        let batch = || {
+            http::Request::new(BatchJsonRpcRequest::from(vec![
+                JsonRpcRequest::new("method", ()),
+                JsonRpcRequest::new("method", ()),
+            ]))
+        };
+
+        let mut ids = Vec::new();
+        for mut service in [service("call-1"), service("call-2")] {
+            let batch = service.ready().await.unwrap().call(batch()).await.unwrap();
+            ids.extend(batch.ids().cloned());
+        }
+
+        assert_eq!(
+            ids,
+            ["call-1-0", "call-1-1", "call-2-0", "call-2-1"].map(|id| Id::String(id.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_assign_ids_from_generator() {
+        let mut service = ServiceBuilder::new()
+            .layer(JsonRpcIdLayer::new(AssignJsonRpcId::with_generator(|| {
+                Id::String("fixed".to_string())
+            })))
+            .service_fn(|request: HttpBatchJsonRpcRequest<()>| async move {
+                Ok::<_, BoxError>(request.into_body())
+            });
+
+        let batch = BatchJsonRpcRequest::from(vec![
+            JsonRpcRequest::new("method", ()),
+            JsonRpcRequest::new("method", ()).with_id(ConstantSizeId::from(1_u8)),
+        ]);
+        let batch = service
+            .ready()
+            .await
+            .unwrap()
+            .call(http::Request::new(batch))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            batch.ids().cloned().collect::<Vec<_>>(),
+            vec![
+                Id::String("fixed".to_string()),
+                Id::from(ConstantSizeId::from(1_u8))
+            ]
+        );
+    }
+}
+
 #[tokio::test]
 async fn should_convert_json_request() {
     let url = URL;
@@ -242,6 +461,30 @@ async fn should_convert_json_response() {
     assert_eq!(converted_response.into_body(), expected_response);
 }
 
+#[test]
+fn should_decode_json_response_body() {
+    let response = http::Response::builder()
+        .status(http::StatusCode::OK)
+        .body(b"{\"foo\":\"bar\"}".to_vec())
+        .unwrap();
+
+    assert_eq!(
+        response.json::<serde_json::Value>(),
+        Ok(json!({"foo": "bar"}))
+    );
+
+    let response = http::Response::builder()
+        .status(http::StatusCode::BAD_GATEWAY)
+        .body(b"<html>Bad Gateway</html>".to_vec())
+        .unwrap();
+
+    assert_matches!(
+        response.json::<serde_json::Value>(),
+        Err(JsonResponseConversionError::InvalidJsonResponse { status, body, .. })
+        if status == 502 && body == "<html>Bad Gateway</html>"
+    );
+}
+
 #[tokio::test]
 async fn should_convert_both_request_and_response() {
     let mut service = ServiceBuilder::new()
@@ -263,6 +506,51 @@ async fn should_convert_both_request_and_response() {
     assert_eq!(response.into_body(), json!({"foo": "bar"}));
 }
 
+#[tokio::test]
+async fn should_convert_ndjson_response() {
+    let mut service = ServiceBuilder::new()
+        .convert_response(NdJsonResponseConverter::<serde_json::Value>::new())
+        .service_fn(echo_response);
+
+    let response = http::Response::new(b"{\"foo\":1}\r\n\n{\"foo\":2}\n".to_vec());
+
+    let converted_response = service.ready().await.unwrap().call(response).await.unwrap();
+
+    assert_eq!(
+        converted_response.into_body(),
+        vec![json!({"foo": 1}), json!({"foo": 2})]
+    );
+}
+
+#[tokio::test]
+async fn should_report_invalid_ndjson_line() {
+    let mut service = ServiceBuilder::new()
+        .convert_response(NdJsonResponseConverter::<serde_json::Value>::new())
+        .service_fn(echo_response);
+
+    let response = http::Response::new(b"{\"foo\":1}\n{\"foo\":\n".to_vec());
+
+    let error = service
+        .ready()
+        .await
+        .unwrap()
+        .call(response)
+        .await
+        .unwrap_err()
+        .downcast::<NdJsonResponseConversionError>()
+        .unwrap();
+
+    let NdJsonResponseConversionError::InvalidJsonLine {
+        status,
+        line_number,
+        line,
+        ..
+    } = *error;
+    assert_eq!(status, 200);
+    assert_eq!(line_number, 2);
+    assert_eq!(line, "{\"foo\":");
+}
+
 mod filter_json_rpc_id {
     use super::*;
     use std::hash::Hasher;
@@ -356,13 +644,18 @@ mod filter_json_rpc_id {
                 .map_response(shuffle_json_rpc_batch_responses)
                 .service_fn(
                     |_request: HttpBatchJsonRpcRequest<serde_json::Value>| async {
-                        Ok::<_, BoxError>(http::Response::new(responses.clone()))
+                        Ok::<_, BoxError>(http::Response::new(BatchJsonRpcResponse::from(
+                            responses.clone(),
+                        )))
                     },
                 );
 
             let service_result = service.ready().await.unwrap().call(request).await;
 
-            assert_expected_result(service_result, expected_result.map(|_| responses));
+            assert_expected_result(
+                service_result,
+                expected_result.map(|_| BatchJsonRpcResponse::from(responses)),
+            );
         }
 
         check(
@@ -411,6 +704,57 @@ mod filter_json_rpc_id {
         .await;
     }
 
+    #[tokio::test]
+    async fn should_match_numeric_string_id_when_lenient() {
+        async fn check(
+            id_matching: IdMatching,
+            request_id: Id,
+            response_id: Id,
+        ) -> Result<HttpJsonRpcResponse<serde_json::Value>, BoxError> {
+            let request = http::Request::post(URL)
+                .body(JsonRpcRequest::new("foo", json!([])).with_id(request_id))
+                .unwrap();
+            let response = JsonRpcResponse::from_ok(response_id, json!(1));
+            ServiceBuilder::new()
+                .filter_response(CreateJsonRpcIdFilter::with_id_matching(id_matching))
+                .service_fn(|_request: HttpJsonRpcRequest<serde_json::Value>| async {
+                    Ok::<_, BoxError>(http::Response::new(response.clone()))
+                })
+                .ready()
+                .await
+                .unwrap()
+                .call(request)
+                .await
+        }
+        let number = Id::from(1_u64);
+        let string = Id::String("1".to_string());
+        let constant_size = Id::from(ConstantSizeId::from(1_u64));
+
+        for (request_id, response_id) in [
+            (number.clone(), string.clone()),
+            (string.clone(), number.clone()),
+            (constant_size.clone(), number.clone()),
+        ] {
+            assert_matches!(
+                check(IdMatching::Strict, request_id.clone(), response_id.clone()).await,
+                Err(_)
+            );
+            let response = check(IdMatching::Lenient, request_id, response_id.clone())
+                .await
+                .unwrap();
+            assert_eq!(response.body().id(), &response_id);
+        }
+
+        assert_matches!(
+            check(IdMatching::Lenient, number, Id::String("2".to_string())).await,
+            Err(_)
+        );
+        assert_matches!(
+            check(IdMatching::Lenient, string, Id::String("01".to_string())).await,
+            Ok(_)
+        );
+    }
+
     #[tokio::test]
     #[should_panic(expected = "ERROR: a null request ID")]
     async fn should_panic_when_request_id_null() {
@@ -440,7 +784,11 @@ mod filter_json_rpc_id {
             .ready()
             .await
             .unwrap()
-            .call(http::Request::post(URL).body(vec![]).unwrap())
+            .call(
+                http::Request::post(URL)
+                    .body(BatchJsonRpcRequest::new())
+                    .unwrap(),
+            )
             .await
             .unwrap();
     }
@@ -452,11 +800,11 @@ mod filter_json_rpc_id {
             .filter_response(CreateJsonRpcIdFilter::new())
             .service_fn(echo_json_rpc_batch_request_ids);
 
-        let request = vec![
+        let request = BatchJsonRpcRequest::from(vec![
             JsonRpcRequest::new("foo", json!(["param1", "param2"])).with_id(Id::from(100_u64)),
             JsonRpcRequest::new("bar", json!(["param3", "param4"])).with_id(Id::from(101_u64)),
             JsonRpcRequest::new("bar", json!(["param3", "param4"])).with_id(Id::from(101_u64)),
-        ];
+        ]);
 
         let _response = service
             .ready()
@@ -474,11 +822,11 @@ mod filter_json_rpc_id {
             .filter_response(CreateJsonRpcIdFilter::new())
             .service_fn(echo_json_rpc_batch_request_ids);
 
-        let request = vec![
+        let request = BatchJsonRpcRequest::from(vec![
             JsonRpcRequest::new("foo", json!(["param1", "param2"])).with_id(Id::from(100_u64)),
             JsonRpcRequest::new("bar", json!(["param3", "param4"])).with_id(Id::from(101_u64)),
             JsonRpcRequest::new("bar", json!(["param3", "param4"])).with_id(Id::Null),
-        ];
+        ]);
 
         let _response = service
             .ready()
@@ -566,3 +914,830 @@ async fn echo_response(response: HttpResponse) -> Result<HttpResponse, BoxError>
 async fn forward_body(request: HttpRequest) -> Result<HttpResponse, BoxError> {
     Ok(http::Response::new(request.into_body()))
 }
+
+mod notification {
+    use super::*;
+    use crate::http::json::{JsonRpcNotification, JsonRpcNotificationHttpLayer};
+    use ic_cdk_management_canister::{
+        HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+    };
+
+    #[test]
+    fn should_serialize_notification_without_id() {
+        let notification = JsonRpcNotification::new("method", json!(["param"]));
+
+        assert_eq!(
+            serde_json::to_value(&notification).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "method", "params": ["param"]})
+        );
+    }
+
+    #[tokio::test]
+    async fn should_accept_any_response_body() {
+        for body in [b"".to_vec(), b"not json".to_vec()] {
+            let mut service = ServiceBuilder::new()
+                .layer(JsonRpcNotificationHttpLayer::<serde_json::Value>::new())
+                .service_fn(move |_request: IcHttpRequest| {
+                    let body = body.clone();
+                    async move {
+                        Ok::<_, BoxError>(IcHttpResponse {
+                            status: 200_u16.into(),
+                            body,
+                            ..Default::default()
+                        })
+                    }
+                });
+
+            let request = http::Request::post(URL)
+                .body(JsonRpcNotification::new("method", json!(null)))
+                .unwrap();
+            let response = service.ready().await.unwrap().call(request).await.unwrap();
+
+            assert_eq!(response.status(), http::StatusCode::OK);
+        }
+    }
+}
+
+mod batch_chunking {
+    use super::*;
+    use crate::http::json::{BatchChunkingLayer, JsonRequestConversionError};
+    use std::{cell::RefCell, rc::Rc};
+
+    fn echo_service(
+        chunks: Rc<RefCell<Vec<Vec<Id>>>>,
+    ) -> impl Service<
+        HttpBatchJsonRpcRequest<String>,
+        Response = HttpBatchJsonRpcResponse<String>,
+        Error = BoxError,
+    > + Clone {
+        tower::service_fn(move |request: HttpBatchJsonRpcRequest<String>| {
+            chunks
+                .borrow_mut()
+                .push(request.body().ids().cloned().collect());
+            let fail = request
+                .body()
+                .iter()
+                .any(|request| request.params().is_some_and(|p| p == "fail"));
+            let responses: BatchJsonRpcResponse<String> = request
+                .into_body()
+                .into_iter()
+                .map(|request| {
+                    JsonRpcResponse::from_ok(request.id().clone(), request.method().to_string())
+                })
+                .collect();
+            async move {
+                if fail {
+                    Err(BoxError::from("chunk failed"))
+                } else {
+                    Ok(http::Response::new(responses))
+                }
+            }
+        })
+    }
+
+    fn batch(params: &[&str]) -> HttpBatchJsonRpcRequest<String> {
+        let batch: BatchJsonRpcRequest<String> = params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| JsonRpcRequest::new(format!("m{i}"), p.to_string()).with_id(i as u64))
+            .collect();
+        http::Request::post(URL).body(batch).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_split_batch_by_size() {
+        let chunks = Rc::default();
+        let single_request_len =
+            serde_json::to_vec(&JsonRpcRequest::new("m0", "a".to_string()).with_id(0_u64))
+                .unwrap()
+                .len();
+        let mut service = ServiceBuilder::new()
+            .layer(
+                BatchChunkingLayer::new()
+                    .max_bytes(2 * single_request_len + 3)
+                    .max_concurrency(2),
+            )
+            .service(echo_service(Rc::clone(&chunks)));
+
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(batch(&["a", "b", "c", &"x".repeat(100), "d"]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *chunks.borrow(),
+            vec![
+                vec![Id::from(0_u8), Id::from(1_u8)],
+                vec![Id::from(2_u8)],
+                vec![Id::from(3_u8)],
+                vec![Id::from(4_u8)],
+            ]
+        );
+        let methods: Vec<_> = response
+            .into_body()
+            .into_iter()
+            .map(|response| response.into_result().unwrap())
+            .collect();
+        assert_eq!(methods, vec!["m0", "m1", "m2", "m3", "m4"]);
+    }
+
+    #[tokio::test]
+    async fn should_not_split_small_batch() {
+        let chunks = Rc::default();
+        let mut service = ServiceBuilder::new()
+            .layer(BatchChunkingLayer::new().max_entries(3))
+            .service(echo_service(Rc::clone(&chunks)));
+
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(batch(&["a", "b", "c"]))
+            .await
+            .unwrap();
+
+        assert_eq!(chunks.borrow().len(), 1);
+        assert_eq!(response.body().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_any_chunk_fails() {
+        let chunks = Rc::default();
+        let mut service = ServiceBuilder::new()
+            .layer(BatchChunkingLayer::new().max_entries(1))
+            .service(echo_service(Rc::clone(&chunks)));
+
+        let error = service
+            .ready()
+            .await
+            .unwrap()
+            .call(batch(&["a", "fail", "c"]))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "chunk failed");
+        assert_eq!(chunks.borrow().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn should_reject_duplicate_ids_across_chunks() {
+        let chunks = Rc::default();
+        let mut service = ServiceBuilder::new()
+            .layer(BatchChunkingLayer::new().max_entries(2))
+            .service(echo_service(Rc::clone(&chunks)));
+        let mut request = batch(&["a", "b", "c"]);
+        request
+            .body_mut()
+            .push(JsonRpcRequest::new("m3", "d".to_string()).with_id(0_u64));
+
+        let error = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<JsonRequestConversionError>(),
+            Some(&JsonRequestConversionError::DuplicateId { id: Id::from(0_u8) })
+        );
+        assert!(chunks.borrow().is_empty());
+    }
+}
+
+mod method {
+    use super::*;
+    use crate::http::json::{JsonRpcMethod, JsonRpcServiceExt};
+
+    struct Add;
+
+    impl JsonRpcMethod for Add {
+        const METHOD_NAME: &'static str = "add";
+        type Params = (u64, u64);
+        type Result = u64;
+    }
+
+    #[tokio::test]
+    async fn should_call_typed_method() {
+        let mut service = tower::service_fn(|request: HttpJsonRpcRequest<(u64, u64)>| async move {
+            let body = request.into_body();
+            assert_eq!(body.method(), "add");
+            let (a, b) = *body.params().unwrap();
+            let result = a
+                .checked_add(b)
+                .ok_or_else(|| JsonRpcError::new(-32602, "overflow"));
+            Ok::<_, BoxError>(http::Response::new(JsonRpcResponse::from_parts(
+                body.id().clone(),
+                result,
+            )))
+        });
+
+        let sum = service
+            .call_method::<Add>(http::Request::post(URL), (1, 2))
+            .await
+            .unwrap();
+        assert_eq!(sum, Ok(3));
+
+        let overflow = service
+            .call_method::<Add>(http::Request::post(URL), (u64::MAX, 1))
+            .await
+            .unwrap();
+        assert_matches!(overflow, Err(e) if e.code == -32602);
+
+        let invalid_request = service
+            .call_method::<Add>(http::Request::post("not a url\n"), (1, 2))
+            .await;
+        assert!(invalid_request.is_err());
+    }
+}
+
+mod leniency {
+    use super::*;
+    use crate::convert::Convert;
+    use crate::http::json::{JsonRpcHttpLayer, JsonRpcLeniency};
+    use ic_cdk_management_canister::{
+        HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+    };
+
+    #[tokio::test]
+    async fn should_reject_missing_version_by_default() {
+        let result = call(
+            JsonRpcLeniency::default(),
+            json!({"id": 1, "result": "0x1"}),
+        )
+        .await;
+
+        assert_matches!(
+            result
+                .unwrap_err()
+                .downcast_ref::<JsonResponseConversionError>(),
+            Some(JsonResponseConversionError::InvalidJsonResponse { .. })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_accept_missing_or_legacy_version() {
+        for response in [
+            json!({"id": 1, "result": "0x1"}),
+            json!({"jsonrpc": "1.0", "id": 1, "result": "0x1"}),
+            json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"}),
+        ] {
+            let result = call(JsonRpcLeniency::default().allow_missing_version(), response).await;
+
+            assert_eq!(
+                result.unwrap().into_body(),
+                JsonRpcResponse::from_ok(Id::from(1_u64), json!("0x1"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn should_still_reject_other_versions() {
+        let result = call(
+            JsonRpcLeniency::default().allow_missing_version(),
+            json!({"jsonrpc": "3.0", "id": 1, "result": "0x1"}),
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_still_validate_ids() {
+        let result = call(
+            JsonRpcLeniency::default().allow_missing_version(),
+            json!({"id": 2, "result": "0x1"}),
+        )
+        .await;
+
+        assert!(result.unwrap_err().to_string().contains("ID"));
+    }
+
+    #[tokio::test]
+    async fn should_resolve_responses_with_both_result_and_error() {
+        let error = json!({"code": -32000, "message": "header not found"});
+
+        for (response, expected) in [
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "result": null, "error": error}),
+                JsonRpcResponse::from_error(
+                    Id::from(1_u64),
+                    JsonRpcError::new(-32000, "header not found"),
+                ),
+            ),
+            (
+                json!({"jsonrpc": "2.0", "id": 1, "result": "0x1", "error": null}),
+                JsonRpcResponse::from_ok(Id::from(1_u64), json!("0x1")),
+            ),
+        ] {
+            let result = call(
+                JsonRpcLeniency::default().allow_result_and_error(),
+                response,
+            )
+            .await;
+
+            assert_eq!(result.unwrap().into_body(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_unsupported_version_when_strict() {
+        for (response, expected_version) in [
+            (json!({"id": 1, "result": "0x1"}), None),
+            (
+                json!({"jsonrpc": "1.0", "id": 1, "result": "0x1"}),
+                Some("1.0".to_string()),
+            ),
+            (
+                json!({"jsonrpc": 2, "id": 1, "result": "0x1"}),
+                Some("2".to_string()),
+            ),
+        ] {
+            let mut service = ServiceBuilder::new()
+                .layer(
+                    JsonRpcHttpLayer::new()
+                        .with_leniency(JsonRpcLeniency::default().allow_missing_version())
+                        .with_strict_version(),
+                )
+                .service_fn(move |_request: IcHttpRequest| {
+                    let body = serde_json::to_vec(&response).unwrap();
+                    async move {
+                        Ok::<_, BoxError>(IcHttpResponse {
+                            status: 200_u16.into(),
+                            body,
+                            ..Default::default()
+                        })
+                    }
+                });
+            let request = http::Request::post(URL)
+                .body(JsonRpcRequest::new("foo", json!(null)).with_id(1_u64))
+                .unwrap();
+
+            let result: Result<HttpJsonRpcResponse<serde_json::Value>, _> =
+                service.ready().await.unwrap().call(request).await;
+
+            assert_eq!(
+                result
+                    .unwrap_err()
+                    .downcast_ref::<JsonResponseConversionError>(),
+                Some(&JsonResponseConversionError::UnsupportedVersion {
+                    status: 200,
+                    version: expected_version,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn should_accept_version_2_when_strict() {
+        let mut converter = JsonResponseConverter::<BatchJsonRpcResponse<serde_json::Value>>::new()
+            .with_strict_version();
+        let response = http::Response::new(
+            serde_json::to_vec(&json!([{"jsonrpc": "2.0", "id": 1, "result": "0x1"}])).unwrap(),
+        );
+
+        assert_eq!(
+            Vec::from(converter.try_convert(response).unwrap().into_body()),
+            vec![JsonRpcResponse::from_ok(Id::from(1_u64), json!("0x1"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_accept_missing_version_in_batch() {
+        let mut service = ServiceBuilder::new()
+            .layer(
+                JsonRpcHttpLayer::<BatchJsonRpcRequest<serde_json::Value>, _>::new()
+                    .with_leniency(JsonRpcLeniency::default().allow_missing_version()),
+            )
+            .service_fn(|_request: IcHttpRequest| async {
+                Ok::<_, BoxError>(IcHttpResponse {
+                    status: 200_u16.into(),
+                    body: serde_json::to_vec(&json!([
+                        {"id": 2, "result": "0x2"},
+                        {"jsonrpc": "1.0", "id": 1, "result": "0x1"}
+                    ]))
+                    .unwrap(),
+                    ..Default::default()
+                })
+            });
+        let request = http::Request::post(URL)
+            .body(BatchJsonRpcRequest::from(vec![
+                JsonRpcRequest::new("foo", json!(null)).with_id(1_u64),
+                JsonRpcRequest::new("bar", json!(null)).with_id(2_u64),
+            ]))
+            .unwrap();
+
+        let response: HttpBatchJsonRpcResponse<serde_json::Value> =
+            service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(
+            Vec::from(response.into_body()),
+            vec![
+                JsonRpcResponse::from_ok(Id::from(1_u64), json!("0x1")),
+                JsonRpcResponse::from_ok(Id::from(2_u64), json!("0x2")),
+            ]
+        );
+    }
+
+    async fn call(
+        leniency: JsonRpcLeniency,
+        response: serde_json::Value,
+    ) -> Result<HttpJsonRpcResponse<serde_json::Value>, BoxError> {
+        let mut service = ServiceBuilder::new()
+            .layer(JsonRpcHttpLayer::new().with_leniency(leniency))
+            .service_fn(move |_request: IcHttpRequest| {
+                let body = serde_json::to_vec(&response).unwrap();
+                async move {
+                    Ok::<_, BoxError>(IcHttpResponse {
+                        status: 200_u16.into(),
+                        body,
+                        ..Default::default()
+                    })
+                }
+            });
+        let request = http::Request::post(URL)
+            .body(JsonRpcRequest::new("foo", json!(null)).with_id(1_u64))
+            .unwrap();
+
+        service.ready().await.unwrap().call(request).await
+    }
+}
+
+mod heterogeneous_batch {
+    use super::*;
+    use crate::http::json::{BatchEntryError, HeterogeneousBatch, JsonRpcHttpLayer, JsonRpcMethod};
+    use ic_cdk_management_canister::{
+        HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+    };
+    use serde::Deserialize;
+
+    struct GetBalance;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Balance {
+        value: u64,
+    }
+
+    impl JsonRpcMethod for GetBalance {
+        const METHOD_NAME: &'static str = "getBalance";
+        type Params = (String,);
+        type Result = Balance;
+    }
+
+    #[test]
+    fn should_build_batch_with_different_params() {
+        let mut batch = HeterogeneousBatch::new();
+        batch.add::<u64>("getSlot", ()).unwrap();
+        batch
+            .add_method::<GetBalance>(("account".to_string(),))
+            .unwrap();
+
+        assert_eq!(batch.len(), 2);
+        assert_eq!(
+            serde_json::to_value(batch.build()).unwrap(),
+            json!([
+                {"jsonrpc": "2.0", "method": "getSlot", "params": null, "id": 0},
+                {"jsonrpc": "2.0", "method": "getBalance", "params": ["account"], "id": 1},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn should_decode_each_entry_with_its_own_type() {
+        let mut batch = HeterogeneousBatch::new();
+        let slot = batch.add::<u64>("getSlot", ()).unwrap();
+        let balance = batch
+            .add_method::<GetBalance>(("account".to_string(),))
+            .unwrap();
+        let version = batch.add::<serde_json::Value>("getVersion", ()).unwrap();
+        let invalid = batch.add::<u64>("getSlot", ()).unwrap();
+
+        let mut service = ServiceBuilder::new()
+            .layer(JsonRpcHttpLayer::new())
+            .service_fn(|_request: IcHttpRequest| async {
+                Ok::<_, BoxError>(IcHttpResponse {
+                    status: 200_u16.into(),
+                    body: serde_json::to_vec(&json!([
+                        {"jsonrpc": "2.0", "id": 2, "error": {"code": -32601, "message": "Method not found"}},
+                        {"jsonrpc": "2.0", "id": 1, "result": {"value": 42}},
+                        {"jsonrpc": "2.0", "id": 0, "result": 123},
+                        {"jsonrpc": "2.0", "id": 3, "result": "not a number"},
+                    ]))
+                    .unwrap(),
+                    ..Default::default()
+                })
+            });
+        let request = http::Request::post(URL).body(batch.build()).unwrap();
+
+        let response: HttpBatchJsonRpcResponse<serde_json::Value> =
+            service.ready().await.unwrap().call(request).await.unwrap();
+        let responses = response.into_body();
+
+        assert_eq!(slot.decode(&responses), Ok(Ok(123)));
+        assert_eq!(balance.decode(&responses), Ok(Ok(Balance { value: 42 })));
+        assert_eq!(
+            version.decode(&responses),
+            Ok(Err(JsonRpcError::new(-32601, "Method not found")))
+        );
+        assert_matches!(
+            invalid.decode(&responses),
+            Err(BatchEntryError::InvalidResult { id, .. }) if id == Id::from(3_u64)
+        );
+    }
+
+    #[test]
+    fn should_fail_when_response_is_missing() {
+        let mut batch = HeterogeneousBatch::new();
+        let slot = batch.add::<u64>("getSlot", ()).unwrap();
+
+        assert_eq!(
+            slot.decode(&BatchJsonRpcResponse::new()),
+            Err(BatchEntryError::MissingResponse {
+                id: Id::from(0_u64)
+            })
+        );
+    }
+}
+
+mod request_filter {
+    use super::*;
+    use crate::http::json::{JsonRequestConversionError, JsonRpcHttpLayer, JsonRpcRequestFilter};
+    use ic_cdk_management_canister::{
+        HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+    };
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn should_find_duplicate_id() {
+        let batch = |ids: &[u64]| {
+            ids.iter()
+                .map(|id| JsonRpcRequest::new("foo", json!(null)).with_id(*id))
+                .collect::<BatchJsonRpcRequest<serde_json::Value>>()
+        };
+
+        assert_eq!(batch(&[]).find_duplicate_id(), None);
+        assert_eq!(batch(&[1, 2, 3]).find_duplicate_id(), None);
+        assert_eq!(
+            batch(&[1, 2, 3, 2, 1]).find_duplicate_id(),
+            Some(&Id::from(2_u64))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_batch_with_duplicate_ids_before_sending() {
+        let calls = Rc::new(Cell::new(0));
+        let mut service = ServiceBuilder::new()
+            .layer(JsonRpcHttpLayer::<_, BatchJsonRpcResponse<serde_json::Value>>::new())
+            .service_fn(|_request: IcHttpRequest| {
+                calls.set(calls.get() + 1);
+                async { Ok::<_, BoxError>(IcHttpResponse::default()) }
+            });
+        let request = http::Request::post(URL)
+            .body(BatchJsonRpcRequest::from(vec![
+                JsonRpcRequest::new("foo", json!(null)).with_id(1_u64),
+                JsonRpcRequest::new("bar", json!(null)).with_id(1_u64),
+            ]))
+            .unwrap();
+
+        let result = service.ready().await.unwrap().call(request).await;
+
+        assert_eq!(
+            result
+                .unwrap_err()
+                .downcast_ref::<JsonRequestConversionError>(),
+            Some(&JsonRequestConversionError::DuplicateId {
+                id: Id::from(1_u64)
+            })
+        );
+        assert_eq!(calls.get(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_reject_empty_batch_before_sending() {
+        let calls = Rc::new(Cell::new(0));
+        let mut service = ServiceBuilder::new()
+            .layer(JsonRpcHttpLayer::<_, BatchJsonRpcResponse<serde_json::Value>>::new())
+            .service_fn(|_request: IcHttpRequest| {
+                calls.set(calls.get() + 1);
+                async { Ok::<_, BoxError>(IcHttpResponse::default()) }
+            });
+        let request = http::Request::post(URL)
+            .body(BatchJsonRpcRequest::<serde_json::Value>::new())
+            .unwrap();
+
+        let result = service.ready().await.unwrap().call(request).await;
+
+        assert_eq!(
+            result
+                .unwrap_err()
+                .downcast_ref::<JsonRequestConversionError>(),
+            Some(&JsonRequestConversionError::EmptyBatch)
+        );
+        assert_eq!(calls.get(), 0);
+    }
+
+    #[test]
+    fn should_accept_single_request() {
+        use crate::convert::Filter;
+
+        let request = http::Request::post(URL)
+            .body(JsonRpcRequest::new("foo", json!(null)))
+            .unwrap();
+
+        assert!(JsonRpcRequestFilter::new().filter(request).is_ok());
+    }
+
+    #[test]
+    fn should_reject_ids_matching_leniently() {
+        use crate::convert::Filter;
+
+        let request = http::Request::post(URL)
+            .body(BatchJsonRpcRequest::from(vec![
+                JsonRpcRequest::new("foo", json!(null)).with_id(1_u64),
+                JsonRpcRequest::new("bar", json!(null)).with_id(Id::String("01".to_string())),
+            ]))
+            .unwrap();
+
+        assert!(JsonRpcRequestFilter::new().filter(request.clone()).is_ok());
+        assert_eq!(
+            JsonRpcRequestFilter::with_id_matching(IdMatching::Lenient)
+                .filter(request)
+                .unwrap_err(),
+            JsonRequestConversionError::DuplicateId {
+                id: Id::String("01".to_string())
+            }
+        );
+    }
+}
+
+mod max_response_bytes_hints {
+    use super::*;
+    use crate::{
+        convert::Filter, http::json::MaxResponseBytesHints, MaxResponseBytesRequestExtension,
+    };
+
+    fn hints() -> MaxResponseBytesHints {
+        MaxResponseBytesHints::new()
+            .method("getSlot", 100)
+            .method("getBlock", 1_500_000)
+    }
+
+    fn request(method: &str) -> JsonRpcRequest<serde_json::Value> {
+        JsonRpcRequest::new(method, json!(null)).with_id(1_u64)
+    }
+
+    #[test]
+    fn should_set_max_response_bytes_by_method() {
+        for (method, expected) in [("getSlot", Some(100)), ("getBalance", None)] {
+            let request = http::Request::post(URL).body(request(method)).unwrap();
+
+            let request = hints().filter(request).unwrap();
+
+            assert_eq!(request.get_max_response_bytes(), expected);
+        }
+
+        let request = http::Request::post(URL)
+            .body(request("getBalance"))
+            .unwrap();
+        let request = hints()
+            .default_max_response_bytes(1_000)
+            .filter(request)
+            .unwrap();
+        assert_eq!(request.get_max_response_bytes(), Some(1_000));
+    }
+
+    #[test]
+    fn should_not_override_max_response_bytes_set_by_caller() {
+        let request = http::Request::post(URL)
+            .max_response_bytes(42)
+            .body(request("getSlot"))
+            .unwrap();
+
+        let request = hints().filter(request).unwrap();
+
+        assert_eq!(request.get_max_response_bytes(), Some(42));
+    }
+
+    #[test]
+    fn should_add_up_hints_of_batch() {
+        let batch = |methods: &[&str]| {
+            http::Request::post(URL)
+                .body(
+                    methods
+                        .iter()
+                        .map(|method| request(method))
+                        .collect::<BatchJsonRpcRequest<_>>(),
+                )
+                .unwrap()
+        };
+
+        for (methods, expected) in [
+            (vec!["getSlot", "getSlot"], Some(200)),
+            (vec!["getSlot", "getBlock", "getBlock"], Some(2_000_000)),
+            (vec!["getSlot", "getBalance"], None),
+            (vec![], None),
+        ] {
+            let request = hints().filter(batch(&methods)).unwrap();
+
+            assert_eq!(request.get_max_response_bytes(), expected, "{methods:?}");
+        }
+    }
+}
+
+mod named_params {
+    use super::*;
+    use crate::http::json::{NamedParams, NamedParamsError};
+    use serde_json::Value;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct TraceConfig {
+        tracer: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timeout: Option<&'static str>,
+        only_top_call: bool,
+    }
+
+    #[test]
+    fn should_build_named_params_from_struct() {
+        let config = TraceConfig {
+            tracer: "callTracer",
+            timeout: None,
+            only_top_call: true,
+        };
+
+        let params = NamedParams::from_struct(&config).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(JsonRpcRequest::new("debug_traceCall", params).with_id(1_u64))
+                .unwrap(),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "debug_traceCall",
+                "params": {"tracer": "callTracer", "onlyTopCall": true},
+                "id": 1
+            })
+        );
+    }
+
+    #[test]
+    fn should_merge_extra_fields() {
+        let config = TraceConfig {
+            tracer: "callTracer",
+            timeout: Some("10s"),
+            only_top_call: false,
+        };
+
+        let params = NamedParams::new()
+            .with("tracer", "prestateTracer")
+            .with("blockNumber", "latest")
+            .merge(&config)
+            .unwrap()
+            .with("onlyTopCall", true);
+
+        assert_eq!(
+            Value::from(params),
+            json!({
+                "tracer": "callTracer",
+                "blockNumber": "latest",
+                "timeout": "10s",
+                "onlyTopCall": true
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_params_not_serialized_as_object() {
+        assert_eq!(
+            NamedParams::from_struct(&("0x1", "latest")),
+            Err(NamedParamsError::NotAnObject {
+                kind: "array".to_string()
+            })
+        );
+        assert_eq!(
+            NamedParams::new().merge(&42),
+            Err(NamedParamsError::NotAnObject {
+                kind: "number".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn should_deserialize_named_params() {
+        let request: JsonRpcRequest<NamedParams> = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "method": "block",
+            "params": {"height": "42"},
+            "id": 1
+        }))
+        .unwrap();
+
+        let params = request.params().unwrap();
+        assert_eq!(params.len(), 1);
+        assert_eq!(params.get("height"), Some(&json!("42")));
+    }
+}
@@ -0,0 +1,543 @@
+//! Verification of [JSON Web Signatures](https://www.rfc-editor.org/rfc/rfc7515) (JWS) and
+//! [JSON Web Tokens](https://www.rfc-editor.org/rfc/rfc7519) (JWT) returned by APIs.
+//!
+//! A [`JwsVerifier`] holds the public keys (or shared secrets) of the issuer, typically obtained
+//! from its [JSON Web Key Set](https://www.rfc-editor.org/rfc/rfc7517#section-5) (JWKS).
+//! It verifies tokens in the compact serialization, i.e. `header.payload.signature`, and
+//! decodes the payload into a user-defined type.
+//! Used as a response converter via [`JwsResponseConverter`], it turns a response whose body is
+//! such a token into a response with the decoded claims as body.
+//!
+//! The following algorithms are supported:
+//! * `HS256`: HMAC with SHA-256;
+//! * `RS256`: RSASSA-PKCS1-v1_5 with SHA-256;
+//! * `ES256`: ECDSA with the P-256 curve and SHA-256;
+//! * `EdDSA`: EdDSA with the Ed25519 curve.
+//!
+//! The algorithm announced in the header of a token must match the algorithm of the key used
+//! to verify it. If the payload is a JSON object containing the `exp` (expiration time) or `nbf`
+//! (not before) claims, they are validated against the current time.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::{
+//!     convert::ConvertServiceBuilder,
+//!     http::{
+//!         jws::{JwsResponseConverter, JwsVerifier, VerificationKey},
+//!         HttpRequest, HttpResponse,
+//!     },
+//! };
+//! use serde::Deserialize;
+//! use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+//!
+//! #[derive(Debug, Deserialize, PartialEq)]
+//! struct Price {
+//!     symbol: String,
+//!     price: String,
+//! }
+//!
+//! async fn signed_price(_request: HttpRequest) -> Result<HttpResponse, BoxError> {
+//!     let token = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.\
+//!         eyJzeW1ib2wiOiJJQ1BVU0RUIiwicHJpY2UiOiI1LjQyIiwiZXhwIjoxNzAwMDAwMDYwfQ.\
+//!         W53dPTvO8dtwDsWb6BvxwaVbzH8Wjne2WtBVI7aJ-a4";
+//!     Ok(http::Response::new(token.as_bytes().to_vec()))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let verifier = JwsVerifier::new().key(VerificationKey::hmac(b"secret"));
+//! # let verifier = verifier.clock(|| 1_700_000_000_000_000_000);
+//!
+//! let mut service = ServiceBuilder::new()
+//!     .convert_response(JwsResponseConverter::<Price>::new(verifier))
+//!     .service_fn(signed_price);
+//!
+//! let request = http::Request::get("https://api.example.com/price").body(vec![])?;
+//! let response = service.ready().await?.call(request).await?;
+//!
+//! assert_eq!(
+//!     response.into_body(),
+//!     Price {
+//!         symbol: "ICPUSDT".to_string(),
+//!         price: "5.42".to_string()
+//!     }
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    clock::Clock,
+    convert::Convert,
+    http::{signing::hmac_sha256, HttpResponse},
+};
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use p256::ecdsa::signature::Verifier;
+use rsa::{pkcs1v15::Pkcs1v15Sign, BigUint, RsaPublicKey};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{fmt, marker::PhantomData, time::Duration};
+use thiserror::Error;
+
+/// Base64url encoding as used by JOSE, accepting both padded and unpadded inputs.
+const BASE64_URL: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+const DEFAULT_LEEWAY: Duration = Duration::from_secs(60);
+
+/// Error returned when verifying a JWS or JWT.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum JwsError {
+    /// The token is not a valid JWS in the compact serialization.
+    #[error("Malformed JWS: {0}")]
+    Malformed(String),
+    /// The algorithm in the header of the token is not supported.
+    #[error("Unsupported JWS algorithm `{0}`")]
+    UnsupportedAlgorithm(String),
+    /// No configured key matches the key ID and the algorithm in the header of the token.
+    #[error("No key to verify JWS with algorithm `{alg}` and key ID {kid:?}")]
+    UnknownKey {
+        /// Algorithm in the header of the token.
+        alg: String,
+        /// Key ID in the header of the token, if any.
+        kid: Option<String>,
+    },
+    /// The signature of the token is invalid.
+    #[error("Invalid JWS signature")]
+    InvalidSignature,
+    /// The token expired.
+    #[error("JWT expired at {exp}, current time is {now} (seconds since UNIX epoch)")]
+    Expired {
+        /// Expiration time of the token, in seconds since the UNIX epoch.
+        exp: u64,
+        /// Current time, in seconds since the UNIX epoch.
+        now: u64,
+    },
+    /// The token is not yet valid.
+    #[error("JWT not valid before {nbf}, current time is {now} (seconds since UNIX epoch)")]
+    NotYetValid {
+        /// Time before which the token must not be accepted, in seconds since the UNIX epoch.
+        nbf: u64,
+        /// Current time, in seconds since the UNIX epoch.
+        now: u64,
+    },
+    /// The payload of the token could not be deserialized.
+    #[error("Invalid JWS payload: {0}")]
+    InvalidClaims(String),
+    /// A verification key is invalid.
+    #[error("Invalid JWS verification key: {0}")]
+    InvalidKey(String),
+}
+
+/// Signature algorithm of a JWS, see [RFC 7518](https://www.rfc-editor.org/rfc/rfc7518#section-3.1).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Algorithm {
+    /// HMAC with SHA-256.
+    #[serde(rename = "HS256")]
+    Hs256,
+    /// RSASSA-PKCS1-v1_5 with SHA-256.
+    #[serde(rename = "RS256")]
+    Rs256,
+    /// ECDSA with the P-256 curve and SHA-256.
+    #[serde(rename = "ES256")]
+    Es256,
+    /// EdDSA with the Ed25519 curve, see [RFC 8037](https://www.rfc-editor.org/rfc/rfc8037).
+    #[serde(rename = "EdDSA")]
+    EdDsa,
+}
+
+impl Algorithm {
+    /// Returns the name of the algorithm as used in the `alg` header parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Algorithm::Hs256 => "HS256",
+            Algorithm::Rs256 => "RS256",
+            Algorithm::Es256 => "ES256",
+            Algorithm::EdDsa => "EdDSA",
+        }
+    }
+}
+
+/// A JSON Web Key, see [RFC 7517](https://www.rfc-editor.org/rfc/rfc7517).
+///
+/// Only the parameters needed to verify signatures are retained.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Jwk {
+    /// Key type, e.g. `RSA`, `EC`, `OKP` or `oct`.
+    pub kty: String,
+    /// Key ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// Algorithm intended for use with the key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    /// Curve of an elliptic curve key, e.g. `P-256` or `Ed25519`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// Base64url-encoded x coordinate (`EC`) or public key (`OKP`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    /// Base64url-encoded y coordinate of an `EC` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+    /// Base64url-encoded modulus of an `RSA` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// Base64url-encoded exponent of an `RSA` key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    /// Base64url-encoded secret of an `oct` (symmetric) key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k: Option<String>,
+}
+
+/// A JSON Web Key Set, as typically served by issuers at a `jwks.json` endpoint.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct JwkSet {
+    /// The keys of the set.
+    pub keys: Vec<Jwk>,
+}
+
+/// A key to verify the signature of a JWS.
+#[derive(Clone)]
+pub enum VerificationKey {
+    /// Shared secret for `HS256`.
+    Hmac(Vec<u8>),
+    /// RSA public key for `RS256`.
+    Rsa(RsaPublicKey),
+    /// P-256 public key for `ES256`.
+    P256(p256::ecdsa::VerifyingKey),
+    /// Ed25519 public key for `EdDSA`.
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+impl VerificationKey {
+    /// Create a key for `HS256` from the given shared secret.
+    pub fn hmac(secret: impl AsRef<[u8]>) -> Self {
+        Self::Hmac(secret.as_ref().to_vec())
+    }
+
+    /// Create a key for `RS256` from the big-endian modulus and public exponent.
+    pub fn rsa(n: &[u8], e: &[u8]) -> Result<Self, JwsError> {
+        RsaPublicKey::new(BigUint::from_bytes_be(n), BigUint::from_bytes_be(e))
+            .map(Self::Rsa)
+            .map_err(|e| JwsError::InvalidKey(e.to_string()))
+    }
+
+    /// Create a key for `ES256` from the big-endian coordinates of the public point.
+    pub fn p256(x: &[u8], y: &[u8]) -> Result<Self, JwsError> {
+        let sec1: Vec<u8> = [&[0x04], x, y].concat();
+        p256::ecdsa::VerifyingKey::from_sec1_bytes(&sec1)
+            .map(Self::P256)
+            .map_err(|e| JwsError::InvalidKey(e.to_string()))
+    }
+
+    /// Create a key for `EdDSA` from the 32-byte Ed25519 public key.
+    pub fn ed25519(public_key: &[u8]) -> Result<Self, JwsError> {
+        let bytes: [u8; 32] = public_key
+            .try_into()
+            .map_err(|_| JwsError::InvalidKey("Ed25519 public key must be 32 bytes".to_string()))?;
+        ed25519_dalek::VerifyingKey::from_bytes(&bytes)
+            .map(Self::Ed25519)
+            .map_err(|e| JwsError::InvalidKey(e.to_string()))
+    }
+
+    /// Returns the algorithm that this key verifies.
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            VerificationKey::Hmac(_) => Algorithm::Hs256,
+            VerificationKey::Rsa(_) => Algorithm::Rs256,
+            VerificationKey::P256(_) => Algorithm::Es256,
+            VerificationKey::Ed25519(_) => Algorithm::EdDsa,
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            VerificationKey::Hmac(secret) => {
+                let expected = hmac_sha256(secret, message);
+                // Compare in constant time to not leak how many leading bytes are correct.
+                signature.len() == expected.len()
+                    && signature
+                        .iter()
+                        .zip(expected.iter())
+                        .fold(0, |acc, (a, b)| acc | (a ^ b))
+                        == 0
+            }
+            VerificationKey::Rsa(key) => key
+                .verify(
+                    Pkcs1v15Sign::new::<rsa::sha2::Sha256>(),
+                    &Sha256::digest(message),
+                    signature,
+                )
+                .is_ok(),
+            VerificationKey::P256(key) => p256::ecdsa::Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify(message, &signature).is_ok()),
+            VerificationKey::Ed25519(key) => ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify_strict(message, &signature).is_ok()),
+        }
+    }
+}
+
+impl TryFrom<&Jwk> for VerificationKey {
+    type Error = JwsError;
+
+    fn try_from(jwk: &Jwk) -> Result<Self, Self::Error> {
+        let param = |name: &str, value: &Option<String>| -> Result<Vec<u8>, JwsError> {
+            let value = value.as_ref().ok_or_else(|| {
+                JwsError::InvalidKey(format!("missing parameter `{name}` in {} key", jwk.kty))
+            })?;
+            BASE64_URL.decode(value).map_err(|e| {
+                JwsError::InvalidKey(format!(
+                    "invalid parameter `{name}` in {} key: {e}",
+                    jwk.kty
+                ))
+            })
+        };
+        match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+            ("oct", _) => Ok(Self::hmac(param("k", &jwk.k)?)),
+            ("RSA", _) => Self::rsa(&param("n", &jwk.n)?, &param("e", &jwk.e)?),
+            ("EC", Some("P-256")) => Self::p256(&param("x", &jwk.x)?, &param("y", &jwk.y)?),
+            ("OKP", Some("Ed25519")) => Self::ed25519(&param("x", &jwk.x)?),
+            (kty, crv) => Err(JwsError::InvalidKey(format!(
+                "unsupported key type `{kty}` with curve {crv:?}"
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for VerificationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationKey::Hmac(_) => f.write_str("Hmac(<redacted>)"),
+            VerificationKey::Rsa(key) => f.debug_tuple("Rsa").field(key).finish(),
+            VerificationKey::P256(key) => f.debug_tuple("P256").field(key).finish(),
+            VerificationKey::Ed25519(key) => f.debug_tuple("Ed25519").field(key).finish(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+/// Verify JWS and JWT in the compact serialization and decode their payload.
+///
+/// A token is verified with the configured keys that have the same key ID (if the header of the
+/// token specifies one) and whose algorithm is the one announced in the header of the token.
+#[derive(Clone)]
+pub struct JwsVerifier {
+    keys: Vec<(Option<String>, VerificationKey)>,
+    leeway: Duration,
+    clock: Clock,
+}
+
+impl JwsVerifier {
+    /// Create a new [`JwsVerifier`] without any key.
+    pub fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            leeway: DEFAULT_LEEWAY,
+            clock: Clock::default(),
+        }
+    }
+
+    /// Create a new [`JwsVerifier`] with all keys of the given JSON Web Key Set.
+    ///
+    /// Fails if any of the keys is invalid or of an unsupported type.
+    pub fn from_jwks(jwks: &JwkSet) -> Result<Self, JwsError> {
+        jwks.keys.iter().try_fold(Self::new(), |verifier, jwk| {
+            let key = VerificationKey::try_from(jwk)?;
+            if let Some(alg) = &jwk.alg {
+                if alg != key.algorithm().as_str() {
+                    return Err(JwsError::InvalidKey(format!(
+                        "algorithm `{alg}` does not match {} key",
+                        jwk.kty
+                    )));
+                }
+            }
+            Ok(match &jwk.kid {
+                Some(kid) => verifier.key_with_id(kid.clone(), key),
+                None => verifier.key(key),
+            })
+        })
+    }
+
+    /// Add a key without key ID, which is used for tokens regardless of their key ID.
+    pub fn key(mut self, key: VerificationKey) -> Self {
+        self.keys.push((None, key));
+        self
+    }
+
+    /// Add a key with the given key ID, which is only used for tokens with that key ID.
+    pub fn key_with_id(mut self, kid: impl Into<String>, key: VerificationKey) -> Self {
+        self.keys.push((Some(kid.into()), key));
+        self
+    }
+
+    /// Set the tolerance for clock skew when validating the `exp` and `nbf` claims.
+    ///
+    /// Defaults to one minute.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Change the clock used to validate the `exp` and `nbf` claims.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+
+    /// Verify the given token and deserialize its payload.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T, JwsError> {
+        let token = token.trim();
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(JwsError::Malformed(
+                "expected 3 base64url-encoded parts separated by `.`".to_string(),
+            ));
+        };
+        let signing_input = &token.as_bytes()[..header.len() + 1 + payload.len()];
+        let decode = |name: &str, part: &str| {
+            BASE64_URL
+                .decode(part)
+                .map_err(|e| JwsError::Malformed(format!("invalid {name}: {e}")))
+        };
+        let header_bytes = decode("header", header)?;
+        let payload_bytes = decode("payload", payload)?;
+        let signature = decode("signature", signature)?;
+        let header: Header = serde_json::from_slice(&header_bytes)
+            .map_err(|e| JwsError::Malformed(format!("invalid header: {e}")))?;
+        let alg: Algorithm = serde_json::from_value(Value::String(header.alg.clone()))
+            .map_err(|_| JwsError::UnsupportedAlgorithm(header.alg.clone()))?;
+
+        let mut candidates = self
+            .keys
+            .iter()
+            .filter(|(kid, key)| {
+                key.algorithm() == alg && (kid.is_none() || kid.as_ref() == header.kid.as_ref())
+            })
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(JwsError::UnknownKey {
+                alg: header.alg,
+                kid: header.kid,
+            });
+        }
+        if !candidates.any(|(_kid, key)| key.verify(signing_input, &signature)) {
+            return Err(JwsError::InvalidSignature);
+        }
+
+        let claims: Value = serde_json::from_slice(&payload_bytes)
+            .map_err(|e| JwsError::InvalidClaims(e.to_string()))?;
+        self.validate_time(&claims)?;
+        serde_json::from_value(claims).map_err(|e| JwsError::InvalidClaims(e.to_string()))
+    }
+
+    fn validate_time(&self, claims: &Value) -> Result<(), JwsError> {
+        let now = self.clock.now() / 1_000_000_000;
+        let leeway = self.leeway.as_secs();
+        let numeric_date = |name: &str| -> Result<Option<u64>, JwsError> {
+            match claims.get(name) {
+                None => Ok(None),
+                Some(value) => value
+                    .as_f64()
+                    .filter(|date| *date >= 0.0)
+                    .map(|date| Some(date as u64))
+                    .ok_or_else(|| {
+                        JwsError::InvalidClaims(format!("`{name}` is not a NumericDate: {value}"))
+                    }),
+            }
+        };
+        if let Some(exp) = numeric_date("exp")? {
+            if now > exp.saturating_add(leeway) {
+                return Err(JwsError::Expired { exp, now });
+            }
+        }
+        if let Some(nbf) = numeric_date("nbf")? {
+            if now.saturating_add(leeway) < nbf {
+                return Err(JwsError::NotYetValid { nbf, now });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for JwsVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for JwsVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwsVerifier")
+            .field("keys", &self.keys)
+            .field("leeway", &self.leeway)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Convert responses of type [`HttpResponse`] whose body is a JWS or JWT in the compact
+/// serialization into [`http::Response<T>`], where `T` is the `Deserialize` payload,
+/// see [`JwsVerifier`].
+#[derive(Debug)]
+pub struct JwsResponseConverter<T> {
+    verifier: JwsVerifier,
+    _marker: PhantomData<T>,
+}
+
+impl<T> JwsResponseConverter<T> {
+    /// Create a new instance of [`JwsResponseConverter`] verifying tokens with the given verifier.
+    pub fn new(verifier: JwsVerifier) -> Self {
+        Self {
+            verifier,
+            _marker: PhantomData,
+        }
+    }
+}
+
+// #[derive(Clone)] would otherwise introduce a bound T: Clone, which is not needed.
+impl<T> Clone for JwsResponseConverter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            verifier: self.verifier.clone(),
+            _marker: self._marker,
+        }
+    }
+}
+
+impl<T> Convert<HttpResponse> for JwsResponseConverter<T>
+where
+    T: DeserializeOwned,
+{
+    type Output = http::Response<T>;
+    type Error = JwsError;
+
+    fn try_convert(&mut self, response: HttpResponse) -> Result<Self::Output, Self::Error> {
+        let (parts, body) = response.into_parts();
+        let token = std::str::from_utf8(&body)
+            .map_err(|e| JwsError::Malformed(format!("body is not UTF-8: {e}")))?;
+        let claims = self.verifier.verify(token)?;
+        Ok(http::Response::from_parts(parts, claims))
+    }
+}
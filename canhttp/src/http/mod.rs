@@ -65,16 +65,67 @@
 #[cfg(test)]
 mod tests;
 
-pub use request::{HttpRequest, HttpRequestConversionError, HttpRequestConverter};
+pub use api_key::{ApiKeyError, ApiKeyRotation, ApiKeyRotationLayer, ApiKeyRotationService};
+pub use auth::{AuthorizationError, BasicAuth, BasicAuthLayer, BearerAuth, BearerAuthLayer};
+pub use builder::{RequestBuilder, RequestBuilderError};
+pub use checksum::{
+    BodyChecksumError, BodyChecksumFilter, BodyChecksumLayer, BodyChecksumRequestExtension,
+    VerifyBodyChecksum,
+};
+pub use correlation::{
+    AddCorrelationId, CorrelationIdError, CorrelationIdLayer, CorrelationIdRequestExtension,
+    X_REQUEST_ID,
+};
+pub use ecdsa::{
+    EcdsaSignatureError, EcdsaSignatureLayer, EcdsaSignatureService, EcdsaSigner,
+    ThresholdEcdsaClient,
+};
+pub use header::{
+    CanonicalizeHeaders, CanonicalizeHeadersLayer, HeaderResponseExtension, InvalidHeaderError,
+    MediaType, RetryAfter,
+};
+pub use idempotency::{
+    AddIdempotencyKey, IdempotencyKeyError, IdempotencyKeyLayer, IdempotencyKeyRequestExtension,
+};
+pub use query::{QueryParams, QueryRequestExtension};
+pub use request::{
+    HttpRequest, HttpRequestConversionError, HttpRequestConverter, UrlPolicy, UrlPolicyError,
+    UrlPolicyLayer,
+};
 pub use response::{
-    FilterNonSuccessfulHttpResponse, FilterNonSuccessfulHttpResponseError, HttpResponse,
-    HttpResponseConversionError, HttpResponseConverter,
+    CharsetConversionError, CharsetConverter, FilterContentType, FilterNonSuccessfulHttpResponse,
+    FilterNonSuccessfulHttpResponseError, FilterStatusCode, HttpResponse,
+    HttpResponseConversionError, HttpResponseConverter, HttpStatusError, StatusCodeFilterLayer,
+    UnexpectedContentTypeError,
+};
+pub use signing::{
+    Canonicalization, HmacSignatureError, HmacSignatureLayer, HmacSigner, Placement,
+    SignatureEncoding,
 };
 
+mod api_key;
+mod auth;
+mod builder;
+mod checksum;
+mod correlation;
+mod ecdsa;
+#[cfg(feature = "candid")]
+pub mod gateway;
+mod header;
+mod idempotency;
 #[cfg(feature = "json")]
 pub mod json;
+#[cfg(feature = "jws")]
+pub mod jws;
+#[cfg(feature = "json")]
+pub mod oauth2;
+mod query;
 mod request;
 mod response;
+#[cfg(feature = "json")]
+pub mod rest;
+mod signing;
+pub mod stable;
 
 use crate::convert::{ConvertRequest, ConvertRequestLayer, ConvertResponse, ConvertResponseLayer};
 use tower::Layer;
@@ -98,3 +149,19 @@ impl<S> Layer<S> for HttpConversionLayer {
         stack.layer(inner)
     }
 }
+
+/// Take the given service, which was driven to readiness, and leave a clone in its place.
+///
+/// Middlewares calling their inner service only after awaiting something else (e.g. charging
+/// cycles or obtaining a token) must move the inner service into the returned future. Since
+/// readiness is only guaranteed for the instance on which `poll_ready` was called, that instance
+/// must be the one that is moved, and not its clone.
+pub(crate) fn take_ready_service<S: Clone>(service: &mut S) -> S {
+    let clone = service.clone();
+    std::mem::replace(service, clone)
+}
+
+/// Encode the given bytes as a lowercase hexadecimal string.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,478 @@
+//! Middleware to authenticate requests with OAuth2 access tokens.
+//!
+//! An [`OAuth2TokenManager`] obtains access tokens from the token endpoint of an authorization
+//! server using the [client credentials grant](https://www.rfc-editor.org/rfc/rfc6749#section-4.4),
+//! caches the token together with its expiry, and refreshes it proactively shortly before it
+//! expires. Used as a [`Layer`] via [`OAuth2Layer`], it sets the `Authorization` header of every
+//! request to the current bearer token:
+//!
+//! ```text
+//!                 │                     ▲
+//!  http::Request  │                     │
+//!               ┌─┴─────────────────────┴───┐   token    ┌─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┐
+//!               │       OAuth2Service       ├───────────►│   TOKEN ENDPOINT CLIENT   │
+//!               └─┬─────────────────────┬───┘  request   └─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┘
+//!  http::Request  │                     │
+//!                 ▼                     │
+//!               ┌─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┐
+//!               │          SERVICE          │
+//!               └─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┘
+//! ```
+//!
+//! The cached token is shared by all clones of the manager and can be persisted across
+//! canister upgrades with [`OAuth2TokenManager::token`] and [`OAuth2TokenManager::set_token`].
+//!
+//! Note that the token endpoint typically returns a different token for each request, so that
+//! replicated HTTPs outcalls to the token endpoint would not reach consensus. Use a
+//! [non-replicated](ClientCredentials::replicated) outcall or an appropriate transform function.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::http::{
+//!     oauth2::{ClientCredentials, OAuth2Layer, OAuth2TokenManager},
+//!     HttpRequest, HttpResponse,
+//! };
+//! use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+//!
+//! async fn token_endpoint(_request: HttpRequest) -> Result<HttpResponse, BoxError> {
+//!     let body = r#"{"access_token": "secret", "token_type": "Bearer", "expires_in": 3600}"#;
+//!     Ok(http::Response::new(body.as_bytes().to_vec()))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let credentials = ClientCredentials::new(
+//!     "https://auth.example.com/oauth/token",
+//!     "client-id",
+//!     "client-secret",
+//! )
+//! .scope("read");
+//! let tokens = OAuth2TokenManager::new(credentials, tower::service_fn(token_endpoint));
+//! # let tokens = tokens.clock(|| 0);
+//!
+//! let mut service = ServiceBuilder::new()
+//!     .layer(OAuth2Layer::new(tokens.clone()))
+//!     .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+//!
+//! let request = http::Request::get("https://api.example.com").body(vec![])?;
+//! let request = service.ready().await?.call(request).await?;
+//!
+//! assert_eq!(request.headers()["authorization"], "Bearer secret");
+//! assert_eq!(tokens.token().unwrap().access_token, "secret");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Layer`]: tower::Layer
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    clock::Clock,
+    convert::Filter,
+    http::{take_ready_service, BearerAuth, HttpRequest, HttpResponse, QueryParams},
+    IsReplicatedRequestExtension, MaxResponseBytesRequestExtension,
+};
+use futures_util::{
+    future::{LocalBoxFuture, Shared},
+    FutureExt,
+};
+use http::{header::CONTENT_TYPE, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use thiserror::Error;
+use tower::{BoxError, Service, ServiceExt};
+use tower_layer::Layer;
+
+/// Default duration for which a token is considered valid when the token endpoint
+/// does not specify it.
+const DEFAULT_EXPIRES_IN: Duration = Duration::from_secs(3_600);
+
+/// Default duration before the expiry of a token at which it is refreshed.
+const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Credentials to obtain access tokens with the OAuth2 client credentials grant.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    max_response_bytes: Option<u64>,
+    is_replicated: Option<bool>,
+}
+
+impl ClientCredentials {
+    /// Create new credentials for the given token endpoint.
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: None,
+            max_response_bytes: None,
+            is_replicated: None,
+        }
+    }
+
+    /// Set the scope of the requested access tokens.
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Set the max response bytes of requests to the token endpoint,
+    /// see [`MaxResponseBytesRequestExtension`].
+    pub fn max_response_bytes(mut self, value: u64) -> Self {
+        self.max_response_bytes = Some(value);
+        self
+    }
+
+    /// Set the replication mode of requests to the token endpoint,
+    /// see [`IsReplicatedRequestExtension`].
+    pub fn replicated(mut self, value: bool) -> Self {
+        self.is_replicated = Some(value);
+        self
+    }
+
+    /// Returns the request to the token endpoint to obtain a new access token.
+    pub fn token_request(&self) -> Result<HttpRequest, http::Error> {
+        let mut form = QueryParams::new()
+            .with_param("grant_type", "client_credentials")
+            .with_param("client_id", &self.client_id)
+            .with_param("client_secret", &self.client_secret);
+        if let Some(scope) = &self.scope {
+            form.append("scope", scope);
+        }
+        let mut builder = http::Request::post(&self.token_url).header(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        if let Some(max_response_bytes) = self.max_response_bytes {
+            builder = builder.max_response_bytes(max_response_bytes);
+        }
+        if let Some(is_replicated) = self.is_replicated {
+            builder = builder.replicated(is_replicated);
+        }
+        builder.body(form.to_string().into_bytes())
+    }
+}
+
+impl fmt::Debug for ClientCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientCredentials")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"<redacted>")
+            .field("scope", &self.scope)
+            .field("max_response_bytes", &self.max_response_bytes)
+            .field("is_replicated", &self.is_replicated)
+            .finish()
+    }
+}
+
+/// An access token together with its expiry.
+///
+/// The token can be serialized to persist it across canister upgrades.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuth2Token {
+    /// The access token.
+    pub access_token: String,
+    /// Time at which the token expires, in nanoseconds since the UNIX epoch.
+    pub expires_at: u64,
+}
+
+impl fmt::Debug for OAuth2Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2Token")
+            .field("access_token", &"<redacted>")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// Successful response of the token endpoint,
+/// see [RFC 6749](https://www.rfc-editor.org/rfc/rfc6749#section-5.1).
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: String,
+    expires_in: Option<u64>,
+}
+
+/// Error returned when an access token cannot be obtained from the token endpoint.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum OAuth2Error {
+    /// The token endpoint returned a non-successful HTTP status code.
+    #[error("Token endpoint returned HTTP status {status}: {body}")]
+    TokenEndpoint {
+        /// The HTTP status code.
+        status: u16,
+        /// The response body, lossily decoded as UTF-8.
+        body: String,
+    },
+    /// The response of the token endpoint could not be parsed.
+    #[error("Invalid response from token endpoint: {0}")]
+    InvalidTokenResponse(String),
+    /// The token endpoint returned a token of another type than `Bearer`.
+    #[error("Unsupported token type `{0}`, expected `Bearer`")]
+    UnsupportedTokenType(String),
+    /// The request to the token endpoint could not be built.
+    #[error("Invalid token request: {0}")]
+    InvalidTokenRequest(String),
+}
+
+/// Request to the token endpoint shared by all concurrent refreshes of a token.
+type PendingRefresh = Shared<LocalBoxFuture<'static, Result<String, Arc<BoxError>>>>;
+
+/// Obtain, cache and refresh OAuth2 access tokens.
+///
+/// The given `client` is used to send requests to the token endpoint.
+/// All clones of a manager share the same cached token, and at most one request to the token
+/// endpoint is in flight at any time: concurrent refreshes wait for the pending one.
+///
+/// See the [module docs](crate::http::oauth2) for an example.
+#[derive(Clone)]
+pub struct OAuth2TokenManager<S> {
+    credentials: ClientCredentials,
+    client: S,
+    token: Rc<RefCell<Option<OAuth2Token>>>,
+    pending_refresh: Rc<RefCell<Option<PendingRefresh>>>,
+    refresh_margin: Duration,
+    clock: Clock,
+}
+
+impl<S> OAuth2TokenManager<S> {
+    /// Create a new [`OAuth2TokenManager`] without any cached token.
+    pub fn new(credentials: ClientCredentials, client: S) -> Self {
+        Self {
+            credentials,
+            client,
+            token: Rc::new(RefCell::new(None)),
+            pending_refresh: Rc::new(RefCell::new(None)),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            clock: Clock::default(),
+        }
+    }
+
+    /// Set how long before its expiry a token is refreshed.
+    ///
+    /// Defaults to one minute.
+    pub fn refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    /// Change the clock used to determine when tokens expire.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+
+    /// Returns the currently cached token, if any.
+    ///
+    /// Store the result in stable memory before upgrading the canister and restore it
+    /// with [`OAuth2TokenManager::set_token`] afterwards.
+    pub fn token(&self) -> Option<OAuth2Token> {
+        self.token.borrow().clone()
+    }
+
+    /// Replace the cached token, e.g. to restore a token persisted before an upgrade.
+    pub fn set_token(&self, token: Option<OAuth2Token>) {
+        *self.token.borrow_mut() = token;
+    }
+
+    fn valid_token(&self) -> Option<String> {
+        let now = self.clock.now();
+        let margin = u64::try_from(self.refresh_margin.as_nanos()).unwrap_or(u64::MAX);
+        self.token
+            .borrow()
+            .as_ref()
+            .filter(|token| now.saturating_add(margin) < token.expires_at)
+            .map(|token| token.access_token.clone())
+    }
+}
+
+impl<S> OAuth2TokenManager<S>
+where
+    S: Service<HttpRequest, Response = HttpResponse> + Clone + 'static,
+    S::Error: Into<BoxError>,
+{
+    /// Returns a valid access token, fetching a new one from the token endpoint if
+    /// no token is cached or if the cached token is about to expire.
+    ///
+    /// Errors from the client are returned as is, while errors from the
+    /// token endpoint are returned as [`OAuth2Error`].
+    pub async fn access_token(&self) -> Result<String, BoxError> {
+        match self.valid_token() {
+            Some(token) => Ok(token),
+            None => self.refresh().await,
+        }
+    }
+
+    /// Fetch a new access token from the token endpoint and cache it,
+    /// regardless of whether the cached token is still valid.
+    ///
+    /// If a token is already being fetched, e.g. by a concurrent call to
+    /// [`OAuth2TokenManager::access_token`], no new request is sent and the result of the
+    /// pending request is returned instead.
+    pub async fn refresh(&self) -> Result<String, BoxError> {
+        let pending = self.pending_refresh.borrow().clone();
+        let refresh = match pending {
+            Some(refresh) => refresh,
+            None => {
+                let refresh = self.fetch_token().boxed_local().shared();
+                *self.pending_refresh.borrow_mut() = Some(refresh.clone());
+                refresh
+            }
+        };
+        let result = refresh.clone().await;
+        let mut pending = self.pending_refresh.borrow_mut();
+        if pending
+            .as_ref()
+            .is_some_and(|pending| pending.ptr_eq(&refresh))
+        {
+            *pending = None;
+        }
+        result.map_err(into_box_error)
+    }
+
+    fn fetch_token(&self) -> impl Future<Output = Result<String, Arc<BoxError>>> + 'static {
+        let request = self.credentials.token_request();
+        let client = self.client.clone();
+        let token = self.token.clone();
+        let clock = self.clock.clone();
+        async move {
+            let request = request
+                .map_err(|e| BoxError::from(OAuth2Error::InvalidTokenRequest(e.to_string())))?;
+            let response = client.oneshot(request).await.map_err(Into::into)?;
+            let new_token = parse_token_response(response, clock.now()).map_err(BoxError::from)?;
+            let access_token = new_token.access_token.clone();
+            *token.borrow_mut() = Some(new_token);
+            Ok(access_token)
+        }
+        .map(|result: Result<String, BoxError>| result.map_err(Arc::new))
+    }
+}
+
+fn parse_token_response(response: HttpResponse, now: u64) -> Result<OAuth2Token, OAuth2Error> {
+    if !response.status().is_success() {
+        return Err(OAuth2Error::TokenEndpoint {
+            status: response.status().as_u16(),
+            body: String::from_utf8_lossy(response.body()).to_string(),
+        });
+    }
+    let response: TokenResponse = serde_json::from_slice(response.body())
+        .map_err(|e| OAuth2Error::InvalidTokenResponse(e.to_string()))?;
+    if !response.token_type.eq_ignore_ascii_case("bearer") {
+        return Err(OAuth2Error::UnsupportedTokenType(response.token_type));
+    }
+    let expires_in = response
+        .expires_in
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_EXPIRES_IN);
+    Ok(OAuth2Token {
+        access_token: response.access_token,
+        expires_at: now.saturating_add(u64::try_from(expires_in.as_nanos()).unwrap_or(u64::MAX)),
+    })
+}
+
+/// Converts the error of a shared refresh into the error returned to one of its callers.
+///
+/// Since the error is shared by all callers, it is cloned if it is an [`OAuth2Error`] and
+/// otherwise replaced by its message, except for the last caller.
+fn into_box_error(error: Arc<BoxError>) -> BoxError {
+    Arc::try_unwrap(error).unwrap_or_else(|error| match error.downcast_ref::<OAuth2Error>() {
+        Some(error) => error.clone().into(),
+        None => error.to_string().into(),
+    })
+}
+
+impl<S> fmt::Debug for OAuth2TokenManager<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2TokenManager")
+            .field("credentials", &self.credentials)
+            .field("token", &self.token.borrow())
+            .field("refresh_margin", &self.refresh_margin)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Middleware that sets the `Authorization` header of requests to an OAuth2 access token
+/// obtained from an [`OAuth2TokenManager`].
+///
+/// This [`Layer`] produces instances of the [`OAuth2Service`] service.
+///
+/// [`Layer`]: tower::Layer
+#[derive(Clone, Debug)]
+pub struct OAuth2Layer<S> {
+    tokens: OAuth2TokenManager<S>,
+}
+
+impl<S> OAuth2Layer<S> {
+    /// Returns a new [`OAuth2Layer`] using the given token manager.
+    pub fn new(tokens: OAuth2TokenManager<S>) -> Self {
+        Self { tokens }
+    }
+}
+
+impl<Inner, S: Clone> Layer<Inner> for OAuth2Layer<S> {
+    type Service = OAuth2Service<Inner, S>;
+
+    fn layer(&self, inner: Inner) -> Self::Service {
+        OAuth2Service {
+            inner,
+            tokens: self.tokens.clone(),
+        }
+    }
+}
+
+/// Obtain an access token before forwarding the request with the `Authorization` header set
+/// to the inner service.
+#[derive(Clone, Debug)]
+pub struct OAuth2Service<Inner, S> {
+    inner: Inner,
+    tokens: OAuth2TokenManager<S>,
+}
+
+impl<Inner, S, T> Service<http::Request<T>> for OAuth2Service<Inner, S>
+where
+    Inner: Service<http::Request<T>> + Clone + 'static,
+    Inner::Error: Into<BoxError>,
+    S: Service<HttpRequest, Response = HttpResponse> + Clone + 'static,
+    S::Error: Into<BoxError>,
+    T: 'static,
+{
+    type Response = Inner::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: http::Request<T>) -> Self::Future {
+        let mut inner = take_ready_service(&mut self.inner);
+        let tokens = self.tokens.clone();
+        Box::pin(async move {
+            let token = tokens.access_token().await?;
+            let request = BearerAuth::new(move || token.clone()).filter(request)?;
+            inner.call(request).await.map_err(Into::into)
+        })
+    }
+}
@@ -0,0 +1,275 @@
+use crate::http::{
+    oauth2::{ClientCredentials, OAuth2Error, OAuth2Layer, OAuth2Token, OAuth2TokenManager},
+    HttpRequest, HttpResponse,
+};
+use crate::{IsReplicatedRequestExtension, MaxResponseBytesRequestExtension};
+use assert_matches::assert_matches;
+use std::{cell::Cell, rc::Rc, time::Duration};
+use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+const TOKEN_URL: &str = "https://auth.example.com/oauth/token";
+const SECOND: u64 = 1_000_000_000;
+
+fn credentials() -> ClientCredentials {
+    ClientCredentials::new(TOKEN_URL, "my client", "s3cr&t")
+}
+
+/// Token endpoint issuing tokens `token-1`, `token-2`, ... valid for 100 seconds.
+#[derive(Clone, Default)]
+struct TokenEndpoint {
+    calls: Rc<Cell<u32>>,
+}
+
+impl TokenEndpoint {
+    fn calls(&self) -> u32 {
+        self.calls.get()
+    }
+}
+
+impl Service<HttpRequest> for TokenEndpoint {
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = std::future::Ready<Result<HttpResponse, BoxError>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _request: HttpRequest) -> Self::Future {
+        self.calls.set(self.calls.get() + 1);
+        let body = format!(
+            r#"{{"access_token": "token-{}", "token_type": "bearer", "expires_in": 100}}"#,
+            self.calls.get()
+        );
+        std::future::ready(Ok(http::Response::new(body.into_bytes())))
+    }
+}
+
+fn manager(endpoint: &TokenEndpoint, now: &Rc<Cell<u64>>) -> OAuth2TokenManager<TokenEndpoint> {
+    let now = now.clone();
+    OAuth2TokenManager::new(credentials(), endpoint.clone()).clock(move || now.get())
+}
+
+#[test]
+fn should_build_token_request() {
+    let request = credentials()
+        .scope("read write")
+        .max_response_bytes(1_000)
+        .replicated(false)
+        .token_request()
+        .unwrap();
+
+    assert_eq!(request.method(), http::Method::POST);
+    assert_eq!(request.uri(), TOKEN_URL);
+    assert_eq!(
+        request.headers()["content-type"],
+        "application/x-www-form-urlencoded"
+    );
+    assert_eq!(
+        request.body(),
+        b"grant_type=client_credentials&client_id=my%20client&client_secret=s3cr%26t&scope=read%20write"
+    );
+    assert_eq!(request.get_max_response_bytes(), Some(1_000));
+    assert_eq!(request.get_is_replicated(), Some(false));
+}
+
+#[test]
+fn should_not_debug_secrets() {
+    let debug = format!("{:?}", credentials());
+    assert!(!debug.contains("s3cr&t"));
+
+    let token = OAuth2Token {
+        access_token: "secret-token".to_string(),
+        expires_at: 0,
+    };
+    assert!(!format!("{token:?}").contains("secret-token"));
+}
+
+#[tokio::test]
+async fn should_cache_token_until_refresh_margin() {
+    let endpoint = TokenEndpoint::default();
+    let now = Rc::new(Cell::new(1_000 * SECOND));
+    let tokens = manager(&endpoint, &now).refresh_margin(Duration::from_secs(10));
+
+    assert_eq!(tokens.access_token().await.unwrap(), "token-1");
+    assert_eq!(
+        tokens.token(),
+        Some(OAuth2Token {
+            access_token: "token-1".to_string(),
+            expires_at: 1_100 * SECOND,
+        })
+    );
+
+    now.set(1_089 * SECOND);
+    assert_eq!(tokens.access_token().await.unwrap(), "token-1");
+    assert_eq!(endpoint.calls(), 1);
+
+    now.set(1_090 * SECOND);
+    assert_eq!(tokens.access_token().await.unwrap(), "token-2");
+    assert_eq!(endpoint.calls(), 2);
+}
+
+#[tokio::test]
+async fn should_restore_persisted_token() {
+    let endpoint = TokenEndpoint::default();
+    let now = Rc::new(Cell::new(0));
+    let persisted = {
+        let tokens = manager(&endpoint, &now);
+        tokens.access_token().await.unwrap();
+        serde_json::to_vec(&tokens.token()).unwrap()
+    };
+
+    let tokens = manager(&endpoint, &now);
+    tokens.set_token(serde_json::from_slice(&persisted).unwrap());
+
+    assert_eq!(tokens.access_token().await.unwrap(), "token-1");
+    assert_eq!(endpoint.calls(), 1);
+}
+
+#[tokio::test]
+async fn should_share_token_between_clones() {
+    let endpoint = TokenEndpoint::default();
+    let now = Rc::new(Cell::new(0));
+    let tokens = manager(&endpoint, &now);
+    let clone = tokens.clone();
+
+    assert_eq!(tokens.refresh().await.unwrap(), "token-1");
+    assert_eq!(clone.access_token().await.unwrap(), "token-1");
+    assert_eq!(endpoint.calls(), 1);
+}
+
+#[tokio::test]
+async fn should_fail_on_invalid_token_responses() {
+    for (status, body, expected_error) in [
+        (
+            http::StatusCode::UNAUTHORIZED,
+            r#"{"error": "invalid_client"}"#,
+            OAuth2Error::TokenEndpoint {
+                status: 401,
+                body: r#"{"error": "invalid_client"}"#.to_string(),
+            },
+        ),
+        (
+            http::StatusCode::OK,
+            r#"{"access_token": "token", "token_type": "mac"}"#,
+            OAuth2Error::UnsupportedTokenType("mac".to_string()),
+        ),
+    ] {
+        let endpoint = tower::service_fn(move |_request: HttpRequest| async move {
+            Ok::<_, BoxError>(
+                http::Response::builder()
+                    .status(status)
+                    .body(body.as_bytes().to_vec())
+                    .unwrap(),
+            )
+        });
+        let tokens = OAuth2TokenManager::new(credentials(), endpoint).clock(|| 0);
+
+        let error = tokens.access_token().await.unwrap_err();
+
+        assert_eq!(error.downcast_ref::<OAuth2Error>(), Some(&expected_error));
+        assert_eq!(tokens.token(), None);
+    }
+
+    let endpoint = tower::service_fn(|_request: HttpRequest| async move {
+        Ok::<_, BoxError>(http::Response::new(b"not json".to_vec()))
+    });
+    let tokens = OAuth2TokenManager::new(credentials(), endpoint).clock(|| 0);
+    let error = tokens.access_token().await.unwrap_err();
+    assert_matches!(
+        error.downcast_ref::<OAuth2Error>(),
+        Some(OAuth2Error::InvalidTokenResponse(_))
+    );
+}
+
+#[tokio::test]
+async fn should_send_single_token_request_for_concurrent_refreshes() {
+    let calls = Rc::new(Cell::new(0));
+    let status = Rc::new(Cell::new(http::StatusCode::OK));
+    let endpoint = tower::service_fn({
+        let calls = calls.clone();
+        let status = status.clone();
+        move |_request: HttpRequest| {
+            calls.set(calls.get() + 1);
+            let call = calls.get();
+            let status = status.get();
+            async move {
+                tokio::task::yield_now().await;
+                let body = format!(r#"{{"access_token": "token-{call}", "token_type": "bearer"}}"#);
+                let mut response = http::Response::new(body.into_bytes());
+                *response.status_mut() = status;
+                Ok::<_, BoxError>(response)
+            }
+        }
+    });
+    let tokens = OAuth2TokenManager::new(credentials(), endpoint).clock(|| 0);
+    let clone = tokens.clone();
+
+    let (first, second) = futures_util::join!(tokens.access_token(), clone.refresh());
+
+    assert_eq!(calls.get(), 1);
+    assert_eq!(first.unwrap(), "token-1");
+    assert_eq!(second.unwrap(), "token-1");
+
+    status.set(http::StatusCode::UNAUTHORIZED);
+    let (first, second) = futures_util::join!(tokens.refresh(), tokens.refresh());
+
+    assert_eq!(calls.get(), 2);
+    for result in [first, second] {
+        assert_matches!(
+            result.unwrap_err().downcast_ref::<OAuth2Error>(),
+            Some(OAuth2Error::TokenEndpoint { status: 401, .. })
+        );
+    }
+    assert_eq!(tokens.token().unwrap().access_token, "token-1");
+}
+
+#[tokio::test]
+async fn should_set_authorization_header_with_layer() {
+    let endpoint = TokenEndpoint::default();
+    let now = Rc::new(Cell::new(0));
+    let mut service = ServiceBuilder::new()
+        .layer(OAuth2Layer::new(manager(&endpoint, &now)))
+        .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+
+    for _ in 0..2 {
+        let request = http::Request::get("https://api.example.com")
+            .header("Authorization", "Bearer old")
+            .body(vec![])
+            .unwrap();
+        let request = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(request.headers()["authorization"], "Bearer token-1");
+        assert!(request.headers()["authorization"].is_sensitive());
+    }
+    assert_eq!(endpoint.calls(), 1);
+}
+
+#[tokio::test]
+async fn should_not_call_inner_service_when_token_cannot_be_obtained() {
+    let endpoint = tower::service_fn(|_request: HttpRequest| async move {
+        Err::<HttpResponse, BoxError>("connection refused".into())
+    });
+    let tokens = OAuth2TokenManager::new(credentials(), endpoint).clock(|| 0);
+    let mut service = ServiceBuilder::new()
+        .layer(OAuth2Layer::new(tokens))
+        .service_fn(|_request: HttpRequest| async move {
+            Err::<(), BoxError>("inner service must not be called".into())
+        });
+
+    let request = http::Request::get("https://api.example.com")
+        .body(vec![])
+        .unwrap();
+    let error = service
+        .ready()
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.to_string(), "connection refused");
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+
+/// Query parameters of a URL.
+///
+/// Keys and values are percent-encoded when the query string is built, so that they may contain
+/// arbitrary characters. Keys may be repeated, in which case all values are kept in insertion
+/// order. Values can be of any type implementing [`fmt::Display`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::QueryParams;
+///
+/// let params = QueryParams::new()
+///     .with_param("symbol", "ICP/USDT")
+///     .with_param("limit", 10)
+///     .with_param("interval", "1m")
+///     .with_param("interval", "5m");
+///
+/// assert_eq!(
+///     params.to_string(),
+///     "symbol=ICP%2FUSDT&limit=10&interval=1m&interval=5m"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct QueryParams(Vec<(String, String)>);
+
+impl QueryParams {
+    /// Create new empty query parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a parameter, keeping any existing parameter with the same key.
+    pub fn append(&mut self, key: impl Into<String>, value: impl fmt::Display) {
+        self.0.push((key.into(), value.to_string()));
+    }
+
+    /// Convenience method to use the builder pattern.
+    pub fn with_param(mut self, key: impl Into<String>, value: impl fmt::Display) -> Self {
+        self.append(key, value);
+        self
+    }
+
+    /// Returns the number of parameters.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the (non-encoded) parameters in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Returns the values of all parameters with the given key.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.iter()
+            .filter(move |(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+}
+
+impl fmt::Display for QueryParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (key, value)) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str("&")?;
+            }
+            write!(f, "{}={}", PercentEncoded(key), PercentEncoded(value))?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Into<String>, V: fmt::Display> FromIterator<(K, V)> for QueryParams {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut params = Self::new();
+        params.extend(iter);
+        params
+    }
+}
+
+impl<K: Into<String>, V: fmt::Display> Extend<(K, V)> for QueryParams {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.append(key, value);
+        }
+    }
+}
+
+impl<K: Into<String> + Clone, V: fmt::Display> From<&[(K, V)]> for QueryParams {
+    fn from(params: &[(K, V)]) -> Self {
+        params
+            .iter()
+            .map(|(key, value)| (key.clone(), value))
+            .collect()
+    }
+}
+
+impl<K: Into<String> + Clone, V: fmt::Display, const N: usize> From<&[(K, V); N]> for QueryParams {
+    fn from(params: &[(K, V); N]) -> Self {
+        Self::from(params.as_slice())
+    }
+}
+
+/// Percent-encode every byte except unreserved characters, as defined in
+/// [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986#section-2.3).
+pub(crate) struct PercentEncoded<'a>(pub(crate) &'a str);
+
+impl fmt::Display for PercentEncoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                write!(f, "{}", byte as char)?;
+            } else {
+                write!(f, "%{byte:02X}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Add query parameters to the URL of a request.
+pub trait QueryRequestExtension: Sized {
+    /// Append the given parameters to the query of the request URL.
+    ///
+    /// Parameters already present in the URL are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::http::QueryRequestExtension;
+    ///
+    /// let request = http::Request::get("https://api.binance.com/api/v3/ticker/price")
+    ///     .query(&[("symbol", "ICPUSDT")])
+    ///     .body(())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     request.uri(),
+    ///     "https://api.binance.com/api/v3/ticker/price?symbol=ICPUSDT"
+    /// );
+    /// ```
+    fn query(self, params: impl Into<QueryParams>) -> Self;
+}
+
+impl QueryRequestExtension for http::request::Builder {
+    fn query(self, params: impl Into<QueryParams>) -> Self {
+        let params = params.into();
+        let Some(uri) = self.uri_ref() else {
+            // The builder already contains an error, which will be returned when building the request.
+            return self;
+        };
+        if params.is_empty() {
+            return self;
+        }
+        let uri = append_query(uri, &params);
+        self.uri(uri)
+    }
+}
+
+pub(crate) fn append_query(uri: &http::Uri, params: &QueryParams) -> String {
+    let mut uri = uri.to_string();
+    if !uri.ends_with('?') {
+        uri.push(if uri.contains('?') { '&' } else { '?' });
+    }
+    uri.push_str(&params.to_string());
+    uri
+}
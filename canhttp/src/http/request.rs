@@ -1,13 +1,15 @@
-use crate::convert::Convert;
+use crate::convert::{Convert, ConvertRequest, ConvertRequestLayer, Filter};
 use crate::{
-    IsReplicatedRequestExtension, MaxResponseBytesRequestExtension,
-    TransformContextRequestExtension,
+    CancellationRequestExtension, CancellationToken, IsReplicatedRequestExtension,
+    MaxResponseBytesRequestExtension, TransformContextRequestExtension,
 };
 use ic_cdk_management_canister::{
     HttpHeader as IcHttpHeader, HttpMethod as IcHttpMethod, HttpRequestArgs as IcHttpRequest,
     TransformContext,
 };
+use std::collections::BTreeSet;
 use thiserror::Error;
+use tower_layer::Layer;
 
 /// HTTP request with a body made of bytes.
 pub type HttpRequest = http::Request<Vec<u8>>;
@@ -102,9 +104,17 @@ impl IsReplicatedRequestExtension for http::request::Builder {
 /// Error return when converting requests with [`HttpRequestConverter`].
 #[derive(Error, Clone, Debug, Eq, PartialEq)]
 pub enum HttpRequestConversionError {
-    /// HTTP method is not supported
-    #[error("HTTP method `{0}` is not supported")]
-    UnsupportedHttpMethod(String),
+    /// HTTP method is not supported by HTTPs outcalls.
+    #[error(
+        "HTTP method `{method}` is not supported, expected one of: {}",
+        supported.iter().map(http::Method::as_str).collect::<Vec<_>>().join(", ")
+    )]
+    UnsupportedHttpMethod {
+        /// The unsupported HTTP method.
+        method: http::Method,
+        /// HTTP methods supported by HTTPs outcalls.
+        supported: Vec<http::Method>,
+    },
     /// Header name is invalid.
     #[error("HTTP header `{name}` has an invalid value: {reason}")]
     InvalidHttpHeaderValue {
@@ -119,6 +129,12 @@ pub enum HttpRequestConversionError {
 #[derive(Clone, Debug)]
 pub struct HttpRequestConverter;
 
+impl HttpRequestConverter {
+    /// HTTP methods supported by HTTPs outcalls.
+    pub const SUPPORTED_HTTP_METHODS: [http::Method; 3] =
+        [http::Method::GET, http::Method::HEAD, http::Method::POST];
+}
+
 impl Convert<HttpRequest> for HttpRequestConverter {
     type Output = IcHttpRequest;
     type Error = HttpRequestConversionError;
@@ -126,14 +142,15 @@ impl Convert<HttpRequest> for HttpRequestConverter {
     fn try_convert(&mut self, request: HttpRequest) -> Result<Self::Output, Self::Error> {
         let url = request.uri().to_string();
         let max_response_bytes = request.get_max_response_bytes();
-        let method = match request.method().as_str() {
-            "GET" => IcHttpMethod::GET,
-            "POST" => IcHttpMethod::POST,
-            "HEAD" => IcHttpMethod::HEAD,
-            unsupported => {
-                return Err(HttpRequestConversionError::UnsupportedHttpMethod(
-                    unsupported.to_string(),
-                ))
+        let method = match *request.method() {
+            http::Method::GET => IcHttpMethod::GET,
+            http::Method::POST => IcHttpMethod::POST,
+            http::Method::HEAD => IcHttpMethod::HEAD,
+            ref unsupported => {
+                return Err(HttpRequestConversionError::UnsupportedHttpMethod {
+                    method: unsupported.clone(),
+                    supported: Self::SUPPORTED_HTTP_METHODS.to_vec(),
+                })
             }
         };
         let headers = request
@@ -164,3 +181,231 @@ impl Convert<HttpRequest> for HttpRequestConverter {
         })
     }
 }
+
+/// Policy that request URLs must satisfy, see [`UrlPolicyLayer`].
+///
+/// By default, only `https` URLs of at most 8192 bytes are accepted, for any host.
+///
+/// Hosts are compared case-insensitively. A host pattern starting with `*.` matches any
+/// subdomain of the given domain (but not the domain itself), e.g. `*.example.com` matches
+/// `api.example.com` but not `example.com`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UrlPolicy {
+    require_https: bool,
+    max_url_bytes: usize,
+    allowed_hosts: Option<BTreeSet<String>>,
+    denied_hosts: BTreeSet<String>,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> Self {
+        Self {
+            require_https: true,
+            max_url_bytes: 8 * 1024,
+            allowed_hosts: None,
+            denied_hosts: BTreeSet::new(),
+        }
+    }
+}
+
+impl UrlPolicy {
+    /// Create a new [`UrlPolicy`] with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether the `https` scheme is required.
+    pub fn require_https(mut self, require_https: bool) -> Self {
+        self.require_https = require_https;
+        self
+    }
+
+    /// Set the maximum length in bytes of the URL.
+    pub fn max_url_bytes(mut self, max_url_bytes: usize) -> Self {
+        self.max_url_bytes = max_url_bytes;
+        self
+    }
+
+    /// Only accept URLs whose host matches one of the given patterns.
+    pub fn allow_hosts(mut self, hosts: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.allowed_hosts
+            .get_or_insert_with(BTreeSet::new)
+            .extend(hosts.into_iter().map(|host| normalize_host(&host.into())));
+        self
+    }
+
+    /// Reject URLs whose host matches one of the given patterns.
+    ///
+    /// Takes precedence over [`UrlPolicy::allow_hosts`].
+    pub fn deny_hosts(mut self, hosts: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.denied_hosts
+            .extend(hosts.into_iter().map(|host| normalize_host(&host.into())));
+        self
+    }
+
+    /// Check that the given URL satisfies the policy.
+    pub fn check(&self, uri: &http::Uri) -> Result<(), UrlPolicyError> {
+        let url = uri.to_string();
+        if url.len() > self.max_url_bytes {
+            return Err(UrlPolicyError::UrlTooLong {
+                length: url.len(),
+                limit: self.max_url_bytes,
+            });
+        }
+        if self.require_https && uri.scheme() != Some(&http::uri::Scheme::HTTPS) {
+            return Err(UrlPolicyError::HttpsRequired { url });
+        }
+        // A fully-qualified domain name such as `example.com.` designates the same host as
+        // `example.com` and must not bypass the allow- or deny-list.
+        let host = uri
+            .host()
+            .map(normalize_host)
+            .ok_or_else(|| UrlPolicyError::MissingHost { url: url.clone() })?;
+        let matches_any = |patterns: &BTreeSet<String>| {
+            patterns.iter().any(|pattern| host_matches(pattern, &host))
+        };
+        if matches_any(&self.denied_hosts)
+            || self
+                .allowed_hosts
+                .as_ref()
+                .is_some_and(|allowed| !matches_any(allowed))
+        {
+            return Err(UrlPolicyError::HostNotAllowed { host });
+        }
+        Ok(())
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    let host = host.to_ascii_lowercase();
+    match host.strip_suffix('.') {
+        Some(trimmed) => trimmed.to_string(),
+        None => host,
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+impl<T> Filter<http::Request<T>> for UrlPolicy {
+    type Error = UrlPolicyError;
+
+    fn filter(&mut self, request: http::Request<T>) -> Result<http::Request<T>, Self::Error> {
+        self.check(request.uri())?;
+        Ok(request)
+    }
+}
+
+/// Error returned when a request URL does not satisfy a [`UrlPolicy`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum UrlPolicyError {
+    /// The URL does not use the `https` scheme.
+    #[error("URL `{url}` does not use the https scheme")]
+    HttpsRequired {
+        /// The rejected URL.
+        url: String,
+    },
+    /// The URL is too long.
+    #[error("URL length of {length} bytes exceeds limit of {limit} bytes")]
+    UrlTooLong {
+        /// Length of the URL in bytes.
+        length: usize,
+        /// Maximum length in bytes.
+        limit: usize,
+    },
+    /// The URL has no host.
+    #[error("URL `{url}` has no host")]
+    MissingHost {
+        /// The rejected URL.
+        url: String,
+    },
+    /// The host of the URL is denied or not allowed.
+    #[error("Host `{host}` is not allowed")]
+    HostNotAllowed {
+        /// The rejected host.
+        host: String,
+    },
+}
+
+/// Middleware that rejects requests whose URL does not satisfy a [`UrlPolicy`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{HttpRequest, UrlPolicy, UrlPolicyError, UrlPolicyLayer};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(UrlPolicyLayer::new(
+///         UrlPolicy::new().allow_hosts(["*.infura.io", "cloudflare-eth.com"]),
+///     ))
+///     .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+///
+/// let request = http::Request::get("https://mainnet.infura.io/v3").body(vec![])?;
+/// assert!(service.ready().await?.call(request).await.is_ok());
+///
+/// let request = http::Request::get("http://cloudflare-eth.com").body(vec![])?;
+/// let error = service.ready().await?.call(request).await.unwrap_err();
+/// assert!(matches!(
+///     error.downcast_ref::<UrlPolicyError>(),
+///     Some(UrlPolicyError::HttpsRequired { .. })
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UrlPolicyLayer {
+    policy: UrlPolicy,
+}
+
+impl UrlPolicyLayer {
+    /// Returns a new [`UrlPolicyLayer`] enforcing the given policy.
+    pub fn new(policy: UrlPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> Layer<S> for UrlPolicyLayer {
+    type Service = ConvertRequest<S, UrlPolicy>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.policy.clone()).layer(inner)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CancellationTokenExtension(CancellationToken);
+
+impl<T> CancellationRequestExtension for http::Request<T> {
+    fn set_cancellation_token(&mut self, value: CancellationToken) {
+        let extensions = self.extensions_mut();
+        extensions.insert(CancellationTokenExtension(value));
+    }
+
+    fn get_cancellation_token(&self) -> Option<&CancellationToken> {
+        self.extensions()
+            .get::<CancellationTokenExtension>()
+            .map(|e| &e.0)
+    }
+}
+
+impl CancellationRequestExtension for http::request::Builder {
+    fn set_cancellation_token(&mut self, value: CancellationToken) {
+        if let Some(extensions) = self.extensions_mut() {
+            extensions.insert(CancellationTokenExtension(value));
+        }
+    }
+
+    fn get_cancellation_token(&self) -> Option<&CancellationToken> {
+        self.extensions_ref()
+            .and_then(|extensions| extensions.get::<CancellationTokenExtension>().map(|e| &e.0))
+    }
+}
@@ -1,6 +1,11 @@
-use crate::convert::{Convert, Filter};
+use crate::{
+    convert::{Convert, ConvertResponse, ConvertResponseLayer, Filter},
+    HttpsOutcallError,
+};
 use ic_cdk_management_canister::HttpRequestResult as IcHttpResponse;
+use std::collections::BTreeSet;
 use thiserror::Error;
+use tower_layer::Layer;
 
 /// HTTP response with a body made of bytes.
 pub type HttpResponse = http::Response<Vec<u8>>;
@@ -102,3 +107,376 @@ impl<T> Filter<http::Response<T>> for FilterNonSuccessfulHttpResponse {
         Ok(response)
     }
 }
+
+/// Error returned when converting responses with [`CharsetConverter`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum CharsetConversionError {
+    /// The charset specified in the `Content-Type` header is not supported.
+    #[error("Unsupported charset `{charset}`")]
+    UnsupportedCharset {
+        /// Charset specified in the `Content-Type` header.
+        charset: String,
+    },
+    /// The response body is not valid for the specified charset.
+    #[error("Response body is not valid `{charset}`: {reason}")]
+    InvalidBody {
+        /// Charset specified in the `Content-Type` header.
+        charset: String,
+        /// Reason for the body being invalid.
+        reason: String,
+    },
+}
+
+/// Transcode the body of responses of type [`HttpResponse`] to UTF-8, according to the charset
+/// specified in the `Content-Type` header.
+///
+/// Responses without a charset are left unchanged, so that parsing them, e.g. with
+/// [`JsonResponseConverter`], still assumes UTF-8. Otherwise, the body is transcoded to UTF-8
+/// and the charset in the `Content-Type` header is updated accordingly.
+///
+/// The following charsets are supported (case-insensitive):
+/// * `utf-8`, `us-ascii`;
+/// * `iso-8859-1` (also known as `latin1`);
+/// * `utf-16`, `utf-16le` and `utf-16be` (`utf-16` defaults to big endian without a byte order
+///   mark).
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{convert::Convert, http::CharsetConverter};
+///
+/// let response = http::Response::builder()
+///     .header("Content-Type", "text/plain; charset=ISO-8859-1")
+///     .body(vec![b'c', b'a', b'f', 0xe9])
+///     .unwrap();
+///
+/// let response = CharsetConverter.try_convert(response).unwrap();
+///
+/// assert_eq!(response.body(), "café".as_bytes());
+/// assert_eq!(response.headers()["Content-Type"], "text/plain; charset=utf-8");
+/// ```
+///
+/// [`JsonResponseConverter`]: crate::http::json::JsonResponseConverter
+#[derive(Clone, Debug)]
+pub struct CharsetConverter;
+
+impl Convert<HttpResponse> for CharsetConverter {
+    type Output = HttpResponse;
+    type Error = CharsetConversionError;
+
+    fn try_convert(&mut self, response: HttpResponse) -> Result<Self::Output, Self::Error> {
+        let Some((charset, utf8_content_type)) = response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(replace_charset_with_utf8)
+        else {
+            return Ok(response);
+        };
+        let (mut parts, body) = response.into_parts();
+        let body = transcode_to_utf8(&charset, body)?;
+        parts.headers.insert(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::try_from(utf8_content_type)
+                .expect("BUG: header value was already valid"),
+        );
+        Ok(http::Response::from_parts(parts, body))
+    }
+}
+
+/// Returns the (lower-cased) charset of the given `Content-Type` header value, if any,
+/// together with the header value where the charset is replaced by `utf-8`.
+fn replace_charset_with_utf8(content_type: &str) -> Option<(String, String)> {
+    const CHARSET_PARAM: &str = "charset=";
+    let mut params: Vec<&str> = content_type.split(';').map(str::trim).collect();
+    let charset_index = params.iter().position(|param| {
+        param
+            .get(..CHARSET_PARAM.len())
+            .is_some_and(|name| name.eq_ignore_ascii_case(CHARSET_PARAM))
+    })?;
+    let charset = params[charset_index][CHARSET_PARAM.len()..]
+        .trim_matches('"')
+        .to_ascii_lowercase();
+    params[charset_index] = "charset=utf-8";
+    Some((charset, params.join("; ")))
+}
+
+fn transcode_to_utf8(charset: &str, body: Vec<u8>) -> Result<Vec<u8>, CharsetConversionError> {
+    let invalid_body = |reason: String| CharsetConversionError::InvalidBody {
+        charset: charset.to_string(),
+        reason,
+    };
+    match charset {
+        "utf-8" | "utf8" => String::from_utf8(body)
+            .map(String::into_bytes)
+            .map_err(|e| invalid_body(e.to_string())),
+        "us-ascii" | "ascii" => match body.iter().position(|b| !b.is_ascii()) {
+            Some(index) => Err(invalid_body(format!("non-ASCII byte at index {index}"))),
+            None => Ok(body),
+        },
+        "iso-8859-1" | "latin1" | "l1" => Ok(body
+            .into_iter()
+            .map(char::from)
+            .collect::<String>()
+            .into_bytes()),
+        "utf-16" | "utf-16be" | "utf-16le" => {
+            if !body.len().is_multiple_of(2) {
+                return Err(invalid_body("odd number of bytes".to_string()));
+            }
+            let (little_endian, units) = match (charset, body.get(..2)) {
+                ("utf-16", Some([0xff, 0xfe])) => (true, &body[2..]),
+                ("utf-16", Some([0xfe, 0xff])) => (false, &body[2..]),
+                (charset, _) => (charset == "utf-16le", &body[..]),
+            };
+            let units = units.chunks_exact(2).map(|unit| {
+                if little_endian {
+                    u16::from_le_bytes([unit[0], unit[1]])
+                } else {
+                    u16::from_be_bytes([unit[0], unit[1]])
+                }
+            });
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map(String::into_bytes)
+                .map_err(|e| invalid_body(e.to_string()))
+        }
+        _ => Err(CharsetConversionError::UnsupportedCharset {
+            charset: charset.to_string(),
+        }),
+    }
+}
+
+/// Error returned when filtering responses with [`FilterContentType`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+#[error(
+    "Expected response with content type `{expected}`, but got {actual:?} (status {status}): {body_preview}"
+)]
+pub struct UnexpectedContentTypeError {
+    /// Response status code.
+    pub status: u16,
+    /// Expected media type.
+    pub expected: String,
+    /// Value of the `Content-Type` header of the response, if any.
+    pub actual: Option<String>,
+    /// Beginning of the response body, lossily decoded as UTF-8.
+    pub body_preview: String,
+}
+
+/// Filter out responses whose `Content-Type` header does not match the expected media type.
+///
+/// Parameters of the media type, such as `charset`, as well as the case are ignored when
+/// comparing media types. This is typically useful to reject HTML error pages returned by
+/// load balancers before trying to parse them as JSON.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{convert::Filter, http::FilterContentType};
+///
+/// let mut filter = FilterContentType::json();
+///
+/// let json_response = http::Response::builder()
+///     .header("Content-Type", "application/json; charset=utf-8")
+///     .body(b"{}".to_vec())
+///     .unwrap();
+/// assert!(filter.filter(json_response).is_ok());
+///
+/// let html_response = http::Response::builder()
+///     .status(502)
+///     .header("Content-Type", "text/html")
+///     .body(b"<html>Bad Gateway</html>".to_vec())
+///     .unwrap();
+/// let error = filter.filter(html_response).unwrap_err();
+/// assert_eq!(error.actual.as_deref(), Some("text/html"));
+/// assert_eq!(error.body_preview, "<html>Bad Gateway</html>");
+/// ```
+#[derive(Clone, Debug)]
+pub struct FilterContentType {
+    expected: String,
+}
+
+impl FilterContentType {
+    /// Maximum number of bytes of the response body included in an
+    /// [`UnexpectedContentTypeError`].
+    pub const MAX_BODY_PREVIEW_BYTES: usize = 256;
+
+    /// Create a new [`FilterContentType`] only accepting responses with the given media type,
+    /// e.g. `application/json`.
+    pub fn new(expected_media_type: impl Into<String>) -> Self {
+        Self {
+            expected: expected_media_type.into(),
+        }
+    }
+
+    /// Create a new [`FilterContentType`] only accepting `application/json` responses.
+    pub fn json() -> Self {
+        Self::new("application/json")
+    }
+}
+
+impl Filter<HttpResponse> for FilterContentType {
+    type Error = UnexpectedContentTypeError;
+
+    fn filter(&mut self, response: HttpResponse) -> Result<HttpResponse, Self::Error> {
+        let actual = response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).to_string());
+        let media_type_matches = actual.as_deref().is_some_and(|content_type| {
+            let media_type = content_type.split(';').next().unwrap_or_default().trim();
+            media_type.eq_ignore_ascii_case(self.expected.trim())
+        });
+        if media_type_matches {
+            return Ok(response);
+        }
+        let body = response.body();
+        let preview_len = body.len().min(Self::MAX_BODY_PREVIEW_BYTES);
+        Err(UnexpectedContentTypeError {
+            status: response.status().as_u16(),
+            expected: self.expected.clone(),
+            actual,
+            body_preview: String::from_utf8_lossy(&body[..preview_len]).to_string(),
+        })
+    }
+}
+
+/// Error returned by [`FilterStatusCode`] for responses with a non-successful status code.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+#[error("HTTP response has non-successful status {status}")]
+pub struct HttpStatusError<T> {
+    /// Response status code.
+    pub status: http::StatusCode,
+    /// Response headers.
+    pub headers: http::HeaderMap,
+    /// Response body.
+    pub body: T,
+}
+
+impl<T> HttpsOutcallError for HttpStatusError<T> {
+    fn is_response_too_large(&self) -> bool {
+        false
+    }
+
+    fn is_transient(&self) -> bool {
+        self.is_rate_limited()
+            || self.is_timeout()
+            || matches!(
+                self.status,
+                http::StatusCode::TOO_EARLY
+                    | http::StatusCode::INTERNAL_SERVER_ERROR
+                    | http::StatusCode::BAD_GATEWAY
+                    | http::StatusCode::SERVICE_UNAVAILABLE
+            )
+    }
+
+    fn is_rate_limited(&self) -> bool {
+        self.status == http::StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn is_timeout(&self) -> bool {
+        matches!(
+            self.status,
+            http::StatusCode::REQUEST_TIMEOUT | http::StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+}
+
+/// Turn responses with a non-successful (i.e., not 2xx) status code into an [`HttpStatusError`],
+/// unless the status code was explicitly allowed.
+///
+/// Unlike [`FilterNonSuccessfulHttpResponse`], the error only retains the parts of the response
+/// relevant to handle it, so that it can be matched on without the full [`http::Response`].
+#[derive(Clone, Debug, Default)]
+pub struct FilterStatusCode {
+    allowed: BTreeSet<http::StatusCode>,
+}
+
+impl FilterStatusCode {
+    /// Create a new [`FilterStatusCode`] rejecting all non-successful responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let responses with the given status code through, e.g. [`http::StatusCode::NOT_FOUND`].
+    pub fn allow(mut self, status: http::StatusCode) -> Self {
+        self.allowed.insert(status);
+        self
+    }
+}
+
+impl<T> Filter<http::Response<T>> for FilterStatusCode {
+    type Error = HttpStatusError<T>;
+
+    fn filter(&mut self, response: http::Response<T>) -> Result<http::Response<T>, Self::Error> {
+        if response.status().is_success() || self.allowed.contains(&response.status()) {
+            return Ok(response);
+        }
+        let (parts, body) = response.into_parts();
+        Err(HttpStatusError {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        })
+    }
+}
+
+/// Middleware applying a [`FilterStatusCode`] to the responses of a [`Service`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::http::{HttpResponse, HttpStatusError, StatusCodeFilterLayer};
+/// use http::StatusCode;
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let mut service = ServiceBuilder::new()
+///     .layer(StatusCodeFilterLayer::new().allow(StatusCode::NOT_FOUND))
+///     .service_fn(|status: u16| async move {
+///         Ok::<_, BoxError>(
+///             http::Response::builder()
+///                 .status(status)
+///                 .body(b"body".to_vec())
+///                 .unwrap(),
+///         )
+///     });
+///
+/// let not_found: HttpResponse = service.ready().await?.call(404).await?;
+/// assert_eq!(not_found.status(), StatusCode::NOT_FOUND);
+///
+/// let error = service.ready().await?.call(503).await.unwrap_err();
+/// let error = error.downcast_ref::<HttpStatusError<Vec<u8>>>().unwrap();
+/// assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+/// assert_eq!(error.body, b"body");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Service`]: tower::Service
+#[derive(Clone, Debug, Default)]
+pub struct StatusCodeFilterLayer {
+    filter: FilterStatusCode,
+}
+
+impl StatusCodeFilterLayer {
+    /// Returns a new [`StatusCodeFilterLayer`] rejecting all non-successful responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let responses with the given status code through, see [`FilterStatusCode::allow`].
+    pub fn allow(self, status: http::StatusCode) -> Self {
+        Self {
+            filter: self.filter.allow(status),
+        }
+    }
+}
+
+impl<S> Layer<S> for StatusCodeFilterLayer {
+    type Service = ConvertResponse<S, FilterStatusCode>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertResponseLayer::new(self.filter.clone()).layer(inner)
+    }
+}
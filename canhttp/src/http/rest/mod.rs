@@ -0,0 +1,417 @@
+//! Middleware to call REST endpoints with typed responses.
+//!
+//! A [`RestEndpoint`] describes an endpoint of a REST API by its HTTP method, base URL,
+//! [path template](PathTemplate) (e.g. `/v1/accounts/{id}/txs`), and the type of the JSON
+//! response. Used as a [`Layer`], it turns a service handling [`HttpRequest`]s into a service
+//! handling [`RestRequest`]s, which only need to specify the path and query parameters:
+//!
+//! ```text
+//!                 │                     ▲
+//!      RestRequest│                     │http::Response<T>
+//!               ┌─┴─────────────────────┴───┐
+//!               │   JsonResponseConverter   │
+//!               └─┬─────────────────────▲───┘
+//!                 │                     │
+//!               ┌─▼─────────────────────┴───┐
+//!               │       RestEndpoint        │
+//!               └─┬─────────────────────┬───┘
+//!      HttpRequest│                     │HttpResponse
+//!                 ▼                     │
+//!               ┌─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┐
+//!               │          SERVICE          │
+//!               └─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┘
+//! ```
+//!
+//! Path parameters are validated against the template and percent-encoded,
+//! as are query parameters. Path parameters equal to `.` or `..` are rejected, since they would
+//! otherwise be interpreted as dot-segments and could change the path of the request.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::http::{HttpRequest, HttpResponse, rest::{RestEndpoint, RestRequest}};
+//! use serde::Deserialize;
+//! use tower::{Service, ServiceBuilder, ServiceExt, BoxError};
+//!
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! struct Transaction {
+//!     hash: String,
+//! }
+//!
+//! async fn echo_url(request: HttpRequest) -> Result<HttpResponse, BoxError> {
+//!     let body = format!(r#"[{{"hash": "{}"}}]"#, request.uri());
+//!     Ok(http::Response::new(body.into_bytes()))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let endpoint = RestEndpoint::<Vec<Transaction>>::get(
+//!     "https://api.example.com",
+//!     "/v1/accounts/{id}/txs",
+//! )?;
+//!
+//! let mut service = ServiceBuilder::new()
+//!     .layer(endpoint)
+//!     .service_fn(echo_url);
+//!
+//! let request = RestRequest::new()
+//!     .path_param("id", "alice & bob")
+//!     .query_param("limit", 10);
+//!
+//! let response = service.ready().await?.call(request).await?;
+//!
+//! assert_eq!(
+//!     response.into_body(),
+//!     vec![Transaction {
+//!         hash: "https://api.example.com/v1/accounts/alice%20%26%20bob/txs?limit=10".to_string()
+//!     }]
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Layer`]: tower::Layer
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    convert::{
+        Convert, ConvertRequest, ConvertRequestLayer, ConvertResponse, ConvertResponseLayer,
+    },
+    http::{json::JsonResponseConverter, query::PercentEncoded, HttpRequest, QueryParams},
+    MaxResponseBytesRequestExtension,
+};
+use http::{header::CONTENT_TYPE, HeaderValue, Method};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::BTreeMap, fmt::Write, marker::PhantomData, str::FromStr};
+use thiserror::Error;
+use tower_layer::Layer;
+
+/// Template of a URL path containing named parameters enclosed in braces,
+/// e.g. `/v1/accounts/{id}/txs`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PathTemplate {
+    segments: Vec<PathSegment>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum PathSegment {
+    Literal(String),
+    Param(String),
+}
+
+/// Error returned when parsing a [`PathTemplate`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum PathTemplateError {
+    /// The template does not start with `/`.
+    #[error("Path template `{0}` must start with `/`")]
+    MissingLeadingSlash(String),
+    /// A parameter is not properly enclosed in braces.
+    #[error("Path template `{0}` contains unbalanced braces")]
+    UnbalancedBraces(String),
+    /// A parameter name is empty.
+    #[error("Path template `{0}` contains an empty parameter name")]
+    EmptyParameterName(String),
+    /// A parameter name is used more than once.
+    #[error("Path template `{template}` contains parameter `{name}` more than once")]
+    DuplicateParameter {
+        /// The path template.
+        template: String,
+        /// Name of the duplicate parameter.
+        name: String,
+    },
+}
+
+impl PathTemplate {
+    /// Returns the names of the parameters in the template, in order of appearance.
+    pub fn parameters(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            PathSegment::Param(name) => Some(name.as_str()),
+            PathSegment::Literal(_) => None,
+        })
+    }
+
+    /// Render the path by replacing each parameter with its percent-encoded value.
+    ///
+    /// Values equal to `.` or `..` are rejected with [`RestRequestError::DotSegmentPathParameter`],
+    /// so that a parameter cannot traverse the path, e.g. `/v1/accounts/{id}` with
+    /// `id = ".."` would otherwise resolve to `/v1/`.
+    pub fn render(&self, params: &BTreeMap<String, String>) -> Result<String, RestRequestError> {
+        if let Some(unknown) = params
+            .keys()
+            .find(|name| !self.parameters().any(|param| param == name.as_str()))
+        {
+            return Err(RestRequestError::UnknownPathParameter(unknown.clone()));
+        }
+        let mut path = String::new();
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Literal(literal) => path.push_str(literal),
+                PathSegment::Param(name) => {
+                    let value = params
+                        .get(name)
+                        .ok_or_else(|| RestRequestError::MissingPathParameter(name.clone()))?;
+                    if value.is_empty() {
+                        return Err(RestRequestError::EmptyPathParameter(name.clone()));
+                    }
+                    if value == "." || value == ".." {
+                        return Err(RestRequestError::DotSegmentPathParameter {
+                            name: name.clone(),
+                            value: value.clone(),
+                        });
+                    }
+                    write!(path, "{}", PercentEncoded(value))
+                        .expect("BUG: writing to a String cannot fail");
+                }
+            }
+        }
+        Ok(path)
+    }
+}
+
+impl FromStr for PathTemplate {
+    type Err = PathTemplateError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        if !template.starts_with('/') {
+            return Err(PathTemplateError::MissingLeadingSlash(template.to_string()));
+        }
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            let (literal, tail) = rest.split_at(start);
+            let name_and_tail = tail
+                .strip_prefix('{')
+                .ok_or_else(|| PathTemplateError::UnbalancedBraces(template.to_string()))?;
+            let end = name_and_tail
+                .find(['{', '}'])
+                .filter(|end| name_and_tail[*end..].starts_with('}'))
+                .ok_or_else(|| PathTemplateError::UnbalancedBraces(template.to_string()))?;
+            let name = &name_and_tail[..end];
+            if name.is_empty() {
+                return Err(PathTemplateError::EmptyParameterName(template.to_string()));
+            }
+            if segments.contains(&PathSegment::Param(name.to_string())) {
+                return Err(PathTemplateError::DuplicateParameter {
+                    template: template.to_string(),
+                    name: name.to_string(),
+                });
+            }
+            if !literal.is_empty() {
+                segments.push(PathSegment::Literal(literal.to_string()));
+            }
+            segments.push(PathSegment::Param(name.to_string()));
+            rest = &name_and_tail[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(PathSegment::Literal(rest.to_string()));
+        }
+        Ok(Self { segments })
+    }
+}
+
+/// Request to a [`RestEndpoint`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RestRequest {
+    path_params: BTreeMap<String, String>,
+    query_params: QueryParams,
+    body: Option<serde_json::Value>,
+}
+
+impl RestRequest {
+    /// Create a new request without any parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the value of a parameter of the path template.
+    pub fn path_param(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.path_params.insert(name.into(), value.to_string());
+        self
+    }
+
+    /// Add a query parameter.
+    ///
+    /// Query parameters are appended after the ones defined by the endpoint.
+    pub fn query_param(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.query_params.append(name, value.to_string());
+        self
+    }
+
+    /// Set a JSON body, which is typically needed for `POST` requests.
+    pub fn json_body<T: Serialize>(mut self, body: &T) -> Result<Self, RestRequestError> {
+        let body = serde_json::to_value(body)
+            .map_err(|e| RestRequestError::InvalidJsonBody(e.to_string()))?;
+        self.body = Some(body);
+        Ok(self)
+    }
+}
+
+/// Error returned when converting a [`RestRequest`] with a [`RestEndpoint`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum RestRequestError {
+    /// A parameter of the path template has no value.
+    #[error("Missing value for path parameter `{0}`")]
+    MissingPathParameter(String),
+    /// A path parameter does not appear in the path template.
+    #[error("Unknown path parameter `{0}`")]
+    UnknownPathParameter(String),
+    /// A path parameter has an empty value.
+    #[error("Path parameter `{0}` must not be empty")]
+    EmptyPathParameter(String),
+    /// A path parameter is a dot-segment, i.e. `.` or `..`, which would change the path.
+    #[error("Path parameter `{name}` must not be `{value}`")]
+    DotSegmentPathParameter {
+        /// Name of the path parameter.
+        name: String,
+        /// Value of the path parameter.
+        value: String,
+    },
+    /// The body could not be serialized to JSON.
+    #[error("Invalid JSON body: {0}")]
+    InvalidJsonBody(String),
+    /// The resulting URI is invalid.
+    #[error("Invalid URI `{uri}`: {reason}")]
+    InvalidUri {
+        /// The invalid URI.
+        uri: String,
+        /// Reason for the URI being invalid.
+        reason: String,
+    },
+}
+
+/// An endpoint of a REST API returning JSON responses of type `T`.
+///
+/// See the [module docs](crate::http::rest) for an example.
+#[derive(Debug)]
+pub struct RestEndpoint<T> {
+    method: Method,
+    base_url: String,
+    path: PathTemplate,
+    query_params: QueryParams,
+    max_response_bytes: Option<u64>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> RestEndpoint<T> {
+    /// Create a new endpoint with the given HTTP method, base URL (e.g. `https://api.example.com`)
+    /// and path template (e.g. `/v1/accounts/{id}/txs`).
+    pub fn new(
+        method: Method,
+        base_url: impl Into<String>,
+        path: &str,
+    ) -> Result<Self, PathTemplateError> {
+        Ok(Self {
+            method,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            path: path.parse()?,
+            query_params: QueryParams::new(),
+            max_response_bytes: None,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Create a new `GET` endpoint.
+    pub fn get(base_url: impl Into<String>, path: &str) -> Result<Self, PathTemplateError> {
+        Self::new(Method::GET, base_url, path)
+    }
+
+    /// Create a new `POST` endpoint.
+    pub fn post(base_url: impl Into<String>, path: &str) -> Result<Self, PathTemplateError> {
+        Self::new(Method::POST, base_url, path)
+    }
+
+    /// Add a query parameter that will be sent with every request to this endpoint.
+    pub fn with_query_param(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.query_params.append(name, value.to_string());
+        self
+    }
+
+    /// Set the max response bytes of every request to this endpoint.
+    ///
+    /// See [`MaxResponseBytesRequestExtension`].
+    pub fn with_max_response_bytes(mut self, value: u64) -> Self {
+        self.max_response_bytes = Some(value);
+        self
+    }
+
+    /// Returns the path template of this endpoint.
+    pub fn path(&self) -> &PathTemplate {
+        &self.path
+    }
+
+    /// Returns the URI for the given request.
+    pub fn uri(&self, request: &RestRequest) -> Result<String, RestRequestError> {
+        let mut uri = self.base_url.clone();
+        uri.push_str(&self.path.render(&request.path_params)?);
+        let query: QueryParams = self
+            .query_params
+            .iter()
+            .chain(request.query_params.iter())
+            .collect();
+        if !query.is_empty() {
+            write!(uri, "?{query}").expect("BUG: writing to a String cannot fail");
+        }
+        Ok(uri)
+    }
+}
+
+// #[derive(Clone)] would otherwise introduce a bound T: Clone, which is not needed.
+impl<T> Clone for RestEndpoint<T> {
+    fn clone(&self) -> Self {
+        Self {
+            method: self.method.clone(),
+            base_url: self.base_url.clone(),
+            path: self.path.clone(),
+            query_params: self.query_params.clone(),
+            max_response_bytes: self.max_response_bytes,
+            _marker: self._marker,
+        }
+    }
+}
+
+impl<T> Convert<RestRequest> for RestEndpoint<T> {
+    type Output = HttpRequest;
+    type Error = RestRequestError;
+
+    fn try_convert(&mut self, request: RestRequest) -> Result<Self::Output, Self::Error> {
+        let uri = self.uri(&request)?;
+        let mut builder = http::Request::builder()
+            .method(self.method.clone())
+            .uri(&uri);
+        if let Some(max_response_bytes) = self.max_response_bytes {
+            builder = builder.max_response_bytes(max_response_bytes);
+        }
+        let body = match request.body {
+            Some(body) => {
+                builder =
+                    builder.header(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                serde_json::to_vec(&body)
+                    .map_err(|e| RestRequestError::InvalidJsonBody(e.to_string()))?
+            }
+            None => Vec::new(),
+        };
+        builder
+            .body(body)
+            .map_err(|e| RestRequestError::InvalidUri {
+                uri,
+                reason: e.to_string(),
+            })
+    }
+}
+
+impl<S, T> Layer<S> for RestEndpoint<T>
+where
+    T: DeserializeOwned,
+{
+    type Service = ConvertResponse<ConvertRequest<S, RestEndpoint<T>>, JsonResponseConverter<T>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let stack = tower_layer::Stack::new(
+            ConvertRequestLayer::new(self.clone()),
+            ConvertResponseLayer::new(JsonResponseConverter::<T>::new()),
+        );
+        stack.layer(inner)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::http::{
+    rest::{PathTemplate, PathTemplateError, RestEndpoint, RestRequest, RestRequestError},
+    HttpRequest, HttpResponse,
+};
+use crate::MaxResponseBytesRequestExtension;
+use serde_json::json;
+use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+const BASE_URL: &str = "https://api.example.com";
+
+#[test]
+fn should_parse_path_template() {
+    let template: PathTemplate = "/v1/accounts/{id}/txs/{tx_id}".parse().unwrap();
+    assert_eq!(
+        template.parameters().collect::<Vec<_>>(),
+        vec!["id", "tx_id"]
+    );
+
+    let template: PathTemplate = "/v1/status".parse().unwrap();
+    assert_eq!(template.parameters().count(), 0);
+}
+
+#[test]
+fn should_fail_to_parse_invalid_path_template() {
+    for (template, expected_error) in [
+        (
+            "v1/accounts",
+            PathTemplateError::MissingLeadingSlash("v1/accounts".to_string()),
+        ),
+        (
+            "/v1/accounts/{id",
+            PathTemplateError::UnbalancedBraces("/v1/accounts/{id".to_string()),
+        ),
+        (
+            "/v1/accounts/id}",
+            PathTemplateError::UnbalancedBraces("/v1/accounts/id}".to_string()),
+        ),
+        (
+            "/v1/accounts/{a{b}}",
+            PathTemplateError::UnbalancedBraces("/v1/accounts/{a{b}}".to_string()),
+        ),
+        (
+            "/v1/accounts/{}",
+            PathTemplateError::EmptyParameterName("/v1/accounts/{}".to_string()),
+        ),
+        (
+            "/v1/{id}/{id}",
+            PathTemplateError::DuplicateParameter {
+                template: "/v1/{id}/{id}".to_string(),
+                name: "id".to_string(),
+            },
+        ),
+    ] {
+        assert_eq!(template.parse::<PathTemplate>(), Err(expected_error));
+    }
+}
+
+#[test]
+fn should_build_uri() {
+    let endpoint = RestEndpoint::<()>::get(format!("{BASE_URL}/"), "/v1/accounts/{id}/txs")
+        .unwrap()
+        .with_query_param("api-key", "s3cr3t/=");
+
+    assert_eq!(
+        endpoint.uri(&RestRequest::new().path_param("id", "a/b?c")),
+        Ok(format!(
+            "{BASE_URL}/v1/accounts/a%2Fb%3Fc/txs?api-key=s3cr3t%2F%3D"
+        ))
+    );
+    assert_eq!(
+        endpoint.uri(
+            &RestRequest::new()
+                .path_param("id", 42)
+                .query_param("limit", 10)
+                .query_param("cursor", "ü")
+        ),
+        Ok(format!(
+            "{BASE_URL}/v1/accounts/42/txs?api-key=s3cr3t%2F%3D&limit=10&cursor=%C3%BC"
+        ))
+    );
+}
+
+#[test]
+fn should_validate_path_parameters() {
+    let endpoint = RestEndpoint::<()>::get(BASE_URL, "/v1/accounts/{id}").unwrap();
+
+    assert_eq!(
+        endpoint.uri(&RestRequest::new()),
+        Err(RestRequestError::MissingPathParameter("id".to_string()))
+    );
+    assert_eq!(
+        endpoint.uri(&RestRequest::new().path_param("id", "")),
+        Err(RestRequestError::EmptyPathParameter("id".to_string()))
+    );
+    assert_eq!(
+        endpoint.uri(&RestRequest::new().path_param("id", 1).path_param("idd", 2)),
+        Err(RestRequestError::UnknownPathParameter("idd".to_string()))
+    );
+}
+
+#[test]
+fn should_reject_dot_segment_path_parameters() {
+    let endpoint = RestEndpoint::<()>::get(BASE_URL, "/v1/accounts/{id}/txs").unwrap();
+
+    for value in [".", ".."] {
+        assert_eq!(
+            endpoint.uri(&RestRequest::new().path_param("id", value)),
+            Err(RestRequestError::DotSegmentPathParameter {
+                name: "id".to_string(),
+                value: value.to_string(),
+            })
+        );
+    }
+    assert_eq!(
+        endpoint.uri(&RestRequest::new().path_param("id", "../admin")),
+        Ok(format!("{BASE_URL}/v1/accounts/..%2Fadmin/txs"))
+    );
+    assert_eq!(
+        endpoint.uri(&RestRequest::new().path_param("id", "...")),
+        Ok(format!("{BASE_URL}/v1/accounts/.../txs"))
+    );
+}
+
+#[tokio::test]
+async fn should_call_endpoint_and_decode_response() {
+    async fn echo_request(request: HttpRequest) -> Result<HttpResponse, BoxError> {
+        let body = json!({
+            "method": request.method().as_str(),
+            "uri": request.uri().to_string(),
+            "content_type": request
+                .headers()
+                .get(http::header::CONTENT_TYPE)
+                .map(|value| value.to_str().unwrap()),
+            "max_response_bytes": request.get_max_response_bytes(),
+            "body": serde_json::from_slice::<serde_json::Value>(request.body()).ok(),
+        });
+        Ok(http::Response::new(serde_json::to_vec(&body).unwrap()))
+    }
+
+    let endpoint = RestEndpoint::<serde_json::Value>::post(BASE_URL, "/v1/accounts/{id}")
+        .unwrap()
+        .with_max_response_bytes(1_000);
+    let mut service = ServiceBuilder::new()
+        .layer(endpoint)
+        .service_fn(echo_request);
+
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(
+            RestRequest::new()
+                .path_param("id", "alice")
+                .json_body(&json!({"amount": 1}))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.into_body(),
+        json!({
+            "method": "POST",
+            "uri": format!("{BASE_URL}/v1/accounts/alice"),
+            "content_type": "application/json",
+            "max_response_bytes": 1_000,
+            "body": {"amount": 1},
+        })
+    );
+}
@@ -0,0 +1,287 @@
+use crate::{
+    clock::Clock,
+    convert::{ConvertRequest, ConvertRequestLayer, Filter},
+    http::{encode_hex, query::append_query, HttpRequest, QueryParams},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{HeaderName, HeaderValue};
+use sha2::{Digest, Sha256};
+use std::{fmt, sync::Arc};
+use thiserror::Error;
+use tower_layer::Layer;
+
+type CanonicalizeFn = Arc<dyn Fn(&HttpRequest) -> Vec<u8>>;
+
+/// Part of the request that is signed by an [`HmacSigner`].
+#[derive(Clone)]
+pub enum Canonicalization {
+    /// Sign the query string of the URL (without the leading `?`).
+    Query,
+    /// Sign the request body.
+    Body,
+    /// Sign the query string of the URL followed by the request body.
+    QueryAndBody,
+    /// Sign the bytes returned by the given function.
+    Custom(CanonicalizeFn),
+}
+
+impl Canonicalization {
+    /// Sign the bytes returned by the given function.
+    pub fn custom(f: impl Fn(&HttpRequest) -> Vec<u8> + 'static) -> Self {
+        Canonicalization::Custom(Arc::new(f))
+    }
+
+    pub(crate) fn message(&self, request: &HttpRequest) -> Vec<u8> {
+        let query = request.uri().query().unwrap_or_default().as_bytes();
+        match self {
+            Canonicalization::Query => query.to_vec(),
+            Canonicalization::Body => request.body().clone(),
+            Canonicalization::QueryAndBody => [query, request.body()].concat(),
+            Canonicalization::Custom(f) => f(request),
+        }
+    }
+}
+
+impl fmt::Debug for Canonicalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Canonicalization::Query => write!(f, "Query"),
+            Canonicalization::Body => write!(f, "Body"),
+            Canonicalization::QueryAndBody => write!(f, "QueryAndBody"),
+            Canonicalization::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// Where a value (signature or timestamp) is added to the request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Placement {
+    /// Set the given header.
+    Header(HeaderName),
+    /// Append the given query parameter to the URL.
+    QueryParam(String),
+}
+
+/// Encoding of the signature.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SignatureEncoding {
+    /// Lower-case hexadecimal encoding.
+    #[default]
+    Hex,
+    /// Standard base64 encoding with padding.
+    Base64,
+}
+
+/// Error returned when a request cannot be signed by an [`HmacSigner`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum HmacSignatureError {
+    /// The URL with the added query parameters is invalid.
+    #[error("Invalid URL after adding query parameters `{url}`: {reason}")]
+    InvalidUrl {
+        /// The invalid URL.
+        url: String,
+        /// Reason for the URL being invalid.
+        reason: String,
+    },
+}
+
+/// Sign requests with HMAC-SHA256, as required by many exchange APIs.
+///
+/// Signing a request consists of the following steps:
+/// 1. If configured, add the current timestamp in milliseconds to the request,
+///    see [`HmacSigner::timestamp`].
+/// 2. Compute the HMAC-SHA256 of the [`Canonicalization`] of the request with the secret key.
+/// 3. Add the encoded signature to the request at the configured [`Placement`].
+///
+/// # Examples
+///
+/// Sign requests to the Binance API, where the signature of the query string
+/// (including a `timestamp` parameter) is appended as a `signature` query parameter:
+///
+/// ```rust
+/// use canhttp::http::{Canonicalization, HmacSigner, Placement};
+/// use canhttp::convert::Filter;
+///
+/// let mut signer = HmacSigner::new(
+///     "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j",
+///     Canonicalization::Query,
+///     Placement::QueryParam("signature".to_string()),
+/// )
+/// .timestamp(Placement::QueryParam("timestamp".to_string()))
+/// .clock(|| 1499827319559);
+///
+/// let request = http::Request::post(
+///     "https://api.binance.com/api/v3/order?symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000"
+/// )
+/// .body(vec![])
+/// .unwrap();
+///
+/// let signed_request = signer.filter(request).unwrap();
+///
+/// assert_eq!(
+///     signed_request.uri().query(),
+///     Some("symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559&signature=c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71")
+/// );
+/// ```
+#[derive(Clone)]
+pub struct HmacSigner {
+    secret: Vec<u8>,
+    canonicalization: Canonicalization,
+    signature: Placement,
+    encoding: SignatureEncoding,
+    timestamp: Option<Placement>,
+    clock: Clock,
+}
+
+impl HmacSigner {
+    /// Create a new [`HmacSigner`] with the given secret key that signs the given part of
+    /// the request and adds the hex-encoded signature at the given place.
+    pub fn new(
+        secret: impl AsRef<[u8]>,
+        canonicalization: Canonicalization,
+        signature: Placement,
+    ) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+            canonicalization,
+            signature,
+            encoding: SignatureEncoding::default(),
+            timestamp: None,
+            clock: Clock::new(|| ic_cdk::api::time() / 1_000_000),
+        }
+    }
+
+    /// Set the encoding of the signature.
+    pub fn encoding(mut self, encoding: SignatureEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Add the current timestamp in milliseconds at the given place before signing the request.
+    pub fn timestamp(mut self, placement: Placement) -> Self {
+        self.timestamp = Some(placement);
+        self
+    }
+
+    /// Change how the current timestamp in milliseconds is obtained.
+    ///
+    /// By default, the timestamp is derived from [`ic_cdk::api::time`].
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+
+    fn sign(&self, request: &HttpRequest) -> String {
+        let signature = hmac_sha256(&self.secret, &self.canonicalization.message(request));
+        match self.encoding {
+            SignatureEncoding::Hex => encode_hex(&signature),
+            SignatureEncoding::Base64 => STANDARD.encode(signature),
+        }
+    }
+}
+
+impl fmt::Debug for HmacSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacSigner")
+            .field("secret", &"<redacted>")
+            .field("canonicalization", &self.canonicalization)
+            .field("signature", &self.signature)
+            .field("encoding", &self.encoding)
+            .field("timestamp", &self.timestamp)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Filter<HttpRequest> for HmacSigner {
+    type Error = HmacSignatureError;
+
+    fn filter(&mut self, mut request: HttpRequest) -> Result<HttpRequest, Self::Error> {
+        if let Some(placement) = &self.timestamp {
+            let timestamp = self.clock.now().to_string();
+            add_value(&mut request, placement, timestamp)?;
+        }
+        let signature = self.sign(&request);
+        add_value(&mut request, &self.signature, signature)?;
+        Ok(request)
+    }
+}
+
+impl Placement {
+    /// Add the given value to the request.
+    ///
+    /// Returns the resulting URL and the reason for it being invalid if the value
+    /// cannot be added as a query parameter.
+    pub(crate) fn add_to<T>(
+        &self,
+        request: &mut http::Request<T>,
+        value: String,
+    ) -> Result<(), (String, String)> {
+        match self {
+            Placement::Header(name) => {
+                let value = HeaderValue::try_from(value)
+                    .expect("BUG: timestamps and encoded signatures are valid header values");
+                request.headers_mut().insert(name.clone(), value);
+            }
+            Placement::QueryParam(name) => {
+                let url = append_query(request.uri(), &QueryParams::new().with_param(name, value));
+                *request.uri_mut() = url
+                    .parse()
+                    .map_err(|e: http::uri::InvalidUri| (url, e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn add_value(
+    request: &mut HttpRequest,
+    placement: &Placement,
+    value: String,
+) -> Result<(), HmacSignatureError> {
+    placement
+        .add_to(request, value)
+        .map_err(|(url, reason)| HmacSignatureError::InvalidUrl { url, reason })
+}
+
+/// HMAC-SHA256 as specified in [RFC 2104](https://www.rfc-editor.org/rfc/rfc2104).
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut key_block = [0_u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        key_block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        key_block[..key.len()].copy_from_slice(key);
+    }
+    let inner_pad: Vec<u8> = key_block.iter().map(|b| b ^ 0x36).collect();
+    let outer_pad: Vec<u8> = key_block.iter().map(|b| b ^ 0x5c).collect();
+
+    let mut inner = Sha256::new();
+    inner.update(&inner_pad);
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(&outer_pad);
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Middleware that signs requests with HMAC-SHA256, see [`HmacSigner`].
+#[derive(Clone, Debug)]
+pub struct HmacSignatureLayer {
+    signer: HmacSigner,
+}
+
+impl HmacSignatureLayer {
+    /// Returns a new [`HmacSignatureLayer`] signing requests with the given signer.
+    pub fn new(signer: HmacSigner) -> Self {
+        Self { signer }
+    }
+}
+
+impl<S> Layer<S> for HmacSignatureLayer {
+    type Service = ConvertRequest<S, HmacSigner>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConvertRequestLayer::new(self.signer.clone()).layer(inner)
+    }
+}
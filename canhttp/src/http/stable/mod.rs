@@ -0,0 +1,285 @@
+//! Middleware to persist large response bodies to stable memory.
+//!
+//! Responses to HTTPs outcalls can be up to 2MB. Converting such a response (e.g. from
+//! [`IcHttpResponse`] to [`HttpResponse`] and then deserializing it) may temporarily require
+//! several copies of the body on the heap. The [`PersistLargeBody`] converter moves bodies
+//! above a given size threshold out of the heap and into a [`StableBodyBuffer`], returning a
+//! [`StableBodyHandle`] that can be used later on to read the body back.
+//!
+//! ```text
+//!                 │                     ▲
+//!      HttpRequest│                     │http::Response<ResponseBody>
+//!               ┌─┴─────────────────────┴───┐
+//!               │     PersistLargeBody      │
+//!               └─┬─────────────────────┬───┘
+//!      HttpRequest│                     │HttpResponse
+//!                 ▼                     │
+//!               ┌─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┐
+//!               │          SERVICE          │
+//!               └─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─ ─┘
+//! ```
+//!
+//! The buffer occupies a fixed region of stable memory that is reused in a circular fashion:
+//! once the region is full, new bodies overwrite the oldest ones. Reading a body that was
+//! overwritten results in a [`StableBodyBufferError::Overwritten`] error.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::{
+//!     convert::ConvertServiceBuilder,
+//!     http::{HttpRequest, HttpResponse, stable::{PersistLargeBody, ResponseBody, StableBodyBuffer}},
+//! };
+//! use tower::{Service, ServiceBuilder, ServiceExt, BoxError};
+//!
+//! async fn large_response(_request: HttpRequest) -> Result<HttpResponse, BoxError> {
+//!     Ok(http::Response::new(vec![42_u8; 1_000_000]))
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! // Reserve 4MiB of stable memory starting at offset 0 for response bodies.
+//! let buffer = StableBodyBuffer::new(0, 4 * 1024 * 1024);
+//! # let buffer = StableBodyBuffer::with_memory(ic_stable_structures::VectorMemory::default(), 0, 4 * 1024 * 1024);
+//!
+//! let mut service = ServiceBuilder::new()
+//!     // Persist bodies larger than 64KiB
+//!     .convert_response(PersistLargeBody::new(buffer.clone(), 64 * 1024))
+//!     .service_fn(large_response);
+//!
+//! let request = http::Request::get("https://internetcomputer.org").body(vec![]).unwrap();
+//!
+//! let response = service.ready().await?.call(request).await?;
+//!
+//! match response.into_body() {
+//!     ResponseBody::Inline(_body) => unreachable!("body is larger than the threshold"),
+//!     ResponseBody::Persisted(handle) => {
+//!         assert_eq!(handle.len(), 1_000_000);
+//!         assert_eq!(buffer.read(&handle)?, vec![42_u8; 1_000_000]);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`IcHttpResponse`]: ic_cdk_management_canister::HttpRequestResult
+
+pub(crate) mod region;
+#[cfg(test)]
+mod tests;
+
+use crate::{convert::Convert, http::HttpResponse};
+pub use ic_stable_structures::Memory;
+use std::{cell::RefCell, rc::Rc};
+use thiserror::Error;
+
+pub(crate) const WASM_PAGE_SIZE_IN_BYTES: u64 = 64 * 1024;
+
+/// The whole stable memory of the canister, accessed through [`ic_cdk::stable`].
+///
+/// Any [`Memory`] of the [`ic-stable-structures`](https://crates.io/crates/ic-stable-structures)
+/// crate can be used instead, e.g. a virtual memory obtained from a `MemoryManager`, which is
+/// required if the canister stores anything else in stable memory.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CanisterStableMemory;
+
+impl Memory for CanisterStableMemory {
+    fn size(&self) -> u64 {
+        ic_cdk::stable::stable_size()
+    }
+
+    fn grow(&self, pages: u64) -> i64 {
+        ic_cdk::stable::stable_grow(pages).map_or(-1, |previous| previous as i64)
+    }
+
+    fn read(&self, offset: u64, dst: &mut [u8]) {
+        ic_cdk::stable::stable_read(offset, dst)
+    }
+
+    fn write(&self, offset: u64, src: &[u8]) {
+        ic_cdk::stable::stable_write(offset, src)
+    }
+}
+
+/// Handle to a response body stored in a [`StableBodyBuffer`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StableBodyHandle {
+    generation: u64,
+    offset: u64,
+    len: u64,
+}
+
+impl StableBodyHandle {
+    /// Returns the length in bytes of the persisted body.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the persisted body is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Error returned by a [`StableBodyBuffer`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum StableBodyBufferError {
+    /// The body does not fit in the buffer.
+    #[error("Body of {len} bytes exceeds the buffer capacity of {capacity} bytes")]
+    BodyTooLarge {
+        /// Length of the body in bytes.
+        len: u64,
+        /// Capacity of the buffer in bytes.
+        capacity: u64,
+    },
+    /// The stable memory could not be grown to hold the buffer.
+    #[error("Failed to grow stable memory to {required_pages} pages")]
+    OutOfMemory {
+        /// Number of pages required to hold the buffer.
+        required_pages: u64,
+    },
+    /// The body referenced by the handle was overwritten by more recent bodies.
+    #[error("Body at offset {offset} was overwritten")]
+    Overwritten {
+        /// Offset of the body in stable memory.
+        offset: u64,
+    },
+}
+
+#[derive(Debug, Default)]
+struct BufferState {
+    generation: u64,
+    cursor: u64,
+}
+
+/// Circular buffer occupying a fixed region of stable memory to store response bodies.
+///
+/// Cloning a [`StableBodyBuffer`] is cheap and all clones share the same underlying region.
+#[derive(Clone, Debug)]
+pub struct StableBodyBuffer<M = CanisterStableMemory> {
+    memory: M,
+    offset: u64,
+    capacity: u64,
+    state: Rc<RefCell<BufferState>>,
+}
+
+impl StableBodyBuffer {
+    /// Create a new [`StableBodyBuffer`] in the canister stable memory occupying `capacity`
+    /// bytes starting at `offset`.
+    ///
+    /// The region must not be used for anything else by the canister.
+    /// Stable memory is grown lazily, when the first body is stored.
+    pub fn new(offset: u64, capacity: u64) -> Self {
+        Self::with_memory(CanisterStableMemory, offset, capacity)
+    }
+}
+
+impl<M: Memory> StableBodyBuffer<M> {
+    /// Create a new [`StableBodyBuffer`] in the given memory occupying `capacity` bytes
+    /// starting at `offset`.
+    pub fn with_memory(memory: M, offset: u64, capacity: u64) -> Self {
+        Self {
+            memory,
+            offset,
+            capacity,
+            state: Rc::new(RefCell::new(BufferState::default())),
+        }
+    }
+
+    /// Write the given body to stable memory and return a handle to read it back.
+    ///
+    /// If there is not enough space left at the end of the buffer, the body is written at the
+    /// beginning of the buffer, which invalidates all bodies stored there.
+    pub fn store(&self, body: &[u8]) -> Result<StableBodyHandle, StableBodyBufferError> {
+        let len = body.len() as u64;
+        if len > self.capacity {
+            return Err(StableBodyBufferError::BodyTooLarge {
+                len,
+                capacity: self.capacity,
+            });
+        }
+        self.ensure_memory_size()?;
+        let mut state = self.state.borrow_mut();
+        if state.cursor + len > self.capacity {
+            state.generation += 1;
+            state.cursor = 0;
+        }
+        let handle = StableBodyHandle {
+            generation: state.generation,
+            offset: state.cursor,
+            len,
+        };
+        self.memory.write(self.offset + handle.offset, body);
+        state.cursor += len;
+        Ok(handle)
+    }
+
+    /// Read the body referenced by the given handle.
+    pub fn read(&self, handle: &StableBodyHandle) -> Result<Vec<u8>, StableBodyBufferError> {
+        if !self.is_valid(handle) {
+            return Err(StableBodyBufferError::Overwritten {
+                offset: self.offset + handle.offset,
+            });
+        }
+        let mut body = vec![0_u8; handle.len as usize];
+        self.memory.read(self.offset + handle.offset, &mut body);
+        Ok(body)
+    }
+
+    /// Returns `true` if the body referenced by the given handle was not overwritten yet.
+    pub fn is_valid(&self, handle: &StableBodyHandle) -> bool {
+        let state = self.state.borrow();
+        // Bodies from the current generation are located before the cursor and are all valid,
+        // while bodies from the previous generation are only valid if located after the cursor.
+        handle.generation == state.generation
+            || (handle.generation + 1 == state.generation && handle.offset >= state.cursor)
+    }
+
+    fn ensure_memory_size(&self) -> Result<(), StableBodyBufferError> {
+        region::grow_to(&self.memory, self.offset + self.capacity)
+            .map_err(|required_pages| StableBodyBufferError::OutOfMemory { required_pages })
+    }
+}
+
+/// Body of a response converted by [`PersistLargeBody`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ResponseBody<H = StableBodyHandle> {
+    /// The body was small enough to be kept on the heap.
+    Inline(Vec<u8>),
+    /// The body was moved to stable memory.
+    Persisted(H),
+}
+
+/// Convert responses of type [`HttpResponse`] into [`http::Response<ResponseBody>`] by moving
+/// bodies strictly larger than a threshold into a [`StableBodyBuffer`].
+///
+/// See the [module docs](crate::http::stable) for an example.
+#[derive(Clone, Debug)]
+pub struct PersistLargeBody<M = CanisterStableMemory> {
+    buffer: StableBodyBuffer<M>,
+    threshold: usize,
+}
+
+impl<M> PersistLargeBody<M> {
+    /// Create a new [`PersistLargeBody`] converter that persists bodies with more than
+    /// `threshold` bytes into the given buffer.
+    pub fn new(buffer: StableBodyBuffer<M>, threshold: usize) -> Self {
+        Self { buffer, threshold }
+    }
+}
+
+impl<M: Memory> Convert<HttpResponse> for PersistLargeBody<M> {
+    type Output = http::Response<ResponseBody>;
+    type Error = StableBodyBufferError;
+
+    fn try_convert(&mut self, response: HttpResponse) -> Result<Self::Output, Self::Error> {
+        let (parts, body) = response.into_parts();
+        let body = if body.len() > self.threshold {
+            // The body is dropped right after being persisted to free the heap.
+            ResponseBody::Persisted(self.buffer.store(&body)?)
+        } else {
+            ResponseBody::Inline(body)
+        };
+        Ok(http::Response::from_parts(parts, body))
+    }
+}
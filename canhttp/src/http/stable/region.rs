@@ -0,0 +1,173 @@
+//! Helpers to persist values in a fixed region of stable memory.
+//!
+//! Values are encoded with an [`Encoder`] and stored prefixed by their length with
+//! [`write_length_prefixed`], so that they can be read back with [`read_length_prefixed`]
+//! and decoded with a [`Decoder`]. Integers are encoded in little-endian and byte strings
+//! are prefixed by their length as a `u64`.
+
+use crate::http::stable::{Memory, WASM_PAGE_SIZE_IN_BYTES};
+
+/// Values are prefixed by their length as a `u64`.
+pub(crate) const LENGTH_PREFIX_SIZE: u64 = 8;
+
+/// Error returned when writing a length-prefixed value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum WriteError {
+    /// The length-prefixed value does not fit in the region.
+    TooLarge { len: u64, capacity: u64 },
+    /// The memory could not be grown to hold the value.
+    OutOfMemory { required_pages: u64 },
+}
+
+/// Error returned when the length-prefixed value stored in a region exceeds its capacity,
+/// e.g. because the region was shrunk or overwritten.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct CapacityExceeded {
+    pub(crate) len: u64,
+    pub(crate) capacity: u64,
+}
+
+/// Returns `true` if the memory holds at least `end` bytes.
+pub(crate) fn has_size<M: Memory>(memory: &M, end: u64) -> bool {
+    memory.size() * WASM_PAGE_SIZE_IN_BYTES >= end
+}
+
+/// Grow the memory so that it holds at least `end` bytes.
+///
+/// Returns the number of pages that would be required if the memory could not be grown.
+pub(crate) fn grow_to<M: Memory>(memory: &M, end: u64) -> Result<(), u64> {
+    let required_pages = end.div_ceil(WASM_PAGE_SIZE_IN_BYTES);
+    let current_pages = memory.size();
+    if current_pages < required_pages && memory.grow(required_pages - current_pages) < 0 {
+        return Err(required_pages);
+    }
+    Ok(())
+}
+
+/// Write the given value prefixed by its length to the region of `capacity` bytes starting
+/// at `offset`, growing the memory if needed.
+pub(crate) fn write_length_prefixed<M: Memory>(
+    memory: &M,
+    offset: u64,
+    capacity: u64,
+    value: &[u8],
+) -> Result<(), WriteError> {
+    let len = LENGTH_PREFIX_SIZE + value.len() as u64;
+    if len > capacity {
+        return Err(WriteError::TooLarge { len, capacity });
+    }
+    grow_to(memory, offset + len)
+        .map_err(|required_pages| WriteError::OutOfMemory { required_pages })?;
+    memory.write(offset, &(value.len() as u64).to_le_bytes());
+    memory.write(offset + LENGTH_PREFIX_SIZE, value);
+    Ok(())
+}
+
+/// Read the value written with [`write_length_prefixed`] to the region of `capacity` bytes
+/// starting at `offset`.
+///
+/// Returns [`None`] if no value was ever written, i.e. if the memory does not hold the length
+/// prefix or if the length is zero.
+pub(crate) fn read_length_prefixed<M: Memory>(
+    memory: &M,
+    offset: u64,
+    capacity: u64,
+) -> Result<Option<Vec<u8>>, CapacityExceeded> {
+    if !has_size(memory, offset + LENGTH_PREFIX_SIZE) {
+        return Ok(None);
+    }
+    let mut len = [0_u8; LENGTH_PREFIX_SIZE as usize];
+    memory.read(offset, &mut len);
+    let len = u64::from_le_bytes(len);
+    if len == 0 {
+        return Ok(None);
+    }
+    let total_len = LENGTH_PREFIX_SIZE.saturating_add(len);
+    if total_len > capacity || !has_size(memory, offset + total_len) {
+        return Err(CapacityExceeded {
+            len: total_len,
+            capacity,
+        });
+    }
+    let mut value = vec![0_u8; len as usize];
+    memory.read(offset + LENGTH_PREFIX_SIZE, &mut value);
+    Ok(Some(value))
+}
+
+/// Encoder of values persisted to stable memory.
+pub(crate) struct Encoder(Vec<u8>);
+
+impl Encoder {
+    /// Create a new encoder whose output starts with the given encoding version.
+    pub(crate) fn new(version: u8) -> Self {
+        Self(vec![version])
+    }
+
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u128(&mut self, value: u128) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn bytes(&mut self, value: &[u8]) {
+        self.u64(value.len() as u64);
+        self.0.extend_from_slice(value);
+    }
+
+    pub(crate) fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// Decoder of values encoded with an [`Encoder`].
+///
+/// All methods return [`None`] if the input is too short or invalid.
+pub(crate) struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    pub(crate) fn u128(&mut self) -> Option<u128> {
+        Some(u128::from_le_bytes(self.take(16)?.try_into().ok()?))
+    }
+
+    pub(crate) fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = usize::try_from(self.u64()?).ok()?;
+        self.take(len)
+    }
+
+    pub(crate) fn str(&mut self) -> Option<String> {
+        String::from_utf8(self.bytes()?.to_vec()).ok()
+    }
+
+    /// Returns `true` if the whole input was decoded.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.0.get(..len)?;
+        self.0 = &self.0[len..];
+        Some(bytes)
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    convert::ConvertServiceBuilder,
+    http::{
+        stable::{Memory, PersistLargeBody, ResponseBody, StableBodyBuffer, StableBodyBufferError},
+        HttpResponse,
+    },
+};
+use ic_stable_structures::VectorMemory;
+use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+#[test]
+fn should_store_and_read_bodies() {
+    let memory = VectorMemory::default();
+    let buffer = StableBodyBuffer::with_memory(memory.clone(), 10, 100);
+
+    let first = buffer.store(&[1_u8; 40]).unwrap();
+    let second = buffer.store(&[2_u8; 60]).unwrap();
+
+    assert_eq!(memory.size(), 1);
+    assert_eq!(buffer.read(&first), Ok(vec![1_u8; 40]));
+    assert_eq!(buffer.read(&second), Ok(vec![2_u8; 60]));
+    assert_eq!(memory.borrow()[..10], [0_u8; 10]);
+}
+
+#[test]
+fn should_invalidate_overwritten_bodies() {
+    let buffer = StableBodyBuffer::with_memory(VectorMemory::default(), 0, 100);
+
+    let first = buffer.store(&[1_u8; 40]).unwrap();
+    let second = buffer.store(&[2_u8; 40]).unwrap();
+    // Does not fit at the end of the buffer and overwrites `first`.
+    let third = buffer.store(&[3_u8; 30]).unwrap();
+
+    assert_eq!(
+        buffer.read(&first),
+        Err(StableBodyBufferError::Overwritten { offset: 0 })
+    );
+    assert_eq!(buffer.read(&second), Ok(vec![2_u8; 40]));
+    assert_eq!(buffer.read(&third), Ok(vec![3_u8; 30]));
+
+    // Overwrites `second`.
+    let _fourth = buffer.store(&[4_u8; 50]).unwrap();
+
+    assert!(!buffer.is_valid(&second));
+    assert_eq!(buffer.read(&third), Ok(vec![3_u8; 30]));
+}
+
+#[test]
+fn should_fail_when_body_too_large() {
+    let buffer = StableBodyBuffer::with_memory(VectorMemory::default(), 0, 100);
+
+    assert_eq!(
+        buffer.store(&[1_u8; 101]),
+        Err(StableBodyBufferError::BodyTooLarge {
+            len: 101,
+            capacity: 100
+        })
+    );
+}
+
+#[tokio::test]
+async fn should_persist_only_large_bodies() {
+    let buffer = StableBodyBuffer::with_memory(VectorMemory::default(), 0, 1_000);
+    let mut service = ServiceBuilder::new()
+        .convert_response(PersistLargeBody::new(buffer.clone(), 10))
+        .service_fn(echo_response);
+
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(http::Response::new(vec![1_u8; 10]))
+        .await
+        .unwrap();
+    assert_eq!(response.into_body(), ResponseBody::Inline(vec![1_u8; 10]));
+
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(http::Response::new(vec![2_u8; 11]))
+        .await
+        .unwrap();
+    match response.into_body() {
+        ResponseBody::Persisted(handle) => {
+            assert_eq!(buffer.read(&handle), Ok(vec![2_u8; 11]));
+        }
+        body => panic!("Expected persisted body, but got {body:?}"),
+    }
+}
+mod region {
+    use super::VectorMemory;
+    use crate::http::stable::region::{
+        read_length_prefixed, write_length_prefixed, CapacityExceeded, Decoder, Encoder, WriteError,
+    };
+
+    #[test]
+    fn should_write_and_read_length_prefixed_value() {
+        let memory = VectorMemory::default();
+        assert_eq!(read_length_prefixed(&memory, 10, 100), Ok(None));
+
+        write_length_prefixed(&memory, 10, 100, b"value").unwrap();
+
+        assert_eq!(
+            read_length_prefixed(&memory, 10, 100),
+            Ok(Some(b"value".to_vec()))
+        );
+        assert_eq!(
+            write_length_prefixed(&memory, 10, 12, b"value"),
+            Err(WriteError::TooLarge {
+                len: 13,
+                capacity: 12
+            })
+        );
+        assert_eq!(
+            read_length_prefixed(&memory, 10, 12),
+            Err(CapacityExceeded {
+                len: 13,
+                capacity: 12
+            })
+        );
+    }
+
+    #[test]
+    fn should_encode_and_decode_values() {
+        let mut encoder = Encoder::new(1);
+        encoder.u64(42);
+        encoder.u128(u128::MAX);
+        encoder.str("canhttp");
+        let encoded = encoder.into_bytes();
+
+        let mut decoder = Decoder::new(&encoded);
+        assert_eq!(decoder.u8(), Some(1));
+        assert_eq!(decoder.u64(), Some(42));
+        assert_eq!(decoder.u128(), Some(u128::MAX));
+        assert_eq!(decoder.str().as_deref(), Some("canhttp"));
+        assert!(decoder.is_empty());
+        assert_eq!(decoder.u8(), None);
+
+        let mut truncated = Decoder::new(&encoded[..encoded.len() - 1]);
+        truncated.u8();
+        truncated.u64();
+        truncated.u128();
+        assert_eq!(truncated.str(), None);
+    }
+}
+
+async fn echo_response(response: HttpResponse) -> Result<HttpResponse, BoxError> {
+    Ok(response)
+}
@@ -1,8 +1,11 @@
 use crate::{
+    convert::{Convert, Filter},
     http::{
         request::HttpRequestConversionError,
         response::{HttpResponse, HttpResponseConversionError},
-        HttpConversionLayer, HttpRequestConverter, HttpResponseConverter,
+        CharsetConversionError, CharsetConverter, FilterContentType, FilterStatusCode,
+        HttpConversionLayer, HttpRequestConverter, HttpResponseConverter, HttpStatusError,
+        UnexpectedContentTypeError,
     },
     ConvertServiceBuilder, IcError, IsReplicatedRequestExtension, MaxResponseBytesRequestExtension,
     TransformContextRequestExtension,
@@ -93,13 +96,13 @@ async fn should_fail_when_http_method_unsupported() {
         .service_fn(echo_request);
     let url = "https://internetcomputer.org/";
 
-    for request_builder in [
-        http::Request::connect(url),
-        http::Request::delete(url),
-        http::Request::patch(url),
-        http::Request::put(url),
-        http::Request::options(url),
-        http::Request::trace(url),
+    for (request_builder, expected_method) in [
+        (http::Request::connect(url), http::Method::CONNECT),
+        (http::Request::delete(url), http::Method::DELETE),
+        (http::Request::patch(url), http::Method::PATCH),
+        (http::Request::put(url), http::Method::PUT),
+        (http::Request::options(url), http::Method::OPTIONS),
+        (http::Request::trace(url), http::Method::TRACE),
     ] {
         let unsupported_request = request_builder.body(vec![]).unwrap();
 
@@ -112,7 +115,17 @@ async fn should_fail_when_http_method_unsupported() {
                 .await,
         );
 
-        assert_matches!(error, HttpRequestConversionError::UnsupportedHttpMethod(_));
+        assert_eq!(
+            error.to_string(),
+            format!("HTTP method `{expected_method}` is not supported, expected one of: GET, HEAD, POST")
+        );
+        assert_eq!(
+            error,
+            HttpRequestConversionError::UnsupportedHttpMethod {
+                method: expected_method,
+                supported: vec![http::Method::GET, http::Method::HEAD, http::Method::POST],
+            }
+        );
     }
 }
 
@@ -182,6 +195,175 @@ async fn should_fail_to_convert_http_response() {
     )
 }
 
+#[test]
+fn should_transcode_response_body_to_utf8() {
+    fn transcode(content_type: &str, body: Vec<u8>) -> HttpResponse {
+        CharsetConverter
+            .try_convert(
+                http::Response::builder()
+                    .header("content-type", content_type)
+                    .body(body)
+                    .unwrap(),
+            )
+            .unwrap()
+    }
+    let expected = r#"{"name":"Zoë"}"#;
+    let utf16_be: Vec<u8> = expected.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let utf16_le: Vec<u8> = expected.encode_utf16().flat_map(u16::to_le_bytes).collect();
+
+    for (content_type, body) in [
+        ("application/json; charset=utf-8", expected.as_bytes().to_vec()),
+        (
+            "application/json; charset=ISO-8859-1",
+            br#"{"name":"Zo"#
+                .iter()
+                .copied()
+                .chain([0xeb])
+                .chain(br#""}"#.iter().copied())
+                .collect(),
+        ),
+        ("application/json; charset=utf-16be", utf16_be.clone()),
+        ("application/json; charset=\"UTF-16LE\"", utf16_le.clone()),
+        ("application/json; charset=utf-16", utf16_be.clone()),
+        (
+            "application/json; charset=utf-16",
+            [0xff, 0xfe].into_iter().chain(utf16_le).collect(),
+        ),
+    ] {
+        let response = transcode(content_type, body);
+        assert_eq!(response.body(), expected.as_bytes(), "{content_type}");
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/json; charset=utf-8"
+        );
+    }
+
+    let response = transcode("application/json", b"unchanged".to_vec());
+    assert_eq!(response.body(), b"unchanged");
+    assert_eq!(response.headers()["content-type"], "application/json");
+}
+
+#[test]
+fn should_fail_to_transcode_response_body() {
+    fn transcode(
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> Result<HttpResponse, CharsetConversionError> {
+        CharsetConverter.try_convert(
+            http::Response::builder()
+                .header("content-type", content_type)
+                .body(body)
+                .unwrap(),
+        )
+    }
+
+    assert_eq!(
+        transcode("text/plain; charset=shift_jis", vec![]).unwrap_err(),
+        CharsetConversionError::UnsupportedCharset {
+            charset: "shift_jis".to_string()
+        }
+    );
+    assert_matches!(
+        transcode("text/plain; charset=us-ascii", vec![0xe9]),
+        Err(CharsetConversionError::InvalidBody { charset, .. }) if charset == "us-ascii"
+    );
+    assert_matches!(
+        transcode("text/plain; charset=utf-16le", vec![0x00]),
+        Err(CharsetConversionError::InvalidBody { charset, .. }) if charset == "utf-16le"
+    );
+    assert_matches!(
+        transcode("text/plain; charset=utf-8", vec![0xff]),
+        Err(CharsetConversionError::InvalidBody { charset, .. }) if charset == "utf-8"
+    );
+}
+
+#[test]
+fn should_filter_response_with_unexpected_content_type() {
+    fn response(content_type: Option<&str>, body: Vec<u8>) -> HttpResponse {
+        let mut builder = http::Response::builder().status(StatusCode::BAD_GATEWAY);
+        if let Some(content_type) = content_type {
+            builder = builder.header("content-type", content_type);
+        }
+        builder.body(body).unwrap()
+    }
+    let mut filter = FilterContentType::json();
+
+    for content_type in [
+        "application/json",
+        "Application/JSON",
+        "application/json; charset=utf-8",
+    ] {
+        assert!(filter
+            .filter(response(Some(content_type), b"{}".to_vec()))
+            .is_ok());
+    }
+
+    assert_eq!(
+        filter
+            .filter(response(
+                Some("text/html; charset=utf-8"),
+                b"<html>Bad Gateway</html>".to_vec()
+            ))
+            .unwrap_err(),
+        UnexpectedContentTypeError {
+            status: 502,
+            expected: "application/json".to_string(),
+            actual: Some("text/html; charset=utf-8".to_string()),
+            body_preview: "<html>Bad Gateway</html>".to_string(),
+        }
+    );
+
+    let error = filter
+        .filter(response(None, vec![b'a'; 1_000]))
+        .unwrap_err();
+    assert_eq!(error.actual, None);
+    assert_eq!(
+        error.body_preview,
+        "a".repeat(FilterContentType::MAX_BODY_PREVIEW_BYTES)
+    );
+}
+
+#[test]
+fn should_filter_response_with_non_successful_status() {
+    fn response(status: StatusCode) -> HttpResponse {
+        http::Response::builder()
+            .status(status)
+            .header("retry-after", "10")
+            .body(b"body".to_vec())
+            .unwrap()
+    }
+    let mut filter = FilterStatusCode::new().allow(StatusCode::NOT_FOUND);
+
+    for status in [
+        StatusCode::OK,
+        StatusCode::NO_CONTENT,
+        StatusCode::NOT_FOUND,
+    ] {
+        assert_eq!(filter.filter(response(status)).unwrap().status(), status);
+    }
+
+    let error = filter
+        .filter(response(StatusCode::TOO_MANY_REQUESTS))
+        .unwrap_err();
+    let mut expected_headers = http::HeaderMap::new();
+    expected_headers.insert("retry-after", http::HeaderValue::from_static("10"));
+    assert_eq!(
+        error,
+        HttpStatusError {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            headers: expected_headers,
+            body: b"body".to_vec(),
+        }
+    );
+    assert_matches!(
+        FilterStatusCode::new().filter(response(StatusCode::NOT_FOUND)),
+        Err(HttpStatusError {
+            status: StatusCode::NOT_FOUND,
+            ..
+        })
+    );
+}
+
 #[tokio::test]
 async fn should_convert_both_request_and_responses() {
     async fn serialize_request_and_add_header(
@@ -286,3 +468,1241 @@ where
         .expect("BUG: unexpected error type")
         .clone()
 }
+
+mod header_response_extension {
+    use crate::http::{HeaderResponseExtension, InvalidHeaderError, RetryAfter};
+    use std::time::Duration;
+
+    #[test]
+    fn should_parse_retry_after() {
+        assert_eq!(
+            response("retry-after", "120").retry_after(),
+            Ok(Some(RetryAfter::Delay(Duration::from_secs(120))))
+        );
+
+        let date = response("retry-after", "Sun, 06 Nov 1994 08:49:37 GMT")
+            .retry_after()
+            .unwrap()
+            .unwrap();
+        assert_eq!(date, RetryAfter::Date(Duration::from_secs(784_111_777)));
+        assert_eq!(
+            date.delay(Duration::from_secs(784_111_700)),
+            Duration::from_secs(77)
+        );
+        assert_eq!(date.delay(Duration::from_secs(784_111_800)), Duration::ZERO);
+
+        assert_eq!(
+            response("retry-after", "2000-02-29T00:00:00Z").retry_after(),
+            Err(InvalidHeaderError {
+                name: http::header::RETRY_AFTER,
+                reason: "expected a number of seconds or an HTTP date".to_string()
+            })
+        );
+        assert_eq!(response("other", "120").retry_after(), Ok(None));
+    }
+
+    #[test]
+    fn should_parse_leap_day() {
+        assert_eq!(
+            response("retry-after", "Tue, 29 Feb 2000 00:00:00 GMT").retry_after(),
+            Ok(Some(RetryAfter::Date(Duration::from_secs(951_782_400))))
+        );
+    }
+
+    #[test]
+    fn should_parse_content_length() {
+        assert_eq!(
+            response("content-length", "1024").content_length(),
+            Ok(Some(1024))
+        );
+        assert!(response("content-length", "-1").content_length().is_err());
+        assert_eq!(response("other", "1024").content_length(), Ok(None));
+    }
+
+    #[test]
+    fn should_parse_content_type() {
+        let media_type = response(
+            "content-type",
+            "Application/JSON; charset=\"utf-8\"; profile=rpc",
+        )
+        .content_type()
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(media_type.type_(), "application");
+        assert_eq!(media_type.subtype(), "json");
+        assert_eq!(media_type.essence(), "application/json");
+        assert_eq!(media_type.charset(), Some("utf-8"));
+        assert_eq!(media_type.parameter("PROFILE"), Some("rpc"));
+        assert_eq!(
+            media_type.to_string(),
+            "application/json; charset=utf-8; profile=rpc"
+        );
+
+        for invalid in ["json", "application/", "text/html; charset"] {
+            assert!(
+                response("content-type", invalid).content_type().is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    fn response(name: &str, value: &str) -> http::Response<()> {
+        http::Response::builder()
+            .header(name, value)
+            .body(())
+            .unwrap()
+    }
+}
+
+mod url_policy {
+    use crate::convert::Filter;
+    use crate::http::{HttpRequest, UrlPolicy, UrlPolicyError};
+
+    fn request(url: &str) -> HttpRequest {
+        http::Request::get(url).body(vec![]).unwrap()
+    }
+
+    fn check(policy: &UrlPolicy, url: &str) -> Result<(), UrlPolicyError> {
+        policy.clone().filter(request(url)).map(|_| ())
+    }
+
+    #[test]
+    fn should_require_https_by_default() {
+        let policy = UrlPolicy::new();
+
+        assert_eq!(check(&policy, "https://example.com/path"), Ok(()));
+        assert_eq!(
+            check(&policy, "http://example.com/path"),
+            Err(UrlPolicyError::HttpsRequired {
+                url: "http://example.com/path".to_string()
+            })
+        );
+        assert_eq!(
+            check(&policy.require_https(false), "http://example.com/path"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn should_reject_too_long_url() {
+        let policy = UrlPolicy::new().max_url_bytes(25);
+
+        assert_eq!(check(&policy, "https://example.com/12345"), Ok(()));
+        assert_eq!(
+            check(&policy, "https://example.com/123456"),
+            Err(UrlPolicyError::UrlTooLong {
+                length: 26,
+                limit: 25
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_url_without_host() {
+        assert_eq!(
+            check(&UrlPolicy::new().require_https(false), "/path"),
+            Err(UrlPolicyError::MissingHost {
+                url: "/path".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn should_only_accept_allowed_hosts() {
+        let policy = UrlPolicy::new().allow_hosts(["Example.com", "*.infura.io"]);
+
+        for url in [
+            "https://example.com",
+            "https://EXAMPLE.com/path",
+            "https://mainnet.infura.io/v3",
+            "https://a.b.infura.io",
+            "https://example.com./path",
+            "https://mainnet.infura.io./v3",
+        ] {
+            assert_eq!(check(&policy, url), Ok(()), "{url}");
+        }
+        for (url, host) in [
+            ("https://api.example.com", "api.example.com"),
+            ("https://infura.io", "infura.io"),
+            ("https://evilinfura.io", "evilinfura.io"),
+            ("https://other.org", "other.org"),
+            ("https://other.org.", "other.org"),
+        ] {
+            assert_eq!(
+                check(&policy, url),
+                Err(UrlPolicyError::HostNotAllowed {
+                    host: host.to_string()
+                }),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_denied_hosts_even_if_allowed() {
+        let policy = UrlPolicy::new()
+            .allow_hosts(["*.example.com"])
+            .deny_hosts(["internal.example.com", "localhost"]);
+
+        assert_eq!(check(&policy, "https://api.example.com"), Ok(()));
+        assert_eq!(
+            check(&policy, "https://internal.example.com"),
+            Err(UrlPolicyError::HostNotAllowed {
+                host: "internal.example.com".to_string()
+            })
+        );
+        assert_eq!(
+            check(&policy, "https://internal.example.com./path"),
+            Err(UrlPolicyError::HostNotAllowed {
+                host: "internal.example.com".to_string()
+            })
+        );
+        assert_eq!(
+            check(
+                &UrlPolicy::new().deny_hosts(["localhost"]),
+                "https://localhost:8080"
+            ),
+            Err(UrlPolicyError::HostNotAllowed {
+                host: "localhost".to_string()
+            })
+        );
+    }
+}
+
+mod canonicalize_headers {
+    use crate::http::CanonicalizeHeaders;
+    use http::{HeaderMap, HeaderName, HeaderValue};
+
+    fn headers(entries: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in entries {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                HeaderValue::from_static(value),
+            );
+        }
+        headers
+    }
+
+    fn entries(headers: &HeaderMap) -> Vec<(&str, &str)> {
+        headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_str().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn should_merge_repeated_headers_and_keep_order() {
+        let canonicalized = CanonicalizeHeaders::new().canonicalize(headers(&[
+            ("X-Custom", "1"),
+            ("Accept", "application/json"),
+            ("x-custom", "2"),
+            ("Cookie", "a=1"),
+            ("cookie", "b=2"),
+        ]));
+
+        assert_eq!(
+            entries(&canonicalized),
+            vec![
+                ("x-custom", "1, 2"),
+                ("accept", "application/json"),
+                ("cookie", "a=1; b=2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_sort_headers_by_name() {
+        let canonicalized = CanonicalizeHeaders::new().sorted().canonicalize(headers(&[
+            ("x-b", "1"),
+            ("content-type", "application/json"),
+            ("x-a", "2"),
+            ("x-b", "3"),
+        ]));
+
+        assert_eq!(
+            entries(&canonicalized),
+            vec![
+                ("content-type", "application/json"),
+                ("x-a", "2"),
+                ("x-b", "1, 3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_produce_same_headers_regardless_of_insertion_order() {
+        let canonicalize = CanonicalizeHeaders::new().sorted();
+        let first = canonicalize.canonicalize(headers(&[("b", "1"), ("a", "2")]));
+        let second = canonicalize.canonicalize(headers(&[("a", "2"), ("b", "1")]));
+
+        assert_eq!(entries(&first), entries(&second));
+    }
+
+    #[test]
+    fn should_preserve_sensitive_flag() {
+        let mut sensitive = HeaderValue::from_static("secret");
+        sensitive.set_sensitive(true);
+        let mut headers = headers(&[("authorization", "public")]);
+        headers.append(http::header::AUTHORIZATION, sensitive);
+
+        let canonicalized = CanonicalizeHeaders::new().canonicalize(headers);
+
+        assert!(canonicalized
+            .get(http::header::AUTHORIZATION)
+            .unwrap()
+            .is_sensitive());
+    }
+}
+
+mod query_params {
+    use crate::http::{QueryParams, QueryRequestExtension};
+
+    #[test]
+    fn should_percent_encode_keys_and_values() {
+        let params = QueryParams::new()
+            .with_param("q", "a b&c=d/é")
+            .with_param("key with space", "-._~")
+            .with_param("empty", "");
+
+        assert_eq!(
+            params.to_string(),
+            "q=a%20b%26c%3Dd%2F%C3%A9&key%20with%20space=-._~&empty="
+        );
+    }
+
+    #[test]
+    fn should_support_repeated_keys_and_typed_values() {
+        let params: QueryParams = [("id", 1), ("id", 2), ("limit", 100)].into_iter().collect();
+        let params = params.with_param("verbose", true);
+
+        assert_eq!(params.to_string(), "id=1&id=2&limit=100&verbose=true");
+        assert_eq!(params.get_all("id").collect::<Vec<_>>(), vec!["1", "2"]);
+        assert_eq!(params.len(), 4);
+    }
+
+    #[test]
+    fn should_add_query_to_request() {
+        for (url, expected_url) in [
+            (
+                "https://example.com",
+                "https://example.com/?symbol=ICP%2FUSDT&limit=5",
+            ),
+            (
+                "https://example.com/path",
+                "https://example.com/path?symbol=ICP%2FUSDT&limit=5",
+            ),
+            (
+                "https://example.com/path?",
+                "https://example.com/path?symbol=ICP%2FUSDT&limit=5",
+            ),
+            (
+                "https://example.com/path?a=1",
+                "https://example.com/path?a=1&symbol=ICP%2FUSDT&limit=5",
+            ),
+        ] {
+            let request = http::Request::get(url)
+                .query(&[("symbol", "ICP/USDT")])
+                .query(QueryParams::new().with_param("limit", 5))
+                .body(())
+                .unwrap();
+
+            assert_eq!(request.uri().to_string(), expected_url, "{url}");
+        }
+    }
+
+    #[test]
+    fn should_leave_url_unchanged_without_params() {
+        let request = http::Request::get("https://example.com/path?a=1")
+            .query(QueryParams::new())
+            .body(())
+            .unwrap();
+
+        assert_eq!(request.uri().to_string(), "https://example.com/path?a=1");
+    }
+
+    #[test]
+    fn should_keep_builder_error() {
+        let result = http::Request::get("not a valid url")
+            .query(&[("a", "1")])
+            .body(());
+
+        assert!(result.is_err());
+    }
+}
+
+mod request_builder {
+    use crate::http::{RequestBuilder, RequestBuilderError};
+    use crate::{
+        IsReplicatedRequestExtension, MaxResponseBytesRequestExtension,
+        TransformContextRequestExtension,
+    };
+    use candid::Principal;
+    use ic_cdk_management_canister::{TransformContext, TransformFunc};
+
+    #[test]
+    fn should_build_request() {
+        let transform_context = TransformContext {
+            function: TransformFunc::new(Principal::management_canister(), "transform".to_string()),
+            context: vec![42],
+        };
+
+        let request = RequestBuilder::get("https://example.com/path?a=1")
+            .query("b", "x y")
+            .query_params(&[("c", 3)])
+            .header("Accept", "application/json")
+            .header("X-Custom", "1")
+            .header("X-Custom", "2")
+            .max_response_bytes(1_000)
+            .transform_context(transform_context.clone())
+            .replicated(false)
+            .body(vec![1_u8])
+            .unwrap();
+
+        assert_eq!(request.method(), http::Method::GET);
+        assert_eq!(request.uri(), "https://example.com/path?a=1&b=x%20y&c=3");
+        assert_eq!(request.headers()["accept"], "application/json");
+        assert_eq!(
+            request
+                .headers()
+                .get_all("x-custom")
+                .iter()
+                .collect::<Vec<_>>(),
+            vec!["1", "2"]
+        );
+        assert_eq!(request.get_max_response_bytes(), Some(1_000));
+        assert_eq!(request.get_transform_context(), Some(&transform_context));
+        assert_eq!(request.get_is_replicated(), Some(false));
+        assert_eq!(request.body(), &vec![1_u8]);
+    }
+
+    #[test]
+    fn should_set_json_content_type_unless_present() {
+        let request = RequestBuilder::post("https://example.com")
+            .json("body")
+            .unwrap();
+        assert_eq!(request.headers()["content-type"], "application/json");
+
+        let request = RequestBuilder::post("https://example.com")
+            .header("Content-Type", "application/json-rpc")
+            .json("body")
+            .unwrap();
+        assert_eq!(request.headers()["content-type"], "application/json-rpc");
+    }
+
+    #[test]
+    fn should_reject_unsupported_method() {
+        let error = RequestBuilder::new(http::Method::PUT, "https://example.com")
+            .body(())
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            RequestBuilderError::UnsupportedHttpMethod {
+                method: http::Method::PUT,
+                supported: vec![http::Method::GET, http::Method::HEAD, http::Method::POST],
+            }
+        );
+    }
+
+    #[test]
+    fn should_report_first_error() {
+        let error = RequestBuilder::get("https://example.com")
+            .header("Invalid Name", "value")
+            .max_response_bytes(2_000_001)
+            .body(())
+            .unwrap_err();
+        assert_eq!(
+            error,
+            RequestBuilderError::InvalidHeader {
+                name: "Invalid Name".to_string(),
+                reason: "invalid HTTP header name".to_string(),
+            }
+        );
+
+        let error = RequestBuilder::get("https://example.com")
+            .max_response_bytes(2_000_001)
+            .header("Invalid Name", "value")
+            .body(())
+            .unwrap_err();
+        assert_eq!(
+            error,
+            RequestBuilderError::MaxResponseBytesTooLarge {
+                value: 2_000_001,
+                limit: 2_000_000,
+            }
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_url() {
+        let error = RequestBuilder::get("not a url").body(()).unwrap_err();
+
+        assert!(matches!(error, RequestBuilderError::InvalidUrl { url, .. } if url == "not a url"));
+    }
+}
+
+mod auth {
+    use crate::http::{AuthorizationError, BasicAuthLayer, BearerAuthLayer, HttpRequest};
+    use std::sync::{Arc, Mutex};
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    async fn echo_request(request: HttpRequest) -> Result<HttpRequest, BoxError> {
+        Ok(request)
+    }
+
+    fn request_with_authorization() -> HttpRequest {
+        http::Request::get("https://example.com")
+            .header("Authorization", "Bearer old")
+            .body(vec![])
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_set_bearer_token_from_provider() {
+        let token = Arc::new(Mutex::new("first".to_string()));
+        let provider = {
+            let token = token.clone();
+            move || token.lock().unwrap().clone()
+        };
+        let mut service = ServiceBuilder::new()
+            .layer(BearerAuthLayer::new(provider))
+            .service_fn(echo_request);
+
+        let request = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request_with_authorization())
+            .await
+            .unwrap();
+        let header = &request.headers()["authorization"];
+        assert_eq!(header, "Bearer first");
+        assert!(header.is_sensitive());
+        assert_eq!(request.headers().get_all("authorization").iter().count(), 1);
+
+        *token.lock().unwrap() = "second".to_string();
+        let request = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request_with_authorization())
+            .await
+            .unwrap();
+        assert_eq!(request.headers()["authorization"], "Bearer second");
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_bearer_token() {
+        let mut service = ServiceBuilder::new()
+            .layer(BearerAuthLayer::new(|| "invalid\ntoken".to_string()))
+            .service_fn(echo_request);
+
+        let error = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request_with_authorization())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<AuthorizationError>(),
+            Some(AuthorizationError::InvalidToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_set_basic_auth() {
+        let mut service = ServiceBuilder::new()
+            .layer(BasicAuthLayer::new("user", "p@ss:word"))
+            .service_fn(echo_request);
+
+        let request = service
+            .ready()
+            .await
+            .unwrap()
+            .call(request_with_authorization())
+            .await
+            .unwrap();
+
+        let header = &request.headers()["authorization"];
+        assert_eq!(header, "Basic dXNlcjpwQHNzOndvcmQ=");
+        assert!(header.is_sensitive());
+    }
+}
+
+mod hmac_signature {
+    use crate::convert::Filter;
+    use crate::http::{
+        Canonicalization, HmacSignatureLayer, HmacSigner, HttpRequest, Placement, SignatureEncoding,
+    };
+    use http::HeaderName;
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    fn post(url: &str, body: &[u8]) -> HttpRequest {
+        http::Request::post(url).body(body.to_vec()).unwrap()
+    }
+
+    fn signature_header() -> Placement {
+        Placement::Header(HeaderName::from_static("x-signature"))
+    }
+
+    #[test]
+    fn should_match_rfc_4231_test_vectors() {
+        // Test cases 2 and 6 of https://www.rfc-editor.org/rfc/rfc4231#section-4
+        for (key, data, expected) in [
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?".to_vec(),
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                vec![0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+        ] {
+            let mut signer = HmacSigner::new(key, Canonicalization::Body, signature_header());
+
+            let request = signer.filter(post("https://example.com", &data)).unwrap();
+
+            assert_eq!(request.headers()["x-signature"], expected);
+            assert_eq!(request.body(), &data);
+        }
+    }
+
+    #[test]
+    fn should_sign_query_and_body_with_timestamp_header() {
+        let mut signer = HmacSigner::new("key", Canonicalization::QueryAndBody, signature_header())
+            .encoding(SignatureEncoding::Base64)
+            .timestamp(Placement::Header(HeaderName::from_static("x-timestamp")))
+            .clock(|| 1_700_000_000_000);
+        let mut expected = HmacSigner::new("key", Canonicalization::Body, signature_header())
+            .encoding(SignatureEncoding::Base64);
+
+        let request = signer
+            .filter(post("https://example.com/order?a=1&b=2", b"{}"))
+            .unwrap();
+        let expected = expected
+            .filter(post("https://example.com", b"a=1&b=2{}"))
+            .unwrap();
+
+        assert_eq!(request.headers()["x-timestamp"], "1700000000000");
+        assert_eq!(
+            request.headers()["x-signature"],
+            expected.headers()["x-signature"]
+        );
+        assert_eq!(request.uri(), "https://example.com/order?a=1&b=2");
+    }
+
+    #[test]
+    fn should_sign_with_custom_canonicalization() {
+        let canonicalization = Canonicalization::custom(|request: &HttpRequest| {
+            let timestamp = request.headers()["x-timestamp"].as_bytes();
+            [
+                timestamp,
+                request.method().as_str().as_bytes(),
+                request.uri().path().as_bytes(),
+            ]
+            .concat()
+        });
+        let mut signer = HmacSigner::new("key", canonicalization, signature_header())
+            .timestamp(Placement::Header(HeaderName::from_static("x-timestamp")))
+            .clock(|| 42);
+        let mut expected = HmacSigner::new("key", Canonicalization::Body, signature_header());
+
+        let request = signer
+            .filter(post("https://example.com/orders", b""))
+            .unwrap();
+        let expected = expected
+            .filter(post("https://example.com", b"42POST/orders"))
+            .unwrap();
+
+        assert_eq!(
+            request.headers()["x-signature"],
+            expected.headers()["x-signature"]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_sign_requests_with_layer() {
+        let signer = HmacSigner::new(
+            "key",
+            Canonicalization::Query,
+            Placement::QueryParam("signature".to_string()),
+        )
+        .timestamp(Placement::QueryParam("timestamp".to_string()))
+        .clock(|| 1);
+        let mut service = ServiceBuilder::new()
+            .layer(HmacSignatureLayer::new(signer))
+            .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+
+        let request = service
+            .ready()
+            .await
+            .unwrap()
+            .call(post("https://example.com/api?symbol=ICP", b""))
+            .await
+            .unwrap();
+
+        let query = request.uri().query().unwrap();
+        assert!(query.starts_with("symbol=ICP&timestamp=1&signature="));
+        let signature = query.rsplit_once('=').unwrap().1;
+        assert_eq!(signature.len(), 64);
+        assert!(signature.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+}
+
+mod api_key_rotation {
+    use crate::http::{
+        ApiKeyError, ApiKeyRotation, ApiKeyRotationLayer, HttpRequest, HttpResponse, Placement,
+    };
+    use http::{HeaderName, StatusCode};
+    use std::{cell::Cell, rc::Rc, time::Duration};
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn header() -> Placement {
+        Placement::Header(HeaderName::from_static("x-api-key"))
+    }
+
+    /// Responds with the given status for the given key and `200 OK` otherwise.
+    fn service_rejecting(
+        rotation: &ApiKeyRotation,
+        key: &'static str,
+        status: StatusCode,
+    ) -> impl Service<HttpRequest, Response = HttpResponse, Error = BoxError> {
+        ServiceBuilder::new()
+            .layer(ApiKeyRotationLayer::new(rotation.clone()))
+            .service_fn(move |request: HttpRequest| async move {
+                let used_key = request.headers()["x-api-key"].to_str()?.to_string();
+                let status = if used_key == key {
+                    status
+                } else {
+                    StatusCode::OK
+                };
+                Ok::<_, BoxError>(
+                    http::Response::builder()
+                        .status(status)
+                        .body(used_key.into_bytes())?,
+                )
+            })
+    }
+
+    async fn call(
+        service: &mut impl Service<HttpRequest, Response = HttpResponse, Error = BoxError>,
+    ) -> Result<HttpResponse, BoxError> {
+        let request = http::Request::get("https://example.com")
+            .body(vec![])
+            .unwrap();
+        service.ready().await?.call(request).await
+    }
+
+    #[tokio::test]
+    async fn should_keep_using_key_until_exhausted() {
+        let rotation = ApiKeyRotation::new(["key-1", "key-2"], header()).clock(|| 0);
+        let mut service = service_rejecting(&rotation, "key-2", StatusCode::TOO_MANY_REQUESTS);
+
+        for _ in 0..3 {
+            let response = call(&mut service).await.unwrap();
+            assert_eq!(response.body(), b"key-1");
+        }
+        assert_eq!(rotation.available_keys(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_rotate_keys_and_cool_down() {
+        let now = Rc::new(Cell::new(0));
+        let rotation = ApiKeyRotation::new(["key-1", "key-2"], header())
+            .cool_down(Duration::from_secs(10))
+            .exhausted_on_status([StatusCode::FORBIDDEN])
+            .clock({
+                let now = now.clone();
+                move || now.get()
+            });
+        let mut service = service_rejecting(&rotation, "key-1", StatusCode::FORBIDDEN);
+
+        let response = call(&mut service).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(rotation.available_keys(), 1);
+
+        let response = call(&mut service).await.unwrap();
+        assert_eq!(response.body(), b"key-2");
+
+        now.set(10 * SECOND);
+        assert_eq!(rotation.available_keys(), 2);
+        let response = call(&mut service).await.unwrap();
+        assert_eq!(response.body(), b"key-2");
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_all_keys_exhausted() {
+        let now = Rc::new(Cell::new(0));
+        let rotation = ApiKeyRotation::new(["key-1"], header())
+            .cool_down(Duration::from_secs(10))
+            .clock({
+                let now = now.clone();
+                move || now.get()
+            });
+        let mut service = service_rejecting(&rotation, "key-1", StatusCode::TOO_MANY_REQUESTS);
+
+        let response = call(&mut service).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        now.set(5 * SECOND);
+        let error = call(&mut service).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ApiKeyError>(),
+            Some(&ApiKeyError::AllKeysExhausted {
+                available_at: 10 * SECOND
+            })
+        );
+
+        let rotation = ApiKeyRotation::new(Vec::<String>::new(), header()).clock(|| 0);
+        let mut service = service_rejecting(&rotation, "key-1", StatusCode::TOO_MANY_REQUESTS);
+        let error = call(&mut service).await.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ApiKeyError>(),
+            Some(&ApiKeyError::NoKeys)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_add_key_as_query_parameter() {
+        let rotation = ApiKeyRotation::new(["s3cr&t"], Placement::QueryParam("apikey".to_string()))
+            .clock(|| 0);
+        let mut service = ServiceBuilder::new()
+            .layer(ApiKeyRotationLayer::new(rotation))
+            .service_fn(|request: HttpRequest| async move {
+                Ok::<_, BoxError>(http::Response::new(request.uri().to_string().into_bytes()))
+            });
+
+        let request = http::Request::get("https://example.com/api?module=account")
+            .body(vec![])
+            .unwrap();
+        let response = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(
+            response.body(),
+            b"https://example.com/api?module=account&apikey=s3cr%26t"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn should_rotate_on_json_rpc_error_codes() {
+        let rotation = ApiKeyRotation::new(["key-1", "key-2"], header())
+            .exhausted_on_json_rpc_error([-32005])
+            .clock(|| 0);
+        let mut service = ServiceBuilder::new()
+            .layer(ApiKeyRotationLayer::new(rotation.clone()))
+            .service_fn(|request: HttpRequest| async move {
+                let body = match request.headers()["x-api-key"].to_str()? {
+                    "key-1" => {
+                        r#"[{"jsonrpc":"2.0","id":1,"result":"0x1"},{"jsonrpc":"2.0","id":2,"error":{"code":-32005,"message":"limit exceeded"}}]"#
+                    }
+                    _ => r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"other"}}"#,
+                };
+                Ok::<_, BoxError>(http::Response::new(body.as_bytes().to_vec()))
+            });
+
+        call(&mut service).await.unwrap();
+        assert_eq!(rotation.available_keys(), 1);
+
+        call(&mut service).await.unwrap();
+        assert_eq!(rotation.available_keys(), 1);
+    }
+}
+
+mod ecdsa_signature {
+    use crate::http::{
+        Canonicalization, EcdsaSignatureError, EcdsaSignatureLayer, EcdsaSigner, HttpRequest,
+        Placement, SignatureEncoding,
+    };
+    use crate::IcError;
+    use ic_cdk_management_canister::{
+        EcdsaCurve, EcdsaKeyId, SignWithEcdsaArgs, SignWithEcdsaResult,
+    };
+    use ic_error_types::RejectCode;
+    use sha2::{Digest, Sha256};
+    use std::{cell::RefCell, rc::Rc};
+    use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+    fn key_id() -> EcdsaKeyId {
+        EcdsaKeyId {
+            curve: EcdsaCurve::Secp256k1,
+            name: "test_key_1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_sign_hash_of_canonical_request() {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let client = {
+            let calls = calls.clone();
+            tower::service_fn(move |args: SignWithEcdsaArgs| {
+                calls.borrow_mut().push(args);
+                async move {
+                    Ok::<_, BoxError>(SignWithEcdsaResult {
+                        signature: vec![1, 2, 3],
+                    })
+                }
+            })
+        };
+        let signer = EcdsaSigner::new(
+            key_id(),
+            Canonicalization::QueryAndBody,
+            Placement::QueryParam("sig".to_string()),
+        )
+        .derivation_path(vec![b"api".to_vec()])
+        .encoding(SignatureEncoding::Base64);
+        let mut service = ServiceBuilder::new()
+            .layer(EcdsaSignatureLayer::with_client(signer, client))
+            .service_fn(|request: HttpRequest| async move { Ok::<_, BoxError>(request) });
+
+        let request = http::Request::post("https://example.com/api?a=1")
+            .body(b"{}".to_vec())
+            .unwrap();
+        let request = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(request.uri(), "https://example.com/api?a=1&sig=AQID");
+        assert_eq!(
+            calls.borrow().as_slice(),
+            &[SignWithEcdsaArgs {
+                message_hash: Sha256::digest(b"a=1{}").to_vec(),
+                derivation_path: vec![b"api".to_vec()],
+                key_id: key_id(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_call_inner_service_when_signing_fails() {
+        let error = EcdsaSignatureError::Ic(IcError::CallRejected {
+            code: RejectCode::CanisterError,
+            message: "insufficient cycles".to_string(),
+        });
+        let client = {
+            let error = error.clone();
+            tower::service_fn(move |_args: SignWithEcdsaArgs| {
+                let error = error.clone();
+                async move { Err::<SignWithEcdsaResult, _>(error) }
+            })
+        };
+        let signer = EcdsaSigner::new(
+            key_id(),
+            Canonicalization::Body,
+            Placement::Header(http::HeaderName::from_static("x-signature")),
+        );
+        let mut service = ServiceBuilder::new()
+            .layer(EcdsaSignatureLayer::with_client(signer, client))
+            .service_fn(|_request: HttpRequest| async move {
+                Err::<(), BoxError>("inner service must not be called".into())
+            });
+
+        let request = http::Request::post("https://example.com")
+            .body(vec![])
+            .unwrap();
+        let result = service.ready().await.unwrap().call(request).await;
+
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<EcdsaSignatureError>(),
+            Some(&error)
+        );
+    }
+}
+
+mod idempotency_key {
+    use crate::convert::Filter;
+    use crate::http::{
+        AddIdempotencyKey, HttpRequest, IdempotencyKeyError, IdempotencyKeyRequestExtension,
+    };
+    use http::{HeaderName, Method};
+
+    fn post(url: &str, body: &[u8]) -> HttpRequest {
+        http::Request::post(url).body(body.to_vec()).unwrap()
+    }
+
+    fn key(request: &HttpRequest) -> Option<&str> {
+        request
+            .headers()
+            .get("idempotency-key")
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[test]
+    fn should_derive_deterministic_key() {
+        let mut add_key = AddIdempotencyKey::new();
+
+        let first = add_key
+            .filter(post("https://example.com/pay", b"1"))
+            .unwrap();
+        let retry = add_key
+            .filter(post("https://example.com/pay", b"1"))
+            .unwrap();
+        let other_body = add_key
+            .filter(post("https://example.com/pay", b"2"))
+            .unwrap();
+        let other_url = add_key
+            .filter(post("https://example.com/refund", b"1"))
+            .unwrap();
+
+        assert_eq!(key(&first).unwrap().len(), 64);
+        assert_eq!(key(&first), key(&retry));
+        assert_ne!(key(&first), key(&other_body));
+        assert_ne!(key(&first), key(&other_url));
+    }
+
+    #[test]
+    fn should_use_caller_supplied_key_and_keep_existing_header() {
+        let mut add_key = AddIdempotencyKey::new();
+
+        let request = http::Request::post("https://example.com")
+            .idempotency_key("order-1")
+            .body(vec![])
+            .unwrap();
+        assert_eq!(request.get_idempotency_key(), Some("order-1"));
+        assert_eq!(key(&add_key.filter(request).unwrap()), Some("order-1"));
+
+        let request = http::Request::post("https://example.com")
+            .header("Idempotency-Key", "existing")
+            .idempotency_key("order-1")
+            .body(vec![])
+            .unwrap();
+        assert_eq!(key(&add_key.filter(request).unwrap()), Some("existing"));
+
+        let request = http::Request::post("https://example.com")
+            .idempotency_key("invalid\n")
+            .body(vec![])
+            .unwrap();
+        assert_eq!(
+            add_key.filter(request).unwrap_err(),
+            IdempotencyKeyError::InvalidKey {
+                key: "invalid\n".to_string(),
+                reason: "failed to parse header value".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn should_only_add_key_for_configured_methods() {
+        let mut add_key = AddIdempotencyKey::new();
+        let request = http::Request::get("https://example.com")
+            .body(vec![])
+            .unwrap();
+        assert_eq!(key(&add_key.filter(request).unwrap()), None);
+
+        let mut add_key = AddIdempotencyKey::new()
+            .methods([Method::GET])
+            .header(HeaderName::from_static("x-idempotency-key"));
+        let request = http::Request::get("https://example.com")
+            .body(vec![])
+            .unwrap();
+        let request = add_key.filter(request).unwrap();
+        assert_eq!(
+            request.headers()["x-idempotency-key"],
+            AddIdempotencyKey::derive_key(&request)
+        );
+    }
+}
+
+mod correlation_id {
+    use crate::convert::Convert;
+    use crate::convert::Filter;
+    use crate::http::{
+        AddCorrelationId, CorrelationIdError, CorrelationIdRequestExtension, HttpRequestConverter,
+        X_REQUEST_ID,
+    };
+    use assert_matches::assert_matches;
+    use http::HeaderName;
+
+    #[test]
+    fn should_generate_distinct_ids_from_seed() {
+        let mut add_id = AddCorrelationId::with_seed([1; 32]);
+
+        let first = add_id
+            .filter(
+                http::Request::get("https://example.com")
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .unwrap();
+        let second = add_id
+            .filter(
+                http::Request::get("https://example.com")
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let first_id = first.get_correlation_id().unwrap();
+        assert_eq!(first.headers()[X_REQUEST_ID], first_id);
+        assert_eq!(first_id.len(), 32);
+        assert_ne!(first_id, second.get_correlation_id().unwrap());
+    }
+
+    #[test]
+    fn should_keep_existing_header() {
+        let mut add_id = AddCorrelationId::with_generator(|| "generated".to_string())
+            .header(HeaderName::from_static("x-correlation-id"));
+
+        let request = http::Request::get("https://example.com")
+            .header("X-Correlation-Id", "upstream")
+            .body(vec![])
+            .unwrap();
+        let request = add_id.filter(request).unwrap();
+        assert_eq!(request.get_correlation_id(), Some("upstream"));
+
+        let request = http::Request::get("https://example.com")
+            .body(vec![])
+            .unwrap();
+        let request = add_id.filter(request).unwrap();
+        assert_eq!(request.get_correlation_id(), Some("generated"));
+        assert_eq!(request.headers()["x-correlation-id"], "generated");
+        assert!(!request.headers().contains_key(X_REQUEST_ID));
+    }
+
+    #[test]
+    fn should_reject_invalid_generated_id() {
+        let mut add_id = AddCorrelationId::with_generator(|| "line\nbreak".to_string());
+
+        let result = add_id.filter(
+            http::Request::get("https://example.com")
+                .body(vec![])
+                .unwrap(),
+        );
+
+        assert_matches!(
+            result,
+            Err(CorrelationIdError::InvalidId { id, .. }) if id == "line\nbreak"
+        );
+    }
+
+    #[test]
+    fn should_read_correlation_id_from_ic_request() {
+        let mut add_id = AddCorrelationId::with_generator(|| "42".to_string());
+        let request = add_id
+            .filter(
+                http::Request::get("https://example.com")
+                    .body(vec![])
+                    .unwrap(),
+            )
+            .unwrap();
+
+        let ic_request = HttpRequestConverter.try_convert(request).unwrap();
+
+        assert_eq!(ic_request.get_correlation_id(), Some("42"));
+    }
+}
+
+mod body_checksum {
+    use crate::convert::{CreateResponseFilter, Filter};
+    use crate::http::{BodyChecksumError, BodyChecksumRequestExtension, VerifyBodyChecksum};
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use http::HeaderName;
+    use sha2::{Digest, Sha256};
+
+    const BODY: &[u8] = b"hello world";
+
+    fn response(checksum: Option<&str>) -> http::Response<Vec<u8>> {
+        let mut builder = http::Response::builder();
+        if let Some(checksum) = checksum {
+            builder = builder.header("x-amz-checksum-sha256", checksum);
+        }
+        builder.body(BODY.to_vec()).unwrap()
+    }
+
+    fn verify(
+        verify: &VerifyBodyChecksum,
+        request: http::Request<Vec<u8>>,
+        response: http::Response<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, BodyChecksumError> {
+        verify.create_filter(&request).filter(response)
+    }
+
+    #[test]
+    fn should_verify_expected_digest_from_request() {
+        let digest: [u8; 32] = Sha256::digest(BODY).into();
+        let verifier = VerifyBodyChecksum::new();
+
+        let request = http::Request::get("https://example.com")
+            .expected_sha256(digest)
+            .body(vec![])
+            .unwrap();
+        assert!(verify(&verifier, request, response(None)).is_ok());
+
+        let request = http::Request::get("https://example.com")
+            .expected_sha256([0; 32])
+            .body(vec![])
+            .unwrap();
+        assert_eq!(
+            verify(&verifier, request, response(None)).unwrap_err(),
+            (BodyChecksumError::Mismatch {
+                expected: "00".repeat(32),
+                actual: "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+                    .to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_verify_expected_digest_from_header() {
+        let digest = Sha256::digest(BODY);
+        let hex: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+        let base64 = STANDARD.encode(digest);
+        let verifier =
+            VerifyBodyChecksum::new().header(HeaderName::from_static("x-amz-checksum-sha256"));
+        let request = || {
+            http::Request::get("https://example.com")
+                .body(vec![])
+                .unwrap()
+        };
+
+        assert!(verify(&verifier, request(), response(Some(&hex))).is_ok());
+        assert!(verify(&verifier, request(), response(Some(&base64))).is_ok());
+        assert!(verify(&verifier, request(), response(None)).is_ok());
+        assert!(matches!(
+            verify(
+                &verifier,
+                request(),
+                response(Some(&STANDARD.encode([1; 32])))
+            ),
+            Err(BodyChecksumError::Mismatch { .. })
+        ));
+        assert_eq!(
+            verify(&verifier, request(), response(Some("not-a-digest"))).unwrap_err(),
+            (BodyChecksumError::InvalidChecksumHeader {
+                header: "x-amz-checksum-sha256".to_string(),
+                value: "not-a-digest".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn should_prefer_digest_from_request_over_header() {
+        let verifier =
+            VerifyBodyChecksum::new().header(HeaderName::from_static("x-amz-checksum-sha256"));
+        let request = http::Request::get("https://example.com")
+            .expected_sha256(Sha256::digest(BODY).into())
+            .body(vec![])
+            .unwrap();
+
+        assert!(verify(&verifier, request, response(Some("not-a-digest"))).is_ok());
+    }
+
+    #[test]
+    fn should_require_checksum() {
+        let verifier = VerifyBodyChecksum::new().required();
+        let request = http::Request::get("https://example.com")
+            .body(vec![])
+            .unwrap();
+
+        assert_eq!(
+            verify(&verifier, request, response(None)).unwrap_err(),
+            (BodyChecksumError::MissingChecksum)
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Execute requests in the background and retrieve their results later.
+//!
+//! An update call sending a slow HTTPs outcall, e.g. with a large response, may submit
+//! the request to a [`JobManager`] and return the [`JobId`] immediately. The request is then
+//! executed in a spawned task and its result is either passed to a callback, or kept until
+//! it is polled by ID, e.g. from a later query call. To bound the memory used by results that
+//! are never polled, at most [`DEFAULT_MAX_COMPLETED`] results are kept by default, see
+//! [`JobManager::max_completed`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::job::{JobManager, JobStatus};
+//! use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc};
+//! use tower::BoxError;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! // In a canister, tasks are spawned with `ic_cdk::futures::spawn` by default.
+//! let spawned: Rc<RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>>> = Rc::default();
+//! let service = tower::service_fn(|url: &'static str| async move { Ok::<_, BoxError>(url.len()) });
+//! let jobs = JobManager::new(service).spawner({
+//!     let spawned = spawned.clone();
+//!     move |task| spawned.borrow_mut().push(task)
+//! });
+//!
+//! let job_id = jobs.submit("https://example.com");
+//! assert_eq!(jobs.status(job_id), Some(JobStatus::Pending));
+//!
+//! // Run the spawned tasks.
+//! futures_util::future::join_all(spawned.take()).await;
+//!
+//! assert_eq!(jobs.status(job_id), Some(JobStatus::Completed));
+//! assert_eq!(jobs.take_result(job_id).unwrap().unwrap(), 19);
+//! assert_eq!(jobs.status(job_id), None);
+//! # }
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+};
+use tower::{Service, ServiceExt};
+
+/// Default maximum number of completed results kept by a [`JobManager`],
+/// see [`JobManager::max_completed`].
+pub const DEFAULT_MAX_COMPLETED: usize = 1_000;
+
+/// Identifier of a request submitted to a [`JobManager`].
+///
+/// The identifier can be converted to and from a [`u64`], e.g. to be returned to the caller
+/// of a canister endpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct JobId(u64);
+
+impl From<u64> for JobId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<JobId> for u64 {
+    fn from(value: JobId) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Status of a job of a [`JobManager`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JobStatus {
+    /// The request is being executed.
+    Pending,
+    /// The result of the request is available with [`JobManager::take_result`].
+    Completed,
+}
+
+/// Task spawned by a [`JobManager`] to execute a request.
+pub type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+enum Job<Output> {
+    Pending,
+    Completed(Output),
+}
+
+type Jobs<S, Request> =
+    BTreeMap<JobId, Job<Result<<S as Service<Request>>::Response, <S as Service<Request>>::Error>>>;
+
+struct JobManagerState<S, Request>
+where
+    S: Service<Request>,
+{
+    next_id: u64,
+    jobs: Jobs<S, Request>,
+    /// IDs of the completed jobs whose result was not taken yet, from oldest to newest.
+    completed: VecDeque<JobId>,
+    max_completed: usize,
+}
+
+impl<S, Request> JobManagerState<S, Request>
+where
+    S: Service<Request>,
+{
+    fn complete(&mut self, id: JobId, result: Result<S::Response, S::Error>) {
+        // The result is discarded if the job was removed.
+        if let Some(job) = self.jobs.get_mut(&id) {
+            *job = Job::Completed(result);
+            self.completed.push_back(id);
+            let excess = self.completed.len().saturating_sub(self.max_completed);
+            for oldest in self.completed.drain(..excess) {
+                self.jobs.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Execute requests in the background, see the [module docs](crate::job).
+///
+/// Cloning a [`JobManager`] is cheap and all clones share the same jobs.
+pub struct JobManager<S, Request>
+where
+    S: Service<Request>,
+{
+    service: S,
+    spawner: Rc<dyn Fn(Task)>,
+    state: Rc<RefCell<JobManagerState<S, Request>>>,
+}
+
+impl<S, Request> JobManager<S, Request>
+where
+    S: Service<Request> + Clone + 'static,
+    Request: 'static,
+{
+    /// Create a new [`JobManager`] executing requests with the given service.
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            spawner: Rc::new(ic_cdk::futures::spawn),
+            state: Rc::new(RefCell::new(JobManagerState {
+                next_id: 0,
+                jobs: BTreeMap::new(),
+                completed: VecDeque::new(),
+                max_completed: DEFAULT_MAX_COMPLETED,
+            })),
+        }
+    }
+
+    /// Change how the tasks executing the requests are spawned.
+    ///
+    /// By default, tasks are spawned with [`ic_cdk::futures::spawn`].
+    pub fn spawner(mut self, spawner: impl Fn(Task) + 'static) -> Self {
+        self.spawner = Rc::new(spawner);
+        self
+    }
+
+    /// Set the maximum number of completed results kept until they are taken with
+    /// [`JobManager::take_result`].
+    ///
+    /// Once the limit is reached, the oldest completed result is discarded for each newly
+    /// completed job. By default, at most [`DEFAULT_MAX_COMPLETED`] results are kept.
+    pub fn max_completed(self, max_completed: usize) -> Self {
+        self.state.borrow_mut().max_completed = max_completed;
+        self
+    }
+
+    /// Execute the given request in the background and keep its result until it is taken
+    /// with [`JobManager::take_result`] or discarded, see [`JobManager::max_completed`].
+    pub fn submit(&self, request: Request) -> JobId {
+        let id = self.insert_pending();
+        let state = Rc::downgrade(&self.state);
+        self.spawn(request, move |result| {
+            // The result is discarded if the manager was dropped.
+            if let Some(state) = state.upgrade() {
+                state.borrow_mut().complete(id, result);
+            }
+        });
+        id
+    }
+
+    /// Execute the given request in the background and pass its result to the given callback.
+    ///
+    /// The job is removed once the callback is invoked.
+    pub fn submit_with_callback<C>(&self, request: Request, callback: C) -> JobId
+    where
+        C: FnOnce(JobId, Result<S::Response, S::Error>) + 'static,
+    {
+        let id = self.insert_pending();
+        let state = Rc::downgrade(&self.state);
+        self.spawn(request, move |result| {
+            if let Some(state) = state.upgrade() {
+                state.borrow_mut().jobs.remove(&id);
+            }
+            callback(id, result)
+        });
+        id
+    }
+
+    /// Returns the status of the job with the given ID, or `None` if there is no such job,
+    /// e.g. because its result was already taken or discarded.
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        self.state.borrow().jobs.get(&id).map(|job| match job {
+            Job::Pending => JobStatus::Pending,
+            Job::Completed(_) => JobStatus::Completed,
+        })
+    }
+
+    /// Removes the job with the given ID and returns its result, if it is completed.
+    pub fn take_result(&self, id: JobId) -> Option<Result<S::Response, S::Error>> {
+        let mut state = self.state.borrow_mut();
+        match state.jobs.remove(&id)? {
+            Job::Completed(result) => {
+                state.completed.retain(|completed| *completed != id);
+                Some(result)
+            }
+            Job::Pending => {
+                state.jobs.insert(id, Job::Pending);
+                None
+            }
+        }
+    }
+
+    /// Returns the number of jobs whose request is being executed.
+    pub fn pending(&self) -> usize {
+        self.state
+            .borrow()
+            .jobs
+            .values()
+            .filter(|job| matches!(job, Job::Pending))
+            .count()
+    }
+
+    fn insert_pending(&self) -> JobId {
+        let mut state = self.state.borrow_mut();
+        let id = JobId(state.next_id);
+        state.next_id += 1;
+        state.jobs.insert(id, Job::Pending);
+        id
+    }
+
+    fn spawn<F>(&self, request: Request, on_result: F)
+    where
+        F: FnOnce(Result<S::Response, S::Error>) + 'static,
+    {
+        let future = self.service.clone().oneshot(request);
+        (self.spawner)(Box::pin(async move { on_result(future.await) }));
+    }
+}
+
+impl<S, Request> Clone for JobManager<S, Request>
+where
+    S: Service<Request> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            spawner: self.spawner.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S, Request> fmt::Debug for JobManager<S, Request>
+where
+    S: Service<Request> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobManager")
+            .field("service", &self.service)
+            .field("jobs", &self.state.borrow().jobs.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,87 @@
+use crate::job::{JobId, JobManager, JobStatus, Task};
+use futures_util::future;
+use std::{cell::RefCell, rc::Rc};
+
+type Spawned = Rc<RefCell<Vec<Task>>>;
+
+fn manager() -> (
+    JobManager<impl tower::Service<u8, Response = u8, Error = String> + Clone, u8>,
+    Spawned,
+) {
+    let spawned = Spawned::default();
+    let service = tower::service_fn(|request: u8| async move {
+        if request == 0 {
+            Err("zero".to_string())
+        } else {
+            Ok(request)
+        }
+    });
+    let manager = JobManager::new(service).spawner({
+        let spawned = spawned.clone();
+        move |task| spawned.borrow_mut().push(task)
+    });
+    (manager, spawned)
+}
+
+#[tokio::test]
+async fn should_keep_results_until_taken() {
+    let (manager, spawned) = manager();
+
+    let ids: Vec<_> = [1, 0, 2].into_iter().map(|r| manager.submit(r)).collect();
+    assert_eq!(ids, vec![JobId::from(0), JobId::from(1), JobId::from(2)]);
+    assert_eq!(manager.pending(), 3);
+    assert_eq!(manager.take_result(ids[0]), None);
+    assert_eq!(manager.status(ids[0]), Some(JobStatus::Pending));
+
+    future::join_all(spawned.take()).await;
+
+    assert_eq!(manager.pending(), 0);
+    assert_eq!(manager.status(ids[1]), Some(JobStatus::Completed));
+    assert_eq!(manager.take_result(ids[1]), Some(Err("zero".to_string())));
+    assert_eq!(manager.take_result(ids[2]), Some(Ok(2)));
+    assert_eq!(manager.take_result(ids[2]), None);
+    assert_eq!(manager.status(ids[2]), None);
+    assert_eq!(manager.status(ids[0]), Some(JobStatus::Completed));
+}
+
+#[tokio::test]
+async fn should_invoke_callback() {
+    let (manager, spawned) = manager();
+    let results = Rc::new(RefCell::new(Vec::new()));
+
+    let id = manager.submit_with_callback(3, {
+        let results = results.clone();
+        move |id, result| results.borrow_mut().push((id, result))
+    });
+    assert_eq!(manager.status(id), Some(JobStatus::Pending));
+
+    future::join_all(spawned.take()).await;
+
+    assert_eq!(*results.borrow(), vec![(id, Ok(3))]);
+    assert_eq!(manager.status(id), None);
+}
+
+#[tokio::test]
+async fn should_discard_oldest_completed_results() {
+    let (manager, spawned) = manager();
+    let manager = manager.max_completed(2);
+
+    let ids: Vec<_> = [1, 2, 3].into_iter().map(|r| manager.submit(r)).collect();
+    let mut tasks = spawned.take();
+    // Complete the jobs in the order 2, 1, 3.
+    tasks.swap(0, 1);
+    for task in tasks {
+        task.await;
+    }
+
+    assert_eq!(manager.status(ids[1]), None);
+    assert_eq!(manager.take_result(ids[0]), Some(Ok(1)));
+    assert_eq!(manager.take_result(ids[2]), Some(Ok(3)));
+
+    let ids: Vec<_> = [4, 5].into_iter().map(|r| manager.submit(r)).collect();
+    assert_eq!(manager.pending(), 2);
+    future::join_all(spawned.take()).await;
+
+    assert_eq!(manager.take_result(ids[0]), Some(Ok(4)));
+    assert_eq!(manager.take_result(ids[1]), Some(Ok(5)));
+}
@@ -5,19 +5,42 @@
 #![forbid(unsafe_code)]
 #![forbid(missing_docs)]
 
+#[cfg(feature = "candid")]
+pub use client::CanisterProxyTransport;
+#[cfg(all(feature = "reqwest", not(target_arch = "wasm32")))]
+pub use client::ReqwestTransport;
 pub use client::{
-    CanisterReadyError, CanisterReadyLayer, CanisterReadyService, Client, HttpsOutcallError,
-    IcError, IsReplicatedRequestExtension, MaxResponseBytesRequestExtension,
-    TransformContextRequestExtension,
+    simulate, transport_fn, CancellationRequestExtension, CancellationToken, Cancelled,
+    CanisterReadyError, CanisterReadyLayer, CanisterReadyService, CheckCancellation, Client,
+    DryRun, DryRunClient, HttpOutcallTransport, HttpsOutcallError, IcError,
+    IsReplicatedRequestExtension, ManagementCanisterTransport, MaxResponseBytesRequestExtension,
+    RequestLimitError, RequestLimits, RequestLimitsLayer, SimulationError,
+    TransformContextRequestExtension, TransportFn,
 };
 pub use convert::ConvertServiceBuilder;
+#[cfg(all(feature = "http", feature = "json"))]
+pub use simple::post_json;
+#[cfg(feature = "http")]
+pub use simple::{get, post, send};
 
+pub mod batch;
 mod client;
+mod clock;
 pub mod convert;
 pub mod cycles;
+pub mod error;
 #[cfg(feature = "http")]
 pub mod http;
+pub mod job;
+pub mod limit;
 #[cfg(feature = "multi")]
 pub mod multi;
 pub mod observability;
+#[cfg(all(feature = "http", feature = "json"))]
+pub mod presets;
+#[cfg(feature = "http")]
+pub mod queue;
 pub mod retry;
+pub mod schedule;
+#[cfg(feature = "http")]
+pub mod simple;
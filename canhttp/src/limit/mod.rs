@@ -0,0 +1,239 @@
+//! Middleware limiting the number of requests in flight, without relying on [`tokio`].
+//!
+//! Some middlewares of [`tower`] cannot be used in a canister, because they assume a [`tokio`]
+//! runtime to spawn tasks or to synchronize between threads:
+//! * [`tower::limit::ConcurrencyLimit`] relies on a [`tokio`] semaphore and [`tower::buffer::Buffer`]
+//!   spawns a worker task with [`tokio::spawn`]. Use [`ConcurrencyLimitLayer`] instead, whose
+//!   services can be cloned to share a service between several callers while limiting
+//!   the number of concurrent requests across all clones. Use [`PriorityConcurrencyLimitLayer`]
+//!   to dispatch deferred requests by [`Priority`], e.g. so that critical requests are not stuck
+//!   behind routine polling.
+//! * [`tower::retry::Retry`] does not require [`Send`] futures nor any runtime and can be used
+//!   as is, see the [`retry`](crate::retry) module for policies tailored to HTTPs outcalls.
+//!
+//! [`tokio`]: https://docs.rs/tokio
+//! [`tokio::spawn`]: https://docs.rs/tokio/latest/tokio/task/fn.spawn.html
+//! [`tower::limit::ConcurrencyLimit`]: https://docs.rs/tower/latest/tower/limit/struct.ConcurrencyLimit.html
+//! [`tower::buffer::Buffer`]: https://docs.rs/tower/latest/tower/buffer/struct.Buffer.html
+
+#[cfg(feature = "http")]
+mod priority;
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "http")]
+pub use priority::{
+    Priority, PriorityConcurrencyLimit, PriorityConcurrencyLimitFuture,
+    PriorityConcurrencyLimitLayer, PriorityRequestExtension,
+};
+
+use pin_project::pin_project;
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+use tower::{Layer, Service};
+
+/// Limit the number of requests in flight across all clones of the produced services.
+///
+/// Contrary to [`tower::limit::ConcurrencyLimitLayer`], the permits are shared with
+/// reference counting and tasks waiting for a permit are woken up without any runtime,
+/// which makes it usable from a canister. A permit is acquired when the service is ready
+/// and released once the response future completes or is dropped.
+///
+/// This [`Layer`] produces instances of the [`ConcurrencyLimit`] service.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{batch::execute_all, limit::ConcurrencyLimitLayer};
+/// use std::{cell::Cell, rc::Rc};
+/// use tower::ServiceBuilder;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let in_flight = Rc::new(Cell::new(0));
+/// let service = ServiceBuilder::new()
+///     .layer(ConcurrencyLimitLayer::new(2))
+///     .service_fn(|request: u32| {
+///         let in_flight = in_flight.clone();
+///         async move {
+///             in_flight.set(in_flight.get() + 1);
+///             assert!(in_flight.get() <= 2);
+///             tokio::task::yield_now().await;
+///             in_flight.set(in_flight.get() - 1);
+///             Ok::<_, String>(request)
+///         }
+///     });
+///
+/// let (_service, results) = execute_all(service, (0..10).collect(), 10).await;
+///
+/// assert_eq!(results, (0..10).map(Ok).collect::<Vec<_>>());
+/// # }
+/// ```
+///
+/// [`tower::limit::ConcurrencyLimitLayer`]: https://docs.rs/tower/latest/tower/limit/struct.ConcurrencyLimitLayer.html
+#[derive(Clone, Debug)]
+pub struct ConcurrencyLimitLayer {
+    max_in_flight: usize,
+}
+
+impl ConcurrencyLimitLayer {
+    /// Create a new [`ConcurrencyLimitLayer`] allowing at most `max_in_flight` requests
+    /// in flight for each service produced by this layer and all its clones.
+    pub fn new(max_in_flight: usize) -> Self {
+        Self { max_in_flight }
+    }
+}
+
+impl<S> Layer<S> for ConcurrencyLimitLayer {
+    type Service = ConcurrencyLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConcurrencyLimit::new(inner, self.max_in_flight)
+    }
+}
+
+/// Limit the number of requests in flight, see [`ConcurrencyLimitLayer`].
+pub struct ConcurrencyLimit<S> {
+    inner: S,
+    semaphore: Semaphore,
+    permit: Option<Permit>,
+}
+
+impl<S> ConcurrencyLimit<S> {
+    /// Create a new [`ConcurrencyLimit`] allowing at most `max_in_flight` requests in flight
+    /// for this service and all its clones.
+    pub fn new(inner: S, max_in_flight: usize) -> Self {
+        Self {
+            inner,
+            semaphore: Semaphore::new(max_in_flight),
+            permit: None,
+        }
+    }
+
+    /// Returns the number of requests that can currently be sent without waiting,
+    /// across all clones of this service.
+    pub fn available_permits(&self) -> usize {
+        self.semaphore.0.borrow().available
+    }
+}
+
+impl<S: Clone> Clone for ConcurrencyLimit<S> {
+    fn clone(&self) -> Self {
+        // The permit of a ready service is not shared with its clones.
+        Self {
+            inner: self.inner.clone(),
+            semaphore: self.semaphore.clone(),
+            permit: None,
+        }
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for ConcurrencyLimit<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrencyLimit")
+            .field("inner", &self.inner)
+            .field("available_permits", &self.available_permits())
+            .field("has_permit", &self.permit.is_some())
+            .finish()
+    }
+}
+
+impl<S, Request> Service<Request> for ConcurrencyLimit<S>
+where
+    S: Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ConcurrencyLimitFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.permit.is_none() {
+            match self.semaphore.poll_acquire(cx) {
+                Poll::Ready(permit) => self.permit = Some(permit),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let permit = self
+            .permit
+            .take()
+            .expect("BUG: poll_ready must be called before call");
+        ConcurrencyLimitFuture {
+            inner: self.inner.call(request),
+            permit: Some(permit),
+        }
+    }
+}
+
+/// Response future of the [`ConcurrencyLimit`] service.
+#[pin_project]
+pub struct ConcurrencyLimitFuture<F> {
+    #[pin]
+    inner: F,
+    permit: Option<Permit>,
+}
+
+impl<F: Future> Future for ConcurrencyLimitFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = std::task::ready!(this.inner.poll(cx));
+        // Release the permit as soon as the response is available.
+        this.permit.take();
+        Poll::Ready(output)
+    }
+}
+
+#[derive(Clone)]
+struct Semaphore(Rc<RefCell<SemaphoreState>>);
+
+struct SemaphoreState {
+    available: usize,
+    waiters: Vec<Waker>,
+}
+
+impl Semaphore {
+    fn new(permits: usize) -> Self {
+        Self(Rc::new(RefCell::new(SemaphoreState {
+            available: permits,
+            waiters: Vec::new(),
+        })))
+    }
+
+    fn poll_acquire(&self, cx: &mut Context<'_>) -> Poll<Permit> {
+        let mut state = self.0.borrow_mut();
+        if state.available > 0 {
+            state.available -= 1;
+            return Poll::Ready(Permit(self.clone()));
+        }
+        if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+            state.waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+struct Permit(Semaphore);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let waiters = {
+            let mut state = (self.0).0.borrow_mut();
+            state.available += 1;
+            std::mem::take(&mut state.waiters)
+        };
+        // Wake up all waiting tasks, since a woken task may not acquire the permit,
+        // e.g. if it was dropped in the meantime.
+        waiters.into_iter().for_each(Waker::wake);
+    }
+}
@@ -0,0 +1,364 @@
+use pin_project::pin_project;
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+use tower::{util::Oneshot, Layer, Service, ServiceExt};
+
+/// Priority of a request deferred by a [`PriorityConcurrencyLimit`].
+///
+/// Requests without any priority have the [`Priority::Normal`] priority.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    /// Requests that can wait, e.g. routine polling.
+    Low,
+    /// Default priority.
+    #[default]
+    Normal,
+    /// Requests dispatched before any request with a normal priority.
+    High,
+    /// Requests dispatched before any other request, e.g. liquidations.
+    Critical,
+}
+
+/// Add support for request priorities, see [`PriorityConcurrencyLimitLayer`].
+pub trait PriorityRequestExtension: Sized {
+    /// Set the priority of the request.
+    fn set_priority(&mut self, value: Priority);
+
+    /// Retrieves the priority of the request, if any.
+    fn get_priority(&self) -> Option<Priority>;
+
+    /// Convenience method to use the builder pattern.
+    fn priority(mut self, value: Priority) -> Self {
+        self.set_priority(value);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PriorityExtension(pub Priority);
+
+impl<T> PriorityRequestExtension for http::Request<T> {
+    fn set_priority(&mut self, value: Priority) {
+        let extensions = self.extensions_mut();
+        extensions.insert(PriorityExtension(value));
+    }
+
+    fn get_priority(&self) -> Option<Priority> {
+        self.extensions().get::<PriorityExtension>().map(|e| e.0)
+    }
+}
+
+impl PriorityRequestExtension for http::request::Builder {
+    fn set_priority(&mut self, value: Priority) {
+        if let Some(extensions) = self.extensions_mut() {
+            extensions.insert(PriorityExtension(value));
+        }
+    }
+
+    fn get_priority(&self) -> Option<Priority> {
+        self.extensions_ref()
+            .and_then(|extensions| extensions.get::<PriorityExtension>().map(|e| e.0))
+    }
+}
+
+/// Limit the number of requests in flight like [`ConcurrencyLimitLayer`], but dispatch the
+/// deferred requests by decreasing [`Priority`], and in the order they were sent for
+/// requests with the same priority.
+///
+/// Since the priority of a request is only known when it is sent, a request waits for a
+/// permit in its response future and the produced services are always ready. The inner
+/// service must therefore be [`Clone`], and a clone of it is used for each request.
+///
+/// This [`Layer`] produces instances of the [`PriorityConcurrencyLimit`] service.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::limit::{Priority, PriorityConcurrencyLimitLayer, PriorityRequestExtension};
+/// use futures_util::future::join_all;
+/// use std::{cell::RefCell, rc::Rc};
+/// use tower::{Service, ServiceBuilder};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let dispatched = Rc::new(RefCell::new(Vec::new()));
+/// let mut service = ServiceBuilder::new()
+///     .layer(PriorityConcurrencyLimitLayer::new(1))
+///     .service_fn(|request: http::Request<&'static str>| {
+///         let dispatched = dispatched.clone();
+///         async move {
+///             dispatched.borrow_mut().push(*request.body());
+///             Ok::<_, String>(())
+///         }
+///     });
+///
+/// let requests = vec![
+///     http::Request::new("poll prices"),
+///     http::Request::new("poll balances"),
+///     http::Request::new("liquidate").priority(Priority::Critical),
+/// ];
+/// join_all(requests.into_iter().map(|request| service.call(request))).await;
+///
+/// assert_eq!(*dispatched.borrow(), vec!["liquidate", "poll prices", "poll balances"]);
+/// # }
+/// ```
+///
+/// [`ConcurrencyLimitLayer`]: crate::limit::ConcurrencyLimitLayer
+/// [`Layer`]: tower::Layer
+#[derive(Clone, Debug)]
+pub struct PriorityConcurrencyLimitLayer {
+    max_in_flight: usize,
+}
+
+impl PriorityConcurrencyLimitLayer {
+    /// Create a new [`PriorityConcurrencyLimitLayer`] allowing at most `max_in_flight` requests
+    /// in flight for each service produced by this layer and all its clones.
+    pub fn new(max_in_flight: usize) -> Self {
+        Self { max_in_flight }
+    }
+}
+
+impl<S> Layer<S> for PriorityConcurrencyLimitLayer {
+    type Service = PriorityConcurrencyLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PriorityConcurrencyLimit::new(inner, self.max_in_flight)
+    }
+}
+
+/// Limit the number of requests in flight and dispatch deferred requests by priority,
+/// see [`PriorityConcurrencyLimitLayer`].
+#[derive(Clone)]
+pub struct PriorityConcurrencyLimit<S> {
+    inner: S,
+    semaphore: PrioritySemaphore,
+}
+
+impl<S> PriorityConcurrencyLimit<S> {
+    /// Create a new [`PriorityConcurrencyLimit`] allowing at most `max_in_flight` requests
+    /// in flight for this service and all its clones.
+    pub fn new(inner: S, max_in_flight: usize) -> Self {
+        Self {
+            inner,
+            semaphore: PrioritySemaphore::new(max_in_flight),
+        }
+    }
+
+    /// Returns the number of requests that can currently be sent without waiting,
+    /// across all clones of this service.
+    pub fn available_permits(&self) -> usize {
+        self.semaphore.0.borrow().available
+    }
+
+    /// Returns the number of requests waiting for a permit, across all clones of this service.
+    pub fn waiting(&self) -> usize {
+        self.semaphore.0.borrow().waiters.len()
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for PriorityConcurrencyLimit<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PriorityConcurrencyLimit")
+            .field("inner", &self.inner)
+            .field("available_permits", &self.available_permits())
+            .field("waiting", &self.waiting())
+            .finish()
+    }
+}
+
+impl<S, T> Service<http::Request<T>> for PriorityConcurrencyLimit<S>
+where
+    S: Service<http::Request<T>> + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = PriorityConcurrencyLimitFuture<S, http::Request<T>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The readiness of the inner service is checked once the request has a permit.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<T>) -> Self::Future {
+        let priority = request.get_priority().unwrap_or_default();
+        PriorityConcurrencyLimitFuture {
+            state: State::Waiting {
+                ticket: self.semaphore.ticket(priority),
+                service: Some(self.inner.clone()),
+                request: Some(request),
+            },
+        }
+    }
+}
+
+/// Response future of the [`PriorityConcurrencyLimit`] service.
+#[pin_project]
+pub struct PriorityConcurrencyLimitFuture<S, Request>
+where
+    S: Service<Request>,
+{
+    #[pin]
+    state: State<S, Request>,
+}
+
+#[pin_project(project = StateProj)]
+enum State<S, Request>
+where
+    S: Service<Request>,
+{
+    Waiting {
+        ticket: Ticket,
+        service: Option<S>,
+        request: Option<Request>,
+    },
+    Running {
+        #[pin]
+        future: Oneshot<S, Request>,
+        permit: Option<Permit>,
+    },
+}
+
+impl<S, Request> Future for PriorityConcurrencyLimitFuture<S, Request>
+where
+    S: Service<Request>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::Waiting {
+                    ticket,
+                    service,
+                    request,
+                } => {
+                    let permit = std::task::ready!(ticket.poll_acquire(cx));
+                    let future = service
+                        .take()
+                        .expect("BUG: future polled after completion")
+                        .oneshot(request.take().expect("BUG: future polled after completion"));
+                    this.state.set(State::Running {
+                        future,
+                        permit: Some(permit),
+                    });
+                }
+                StateProj::Running { future, permit } => {
+                    let output = std::task::ready!(future.poll(cx));
+                    // Release the permit as soon as the response is available.
+                    permit.take();
+                    return Poll::Ready(output);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct PrioritySemaphore(Rc<RefCell<PrioritySemaphoreState>>);
+
+/// Waiters are ordered by decreasing priority, then by increasing sequence number.
+type WaiterKey = (Reverse<Priority>, u64);
+
+struct PrioritySemaphoreState {
+    available: usize,
+    next_sequence: u64,
+    waiters: BTreeMap<WaiterKey, Option<Waker>>,
+}
+
+impl PrioritySemaphoreState {
+    /// Returns the waker of the next waiting request, if there is an available permit.
+    fn next_waker(&mut self) -> Option<Waker> {
+        if self.available == 0 {
+            return None;
+        }
+        self.waiters.values_mut().next().and_then(Option::take)
+    }
+}
+
+impl PrioritySemaphore {
+    fn new(permits: usize) -> Self {
+        Self(Rc::new(RefCell::new(PrioritySemaphoreState {
+            available: permits,
+            next_sequence: 0,
+            waiters: BTreeMap::new(),
+        })))
+    }
+
+    fn ticket(&self, priority: Priority) -> Ticket {
+        let mut state = self.0.borrow_mut();
+        let key = (Reverse(priority), state.next_sequence);
+        state.next_sequence += 1;
+        state.waiters.insert(key, None);
+        Ticket {
+            semaphore: self.clone(),
+            key: Some(key),
+        }
+    }
+}
+
+/// Place of a request in the queue of requests waiting for a permit.
+struct Ticket {
+    semaphore: PrioritySemaphore,
+    key: Option<WaiterKey>,
+}
+
+impl Ticket {
+    fn poll_acquire(&mut self, cx: &mut Context<'_>) -> Poll<Permit> {
+        let key = self.key.expect("BUG: permit already acquired");
+        let mut state = self.semaphore.0.borrow_mut();
+        let is_next = state.waiters.keys().next() == Some(&key);
+        if is_next && state.available > 0 {
+            state.available -= 1;
+            state.waiters.remove(&key);
+            self.key = None;
+            // Several permits may be available.
+            let next = state.next_waker();
+            drop(state);
+            if let Some(waker) = next {
+                waker.wake();
+            }
+            return Poll::Ready(Permit(self.semaphore.clone()));
+        }
+        state.waiters.insert(key, Some(cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            let next = {
+                let mut state = self.semaphore.0.borrow_mut();
+                state.waiters.remove(&key);
+                state.next_waker()
+            };
+            if let Some(waker) = next {
+                waker.wake();
+            }
+        }
+    }
+}
+
+struct Permit(PrioritySemaphore);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let next = {
+            let mut state = (self.0).0.borrow_mut();
+            state.available += 1;
+            state.next_waker()
+        };
+        if let Some(waker) = next {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::limit::ConcurrencyLimitLayer;
+use futures_util::{future, FutureExt};
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+use tower::{Layer, Service};
+
+#[tokio::test]
+async fn should_share_permits_between_clones() {
+    let mut service = ConcurrencyLimitLayer::new(2).layer(tower::service_fn(|request: u8| {
+        future::ready(Ok::<_, String>(request))
+    }));
+    let mut other = service.clone();
+    let mut third = service.clone();
+    let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+
+    assert_eq!(service.poll_ready(&mut cx), Poll::Ready(Ok(())));
+    assert_eq!(other.poll_ready(&mut cx), Poll::Ready(Ok(())));
+    assert_eq!(service.available_permits(), 0);
+    assert_eq!(third.poll_ready(&mut cx), Poll::Pending);
+
+    let first = service.call(1);
+    assert_eq!(third.poll_ready(&mut cx), Poll::Pending);
+    assert_eq!(first.await, Ok(1));
+    assert_eq!(third.poll_ready(&mut cx), Poll::Ready(Ok(())));
+
+    drop(other);
+    assert_eq!(service.available_permits(), 1);
+}
+
+#[tokio::test]
+async fn should_release_permit_when_future_is_dropped() {
+    let mut service = ConcurrencyLimitLayer::new(1).layer(tower::service_fn(|_request: u8| {
+        future::pending::<Result<u8, String>>()
+    }));
+    let mut other = service.clone();
+    let woken = Arc::new(WakeFlag::default());
+    let waker = Waker::from(woken.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    assert_eq!(service.poll_ready(&mut cx), Poll::Ready(Ok(())));
+    let mut request = Box::pin(service.call(1));
+    assert!(request.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(other.poll_ready(&mut cx), Poll::Pending);
+    assert!(!woken.0.load(Ordering::SeqCst));
+
+    drop(request);
+    assert!(woken.0.load(Ordering::SeqCst));
+    assert!(other.poll_ready(&mut cx).is_ready());
+    assert!(other.call(2).now_or_never().is_none());
+}
+
+#[derive(Default)]
+struct WakeFlag(AtomicBool);
+
+impl Wake for WakeFlag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "http")]
+mod priority {
+    use crate::limit::{
+        tests::WakeFlag, Priority, PriorityConcurrencyLimitLayer, PriorityRequestExtension,
+    };
+    use futures_util::{future, FutureExt};
+    use std::{
+        cell::RefCell,
+        future::Future,
+        rc::Rc,
+        sync::{atomic::Ordering, Arc},
+        task::{Context, Waker},
+    };
+    use tower::{Layer, Service};
+
+    #[tokio::test]
+    async fn should_dispatch_deferred_requests_by_priority() {
+        let dispatched = Rc::new(RefCell::new(Vec::new()));
+        let mut service = PriorityConcurrencyLimitLayer::new(1).layer(tower::service_fn({
+            let dispatched = dispatched.clone();
+            move |request: http::Request<u8>| {
+                dispatched.borrow_mut().push(*request.body());
+                future::ready(Ok::<_, String>(()))
+            }
+        }));
+        let mut other = service.clone();
+
+        let requests = vec![
+            service.call(http::Request::new(0)),
+            other.call(http::Request::new(1).priority(Priority::Low)),
+            service.call(http::Request::new(2)),
+            other.call(http::Request::new(3).priority(Priority::High)),
+            service.call(http::Request::new(4).priority(Priority::Critical)),
+            other.call(http::Request::new(5).priority(Priority::High)),
+        ];
+        assert_eq!(service.waiting(), 6);
+
+        future::join_all(requests).await;
+
+        assert_eq!(*dispatched.borrow(), vec![4, 3, 5, 0, 2, 1]);
+        assert_eq!(service.available_permits(), 1);
+        assert_eq!(service.waiting(), 0);
+    }
+
+    #[tokio::test]
+    async fn should_wake_next_request_when_waiting_request_is_dropped() {
+        let mut service = PriorityConcurrencyLimitLayer::new(1).layer(tower::service_fn(
+            |request: http::Request<u8>| match *request.body() {
+                0 => future::pending().boxed_local(),
+                body => future::ready(Ok::<_, String>(body)).boxed_local(),
+            },
+        ));
+        let woken = Arc::new(WakeFlag::default());
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut in_flight = Box::pin(service.call(http::Request::new(0)));
+        assert!(in_flight.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(service.available_permits(), 0);
+
+        let high = service.call(http::Request::new(1).priority(Priority::High));
+        let mut low = Box::pin(service.call(http::Request::new(2).priority(Priority::Low)));
+        assert!(low.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(service.waiting(), 2);
+
+        drop(in_flight);
+        assert_eq!(service.available_permits(), 1);
+        // The request with a high priority was never polled and has no waker.
+        assert!(!woken.0.load(Ordering::SeqCst));
+
+        drop(high);
+        assert!(woken.0.load(Ordering::SeqCst));
+        assert_eq!(low.now_or_never(), Some(Ok(2)));
+    }
+}
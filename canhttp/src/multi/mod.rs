@@ -3,17 +3,21 @@
 
 pub use cache::{TimedSizedMap, TimedSizedVec, Timestamp};
 pub use reduce::{Reduce, ReduceWithEquality, ReduceWithThreshold, ReducedResult, ReductionError};
+pub use routing::{NoEligibleProviderError, RoutingPolicy};
 
 mod cache;
 mod reduce;
+mod routing;
 #[cfg(test)]
 mod tests;
 
+use crate::{CancellationToken, Cancelled};
 use futures_channel::mpsc;
-use futures_util::StreamExt;
+use futures_util::{future, StreamExt};
 use std::collections::{btree_map, btree_map::IntoIter as BTreeMapIntoIter, BTreeMap};
 use std::fmt::Debug;
 use std::iter::FusedIterator;
+use std::task::{Context, Poll};
 use tower::{Service, ServiceExt};
 
 /// Process all requests from the given iterator and produce a result for reach request.
@@ -80,6 +84,148 @@ where
     (parallel_service.into_inner(), results)
 }
 
+/// Like [`parallel_call`], but the given [`CancellationToken`] is checked before dispatching
+/// each request to the underlying service.
+///
+/// Requests that were not dispatched because the token was cancelled produce a [`Cancelled`]
+/// error. Requests that were already dispatched are not affected. Since requests are dispatched
+/// as soon as the underlying service is ready, this is mostly useful when the service limits
+/// how many requests are in flight, e.g. with a concurrency limit.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use canhttp::{multi::parallel_call_with_cancellation, CancellationToken, Cancelled};
+/// use tower::ServiceBuilder;
+///
+/// let token = CancellationToken::new();
+/// let service = ServiceBuilder::new().service_fn({
+///     let token = token.clone();
+///     move |n: u32| {
+///         // The first result is enough.
+///         token.cancel();
+///         async move { Ok::<_, Cancelled>(n) }
+///     }
+/// });
+///
+/// let (_service, results) =
+///     parallel_call_with_cancellation(service, vec![(0, 1), (1, 2)], &token).await;
+///
+/// assert_eq!(results.get(&0).unwrap(), Ok(&1_u32));
+/// assert_eq!(results.get(&1).unwrap(), Err(&Cancelled));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// If two requests produced by the iterator have the same request ID.
+pub async fn parallel_call_with_cancellation<S, I, RequestId, Request, Response, Error>(
+    service: S,
+    requests: I,
+    token: &CancellationToken,
+) -> (S, MultiResults<RequestId, Response, Error>)
+where
+    S: Service<Request, Response = Response, Error = Error>,
+    I: IntoIterator<Item = (RequestId, Request)>,
+    RequestId: Ord,
+    Error: From<Cancelled>,
+{
+    let service = CheckCancellationService {
+        inner: service,
+        token: token.clone(),
+    };
+    let (service, results) = parallel_call(service, requests).await;
+    (service.inner, results)
+}
+
+/// Like [`parallel_call`], but only the requests whose provider may serve the given method
+/// according to the given [`RoutingPolicy`] are sent to the underlying service.
+///
+/// Requests are keyed by provider and requests to ineligible providers are dropped before any
+/// of them is sent, so that no HTTPs outcall is made to a provider that cannot serve the
+/// method. If no provider is eligible, no request is sent and a [`NoEligibleProviderError`] is
+/// returned alongside the service.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use canhttp::multi::{parallel_call_with_routing, RoutingPolicy};
+/// use std::convert::Infallible;
+/// use tower::ServiceBuilder;
+///
+/// let policy = RoutingPolicy::new().route_method("eth_getProof", ["archive"]);
+/// let service = ServiceBuilder::new().service_fn(|provider: &'static str| async move {
+///     Ok::<_, Infallible>(format!("served by {provider}"))
+/// });
+/// let requests = ["archive", "public"].map(|provider| (provider, provider));
+///
+/// let (_service, results) =
+///     parallel_call_with_routing(service, requests, "eth_getProof", &policy).await;
+///
+/// let results = results?;
+/// assert_eq!(results.len(), 1);
+/// assert_eq!(results.get(&"archive").unwrap(), Ok(&"served by archive".to_string()));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// If two requests produced by the iterator have the same provider.
+pub async fn parallel_call_with_routing<S, I, P, Request, Response, Error>(
+    service: S,
+    requests: I,
+    method: &str,
+    policy: &RoutingPolicy<P>,
+) -> (
+    S,
+    Result<MultiResults<P, Response, Error>, NoEligibleProviderError>,
+)
+where
+    S: Service<Request, Response = Response, Error = Error>,
+    I: IntoIterator<Item = (P, Request)>,
+    P: Ord,
+{
+    match policy.select_requests(method, requests) {
+        Ok(requests) => {
+            let (service, results) = parallel_call(service, requests).await;
+            (service, Ok(results))
+        }
+        Err(error) => (service, Err(error)),
+    }
+}
+
+struct CheckCancellationService<S> {
+    inner: S,
+    token: CancellationToken,
+}
+
+impl<S, Request> Service<Request> for CheckCancellationService<S>
+where
+    S: Service<Request>,
+    S::Error: From<Cancelled>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = future::Either<S::Future, future::Ready<Result<S::Response, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        if self.token.is_cancelled() {
+            return future::Either::Right(future::ready(Err(Cancelled.into())));
+        }
+        future::Either::Left(self.inner.call(request))
+    }
+}
+
 /// Aggregates multiple results, where each result is identified by a *unique* key.
 ///
 /// At the implementation level, results are split between [`Ok`] values and [`Err`] values.
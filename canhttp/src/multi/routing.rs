@@ -0,0 +1,175 @@
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+/// Declarative policy restricting which providers may serve a given (JSON-RPC) method.
+///
+/// Some methods can only be served by a subset of providers, e.g. methods requiring an archive
+/// node or `trace_*` methods only offered by paid tiers. Sending those requests to other providers
+/// wastes cycles on HTTPs outcalls that will certainly be refused.
+///
+/// Routes are resolved as follows:
+/// 1. A route for the exact method name, see [`RoutingPolicy::route_method`].
+/// 2. Otherwise, the route with the longest matching prefix, see [`RoutingPolicy::route_prefix`].
+/// 3. Otherwise, all providers are eligible.
+///
+/// Multi-provider calls should be made with
+/// [`parallel_call_with_routing`](crate::multi::parallel_call_with_routing), which only sends
+/// the requests to the eligible providers, so that no HTTPs outcall is made to a provider that
+/// cannot serve the method.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use canhttp::multi::{parallel_call_with_routing, RoutingPolicy};
+/// use std::convert::Infallible;
+/// use tower::ServiceBuilder;
+///
+/// let policy = RoutingPolicy::new()
+///     .route_method("eth_getProof", ["archive"])
+///     .route_prefix("trace_", ["paid"]);
+/// let providers = ["archive", "paid", "public"];
+///
+/// let providers_for_trace = policy.select("trace_block", providers)?;
+/// assert_eq!(providers_for_trace, vec!["paid"]);
+///
+/// let service = ServiceBuilder::new().service_fn(
+///     |(provider, method): (&'static str, &'static str)| async move {
+///         Ok::<_, Infallible>(format!("{method} served by {provider}"))
+///     },
+/// );
+/// let requests = providers.map(|provider| (provider, (provider, "eth_getProof")));
+/// let (_service, results) =
+///     parallel_call_with_routing(service, requests, "eth_getProof", &policy).await;
+///
+/// let results = results?;
+/// assert_eq!(results.len(), 1);
+/// assert_eq!(
+///     results.get(&"archive").unwrap(),
+///     Ok(&"eth_getProof served by archive".to_string())
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutingPolicy<P> {
+    methods: BTreeMap<String, BTreeSet<P>>,
+    prefixes: BTreeMap<String, BTreeSet<P>>,
+}
+
+impl<P> Default for RoutingPolicy<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> RoutingPolicy<P> {
+    /// Create a new [`RoutingPolicy`] where all providers are eligible for all methods.
+    pub fn new() -> Self {
+        Self {
+            methods: BTreeMap::new(),
+            prefixes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<P: Ord> RoutingPolicy<P> {
+    /// Restrict the given method to the given providers.
+    ///
+    /// Replaces any existing route for that method.
+    pub fn route_method(
+        mut self,
+        method: impl Into<String>,
+        providers: impl IntoIterator<Item = impl Into<P>>,
+    ) -> Self {
+        self.methods.insert(
+            method.into(),
+            providers.into_iter().map(Into::into).collect(),
+        );
+        self
+    }
+
+    /// Restrict all methods starting with the given prefix to the given providers.
+    ///
+    /// Replaces any existing route for that prefix.
+    pub fn route_prefix(
+        mut self,
+        prefix: impl Into<String>,
+        providers: impl IntoIterator<Item = impl Into<P>>,
+    ) -> Self {
+        self.prefixes.insert(
+            prefix.into(),
+            providers.into_iter().map(Into::into).collect(),
+        );
+        self
+    }
+
+    /// Returns `true` if and only if the given provider may serve the given method.
+    pub fn is_eligible(&self, method: &str, provider: &P) -> bool {
+        self.route(method)
+            .is_none_or(|providers| providers.contains(provider))
+    }
+
+    /// Returns the providers, among the given ones, that may serve the given method.
+    ///
+    /// The order of the given providers is preserved.
+    pub fn select<I>(&self, method: &str, providers: I) -> Result<Vec<P>, NoEligibleProviderError>
+    where
+        I: IntoIterator<Item = P>,
+    {
+        let eligible: Vec<P> = providers
+            .into_iter()
+            .filter(|provider| self.is_eligible(method, provider))
+            .collect();
+        if eligible.is_empty() {
+            return Err(NoEligibleProviderError {
+                method: method.to_string(),
+            });
+        }
+        Ok(eligible)
+    }
+
+    /// Returns the requests, among the given ones keyed by provider, whose provider may serve
+    /// the given method.
+    ///
+    /// The order of the given requests is preserved.
+    pub fn select_requests<I, Request>(
+        &self,
+        method: &str,
+        requests: I,
+    ) -> Result<Vec<(P, Request)>, NoEligibleProviderError>
+    where
+        I: IntoIterator<Item = (P, Request)>,
+    {
+        let eligible: Vec<(P, Request)> = requests
+            .into_iter()
+            .filter(|(provider, _request)| self.is_eligible(method, provider))
+            .collect();
+        if eligible.is_empty() {
+            return Err(NoEligibleProviderError {
+                method: method.to_string(),
+            });
+        }
+        Ok(eligible)
+    }
+
+    fn route(&self, method: &str) -> Option<&BTreeSet<P>> {
+        self.methods.get(method).or_else(|| {
+            self.prefixes
+                .iter()
+                .filter(|(prefix, _)| method.starts_with(prefix.as_str()))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map(|(_, providers)| providers)
+        })
+    }
+}
+
+/// Error returned by [`RoutingPolicy::select`] and [`RoutingPolicy::select_requests`] when no
+/// provider may serve the method.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+#[error("No eligible provider for method `{method}`")]
+pub struct NoEligibleProviderError {
+    /// The method that cannot be served.
+    pub method: String,
+}
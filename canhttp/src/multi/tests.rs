@@ -1,8 +1,12 @@
 use crate::multi::Timestamp;
 
 mod parallel_call {
-    use crate::multi::parallel_call;
-    use std::convert::Infallible;
+    use crate::{
+        multi::{parallel_call, parallel_call_with_cancellation},
+        CancellationToken, Cancelled,
+    };
+    use maplit::btreemap;
+    use std::{cell::RefCell, convert::Infallible, rc::Rc};
     use tower::ServiceBuilder;
 
     #[tokio::test]
@@ -16,6 +20,31 @@ mod parallel_call {
         let (_service, _results) =
             parallel_call(adding_service, vec![(0, (2, 3)), (1, (4, 5)), (0, (6, 7))]).await;
     }
+
+    #[tokio::test]
+    async fn should_not_dispatch_requests_once_cancelled() {
+        let token = CancellationToken::new();
+        let dispatched = Rc::new(RefCell::new(Vec::new()));
+        let service = ServiceBuilder::new().service_fn({
+            let token = token.clone();
+            let dispatched = dispatched.clone();
+            move |n: u32| {
+                dispatched.borrow_mut().push(n);
+                if n == 1 {
+                    token.cancel();
+                }
+                async move { Ok::<_, Cancelled>(n) }
+            }
+        });
+
+        let (_service, results) =
+            parallel_call_with_cancellation(service, (0..4).map(|n| (n, n)), &token).await;
+
+        assert_eq!(*dispatched.borrow(), vec![0, 1]);
+        let (ok, errors) = results.into_inner();
+        assert_eq!(ok, btreemap! {0 => 0, 1 => 1});
+        assert_eq!(errors, btreemap! {2 => Cancelled, 3 => Cancelled});
+    }
 }
 
 mod reduce_with_equality {
@@ -611,3 +640,142 @@ mod timed_sized_map {
 fn timestamp(nanos: u64) -> Timestamp {
     Timestamp::from_nanos_since_unix_epoch(nanos)
 }
+
+mod routing_policy {
+    use crate::multi::{
+        parallel_call, parallel_call_with_routing, NoEligibleProviderError, ReduceWithEquality,
+        RoutingPolicy,
+    };
+    use std::{cell::RefCell, convert::Infallible, rc::Rc};
+    use tower::ServiceBuilder;
+
+    const PROVIDERS: [&str; 3] = ["archive", "paid", "public"];
+
+    #[test]
+    fn should_select_all_providers_without_route() {
+        let policy = RoutingPolicy::<&str>::new().route_method("eth_getProof", ["archive"]);
+
+        assert_eq!(
+            policy.select("eth_blockNumber", PROVIDERS),
+            Ok(PROVIDERS.to_vec())
+        );
+    }
+
+    #[test]
+    fn should_prefer_exact_method_over_longest_prefix() {
+        let policy = RoutingPolicy::<&str>::new()
+            .route_prefix("trace_", ["paid"])
+            .route_prefix("trace_replay", ["archive", "paid"])
+            .route_method("trace_replayBlockTransactions", ["archive"]);
+
+        assert_eq!(policy.select("trace_block", PROVIDERS), Ok(vec!["paid"]));
+        assert_eq!(
+            policy.select("trace_replayTransaction", PROVIDERS),
+            Ok(vec!["archive", "paid"])
+        );
+        assert_eq!(
+            policy.select("trace_replayBlockTransactions", PROVIDERS),
+            Ok(vec!["archive"])
+        );
+        assert!(!policy.is_eligible("trace_block", &"public"));
+        assert!(policy.is_eligible("eth_call", &"public"));
+    }
+
+    #[test]
+    fn should_fail_when_no_provider_eligible() {
+        let policy = RoutingPolicy::<&str>::new().route_method("eth_getProof", ["archive"]);
+
+        assert_eq!(
+            policy.select("eth_getProof", ["paid", "public"]),
+            Err(NoEligibleProviderError {
+                method: "eth_getProof".to_string()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_only_call_eligible_providers() {
+        let policy = RoutingPolicy::<&str>::new().route_prefix("trace_", ["archive", "paid"]);
+        let called = Rc::new(RefCell::new(Vec::new()));
+        let service = ServiceBuilder::new().service_fn({
+            let called = called.clone();
+            move |(provider, method): (&'static str, &'static str)| {
+                called.borrow_mut().push(provider);
+                async move { Ok::<_, Infallible>(format!("{method} result")) }
+            }
+        });
+        let requests = |method| PROVIDERS.map(|provider| (provider, (provider, method)));
+
+        let (service, results) = parallel_call(
+            service,
+            policy
+                .select_requests("trace_block", requests("trace_block"))
+                .unwrap(),
+        )
+        .await;
+
+        assert_eq!(*called.borrow(), vec!["archive", "paid"]);
+        assert_eq!(
+            results.reduce(ReduceWithEquality),
+            Ok("trace_block result".to_string())
+        );
+
+        let (_service, results) = parallel_call(
+            service,
+            policy
+                .select_requests("eth_call", requests("eth_call"))
+                .unwrap(),
+        )
+        .await;
+
+        assert_eq!(
+            *called.borrow(),
+            vec!["archive", "paid", "archive", "paid", "public"]
+        );
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            RoutingPolicy::<&str>::new()
+                .route_method("eth_getProof", ["archive"])
+                .select_requests("eth_getProof", [("public", ())]),
+            Err(NoEligibleProviderError {
+                method: "eth_getProof".to_string()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_enforce_routing_policy_when_calling_providers() {
+        let policy = RoutingPolicy::<&str>::new()
+            .route_method("eth_getProof", ["archive"])
+            .route_prefix("trace_", ["paid"]);
+        let called = Rc::new(RefCell::new(Vec::new()));
+        let service = ServiceBuilder::new().service_fn({
+            let called = called.clone();
+            move |provider: &'static str| {
+                called.borrow_mut().push(provider);
+                async move { Ok::<_, Infallible>(provider) }
+            }
+        });
+        let requests = PROVIDERS.map(|provider| (provider, provider));
+
+        let (service, results) =
+            parallel_call_with_routing(service, requests, "eth_getProof", &policy).await;
+        assert_eq!(results.unwrap().reduce(ReduceWithEquality), Ok("archive"));
+        assert_eq!(*called.borrow(), vec!["archive"]);
+
+        let (_service, results) = parallel_call_with_routing(
+            service,
+            [("archive", "archive"), ("public", "public")],
+            "trace_block",
+            &policy,
+        )
+        .await;
+        assert_eq!(
+            results,
+            Err(NoEligibleProviderError {
+                method: "trace_block".to_string()
+            })
+        );
+        assert_eq!(*called.borrow(), vec!["archive"]);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{observability::ResponseObserver, HttpsOutcallError};
+use ic_error_types::RejectCode;
+
+/// Trait used to tell [`Observability`] what to do when an outcall is rejected by the
+/// Internet Computer, see [`ObservabilityLayer::on_reject`].
+///
+/// [`Observability`]: crate::observability::Observability
+/// [`ObservabilityLayer::on_reject`]: crate::observability::ObservabilityLayer::on_reject
+pub trait RejectObserver<RequestData> {
+    /// Observe the reject code and message, and the request data produced by a
+    /// [`RequestObserver`](crate::observability::RequestObserver).
+    fn observe_reject(&self, request_data: RequestData, code: RejectCode, message: &str);
+}
+
+impl<RequestData> RejectObserver<RequestData> for () {
+    fn observe_reject(&self, _request_data: RequestData, _code: RejectCode, _message: &str) {
+        //NOP
+    }
+}
+
+impl<F, RequestData> RejectObserver<RequestData> for F
+where
+    F: Fn(RequestData, RejectCode, &str),
+{
+    fn observe_reject(&self, request_data: RequestData, code: RejectCode, message: &str) {
+        self(request_data, code, message);
+    }
+}
+
+/// [`ResponseObserver`] passing rejected outcalls to a [`RejectObserver`] and all other
+/// errors to the wrapped error observer, see [`ObservabilityLayer::on_reject`].
+///
+/// [`ObservabilityLayer::on_reject`]: crate::observability::ObservabilityLayer::on_reject
+#[derive(Clone, Debug)]
+pub struct ObserveReject<OnReject, OnError> {
+    on_reject: OnReject,
+    on_error: OnError,
+}
+
+impl<OnReject, OnError> ObserveReject<OnReject, OnError> {
+    pub(super) fn new(on_reject: OnReject, on_error: OnError) -> Self {
+        Self {
+            on_reject,
+            on_error,
+        }
+    }
+}
+
+impl<OnReject, OnError, RequestData, Error> ResponseObserver<RequestData, Error>
+    for ObserveReject<OnReject, OnError>
+where
+    OnReject: RejectObserver<RequestData>,
+    OnError: ResponseObserver<RequestData, Error>,
+    Error: HttpsOutcallError,
+{
+    fn observe_response(&self, request_data: RequestData, error: &Error) {
+        match error.reject() {
+            Some((code, message)) => self.on_reject.observe_reject(request_data, code, message),
+            None => self.on_error.observe_response(request_data, error),
+        }
+    }
+}
+
+/// [`ResponseObserver`] passing timeouts to a dedicated observer and all other errors
+/// to the wrapped error observer, see [`ObservabilityLayer::on_timeout`].
+///
+/// [`ObservabilityLayer::on_timeout`]: crate::observability::ObservabilityLayer::on_timeout
+#[derive(Clone, Debug)]
+pub struct ObserveTimeout<OnTimeout, OnError> {
+    on_timeout: OnTimeout,
+    on_error: OnError,
+}
+
+impl<OnTimeout, OnError> ObserveTimeout<OnTimeout, OnError> {
+    pub(super) fn new(on_timeout: OnTimeout, on_error: OnError) -> Self {
+        Self {
+            on_timeout,
+            on_error,
+        }
+    }
+}
+
+impl<OnTimeout, OnError, RequestData, Error> ResponseObserver<RequestData, Error>
+    for ObserveTimeout<OnTimeout, OnError>
+where
+    OnTimeout: ResponseObserver<RequestData, Error>,
+    OnError: ResponseObserver<RequestData, Error>,
+    Error: HttpsOutcallError,
+{
+    fn observe_response(&self, request_data: RequestData, error: &Error) {
+        if error.is_timeout() {
+            self.on_timeout.observe_response(request_data, error);
+        } else {
+            self.on_error.observe_response(request_data, error);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{
+    clock::Clock,
+    observability::{LoggableRequest, Observability, RequestObserver, ResponseObserver},
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    fmt,
+    rc::Rc,
+};
+use tower::Layer;
+
+/// HTTPs outcall currently in flight, as registered by an [`InFlightLayer`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InFlightRequest {
+    /// Identifier of the request in the registry, unique among all registered requests.
+    pub id: u64,
+    /// Host of the request URL, e.g. `internetcomputer.org`.
+    pub host: String,
+    /// HTTP method of the request, e.g. `POST`.
+    pub method: String,
+    /// Time at which the request was sent, in nanoseconds since the UNIX epoch.
+    pub start_ns: u64,
+    /// Attempt number of the request, if known, see [`CountAttempts`](crate::observability::CountAttempts).
+    pub attempt: Option<u32>,
+}
+
+/// Registry of the HTTPs outcalls currently in flight, e.g. to expose them via a query endpoint
+/// of the canister for debugging stuck calls.
+///
+/// All clones share the same requests. See [`InFlightLayer`] to register requests.
+#[derive(Clone, Debug, Default)]
+pub struct InFlightRegistry {
+    requests: Rc<RefCell<BTreeMap<u64, InFlightRequest>>>,
+    next_id: Rc<Cell<u64>>,
+}
+
+impl InFlightRegistry {
+    /// Create a new empty [`InFlightRegistry`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the requests currently in flight, in the order in which they were sent.
+    pub fn snapshot(&self) -> Vec<InFlightRequest> {
+        self.requests.borrow().values().cloned().collect()
+    }
+
+    /// Returns the number of requests currently in flight.
+    pub fn len(&self) -> usize {
+        self.requests.borrow().len()
+    }
+
+    /// Returns `true` if no request is currently in flight.
+    pub fn is_empty(&self) -> bool {
+        self.requests.borrow().is_empty()
+    }
+
+    fn register(&self, mut request: InFlightRequest) -> InFlightGuard {
+        let id = self.next_id.get();
+        self.next_id.set(id.wrapping_add(1));
+        request.id = id;
+        self.requests.borrow_mut().insert(id, request);
+        InFlightGuard {
+            registry: self.clone(),
+            id,
+        }
+    }
+}
+
+/// Data observed from a request by [`RegisterInFlight`], which removes the request from the
+/// [`InFlightRegistry`] when dropped, i.e. once the request completed.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    registry: InFlightRegistry,
+    id: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.registry.requests.borrow_mut().remove(&self.id);
+    }
+}
+
+/// [`Layer`] that registers HTTPs outcalls in an [`InFlightRegistry`] while they are in flight.
+///
+/// Each attempt of a retried request is registered separately if the layer is placed below
+/// the retry layer.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::observability::{InFlightLayer, InFlightRegistry};
+/// use ic_cdk_management_canister::{HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let registry = InFlightRegistry::new();
+///
+/// let observed = registry.clone();
+/// let mut service = ServiceBuilder::new()
+///     .layer(InFlightLayer::new(registry.clone()).clock(|| 1_000))
+///     .service_fn(move |_request: IcHttpRequest| {
+///         let observed = observed.clone();
+///         async move {
+///             let in_flight = observed.snapshot();
+///             assert_eq!(in_flight.len(), 1);
+///             assert_eq!(in_flight[0].host, "internetcomputer.org");
+///             assert_eq!(in_flight[0].method, "GET");
+///             assert_eq!(in_flight[0].start_ns, 1_000);
+///             Ok::<_, BoxError>(IcHttpResponse::default())
+///         }
+///     });
+///
+/// let request = IcHttpRequest {
+///     url: "https://internetcomputer.org/".to_string(),
+///     ..Default::default()
+/// };
+/// service.ready().await?.call(request).await?;
+///
+/// assert!(registry.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InFlightLayer {
+    registry: InFlightRegistry,
+    clock: Clock,
+}
+
+impl InFlightLayer {
+    /// Create a new [`InFlightLayer`] registering requests in the given registry.
+    pub fn new(registry: InFlightRegistry) -> Self {
+        Self {
+            registry,
+            clock: Clock::default(),
+        }
+    }
+
+    /// Change the clock used to record when requests started.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+}
+
+impl fmt::Debug for InFlightLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InFlightLayer")
+            .field("registry", &self.registry)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for InFlightLayer {
+    type Service = Observability<S, RegisterInFlight, UnregisterInFlight, UnregisterInFlight>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Observability {
+            inner,
+            on_request: RegisterInFlight {
+                registry: self.registry.clone(),
+                clock: self.clock.clone(),
+            },
+            on_response: UnregisterInFlight,
+            on_error: UnregisterInFlight,
+            redaction: (),
+        }
+    }
+}
+
+/// [`RequestObserver`] used by [`InFlightLayer`].
+#[derive(Clone)]
+pub struct RegisterInFlight {
+    registry: InFlightRegistry,
+    clock: Clock,
+}
+
+impl<Request: LoggableRequest> RequestObserver<Request> for RegisterInFlight {
+    type ObservableRequestData = InFlightGuard;
+
+    fn observe_request(&self, request: &Request) -> Self::ObservableRequestData {
+        let labels = request.metric_labels();
+        self.registry.register(InFlightRequest {
+            id: 0,
+            host: labels.host,
+            method: labels.method,
+            start_ns: self.clock.now(),
+            attempt: request.attempt(),
+        })
+    }
+}
+
+/// [`ResponseObserver`] used by [`InFlightLayer`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnregisterInFlight;
+
+impl<Response> ResponseObserver<InFlightGuard, Response> for UnregisterInFlight {
+    fn observe_response(&self, request_data: InFlightGuard, _value: &Response) {
+        drop(request_data);
+    }
+}
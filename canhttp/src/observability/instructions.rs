@@ -0,0 +1,317 @@
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Number of instructions executed by the canister for the different phases of a request,
+/// measured by an [`InstructionCounterLayer`].
+///
+/// The counts are added to the extensions of the response, where they can be read, e.g. by
+/// a [`ResponseObserver`](crate::observability::ResponseObserver).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct InstructionCounts {
+    /// Instructions executed before the first outcall, e.g. to serialize the request.
+    pub serialization: u64,
+    /// Instructions executed while issuing the outcalls, including retries.
+    pub outcall: u64,
+    /// Instructions executed after the last outcall, e.g. to deserialize the response.
+    pub deserialization: u64,
+}
+
+impl InstructionCounts {
+    /// Returns the total number of instructions executed for the request.
+    pub fn total(&self) -> u64 {
+        self.serialization
+            .saturating_add(self.outcall)
+            .saturating_add(self.deserialization)
+    }
+}
+
+/// Instruction counter values recorded by [`MarkOutcall`] and shared with
+/// [`InstructionCounter`] through the request extensions.
+#[derive(Debug)]
+struct OutcallMarks {
+    first_start: AtomicU64,
+    last_end: AtomicU64,
+    outcall: AtomicU64,
+}
+
+#[derive(Clone, Debug)]
+struct OutcallMarksExtension(Arc<OutcallMarks>);
+
+/// [`Layer`] that measures the instructions executed by the canister to serialize a request,
+/// to issue the outcall and to deserialize the response, see [`InstructionCounts`].
+///
+/// This layer should be placed above the layers serializing requests and deserializing
+/// responses, while the layer returned by [`InstructionCounterLayer::mark_outcall`] should be
+/// placed right above the [`Client`](crate::Client), or the conversion to its request type.
+/// The counts are only recorded for successful responses of requests that went through both layers.
+///
+/// Instructions are measured with the call context instruction counter
+/// (see [`ic_cdk::api::performance_counter`]), which keeps counting across the awaits of the outcalls.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::observability::{InstructionCounterLayer, InstructionCounts, ObservabilityLayer};
+/// use std::{cell::Cell, rc::Rc};
+/// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let instructions = InstructionCounterLayer::new();
+/// # let counter = Rc::new(Cell::new(0_u64));
+/// # let instructions = instructions.counter({
+/// #     let counter = counter.clone();
+/// #     move || { counter.set(counter.get() + 100); counter.get() }
+/// # });
+///
+/// let mut service = ServiceBuilder::new()
+///     .layer(ObservabilityLayer::new().on_response(|_: (), response: &http::Response<String>| {
+///         let counts = response.extensions().get::<InstructionCounts>().unwrap();
+///         ic_cdk::println!("Instructions: {counts:?}");
+///     }))
+///     .layer(instructions.clone())
+///     // Serialize requests and deserialize responses
+///     .map_request(|request: http::Request<String>| request.map(String::into_bytes))
+///     .map_response(|response: http::Response<Vec<u8>>| {
+///         response.map(|body| String::from_utf8(body).unwrap())
+///     })
+///     .layer(instructions.mark_outcall())
+///     .service_fn(|request: http::Request<Vec<u8>>| async move {
+///         Ok::<_, BoxError>(http::Response::new(request.into_body()))
+///     });
+///
+/// let request = http::Request::post("https://example.com").body("Hello, world!".to_string())?;
+/// let response = service.ready().await?.call(request).await?;
+///
+/// let counts = response.extensions().get::<InstructionCounts>().unwrap();
+/// assert_eq!(counts.total(), 300);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct InstructionCounterLayer {
+    counter: Rc<dyn Fn() -> u64>,
+}
+
+impl InstructionCounterLayer {
+    /// Create a new [`InstructionCounterLayer`] using the call context instruction counter.
+    pub fn new() -> Self {
+        Self {
+            counter: Rc::new(|| ic_cdk::api::performance_counter(1)),
+        }
+    }
+
+    /// Change how the instruction counter is read.
+    ///
+    /// By default, the call context instruction counter is used, i.e.
+    /// `ic_cdk::api::performance_counter(1)`.
+    pub fn counter(mut self, counter: impl Fn() -> u64 + 'static) -> Self {
+        self.counter = Rc::new(counter);
+        self
+    }
+
+    /// Returns the [`Layer`] marking the start and end of outcalls.
+    pub fn mark_outcall(&self) -> MarkOutcallLayer {
+        MarkOutcallLayer {
+            counter: self.counter.clone(),
+        }
+    }
+}
+
+impl Default for InstructionCounterLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for InstructionCounterLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InstructionCounterLayer")
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for InstructionCounterLayer {
+    type Service = InstructionCounter<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        InstructionCounter {
+            inner,
+            counter: self.counter.clone(),
+        }
+    }
+}
+
+/// Middleware that measures the instructions executed for each request,
+/// see [`InstructionCounterLayer`].
+#[derive(Clone)]
+pub struct InstructionCounter<S> {
+    inner: S,
+    counter: Rc<dyn Fn() -> u64>,
+}
+
+impl<S, T, U> Service<http::Request<T>> for InstructionCounter<S>
+where
+    S: Service<http::Request<T>, Response = http::Response<U>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = InstructionCounterFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<T>) -> Self::Future {
+        let marks = Arc::new(OutcallMarks {
+            first_start: AtomicU64::new(u64::MAX),
+            last_end: AtomicU64::new(0),
+            outcall: AtomicU64::new(0),
+        });
+        request
+            .extensions_mut()
+            .insert(OutcallMarksExtension(marks.clone()));
+        let start = (self.counter)();
+        InstructionCounterFuture {
+            response_future: self.inner.call(request),
+            counter: self.counter.clone(),
+            start,
+            marks,
+        }
+    }
+}
+
+/// Response future for [`InstructionCounter`].
+#[pin_project]
+pub struct InstructionCounterFuture<F> {
+    #[pin]
+    response_future: F,
+    counter: Rc<dyn Fn() -> u64>,
+    start: u64,
+    marks: Arc<OutcallMarks>,
+}
+
+impl<F, U, Error> Future for InstructionCounterFuture<F>
+where
+    F: Future<Output = Result<http::Response<U>, Error>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = match this.response_future.poll(cx) {
+            Poll::Ready(Ok(response)) => response,
+            other => return other,
+        };
+        let end = (this.counter)();
+        let first_start = this.marks.first_start.load(Ordering::Relaxed);
+        if first_start != u64::MAX {
+            let last_end = this.marks.last_end.load(Ordering::Relaxed);
+            response.extensions_mut().insert(InstructionCounts {
+                serialization: first_start.saturating_sub(*this.start),
+                outcall: this.marks.outcall.load(Ordering::Relaxed),
+                deserialization: end.saturating_sub(last_end),
+            });
+        }
+        Poll::Ready(Ok(response))
+    }
+}
+
+/// [`Layer`] marking the start and end of outcalls for an [`InstructionCounterLayer`],
+/// see [`InstructionCounterLayer::mark_outcall`].
+#[derive(Clone)]
+pub struct MarkOutcallLayer {
+    counter: Rc<dyn Fn() -> u64>,
+}
+
+impl fmt::Debug for MarkOutcallLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MarkOutcallLayer").finish_non_exhaustive()
+    }
+}
+
+impl<S> Layer<S> for MarkOutcallLayer {
+    type Service = MarkOutcall<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MarkOutcall {
+            inner,
+            counter: self.counter.clone(),
+        }
+    }
+}
+
+/// Middleware marking the start and end of outcalls, see [`MarkOutcallLayer`].
+#[derive(Clone)]
+pub struct MarkOutcall<S> {
+    inner: S,
+    counter: Rc<dyn Fn() -> u64>,
+}
+
+impl<S, T> Service<http::Request<T>> for MarkOutcall<S>
+where
+    S: Service<http::Request<T>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = MarkOutcallFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<T>) -> Self::Future {
+        let marks = request
+            .extensions()
+            .get::<OutcallMarksExtension>()
+            .map(|e| e.0.clone());
+        let start = (self.counter)();
+        if let Some(marks) = &marks {
+            marks.first_start.fetch_min(start, Ordering::Relaxed);
+        }
+        MarkOutcallFuture {
+            response_future: self.inner.call(request),
+            counter: self.counter.clone(),
+            start,
+            marks,
+        }
+    }
+}
+
+/// Response future for [`MarkOutcall`].
+#[pin_project]
+pub struct MarkOutcallFuture<F> {
+    #[pin]
+    response_future: F,
+    counter: Rc<dyn Fn() -> u64>,
+    start: u64,
+    marks: Option<Arc<OutcallMarks>>,
+}
+
+impl<F: Future> Future for MarkOutcallFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = this.response_future.poll(cx);
+        if let (Poll::Ready(_), Some(marks)) = (&result, this.marks.take()) {
+            let end = (this.counter)();
+            marks
+                .outcall
+                .fetch_add(end.saturating_sub(*this.start), Ordering::Relaxed);
+            marks.last_end.store(end, Ordering::Relaxed);
+        }
+        result
+    }
+}
@@ -0,0 +1,564 @@
+#[cfg(feature = "http")]
+use crate::observability::RequestMetadataExtension;
+use crate::{
+    clock::Clock,
+    error::ErrorCategory,
+    observability::{
+        sampling::Sampler, MeteredError, MeteredRequest, MeteredResponse, Observability, Redaction,
+        RequestMetadata, RequestObserver, ResponseObserver,
+    },
+};
+use ic_cdk_management_canister::{
+    HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+};
+use std::{fmt, num::NonZeroU64, rc::Rc, time::Duration};
+use tower::Layer;
+
+/// Destination of the log messages produced by [`LoggingLayer`].
+///
+/// This trait is implemented for closures taking a `&str`, so that messages can be forwarded to
+/// any logging library.
+pub trait LogSink {
+    /// Log the given message.
+    fn log(&self, message: &str);
+
+    /// Log the given event.
+    ///
+    /// By default, the event is logged with [`LogSink::log`] in its
+    /// [`Display`](fmt::Display) format. Override this method to handle the event fields
+    /// differently, e.g. to serialize them in another format.
+    fn log_event(&self, event: &HttpEvent) {
+        self.log(&event.to_string())
+    }
+}
+
+impl<F: Fn(&str)> LogSink for F {
+    fn log(&self, message: &str) {
+        self(message)
+    }
+}
+
+/// [`LogSink`] printing messages to the canister debug output with [`ic_cdk::println`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrintlnLogSink;
+
+impl LogSink for PrintlnLogSink {
+    fn log(&self, message: &str) {
+        ic_cdk::println!("{message}");
+    }
+}
+
+/// Severity of an [`HttpEvent`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum LogLevel {
+    /// Details useful when debugging, e.g. that a request is sent.
+    Debug,
+    /// Normal operation, e.g. a successful response.
+    Info,
+    /// Unexpected but handled situation, e.g. a non-successful HTTP status.
+    Warn,
+    /// Failure, e.g. a rejected HTTPs outcall.
+    Error,
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        })
+    }
+}
+
+/// Phase of an HTTPs outcall in which an [`HttpEvent`] occurred.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum HttpEventPhase {
+    /// The request is sent.
+    Request,
+    /// A response was received.
+    Response,
+    /// An error occurred.
+    Error,
+}
+
+impl fmt::Display for HttpEventPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HttpEventPhase::Request => "request",
+            HttpEventPhase::Response => "response",
+            HttpEventPhase::Error => "error",
+        })
+    }
+}
+
+/// Structured record of an HTTPs outcall produced by [`LoggingLayer`].
+///
+/// Events are displayed in the [logfmt](https://brandur.org/logfmt) format, e.g.
+/// ```text
+/// level=INFO target=canhttp phase=response method=GET host=example.com url=https://example.com/ status=200 size=13 duration_ms=120
+/// ```
+/// where fields without a value are omitted and the entries of the request metadata are
+/// appended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpEvent {
+    /// Severity of the event.
+    pub level: LogLevel,
+    /// Target of the event, see [`LoggingLayer::target`].
+    pub target: String,
+    /// Phase of the outcall.
+    pub phase: HttpEventPhase,
+    /// HTTP method of the request, e.g. `GET`.
+    pub method: String,
+    /// Host of the request URL, e.g. `example.com`.
+    pub host: String,
+    /// Request URL, redacted by the [`LoggingLayer`].
+    pub url: String,
+    /// Attempt number of the request, if known, see [`CountAttempts`].
+    pub attempt: Option<u32>,
+    /// HTTP status of the response.
+    pub status: Option<u16>,
+    /// Size of the response body in bytes.
+    pub response_size: Option<u64>,
+    /// Time elapsed since the request was sent.
+    pub duration: Option<Duration>,
+    /// Category of the error, if known.
+    pub error_category: Option<ErrorCategory>,
+    /// Description of the error.
+    pub error: Option<String>,
+    /// Metadata of the request, see [`RequestMetadataExtension`](crate::observability::RequestMetadataExtension).
+    pub metadata: RequestMetadata,
+}
+
+impl fmt::Display for HttpEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "level={} target={} phase={} method={} host={} url={}",
+            self.level,
+            LogfmtValue(&self.target),
+            self.phase,
+            LogfmtValue(&self.method),
+            LogfmtValue(&self.host),
+            LogfmtValue(&self.url)
+        )?;
+        if let Some(attempt) = self.attempt {
+            write!(f, " attempt={attempt}")?;
+        }
+        if let Some(status) = self.status {
+            write!(f, " status={status}")?;
+        }
+        if let Some(size) = self.response_size {
+            write!(f, " size={size}")?;
+        }
+        if let Some(duration) = self.duration {
+            write!(f, " duration_ms={}", duration.as_millis())?;
+        }
+        if let Some(category) = self.error_category {
+            let category = match category {
+                ErrorCategory::Network => "network",
+                ErrorCategory::Request => "request",
+                ErrorCategory::Upstream => "upstream",
+                ErrorCategory::Decoding => "decoding",
+            };
+            write!(f, " error_category={category}")?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " error={}", LogfmtValue(error))?;
+        }
+        for (key, value) in self.metadata.iter() {
+            write!(f, " {}={}", LogfmtValue(key), LogfmtValue(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Quote values that would otherwise be ambiguous in logfmt.
+struct LogfmtValue<'a>(&'a str);
+
+impl fmt::Display for LogfmtValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty()
+            || self
+                .0
+                .contains(|c: char| c.is_whitespace() || c.is_control() || c == '"' || c == '=')
+        {
+            write!(f, "{:?}", self.0)
+        } else {
+            f.write_str(self.0)
+        }
+    }
+}
+
+/// Request that can be logged by [`LoggingLayer`].
+pub trait LoggableRequest: MeteredRequest {
+    /// Returns the request URL.
+    fn url(&self) -> String;
+
+    /// Returns the attempt number of the request, if known.
+    fn attempt(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns the metadata of the request, if any.
+    fn metadata(&self) -> Option<&RequestMetadata> {
+        None
+    }
+}
+
+impl LoggableRequest for IcHttpRequest {
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+#[cfg(feature = "http")]
+impl<T> LoggableRequest for http::Request<T> {
+    fn url(&self) -> String {
+        self.uri().to_string()
+    }
+
+    fn attempt(&self) -> Option<u32> {
+        self.extensions().get::<AttemptExtension>().map(|e| e.0)
+    }
+
+    fn metadata(&self) -> Option<&RequestMetadata> {
+        self.get_request_metadata()
+    }
+}
+
+/// Response that can be logged by [`LoggingLayer`].
+pub trait LoggableResponse: MeteredResponse {
+    /// Returns the HTTP status code of the response.
+    fn status_code(&self) -> u16;
+}
+
+impl LoggableResponse for IcHttpResponse {
+    fn status_code(&self) -> u16 {
+        self.status.0.to_string().parse().unwrap_or(u16::MAX)
+    }
+}
+
+#[cfg(feature = "http")]
+impl LoggableResponse for http::Response<Vec<u8>> {
+    fn status_code(&self) -> u16 {
+        self.status().as_u16()
+    }
+}
+
+/// [`Layer`] that logs requests, responses and errors as [`HttpEvent`]s to a [`LogSink`].
+///
+/// Each request produces an event when it is sent (at [`LogLevel::Debug`]) and an event
+/// when its response (at [`LogLevel::Info`], or [`LogLevel::Warn`] for a non-successful
+/// status) or its error (at [`LogLevel::Error`]) is received.
+/// Events below the [minimum level](LoggingLayer::min_level) are discarded.
+/// Sensitive URL query parameters and user information are masked, see [`LoggingLayer::redact`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{observability::{LogLevel, LoggingLayer}, IcError};
+/// use ic_cdk_management_canister::{HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse};
+/// use std::{cell::RefCell, rc::Rc};
+/// use tower::{Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let logs = Rc::new(RefCell::new(Vec::new()));
+/// let sink = {
+///     let logs = logs.clone();
+///     move |message: &str| logs.borrow_mut().push(message.to_string())
+/// };
+///
+/// // In a canister, simply use `LoggingLayer::new(PrintlnLogSink)`.
+/// let mut service = ServiceBuilder::new()
+///     .layer(LoggingLayer::new(sink).min_level(LogLevel::Info).clock(|| 0))
+///     .service_fn(|_request: IcHttpRequest| async move {
+///         Ok::<_, IcError>(IcHttpResponse {
+///             status: 200_u16.into(),
+///             body: b"Hello, world!".to_vec(),
+///             ..Default::default()
+///         })
+///     });
+///
+/// let request = IcHttpRequest {
+///     url: "https://internetcomputer.org/".to_string(),
+///     ..Default::default()
+/// };
+/// service.ready().await?.call(request).await?;
+///
+/// assert_eq!(
+///     *logs.borrow(),
+///     vec![
+///         "level=INFO target=canhttp phase=response method=GET host=internetcomputer.org \
+///          url=https://internetcomputer.org/ status=200 size=13 duration_ms=0",
+///     ]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LoggingLayer<L> {
+    logger: Logger<L>,
+}
+
+impl<L> LoggingLayer<L> {
+    /// Create a new [`LoggingLayer`] logging all events to the given sink
+    /// with the `canhttp` target.
+    pub fn new(sink: L) -> Self {
+        Self {
+            logger: Logger {
+                sink,
+                target: Rc::from("canhttp"),
+                min_level: LogLevel::Debug,
+                redaction: Redaction::new(),
+                sampler: None,
+                clock: Clock::default(),
+            },
+        }
+    }
+
+    /// Set the target of the events, e.g. the name of the component issuing the requests.
+    pub fn target(mut self, target: impl AsRef<str>) -> Self {
+        self.logger.target = Rc::from(target.as_ref());
+        self
+    }
+
+    /// Discard events below the given level.
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.logger.min_level = level;
+        self
+    }
+
+    /// Only log the events of 1 in `one_in` requests, except for errors and non-successful
+    /// responses which are always logged. The first request is always logged.
+    pub fn sample(mut self, one_in: NonZeroU64) -> Self {
+        self.logger.sampler = Some(Sampler::new(one_in));
+        self
+    }
+
+    /// Change which parts of the request URLs are masked.
+    ///
+    /// By default, [`Redaction::new`] is used.
+    pub fn redact(mut self, redaction: Redaction) -> Self {
+        self.logger.redaction = redaction;
+        self
+    }
+
+    /// Change the clock used to measure the duration of requests.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.logger.clock = Clock::new(clock);
+        self
+    }
+}
+
+impl<L: fmt::Debug> fmt::Debug for LoggingLayer<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoggingLayer")
+            .field("sink", &self.logger.sink)
+            .field("target", &self.logger.target)
+            .field("min_level", &self.logger.min_level)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, L: Clone> Layer<S> for LoggingLayer<L> {
+    type Service = Observability<S, LogRequest<L>, LogResponse<L>, LogError<L>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Observability {
+            inner,
+            on_request: LogRequest {
+                logger: self.logger.clone(),
+            },
+            on_response: LogResponse {
+                logger: self.logger.clone(),
+            },
+            on_error: LogError {
+                logger: self.logger.clone(),
+            },
+            redaction: (),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Logger<L> {
+    sink: L,
+    target: Rc<str>,
+    min_level: LogLevel,
+    redaction: Redaction,
+    sampler: Option<Sampler>,
+    clock: Clock,
+}
+
+impl<L: LogSink> Logger<L> {
+    fn log(&self, event: HttpEvent) {
+        if event.level >= self.min_level {
+            self.sink.log_event(&event);
+        }
+    }
+}
+
+/// Data observed from a request by [`LogRequest`].
+#[derive(Clone, Debug)]
+pub struct LoggedRequestData {
+    event: HttpEvent,
+    start_ns: u64,
+    sampled: bool,
+}
+
+impl LoggedRequestData {
+    fn into_event(self, level: LogLevel, phase: HttpEventPhase, clock: &Clock) -> HttpEvent {
+        HttpEvent {
+            level,
+            phase,
+            duration: Some(Duration::from_nanos(
+                clock.now().saturating_sub(self.start_ns),
+            )),
+            ..self.event
+        }
+    }
+}
+
+/// [`RequestObserver`] used by [`LoggingLayer`].
+#[derive(Clone)]
+pub struct LogRequest<L> {
+    logger: Logger<L>,
+}
+
+impl<L, Request> RequestObserver<Request> for LogRequest<L>
+where
+    L: LogSink,
+    Request: LoggableRequest,
+{
+    type ObservableRequestData = LoggedRequestData;
+
+    fn observe_request(&self, request: &Request) -> Self::ObservableRequestData {
+        let labels = request.metric_labels();
+        let event = HttpEvent {
+            level: LogLevel::Debug,
+            target: self.logger.target.to_string(),
+            phase: HttpEventPhase::Request,
+            method: labels.method,
+            host: labels.host,
+            url: self.logger.redaction.redact_url(&request.url()),
+            attempt: request.attempt(),
+            status: None,
+            response_size: None,
+            duration: None,
+            error_category: None,
+            error: None,
+            metadata: request.metadata().cloned().unwrap_or_default(),
+        };
+        let sampled = self
+            .logger
+            .sampler
+            .as_ref()
+            .is_none_or(|sampler| sampler.sample());
+        if sampled {
+            self.logger.log(event.clone());
+        }
+        LoggedRequestData {
+            event,
+            start_ns: self.logger.clock.now(),
+            sampled,
+        }
+    }
+}
+
+/// [`ResponseObserver`] of responses used by [`LoggingLayer`].
+#[derive(Clone)]
+pub struct LogResponse<L> {
+    logger: Logger<L>,
+}
+
+impl<L, Response> ResponseObserver<LoggedRequestData, Response> for LogResponse<L>
+where
+    L: LogSink,
+    Response: LoggableResponse,
+{
+    fn observe_response(&self, request_data: LoggedRequestData, value: &Response) {
+        let status = value.status_code();
+        let level = if (200..400).contains(&status) {
+            LogLevel::Info
+        } else {
+            LogLevel::Warn
+        };
+        if !request_data.sampled && level < LogLevel::Warn {
+            return;
+        }
+        self.logger.log(HttpEvent {
+            status: Some(status),
+            response_size: Some(value.response_size()),
+            ..request_data.into_event(level, HttpEventPhase::Response, &self.logger.clock)
+        });
+    }
+}
+
+/// [`ResponseObserver`] of errors used by [`LoggingLayer`].
+#[derive(Clone)]
+pub struct LogError<L> {
+    logger: Logger<L>,
+}
+
+impl<L, Error> ResponseObserver<LoggedRequestData, Error> for LogError<L>
+where
+    L: LogSink,
+    Error: MeteredError + fmt::Display,
+{
+    fn observe_response(&self, request_data: LoggedRequestData, value: &Error) {
+        self.logger.log(HttpEvent {
+            error_category: value.error_category(),
+            error: Some(value.to_string()),
+            ..request_data.into_event(LogLevel::Error, HttpEventPhase::Error, &self.logger.clock)
+        });
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct AttemptExtension(u32);
+
+/// Retry policy that records the attempt number of retried [`http::Request`]s, so that it
+/// appears in the [`HttpEvent`]s produced by a [`LoggingLayer`] placed below the retry layer.
+///
+/// The first attempt of a request has no attempt number, the first retry is attempt 2.
+#[cfg(feature = "http")]
+#[derive(Clone, Debug)]
+pub struct CountAttempts<P> {
+    policy: P,
+}
+
+#[cfg(feature = "http")]
+impl<P> CountAttempts<P> {
+    /// Wrap the given retry policy.
+    pub fn new(policy: P) -> Self {
+        Self { policy }
+    }
+}
+
+#[cfg(feature = "http")]
+impl<P, T, Response, Error> tower::retry::Policy<http::Request<T>, Response, Error>
+    for CountAttempts<P>
+where
+    P: tower::retry::Policy<http::Request<T>, Response, Error>,
+{
+    type Future = P::Future;
+
+    fn retry(
+        &mut self,
+        req: &mut http::Request<T>,
+        result: &mut Result<Response, Error>,
+    ) -> Option<Self::Future> {
+        let future = self.policy.retry(req, result)?;
+        let attempt = req.attempt().unwrap_or(1).saturating_add(1);
+        req.extensions_mut().insert(AttemptExtension(attempt));
+        Some(future)
+    }
+
+    fn clone_request(&mut self, req: &http::Request<T>) -> Option<http::Request<T>> {
+        self.policy.clone_request(req)
+    }
+}
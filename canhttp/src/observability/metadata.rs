@@ -0,0 +1,126 @@
+use crate::observability::RequestObserver;
+use std::{collections::BTreeMap, fmt};
+
+/// Application-specific key/value pairs attached to a request, e.g. `price-feed: BTC/USD`,
+/// to give context to the observability hooks, see [`RequestMetadataExtension`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RequestMetadata(BTreeMap<String, String>);
+
+impl RequestMetadata {
+    /// Create new empty [`RequestMetadata`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the given entry, replacing any previous value for the same key.
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Insert the given entry and return the previous value for the same key, if any.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    /// Returns the value for the given key, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Returns an iterator over the entries, sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for RequestMetadata {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for RequestMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<_> = self.iter().map(|(k, v)| format!("{k}: {v}")).collect();
+        write!(f, "{}", entries.join(", "))
+    }
+}
+
+/// Add support for [`RequestMetadata`].
+pub trait RequestMetadataExtension: Sized {
+    /// Set the request metadata.
+    fn set_request_metadata(&mut self, value: RequestMetadata);
+
+    /// Retrieves the request metadata, if any.
+    fn get_request_metadata(&self) -> Option<&RequestMetadata>;
+
+    /// Convenience method to use the builder pattern.
+    fn request_metadata(mut self, value: RequestMetadata) -> Self {
+        self.set_request_metadata(value);
+        self
+    }
+}
+
+#[cfg(feature = "http")]
+impl<T> RequestMetadataExtension for http::Request<T> {
+    fn set_request_metadata(&mut self, value: RequestMetadata) {
+        let extensions = self.extensions_mut();
+        extensions.insert(value);
+    }
+
+    fn get_request_metadata(&self) -> Option<&RequestMetadata> {
+        self.extensions().get::<RequestMetadata>()
+    }
+}
+
+#[cfg(feature = "http")]
+impl RequestMetadataExtension for http::request::Builder {
+    fn set_request_metadata(&mut self, value: RequestMetadata) {
+        if let Some(extensions) = self.extensions_mut() {
+            extensions.insert(value);
+        }
+    }
+
+    fn get_request_metadata(&self) -> Option<&RequestMetadata> {
+        self.extensions_ref()
+            .and_then(|extensions| extensions.get::<RequestMetadata>())
+    }
+}
+
+/// [`RequestObserver`] adding the [`RequestMetadata`] of requests to the observed request data,
+/// see [`ObservabilityLayer::with_metadata`].
+///
+/// [`ObservabilityLayer::with_metadata`]: crate::observability::ObservabilityLayer::with_metadata
+#[derive(Clone, Debug)]
+pub struct ObserveMetadata<O> {
+    observer: O,
+}
+
+impl<O> ObserveMetadata<O> {
+    pub(super) fn new(observer: O) -> Self {
+        Self { observer }
+    }
+}
+
+impl<O, Request> RequestObserver<Request> for ObserveMetadata<O>
+where
+    O: RequestObserver<Request>,
+    Request: RequestMetadataExtension,
+{
+    type ObservableRequestData = (RequestMetadata, O::ObservableRequestData);
+
+    fn observe_request(&self, request: &Request) -> Self::ObservableRequestData {
+        let metadata = request.get_request_metadata().cloned().unwrap_or_default();
+        (metadata, self.observer.observe_request(request))
+    }
+}
@@ -0,0 +1,706 @@
+use crate::{
+    clock::Clock,
+    cycles::{CyclesEvent, CyclesObserver},
+    error::{error_category, CanHttpError, ErrorCategory},
+    observability::{Observability, RequestObserver, ResponseObserver},
+    IcError,
+};
+use ic_cdk_management_canister::{
+    HttpMethod, HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse,
+};
+use std::{cell::RefCell, collections::BTreeMap, fmt, rc::Rc, time::Duration};
+use tower::{retry, BoxError, Layer};
+
+/// Default upper bounds (inclusive) of the buckets of the response size histogram, in bytes.
+pub const DEFAULT_RESPONSE_SIZE_BUCKETS: [u64; 8] = [
+    1_024, 4_096, 16_384, 65_536, 262_144, 524_288, 1_048_576, 2_000_000,
+];
+
+/// Default upper bounds (inclusive) of the buckets of the duration histogram, in milliseconds.
+pub const DEFAULT_DURATION_BUCKETS: [u64; 8] = [100, 250, 500, 1_000, 2_000, 5_000, 10_000, 30_000];
+
+/// Default maximum number of distinct [`MetricLabels`] recorded by [`Metrics`],
+/// see [`Metrics::max_labels`].
+pub const DEFAULT_MAX_LABELS: usize = 500;
+
+/// Host of the labels under which [`Metrics`] records the metrics of requests once the
+/// maximum number of distinct labels is reached, see [`Metrics::max_labels`].
+pub const OVERFLOW_HOST: &str = "other";
+
+/// Labels by which metrics are keyed.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MetricLabels {
+    /// Host of the request URL, e.g. `internetcomputer.org`.
+    pub host: String,
+    /// HTTP method of the request, e.g. `POST`.
+    pub method: String,
+}
+
+/// Request whose metrics can be recorded by [`MetricsLayer`].
+pub trait MeteredRequest {
+    /// Returns the labels of the metrics recorded for this request.
+    fn metric_labels(&self) -> MetricLabels;
+}
+
+impl MeteredRequest for IcHttpRequest {
+    fn metric_labels(&self) -> MetricLabels {
+        let method = match self.method {
+            HttpMethod::GET => "GET",
+            HttpMethod::POST => "POST",
+            HttpMethod::HEAD => "HEAD",
+        };
+        MetricLabels {
+            host: host(&self.url).to_string(),
+            method: method.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl<T> MeteredRequest for http::Request<T> {
+    fn metric_labels(&self) -> MetricLabels {
+        MetricLabels {
+            host: self.uri().host().unwrap_or_default().to_string(),
+            method: self.method().to_string(),
+        }
+    }
+}
+
+/// Extract the host of the given URL without parsing it entirely.
+fn host(url: &str) -> &str {
+    let authority = url.split_once("://").map_or(url, |(_scheme, rest)| rest);
+    let authority = authority.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_userinfo, host)| host);
+    match host.find(']') {
+        // IPv6 address, e.g. `[::1]:8080`
+        Some(end) if host.starts_with('[') => &host[..=end],
+        _ => host.split(':').next().unwrap_or_default(),
+    }
+}
+
+/// Response whose metrics can be recorded by [`MetricsLayer`].
+pub trait MeteredResponse {
+    /// Returns the size of the response body in bytes.
+    fn response_size(&self) -> u64;
+}
+
+impl MeteredResponse for IcHttpResponse {
+    fn response_size(&self) -> u64 {
+        self.body.len() as u64
+    }
+}
+
+#[cfg(feature = "http")]
+impl MeteredResponse for http::Response<Vec<u8>> {
+    fn response_size(&self) -> u64 {
+        self.body().len() as u64
+    }
+}
+
+/// Error whose metrics can be recorded by [`MetricsLayer`].
+pub trait MeteredError {
+    /// Returns the category of the error, if known.
+    fn error_category(&self) -> Option<ErrorCategory>;
+}
+
+impl MeteredError for CanHttpError {
+    fn error_category(&self) -> Option<ErrorCategory> {
+        Some(self.category())
+    }
+}
+
+impl MeteredError for IcError {
+    fn error_category(&self) -> Option<ErrorCategory> {
+        Some(CanHttpError::from(self.clone()).category())
+    }
+}
+
+impl MeteredError for BoxError {
+    fn error_category(&self) -> Option<ErrorCategory> {
+        error_category(self)
+    }
+}
+
+/// Records the metrics of HTTPs outcalls, see [`MetricsLayer`].
+///
+/// Implement this trait to export the metrics to another backend than [`Metrics`].
+pub trait MetricsRecorder {
+    /// Record that a request was sent.
+    fn record_request(&self, labels: &MetricLabels);
+
+    /// Record that a response of the given size was received after the given duration.
+    fn record_response(&self, labels: &MetricLabels, size: u64, duration: Duration);
+
+    /// Record that an error of the given category (`None` if unknown) occurred after the
+    /// given duration.
+    fn record_error(
+        &self,
+        labels: &MetricLabels,
+        category: Option<ErrorCategory>,
+        duration: Duration,
+    );
+
+    /// Record that a request is retried.
+    fn record_retry(&self, labels: &MetricLabels);
+
+    /// Record the cycles attached to a request and charged to the caller,
+    /// see [`MetricsLayer::record_cycles`].
+    ///
+    /// Does nothing by default.
+    fn record_cycles(&self, _labels: &MetricLabels, _attached: u128, _charged: u128) {}
+}
+
+/// Histogram with fixed buckets.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Histogram {
+    bounds: Vec<u64>,
+    counts: Vec<u64>,
+    sum: u64,
+}
+
+impl Histogram {
+    /// Create a new empty histogram with buckets of the given (inclusive, increasing) upper bounds.
+    ///
+    /// An additional bucket holds all values greater than the last bound.
+    pub fn new(bounds: Vec<u64>) -> Self {
+        debug_assert!(bounds.is_sorted(), "BUG: bounds must be increasing");
+        Self {
+            counts: vec![0; bounds.len() + 1],
+            bounds,
+            sum: 0,
+        }
+    }
+
+    pub(super) fn from_parts(bounds: Vec<u64>, counts: Vec<u64>, sum: u64) -> Option<Self> {
+        (counts.len() == bounds.len() + 1).then_some(Self {
+            bounds,
+            counts,
+            sum,
+        })
+    }
+
+    /// Add the given value to the histogram.
+    pub fn observe(&mut self, value: u64) {
+        let bucket = self.bounds.partition_point(|bound| *bound < value);
+        self.counts[bucket] += 1;
+        self.sum = self.sum.saturating_add(value);
+    }
+
+    /// Returns the upper bounds of the buckets, excluding the last unbounded bucket.
+    pub fn bounds(&self) -> &[u64] {
+        &self.bounds
+    }
+
+    /// Returns the number of observed values in each bucket, including the last unbounded bucket.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Returns the sum of all observed values.
+    pub fn sum(&self) -> u64 {
+        self.sum
+    }
+
+    /// Returns the number of observed values.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Snapshot of the metrics collected by [`Metrics`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Number of requests.
+    pub requests: BTreeMap<MetricLabels, u64>,
+    /// Number of errors by category (`None` if unknown).
+    pub errors: BTreeMap<(MetricLabels, Option<ErrorCategory>), u64>,
+    /// Number of retries.
+    pub retries: BTreeMap<MetricLabels, u64>,
+    /// Histogram of response sizes in bytes.
+    pub response_sizes: BTreeMap<MetricLabels, Histogram>,
+    /// Histogram of the durations in milliseconds of requests resulting in a response or an error.
+    pub durations: BTreeMap<MetricLabels, Histogram>,
+    /// Number of cycles attached to requests.
+    pub cycles_attached: BTreeMap<MetricLabels, u128>,
+    /// Number of cycles charged to the callers of the canister for requests.
+    pub cycles_charged: BTreeMap<MetricLabels, u128>,
+}
+
+/// Storage of the metrics collected by [`Metrics`].
+///
+/// This allows to choose where the metrics are kept, e.g. on the heap with [`HeapMetricsSink`]
+/// or in stable memory with [`StableMetricsSink`] so that they survive canister upgrades.
+pub trait MetricsSink {
+    /// Returns a copy of the stored metrics.
+    fn load(&self) -> MetricsSnapshot;
+
+    /// Modify the stored metrics with the given function.
+    fn update<F: FnOnce(&mut MetricsSnapshot)>(&self, f: F);
+}
+
+/// [`MetricsSink`] keeping the metrics on the heap.
+///
+/// Note that the metrics are lost when the canister is upgraded.
+#[derive(Clone, Debug, Default)]
+pub struct HeapMetricsSink(Rc<RefCell<MetricsSnapshot>>);
+
+impl MetricsSink for HeapMetricsSink {
+    fn load(&self) -> MetricsSnapshot {
+        self.0.borrow().clone()
+    }
+
+    fn update<F: FnOnce(&mut MetricsSnapshot)>(&self, f: F) {
+        f(&mut self.0.borrow_mut())
+    }
+}
+
+/// [`MetricsRecorder`] aggregating metrics into counters and histograms stored in a
+/// [`MetricsSink`], by default on the heap.
+///
+/// All clones share the same metrics, so that a clone can be passed to the [`MetricsLayer`]
+/// and another one be used to read the metrics, e.g. in a query endpoint of the canister.
+///
+/// To bound the memory used by the metrics, at most [`DEFAULT_MAX_LABELS`] distinct labels are
+/// recorded by default, see [`Metrics::max_labels`].
+#[derive(Clone)]
+pub struct Metrics<S = HeapMetricsSink> {
+    sink: S,
+    response_size_buckets: Option<Vec<u64>>,
+    duration_buckets: Option<Vec<u64>>,
+    max_labels: usize,
+}
+
+impl<S: Default> Default for Metrics<S> {
+    fn default() -> Self {
+        Self::with_sink(S::default())
+    }
+}
+
+impl Metrics {
+    /// Create a new empty [`Metrics`] kept on the heap with the default histogram buckets,
+    /// see [`DEFAULT_RESPONSE_SIZE_BUCKETS`] and [`DEFAULT_DURATION_BUCKETS`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Metrics<S> {
+    /// Create a new [`Metrics`] stored in the given sink with the default histogram buckets.
+    pub fn with_sink(sink: S) -> Self {
+        Self {
+            sink,
+            response_size_buckets: None,
+            duration_buckets: None,
+            max_labels: DEFAULT_MAX_LABELS,
+        }
+    }
+
+    /// Set the maximum number of distinct labels for which metrics are recorded.
+    ///
+    /// Since labels are derived from the request URLs, which may be chosen by the callers of
+    /// the canister, this bounds the memory used by the metrics. Once the maximum is reached,
+    /// metrics of requests with new labels are recorded under the labels with the host
+    /// [`OVERFLOW_HOST`] and the method of the request.
+    pub fn max_labels(mut self, max_labels: usize) -> Self {
+        self.max_labels = max_labels;
+        self
+    }
+
+    /// Set the upper bounds of the buckets of the response size histogram.
+    ///
+    /// This only applies to histograms created afterwards.
+    pub fn response_size_buckets(mut self, bounds: Vec<u64>) -> Self {
+        self.response_size_buckets = Some(bounds);
+        self
+    }
+
+    /// Set the upper bounds of the buckets of the duration histogram.
+    ///
+    /// This only applies to histograms created afterwards.
+    pub fn duration_buckets(mut self, bounds: Vec<u64>) -> Self {
+        self.duration_buckets = Some(bounds);
+        self
+    }
+}
+
+impl<S: MetricsSink> Metrics<S> {
+    /// Returns a copy of the current metrics.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.sink.load()
+    }
+
+    /// Reset all metrics.
+    pub fn reset(&self) {
+        self.sink
+            .update(|snapshot| *snapshot = MetricsSnapshot::default());
+    }
+
+    /// Returns the labels under which the metrics of the given labels are recorded and registers
+    /// them in the request counters, so that the number of distinct labels is bounded by
+    /// [`Metrics::max_labels`].
+    fn bounded_labels(
+        &self,
+        snapshot: &mut MetricsSnapshot,
+        labels: &MetricLabels,
+    ) -> MetricLabels {
+        let labels = if snapshot.requests.contains_key(labels)
+            || snapshot.requests.len() < self.max_labels
+        {
+            labels.clone()
+        } else {
+            MetricLabels {
+                host: OVERFLOW_HOST.to_string(),
+                method: labels.method.clone(),
+            }
+        };
+        snapshot.requests.entry(labels.clone()).or_default();
+        labels
+    }
+
+    fn observe_duration(
+        &self,
+        snapshot: &mut MetricsSnapshot,
+        labels: &MetricLabels,
+        duration: Duration,
+    ) {
+        snapshot
+            .durations
+            .entry(labels.clone())
+            .or_insert_with(|| {
+                Histogram::new(
+                    self.duration_buckets
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_DURATION_BUCKETS.to_vec()),
+                )
+            })
+            .observe(duration.as_millis().try_into().unwrap_or(u64::MAX));
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Metrics<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("sink", &self.sink)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: MetricsSink> MetricsRecorder for Metrics<S> {
+    fn record_request(&self, labels: &MetricLabels) {
+        self.sink.update(|snapshot| {
+            let labels = self.bounded_labels(snapshot, labels);
+            *snapshot.requests.entry(labels).or_default() += 1;
+        });
+    }
+
+    fn record_response(&self, labels: &MetricLabels, size: u64, duration: Duration) {
+        self.sink.update(|snapshot| {
+            let labels = self.bounded_labels(snapshot, labels);
+            snapshot
+                .response_sizes
+                .entry(labels.clone())
+                .or_insert_with(|| {
+                    Histogram::new(
+                        self.response_size_buckets
+                            .clone()
+                            .unwrap_or_else(|| DEFAULT_RESPONSE_SIZE_BUCKETS.to_vec()),
+                    )
+                })
+                .observe(size);
+            self.observe_duration(snapshot, &labels, duration);
+        });
+    }
+
+    fn record_error(
+        &self,
+        labels: &MetricLabels,
+        category: Option<ErrorCategory>,
+        duration: Duration,
+    ) {
+        self.sink.update(|snapshot| {
+            let labels = self.bounded_labels(snapshot, labels);
+            *snapshot
+                .errors
+                .entry((labels.clone(), category))
+                .or_default() += 1;
+            self.observe_duration(snapshot, &labels, duration);
+        });
+    }
+
+    fn record_retry(&self, labels: &MetricLabels) {
+        self.sink.update(|snapshot| {
+            let labels = self.bounded_labels(snapshot, labels);
+            *snapshot.retries.entry(labels).or_default() += 1;
+        });
+    }
+
+    fn record_cycles(&self, labels: &MetricLabels, attached: u128, charged: u128) {
+        self.sink.update(|snapshot| {
+            let labels = self.bounded_labels(snapshot, labels);
+            let total_attached = snapshot.cycles_attached.entry(labels.clone()).or_default();
+            *total_attached = total_attached.saturating_add(attached);
+            let total_charged = snapshot.cycles_charged.entry(labels).or_default();
+            *total_charged = total_charged.saturating_add(charged);
+        });
+    }
+}
+
+/// [`Layer`] that records metrics of HTTPs outcalls, i.e. counters of requests and errors
+/// (by [`ErrorCategory`]) and histograms of response sizes and durations,
+/// keyed by [`MetricLabels`].
+///
+/// The layer should be placed close to the [`Client`](crate::Client), where requests are
+/// either [`IcHttpRequest`] or [`http::Request`], and responses are either [`IcHttpResponse`]
+/// or [`http::Response<Vec<u8>>`]. Each attempt of a retried request is counted as a request
+/// if the layer is placed below the retry layer. To count retries, wrap the retry policy
+/// with [`MetricsLayer::count_retries`].
+///
+/// The duration of a request is measured with the canister time, which only advances
+/// between messages, see [`ic_cdk::api::time`].
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{
+///     observability::{MetricLabels, Metrics, MetricsLayer},
+///     IcError,
+/// };
+/// use ic_cdk_management_canister::{HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse};
+/// use tower::{Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let metrics = Metrics::new();
+///
+/// let layer = MetricsLayer::new(metrics.clone());
+/// # let layer = layer.clock(|| 0);
+///
+/// let mut service = ServiceBuilder::new()
+///     .layer(layer)
+///     .service_fn(|_request: IcHttpRequest| async move {
+///         Ok::<_, IcError>(IcHttpResponse {
+///             body: b"Hello, world!".to_vec(),
+///             ..Default::default()
+///         })
+///     });
+///
+/// let request = IcHttpRequest {
+///     url: "https://internetcomputer.org/".to_string(),
+///     ..Default::default()
+/// };
+/// service.ready().await?.call(request).await?;
+///
+/// let labels = MetricLabels {
+///     host: "internetcomputer.org".to_string(),
+///     method: "GET".to_string(),
+/// };
+/// let snapshot = metrics.snapshot();
+/// assert_eq!(snapshot.requests[&labels], 1);
+/// assert_eq!(snapshot.response_sizes[&labels].sum(), 13);
+/// assert!(snapshot.errors.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MetricsLayer<M = Metrics> {
+    recorder: M,
+    clock: Clock,
+}
+
+impl<M> MetricsLayer<M> {
+    /// Create a new [`MetricsLayer`] recording metrics with the given recorder.
+    pub fn new(recorder: M) -> Self {
+        Self {
+            recorder,
+            clock: Clock::default(),
+        }
+    }
+
+    /// Change the clock used to measure the latency of requests.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+}
+
+impl<M: Clone> MetricsLayer<M> {
+    /// Wrap the given retry policy to record a retry each time the policy decides to retry
+    /// a request.
+    pub fn count_retries<P>(&self, policy: P) -> CountRetries<P, M> {
+        CountRetries {
+            policy,
+            recorder: self.recorder.clone(),
+        }
+    }
+
+    /// Returns a [`CyclesObserver`] recording the cycles attached to and charged for each
+    /// request, to be passed to [`CyclesAccounting::on_cycles`].
+    ///
+    /// Requests for which charging failed are not recorded, since they are not sent.
+    ///
+    /// [`CyclesAccounting::on_cycles`]: crate::cycles::CyclesAccounting::on_cycles
+    pub fn record_cycles(&self) -> RecordCycles<M> {
+        RecordCycles {
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+impl<M: fmt::Debug> fmt::Debug for MetricsLayer<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsLayer")
+            .field("recorder", &self.recorder)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, M: Clone> Layer<S> for MetricsLayer<M> {
+    type Service = Observability<S, RecordRequest<M>, RecordResponse<M>, RecordError<M>>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Observability {
+            inner,
+            on_request: RecordRequest {
+                recorder: self.recorder.clone(),
+                clock: self.clock.clone(),
+            },
+            on_response: RecordResponse {
+                recorder: self.recorder.clone(),
+                clock: self.clock.clone(),
+            },
+            on_error: RecordError {
+                recorder: self.recorder.clone(),
+                clock: self.clock.clone(),
+            },
+            redaction: (),
+        }
+    }
+}
+
+/// Data observed from a request by [`RecordRequest`].
+#[derive(Clone, Debug)]
+pub struct MeteredRequestData {
+    labels: MetricLabels,
+    start_ns: u64,
+}
+
+impl MeteredRequestData {
+    fn duration(&self, clock: &Clock) -> Duration {
+        Duration::from_nanos(clock.now().saturating_sub(self.start_ns))
+    }
+}
+
+/// [`RequestObserver`] used by [`MetricsLayer`].
+#[derive(Clone)]
+pub struct RecordRequest<M> {
+    recorder: M,
+    clock: Clock,
+}
+
+impl<M, Request> RequestObserver<Request> for RecordRequest<M>
+where
+    M: MetricsRecorder,
+    Request: MeteredRequest,
+{
+    type ObservableRequestData = MeteredRequestData;
+
+    fn observe_request(&self, request: &Request) -> Self::ObservableRequestData {
+        let labels = request.metric_labels();
+        self.recorder.record_request(&labels);
+        MeteredRequestData {
+            labels,
+            start_ns: self.clock.now(),
+        }
+    }
+}
+
+/// [`ResponseObserver`] of responses used by [`MetricsLayer`].
+#[derive(Clone)]
+pub struct RecordResponse<M> {
+    recorder: M,
+    clock: Clock,
+}
+
+impl<M, Response> ResponseObserver<MeteredRequestData, Response> for RecordResponse<M>
+where
+    M: MetricsRecorder,
+    Response: MeteredResponse,
+{
+    fn observe_response(&self, request_data: MeteredRequestData, value: &Response) {
+        self.recorder.record_response(
+            &request_data.labels,
+            value.response_size(),
+            request_data.duration(&self.clock),
+        );
+    }
+}
+
+/// [`ResponseObserver`] of errors used by [`MetricsLayer`].
+#[derive(Clone)]
+pub struct RecordError<M> {
+    recorder: M,
+    clock: Clock,
+}
+
+impl<M, Error> ResponseObserver<MeteredRequestData, Error> for RecordError<M>
+where
+    M: MetricsRecorder,
+    Error: MeteredError,
+{
+    fn observe_response(&self, request_data: MeteredRequestData, value: &Error) {
+        self.recorder.record_error(
+            &request_data.labels,
+            value.error_category(),
+            request_data.duration(&self.clock),
+        );
+    }
+}
+
+/// Retry policy that records retries decided by the wrapped policy,
+/// see [`MetricsLayer::count_retries`].
+#[derive(Clone, Debug)]
+pub struct CountRetries<P, M> {
+    policy: P,
+    recorder: M,
+}
+
+impl<P, M, Request, Response, Error> retry::Policy<Request, Response, Error> for CountRetries<P, M>
+where
+    P: retry::Policy<Request, Response, Error>,
+    M: MetricsRecorder,
+    Request: MeteredRequest,
+{
+    type Future = P::Future;
+
+    fn retry(
+        &mut self,
+        req: &mut Request,
+        result: &mut Result<Response, Error>,
+    ) -> Option<Self::Future> {
+        let future = self.policy.retry(req, result)?;
+        self.recorder.record_retry(&req.metric_labels());
+        Some(future)
+    }
+
+    fn clone_request(&mut self, req: &Request) -> Option<Request> {
+        self.policy.clone_request(req)
+    }
+}
+
+/// [`CyclesObserver`] recording the cycles spent for each request,
+/// see [`MetricsLayer::record_cycles`].
+#[derive(Clone, Debug)]
+pub struct RecordCycles<M> {
+    recorder: M,
+}
+
+impl<M: MetricsRecorder> CyclesObserver for RecordCycles<M> {
+    fn observe_cycles(&self, request: &IcHttpRequest, event: &CyclesEvent) {
+        if let Some(charged) = event.charged {
+            self.recorder
+                .record_cycles(&request.metric_labels(), event.attached, charged);
+        }
+    }
+}
@@ -160,8 +160,57 @@
 //! [`Service`]: tower::Service
 //! [`tower_http`]: https://crates.io/crates/tower-http
 
+#[cfg(test)]
+mod tests;
+
+mod classification;
+mod inflight;
+#[cfg(feature = "http")]
+mod instructions;
+mod logging;
+mod metadata;
+mod metrics;
+mod prometheus;
+mod redaction;
+mod sampling;
+#[cfg(feature = "http")]
+mod stable;
+
+pub use classification::{ObserveReject, ObserveTimeout, RejectObserver};
+pub use inflight::{
+    InFlightGuard, InFlightLayer, InFlightRegistry, InFlightRequest, RegisterInFlight,
+    UnregisterInFlight,
+};
+#[cfg(feature = "http")]
+pub use instructions::{
+    InstructionCounter, InstructionCounterFuture, InstructionCounterLayer, InstructionCounts,
+    MarkOutcall, MarkOutcallFuture, MarkOutcallLayer,
+};
+#[cfg(feature = "http")]
+pub use logging::CountAttempts;
+pub use logging::{
+    HttpEvent, HttpEventPhase, LogError, LogLevel, LogRequest, LogResponse, LogSink,
+    LoggableRequest, LoggableResponse, LoggedRequestData, LoggingLayer, PrintlnLogSink,
+};
+pub use metadata::{ObserveMetadata, RequestMetadata, RequestMetadataExtension};
+pub use metrics::{
+    CountRetries, HeapMetricsSink, Histogram, MeteredError, MeteredRequest, MeteredRequestData,
+    MeteredResponse, MetricLabels, Metrics, MetricsLayer, MetricsRecorder, MetricsSink,
+    MetricsSnapshot, RecordCycles, RecordError, RecordRequest, RecordResponse,
+    DEFAULT_DURATION_BUCKETS, DEFAULT_MAX_LABELS, DEFAULT_RESPONSE_SIZE_BUCKETS, OVERFLOW_HOST,
+};
+pub use prometheus::PrometheusEncoder;
+pub use redaction::{
+    Redact, RedactRequest, Redaction, DEFAULT_REDACTED_HEADERS, DEFAULT_REDACTED_QUERY_PARAMS,
+    REDACTED,
+};
+pub use sampling::{SampleRequest, SampleResponse};
+#[cfg(feature = "http")]
+pub use stable::{LogEntry, StableLogSink, StableMetricsSink, StableMetricsSinkError};
+
 use pin_project::pin_project;
 use std::future::Future;
+use std::num::NonZeroU64;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tower::{Layer, Service};
@@ -173,10 +222,11 @@ use tower::{Layer, Service};
 /// [`Layer`]: tower::Layer
 /// [`Service`]: tower::Service
 #[derive(Clone, Debug)]
-pub struct ObservabilityLayer<OnRequest, OnResponse, OnError> {
+pub struct ObservabilityLayer<OnRequest, OnResponse, OnError, R = ()> {
     on_request: OnRequest,
     on_response: OnResponse,
     on_error: OnError,
+    redaction: R,
 }
 
 impl ObservabilityLayer<(), (), ()> {
@@ -186,6 +236,7 @@ impl ObservabilityLayer<(), (), ()> {
             on_request: (),
             on_response: (),
             on_error: (),
+            redaction: (),
         }
     }
 }
@@ -196,18 +247,19 @@ impl Default for ObservabilityLayer<(), (), ()> {
     }
 }
 
-impl<OnRequest, OnResponse, OnError> ObservabilityLayer<OnRequest, OnResponse, OnError> {
+impl<OnRequest, OnResponse, OnError, R> ObservabilityLayer<OnRequest, OnResponse, OnError, R> {
     /// Customize what to do when a request is received.
     ///
     /// `NewOnRequest` is expected to implement [`RequestObserver`].
     pub fn on_request<NewOnRequest>(
         self,
         new_on_request: NewOnRequest,
-    ) -> ObservabilityLayer<NewOnRequest, OnResponse, OnError> {
+    ) -> ObservabilityLayer<NewOnRequest, OnResponse, OnError, R> {
         ObservabilityLayer {
             on_request: new_on_request,
             on_response: self.on_response,
             on_error: self.on_error,
+            redaction: self.redaction,
         }
     }
 
@@ -217,11 +269,12 @@ impl<OnRequest, OnResponse, OnError> ObservabilityLayer<OnRequest, OnResponse, O
     pub fn on_response<NewOnResponse>(
         self,
         new_on_response: NewOnResponse,
-    ) -> ObservabilityLayer<OnRequest, NewOnResponse, OnError> {
+    ) -> ObservabilityLayer<OnRequest, NewOnResponse, OnError, R> {
         ObservabilityLayer {
             on_request: self.on_request,
             on_response: new_on_response,
             on_error: self.on_error,
+            redaction: self.redaction,
         }
     }
 
@@ -231,23 +284,245 @@ impl<OnRequest, OnResponse, OnError> ObservabilityLayer<OnRequest, OnResponse, O
     pub fn on_error<NewOnError>(
         self,
         new_on_error: NewOnError,
-    ) -> ObservabilityLayer<OnRequest, OnResponse, NewOnError> {
+    ) -> ObservabilityLayer<OnRequest, OnResponse, NewOnError, R> {
         ObservabilityLayer {
             on_request: self.on_request,
             on_response: self.on_response,
             on_error: new_on_error,
+            redaction: self.redaction,
+        }
+    }
+
+    /// Observe errors corresponding to outcalls rejected by the Internet Computer separately,
+    /// e.g. to distinguish consensus failures from upstream failures on a dashboard.
+    ///
+    /// Errors for which [`HttpsOutcallError::reject`] returns a reject code are passed to
+    /// `new_on_reject`, which is expected to implement [`RejectObserver`], while all other
+    /// errors are still passed to the current error observer.
+    ///
+    /// This wraps the current error observer, and must therefore be called after
+    /// [`ObservabilityLayer::on_error`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::{observability::ObservabilityLayer, IcError};
+    /// use ic_cdk_management_canister::{HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse};
+    /// use ic_error_types::RejectCode;
+    /// use tower::{Service, ServiceBuilder, ServiceExt};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let mut service = ServiceBuilder::new()
+    ///     .layer(
+    ///         ObservabilityLayer::new()
+    ///             .on_error(|_: (), error: &IcError| ic_cdk::println!("Request failed: {error}"))
+    ///             .on_reject(|_: (), code: RejectCode, message: &str| {
+    ///                 assert_eq!(code, RejectCode::SysTransient);
+    ///                 ic_cdk::println!("Request rejected with code {code:?}: {message}")
+    ///             })
+    ///             .on_timeout(|_: (), _error: &IcError| ic_cdk::println!("Request timed out")),
+    ///     )
+    ///     .service_fn(|_request: IcHttpRequest| async move {
+    ///         Err::<IcHttpResponse, _>(IcError::CallRejected {
+    ///             code: RejectCode::SysTransient,
+    ///             message: "No consensus could be reached".to_string(),
+    ///         })
+    ///     });
+    ///
+    /// let result = service.ready().await.unwrap().call(IcHttpRequest::default()).await;
+    /// assert!(result.is_err());
+    /// # }
+    /// ```
+    ///
+    /// [`HttpsOutcallError::reject`]: crate::HttpsOutcallError::reject
+    pub fn on_reject<NewOnReject>(
+        self,
+        new_on_reject: NewOnReject,
+    ) -> ObservabilityLayer<OnRequest, OnResponse, ObserveReject<NewOnReject, OnError>, R> {
+        ObservabilityLayer {
+            on_request: self.on_request,
+            on_response: self.on_response,
+            on_error: ObserveReject::new(new_on_reject, self.on_error),
+            redaction: self.redaction,
+        }
+    }
+
+    /// Observe timeouts separately, as classified by [`HttpsOutcallError::is_timeout`].
+    ///
+    /// Timeouts are passed to `new_on_timeout`, which is expected to implement
+    /// [`ResponseObserver`], while all other errors are still passed to the current
+    /// error observer.
+    ///
+    /// This wraps the current error observer, and must therefore be called after
+    /// [`ObservabilityLayer::on_error`]. Since timeouts reported by the Internet Computer are
+    /// also rejects, call this method after [`ObservabilityLayer::on_reject`] for those to be
+    /// observed as timeouts.
+    ///
+    /// [`HttpsOutcallError::is_timeout`]: crate::HttpsOutcallError::is_timeout
+    pub fn on_timeout<NewOnTimeout>(
+        self,
+        new_on_timeout: NewOnTimeout,
+    ) -> ObservabilityLayer<OnRequest, OnResponse, ObserveTimeout<NewOnTimeout, OnError>, R> {
+        ObservabilityLayer {
+            on_request: self.on_request,
+            on_response: self.on_response,
+            on_error: ObserveTimeout::new(new_on_timeout, self.on_error),
+            redaction: self.redaction,
+        }
+    }
+
+    /// Mask sensitive headers and URL query parameters of requests before they are passed
+    /// to the [`RequestObserver`], see [`Redaction`].
+    ///
+    /// The redaction applies to the request observer whether it is set before or after calling
+    /// this method, and replaces any previously set redaction. Note that each request is copied
+    /// to be redacted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::observability::{ObservabilityLayer, Redaction};
+    ///
+    /// let layer = ObservabilityLayer::new()
+    ///     // Headers such as `Authorization` are printed as `REDACTED`.
+    ///     .on_request(|request: &http::Request<Vec<u8>>| ic_cdk::println!("{request:?}"))
+    ///     .redact(Redaction::new());
+    /// ```
+    pub fn redact(
+        self,
+        redaction: Redaction,
+    ) -> ObservabilityLayer<OnRequest, OnResponse, OnError, Redaction> {
+        ObservabilityLayer {
+            on_request: self.on_request,
+            on_response: self.on_response,
+            on_error: self.on_error,
+            redaction,
+        }
+    }
+
+    /// Only observe 1 in `one_in` requests, e.g. to bound the amount of debug output of a
+    /// canister issuing many requests. The first request is always observed.
+    ///
+    /// Responses and errors are only observed for sampled requests, which means that the
+    /// [`RequestObserver`] is not called at all for the other requests.
+    /// To observe all errors, use [`ObservabilityLayer::sample_except_errors`] instead.
+    ///
+    /// This wraps the current observers, and must therefore be called after them being set.
+    pub fn sample(
+        self,
+        one_in: NonZeroU64,
+    ) -> ObservabilityLayer<
+        SampleRequest<OnRequest>,
+        SampleResponse<OnResponse>,
+        SampleResponse<OnError>,
+        R,
+    > {
+        ObservabilityLayer {
+            on_request: SampleRequest::new(self.on_request, one_in),
+            on_response: SampleResponse::new(self.on_response),
+            on_error: SampleResponse::new(self.on_error),
+            redaction: self.redaction,
+        }
+    }
+
+    /// Only observe 1 in `one_in` requests and their responses, but observe all errors.
+    ///
+    /// Since the [`RequestObserver`] is not called for requests that are not sampled, the
+    /// error observer receives the request data as an [`Option`], which is `None` for
+    /// such requests.
+    ///
+    /// This wraps the current observers, and must therefore be called after them being set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::{observability::ObservabilityLayer, IcError};
+    /// use ic_cdk_management_canister::{HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse};
+    /// use std::num::NonZeroU64;
+    ///
+    /// let layer = ObservabilityLayer::new()
+    ///     .on_request(|request: &IcHttpRequest| request.url.clone())
+    ///     .on_response(|url: String, _response: &IcHttpResponse| {
+    ///         ic_cdk::println!("Received response from {url}")
+    ///     })
+    ///     .on_error(|url: Option<String>, error: &IcError| {
+    ///         ic_cdk::println!("Request to {} failed: {error}", url.as_deref().unwrap_or("<not sampled>"))
+    ///     })
+    ///     .sample_except_errors(NonZeroU64::new(100).unwrap());
+    /// ```
+    pub fn sample_except_errors(
+        self,
+        one_in: NonZeroU64,
+    ) -> ObservabilityLayer<SampleRequest<OnRequest>, SampleResponse<OnResponse>, OnError, R> {
+        ObservabilityLayer {
+            on_request: SampleRequest::new(self.on_request, one_in),
+            on_response: SampleResponse::new(self.on_response),
+            on_error: self.on_error,
+            redaction: self.redaction,
+        }
+    }
+
+    /// Pass the [`RequestMetadata`] of each request to the response and error observers,
+    /// by adding it to the data observed from the request.
+    ///
+    /// The observers then receive a tuple `(RequestMetadata, RequestData)`, where the metadata
+    /// is empty for requests without any.
+    ///
+    /// This wraps the current request observer, and must therefore be called after
+    /// [`ObservabilityLayer::on_request`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use canhttp::observability::{ObservabilityLayer, RequestMetadata, RequestMetadataExtension};
+    /// use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), BoxError> {
+    /// let mut service = ServiceBuilder::new()
+    ///     .layer(
+    ///         ObservabilityLayer::new()
+    ///             .on_request(|request: &http::Request<Vec<u8>>| request.uri().to_string())
+    ///             .on_error(|(metadata, url): (RequestMetadata, String), error: &BoxError| {
+    ///                 assert_eq!(metadata.to_string(), "price-feed: BTC/USD");
+    ///                 ic_cdk::println!("[{metadata}] Request to {url} failed: {error}");
+    ///             })
+    ///             .with_metadata(),
+    ///     )
+    ///     .service_fn(|_request: http::Request<Vec<u8>>| async move {
+    ///         Err::<http::Response<Vec<u8>>, BoxError>(BoxError::from("Connection refused"))
+    ///     });
+    ///
+    /// let request = http::Request::get("https://example.com/prices")
+    ///     .request_metadata(RequestMetadata::new().with("price-feed", "BTC/USD"))
+    ///     .body(vec![])?;
+    /// let result = service.ready().await?.call(request).await;
+    /// assert!(result.is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_metadata(
+        self,
+    ) -> ObservabilityLayer<ObserveMetadata<OnRequest>, OnResponse, OnError, R> {
+        ObservabilityLayer {
+            on_request: ObserveMetadata::new(self.on_request),
+            on_response: self.on_response,
+            on_error: self.on_error,
+            redaction: self.redaction,
         }
     }
 }
 
-impl<S, OnRequest, OnResponse, OnError> Layer<S>
-    for ObservabilityLayer<OnRequest, OnResponse, OnError>
+impl<S, OnRequest, OnResponse, OnError, R> Layer<S>
+    for ObservabilityLayer<OnRequest, OnResponse, OnError, R>
 where
     OnRequest: Clone,
     OnResponse: Clone,
     OnError: Clone,
+    R: Clone,
 {
-    type Service = Observability<S, OnRequest, OnResponse, OnError>;
+    type Service = Observability<S, OnRequest, OnResponse, OnError, R>;
 
     fn layer(&self, inner: S) -> Self::Service {
         Self::Service {
@@ -255,6 +530,7 @@ where
             on_request: self.on_request.clone(),
             on_response: self.on_response.clone(),
             on_error: self.on_error.clone(),
+            redaction: self.redaction.clone(),
         }
     }
 }
@@ -265,18 +541,20 @@ where
 ///
 /// [`Service`]: tower::Service
 #[derive(Clone, Debug)]
-pub struct Observability<S, OnRequest, OnResponse, OnError> {
+pub struct Observability<S, OnRequest, OnResponse, OnError, R = ()> {
     inner: S,
     on_request: OnRequest,
     on_response: OnResponse,
     on_error: OnError,
+    redaction: R,
 }
 
-impl<S, Request, Response, OnRequest, RequestData, OnResponse, OnError> Service<Request>
-    for Observability<S, OnRequest, OnResponse, OnError>
+impl<S, Request, Response, OnRequest, RequestData, OnResponse, OnError, R> Service<Request>
+    for Observability<S, OnRequest, OnResponse, OnError, R>
 where
     S: Service<Request, Response = Response>,
     OnRequest: RequestObserver<Request, ObservableRequestData = RequestData>,
+    R: RedactRequest<Request>,
     OnResponse: ResponseObserver<RequestData, S::Response> + Clone,
     OnError: ResponseObserver<RequestData, S::Error> + Clone,
 {
@@ -289,7 +567,7 @@ where
     }
 
     fn call(&mut self, req: Request) -> Self::Future {
-        let req_data = self.on_request.observe_request(&req);
+        let req_data = self.redaction.observe_request(&self.on_request, &req);
         ResponseFuture {
             response_future: self.inner.call(req),
             request_data: Some(req_data),
@@ -0,0 +1,203 @@
+use crate::{
+    error::ErrorCategory,
+    observability::{Histogram, MetricLabels, MetricsSnapshot},
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+/// Render [`MetricsSnapshot`]s in the [Prometheus text exposition format], so that a canister
+/// can serve its outcall metrics, e.g. from its `http_request` query endpoint.
+///
+/// The following metrics are rendered, each prefixed by the [namespace](Self::namespace)
+/// (`canhttp` by default) and labelled by `host` and `method`:
+/// * `<namespace>_requests_total`: counter of requests.
+/// * `<namespace>_errors_total`: counter of errors, additionally labelled by `category`,
+///   see [`ErrorCategory`].
+/// * `<namespace>_retries_total`: counter of retries.
+/// * `<namespace>_response_size_bytes`: histogram of response sizes.
+/// * `<namespace>_request_duration_milliseconds`: histogram of request durations.
+/// * `<namespace>_cycles_attached_total`: counter of cycles attached to requests.
+/// * `<namespace>_cycles_charged_total`: counter of cycles charged to the callers of the canister.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::observability::{MetricLabels, Metrics, MetricsRecorder, PrometheusEncoder};
+///
+/// let metrics = Metrics::new().response_size_buckets(vec![1_024]);
+/// let labels = MetricLabels {
+///     host: "internetcomputer.org".to_string(),
+///     method: "GET".to_string(),
+/// };
+/// metrics.record_request(&labels);
+///
+/// let text = PrometheusEncoder::new().encode(&metrics.snapshot());
+/// assert!(text.contains(
+///     "canhttp_requests_total{host=\"internetcomputer.org\",method=\"GET\"} 1\n"
+/// ));
+/// ```
+///
+/// [Prometheus text exposition format]: https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format
+#[derive(Clone, Debug)]
+pub struct PrometheusEncoder {
+    namespace: String,
+}
+
+impl Default for PrometheusEncoder {
+    fn default() -> Self {
+        Self {
+            namespace: "canhttp".to_string(),
+        }
+    }
+}
+
+impl PrometheusEncoder {
+    /// Create a new [`PrometheusEncoder`] with the default `canhttp` namespace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the prefix of all metric names.
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// Render the given metrics.
+    pub fn encode(&self, snapshot: &MetricsSnapshot) -> String {
+        let mut output = String::new();
+        self.counter(
+            &mut output,
+            "requests_total",
+            "Number of HTTPs outcalls.",
+            snapshot
+                .requests
+                .iter()
+                .map(|(labels, count)| (labels_to_string(labels, None), count)),
+        );
+        self.counter(
+            &mut output,
+            "errors_total",
+            "Number of HTTPs outcalls resulting in an error.",
+            snapshot.errors.iter().map(|((labels, category), count)| {
+                (labels_to_string(labels, Some(*category)), count)
+            }),
+        );
+        self.counter(
+            &mut output,
+            "retries_total",
+            "Number of retried HTTPs outcalls.",
+            snapshot
+                .retries
+                .iter()
+                .map(|(labels, count)| (labels_to_string(labels, None), count)),
+        );
+        self.histogram(
+            &mut output,
+            "response_size_bytes",
+            "Size of HTTPs outcall responses in bytes.",
+            &snapshot.response_sizes,
+        );
+        self.histogram(
+            &mut output,
+            "request_duration_milliseconds",
+            "Duration of HTTPs outcalls in milliseconds.",
+            &snapshot.durations,
+        );
+        self.counter(
+            &mut output,
+            "cycles_attached_total",
+            "Number of cycles attached to HTTPs outcalls.",
+            snapshot
+                .cycles_attached
+                .iter()
+                .map(|(labels, cycles)| (labels_to_string(labels, None), cycles)),
+        );
+        self.counter(
+            &mut output,
+            "cycles_charged_total",
+            "Number of cycles charged to callers for HTTPs outcalls.",
+            snapshot
+                .cycles_charged
+                .iter()
+                .map(|(labels, cycles)| (labels_to_string(labels, None), cycles)),
+        );
+        output
+    }
+
+    fn counter<T: fmt::Display>(
+        &self,
+        output: &mut String,
+        name: &str,
+        help: &str,
+        samples: impl Iterator<Item = (String, T)>,
+    ) {
+        let name = format!("{}_{name}", self.namespace);
+        header(output, &name, help, "counter");
+        for (labels, count) in samples {
+            let _ = writeln!(output, "{name}{{{labels}}} {count}");
+        }
+    }
+
+    fn histogram(
+        &self,
+        output: &mut String,
+        name: &str,
+        help: &str,
+        histograms: &BTreeMap<MetricLabels, Histogram>,
+    ) {
+        let name = format!("{}_{name}", self.namespace);
+        header(output, &name, help, "histogram");
+        for (labels, histogram) in histograms {
+            let labels = labels_to_string(labels, None);
+            let bounds = histogram
+                .bounds()
+                .iter()
+                .map(|bound| bound.to_string())
+                .chain(std::iter::once("+Inf".to_string()));
+            let mut cumulative_count = 0_u64;
+            for (bound, count) in bounds.zip(histogram.counts()) {
+                cumulative_count += count;
+                let _ = writeln!(
+                    output,
+                    "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative_count}"
+                );
+            }
+            let _ = writeln!(output, "{name}_sum{{{labels}}} {}", histogram.sum());
+            let _ = writeln!(output, "{name}_count{{{labels}}} {cumulative_count}");
+        }
+    }
+}
+
+fn header(output: &mut String, name: &str, help: &str, metric_type: &str) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} {metric_type}");
+}
+
+fn labels_to_string(labels: &MetricLabels, category: Option<Option<ErrorCategory>>) -> String {
+    let mut result = format!(
+        "host=\"{}\",method=\"{}\"",
+        escape(&labels.host),
+        escape(&labels.method)
+    );
+    if let Some(category) = category {
+        let category = match category {
+            Some(ErrorCategory::Network) => "network",
+            Some(ErrorCategory::Request) => "request",
+            Some(ErrorCategory::Upstream) => "upstream",
+            Some(ErrorCategory::Decoding) => "decoding",
+            None => "unknown",
+        };
+        let _ = write!(result, ",category=\"{category}\"");
+    }
+    result
+}
+
+fn escape(label_value: &str) -> String {
+    label_value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
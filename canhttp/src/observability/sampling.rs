@@ -0,0 +1,86 @@
+use crate::observability::{RequestObserver, ResponseObserver};
+use std::{cell::Cell, num::NonZeroU64, rc::Rc};
+
+/// Deterministically selects 1 in N requests.
+///
+/// All clones share the same counter, so that the first request and then every N-th request
+/// is selected, independently of how many services were created by a layer.
+#[derive(Clone, Debug)]
+pub(super) struct Sampler {
+    one_in: NonZeroU64,
+    counter: Rc<Cell<u64>>,
+}
+
+impl Sampler {
+    pub(super) fn new(one_in: NonZeroU64) -> Self {
+        Self {
+            one_in,
+            counter: Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub(super) fn sample(&self) -> bool {
+        let count = self.counter.get();
+        self.counter.set(count.wrapping_add(1));
+        count.is_multiple_of(self.one_in.get())
+    }
+}
+
+/// [`RequestObserver`] only observing 1 in N requests, see [`ObservabilityLayer::sample`].
+///
+/// The observable data is `None` for requests that are not sampled.
+///
+/// [`ObservabilityLayer::sample`]: crate::observability::ObservabilityLayer::sample
+#[derive(Clone, Debug)]
+pub struct SampleRequest<O> {
+    observer: O,
+    sampler: Sampler,
+}
+
+impl<O> SampleRequest<O> {
+    pub(super) fn new(observer: O, one_in: NonZeroU64) -> Self {
+        Self {
+            observer,
+            sampler: Sampler::new(one_in),
+        }
+    }
+}
+
+impl<O, Request> RequestObserver<Request> for SampleRequest<O>
+where
+    O: RequestObserver<Request>,
+{
+    type ObservableRequestData = Option<O::ObservableRequestData>;
+
+    fn observe_request(&self, request: &Request) -> Self::ObservableRequestData {
+        self.sampler
+            .sample()
+            .then(|| self.observer.observe_request(request))
+    }
+}
+
+/// [`ResponseObserver`] only observing the results of sampled requests,
+/// see [`ObservabilityLayer::sample`].
+///
+/// [`ObservabilityLayer::sample`]: crate::observability::ObservabilityLayer::sample
+#[derive(Clone, Debug)]
+pub struct SampleResponse<O> {
+    observer: O,
+}
+
+impl<O> SampleResponse<O> {
+    pub(super) fn new(observer: O) -> Self {
+        Self { observer }
+    }
+}
+
+impl<O, RequestData, Response> ResponseObserver<Option<RequestData>, Response> for SampleResponse<O>
+where
+    O: ResponseObserver<RequestData, Response>,
+{
+    fn observe_response(&self, request_data: Option<RequestData>, value: &Response) {
+        if let Some(request_data) = request_data {
+            self.observer.observe_response(request_data, value);
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use crate::{
+    clock::Clock,
+    error::ErrorCategory,
+    http::stable::{
+        region::{self, CapacityExceeded, Decoder, Encoder, WriteError},
+        Memory,
+    },
+    observability::{Histogram, LogSink, MetricLabels, MetricsSink, MetricsSnapshot},
+};
+use std::{cell::RefCell, collections::BTreeMap, fmt, rc::Rc};
+use thiserror::Error;
+
+/// Version 2 added the cycles counters, version 1 is still decoded.
+const ENCODING_VERSION: u8 = 2;
+/// The log ring buffer is prefixed by the total number of logged messages as a `u64`.
+const LOG_HEADER_SIZE: u64 = 8;
+/// Each log entry is prefixed by its timestamp as a `u64` and its length as a `u32`.
+const LOG_ENTRY_HEADER_SIZE: u64 = 12;
+
+/// Error returned when persisting or restoring metrics with a [`StableMetricsSink`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum StableMetricsSinkError {
+    /// The encoded metrics do not fit in the region.
+    #[error("Encoded metrics of {len} bytes exceed the capacity of {capacity} bytes")]
+    MetricsTooLarge {
+        /// Length of the encoded metrics in bytes.
+        len: u64,
+        /// Capacity of the region in bytes.
+        capacity: u64,
+    },
+    /// The stable memory could not be grown to hold the metrics.
+    #[error("Failed to grow stable memory to {required_pages} pages")]
+    OutOfMemory {
+        /// Number of pages required to hold the metrics.
+        required_pages: u64,
+    },
+    /// The metrics stored in stable memory exceed the capacity of the region, e.g. because the
+    /// region was shrunk or overwritten.
+    #[error("Stored metrics of {len} bytes exceed the capacity of {capacity} bytes")]
+    CapacityExceeded {
+        /// Length of the stored metrics in bytes.
+        len: u64,
+        /// Capacity of the region in bytes.
+        capacity: u64,
+    },
+    /// The metrics stored in stable memory could not be decoded.
+    #[error("Stored metrics are corrupted")]
+    Corrupted,
+}
+
+impl From<CapacityExceeded> for StableMetricsSinkError {
+    fn from(CapacityExceeded { len, capacity }: CapacityExceeded) -> Self {
+        Self::CapacityExceeded { len, capacity }
+    }
+}
+
+impl From<WriteError> for StableMetricsSinkError {
+    fn from(error: WriteError) -> Self {
+        match error {
+            WriteError::TooLarge { len, capacity } => Self::MetricsTooLarge { len, capacity },
+            WriteError::OutOfMemory { required_pages } => Self::OutOfMemory { required_pages },
+        }
+    }
+}
+
+/// [`MetricsSink`] keeping the metrics on the heap and persisting them on demand to a fixed
+/// region of stable memory, so that they survive canister upgrades.
+///
+/// Updates only modify the metrics on the heap, so that recording metrics does not write to
+/// stable memory. The metrics are written to stable memory with [`StableMetricsSink::persist`],
+/// typically in the `pre_upgrade` hook of the canister, and read back after the upgrade with
+/// [`StableMetricsSink::init`]. Metrics recorded since the last call to `persist` are lost if
+/// the canister is upgraded without calling it, e.g. if the `pre_upgrade` hook traps.
+///
+/// Cloning a [`StableMetricsSink`] is cheap and all clones share the same metrics.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::observability::{Metrics, MetricsLayer, StableMetricsSink};
+/// use ic_stable_structures::{
+///     memory_manager::{MemoryId, MemoryManager},
+///     DefaultMemoryImpl,
+/// };
+///
+/// let memory_manager = MemoryManager::init(DefaultMemoryImpl::default());
+/// let memory = memory_manager.get(MemoryId::new(1));
+///
+/// // In the `post_upgrade` hook, restore the metrics persisted before the upgrade.
+/// let sink = StableMetricsSink::init(memory.clone(), 0, 64 * 1024).unwrap_or_else(|_error| {
+///     // Start again from empty metrics, e.g. after logging the error.
+///     StableMetricsSink::new(memory, 0, 64 * 1024)
+/// });
+/// let metrics = Metrics::with_sink(sink.clone());
+/// let layer = MetricsLayer::new(metrics.clone());
+///
+/// // In the `pre_upgrade` hook, persist the metrics.
+/// assert_eq!(sink.persist(), Ok(()));
+/// ```
+#[derive(Clone, Debug)]
+pub struct StableMetricsSink<M> {
+    memory: M,
+    offset: u64,
+    capacity: u64,
+    metrics: Rc<RefCell<MetricsSnapshot>>,
+}
+
+impl<M: Memory> StableMetricsSink<M> {
+    /// Create a new [`StableMetricsSink`] with empty metrics, persisted to the given memory in
+    /// a region of `capacity` bytes starting at `offset`.
+    ///
+    /// Metrics already persisted in the region are ignored and overwritten by the next call to
+    /// [`StableMetricsSink::persist`], use [`StableMetricsSink::init`] to restore them.
+    /// The region must not be used for anything else by the canister and must remain the same
+    /// across upgrades. Using the raw stable memory of the canister (e.g.
+    /// [`CanisterStableMemory`](crate::http::stable::CanisterStableMemory)) is only safe if
+    /// nothing else, such as a `MemoryManager`, manages it.
+    pub fn new(memory: M, offset: u64, capacity: u64) -> Self {
+        Self {
+            memory,
+            offset,
+            capacity,
+            metrics: Rc::new(RefCell::new(MetricsSnapshot::default())),
+        }
+    }
+
+    /// Create a new [`StableMetricsSink`] as with [`StableMetricsSink::new`], starting from the
+    /// metrics persisted in the region, or from empty metrics if the region was never written.
+    ///
+    /// Returns an error if the persisted metrics cannot be read, in which case the region is
+    /// left untouched.
+    pub fn init(memory: M, offset: u64, capacity: u64) -> Result<Self, StableMetricsSinkError> {
+        let sink = Self::new(memory, offset, capacity);
+        if let Some(encoded) =
+            region::read_length_prefixed(&sink.memory, sink.offset, sink.capacity)?
+        {
+            *sink.metrics.borrow_mut() =
+                decode(&encoded).ok_or(StableMetricsSinkError::Corrupted)?;
+        }
+        Ok(sink)
+    }
+
+    /// Write the current metrics to stable memory.
+    ///
+    /// If the encoded metrics do not fit in the region, the previously persisted metrics are
+    /// left untouched and the current metrics are only kept on the heap.
+    pub fn persist(&self) -> Result<(), StableMetricsSinkError> {
+        let encoded = encode(&self.metrics.borrow());
+        region::write_length_prefixed(&self.memory, self.offset, self.capacity, &encoded)?;
+        Ok(())
+    }
+}
+
+impl<M> MetricsSink for StableMetricsSink<M> {
+    fn load(&self) -> MetricsSnapshot {
+        self.metrics.borrow().clone()
+    }
+
+    fn update<F: FnOnce(&mut MetricsSnapshot)>(&self, f: F) {
+        f(&mut self.metrics.borrow_mut())
+    }
+}
+
+fn encode(snapshot: &MetricsSnapshot) -> Vec<u8> {
+    let mut encoder = Encoder::new(ENCODING_VERSION);
+    encoder.counters(&snapshot.requests);
+    encoder.u64(snapshot.errors.len() as u64);
+    for ((labels, category), count) in &snapshot.errors {
+        encoder.labels(labels);
+        encoder.u8(match category {
+            None => 0,
+            Some(ErrorCategory::Network) => 1,
+            Some(ErrorCategory::Request) => 2,
+            Some(ErrorCategory::Upstream) => 3,
+            Some(ErrorCategory::Decoding) => 4,
+        });
+        encoder.u64(*count);
+    }
+    encoder.counters(&snapshot.retries);
+    encoder.histograms(&snapshot.response_sizes);
+    encoder.histograms(&snapshot.durations);
+    encoder.cycles(&snapshot.cycles_attached);
+    encoder.cycles(&snapshot.cycles_charged);
+    encoder.into_bytes()
+}
+
+impl Encoder {
+    fn labels(&mut self, labels: &MetricLabels) {
+        self.str(&labels.host);
+        self.str(&labels.method);
+    }
+
+    fn counters(&mut self, counters: &BTreeMap<MetricLabels, u64>) {
+        self.u64(counters.len() as u64);
+        for (labels, count) in counters {
+            self.labels(labels);
+            self.u64(*count);
+        }
+    }
+
+    fn cycles(&mut self, cycles: &BTreeMap<MetricLabels, u128>) {
+        self.u64(cycles.len() as u64);
+        for (labels, value) in cycles {
+            self.labels(labels);
+            self.u128(*value);
+        }
+    }
+
+    fn histograms(&mut self, histograms: &BTreeMap<MetricLabels, Histogram>) {
+        self.u64(histograms.len() as u64);
+        for (labels, histogram) in histograms {
+            self.labels(labels);
+            self.u64(histogram.bounds().len() as u64);
+            histogram.bounds().iter().for_each(|bound| self.u64(*bound));
+            histogram.counts().iter().for_each(|count| self.u64(*count));
+            self.u64(histogram.sum());
+        }
+    }
+}
+
+fn decode(bytes: &[u8]) -> Option<MetricsSnapshot> {
+    let mut decoder = Decoder::new(bytes);
+    let version = decoder.u8()?;
+    if version == 0 || version > ENCODING_VERSION {
+        return None;
+    }
+    let requests = decoder.counters()?;
+    let errors = (0..decoder.u64()?)
+        .map(|_| {
+            let labels = decoder.labels()?;
+            let category = match decoder.u8()? {
+                0 => None,
+                1 => Some(ErrorCategory::Network),
+                2 => Some(ErrorCategory::Request),
+                3 => Some(ErrorCategory::Upstream),
+                4 => Some(ErrorCategory::Decoding),
+                _ => return None,
+            };
+            Some(((labels, category), decoder.u64()?))
+        })
+        .collect::<Option<_>>()?;
+    let retries = decoder.counters()?;
+    let response_sizes = decoder.histograms()?;
+    let durations = decoder.histograms()?;
+    let (cycles_attached, cycles_charged) = if version >= 2 {
+        (decoder.cycles()?, decoder.cycles()?)
+    } else {
+        (BTreeMap::new(), BTreeMap::new())
+    };
+    decoder.is_empty().then_some(MetricsSnapshot {
+        requests,
+        errors,
+        retries,
+        response_sizes,
+        durations,
+        cycles_attached,
+        cycles_charged,
+    })
+}
+
+impl Decoder<'_> {
+    fn labels(&mut self) -> Option<MetricLabels> {
+        Some(MetricLabels {
+            host: self.str()?,
+            method: self.str()?,
+        })
+    }
+
+    fn counters(&mut self) -> Option<BTreeMap<MetricLabels, u64>> {
+        (0..self.u64()?)
+            .map(|_| Some((self.labels()?, self.u64()?)))
+            .collect()
+    }
+
+    fn cycles(&mut self) -> Option<BTreeMap<MetricLabels, u128>> {
+        (0..self.u64()?)
+            .map(|_| {
+                let labels = self.labels()?;
+                Some((labels, self.u128()?))
+            })
+            .collect()
+    }
+
+    fn histograms(&mut self) -> Option<BTreeMap<MetricLabels, Histogram>> {
+        (0..self.u64()?)
+            .map(|_| {
+                let labels = self.labels()?;
+                let num_bounds = self.u64()?;
+                let bounds = (0..num_bounds).map(|_| self.u64()).collect::<Option<_>>()?;
+                let counts = (0..=num_bounds)
+                    .map(|_| self.u64())
+                    .collect::<Option<_>>()?;
+                let histogram = Histogram::from_parts(bounds, counts, self.u64()?)?;
+                Some((labels, histogram))
+            })
+            .collect()
+    }
+}
+
+/// Entry of a [`StableLogSink`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LogEntry {
+    /// Time in nanoseconds since the UNIX epoch at which the message was logged.
+    pub timestamp: u64,
+    /// Logged message, possibly truncated.
+    pub message: String,
+}
+
+/// [`LogSink`] keeping the last log messages in a ring buffer in a fixed region of stable
+/// memory, so that they can be retrieved later on, e.g. from a query endpoint, and survive
+/// canister upgrades.
+///
+/// The region holds a header of 8 bytes followed by `max_entries` slots of
+/// `12 + max_message_len` bytes each, see [`StableLogSink::region_size`]. Longer messages
+/// are truncated. Once all slots are used, the oldest entry is overwritten.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::observability::{LoggingLayer, StableLogSink};
+/// use ic_stable_structures::{
+///     memory_manager::{MemoryId, MemoryManager},
+///     DefaultMemoryImpl,
+/// };
+///
+/// let memory_manager = MemoryManager::init(DefaultMemoryImpl::default());
+///
+/// // Keep the last 1000 messages of at most 256 bytes in a virtual memory dedicated to the logs.
+/// let logs = StableLogSink::new(memory_manager.get(MemoryId::new(2)), 0, 1_000, 256)
+///     .clock(|| 1_620_328_630_000_000_000);
+/// let layer = LoggingLayer::new(logs.clone());
+///
+/// // Later on, e.g. in a query endpoint:
+/// assert_eq!(logs.entries(), vec![]);
+/// ```
+#[derive(Clone)]
+pub struct StableLogSink<M> {
+    memory: M,
+    offset: u64,
+    max_entries: u64,
+    max_message_len: u64,
+    clock: Clock,
+}
+
+impl<M: Memory> StableLogSink<M> {
+    /// Create a new [`StableLogSink`] in the given memory starting at `offset` and keeping
+    /// the last `max_entries` messages of at most `max_message_len` bytes.
+    ///
+    /// The region must not be used for anything else by the canister and, as well as the
+    /// number and length of messages, must remain the same across upgrades.
+    /// The memory is grown lazily, when the first message is logged.
+    pub fn new(memory: M, offset: u64, max_entries: u64, max_message_len: u64) -> Self {
+        Self {
+            memory,
+            offset,
+            max_entries,
+            max_message_len,
+            clock: Clock::default(),
+        }
+    }
+
+    /// Change the clock used to timestamp the persisted log entries.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+
+    /// Returns the size in bytes of the region of stable memory used by this sink.
+    pub fn region_size(&self) -> u64 {
+        LOG_HEADER_SIZE + self.max_entries * self.slot_size()
+    }
+
+    /// Returns the retained entries, from the oldest to the most recent one.
+    pub fn entries(&self) -> Vec<LogEntry> {
+        let num_logged = self.num_logged();
+        let first = num_logged.saturating_sub(self.max_entries);
+        (first..num_logged)
+            .map(|index| {
+                let slot = self.slot_offset(index);
+                let mut header = [0_u8; LOG_ENTRY_HEADER_SIZE as usize];
+                self.memory.read(slot, &mut header);
+                let (timestamp, len) = header.split_at(8);
+                let len = u32::from_le_bytes(len.try_into().unwrap()) as u64;
+                let mut message = vec![0_u8; len.min(self.max_message_len) as usize];
+                self.memory.read(slot + LOG_ENTRY_HEADER_SIZE, &mut message);
+                LogEntry {
+                    timestamp: u64::from_le_bytes(timestamp.try_into().unwrap()),
+                    message: String::from_utf8_lossy(&message).into_owned(),
+                }
+            })
+            .collect()
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) {
+        if self.num_logged() > 0 {
+            self.memory.write(self.offset, &0_u64.to_le_bytes());
+        }
+    }
+
+    fn num_logged(&self) -> u64 {
+        if !region::has_size(&self.memory, self.offset + self.region_size()) {
+            return 0;
+        }
+        let mut num_logged = [0_u8; LOG_HEADER_SIZE as usize];
+        self.memory.read(self.offset, &mut num_logged);
+        u64::from_le_bytes(num_logged)
+    }
+
+    fn slot_size(&self) -> u64 {
+        LOG_ENTRY_HEADER_SIZE + self.max_message_len
+    }
+
+    fn slot_offset(&self, index: u64) -> u64 {
+        self.offset + LOG_HEADER_SIZE + (index % self.max_entries) * self.slot_size()
+    }
+
+    fn ensure_memory_size(&self) -> bool {
+        region::grow_to(&self.memory, self.offset + self.region_size()).is_ok()
+    }
+}
+
+impl<M: Memory> LogSink for StableLogSink<M> {
+    fn log(&self, message: &str) {
+        if self.max_entries == 0 || !self.ensure_memory_size() {
+            return;
+        }
+        let mut len = message.len().min(self.max_message_len as usize);
+        while !message.is_char_boundary(len) {
+            len -= 1;
+        }
+        let num_logged = self.num_logged();
+        let slot = self.slot_offset(num_logged);
+        let mut entry = Vec::with_capacity(LOG_ENTRY_HEADER_SIZE as usize + len);
+        entry.extend_from_slice(&self.clock.now().to_le_bytes());
+        entry.extend_from_slice(&(len as u32).to_le_bytes());
+        entry.extend_from_slice(&message.as_bytes()[..len]);
+        self.memory.write(slot, &entry);
+        self.memory
+            .write(self.offset, &(num_logged + 1).to_le_bytes());
+    }
+}
+
+impl<M: fmt::Debug> fmt::Debug for StableLogSink<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableLogSink")
+            .field("memory", &self.memory)
+            .field("offset", &self.offset)
+            .field("max_entries", &self.max_entries)
+            .field("max_message_len", &self.max_message_len)
+            .finish_non_exhaustive()
+    }
+}
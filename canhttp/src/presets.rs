@@ -0,0 +1,123 @@
+//! Pre-assembled service stacks for common use cases.
+//!
+//! Each preset returns a fully wired [`tower::Service`], so that a canister gets a correct stack
+//! in one line. For anything more advanced (observability, charging the caller, custom retry
+//! strategies, etc.), build your own stack with the tower API instead, e.g. by starting from
+//! the layers used by the preset.
+
+#[cfg(feature = "bitcoin")]
+use crate::http::{json::bitcoin, BasicAuthLayer};
+use crate::{
+    cycles::{ChargeMyself, CyclesAccountingServiceBuilder},
+    http::json::{HttpJsonRpcResponse, JsonRpcHttpLayer, JsonRpcRequest, JsonRpcResponse},
+    retry::DoubleMaxResponseBytes,
+    Client, ConvertServiceBuilder, MaxResponseBytesRequestExtension,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+use tower::{BoxError, Service, ServiceBuilder};
+
+/// Initial value of `max_response_bytes` used by [`json_rpc`].
+pub const JSON_RPC_INITIAL_MAX_RESPONSE_BYTES: u64 = 8 * 1024;
+
+/// Returns a service to make JSON-RPC requests to the given URL.
+///
+/// The service uses the following stack:
+/// * Each JSON-RPC request is sent with a `POST` HTTP request to the given URL with a
+///   `Content-Type: application/json` header and an initial `max_response_bytes` of
+///   [`JSON_RPC_INITIAL_MAX_RESPONSE_BYTES`];
+/// * [`DoubleMaxResponseBytes`] to retry requests whose response was too large;
+/// * [`JsonRpcHttpLayer`] to serialize requests and deserialize responses, which also ensures
+///   that the ID of each response matches the ID of the corresponding request;
+/// * [`ChargeMyself`] so that the canister pays for the HTTPs outcalls with its own cycles;
+/// * [`Client`] to issue the HTTPs outcalls.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use canhttp::http::json::{Id, JsonRpcRequest};
+/// use serde_json::json;
+/// use tower::{Service, ServiceExt};
+///
+/// # async fn example() -> Result<(), tower::BoxError> {
+/// let mut client = canhttp::presets::json_rpc("https://api.mainnet-beta.solana.com");
+///
+/// let request = JsonRpcRequest::new("getSlot", json!([{"commitment": "finalized"}]))
+///     .with_id(Id::Number(1));
+/// let response = client.ready().await?.call(request).await?;
+///
+/// let slot: u64 = response.into_result()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn json_rpc<Params, Result>(
+    url: impl Into<String>,
+) -> impl Service<JsonRpcRequest<Params>, Response = JsonRpcResponse<Result>, Error = BoxError> + Clone
+where
+    Params: Clone + Debug + Serialize,
+    Result: Debug + DeserializeOwned,
+{
+    let url = url.into();
+    ServiceBuilder::new()
+        .map_response(|response: HttpJsonRpcResponse<Result>| response.into_body())
+        .try_map_request(move |request: JsonRpcRequest<Params>| {
+            http::Request::post(&url)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .max_response_bytes(JSON_RPC_INITIAL_MAX_RESPONSE_BYTES)
+                .body(request)
+        })
+        .retry(DoubleMaxResponseBytes)
+        .layer(JsonRpcHttpLayer::new())
+        .cycles_accounting(ChargeMyself::default())
+        .service(Client::new_with_box_error())
+}
+
+/// Returns a service to make JSON-RPC requests to the Bitcoin Core node at the given URL,
+/// authenticated with the node's `rpcuser` and `rpcpassword`.
+///
+/// The service uses the same stack as [`json_rpc`], except that:
+/// * [`BasicAuthLayer`] sets the `Authorization` header of each HTTP request;
+/// * [`bitcoin::json_rpc_layer`] tolerates the legacy response format of Bitcoin Core,
+///   i.e. responses without a `jsonrpc` member and with both a `result` and an `error` member.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use canhttp::http::json::{bitcoin::GetBlockCount, Id, JsonRpcRequest};
+/// use tower::{Service, ServiceExt};
+///
+/// # async fn example() -> Result<(), tower::BoxError> {
+/// let mut client = canhttp::presets::bitcoin_core("http://127.0.0.1:8332", "bitcoin", "secret");
+///
+/// let request = JsonRpcRequest::from_method::<GetBlockCount>([]).with_id(Id::Number(1));
+/// let response = client.ready().await?.call(request).await?;
+///
+/// let block_count: u64 = response.into_result()?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "bitcoin")]
+pub fn bitcoin_core<Params, Result>(
+    url: impl Into<String>,
+    username: impl AsRef<str>,
+    password: impl AsRef<str>,
+) -> impl Service<JsonRpcRequest<Params>, Response = JsonRpcResponse<Result>, Error = BoxError> + Clone
+where
+    Params: Clone + Debug + Serialize,
+    Result: Debug + DeserializeOwned,
+{
+    let url = url.into();
+    ServiceBuilder::new()
+        .map_response(|response: HttpJsonRpcResponse<Result>| response.into_body())
+        .try_map_request(move |request: JsonRpcRequest<Params>| {
+            http::Request::post(&url)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .max_response_bytes(JSON_RPC_INITIAL_MAX_RESPONSE_BYTES)
+                .body(request)
+        })
+        .retry(DoubleMaxResponseBytes)
+        .layer(BasicAuthLayer::new(username, password))
+        .layer(bitcoin::json_rpc_layer())
+        .cycles_accounting(ChargeMyself::default())
+        .service(Client::new_with_box_error())
+}
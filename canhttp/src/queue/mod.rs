@@ -0,0 +1,617 @@
+//! Durable queue of outbound requests persisted to stable memory.
+//!
+//! Fire-and-forget requests, such as webhooks, must eventually be delivered even if the HTTPs
+//! outcall fails or the canister is upgraded in the meantime. A [`StableRequestQueue`] persists
+//! enqueued requests to a fixed region of stable memory and only removes them once they were
+//! successfully delivered, which guarantees *at-least-once* delivery:
+//! 1. Requests are added to the queue with [`StableRequestQueue::enqueue`], e.g. in an update
+//!    call, which returns immediately.
+//! 2. The queue is drained with [`StableRequestQueue::drain`], which sends the requests that are
+//!    due to a service and should be called periodically, e.g. from a timer set up with
+//!    [`ic-cdk-timers`](https://crates.io/crates/ic-cdk-timers) in the `init` and `post_upgrade`
+//!    hooks of the canister.
+//! 3. Failed requests are retried with an exponential backoff (see [`Backoff`]) and abandoned
+//!    after a maximum number of attempts.
+//!
+//! Requests that were in flight when the canister was upgraded are sent again once the queue is
+//! drained after the upgrade, so the receiver should be idempotent, e.g. by setting an
+//! idempotency key header on each request before enqueuing it.
+//!
+//! # Security
+//!
+//! **Enqueued requests, including all their headers such as `Authorization`, are persisted in
+//! plaintext in stable memory**, which is readable by the nodes hosting the canister and
+//! survives upgrades. Do not enqueue requests carrying secrets. Instead, add credentials when the
+//! queue is drained, with a layer on the service given to [`StableRequestQueue::drain`], e.g. an
+//! [`ApiKeyRotationLayer`](crate::http::ApiKeyRotationLayer).
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::queue::{Backoff, StableRequestQueue};
+//! use ic_stable_structures::{
+//!     memory_manager::{MemoryId, MemoryManager},
+//!     DefaultMemoryImpl,
+//! };
+//! use tower::BoxError;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), BoxError> {
+//! let memory_manager = MemoryManager::init(DefaultMemoryImpl::default());
+//!
+//! // Dedicate the virtual memory with ID 0 to the queue, of which it may use up to 1MiB.
+//! let queue = StableRequestQueue::new(memory_manager.get(MemoryId::new(0)), 0, 1024 * 1024)
+//!     .backoff(Backoff::default().max_attempts(5))
+//!     .clock(|| 0);
+//!
+//! let webhook = http::Request::post("https://example.com/webhook")
+//!     .body(b"{\"event\":\"deposit\"}".to_vec())?;
+//! queue.enqueue(webhook)?;
+//!
+//! // Periodically, e.g. from a timer:
+//! let service = tower::service_fn(|_request: canhttp::http::HttpRequest| async {
+//!     Ok::<_, BoxError>(http::Response::new(Vec::<u8>::new()))
+//! });
+//! let (_service, report) = queue.drain(service).await;
+//!
+//! assert_eq!(report?.delivered.len(), 1);
+//! assert!(queue.is_empty()?);
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    batch::execute_all,
+    clock::Clock,
+    http::{
+        stable::{
+            region::{self, CapacityExceeded, Decoder, Encoder, WriteError},
+            Memory,
+        },
+        HttpRequest,
+    },
+    MaxResponseBytesRequestExtension,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    rc::Rc,
+    time::Duration,
+};
+use thiserror::Error;
+use tower::Service;
+
+const ENCODING_VERSION: u8 = 1;
+
+/// Error returned by a [`StableRequestQueue`].
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum StableRequestQueueError {
+    /// The encoded queue including the new request does not fit in the region.
+    #[error("Encoded queue of {len} bytes exceeds the capacity of {capacity} bytes")]
+    QueueFull {
+        /// Length of the encoded queue in bytes.
+        len: u64,
+        /// Capacity of the region in bytes.
+        capacity: u64,
+    },
+    /// The stable memory could not be grown to hold the queue.
+    #[error("Failed to grow stable memory to {required_pages} pages")]
+    OutOfMemory {
+        /// Number of pages required to hold the queue.
+        required_pages: u64,
+    },
+    /// The queue stored in stable memory exceeds the capacity of the region, e.g. because the
+    /// region was shrunk or overwritten.
+    #[error("Stored queue of {len} bytes exceeds the capacity of {capacity} bytes")]
+    CapacityExceeded {
+        /// Length of the stored queue in bytes.
+        len: u64,
+        /// Capacity of the region in bytes.
+        capacity: u64,
+    },
+    /// The queue stored in stable memory could not be decoded.
+    #[error("Stored queue is corrupted")]
+    Corrupted,
+}
+
+impl From<WriteError> for StableRequestQueueError {
+    fn from(error: WriteError) -> Self {
+        match error {
+            WriteError::TooLarge { len, capacity } => Self::QueueFull { len, capacity },
+            WriteError::OutOfMemory { required_pages } => Self::OutOfMemory { required_pages },
+        }
+    }
+}
+
+impl From<CapacityExceeded> for StableRequestQueueError {
+    fn from(CapacityExceeded { len, capacity }: CapacityExceeded) -> Self {
+        Self::CapacityExceeded { len, capacity }
+    }
+}
+
+/// Request stored in a [`StableRequestQueue`].
+///
+/// Only the method, URI, headers, body and `max_response_bytes` of the enqueued request are
+/// persisted; other request extensions are lost.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueuedRequest {
+    /// Identifier of the request in the queue, increasing with each enqueued request.
+    pub id: u64,
+    /// Number of failed attempts to deliver the request.
+    pub failed_attempts: u32,
+    /// Time in nanoseconds since the UNIX epoch from which the request may be sent.
+    pub next_attempt_ns: u64,
+    /// HTTP method of the request.
+    pub method: http::Method,
+    /// URI of the request.
+    pub uri: http::Uri,
+    /// Headers of the request.
+    pub headers: http::HeaderMap,
+    /// Body of the request.
+    pub body: Vec<u8>,
+    /// Value of `max_response_bytes` of the request, if any.
+    pub max_response_bytes: Option<u64>,
+}
+
+impl QueuedRequest {
+    /// Returns the [`HttpRequest`] to send.
+    pub fn to_http_request(&self) -> HttpRequest {
+        let mut request = http::Request::new(self.body.clone());
+        *request.method_mut() = self.method.clone();
+        *request.uri_mut() = self.uri.clone();
+        *request.headers_mut() = self.headers.clone();
+        if let Some(max_response_bytes) = self.max_response_bytes {
+            request.set_max_response_bytes(max_response_bytes);
+        }
+        request
+    }
+}
+
+/// Exponential backoff between attempts to deliver a request of a [`StableRequestQueue`]
+/// or to retry a rate-limited request with [`RetryOnRateLimit`](crate::retry::RetryOnRateLimit).
+///
+/// After the `n`-th failed attempt, the request is retried after `initial_delay * 2^(n-1)`,
+/// but at most after `max_delay`. The request is abandoned after `max_attempts` failed attempts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Backoff {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_attempts: u32,
+}
+
+impl Default for Backoff {
+    /// Retry after 1 second, 2 seconds, 4 seconds, ..., at most after 10 minutes,
+    /// and abandon the request after 10 failed attempts.
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(600),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl Backoff {
+    /// Change the delay before the first retry.
+    pub fn initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    /// Change the maximum delay between two attempts.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Change the number of failed attempts after which a request is abandoned.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Returns the delay before the next attempt after the given number of failed attempts.
+    pub fn delay(&self, failed_attempts: u32) -> Duration {
+        let factor = 1_u32
+            .checked_shl(failed_attempts.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+
+    /// Returns `true` if and only if a request should be abandoned after the given number of
+    /// failed attempts.
+    pub fn is_exhausted(&self, failed_attempts: u32) -> bool {
+        failed_attempts >= self.max_attempts
+    }
+}
+
+/// Outcome of [`StableRequestQueue::drain`].
+#[derive(Debug)]
+pub struct DrainReport<E> {
+    /// IDs of the requests that were successfully delivered and removed from the queue.
+    pub delivered: Vec<u64>,
+    /// IDs of the requests that failed and will be retried.
+    pub retried: Vec<u64>,
+    /// Requests that failed too many times and were removed from the queue,
+    /// together with the error of the last attempt.
+    pub abandoned: Vec<(QueuedRequest, E)>,
+    /// Error that occurred while writing the updated queue to stable memory, if any.
+    ///
+    /// The queue on the heap is up to date in any case and is written again with the next
+    /// modification or call to [`StableRequestQueue::persist`]. Until then, an upgrade would
+    /// restore the queue as it was before this drain.
+    pub persist_error: Option<StableRequestQueueError>,
+}
+
+impl<E> Default for DrainReport<E> {
+    fn default() -> Self {
+        Self {
+            delivered: Vec::new(),
+            retried: Vec::new(),
+            abandoned: Vec::new(),
+            persist_error: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    next_id: u64,
+    requests: BTreeMap<u64, QueuedRequest>,
+}
+
+/// Queue of outbound requests persisted to a fixed region of stable memory, so that they
+/// survive canister upgrades. See the [module docs](crate::queue).
+///
+/// The queue is read from stable memory when first accessed, e.g. after an upgrade, and is then
+/// cached on the heap. Each modification is written through to stable memory by re-encoding the
+/// whole queue, which makes it suitable for queues of moderate size.
+///
+/// Cloning a [`StableRequestQueue`] is cheap and all clones share the same queue.
+#[derive(Clone)]
+pub struct StableRequestQueue<M> {
+    memory: M,
+    offset: u64,
+    capacity: u64,
+    backoff: Backoff,
+    batch_size: usize,
+    clock: Clock,
+    cache: Rc<RefCell<Option<QueueState>>>,
+    // Requests being sent are not persisted, so that they are sent again after an upgrade.
+    in_flight: Rc<RefCell<BTreeSet<u64>>>,
+}
+
+impl<M> StableRequestQueue<M> {
+    /// Create a new [`StableRequestQueue`] in the given memory occupying `capacity` bytes
+    /// starting at `offset`.
+    ///
+    /// The region must not be used for anything else by the canister and must remain the same
+    /// across upgrades. Using the raw stable memory of the canister (e.g.
+    /// [`CanisterStableMemory`](crate::http::stable::CanisterStableMemory)) is only safe if
+    /// nothing else, such as a `MemoryManager`, manages it.
+    pub fn new(memory: M, offset: u64, capacity: u64) -> Self {
+        Self {
+            memory,
+            offset,
+            capacity,
+            backoff: Backoff::default(),
+            batch_size: 10,
+            clock: Clock::default(),
+            cache: Rc::new(RefCell::new(None)),
+            in_flight: Rc::new(RefCell::new(BTreeSet::new())),
+        }
+    }
+
+    /// Change the backoff between attempts to deliver a request.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Change the maximum number of requests sent concurrently by [`StableRequestQueue::drain`].
+    ///
+    /// Defaults to 10.
+    ///
+    /// # Panics
+    ///
+    /// If `batch_size` is zero.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batch_size must be greater than zero");
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Change the clock used to determine when requests are due.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+}
+
+impl<M: Memory> StableRequestQueue<M> {
+    /// Add the given request to the queue and return its ID.
+    ///
+    /// The request is persisted to stable memory before this method returns and may be sent
+    /// with the next call to [`StableRequestQueue::drain`]. All its headers are persisted in
+    /// plaintext, see the [module docs](crate::queue#security).
+    pub fn enqueue(&self, request: HttpRequest) -> Result<u64, StableRequestQueueError> {
+        let max_response_bytes = request.get_max_response_bytes();
+        let (parts, body) = request.into_parts();
+        let now = self.clock.now();
+        self.with_state(|state| {
+            let id = state.next_id;
+            state.next_id += 1;
+            state.requests.insert(
+                id,
+                QueuedRequest {
+                    id,
+                    failed_attempts: 0,
+                    next_attempt_ns: now,
+                    method: parts.method,
+                    uri: parts.uri,
+                    headers: parts.headers,
+                    body,
+                    max_response_bytes,
+                },
+            );
+            if let Err(e) = self.write(state) {
+                // The request is only accepted if it was persisted.
+                state.requests.remove(&id);
+                state.next_id -= 1;
+                return Err(e);
+            }
+            Ok(id)
+        })?
+    }
+
+    /// Returns the requests currently in the queue, ordered by ID.
+    pub fn requests(&self) -> Result<Vec<QueuedRequest>, StableRequestQueueError> {
+        self.with_state(|state| state.requests.values().cloned().collect())
+    }
+
+    /// Returns the number of requests in the queue.
+    pub fn len(&self) -> Result<usize, StableRequestQueueError> {
+        self.with_state(|state| state.requests.len())
+    }
+
+    /// Returns `true` if the queue is empty.
+    pub fn is_empty(&self) -> Result<bool, StableRequestQueueError> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Remove the request with the given ID from the queue, e.g. to cancel it.
+    ///
+    /// Returns the removed request, or [`None`] if there is no request with that ID.
+    /// If the updated queue cannot be written to stable memory, the request is still removed
+    /// from the queue on the heap, but would be restored by an upgrade until the queue is
+    /// successfully persisted.
+    pub fn remove(&self, id: u64) -> Result<Option<QueuedRequest>, StableRequestQueueError> {
+        let removed = self.with_state(|state| state.requests.remove(&id))?;
+        if removed.is_some() {
+            self.persist()?;
+        }
+        Ok(removed)
+    }
+
+    /// Remove all requests from the queue, without reading the queue stored in stable memory.
+    ///
+    /// This is the only way to reuse a region whose queue is
+    /// [`Corrupted`](StableRequestQueueError::Corrupted) or
+    /// [exceeds its capacity](StableRequestQueueError::CapacityExceeded), which discards the
+    /// stored requests. IDs then start again from 0.
+    pub fn clear(&self) -> Result<(), StableRequestQueueError> {
+        let state = QueueState::default();
+        self.write(&state)?;
+        *self.cache.borrow_mut() = Some(state);
+        Ok(())
+    }
+
+    /// Send the requests that are due, at most [`batch_size`](StableRequestQueue::batch_size)
+    /// of them concurrently, to the given service and update the queue with the results.
+    ///
+    /// Requests are delivered once the service returns a response, independently of its content,
+    /// e.g. a layer such as [`FilterNonSuccessfulHttpResponse`] must be used to treat
+    /// non-successful HTTP responses as failures. Requests already being sent by another
+    /// call to this method are skipped.
+    ///
+    /// Returns an error without sending any request if the queue cannot be read from stable
+    /// memory.
+    ///
+    /// [`FilterNonSuccessfulHttpResponse`]: crate::http::FilterNonSuccessfulHttpResponse
+    pub async fn drain<S>(
+        &self,
+        service: S,
+    ) -> (S, Result<DrainReport<S::Error>, StableRequestQueueError>)
+    where
+        S: Service<HttpRequest>,
+    {
+        let now = self.clock.now();
+        let due = self.with_state(|state| {
+            let in_flight = self.in_flight.borrow();
+            state
+                .requests
+                .values()
+                .filter(|request| request.next_attempt_ns <= now)
+                .filter(|request| !in_flight.contains(&request.id))
+                .take(self.batch_size)
+                .cloned()
+                .collect::<Vec<_>>()
+        });
+        let due = match due {
+            Ok(due) => due,
+            Err(e) => return (service, Err(e)),
+        };
+        self.in_flight
+            .borrow_mut()
+            .extend(due.iter().map(|request| request.id));
+
+        let http_requests = due.iter().map(QueuedRequest::to_http_request).collect();
+        let (service, results) = execute_all(service, http_requests, self.batch_size).await;
+
+        let now = self.clock.now();
+        let mut report = DrainReport::default();
+        let mut cache = self.cache.borrow_mut();
+        // The queue was read before sending the requests and is only modified in memory.
+        let state = cache.as_mut().expect("BUG: queue was read before draining");
+        for (request, result) in due.into_iter().zip(results) {
+            self.in_flight.borrow_mut().remove(&request.id);
+            let error = match result {
+                Ok(_) => {
+                    state.requests.remove(&request.id);
+                    report.delivered.push(request.id);
+                    continue;
+                }
+                Err(error) => error,
+            };
+            // The request may have been removed in the meantime.
+            let Some(queued) = state.requests.get_mut(&request.id) else {
+                continue;
+            };
+            queued.failed_attempts += 1;
+            if self.backoff.is_exhausted(queued.failed_attempts) {
+                let abandoned = state
+                    .requests
+                    .remove(&request.id)
+                    .expect("BUG: missing request");
+                report.abandoned.push((abandoned, error));
+            } else {
+                let delay = self.backoff.delay(queued.failed_attempts);
+                queued.next_attempt_ns =
+                    now.saturating_add(u64::try_from(delay.as_nanos()).unwrap_or(u64::MAX));
+                report.retried.push(request.id);
+            }
+        }
+        report.persist_error = self.write(state).err();
+        (service, Ok(report))
+    }
+
+    /// Write the current queue to stable memory.
+    ///
+    /// This is done automatically on each modification, which reports failures as well,
+    /// but can be used to retry after a failure.
+    pub fn persist(&self) -> Result<(), StableRequestQueueError> {
+        let cache = self.cache.borrow();
+        match cache.as_ref() {
+            Some(state) => self.write(state),
+            // The queue was not modified since it was persisted.
+            None => Ok(()),
+        }
+    }
+
+    /// Apply the given function to the queue, which is read from stable memory if not cached.
+    ///
+    /// The queue is only cached if it could be read, so that a corrupted queue is never
+    /// overwritten.
+    fn with_state<T>(
+        &self,
+        f: impl FnOnce(&mut QueueState) -> T,
+    ) -> Result<T, StableRequestQueueError> {
+        let mut cache = self.cache.borrow_mut();
+        if cache.is_none() {
+            *cache = Some(self.read()?);
+        }
+        Ok(f(cache.as_mut().expect("BUG: queue was just read")))
+    }
+
+    fn write(&self, state: &QueueState) -> Result<(), StableRequestQueueError> {
+        region::write_length_prefixed(&self.memory, self.offset, self.capacity, &encode(state))?;
+        Ok(())
+    }
+
+    /// Read the queue from stable memory, starting from an empty queue if the region
+    /// was never written.
+    fn read(&self) -> Result<QueueState, StableRequestQueueError> {
+        match region::read_length_prefixed(&self.memory, self.offset, self.capacity)? {
+            Some(encoded) => decode(&encoded).ok_or(StableRequestQueueError::Corrupted),
+            None => Ok(QueueState::default()),
+        }
+    }
+}
+
+impl<M: fmt::Debug> fmt::Debug for StableRequestQueue<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableRequestQueue")
+            .field("memory", &self.memory)
+            .field("offset", &self.offset)
+            .field("capacity", &self.capacity)
+            .field("backoff", &self.backoff)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+fn encode(state: &QueueState) -> Vec<u8> {
+    let mut encoder = Encoder::new(ENCODING_VERSION);
+    encoder.u64(state.next_id);
+    encoder.u64(state.requests.len() as u64);
+    for request in state.requests.values() {
+        encoder.u64(request.id);
+        encoder.u64(u64::from(request.failed_attempts));
+        encoder.u64(request.next_attempt_ns);
+        encoder.bytes(request.method.as_str().as_bytes());
+        encoder.bytes(request.uri.to_string().as_bytes());
+        encoder.u64(request.headers.len() as u64);
+        for (name, value) in &request.headers {
+            encoder.bytes(name.as_str().as_bytes());
+            encoder.bytes(value.as_bytes());
+        }
+        encoder.bytes(&request.body);
+        match request.max_response_bytes {
+            Some(max_response_bytes) => {
+                encoder.u8(1);
+                encoder.u64(max_response_bytes);
+            }
+            None => encoder.u8(0),
+        }
+    }
+    encoder.into_bytes()
+}
+
+fn decode(bytes: &[u8]) -> Option<QueueState> {
+    let mut decoder = Decoder::new(bytes);
+    if decoder.u8()? != ENCODING_VERSION {
+        return None;
+    }
+    let next_id = decoder.u64()?;
+    let requests = (0..decoder.u64()?)
+        .map(|_| {
+            let id = decoder.u64()?;
+            let failed_attempts = u32::try_from(decoder.u64()?).ok()?;
+            let next_attempt_ns = decoder.u64()?;
+            let method = http::Method::from_bytes(decoder.bytes()?).ok()?;
+            let uri = http::Uri::try_from(decoder.bytes()?).ok()?;
+            let mut headers = http::HeaderMap::new();
+            for _ in 0..decoder.u64()? {
+                let name = http::HeaderName::from_bytes(decoder.bytes()?).ok()?;
+                let value = http::HeaderValue::from_bytes(decoder.bytes()?).ok()?;
+                headers.append(name, value);
+            }
+            let body = decoder.bytes()?.to_vec();
+            let max_response_bytes = match decoder.u8()? {
+                0 => None,
+                1 => Some(decoder.u64()?),
+                _ => return None,
+            };
+            let request = QueuedRequest {
+                id,
+                failed_attempts,
+                next_attempt_ns,
+                method,
+                uri,
+                headers,
+                body,
+                max_response_bytes,
+            };
+            Some((id, request))
+        })
+        .collect::<Option<_>>()?;
+    decoder
+        .is_empty()
+        .then_some(QueueState { next_id, requests })
+}
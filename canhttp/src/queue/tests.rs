@@ -0,0 +1,265 @@
+use crate::{
+    http::{stable::Memory, HttpRequest},
+    queue::{Backoff, StableRequestQueue, StableRequestQueueError},
+    MaxResponseBytesRequestExtension,
+};
+use ic_stable_structures::VectorMemory;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
+use tower::BoxError;
+
+const SECOND_NS: u64 = 1_000_000_000;
+
+#[test]
+fn should_persist_requests_across_upgrades() {
+    let memory = VectorMemory::default();
+    let queue = StableRequestQueue::new(memory.clone(), 100, 64 * 1024).clock(|| 42);
+
+    let first = queue
+        .enqueue(
+            http::Request::post("https://example.com/webhook")
+                .header("Idempotency-Key", "abc")
+                .max_response_bytes(1_000)
+                .body(b"first".to_vec())
+                .unwrap(),
+        )
+        .unwrap();
+    let second = queue.enqueue(request("https://example.com/other")).unwrap();
+    assert_eq!((first, second), (0, 1));
+
+    // Simulate an upgrade by creating a new queue on the same memory.
+    let upgraded = StableRequestQueue::new(memory, 100, 64 * 1024).clock(|| 42);
+
+    assert_eq!(upgraded.requests().unwrap(), queue.requests().unwrap());
+    let requests = upgraded.requests().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].next_attempt_ns, 42);
+    let request = requests[0].to_http_request();
+    assert_eq!(request.method(), http::Method::POST);
+    assert_eq!(request.uri(), "https://example.com/webhook");
+    assert_eq!(request.headers()["idempotency-key"], "abc");
+    assert_eq!(request.get_max_response_bytes(), Some(1_000));
+    assert_eq!(request.body(), b"first");
+    assert_eq!(upgraded.enqueue(request_with_body(b"third")).unwrap(), 2);
+}
+
+#[test]
+fn should_reject_requests_when_queue_is_full() {
+    let queue = StableRequestQueue::new(VectorMemory::default(), 0, 200).clock(|| 0);
+
+    queue.enqueue(request_with_body(&[1_u8; 50])).unwrap();
+
+    assert_eq!(
+        queue.enqueue(request_with_body(&[2_u8; 50])),
+        Err(StableRequestQueueError::QueueFull {
+            len: 287,
+            capacity: 200
+        })
+    );
+    assert_eq!(queue.len().unwrap(), 1);
+
+    assert!(queue.remove(0).unwrap().is_some());
+    assert_eq!(queue.enqueue(request_with_body(&[2_u8; 50])).unwrap(), 1);
+}
+
+#[tokio::test]
+async fn should_not_overwrite_corrupted_queue() {
+    let memory = VectorMemory::default();
+    let queue = StableRequestQueue::new(memory.clone(), 100, 64 * 1024).clock(|| 0);
+    queue.enqueue(request("https://example.com")).unwrap();
+    // Corrupt the encoding version.
+    memory.write(100 + 8, &[u8::MAX]);
+    let stored = memory.borrow().clone();
+
+    let upgraded = StableRequestQueue::new(memory.clone(), 100, 64 * 1024).clock(|| 0);
+
+    assert_eq!(
+        upgraded.enqueue(request("https://example.com")),
+        Err(StableRequestQueueError::Corrupted)
+    );
+    assert_eq!(upgraded.len(), Err(StableRequestQueueError::Corrupted));
+    let service = tower::service_fn(|_request: HttpRequest| async {
+        Ok::<_, BoxError>(http::Response::new(Vec::<u8>::new()))
+    });
+    let (_service, report) = upgraded.drain(service).await;
+    assert_eq!(report.unwrap_err(), StableRequestQueueError::Corrupted);
+    assert_eq!(*memory.borrow(), stored);
+
+    upgraded.clear().unwrap();
+    assert!(upgraded.is_empty().unwrap());
+    assert_eq!(upgraded.enqueue(request("https://example.com")), Ok(0));
+}
+
+#[test]
+fn should_fail_when_stored_queue_exceeds_capacity() {
+    let memory = VectorMemory::default();
+    let queue = StableRequestQueue::new(memory.clone(), 0, 64 * 1024).clock(|| 0);
+    queue.enqueue(request_with_body(&[1_u8; 50])).unwrap();
+
+    let shrunk = StableRequestQueue::new(memory, 0, 100).clock(|| 0);
+
+    assert_eq!(
+        shrunk.requests(),
+        Err(StableRequestQueueError::CapacityExceeded {
+            len: 156,
+            capacity: 100
+        })
+    );
+}
+
+#[tokio::test]
+async fn should_deliver_and_retry_requests_with_backoff() {
+    let now = Rc::new(Cell::new(0));
+    let queue = StableRequestQueue::new(VectorMemory::default(), 0, 64 * 1024)
+        .backoff(
+            Backoff::default()
+                .initial_delay(Duration::from_secs(10))
+                .max_attempts(3),
+        )
+        .clock({
+            let now = now.clone();
+            move || now.get()
+        });
+    let ok = queue.enqueue(request("https://example.com/ok")).unwrap();
+    let failing = queue.enqueue(request("https://example.com/fail")).unwrap();
+    let sent = Rc::new(RefCell::new(Vec::new()));
+    let service = tower::service_fn({
+        let sent = sent.clone();
+        move |request: HttpRequest| {
+            let url = request.uri().to_string();
+            sent.borrow_mut().push(url.clone());
+            async move {
+                if url.ends_with("fail") {
+                    Err::<http::Response<Vec<u8>>, BoxError>("failed".into())
+                } else {
+                    Ok(http::Response::new(Vec::new()))
+                }
+            }
+        }
+    });
+
+    let (service, report) = queue.drain(service).await;
+    let report = report.unwrap();
+    assert_eq!(report.delivered, vec![ok]);
+    assert_eq!(report.retried, vec![failing]);
+    assert_eq!(queue.requests().unwrap()[0].failed_attempts, 1);
+    assert_eq!(queue.requests().unwrap()[0].next_attempt_ns, 10 * SECOND_NS);
+
+    // Not due yet
+    now.set(5 * SECOND_NS);
+    let (service, report) = queue.drain(service).await;
+    let report = report.unwrap();
+    assert!(report.retried.is_empty());
+
+    now.set(10 * SECOND_NS);
+    let (service, report) = queue.drain(service).await;
+    let report = report.unwrap();
+    assert_eq!(report.retried, vec![failing]);
+    assert_eq!(queue.requests().unwrap()[0].next_attempt_ns, 30 * SECOND_NS);
+
+    now.set(30 * SECOND_NS);
+    let (_service, report) = queue.drain(service).await;
+    let report = report.unwrap();
+    assert!(report.retried.is_empty());
+    assert_eq!(report.abandoned.len(), 1);
+    assert_eq!(report.abandoned[0].0.id, failing);
+    assert_eq!(report.abandoned[0].0.failed_attempts, 3);
+    assert!(queue.is_empty().unwrap());
+    assert_eq!(
+        *sent.borrow(),
+        vec![
+            "https://example.com/ok",
+            "https://example.com/fail",
+            "https://example.com/fail",
+            "https://example.com/fail"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn should_send_at_most_batch_size_requests() {
+    let queue = StableRequestQueue::new(VectorMemory::default(), 0, 64 * 1024)
+        .batch_size(2)
+        .clock(|| 0);
+    for _ in 0..3 {
+        queue.enqueue(request("https://example.com")).unwrap();
+    }
+    let service = tower::service_fn(|_request: HttpRequest| async {
+        Ok::<_, BoxError>(http::Response::new(Vec::<u8>::new()))
+    });
+
+    let (service, report) = queue.drain(service).await;
+    let report = report.unwrap();
+    assert_eq!(report.delivered, vec![0, 1]);
+
+    let (_service, report) = queue.drain(service).await;
+    let report = report.unwrap();
+    assert_eq!(report.delivered, vec![2]);
+    assert!(queue.is_empty().unwrap());
+}
+
+#[tokio::test]
+async fn should_report_persist_error_when_draining() {
+    let queue = StableRequestQueue::new(FixedSizeMemory, 0, 64 * 1024).clock(|| 0);
+    let service = tower::service_fn(|_request: HttpRequest| async {
+        Ok::<_, BoxError>(http::Response::new(Vec::<u8>::new()))
+    });
+
+    let (_service, report) = queue.drain(service).await;
+    let report = report.unwrap();
+
+    assert!(report.delivered.is_empty());
+    assert_eq!(
+        report.persist_error,
+        Some(StableRequestQueueError::OutOfMemory { required_pages: 1 })
+    );
+}
+
+#[test]
+fn should_compute_backoff_delays() {
+    let backoff = Backoff::default()
+        .initial_delay(Duration::from_secs(1))
+        .max_delay(Duration::from_secs(60));
+
+    let delays: Vec<_> = (1..=8)
+        .map(|attempts| backoff.delay(attempts).as_secs())
+        .collect();
+
+    assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+    assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(60));
+}
+
+fn request(url: &str) -> HttpRequest {
+    http::Request::post(url).body(vec![]).unwrap()
+}
+
+fn request_with_body(body: &[u8]) -> HttpRequest {
+    http::Request::post("https://example.com")
+        .body(body.to_vec())
+        .unwrap()
+}
+
+/// Empty memory that cannot be grown.
+#[derive(Clone, Debug)]
+struct FixedSizeMemory;
+
+impl Memory for FixedSizeMemory {
+    fn size(&self) -> u64 {
+        0
+    }
+
+    fn grow(&self, _pages: u64) -> i64 {
+        -1
+    }
+
+    fn read(&self, _offset: u64, _dst: &mut [u8]) {
+        unreachable!("memory is empty")
+    }
+
+    fn write(&self, _offset: u64, _src: &[u8]) {
+        unreachable!("memory is empty")
+    }
+}
@@ -1,9 +1,14 @@
 //! Middleware for retrying "failed" requests.
 
+#[cfg(all(feature = "http", feature = "json"))]
+mod rate_limit;
 #[cfg(test)]
 mod tests;
 
-use crate::{HttpsOutcallError, MaxResponseBytesRequestExtension};
+#[cfg(all(feature = "http", feature = "json"))]
+pub use rate_limit::{RetryOnRateLimit, Sleep, RATE_LIMIT_ERROR_CODE};
+
+use crate::{CancellationRequestExtension, HttpsOutcallError, MaxResponseBytesRequestExtension};
 use std::future;
 use tower::retry;
 
@@ -103,3 +108,86 @@ where
         }
     }
 }
+
+/// Stop retrying requests whose [`CancellationToken`](crate::CancellationToken) was cancelled,
+/// and otherwise retry them according to the wrapped policy.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{
+///     http::HttpRequest, CancellationRequestExtension, CancellationToken, IcError,
+///     retry::{DoubleMaxResponseBytes, StopOnCancellation}, MaxResponseBytesRequestExtension,
+/// };
+/// use ic_error_types::RejectCode;
+/// use std::{cell::Cell, rc::Rc};
+/// use tower::{Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let token = CancellationToken::new();
+/// let attempts = Rc::new(Cell::new(0));
+/// let mut service = ServiceBuilder::new()
+///     .retry(StopOnCancellation::new(DoubleMaxResponseBytes))
+///     .service_fn(|_request: HttpRequest| {
+///         let (token, attempts) = (token.clone(), attempts.clone());
+///         async move {
+///             attempts.set(attempts.get() + 1);
+///             // Cancel the token after the second attempt.
+///             if attempts.get() == 2 {
+///                 token.cancel();
+///             }
+///             Err::<(), IcError>(IcError::CallRejected {
+///                 code: RejectCode::SysFatal,
+///                 message: "Http body exceeds size limit".to_string(),
+///             })
+///         }
+///     });
+///
+/// let request = http::Request::post("https://internetcomputer.org/")
+///     .max_response_bytes(0)
+///     .cancellation_token(token.clone())
+///     .body(vec![])?;
+///
+/// assert!(service.ready().await?.call(request).await.is_err());
+/// assert_eq!(attempts.get(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StopOnCancellation<P> {
+    policy: P,
+}
+
+impl<P> StopOnCancellation<P> {
+    /// Wrap the given retry policy.
+    pub fn new(policy: P) -> Self {
+        Self { policy }
+    }
+}
+
+impl<P, Request, Response, Error> retry::Policy<Request, Response, Error> for StopOnCancellation<P>
+where
+    P: retry::Policy<Request, Response, Error>,
+    Request: CancellationRequestExtension,
+{
+    type Future = P::Future;
+
+    fn retry(
+        &mut self,
+        req: &mut Request,
+        result: &mut Result<Response, Error>,
+    ) -> Option<Self::Future> {
+        if req.is_cancelled() {
+            return None;
+        }
+        self.policy.retry(req, result)
+    }
+
+    fn clone_request(&mut self, req: &Request) -> Option<Request> {
+        if req.is_cancelled() {
+            return None;
+        }
+        self.policy.clone_request(req)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    http::json::{BatchJsonRpcResponse, JsonRpcError, JsonRpcResponse},
+    queue::Backoff,
+};
+use std::{collections::BTreeSet, fmt, future::Future, pin::Pin, rc::Rc, time::Duration};
+use tower::retry;
+
+/// JSON-RPC error code used by many providers to signal that a request was rate-limited
+/// (e.g. `"limit exceeded"`), see [EIP-1474](https://eips.ethereum.org/EIPS/eip-1474).
+pub const RATE_LIMIT_ERROR_CODE: i64 = -32005;
+
+/// Future waiting for the backoff delay to elapse before the next attempt.
+pub type Sleep = Pin<Box<dyn Future<Output = ()>>>;
+
+type SleepFn = Rc<dyn Fn(Duration) -> Sleep>;
+
+/// Retry JSON-RPC requests whose response contains a rate-limit error.
+///
+/// Rate-limited JSON-RPC requests usually come back with an HTTP `200 OK` status
+/// and a JSON-RPC error in the body, so that they are invisible to retry policies acting on
+/// the HTTP layer. This policy inspects the decoded JSON-RPC response instead and retries the
+/// request if the error code is one of the configured rate-limit codes
+/// (only [`RATE_LIMIT_ERROR_CODE`] by default), waiting according to the given [`Backoff`]
+/// in between attempts. For a batch response, the whole batch is retried as soon as
+/// one of its responses is rate-limited.
+///
+/// The crate does not depend on a timer, so the delay is actually waited for by the future
+/// returned by [`RetryOnRateLimit::sleep`], which by default completes immediately.
+/// In a canister, such a future can be built, e.g., with `ic_cdk_timers::set_timer`
+/// and a oneshot channel.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{
+///     http::json::{JsonRpcError, JsonRpcRequest, JsonRpcResponse},
+///     retry::RetryOnRateLimit,
+/// };
+/// use serde_json::Value;
+/// use std::{cell::Cell, convert::Infallible, rc::Rc};
+/// use tower::{Service, ServiceBuilder, ServiceExt};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let attempts = Rc::new(Cell::new(0));
+/// let mut service = ServiceBuilder::new()
+///     // Some providers use their own error code in addition to the usual -32005.
+///     .retry(RetryOnRateLimit::default().with_code(429))
+///     .service_fn(|request: http::Request<JsonRpcRequest<Value>>| {
+///         let attempts = attempts.clone();
+///         async move {
+///             attempts.set(attempts.get() + 1);
+///             let id = request.body().id().clone();
+///             let response = if attempts.get() < 3 {
+///                 JsonRpcResponse::from_error(id, JsonRpcError::new(429, "Too many requests"))
+///             } else {
+///                 JsonRpcResponse::from_ok(id, Value::from("0x1"))
+///             };
+///             Ok::<_, Infallible>(http::Response::new(response))
+///         }
+///     });
+///
+/// let request = http::Request::post("https://ethereum.publicnode.com")
+///     .body(JsonRpcRequest::new("eth_chainId", Value::Null).with_id(1_u64))?;
+///
+/// let response = service.ready().await?.call(request).await?;
+///
+/// assert_eq!(response.into_body().into_result(), Ok(Value::from("0x1")));
+/// assert_eq!(attempts.get(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RetryOnRateLimit {
+    codes: BTreeSet<i64>,
+    backoff: Backoff,
+    sleep: SleepFn,
+    failed_attempts: u32,
+}
+
+impl Default for RetryOnRateLimit {
+    /// Retry on [`RATE_LIMIT_ERROR_CODE`] after 1 second and 2 seconds,
+    /// and give up after 3 rate-limited attempts.
+    fn default() -> Self {
+        Self {
+            codes: BTreeSet::from([RATE_LIMIT_ERROR_CODE]),
+            backoff: Backoff::default().max_attempts(3),
+            sleep: Rc::new(|_delay| Box::pin(std::future::ready(()))),
+            failed_attempts: 0,
+        }
+    }
+}
+
+impl RetryOnRateLimit {
+    /// Replace the JSON-RPC error codes that indicate a rate-limited request.
+    pub fn codes(mut self, codes: impl IntoIterator<Item = i64>) -> Self {
+        self.codes = codes.into_iter().collect();
+        self
+    }
+
+    /// Add a JSON-RPC error code that indicates a rate-limited request,
+    /// e.g. a provider-specific code.
+    pub fn with_code(mut self, code: i64) -> Self {
+        self.codes.insert(code);
+        self
+    }
+
+    /// Change the delay between attempts and the maximum number of attempts.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Change how to wait for the backoff delay before retrying a request.
+    pub fn sleep<F, Fut>(mut self, sleep: F) -> Self
+    where
+        F: Fn(Duration) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.sleep = Rc::new(move |delay| Box::pin(sleep(delay)));
+        self
+    }
+
+    /// Returns `true` if and only if the given error indicates a rate-limited request.
+    pub fn is_rate_limited(&self, error: &JsonRpcError) -> bool {
+        self.codes.contains(&error.code)
+    }
+
+    fn next_attempt(&mut self) -> Option<Sleep> {
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+        if self.backoff.is_exhausted(self.failed_attempts) {
+            return None;
+        }
+        Some((self.sleep)(self.backoff.delay(self.failed_attempts)))
+    }
+}
+
+impl fmt::Debug for RetryOnRateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryOnRateLimit")
+            .field("codes", &self.codes)
+            .field("backoff", &self.backoff)
+            .field("failed_attempts", &self.failed_attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Request, T, Error> retry::Policy<Request, http::Response<JsonRpcResponse<T>>, Error>
+    for RetryOnRateLimit
+where
+    Request: Clone,
+{
+    type Future = Sleep;
+
+    fn retry(
+        &mut self,
+        _req: &mut Request,
+        result: &mut Result<http::Response<JsonRpcResponse<T>>, Error>,
+    ) -> Option<Self::Future> {
+        match result {
+            Ok(response) => match response.body().as_result() {
+                Err(error) if self.is_rate_limited(error) => self.next_attempt(),
+                _ => None,
+            },
+            Err(_) => None,
+        }
+    }
+
+    fn clone_request(&mut self, req: &Request) -> Option<Request> {
+        Some(req.clone())
+    }
+}
+
+impl<Request, T, Error> retry::Policy<Request, http::Response<BatchJsonRpcResponse<T>>, Error>
+    for RetryOnRateLimit
+where
+    Request: Clone,
+{
+    type Future = Sleep;
+
+    fn retry(
+        &mut self,
+        _req: &mut Request,
+        result: &mut Result<http::Response<BatchJsonRpcResponse<T>>, Error>,
+    ) -> Option<Self::Future> {
+        match result {
+            Ok(response)
+                if response
+                    .body()
+                    .iter()
+                    .any(|r| matches!(r.as_result(), Err(e) if self.is_rate_limited(e))) =>
+            {
+                self.next_attempt()
+            }
+            _ => None,
+        }
+    }
+
+    fn clone_request(&mut self, req: &Request) -> Option<Request> {
+        Some(req.clone())
+    }
+}
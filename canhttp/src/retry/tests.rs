@@ -1,5 +1,8 @@
 use crate::{
-    client::IcError, http::HttpRequest, retry::DoubleMaxResponseBytes, HttpsOutcallError,
+    client::IcError,
+    http::HttpRequest,
+    retry::{DoubleMaxResponseBytes, StopOnCancellation},
+    CancellationRequestExtension, CancellationToken, HttpsOutcallError,
     MaxResponseBytesRequestExtension,
 };
 use assert_matches::assert_matches;
@@ -123,6 +126,61 @@ async fn should_stop_retrying_when_ok() {
     );
 }
 
+#[tokio::test]
+async fn should_not_retry_cancelled_request() {
+    let (requests_tx, requests_rx) = mpsc::channel::<HttpRequest>();
+
+    let mut service = ServiceBuilder::new()
+        .retry(StopOnCancellation::new(DoubleMaxResponseBytes))
+        .service(StoreRequestServiceAndError::<HttpRequest>::always_error(
+            requests_tx.clone(),
+        ));
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let request = http::Request::post("https://internetcomputer.org/")
+        .max_response_bytes(0)
+        .cancellation_token(token)
+        .body(vec![])
+        .unwrap();
+
+    let response = service
+        .ready()
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap_err();
+    assert!(response.is_response_too_large());
+
+    let all_requests: Vec<_> = requests_rx.try_iter().collect();
+    assert_eq!(all_requests.len(), 1);
+}
+
+#[tokio::test]
+async fn should_retry_request_with_active_cancellation_token() {
+    let (requests_tx, requests_rx) = mpsc::channel::<HttpRequest>();
+
+    let mut service = ServiceBuilder::new()
+        .retry(StopOnCancellation::new(DoubleMaxResponseBytes))
+        .service(StoreRequestServiceAndError::<HttpRequest>::error_n_times(
+            requests_tx.clone(),
+            2,
+        ));
+
+    let request = http::Request::post("https://internetcomputer.org/")
+        .max_response_bytes(0)
+        .cancellation_token(CancellationToken::new())
+        .body(vec![])
+        .unwrap();
+
+    let response = service.ready().await.unwrap().call(request).await;
+    assert_matches!(response, Ok(_));
+
+    let all_requests: Vec<_> = requests_rx.try_iter().collect();
+    assert_eq!(all_requests.len(), 3);
+}
+
 #[derive(Clone, Debug)]
 pub struct StoreRequestServiceAndError<T> {
     requests: Sender<T>,
@@ -182,3 +240,164 @@ fn response_is_too_large_error() -> IcError {
     assert!(error.is_response_too_large());
     error
 }
+
+#[cfg(all(feature = "http", feature = "json"))]
+mod rate_limit {
+    use crate::{
+        http::json::{
+            BatchJsonRpcRequest, BatchJsonRpcResponse, JsonRpcError, JsonRpcRequest,
+            JsonRpcResponse,
+        },
+        queue::Backoff,
+        retry::{RetryOnRateLimit, RATE_LIMIT_ERROR_CODE},
+    };
+    use serde_json::Value;
+    use std::{cell::RefCell, convert::Infallible, rc::Rc, time::Duration};
+    use tower::{Service, ServiceBuilder, ServiceExt};
+
+    #[tokio::test]
+    async fn should_retry_rate_limited_requests_with_backoff() {
+        let delays = Rc::new(RefCell::new(Vec::new()));
+        let policy = RetryOnRateLimit::default()
+            .backoff(
+                Backoff::default()
+                    .initial_delay(Duration::from_millis(100))
+                    .max_delay(Duration::from_millis(300))
+                    .max_attempts(4),
+            )
+            .sleep({
+                let delays = delays.clone();
+                move |delay| {
+                    delays.borrow_mut().push(delay);
+                    std::future::ready(())
+                }
+            });
+        let attempts = Rc::new(RefCell::new(0));
+        let mut service = ServiceBuilder::new().retry(policy).service_fn(
+            |request: http::Request<JsonRpcRequest<Value>>| {
+                let attempts = attempts.clone();
+                async move {
+                    *attempts.borrow_mut() += 1;
+                    let id = request.body().id().clone();
+                    Ok::<_, Infallible>(http::Response::new(JsonRpcResponse::<Value>::from_error(
+                        id,
+                        JsonRpcError::new(RATE_LIMIT_ERROR_CODE, "limit exceeded"),
+                    )))
+                }
+            },
+        );
+
+        let response = service
+            .ready()
+            .await
+            .unwrap()
+            .call(json_rpc_request())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.into_body().into_result().unwrap_err().code,
+            RATE_LIMIT_ERROR_CODE
+        );
+        assert_eq!(*attempts.borrow(), 4);
+        assert_eq!(
+            *delays.borrow(),
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(300)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_other_errors() {
+        for (policy, code) in [
+            (RetryOnRateLimit::default(), -32601),
+            (
+                RetryOnRateLimit::default().codes([429]),
+                RATE_LIMIT_ERROR_CODE,
+            ),
+        ] {
+            let attempts = Rc::new(RefCell::new(0));
+            let mut service = ServiceBuilder::new().retry(policy).service_fn(
+                |request: http::Request<JsonRpcRequest<Value>>| {
+                    let attempts = attempts.clone();
+                    async move {
+                        *attempts.borrow_mut() += 1;
+                        let id = request.body().id().clone();
+                        Ok::<_, Infallible>(http::Response::new(
+                            JsonRpcResponse::<Value>::from_error(
+                                id,
+                                JsonRpcError::new(code, "error"),
+                            ),
+                        ))
+                    }
+                },
+            );
+
+            let response = service
+                .ready()
+                .await
+                .unwrap()
+                .call(json_rpc_request())
+                .await
+                .unwrap();
+
+            assert!(response.into_body().into_result().is_err());
+            assert_eq!(*attempts.borrow(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_retry_batch_when_any_response_is_rate_limited() {
+        let attempts = Rc::new(RefCell::new(0));
+        let mut service = ServiceBuilder::new()
+            .retry(RetryOnRateLimit::default().with_code(-32016))
+            .service_fn(|request: http::Request<BatchJsonRpcRequest<Value>>| {
+                let attempts = attempts.clone();
+                async move {
+                    *attempts.borrow_mut() += 1;
+                    let rate_limited = *attempts.borrow() == 1;
+                    let responses: BatchJsonRpcResponse<Value> = request
+                        .body()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, request)| {
+                            let id = request.id().clone();
+                            if rate_limited && i == 1 {
+                                JsonRpcResponse::from_error(
+                                    id,
+                                    JsonRpcError::new(-32016, "rate limited"),
+                                )
+                            } else {
+                                JsonRpcResponse::from_ok(id, Value::from(i))
+                            }
+                        })
+                        .collect();
+                    Ok::<_, Infallible>(http::Response::new(responses))
+                }
+            });
+
+        let request = http::Request::post("https://ethereum.publicnode.com")
+            .body(BatchJsonRpcRequest::from(vec![
+                JsonRpcRequest::new("eth_chainId", Value::Null).with_id(1_u64),
+                JsonRpcRequest::new("eth_blockNumber", Value::Null).with_id(2_u64),
+            ]))
+            .unwrap();
+
+        let response = service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert!(response
+            .into_body()
+            .iter()
+            .all(|response| response.as_result().is_ok()));
+        assert_eq!(*attempts.borrow(), 2);
+    }
+
+    fn json_rpc_request() -> http::Request<JsonRpcRequest<Value>> {
+        http::Request::post("https://ethereum.publicnode.com")
+            .body(JsonRpcRequest::new("eth_chainId", Value::Null).with_id(1_u64))
+            .unwrap()
+    }
+}
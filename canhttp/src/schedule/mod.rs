@@ -0,0 +1,301 @@
+//! Recurring requests sent at a fixed interval.
+//!
+//! Canisters fetching data periodically, e.g. price feeds, register each recurring request
+//! in a [`Scheduler`] with an interval, a closure creating the request and a callback receiving
+//! the result. The scheduler is then driven by a single timer of the canister calling
+//! [`Scheduler::run_due`], e.g. with `ic_cdk_timers::set_timer_interval` from the
+//! [`ic-cdk-timers`](https://crates.io/crates/ic-cdk-timers) crate, whose interval determines
+//! the granularity of the schedule. With the `timers` feature, `Scheduler::start` installs
+//! such a timer.
+//!
+//! A [`PollingSubscription`] builds on a [`Scheduler`] to emulate a subscription to a
+//! JSON-RPC server, by polling it and only reporting results that changed.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use canhttp::schedule::Scheduler;
+//! use std::{cell::RefCell, rc::Rc, time::Duration};
+//! use tower::BoxError;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = tower::service_fn(|url: String| async move { Ok::<_, BoxError>(url.len()) });
+//! let now = Rc::new(RefCell::new(0_u64));
+//! let scheduler = Scheduler::new(service).clock({
+//!     let now = now.clone();
+//!     move || *now.borrow()
+//! });
+//!
+//! let prices = Rc::new(RefCell::new(Vec::new()));
+//! scheduler.schedule(
+//!     Duration::from_secs(60),
+//!     || "https://api.example.com/v1/prices".to_string(),
+//!     {
+//!         let prices = prices.clone();
+//!         move |result: Result<usize, BoxError>| prices.borrow_mut().push(result.unwrap())
+//!     },
+//! );
+//!
+//! // Called from a timer of the canister, e.g. every 10 seconds.
+//! assert_eq!(scheduler.run_due().await, 1);
+//! *now.borrow_mut() += 10_000_000_000;
+//! assert_eq!(scheduler.run_due().await, 0);
+//! *now.borrow_mut() += 50_000_000_000;
+//! assert_eq!(scheduler.run_due().await, 1);
+//!
+//! assert_eq!(prices.borrow().len(), 2);
+//! # }
+//! ```
+
+#[cfg(all(feature = "http", feature = "json"))]
+mod subscription;
+#[cfg(test)]
+mod tests;
+
+use crate::{batch::execute_all, clock::Clock};
+use std::{cell::RefCell, collections::BTreeMap, fmt, rc::Rc, time::Duration};
+#[cfg(all(feature = "http", feature = "json"))]
+pub use subscription::PollingSubscription;
+use tower::Service;
+
+/// Identifier of a recurring request registered in a [`Scheduler`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ScheduleId(u64);
+
+type Callback<T> = Rc<dyn Fn(T)>;
+
+type SharedState<S, Request> = Rc<
+    RefCell<
+        SchedulerState<
+            Request,
+            Result<<S as Service<Request>>::Response, <S as Service<Request>>::Error>,
+        >,
+    >,
+>;
+
+struct Job<Request, Output> {
+    interval_ns: u64,
+    next_run_ns: u64,
+    running: bool,
+    make_request: Rc<dyn Fn() -> Request>,
+    on_result: Callback<Output>,
+}
+
+struct SchedulerState<Request, Output> {
+    next_id: u64,
+    jobs: BTreeMap<ScheduleId, Job<Request, Output>>,
+}
+
+/// Send recurring requests to a service at a fixed interval and deliver their results
+/// to callbacks. See the [module docs](crate::schedule).
+///
+/// Each recurring request is first sent by the next call to [`Scheduler::run_due`] after it
+/// was scheduled, and then every interval. Runs that were missed, e.g. because the timer
+/// driving the scheduler fired late, are skipped, and a request is not sent again while
+/// the previous one is still in flight.
+///
+/// Cloning a [`Scheduler`] is cheap and all clones share the same schedule.
+pub struct Scheduler<S, Request>
+where
+    S: Service<Request>,
+{
+    service: S,
+    clock: Clock,
+    state: SharedState<S, Request>,
+}
+
+impl<S, Request> Scheduler<S, Request>
+where
+    S: Service<Request> + Clone,
+{
+    /// Create a new [`Scheduler`] sending requests to the given service.
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            clock: Clock::default(),
+            state: Rc::new(RefCell::new(SchedulerState {
+                next_id: 0,
+                jobs: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Change the clock used to determine when requests are due.
+    pub fn clock(mut self, clock: impl Fn() -> u64 + 'static) -> Self {
+        self.clock = Clock::new(clock);
+        self
+    }
+
+    /// Register a request created by `make_request` to be sent every `interval`,
+    /// whose result is passed to `on_result`.
+    pub fn schedule<F, C>(&self, interval: Duration, make_request: F, on_result: C) -> ScheduleId
+    where
+        F: Fn() -> Request + 'static,
+        C: Fn(Result<S::Response, S::Error>) + 'static,
+    {
+        let mut state = self.state.borrow_mut();
+        let id = ScheduleId(state.next_id);
+        state.next_id += 1;
+        state.jobs.insert(
+            id,
+            Job {
+                interval_ns: u64::try_from(interval.as_nanos()).unwrap_or(u64::MAX),
+                next_run_ns: self.clock.now(),
+                running: false,
+                make_request: Rc::new(make_request),
+                on_result: Rc::new(on_result),
+            },
+        );
+        id
+    }
+
+    /// Stop sending the recurring request with the given ID.
+    ///
+    /// Returns `false` if there is no such request. The result of a request in flight
+    /// is discarded.
+    pub fn cancel(&self, id: ScheduleId) -> bool {
+        self.state.borrow_mut().jobs.remove(&id).is_some()
+    }
+
+    /// Returns the number of recurring requests.
+    pub fn len(&self) -> usize {
+        self.state.borrow().jobs.len()
+    }
+
+    /// Returns `true` if there are no recurring requests.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the time in nanoseconds since the UNIX epoch at which the next request is due,
+    /// e.g. to set a one-shot timer instead of a periodic one.
+    pub fn next_due_ns(&self) -> Option<u64> {
+        self.state
+            .borrow()
+            .jobs
+            .values()
+            .filter(|job| !job.running)
+            .map(|job| job.next_run_ns)
+            .min()
+    }
+
+    /// Send all the requests that are due concurrently, deliver their results to the
+    /// corresponding callbacks and return the number of requests sent.
+    pub async fn run_due(&self) -> usize {
+        let now = self.clock.now();
+        let due: Vec<_> = {
+            let mut state = self.state.borrow_mut();
+            state
+                .jobs
+                .iter_mut()
+                .filter(|(_, job)| !job.running && job.next_run_ns <= now)
+                .map(|(id, job)| {
+                    job.running = true;
+                    job.next_run_ns = next_run_ns(job.next_run_ns, job.interval_ns, now);
+                    (*id, job.make_request.clone())
+                })
+                .collect()
+        };
+        if due.is_empty() {
+            return 0;
+        }
+        let (ids, requests): (Vec<_>, Vec<_>) = due
+            .into_iter()
+            .map(|(id, make_request)| (id, make_request()))
+            .unzip();
+        let sent = ids.len();
+
+        let (_service, results) = execute_all(self.service.clone(), requests, sent).await;
+
+        for (id, result) in ids.into_iter().zip(results) {
+            let on_result = {
+                let mut state = self.state.borrow_mut();
+                let Some(job) = state.jobs.get_mut(&id) else {
+                    // The request was cancelled in the meantime.
+                    continue;
+                };
+                job.running = false;
+                job.on_result.clone()
+            };
+            // The state is not borrowed, so that the callback may modify the schedule.
+            on_result(result);
+        }
+        sent
+    }
+
+    /// Install a timer of the canister calling [`Scheduler::run_due`] every `interval`, which
+    /// determines the granularity of the schedule, and return its identifier, e.g. to stop the
+    /// scheduler with [`ic_cdk_timers::clear_timer`].
+    ///
+    /// Timers are not persisted across upgrades, so the scheduler must be started again in the
+    /// `post_upgrade` hook.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use canhttp::schedule::Scheduler;
+    /// use std::time::Duration;
+    /// use tower::BoxError;
+    ///
+    /// let service = tower::service_fn(|url: String| async move { Ok::<_, BoxError>(url.len()) });
+    /// let scheduler = Scheduler::new(service);
+    /// scheduler.schedule(
+    ///     Duration::from_secs(60),
+    ///     || "https://api.example.com/v1/prices".to_string(),
+    ///     |_result: Result<usize, BoxError>| {},
+    /// );
+    ///
+    /// // In the `init` and `post_upgrade` hooks of the canister.
+    /// let _timer_id = scheduler.start(Duration::from_secs(10));
+    /// ```
+    #[cfg(feature = "timers")]
+    pub fn start(&self, interval: Duration) -> ic_cdk_timers::TimerId
+    where
+        S: 'static,
+        Request: 'static,
+    {
+        let scheduler = self.clone();
+        ic_cdk_timers::set_timer_interval(interval, move || {
+            let scheduler = scheduler.clone();
+            async move {
+                scheduler.run_due().await;
+            }
+        })
+    }
+}
+
+/// Returns the first run strictly after `now` that is a multiple of `interval_ns`
+/// after `previous_run_ns`, skipping missed runs.
+fn next_run_ns(previous_run_ns: u64, interval_ns: u64, now: u64) -> u64 {
+    if interval_ns == 0 {
+        return now;
+    }
+    let missed = now.saturating_sub(previous_run_ns) / interval_ns;
+    previous_run_ns.saturating_add(interval_ns.saturating_mul(missed.saturating_add(1)))
+}
+
+impl<S, Request> Clone for Scheduler<S, Request>
+where
+    S: Service<Request> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            clock: self.clock.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S, Request> fmt::Debug for Scheduler<S, Request>
+where
+    S: Service<Request> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("service", &self.service)
+            .field("jobs", &self.state.borrow().jobs.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    http::json::{HttpJsonRpcRequest, HttpJsonRpcResponse, JsonRpcRequest},
+    schedule::{ScheduleId, Scheduler},
+};
+use std::{cell::RefCell, fmt, rc::Rc, time::Duration};
+use tower::{BoxError, Service};
+
+type ErrorCallback = Rc<dyn Fn(BoxError)>;
+
+type ParamsFn<Cursor, Params> = Rc<dyn Fn(Option<&Cursor>) -> Params>;
+
+/// Emulate a subscription to a JSON-RPC server by polling it with a [`Scheduler`].
+///
+/// Canisters cannot open WebSocket connections to receive notifications from a server.
+/// Instead, a [`PollingSubscription`] sends the same JSON-RPC request at a fixed interval and
+/// only invokes its callback when the result changed since the last successful poll.
+///
+/// The parameters of the request may depend on a cursor derived from the last result,
+/// e.g. to only fetch the logs after the last block that was seen, see
+/// [`PollingSubscription::with_cursor`].
+///
+/// Failed polls, either because the service returned an error or because the server replied
+/// with a JSON-RPC error, are skipped and reported to the [error callback](Self::on_error),
+/// if any.
+///
+/// # Examples
+///
+/// ```rust
+/// use canhttp::{
+///     http::json::{HttpJsonRpcRequest, JsonRpcResponse},
+///     schedule::{PollingSubscription, Scheduler},
+/// };
+/// use serde_json::{json, Value};
+/// use std::{cell::RefCell, rc::Rc, time::Duration};
+/// use tower::BoxError;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), BoxError> {
+/// let block_number = Rc::new(RefCell::new(0x10_u64));
+/// let service = tower::service_fn({
+///     let block_number = block_number.clone();
+///     move |request: HttpJsonRpcRequest<Value>| {
+///         let result = json!(format!("{:#x}", *block_number.borrow()));
+///         async move {
+///             Ok::<_, BoxError>(http::Response::new(JsonRpcResponse::from_ok(
+///                 request.body().id().clone(),
+///                 result,
+///             )))
+///         }
+///     }
+/// });
+/// let now = Rc::new(RefCell::new(0_u64));
+/// let scheduler = Scheduler::new(service).clock({
+///     let now = now.clone();
+///     move || *now.borrow()
+/// });
+///
+/// let new_blocks = Rc::new(RefCell::new(Vec::new()));
+/// PollingSubscription::new(
+///     http::Request::post("https://ethereum.publicnode.com").body(())?,
+///     "eth_blockNumber",
+///     json!([]),
+/// )
+/// .subscribe(&scheduler, Duration::from_secs(12), {
+///     let new_blocks = new_blocks.clone();
+///     move |block: Value| new_blocks.borrow_mut().push(block)
+/// });
+///
+/// scheduler.run_due().await;
+/// *now.borrow_mut() += 12_000_000_000;
+/// scheduler.run_due().await; // unchanged
+/// *now.borrow_mut() += 12_000_000_000;
+/// *block_number.borrow_mut() += 1;
+/// scheduler.run_due().await;
+///
+/// assert_eq!(*new_blocks.borrow(), vec![json!("0x10"), json!("0x11")]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct PollingSubscription<Params, Output, Cursor = ()> {
+    request: http::Request<()>,
+    method: String,
+    params: ParamsFn<Cursor, Params>,
+    next_cursor: Rc<dyn Fn(&Output) -> Cursor>,
+    cursor: Option<Cursor>,
+    on_error: Option<ErrorCallback>,
+}
+
+impl<Params, Output> PollingSubscription<Params, Output, ()>
+where
+    Params: Clone + 'static,
+{
+    /// Poll the given method with constant parameters.
+    ///
+    /// The HTTP part of each request (URL, headers, extensions, etc.) is copied from
+    /// the given request.
+    pub fn new(request: http::Request<()>, method: impl Into<String>, params: Params) -> Self {
+        Self::with_cursor(request, method, move |_cursor| params.clone(), |_output| ())
+    }
+}
+
+impl<Params, Output, Cursor> PollingSubscription<Params, Output, Cursor> {
+    /// Poll the given method with parameters depending on a cursor, which is derived from the
+    /// last changed result with `next_cursor`.
+    ///
+    /// The cursor is `None` until the first result is received, unless an
+    /// [initial cursor](Self::initial_cursor) is given.
+    pub fn with_cursor<P, N>(
+        request: http::Request<()>,
+        method: impl Into<String>,
+        params: P,
+        next_cursor: N,
+    ) -> Self
+    where
+        P: Fn(Option<&Cursor>) -> Params + 'static,
+        N: Fn(&Output) -> Cursor + 'static,
+    {
+        Self {
+            request,
+            method: method.into(),
+            params: Rc::new(params),
+            next_cursor: Rc::new(next_cursor),
+            cursor: None,
+            on_error: None,
+        }
+    }
+
+    /// Set the cursor used for the first poll.
+    pub fn initial_cursor(mut self, cursor: Cursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Report failed polls to the given callback.
+    pub fn on_error(mut self, on_error: impl Fn(BoxError) + 'static) -> Self {
+        self.on_error = Some(Rc::new(on_error));
+        self
+    }
+
+    /// Start polling every `interval` with the given scheduler, and pass each result
+    /// that differs from the previous one to `on_change`.
+    ///
+    /// The subscription is stopped by cancelling the returned [`ScheduleId`],
+    /// see [`Scheduler::cancel`].
+    pub fn subscribe<S, F>(
+        self,
+        scheduler: &Scheduler<S, HttpJsonRpcRequest<Params>>,
+        interval: Duration,
+        on_change: F,
+    ) -> ScheduleId
+    where
+        S: Service<HttpJsonRpcRequest<Params>, Response = HttpJsonRpcResponse<Output>> + Clone,
+        S::Error: Into<BoxError>,
+        F: Fn(Output) + 'static,
+        Params: 'static,
+        Output: Clone + PartialEq + 'static,
+        Cursor: 'static,
+    {
+        let Self {
+            request,
+            method,
+            params,
+            next_cursor,
+            cursor,
+            on_error,
+        } = self;
+        let state = Rc::new(RefCell::new(PollingState { cursor, last: None }));
+        let make_request = {
+            let state = state.clone();
+            move || {
+                let params = params(state.borrow().cursor.as_ref());
+                let (parts, ()) = request.clone().into_parts();
+                http::Request::from_parts(parts, JsonRpcRequest::new(method.clone(), params))
+            }
+        };
+        let on_result = move |result: Result<HttpJsonRpcResponse<Output>, S::Error>| {
+            let output = match result
+                .map_err(Into::into)
+                .and_then(|response| response.into_body().into_result().map_err(BoxError::from))
+            {
+                Ok(output) => output,
+                Err(e) => {
+                    if let Some(on_error) = &on_error {
+                        on_error(e);
+                    }
+                    return;
+                }
+            };
+            {
+                let mut state = state.borrow_mut();
+                if state.last.as_ref() == Some(&output) {
+                    return;
+                }
+                state.cursor = Some(next_cursor(&output));
+                state.last = Some(output.clone());
+            }
+            // The state is not borrowed, so that the callback may poll again.
+            on_change(output);
+        };
+        scheduler.schedule(interval, make_request, on_result)
+    }
+}
+
+impl<Params, Output, Cursor: fmt::Debug> fmt::Debug
+    for PollingSubscription<Params, Output, Cursor>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PollingSubscription")
+            .field("request", &self.request)
+            .field("method", &self.method)
+            .field("cursor", &self.cursor)
+            .finish_non_exhaustive()
+    }
+}
+
+struct PollingState<Cursor, Output> {
+    cursor: Option<Cursor>,
+    last: Option<Output>,
+}
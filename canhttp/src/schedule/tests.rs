@@ -0,0 +1,219 @@
+use crate::schedule::{next_run_ns, Scheduler};
+use std::{cell::RefCell, rc::Rc, time::Duration};
+use tower::BoxError;
+
+const SECOND_NS: u64 = 1_000_000_000;
+
+#[tokio::test]
+async fn should_send_requests_at_their_interval() {
+    let now = Rc::new(RefCell::new(0_u64));
+    let service = tower::service_fn(|request: &'static str| async move {
+        if request == "fail" {
+            Err(BoxError::from("failed"))
+        } else {
+            Ok(request)
+        }
+    });
+    let scheduler = Scheduler::new(service).clock({
+        let now = now.clone();
+        move || *now.borrow()
+    });
+    let results = Rc::new(RefCell::new(Vec::new()));
+    for (interval_secs, request) in [(10, "fast"), (30, "slow"), (20, "fail")] {
+        let results = results.clone();
+        scheduler.schedule(
+            Duration::from_secs(interval_secs),
+            move || request,
+            move |result: Result<&'static str, BoxError>| {
+                results.borrow_mut().push(result.unwrap_or_else(|e| {
+                    if e.to_string() == "failed" {
+                        "error"
+                    } else {
+                        ""
+                    }
+                }))
+            },
+        );
+    }
+
+    let mut sent = Vec::new();
+    for t in [0, 5, 10, 20, 30] {
+        *now.borrow_mut() = t * SECOND_NS;
+        sent.push(scheduler.run_due().await);
+    }
+
+    assert_eq!(sent, vec![3, 0, 1, 2, 2]);
+    assert_eq!(
+        *results.borrow(),
+        vec!["fast", "slow", "error", "fast", "fast", "error", "fast", "slow"]
+    );
+    assert_eq!(scheduler.next_due_ns(), Some(40 * SECOND_NS));
+}
+
+#[tokio::test]
+async fn should_cancel_recurring_requests() {
+    let service = tower::service_fn(|request: u8| async move { Ok::<_, BoxError>(request) });
+    let scheduler = Scheduler::new(service).clock(|| 0);
+    let results = Rc::new(RefCell::new(Vec::new()));
+    let ids: Vec<_> = (0..2)
+        .map(|request| {
+            let results = results.clone();
+            scheduler.schedule(
+                Duration::ZERO,
+                move || request,
+                move |result: Result<u8, BoxError>| results.borrow_mut().push(result.unwrap()),
+            )
+        })
+        .collect();
+
+    assert!(scheduler.cancel(ids[0]));
+    assert!(!scheduler.cancel(ids[0]));
+    assert_eq!(scheduler.len(), 1);
+    assert_eq!(scheduler.run_due().await, 1);
+    assert_eq!(*results.borrow(), vec![1]);
+}
+
+#[test]
+fn should_skip_missed_runs() {
+    assert_eq!(next_run_ns(0, 10, 0), 10);
+    assert_eq!(next_run_ns(0, 10, 9), 10);
+    assert_eq!(next_run_ns(0, 10, 10), 20);
+    assert_eq!(next_run_ns(0, 10, 35), 40);
+    assert_eq!(next_run_ns(5, 0, 35), 35);
+}
+
+#[cfg(all(feature = "http", feature = "json"))]
+mod subscription {
+    use super::SECOND_NS;
+    use crate::{
+        http::json::{HttpJsonRpcRequest, JsonRpcError, JsonRpcResponse},
+        schedule::{PollingSubscription, Scheduler},
+    };
+    use serde_json::{json, Value};
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+    use tower::BoxError;
+
+    #[tokio::test]
+    async fn should_poll_with_cursor_and_report_changes() {
+        // Each poll returns the blocks after the one given in the parameters,
+        // up to the current head.
+        let head = Rc::new(RefCell::new(0_u64));
+        let params = Rc::new(RefCell::new(Vec::new()));
+        let service = tower::service_fn({
+            let (head, params) = (head.clone(), params.clone());
+            move |request: HttpJsonRpcRequest<Value>| {
+                let request_params = request.body().params().unwrap().clone();
+                let from = request_params["fromBlock"].as_u64().unwrap();
+                let head = *head.borrow();
+                params.borrow_mut().push(request_params);
+                async move {
+                    Ok::<_, BoxError>(http::Response::new(JsonRpcResponse::from_ok(
+                        request.body().id().clone(),
+                        json!({"blocks": (from + 1..=head).collect::<Vec<_>>(), "head": head}),
+                    )))
+                }
+            }
+        });
+        let now = Rc::new(RefCell::new(0_u64));
+        let scheduler = Scheduler::new(service).clock({
+            let now = now.clone();
+            move || *now.borrow()
+        });
+        let changes = Rc::new(RefCell::new(Vec::new()));
+
+        PollingSubscription::with_cursor(
+            http::Request::post("https://rpc.example.com")
+                .body(())
+                .unwrap(),
+            "getBlocks",
+            |cursor: Option<&u64>| json!({"fromBlock": cursor.copied().unwrap_or_default()}),
+            |result: &Value| result["head"].as_u64().unwrap(),
+        )
+        .initial_cursor(1)
+        .subscribe(&scheduler, Duration::from_secs(10), {
+            let changes = changes.clone();
+            move |result: Value| changes.borrow_mut().push(result["blocks"].clone())
+        });
+
+        for new_head in [3, 5, 5, 5, 6] {
+            *head.borrow_mut() = new_head;
+            assert_eq!(scheduler.run_due().await, 1);
+            *now.borrow_mut() += 10 * SECOND_NS;
+        }
+
+        assert_eq!(
+            *params.borrow(),
+            vec![
+                json!({"fromBlock": 1}),
+                json!({"fromBlock": 3}),
+                json!({"fromBlock": 5}),
+                json!({"fromBlock": 5}),
+                json!({"fromBlock": 5}),
+            ]
+        );
+        // The third poll is reported since it differs from the second one,
+        // but the fourth poll returned the same result as the third one.
+        assert_eq!(
+            *changes.borrow(),
+            vec![json!([2, 3]), json!([4, 5]), json!([]), json!([6])]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_skip_and_report_failed_polls() {
+        let polls = Rc::new(RefCell::new(0));
+        let service = tower::service_fn({
+            let polls = polls.clone();
+            move |request: HttpJsonRpcRequest<Value>| {
+                *polls.borrow_mut() += 1;
+                let poll = *polls.borrow();
+                async move {
+                    let id = request.body().id().clone();
+                    match poll {
+                        1 => Err(BoxError::from("network error")),
+                        2 => Ok(http::Response::new(JsonRpcResponse::from_error(
+                            id,
+                            JsonRpcError::new(-32005, "limit exceeded"),
+                        ))),
+                        _ => Ok(http::Response::new(JsonRpcResponse::from_ok(id, json!(42)))),
+                    }
+                }
+            }
+        });
+        let now = Rc::new(RefCell::new(0_u64));
+        let scheduler = Scheduler::new(service).clock({
+            let now = now.clone();
+            move || *now.borrow()
+        });
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let errors = Rc::new(RefCell::new(Vec::new()));
+
+        let id = PollingSubscription::new(
+            http::Request::post("https://rpc.example.com")
+                .body(())
+                .unwrap(),
+            "getSlot",
+            json!([]),
+        )
+        .on_error({
+            let errors = errors.clone();
+            move |e| errors.borrow_mut().push(e.to_string())
+        })
+        .subscribe(&scheduler, Duration::from_secs(1), {
+            let changes = changes.clone();
+            move |slot| changes.borrow_mut().push(slot)
+        });
+
+        for _ in 0..4 {
+            scheduler.run_due().await;
+            *now.borrow_mut() += SECOND_NS;
+        }
+        assert!(scheduler.cancel(id));
+        assert_eq!(scheduler.run_due().await, 0);
+
+        assert_eq!(*changes.borrow(), vec![json!(42)]);
+        assert_eq!(errors.borrow().len(), 2);
+        assert_eq!(errors.borrow()[0], "network error");
+        assert!(errors.borrow()[1].contains("limit exceeded"));
+    }
+}
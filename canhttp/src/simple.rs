@@ -0,0 +1,71 @@
+//! Convenience functions to issue simple HTTPs outcalls without assembling a [tower] stack.
+//!
+//! Each function uses the following default stack:
+//! * [`HttpConversionLayer`] to use types from the [http](https://crates.io/crates/http) crate;
+//! * [`ChargeMyself`] so that the canister pays for the HTTPs outcall with its own cycles;
+//! * [`Client`] to issue the HTTPs outcall.
+//!
+//! For anything more advanced (retries, observability, charging the caller, etc.), build your
+//! own stack with the tower API instead.
+
+use crate::{
+    cycles::{ChargeMyself, CyclesAccountingServiceBuilder},
+    http::{HttpConversionLayer, HttpRequest, HttpResponse, RequestBuilder},
+    Client,
+};
+use tower::{BoxError, ServiceBuilder, ServiceExt};
+
+/// Issue an HTTPs outcall with a `GET` request to the given URL.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # async fn example() -> Result<(), tower::BoxError> {
+/// let response = canhttp::get("https://api.example.com/v1/prices").await?;
+/// assert_eq!(response.status(), http::StatusCode::OK);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn get(url: impl AsRef<str>) -> Result<HttpResponse, BoxError> {
+    send(RequestBuilder::get(url).body(vec![])?).await
+}
+
+/// Issue an HTTPs outcall with a `POST` request with the given body to the given URL.
+pub async fn post(url: impl AsRef<str>, body: Vec<u8>) -> Result<HttpResponse, BoxError> {
+    send(RequestBuilder::post(url).body(body)?).await
+}
+
+/// Issue an HTTPs outcall with a `POST` request with the given body serialized as JSON
+/// to the given URL.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # async fn example() -> Result<(), tower::BoxError> {
+/// let response = canhttp::post_json(
+///     "https://api.example.com/v1/orders",
+///     &serde_json::json!({"symbol": "ICPUSDT", "quantity": 1}),
+/// )
+/// .await?;
+/// assert_eq!(response.status(), http::StatusCode::OK);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(all(feature = "http", feature = "json"))]
+pub async fn post_json<T: serde::Serialize + ?Sized>(
+    url: impl AsRef<str>,
+    body: &T,
+) -> Result<HttpResponse, BoxError> {
+    let body = serde_json::to_vec(body)?;
+    send(RequestBuilder::post(url).json(body)?).await
+}
+
+/// Issue an HTTPs outcall for the given request using the default stack.
+pub async fn send(request: HttpRequest) -> Result<HttpResponse, BoxError> {
+    ServiceBuilder::new()
+        .layer(HttpConversionLayer)
+        .cycles_accounting(ChargeMyself::default())
+        .service(Client::new_with_box_error())
+        .oneshot(request)
+        .await
+}
@@ -4,8 +4,10 @@ use candid::{CandidType, Deserialize};
 use canhttp::{
     cycles::{ChargeMyself, CyclesAccountingServiceBuilder},
     http::json::{
-        HttpBatchJsonRpcRequest, HttpBatchJsonRpcResponse, HttpJsonRpcRequest, HttpJsonRpcResponse,
-        Id, JsonRpcHttpLayer, JsonRpcRequest, JsonRpcResponse,
+        solana::{CommitmentLevel, GetSlot},
+        BatchJsonRpcRequest, HttpBatchJsonRpcRequest, HttpBatchJsonRpcResponse, HttpJsonRpcRequest,
+        HttpJsonRpcResponse, HttpJsonRpcResponseExtension, Id, JsonRpcHttpLayer, JsonRpcRequest,
+        JsonRpcResponse,
     },
     observability::ObservabilityLayer,
     Client,
@@ -25,7 +27,10 @@ pub async fn make_json_rpc_request() -> u64 {
     // the current height of the Solana blockchain
     let request = http::Request::post(solana_test_validator_base_url())
         .header("Content-Type", "application/json")
-        .body(JsonRpcRequest::new("getSlot", json!([{"commitment": "finalized"}])).with_id(ID))
+        .body(
+            JsonRpcRequest::from_method::<GetSlot>((CommitmentLevel::Finalized.into(),))
+                .with_id(ID),
+        )
         .unwrap();
 
     let response = json_rpc_client()
@@ -35,12 +40,11 @@ pub async fn make_json_rpc_request() -> u64 {
         .call(request)
         .await
         .expect("Request should succeed");
-    assert_eq!(response.status(), http::StatusCode::OK);
-
-    let (id, result) = response.into_body().into_parts();
-    assert_eq!(id, ID);
+    assert_eq!(response.body().id(), &ID);
 
-    result.expect("JSON-RPC API call should succeed")
+    response
+        .into_successful_result()
+        .expect("JSON-RPC API call should succeed")
 }
 
 fn json_rpc_client<Params, Result>(
@@ -67,11 +71,11 @@ pub async fn make_batch_json_rpc_request() -> SlotInfo {
     // together with a `getSlotLeader` that fetches the identity of the leader for that slot.
     let requests = http::Request::post(solana_test_validator_base_url())
         .header("Content-Type", "application/json")
-        .body(vec![
+        .body(BatchJsonRpcRequest::from(vec![
             JsonRpcRequest::new("getSlot", json!([{"commitment": "finalized"}])).with_id(0_u64),
             JsonRpcRequest::new("getSlotLeader", json!([{"commitment": "finalized"}]))
                 .with_id(1_u64),
-        ])
+        ]))
         .unwrap();
 
     let response = batch_json_rpc_client()
@@ -83,9 +87,12 @@ pub async fn make_batch_json_rpc_request() -> SlotInfo {
         .expect("Request should succeed");
     assert_eq!(response.status(), http::StatusCode::OK);
 
+    // The `JsonRpcHttpLayer` orders the responses like the requests, regardless of the order
+    // in which the server sent them.
     let [get_slot_response, get_slot_leader_response]: [JsonRpcResponse<serde_json::Value>; 2] =
         response
             .into_body()
+            .into_vec()
             .try_into()
             .expect("Expected exactly 2 JSON-RPC responses");
 
@@ -1,5 +1,5 @@
 //! Library to abstract the canister runtime so that code making requests to canisters can be reused:
-//! * in production using [`ic_cdk`],
+//! * in production using [`IcRuntime`], which makes calls with [`ic_cdk`],
 //! * in unit tests by mocking this trait,
 //! * in integration tests by implementing this trait for `PocketIc`.
 
@@ -21,7 +21,7 @@ mod stub;
 mod wallet;
 
 /// Abstract the canister runtime so that code making requests to canisters can be reused:
-/// * in production using [`ic_cdk`],
+/// * in production using [`IcRuntime`], which makes calls with [`ic_cdk`],
 /// * in unit tests by mocking this trait,
 /// * in integration tests by implementing this trait for `PocketIc`.
 #[async_trait]
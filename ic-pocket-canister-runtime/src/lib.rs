@@ -6,6 +6,8 @@
 
 mod mock;
 mod proxy;
+#[cfg(test)]
+mod tests;
 
 use async_trait::async_trait;
 use candid::{decode_one, encode_args, utils::ArgumentEncoder, CandidType, Principal};
@@ -19,7 +21,7 @@ pub use mock::{
     },
     AnyCanisterHttpRequestMatcher, CanisterHttpReject, CanisterHttpReply,
     CanisterHttpRequestMatcher, MockHttpOutcall, MockHttpOutcallBuilder, MockHttpOutcalls,
-    MockHttpOutcallsBuilder,
+    MockHttpOutcallsBuilder, MockTransform,
 };
 use pocket_ic::{
     common::rest::{CanisterHttpRequest, CanisterHttpResponse, MockCanisterHttpResponse},
@@ -255,7 +257,11 @@ impl ExecuteHttpOutcallMocks for MockHttpOutcalls {
                         let mock_response = MockCanisterHttpResponse {
                             subnet_id: request.subnet_id,
                             request_id: request.request_id,
-                            response: check_response_size(request, mock.response),
+                            response: check_response_size(
+                                request,
+                                mock.response,
+                                mock.transform.as_ref(),
+                            ),
                             additional_responses: vec![],
                         };
                         env.mock_canister_http_response(mock_response).await;
@@ -271,27 +277,51 @@ impl ExecuteHttpOutcallMocks for MockHttpOutcalls {
     }
 }
 
+/// Approximates the replica behavior regarding the response size limit: the size of the response,
+/// including headers, must not exceed `max_response_bytes`, both before and after applying the
+/// canister transform.
 fn check_response_size(
     request: &CanisterHttpRequest,
     response: CanisterHttpResponse,
+    transform: Option<&MockTransform>,
 ) -> CanisterHttpResponse {
     if let CanisterHttpResponse::CanisterHttpReply(reply) = &response {
         let max_response_bytes = request
             .max_response_bytes
             .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
-        if reply.body.len() as u64 > max_response_bytes {
-            // Approximate replica behavior since headers are not accounted for.
-            return CanisterHttpResponse::CanisterHttpReject(
-                pocket_ic::common::rest::CanisterHttpReject {
-                    reject_code: RejectCode::SysFatal as u64,
-                    message: format!("Http body exceeds size limit of {max_response_bytes} bytes.",),
-                },
-            );
+        if response_size(reply) > max_response_bytes {
+            return reject_response_too_large(format!(
+                "Http body exceeds size limit of {max_response_bytes} bytes.",
+            ));
+        }
+        if let Some(transform) = transform {
+            let transformed_size = response_size(&transform.apply(reply.clone()));
+            if transformed_size > max_response_bytes {
+                return reject_response_too_large(format!(
+                    "Transformed http response exceeds limit: {max_response_bytes}",
+                ));
+            }
         }
     }
     response
 }
 
+fn response_size(reply: &pocket_ic::common::rest::CanisterHttpReply) -> u64 {
+    let headers_size: usize = reply
+        .headers
+        .iter()
+        .map(|header| header.name.len() + header.value.len())
+        .sum();
+    (headers_size + reply.body.len()) as u64
+}
+
+fn reject_response_too_large(message: String) -> CanisterHttpResponse {
+    CanisterHttpResponse::CanisterHttpReject(pocket_ic::common::rest::CanisterHttpReject {
+        reject_code: RejectCode::SysFatal as u64,
+        message,
+    })
+}
+
 fn parse_reject_response(response: RejectResponse) -> IcError {
     CallFailed::CallRejected(CallRejected::with_rejection(
         response.reject_code as u32,
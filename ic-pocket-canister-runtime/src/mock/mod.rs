@@ -63,11 +63,78 @@ impl Drop for MockHttpOutcalls {
 #[must_use]
 /// A mocked HTTP outcall with a mocked canister response and a [`CanisterHttpRequestMatcher`] to
 /// find matching requests.
+///
+/// Create it with [`MockHttpOutcall::new`] or with a [`MockHttpOutcallsBuilder`].
 pub struct MockHttpOutcall {
     /// The matcher to find matching requests.
     pub request: Box<dyn CanisterHttpRequestMatcher>,
     /// The mocked canister response.
     pub response: CanisterHttpResponse,
+    pub(crate) transform: Option<MockTransform>,
+}
+
+impl MockHttpOutcall {
+    /// Create a new [`MockHttpOutcall`] responding with the given response to requests matching
+    /// the given matcher.
+    pub fn new(
+        request: impl CanisterHttpRequestMatcher + 'static,
+        response: impl Into<CanisterHttpResponse>,
+    ) -> Self {
+        Self {
+            request: Box::new(request),
+            response: response.into(),
+            transform: None,
+        }
+    }
+
+    /// Simulate the canister transform when checking the size of the mocked response.
+    ///
+    /// The given function re-implements the transform of the canister, it does not call the
+    /// real one. See [`MockTransform`].
+    pub fn with_transform(
+        mut self,
+        transform: impl Fn(IcCanisterHttpReply) -> IcCanisterHttpReply + Send + Sync + 'static,
+    ) -> Self {
+        self.transform = Some(MockTransform::new(transform));
+        self
+    }
+}
+
+/// Simulates the [transform function] of a canister on a mocked HTTP outcall response.
+///
+/// The mocked response is still transformed by the canister itself, the simulated transform is
+/// only used to check the size of the transformed response against
+/// [`max_response_bytes`](CanisterHttpRequest::max_response_bytes), as done by the replica.
+///
+/// The simulated transform is a re-implementation of the canister transform and does not
+/// exercise the real one: it must be kept in sync with the canister, since any difference
+/// between the two is not detected.
+///
+/// [transform function]: https://internetcomputer.org/docs/references/ic-interface-spec#ic-http_request
+pub struct MockTransform(Box<TransformFn>);
+
+type TransformFn = dyn Fn(IcCanisterHttpReply) -> IcCanisterHttpReply + Send + Sync;
+
+type IcCanisterHttpReply = pocket_ic::common::rest::CanisterHttpReply;
+
+impl MockTransform {
+    /// Create a new [`MockTransform`] from the given function.
+    pub fn new(
+        transform: impl Fn(IcCanisterHttpReply) -> IcCanisterHttpReply + Send + Sync + 'static,
+    ) -> Self {
+        Self(Box::new(transform))
+    }
+
+    /// Apply the transform to the given reply.
+    pub fn apply(&self, reply: IcCanisterHttpReply) -> IcCanisterHttpReply {
+        (self.0)(reply)
+    }
+}
+
+impl Debug for MockTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MockTransform")
+    }
 }
 
 /// A [`MockHttpOutcallsBuilder`] to create a [`MockHttpOutcalls`] with a fluent API.
@@ -116,6 +183,7 @@ impl MockHttpOutcallsBuilder {
         MockHttpOutcallBuilder {
             parent: self,
             request: Box::new(request),
+            transform: None,
         }
     }
 
@@ -140,9 +208,22 @@ impl From<MockHttpOutcallsBuilder> for MockHttpOutcalls {
 pub struct MockHttpOutcallBuilder {
     parent: MockHttpOutcallsBuilder,
     request: Box<dyn CanisterHttpRequestMatcher>,
+    transform: Option<MockTransform>,
 }
 
 impl MockHttpOutcallBuilder {
+    /// Simulate the canister transform when checking the size of the mocked response.
+    ///
+    /// The given function re-implements the transform of the canister, it does not call the
+    /// real one. See [`MockTransform`].
+    pub fn with_transform(
+        mut self,
+        transform: impl Fn(IcCanisterHttpReply) -> IcCanisterHttpReply + Send + Sync + 'static,
+    ) -> Self {
+        self.transform = Some(MockTransform::new(transform));
+        self
+    }
+
     /// Used with [`given`] to add a new mock.
     ///
     /// # Examples
@@ -179,6 +260,7 @@ impl MockHttpOutcallBuilder {
         self.parent.0.push(MockHttpOutcall {
             request: self.request,
             response: response.into(),
+            transform: self.transform,
         });
         self.parent
     }
@@ -0,0 +1,102 @@
+use crate::{check_response_size, MockTransform};
+use candid::Principal;
+use ic_error_types::RejectCode;
+use pocket_ic::common::rest::{
+    CanisterHttpHeader, CanisterHttpMethod, CanisterHttpReject, CanisterHttpReply,
+    CanisterHttpRequest, CanisterHttpResponse,
+};
+
+const MAX_RESPONSE_BYTES: u64 = 100;
+
+#[test]
+fn should_accept_response_within_limit() {
+    let response = reply(vec![], vec![b'a'; MAX_RESPONSE_BYTES as usize]);
+
+    assert_eq!(
+        check_response_size(&request(), response.clone(), None),
+        response
+    );
+}
+
+#[test]
+fn should_account_for_headers() {
+    let response = reply(
+        vec![("Content-Type", "application/json")],
+        vec![b'a'; MAX_RESPONSE_BYTES as usize - 20],
+    );
+
+    assert_matches_reject(check_response_size(&request(), response, None));
+}
+
+#[test]
+fn should_check_size_after_transform() {
+    let response = reply(vec![], vec![b'a'; MAX_RESPONSE_BYTES as usize]);
+    let appending_transform = MockTransform::new(|mut reply| {
+        reply.body.extend_from_slice(b"appended");
+        reply
+    });
+    let truncating_transform = MockTransform::new(|mut reply| {
+        reply.body.truncate(1);
+        reply
+    });
+
+    assert_matches_reject(check_response_size(
+        &request(),
+        response.clone(),
+        Some(&appending_transform),
+    ));
+    assert_eq!(
+        check_response_size(&request(), response.clone(), Some(&truncating_transform)),
+        response
+    );
+}
+
+#[test]
+fn should_check_size_before_transform() {
+    let response = reply(vec![], vec![b'a'; MAX_RESPONSE_BYTES as usize + 1]);
+    let truncating_transform = MockTransform::new(|mut reply| {
+        reply.body.truncate(1);
+        reply
+    });
+
+    assert_matches_reject(check_response_size(
+        &request(),
+        response,
+        Some(&truncating_transform),
+    ));
+}
+
+fn assert_matches_reject(response: CanisterHttpResponse) {
+    match response {
+        CanisterHttpResponse::CanisterHttpReject(CanisterHttpReject { reject_code, .. }) => {
+            assert_eq!(reject_code, RejectCode::SysFatal as u64)
+        }
+        _ => panic!("Expected response to be rejected, but got {response:?}"),
+    }
+}
+
+fn request() -> CanisterHttpRequest {
+    CanisterHttpRequest {
+        subnet_id: Principal::anonymous(),
+        request_id: 0,
+        http_method: CanisterHttpMethod::POST,
+        url: "https://ethereum.publicnode.com/".to_string(),
+        headers: vec![],
+        body: vec![],
+        max_response_bytes: Some(MAX_RESPONSE_BYTES),
+    }
+}
+
+fn reply(headers: Vec<(&str, &str)>, body: Vec<u8>) -> CanisterHttpResponse {
+    CanisterHttpResponse::CanisterHttpReply(CanisterHttpReply {
+        status: 200,
+        headers: headers
+            .into_iter()
+            .map(|(name, value)| CanisterHttpHeader {
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+            .collect(),
+        body,
+    })
+}